@@ -0,0 +1,417 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Worst-case stack usage analysis
+//!
+//! The binary is built with `-Z emit-stack-sizes`, which records the
+//! frame size of every function compiled by rustc in the
+//! `.stack_sizes` section. The call graph is recovered from the
+//! disassembly of the binary, and the deepest path from each interrupt
+//! handler and from the reset vector is reported.
+//!
+//! The analysis cannot follow indirect calls, such as calls through
+//! trait objects or waker vtables, and has no frame size for
+//! functions which were not compiled with stack size information
+//! (including `core` and `compiler_builtins`). Results which depend on
+//! either are reported as lower bounds.
+
+use anyhow::{anyhow, bail, Result};
+use cargo::util::paths::resolve_executable;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::PathBuf,
+};
+use subprocess::{Exec, ExitStatus};
+
+/// The stack depth reached from a function
+#[derive(Clone, Copy)]
+struct Depth {
+    /// The deepest known stack usage, in bytes
+    bytes: u64,
+
+    /// Whether some function on the deepest path has an unknown
+    /// frame size or makes indirect calls
+    incomplete: bool,
+
+    /// Whether the function can recurse, making its depth unbounded
+    recursive: bool,
+}
+
+struct Function {
+    name: String,
+    frame: Option<u64>,
+    calls: HashSet<u64>,
+    indirect: bool,
+}
+
+struct Binutils {
+    nm: PathBuf,
+    objdump: PathBuf,
+}
+
+impl Binutils {
+    fn for_target(target: &str) -> Result<Self> {
+        let prefix = crate::binutils_prefix(target)
+            .ok_or_else(|| anyhow!("Stack analysis is not supported for target `{}`", target))?;
+        Ok(Self {
+            nm: resolve_executable(&PathBuf::from(format!("{}-nm", prefix)))?,
+            objdump: resolve_executable(&PathBuf::from(format!("{}-objdump", prefix)))?,
+        })
+    }
+
+    fn nm(&self, binary: &str) -> Result<String> {
+        run(
+            Exec::cmd(&self.nm)
+                .arg("--defined-only")
+                .arg("--print-size")
+                .arg("--demangle")
+                .arg(binary),
+            "nm",
+        )
+    }
+
+    fn objdump(&self, args: &[&str], binary: &str) -> Result<String> {
+        run(Exec::cmd(&self.objdump).args(args).arg(binary), "objdump")
+    }
+}
+
+fn run(exec: Exec, name: &str) -> Result<String> {
+    let output = exec.capture()?;
+    if output.exit_status != ExitStatus::Exited(0) {
+        bail!("{} error: {}", name, output.stderr_str().trim());
+    }
+    Ok(output.stdout_str())
+}
+
+/// The size of the frame pushed by the hardware or trap vector on
+/// interrupt entry.
+fn entry_frame(target: &str) -> u64 {
+    if target.starts_with("thumbv7em") && target.ends_with("eabihf") {
+        // The integer frame, plus the lazily-stacked FPU registers
+        104
+    } else if target.starts_with("thumb") {
+        32
+    } else {
+        // The Red V trap vector saves 16 registers, after aligning
+        // the stack to 8 bytes.
+        68
+    }
+}
+
+/// Analyze the stack usage of a binary and print a report
+///
+/// The binary must have been built with `-Z emit-stack-sizes`, and
+/// linked with a script which keeps the `.stack_sizes` section.
+pub fn analyze(binary: &str, target: &str) -> Result<()> {
+    let binutils = Binutils::for_target(target)?;
+
+    // Symbols, both for function names and for the linker-provided
+    // memory layout.
+    let mut symbols = HashMap::new();
+    let mut functions = BTreeMap::new();
+    for line in binutils.nm(binary)?.lines() {
+        // Symbols without a size have only three fields
+        let mut fields = line.splitn(3, ' ');
+        let (address, second, rest) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(address), Some(second), Some(rest)) => (address, second, rest),
+            _ => continue,
+        };
+        let (size, kind, name) = if second.len() == 1 {
+            (None, second, rest)
+        } else {
+            let mut fields = rest.splitn(2, ' ');
+            match (fields.next(), fields.next()) {
+                (Some(kind), Some(name)) => (Some(second), kind, name),
+                _ => continue,
+            }
+        };
+        let address = match u64::from_str_radix(address, 16) {
+            Ok(address) => address,
+            Err(_) => continue,
+        };
+        let size = size.and_then(|size| u64::from_str_radix(size, 16).ok());
+        symbols.insert(name.to_owned(), (address, size));
+        if kind.eq_ignore_ascii_case("t") || kind.eq_ignore_ascii_case("w") {
+            // Thumb function symbols have their low bit set
+            functions.entry(address & !1).or_insert_with(|| Function {
+                name: name.to_owned(),
+                frame: None,
+                calls: HashSet::new(),
+                indirect: false,
+            });
+        }
+    }
+
+    // Frame sizes, from the `.stack_sizes` section. Each entry is a
+    // 32-bit function address followed by a ULEB128 frame size.
+    let stack_sizes = binutils
+        .objdump(&["-s", "-j", ".stack_sizes"], binary)
+        .ok()
+        .map(|dump| hex_dump(&dump))
+        .filter(|bytes| !bytes.is_empty())
+        .ok_or_else(|| {
+            anyhow!(
+                "The binary has no stack size information. Is it linked with a Cntrlr link script?"
+            )
+        })?;
+    let mut bytes = stack_sizes.iter().copied();
+    while let Some(address) = read_u32(&mut bytes) {
+        let frame =
+            read_uleb128(&mut bytes).ok_or_else(|| anyhow!("Truncated stack size entry"))?;
+        if let Some(function) = functions.get_mut(&(address & !1)) {
+            function.frame = Some(frame);
+        }
+    }
+
+    // The call graph, from the disassembly
+    let starts = functions.keys().copied().collect::<Vec<_>>();
+    let containing = |address: u64| match starts.binary_search(&address) {
+        Ok(idx) => Some(starts[idx]),
+        Err(0) => None,
+        Err(idx) => Some(starts[idx - 1]),
+    };
+    for line in binutils
+        .objdump(&["-d", "--no-show-raw-insn"], binary)?
+        .lines()
+    {
+        let (address, instruction) = match line.find(':') {
+            Some(idx) => (line[..idx].trim(), line[idx + 1..].trim()),
+            None => continue,
+        };
+        let address = match u64::from_str_radix(address, 16) {
+            Ok(address) => address,
+            Err(_) => continue,
+        };
+        let caller = match containing(address) {
+            Some(caller) => caller,
+            None => continue,
+        };
+        let mut parts = instruction.splitn(2, char::is_whitespace);
+        let mnemonic = parts.next().unwrap_or("");
+        // Strip any comment, which may name a symbol without
+        // branching to it.
+        let operands = parts.next().unwrap_or("");
+        let operands = operands
+            .find(&['@', ';'][..])
+            .or_else(|| operands.find("# "))
+            .map_or(operands, |idx| &operands[..idx])
+            .trim();
+
+        if let Some(idx) = operands.find(" <") {
+            let target = operands[..idx]
+                .rsplit(|c: char| c == ',' || c.is_whitespace())
+                .next()
+                .map(|target| target.trim_start_matches("0x"))
+                .and_then(|target| u64::from_str_radix(target, 16).ok());
+            // Branches within a function are not calls. Anything
+            // else which names a function is a call or a tail call.
+            if let Some(callee) = target.and_then(&containing) {
+                if callee != caller && functions.contains_key(&callee) {
+                    functions.get_mut(&caller).unwrap().calls.insert(callee);
+                }
+            }
+        } else if is_indirect(mnemonic, operands) {
+            functions.get_mut(&caller).unwrap().indirect = true;
+        }
+    }
+
+    // The roots of the call graph
+    let reset = symbols
+        .get("__cntrlr_reset")
+        .map(|&(address, _)| address & !1)
+        .ok_or_else(|| anyhow!("The binary has no reset vector"))?;
+    let mut handlers = Vec::new();
+    for table in &["__cntrlr_exceptions", "__cntrlr_interrupts"] {
+        if let Some(&(start, Some(size))) = symbols.get(*table) {
+            let dump = binutils.objdump(
+                &[
+                    "-s",
+                    &format!("--start-address=0x{:x}", start),
+                    &format!("--stop-address=0x{:x}", start + size),
+                ],
+                binary,
+            )?;
+            let mut bytes = hex_dump(&dump).into_iter();
+            while let Some(handler) = read_u32(&mut bytes) {
+                handlers.push(handler & !1);
+            }
+        }
+    }
+    if let Some(&(trap, _)) = symbols.get("__cntrlr_trap") {
+        handlers.push(trap);
+    }
+    handlers.retain(|handler| *handler != reset && functions.contains_key(handler));
+    handlers.sort_unstable();
+    handlers.dedup();
+
+    let mut depths = HashMap::new();
+    let main = depth(reset, &functions, &mut depths, &mut HashSet::new());
+    let frame = entry_frame(target);
+    let isrs = handlers
+        .iter()
+        .map(|&handler| {
+            let mut isr = depth(handler, &functions, &mut depths, &mut HashSet::new());
+            isr.bytes += frame;
+            (handler, isr)
+        })
+        .collect::<Vec<_>>();
+
+    println!("Worst-case stack usage of {}:", binary);
+    println!("  {:>12}  main task", describe(main));
+    for (handler, isr) in &isrs {
+        println!("  {:>12}  {}", describe(*isr), functions[handler].name);
+    }
+
+    // Interrupts are not nested, so at most one handler's frames are
+    // on the stack on top of the main task.
+    let mut worst = main;
+    if let Some(deepest) = isrs.iter().map(|(_, isr)| isr.bytes).max() {
+        worst.bytes += deepest;
+    }
+    for (_, isr) in &isrs {
+        worst.incomplete |= isr.incomplete;
+        worst.recursive |= isr.recursive;
+    }
+    println!("  {:>12}  total, with interrupts", describe(worst));
+
+    let available = match (
+        symbols.get("__cntrlr_stack_top"),
+        symbols.get("__cntrlr_heap_start"),
+    ) {
+        (Some(&(top, _)), Some(&(bottom, _))) => top.saturating_sub(bottom),
+        _ => bail!("The binary does not define the stack and heap boundaries"),
+    };
+    println!("{} bytes are available for the stack and heap", available);
+
+    if worst.recursive {
+        println!("warning: recursion was found, so stack usage is unbounded");
+    } else if worst.bytes > available {
+        println!(
+            "warning: the stack will overflow. {} bytes are needed, but only {} are available",
+            worst.bytes, available
+        );
+    } else if worst.bytes > available / 2 {
+        println!(
+            "warning: the stack may collide with the heap. {} of {} bytes are needed by the stack alone",
+            worst.bytes, available
+        );
+    }
+    if worst.incomplete {
+        println!("note: some calls could not be followed, so usage may be higher than reported");
+    }
+    Ok(())
+}
+
+fn depth(
+    address: u64,
+    functions: &BTreeMap<u64, Function>,
+    depths: &mut HashMap<u64, Depth>,
+    visiting: &mut HashSet<u64>,
+) -> Depth {
+    if let Some(depth) = depths.get(&address) {
+        return *depth;
+    }
+    let function = &functions[&address];
+    if !visiting.insert(address) {
+        return Depth {
+            bytes: 0,
+            incomplete: false,
+            recursive: true,
+        };
+    }
+
+    let mut deepest = Depth {
+        bytes: 0,
+        incomplete: false,
+        recursive: false,
+    };
+    for &callee in &function.calls {
+        let callee = depth(callee, functions, depths, visiting);
+        deepest.bytes = deepest.bytes.max(callee.bytes);
+        deepest.incomplete |= callee.incomplete;
+        deepest.recursive |= callee.recursive;
+    }
+    deepest.bytes += function.frame.unwrap_or(0);
+    deepest.incomplete |= function.frame.is_none() || function.indirect;
+
+    visiting.remove(&address);
+    // A depth found while part of a cycle is only valid within that
+    // cycle, so it is not cached.
+    if !deepest.recursive {
+        depths.insert(address, deepest);
+    }
+    deepest
+}
+
+fn describe(depth: Depth) -> String {
+    if depth.recursive {
+        "unbounded".to_owned()
+    } else if depth.incomplete {
+        format!(">= {}", depth.bytes)
+    } else {
+        format!("{}", depth.bytes)
+    }
+}
+
+fn is_indirect(mnemonic: &str, operands: &str) -> bool {
+    match mnemonic {
+        // ARM calls and tail calls through a register. `bx lr` is a
+        // return.
+        "blx" => true,
+        "bx" => operands != "lr",
+        // RISC-V calls and tail calls through a register
+        "jalr" => true,
+        "jr" => operands != "ra",
+        _ => false,
+    }
+}
+
+/// Collect the bytes from the output of `objdump -s`
+fn hex_dump(dump: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for line in dump.lines() {
+        // Data lines are an address, then four space-separated
+        // groups of four bytes, then the same data as ASCII. Groups
+        // past the end of the data are padded with spaces.
+        if !line.starts_with(' ') {
+            continue;
+        }
+        let line = line.trim_start();
+        let data = match line.find(' ') {
+            Some(idx) => &line[idx + 1..],
+            None => continue,
+        };
+        let hex = data
+            .chars()
+            .take(4 * 9)
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>();
+        for idx in (0..hex.len() / 2 * 2).step_by(2) {
+            if let Ok(byte) = u8::from_str_radix(&hex[idx..idx + 2], 16) {
+                bytes.push(byte);
+            }
+        }
+    }
+    bytes
+}
+
+fn read_u32(bytes: &mut impl Iterator<Item = u8>) -> Option<u64> {
+    let mut value = 0;
+    for shift in 0..4 {
+        value |= (bytes.next()? as u64) << (shift * 8);
+    }
+    Some(value)
+}
+
+fn read_uleb128(bytes: &mut impl Iterator<Item = u8>) -> Option<u64> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = bytes.next()?;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
@@ -0,0 +1,160 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! On-chip debugging
+//!
+//! Starts a GDB server for the board's debug probe, and attaches GDB
+//! to the built binary. OpenOCD is started by GDB itself, talking
+//! over a pipe, so that it exits along with GDB. The J-Link GDB
+//! server is started in the background, and exits once GDB
+//! disconnects from it.
+
+use anyhow::{anyhow, bail, Result};
+use cargo::util::paths::resolve_executable;
+use cntrlr_build::{Board, Flash};
+use std::{io::Write, path::PathBuf, thread::sleep, time::Duration};
+use subprocess::{Exec, ExitStatus};
+use tempfile::NamedTempFile;
+
+/// The GDB server used to reach the board's debug probe
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Server {
+    OpenOcd,
+    JLink,
+}
+
+/// The port the J-Link GDB server listens on
+const JLINK_PORT: u16 = 2331;
+
+/// The OpenOCD configuration files for a board
+///
+/// Also returns a note on how the probe is connected, for boards
+/// which do not have one built in. Returns `None` if OpenOCD cannot
+/// debug the board.
+pub fn openocd_configs(board: &Board) -> Option<(Vec<&'static str>, Option<&'static str>)> {
+    match (&board.flash, board.mcu) {
+        (Flash::OpenOcd(cfg), _) => Some((vec![*cfg], None)),
+        (_, "mk20dx128") | (_, "mk20dx256") | (_, "mk64fx512") | (_, "mk66fx1m0") => Some((
+            vec!["interface/cmsis-dap.cfg", "target/kx.cfg"],
+            Some("Teensy boards need an SWD probe wired to the MCU's debug pins"),
+        )),
+        (_, "mkl26z64") => Some((
+            vec!["interface/cmsis-dap.cfg", "target/klx.cfg"],
+            Some("Teensy boards need an SWD probe wired to the MCU's debug pins"),
+        )),
+        _ => None,
+    }
+}
+
+/// The J-Link name of a board's MCU
+fn jlink_device(board: &Board) -> Option<&'static str> {
+    match board.mcu {
+        "mk20dx128" => Some("MK20DX128xxx5"),
+        "mk20dx256" => Some("MK20DX256xxx7"),
+        "mk64fx512" => Some("MK64FX512xxx12"),
+        "mk66fx1m0" => Some("MK66FX1M0xxx18"),
+        "mkl26z64" => Some("MKL26Z64xxx4"),
+        "fe310g002" => Some("FE310"),
+        _ => None,
+    }
+}
+
+/// Find the GDB for a target, preferring the target's own
+fn find_gdb(target: &str) -> Result<PathBuf> {
+    let prefixed = crate::binutils_prefix(target).map(|prefix| format!("{}-gdb", prefix));
+    prefixed
+        .iter()
+        .map(String::as_str)
+        .chain(["gdb-multiarch", "gdb"].iter().copied())
+        .find_map(|gdb| resolve_executable(&PathBuf::from(gdb)).ok())
+        .ok_or_else(|| anyhow!("No GDB was found for {}", target))
+}
+
+/// Load a binary onto a board, and debug it with GDB
+///
+/// The board is halted at reset once the binary is loaded, with a
+/// breakpoint on the panic handler. This returns once GDB exits.
+pub fn debug(board: &Board, binary: &str, server: Server) -> Result<()> {
+    let gdb = find_gdb(board.targets[0])?;
+    let mut script = NamedTempFile::new()?;
+    writeln!(
+        script,
+        "set confirm off
+         set pagination off
+         set print asm-demangle on
+         set backtrace limit 32"
+    )?;
+
+    match server {
+        Server::OpenOcd => {
+            let (configs, note) = openocd_configs(board)
+                .ok_or_else(|| anyhow!("OpenOCD cannot debug board `{}`", board.name))?;
+            let openocd = resolve_executable(&PathBuf::from("openocd"))?;
+            if let Some(note) = note {
+                eprintln!("Note: {}", note);
+            }
+            let log = format!("{}.openocd.log", binary);
+            let configs = configs
+                .iter()
+                .map(|cfg| format!(" -f {}", cfg))
+                .collect::<String>();
+            eprintln!("OpenOCD output is logged to {}", log);
+            writeln!(
+                script,
+                "target extended-remote | {} -c \"gdb_port pipe; log_output {}\"{}
+                 monitor reset halt",
+                openocd.display(),
+                log,
+                configs
+            )?;
+        }
+        Server::JLink => {
+            let device = jlink_device(board)
+                .ok_or_else(|| anyhow!("J-Link cannot debug board `{}`", board.name))?;
+            let server = resolve_executable(&PathBuf::from("JLinkGDBServerCLExe"))
+                .or_else(|_| resolve_executable(&PathBuf::from("JLinkGDBServer")))?;
+            let log = format!("{}.jlink.log", binary);
+            eprintln!("J-Link GDB server output is logged to {}", log);
+
+            // A background job of a non-interactive shell ignores
+            // SIGINT, so interrupting GDB with Ctrl-C leaves the
+            // server running.
+            let status = Exec::shell(format!(
+                "\"{}\" -device {} -if SWD -speed 4000 -port {} -singlerun -nogui > \"{}\" 2>&1 &",
+                server.display(),
+                device,
+                JLINK_PORT,
+                log
+            ))
+            .join()?;
+            if status != ExitStatus::Exited(0) {
+                bail!("Could not start the J-Link GDB server");
+            }
+            // Give the server time to connect to the probe
+            sleep(Duration::from_secs(1));
+            writeln!(
+                script,
+                "target extended-remote localhost:{}
+                 monitor reset",
+                JLINK_PORT
+            )?;
+        }
+    }
+
+    writeln!(
+        script,
+        "load
+         break rust_begin_unwind"
+    )?;
+
+    let status = Exec::cmd(gdb)
+        .arg("-q")
+        .arg("-x")
+        .arg(script.path())
+        .arg(binary)
+        .join()?;
+    if status != ExitStatus::Exited(0) {
+        bail!("GDB error");
+    }
+    Ok(())
+}
@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Image stamping
+//!
+//! The Cntrlr link scripts reserve an 8-byte `.cntrlr_image_info`
+//! section just past the end of the flash image. After a build, the
+//! length and CRC-32 of the image are written into it, so that the
+//! power-on self-test can check the flash against them.
+
+use anyhow::{bail, Result};
+use cargo::util::paths::resolve_executable;
+use std::{fs, io::Write, path::PathBuf};
+use subprocess::{Exec, ExitStatus};
+use tempfile::NamedTempFile;
+
+const SECTION: &str = ".cntrlr_image_info";
+
+/// Write the length and CRC of a binary's image into the binary
+///
+/// Binaries which were not linked with a Cntrlr link script have no
+/// image info section, and are left alone.
+pub fn stamp(binary: &str, target: &str) -> Result<()> {
+    let prefix = match crate::binutils_prefix(target) {
+        Some(prefix) => prefix,
+        None => return Ok(()),
+    };
+    let objdump = resolve_executable(&PathBuf::from(format!("{}-objdump", prefix)))?;
+    let objcopy = resolve_executable(&PathBuf::from(format!("{}-objcopy", prefix)))?;
+
+    let headers = Exec::cmd(&objdump).arg("-h").arg(binary).capture()?;
+    if headers.exit_status != ExitStatus::Exited(0) {
+        bail!("objdump error: {}", headers.stderr_str().trim());
+    }
+    if !headers
+        .stdout_str()
+        .lines()
+        .any(|line| line.split_whitespace().nth(1) == Some(SECTION))
+    {
+        return Ok(());
+    }
+
+    // The image info comes last, so the rest of the loadable image is
+    // everything from the start of flash up to it.
+    let image = NamedTempFile::new()?;
+    let status = Exec::cmd(&objcopy)
+        .arg("-O")
+        .arg("binary")
+        .arg("--remove-section")
+        .arg(SECTION)
+        .arg(binary)
+        .arg(image.path())
+        .join()?;
+    if status != ExitStatus::Exited(0) {
+        bail!("objcopy error");
+    }
+    let image = fs::read(image.path())?;
+
+    let mut info = NamedTempFile::new()?;
+    info.write_all(&(image.len() as u32).to_le_bytes())?;
+    info.write_all(&crc32(&image).to_le_bytes())?;
+    info.flush()?;
+
+    let status = Exec::cmd(&objcopy)
+        .arg("--update-section")
+        .arg(format!("{}={}", SECTION, info.path().display()))
+        .arg(binary)
+        .join()?;
+    if status != ExitStatus::Exited(0) {
+        bail!("objcopy error");
+    }
+    Ok(())
+}
+
+/// The CRC-32 used by Ethernet and zlib
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
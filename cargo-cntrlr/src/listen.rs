@@ -0,0 +1,171 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Serial log listener
+//!
+//! Prints everything the board writes to a serial port. If the binary
+//! was built with [defmt](https://defmt.ferrous-systems.com) logging,
+//! the stream is decoded into log frames with the format strings from
+//! the binary's `.defmt` section, and each frame is printed with the
+//! timestamp the board gave it. Otherwise the stream is taken as text,
+//! and each line is printed with the time since the listener started.
+
+use anyhow::{bail, Result};
+use cargo::util::paths::resolve_executable;
+use defmt_decoder::{DecodeError, Table};
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::PathBuf,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+use subprocess::{Exec, ExitStatus};
+
+/// Print the log stream from a board until the port is closed
+pub fn listen(binary: &str, port: &str, baud: u32) -> Result<()> {
+    let table = defmt_elf2table::parse(&fs::read(binary)?)?;
+    configure(port, baud)?;
+    let mut port = File::open(port)?;
+    match table {
+        Some(table) => listen_defmt(&mut port, &table),
+        None => listen_text(&mut port),
+    }
+}
+
+/// Find the serial port of a board connected over USB
+///
+/// A board can take a moment to appear once it is reset after
+/// programming, so this waits a few seconds for a port. Fails if no
+/// port appears, or if more than one is found.
+pub fn find_port() -> Result<String> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let mut ports = usb_ports()?;
+        match ports.len() {
+            1 => return Ok(ports.remove(0)),
+            0 if Instant::now() < deadline => sleep(Duration::from_millis(100)),
+            0 => {
+                bail!("No serial port was found. Is the board connected? Use --port to select one")
+            }
+            _ => bail!(
+                "Several serial ports were found ({}). Use --port to select one",
+                ports.join(", ")
+            ),
+        }
+    }
+}
+
+/// The USB serial ports which are currently connected
+fn usb_ports() -> Result<Vec<String>> {
+    let prefixes: &[&str] = if cfg!(target_os = "macos") {
+        &["cu.usbmodem", "cu.usbserial"]
+    } else {
+        &["ttyACM", "ttyUSB"]
+    };
+    let mut ports = Vec::new();
+    for entry in fs::read_dir("/dev")? {
+        let name = entry?.file_name();
+        if let Some(name) = name.to_str() {
+            if prefixes.iter().any(|prefix| name.starts_with(prefix)) {
+                ports.push(format!("/dev/{}", name));
+            }
+        }
+    }
+    ports.sort();
+    Ok(ports)
+}
+
+/// Put a serial port in raw mode at a baud rate
+fn configure(port: &str, baud: u32) -> Result<()> {
+    let stty = resolve_executable(&PathBuf::from("stty"))?;
+    let device_flag = if cfg!(target_os = "linux") {
+        "-F"
+    } else {
+        "-f"
+    };
+    let status = Exec::cmd(stty)
+        .arg(device_flag)
+        .arg(port)
+        .arg("raw")
+        .arg("-echo")
+        .arg(baud.to_string())
+        .join()?;
+    if status != ExitStatus::Exited(0) {
+        bail!("Could not configure serial port `{}`", port);
+    }
+    Ok(())
+}
+
+fn listen_defmt(port: &mut File, table: &Table) -> Result<()> {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let mut frames = Vec::new();
+    let mut buf = [0; 256];
+    loop {
+        let len = port.read(&mut buf)?;
+        if len == 0 {
+            return Ok(());
+        }
+        frames.extend_from_slice(&buf[..len]);
+
+        loop {
+            match defmt_decoder::decode(&frames, table) {
+                Ok((frame, consumed)) => {
+                    writeln!(stdout, "{}", frame.display(true))?;
+                    frames.drain(..consumed);
+                }
+                Err(DecodeError::UnexpectedEof) => break,
+                Err(DecodeError::Malformed) => {
+                    // Without framing there is no way to find the next
+                    // frame, so the rest of the stream is dropped.
+                    eprintln!("Malformed defmt frame; discarding {} bytes", frames.len());
+                    frames.clear();
+                    break;
+                }
+            }
+        }
+        stdout.flush()?;
+    }
+}
+
+fn listen_text(port: &mut File) -> Result<()> {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let start = Instant::now();
+    let mut line = Vec::new();
+    let mut buf = [0; 256];
+    loop {
+        let len = port.read(&mut buf)?;
+        if len == 0 {
+            if !line.is_empty() {
+                print_line(&mut stdout, start, &line)?;
+            }
+            return Ok(());
+        }
+
+        for &byte in &buf[..len] {
+            match byte {
+                b'\n' => {
+                    print_line(&mut stdout, start, &line)?;
+                    line.clear();
+                }
+                b'\r' => {}
+                byte => line.push(byte),
+            }
+        }
+        stdout.flush()?;
+    }
+}
+
+fn print_line(out: &mut impl Write, start: Instant, line: &[u8]) -> Result<()> {
+    let elapsed = start.elapsed();
+    writeln!(
+        out,
+        "[{:>5}.{:06}] {}",
+        elapsed.as_secs(),
+        elapsed.subsec_micros(),
+        String::from_utf8_lossy(line)
+    )?;
+    Ok(())
+}
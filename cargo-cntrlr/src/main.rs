@@ -35,36 +35,18 @@ use clap::{App, AppSettings, Arg, SubCommand};
 use cntrlr_build::{Board, Flash};
 use std::{
     env::current_dir,
-    fs::OpenOptions,
-    io::{Read, Write},
-    path::PathBuf,
+    fs::{create_dir_all, OpenOptions},
+    io::{ErrorKind, Read, Write},
+    path::{Path, PathBuf},
 };
-use subprocess::{Exec, ExitStatus};
+use subprocess::{Exec, ExitStatus, Redirection};
 use tempfile::NamedTempFile;
 
-const MAIN: &str = "#![no_std]
-#![no_main]
-
-use cntrlr::prelude::*;
-
-#[entry]
-async fn main() -> ! {
-    pin_mode(13, PinMode::Output);
-    loop {
-        digital_write(13, true);
-        sleep_millis(500).await;
-        digital_write(13, false);
-        sleep_millis(500).await;
-    }
-}
-";
-
-const DEPS: &str = "[dependencies]
-cntrlr = \"0.1.0\"
-
-[build-dependencies]
-cntrlr-build = \"0.1.0\"
-";
+mod analyze;
+mod debug;
+mod image;
+mod listen;
+mod template;
 
 const BUILD: &str = "
 use cntrlr_build::configure_board;
@@ -74,6 +56,29 @@ fn main() {
 }
 ";
 
+/// The prefix of the binutils for a target, such as `arm-none-eabi`
+fn binutils_prefix(target: &str) -> Option<&'static str> {
+    if target.starts_with("thumb") {
+        Some("arm-none-eabi")
+    } else if target.starts_with("riscv") {
+        Some("riscv64-unknown-elf")
+    } else {
+        None
+    }
+}
+
+/// Write a new file, leaving any existing file in place
+fn write_new(path: &Path, contents: &str) -> Result<()> {
+    match OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(mut file) => Ok(file.write_all(contents.as_bytes())?),
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+            eprintln!("`{}` already exists, and was not changed", path.display());
+            Ok(())
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
 fn build_command(name: &'static str) -> App<'static, 'static> {
     SubCommand::with_name(name)
         .setting(AppSettings::DeriveDisplayOrder)
@@ -275,6 +280,18 @@ fn new_command(name: &'static str) -> App<'static, 'static> {
              .value_name("EDITION")
              .help("Edition to set for the crate generated [possible values: 2015, 2018]")
         )
+        .arg(Arg::with_name("template")
+             .long("template")
+             .takes_value(true)
+             .possible_values(&["basic", "multi-task", "usb-serial", "bootloader", "datalogger"])
+             .value_name("TEMPLATE")
+             .default_value("basic")
+             .help("The template for the generated main.rs"))
+        .arg(Arg::with_name("board")
+             .long("board")
+             .takes_value(true)
+             .value_name("BOARD")
+             .help("Generate code and build configuration for the target board"))
         .arg(Arg::with_name("name")
              .long("name")
              .takes_value(true)
@@ -286,6 +303,41 @@ fn new_command(name: &'static str) -> App<'static, 'static> {
              .required(true))
 }
 
+fn flash_args(command: App<'static, 'static>) -> App<'static, 'static> {
+    command
+        .arg(
+            Arg::with_name("port")
+                .long("port")
+                .takes_value(true)
+                .value_name("PORT")
+                .help("The serial port the programmer is connected at, if needed"),
+        )
+        .arg(
+            Arg::with_name("verify")
+                .long("verify")
+                .help("Read back the flash after programming, and fail if it does not match"),
+        )
+        .arg(
+            Arg::with_name("no_reset")
+                .long("no-reset")
+                .conflicts_with("reset_halt")
+                .help("Do not reset the board after programming"),
+        )
+        .arg(
+            Arg::with_name("reset_halt")
+                .long("reset-halt")
+                .help("Reset the board after programming, but halt it before it runs"),
+        )
+}
+
+/// What to do with the board once it is programmed
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Reset {
+    Run,
+    None,
+    Halt,
+}
+
 fn main() -> Result<()> {
     maybe_allow_nightly_features();
 
@@ -356,15 +408,67 @@ fn main() -> Result<()> {
                 .help("Unstable (nightly-only) flags to Cargo, see 'cargo -Z help' for details"),
         )
         .subcommand(build_command("build").about("Compile the current package"))
+        .subcommand(flash_args(
+            build_command("flash").about("Flash a binary to a target board"),
+        ))
+        .subcommand(flash_args(
+            build_command("run")
+                .about("Flash a binary to a target board, then print its serial output")
+                .arg(
+                    Arg::with_name("baud")
+                        .long("baud")
+                        .takes_value(true)
+                        .value_name("BAUD")
+                        .default_value("115200")
+                        .help("The baud rate of the serial port"),
+                ),
+        ))
+        .subcommand(flash_args(
+            build_command("run-example")
+                .about("Build and flash one of Cntrlr's examples to a target board")
+                .arg(
+                    Arg::with_name("name")
+                        .takes_value(true)
+                        .value_name("EXAMPLE")
+                        .required(true)
+                        .help("The example to run. 'help' For the list of examples for the board."),
+                ),
+        ))
         .subcommand(
-            build_command("flash")
-                .about("Flash a binary to a target board")
+            build_command("analyze")
+                .about("Report the worst-case stack usage of a binary for a target board"),
+        )
+        .subcommand(
+            build_command("debug")
+                .about("Load a binary onto a board through a debug probe, and attach GDB to it")
+                .arg(
+                    Arg::with_name("server")
+                        .long("server")
+                        .takes_value(true)
+                        .possible_values(&["openocd", "jlink"])
+                        .value_name("SERVER")
+                        .default_value("openocd")
+                        .help("The GDB server used to reach the debug probe"),
+                ),
+        )
+        .subcommand(
+            build_command("listen")
+                .about("Print the log output of a binary from a board's serial port")
                 .arg(
                     Arg::with_name("port")
                         .long("port")
                         .takes_value(true)
                         .value_name("PORT")
-                        .help("The serial port the programmer is connected at, if needed"),
+                        .required(true)
+                        .help("The serial port the board is connected at"),
+                )
+                .arg(
+                    Arg::with_name("baud")
+                        .long("baud")
+                        .takes_value(true)
+                        .value_name("BAUD")
+                        .default_value("115200")
+                        .help("The baud rate of the serial port"),
                 ),
         )
         .subcommand(new_command("new").about("Create a new cntrlr package"))
@@ -417,11 +521,40 @@ fn main() -> Result<()> {
         let name = command_matches.value_of("name").map(ToOwned::to_owned);
         let edition = command_matches.value_of("edition").map(ToOwned::to_owned);
         let registry = command_matches.value_of("registry").map(ToOwned::to_owned);
+        let template_name = command_matches.value_of("template").unwrap_or("basic");
+        let template =
+            template::find(template_name).ok_or_else(|| anyhow!("Invalid template specified"))?;
+        let board = command_matches
+            .value_of("board")
+            .map(|board| {
+                board
+                    .parse::<Board>()
+                    .map_err(|_| anyhow!("Invalid board specified"))
+            })
+            .transpose()?;
+        if let Some(board) = &board {
+            if !template::supports(template, board) {
+                bail!(
+                    "The `{}` template does not support the {} board",
+                    template.name,
+                    board.name
+                );
+            }
+        }
 
         if !path.is_absolute() {
             path = current_dir()?.join(path);
         }
 
+        let package_name = match &name {
+            Some(name) => name.clone(),
+            None => path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .ok_or_else(|| anyhow!("Package path is not UTF-8"))?
+                .to_owned(),
+        };
+
         let opts = NewOptions::new(
             version_control,
             bin,
@@ -443,7 +576,7 @@ fn main() -> Result<()> {
             .read(true)
             .open(&manifest_path)?
             .read_to_string(&mut manifest)?;
-        manifest = manifest.replace("[dependencies]", DEPS);
+        manifest = manifest.replace("[dependencies]", &template::dependencies(template));
         OpenOptions::new()
             .write(true)
             .truncate(true)
@@ -464,7 +597,24 @@ fn main() -> Result<()> {
                 .write(true)
                 .truncate(true)
                 .open(&main_path)?
-                .write_all(MAIN.as_bytes())?;
+                .write_all(template::render(template.main, board.as_ref()).as_bytes())?;
+        }
+
+        if let Some(board) = &board {
+            let cargo_path = path.join(".cargo");
+            create_dir_all(&cargo_path)?;
+            write_new(
+                &cargo_path.join("config.toml"),
+                &template::cargo_config(board),
+            )?;
+
+            if !lib {
+                if let Some(launch) = template::launch_config(board, &package_name) {
+                    let vscode_path = path.join(".vscode");
+                    create_dir_all(&vscode_path)?;
+                    write_new(&vscode_path.join("launch.json"), &launch)?;
+                }
+            }
         }
         return Ok(());
     }
@@ -561,6 +711,47 @@ fn main() -> Result<()> {
     let board: Board = board_name
         .parse()
         .map_err(|_| anyhow!("Invalid board specified"))?;
+
+    if command == "run-example" {
+        let name = command_matches
+            .value_of("name")
+            .ok_or_else(|| anyhow!("Example not specified"))?;
+        if name == "help" {
+            for example in &board.examples {
+                println!("{}", example);
+            }
+            return Ok(());
+        }
+        if !board.examples.iter().any(|example| *example == name) {
+            bail!(
+                "Example `{}` does not run on board `{}`. Available examples are: {}",
+                name,
+                board.name,
+                board.examples.join(", ")
+            );
+        }
+        if !workspace
+            .members()
+            .any(|package| package.name().as_str() == "cntrlr")
+        {
+            bail!("The examples can only be run from a checkout of the Cntrlr repository");
+        }
+
+        compile_options.spec = Packages::Packages(vec!["cntrlr".to_owned()]);
+        compile_options.filter = CompileFilter::from_raw_arguments(
+            false,
+            vec![],
+            false,
+            vec![],
+            false,
+            vec![name.to_owned()],
+            false,
+            vec![],
+            false,
+            false,
+        );
+    }
+
     let requested_targets = command_matches
         .values_of("target")
         .map(|targets| {
@@ -571,10 +762,14 @@ fn main() -> Result<()> {
         })
         .unwrap_or_else(|| vec![board.targets[0].to_owned()]);
 
+    let mut rustflags = board.rustflags.to_owned();
+    if command == "analyze" {
+        rustflags.push_str(" -Z emit-stack-sizes");
+    }
     if let Some(env_rustflags) = std::env::var("RUSTFLAGS").ok() {
-        std::env::set_var("RUSTLAGS", format!("{} {}", board.rustflags, env_rustflags));
+        std::env::set_var("RUSTFLAGS", format!("{} {}", rustflags, env_rustflags));
     } else {
-        std::env::set_var("RUSTFLAGS", board.rustflags);
+        std::env::set_var("RUSTFLAGS", rustflags);
     }
 
     let jobs = command_matches
@@ -662,7 +857,59 @@ fn main() -> Result<()> {
     std::env::set_var("CNTRLR_BOARD", board_name);
     let out = compile(&workspace, &compile_options)?;
 
-    if command == "flash" {
+    for (_, binary) in &out.binaries {
+        let binary = binary
+            .to_str()
+            .ok_or_else(|| anyhow!("Binary path is not UTF-8"))?;
+        image::stamp(binary, &requested_targets[0])?;
+    }
+
+    if command == "analyze" {
+        if out.binaries.len() != 1 {
+            bail!("A single binary must be built in order to analyze it");
+        }
+        let binary = out.binaries[0]
+            .1
+            .to_str()
+            .ok_or_else(|| anyhow!("Binary path is not UTF-8"))?;
+        analyze::analyze(binary, &requested_targets[0])?;
+    }
+
+    if command == "debug" {
+        if out.binaries.len() != 1 {
+            bail!("A single binary must be built in order to debug it");
+        }
+        let binary = out.binaries[0]
+            .1
+            .to_str()
+            .ok_or_else(|| anyhow!("Binary path is not UTF-8"))?;
+        let server = match command_matches.value_of("server") {
+            Some("jlink") => debug::Server::JLink,
+            _ => debug::Server::OpenOcd,
+        };
+        debug::debug(&board, binary, server)?;
+    }
+
+    if command == "listen" {
+        if out.binaries.len() != 1 {
+            bail!("A single binary must be built in order to listen to it");
+        }
+        let binary = out.binaries[0]
+            .1
+            .to_str()
+            .ok_or_else(|| anyhow!("Binary path is not UTF-8"))?;
+        let port = command_matches
+            .value_of("port")
+            .ok_or_else(|| anyhow!("--port is required to listen to a board"))?;
+        let baud = command_matches
+            .value_of("baud")
+            .unwrap_or("115200")
+            .parse()
+            .map_err(|_| anyhow!("Invalid baud rate specified"))?;
+        listen::listen(binary, port, baud)?;
+    }
+
+    if command == "flash" || command == "run" || command == "run-example" {
         if out.binaries.len() != 1 {
             bail!("A single binary must be built in order to flash to a board");
         }
@@ -670,13 +917,35 @@ fn main() -> Result<()> {
             .1
             .to_str()
             .ok_or_else(|| anyhow!("Binary path is not UTF-8"))?;
+        let verify = command_matches.is_present("verify");
+        let reset = if command_matches.is_present("no_reset") {
+            Reset::None
+        } else if command_matches.is_present("reset_halt") {
+            Reset::Halt
+        } else {
+            Reset::Run
+        };
+        if command == "run" && reset != Reset::Run {
+            bail!("The board must be reset after programming in order to run it");
+        }
         match board.flash {
             Flash::AvrDude(programmer) => {
+                // avrdude always verifies what it writes, and resets
+                // the board when it exits.
+                if reset != Reset::Run {
+                    bail!("avrdude cannot leave the board without resetting it");
+                }
                 let avrdude = resolve_executable(&PathBuf::from("avrdude"))?;
                 let flash = format!("-Uflash:w:{}", binary);
                 let port = command_matches
                     .value_of("port")
                     .ok_or_else(|| anyhow!("--port is required to program this board"))?;
+                if !Path::new(port).exists() {
+                    bail!(
+                        "Serial port {} does not exist. Is the board connected?",
+                        port
+                    );
+                }
                 let status = Exec::cmd(avrdude)
                     .arg("-p")
                     .arg(board.mcu)
@@ -691,6 +960,13 @@ fn main() -> Result<()> {
                 }
             }
             Flash::TeensyLoader => {
+                // The Teensy bootloader cannot read back its flash
+                if verify {
+                    bail!("Teensy boards cannot be verified after programming");
+                }
+                if reset == Reset::Halt {
+                    bail!("Teensy boards cannot be halted after programming");
+                }
                 let objcopy = resolve_executable(&PathBuf::from("arm-none-eabi-objcopy"))?;
                 let teensyloader = resolve_executable(&PathBuf::from("teensy_loader_cli"))
                     .or_else(|_| resolve_executable(&PathBuf::from("teensy-loader-cli")))?;
@@ -706,13 +982,13 @@ fn main() -> Result<()> {
                     bail!("objcopy error");
                 }
 
-                let status = Exec::cmd(teensyloader)
-                    .arg("-w")
-                    .arg(&hex)
-                    .arg(&mcu)
-                    .join()?;
+                let mut loader = Exec::cmd(teensyloader).arg("-w").arg(&hex).arg(&mcu);
+                if reset == Reset::None {
+                    loader = loader.arg("-n");
+                }
+                let status = loader.join()?;
                 if status != ExitStatus::Exited(0) {
-                    bail!("teensy-loader-cli error");
+                    bail!("teensy-loader-cli error. Is the board connected, with its program button pressed?");
                 }
             }
             Flash::OpenOcd(cfg) => {
@@ -721,17 +997,49 @@ fn main() -> Result<()> {
                 writeln!(
                     cfg_file,
                     "source [find {}]
-                     flash write_image erase {}
-                     reset run
-                     shutdown",
+                     flash write_image erase {}",
                     cfg, binary
                 )?;
-                let status = Exec::cmd(openocd).arg("-f").arg(cfg_file.path()).join()?;
-                if status != ExitStatus::Exited(0) {
+                if verify {
+                    writeln!(cfg_file, "verify_image {}", binary)?;
+                }
+                match reset {
+                    Reset::Run => writeln!(cfg_file, "reset run")?,
+                    Reset::Halt => writeln!(cfg_file, "reset halt")?,
+                    Reset::None => {}
+                }
+                writeln!(cfg_file, "shutdown")?;
+                let output = Exec::cmd(openocd)
+                    .arg("-f")
+                    .arg(cfg_file.path())
+                    .stderr(Redirection::Pipe)
+                    .capture()?;
+                let log = output.stderr_str();
+                eprint!("{}", log);
+                if !output.success() {
+                    if log.contains("open failed") || log.contains("unable to open") {
+                        bail!("No debug probe was found. Is the board connected?");
+                    } else if log.contains("verify failed") || log.contains("checksum mismatch") {
+                        bail!("The flash did not match the binary after programming");
+                    }
                     bail!("OpenOCD error");
                 }
             }
         }
+
+        if command == "run" {
+            let port = match command_matches.value_of("port") {
+                Some(port) => port.to_owned(),
+                None => listen::find_port()?,
+            };
+            let baud = command_matches
+                .value_of("baud")
+                .unwrap_or("115200")
+                .parse()
+                .map_err(|_| anyhow!("Invalid baud rate specified"))?;
+            eprintln!("Listening on {}. Press Ctrl-C to stop.", port);
+            listen::listen(binary, &port, baud)?;
+        }
     }
     Ok(())
 }
@@ -0,0 +1,164 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Project templates for `cargo cntrlr new` and `cargo cntrlr init`
+//!
+//! The `main.rs` templates use `{{console}}` for the prelude function
+//! of the serial port connected to the host, which differs between
+//! boards.
+
+use cntrlr_build::Board;
+
+/// A starting point for a new application
+pub struct Template {
+    /// The name passed to `--template`
+    pub name: &'static str,
+
+    /// The generated `main.rs`
+    pub main: &'static str,
+
+    /// The Cntrlr features the generated code needs
+    pub features: &'static [&'static str],
+
+    /// The boards the generated code runs on, or `None` for every
+    /// board
+    pub boards: Option<&'static [&'static str]>,
+}
+
+/// Every template
+const TEMPLATES: &[Template] = &[
+    Template {
+        name: "basic",
+        main: include_str!("../templates/basic.rs"),
+        features: &[],
+        boards: None,
+    },
+    Template {
+        name: "multi-task",
+        main: include_str!("../templates/multi_task.rs"),
+        features: &[],
+        boards: None,
+    },
+    Template {
+        name: "usb-serial",
+        main: include_str!("../templates/usb_serial.rs"),
+        features: &["usb-serial"],
+        boards: Some(&[
+            "teensy_30",
+            "teensy_32",
+            "teensy_35",
+            "teensy_36",
+            "teensy_lc",
+        ]),
+    },
+    Template {
+        name: "bootloader",
+        main: include_str!("../templates/bootloader.rs"),
+        features: &[],
+        boards: None,
+    },
+    Template {
+        name: "datalogger",
+        main: include_str!("../templates/datalogger.rs"),
+        features: &[],
+        boards: None,
+    },
+];
+
+/// Find a template by name
+pub fn find(name: &str) -> Option<&'static Template> {
+    TEMPLATES.iter().find(|template| template.name == name)
+}
+
+/// Whether a template's generated code runs on a board
+pub fn supports(template: &Template, board: &Board) -> bool {
+    template
+        .boards
+        .map_or(true, |boards| boards.contains(&board.name))
+}
+
+/// The `[dependencies]` section of the manifest for a template
+pub fn dependencies(template: &Template) -> String {
+    let cntrlr = if template.features.is_empty() {
+        "\"0.1.0\"".to_owned()
+    } else {
+        let features = template
+            .features
+            .iter()
+            .map(|feature| format!("\"{}\"", feature))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{{ version = \"0.1.0\", features = [{}] }}", features)
+    };
+    format!(
+        "[dependencies]\ncntrlr = {}\n\n[build-dependencies]\ncntrlr-build = \"0.1.0\"\n",
+        cntrlr
+    )
+}
+
+/// Fill in a `main.rs` template for a board
+///
+/// Without a board, the code is what works on most boards.
+pub fn render(main: &str, board: Option<&Board>) -> String {
+    let console = board.map_or("serial_1", |board| board.console);
+    main.replace("{{console}}", console)
+}
+
+/// The `.cargo/config.toml` for a board
+///
+/// This selects the board's target and link script, so that editors
+/// and plain `cargo build` check the project for the board.
+pub fn cargo_config(board: &Board) -> String {
+    let mut rustflags = vec!["-C", "link-arg=-Tcntrlr.ld"];
+    rustflags.extend(board.rustflags.split_whitespace());
+    let rustflags = rustflags
+        .iter()
+        .map(|flag| format!("\"{}\"", flag))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "[build]\ntarget = \"{target}\"\n\n[target.{target}]\nrustflags = [{rustflags}]\n",
+        target = board.targets[0],
+        rustflags = rustflags,
+    )
+}
+
+/// The `.vscode/launch.json` for debugging a binary on a board
+///
+/// The configuration uses the Cortex-Debug extension with OpenOCD.
+/// Returns `None` if OpenOCD cannot debug the board.
+pub fn launch_config(board: &Board, name: &str) -> Option<String> {
+    let target = board.targets[0];
+    let prefix = crate::binutils_prefix(target)?;
+    let (configs, note) = crate::debug::openocd_configs(board)?;
+    let configs = configs
+        .iter()
+        .map(|cfg| format!("\"{}\"", cfg))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let note = note.map_or(String::new(), |note| format!("            // {}\n", note));
+    Some(format!(
+        r#"{{
+    "version": "0.2.0",
+    "configurations": [
+        {{
+{note}            "name": "Debug on {board}",
+            "type": "cortex-debug",
+            "request": "launch",
+            "servertype": "openocd",
+            "cwd": "${{workspaceRoot}}",
+            "executable": "target/{target}/debug/{name}",
+            "toolchainPrefix": "{prefix}",
+            "configFiles": [{configs}]
+        }}
+    ]
+}}
+"#,
+        note = note,
+        board = board.name,
+        target = target,
+        name = name,
+        prefix = prefix,
+        configs = configs,
+    ))
+}
@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+#![feature(asm)]
+
+use cntrlr::{prelude::*, sync::without_interrupts};
+
+/// Where the application starts in flash
+///
+/// The application must be linked to run from this address.
+#[cfg(target_arch = "arm")]
+const APPLICATION: usize = 0x0000_8000;
+#[cfg(target_arch = "riscv32")]
+const APPLICATION: usize = 0x2004_0000;
+
+/// Hold this pin low at reset to stay in the bootloader
+const BOOT_PIN: usize = 2;
+
+#[entry]
+async fn main() -> ! {
+    pin_mode(BOOT_PIN, PinMode::PulledInput(Pull::Up));
+    sleep_millis(10).await;
+    if digital_read(BOOT_PIN) {
+        without_interrupts(|| unsafe { start_application(APPLICATION) })
+    }
+
+    {{console}}().await.enable(115200).unwrap();
+    writeln!({{console}}().await, "Bootloader ready").await.unwrap();
+    loop {
+        // Receive and write a new application here, then reboot
+        // into it.
+        let mut buf = [0; 64];
+        let count = {{console}}().await.read(&mut buf).await.unwrap();
+        {{console}}().await.write_all(&buf[..count]).await.unwrap();
+    }
+}
+
+/// Jump to the application whose vector table is at `address`
+#[cfg(target_arch = "arm")]
+unsafe fn start_application(address: usize) -> ! {
+    let vectors = address as *const u32;
+    let stack = vectors.read_volatile();
+    let reset = vectors.add(1).read_volatile();
+    // Point VTOR at the application's vector table
+    (0xE000_ED08 as *mut u32).write_volatile(address as u32);
+    asm!(
+        "msr msp, {0}",
+        "bx {1}",
+        in(reg) stack,
+        in(reg) reset,
+        options(noreturn)
+    )
+}
+
+/// Jump to the application whose entry point is at `address`
+#[cfg(target_arch = "riscv32")]
+unsafe fn start_application(address: usize) -> ! {
+    asm!("jr {0}", in(reg) address, options(noreturn))
+}
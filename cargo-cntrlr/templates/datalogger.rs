@@ -0,0 +1,86 @@
+#![no_std]
+#![no_main]
+#![allow(incomplete_features)]
+#![feature(generic_associated_types)]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use cntrlr::{
+    io::BlockDevice,
+    logger::{Logger, Record},
+    prelude::*,
+};
+use core::future::{ready, Ready};
+
+/// A reading from the inputs
+struct Reading {
+    pin_2: bool,
+}
+
+impl Record for Reading {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(self.pin_2 as u8);
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        Some(Reading {
+            pin_2: *buf.get(0)? != 0,
+        })
+    }
+}
+
+/// Storage for the log
+///
+/// This keeps the log in RAM, so that the project runs as
+/// generated. Replace it with a driver for your storage, such as an
+/// SD card or SPI flash.
+struct Storage {
+    blocks: Vec<Vec<u8>>,
+}
+
+impl Storage {
+    fn new() -> Self {
+        Self {
+            blocks: vec![vec![0; 512]; 4],
+        }
+    }
+}
+
+impl BlockDevice for Storage {
+    type Error = ();
+    type ReadFuture<'a> = Ready<Result<(), ()>>;
+    type WriteFuture<'a> = Ready<Result<(), ()>>;
+
+    fn block_size(&self) -> usize {
+        512
+    }
+
+    fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    fn read_block<'a>(&'a mut self, block: usize, buf: &'a mut [u8]) -> Self::ReadFuture<'a> {
+        buf.copy_from_slice(&self.blocks[block]);
+        ready(Ok(()))
+    }
+
+    fn write_block<'a>(&'a mut self, block: usize, buf: &'a [u8]) -> Self::WriteFuture<'a> {
+        self.blocks[block].copy_from_slice(buf);
+        ready(Ok(()))
+    }
+}
+
+#[entry]
+async fn main() -> ! {
+    pin_mode(2, PinMode::Input);
+    let mut logger = Logger::open(Storage::new(), 0, 4).await.unwrap();
+    loop {
+        let reading = Reading {
+            pin_2: digital_read(2),
+        };
+        logger.log(&reading).await.unwrap();
+        logger.flush().await.unwrap();
+        sleep_millis(1000).await;
+    }
+}
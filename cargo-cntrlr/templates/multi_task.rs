@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+
+use cntrlr::{macros::raw_entry, prelude::*, task::Executor};
+
+/// Blink the LED
+async fn blink() -> ! {
+    pin_mode(13, PinMode::Output);
+    loop {
+        digital_write(13, true);
+        sleep_millis(500).await;
+        digital_write(13, false);
+        sleep_millis(500).await;
+    }
+}
+
+/// Echo every byte received on the serial console
+async fn echo() -> ! {
+    {{console}}().await.enable(115200).unwrap();
+    let mut buf = [0; 64];
+    loop {
+        let count = {{console}}().await.read(&mut buf).await.unwrap();
+        {{console}}().await.write_all(&buf[..count]).await.unwrap();
+    }
+}
+
+#[raw_entry]
+extern "C" fn main() -> ! {
+    let mut executor = Executor::new();
+    executor.add_task_named("blink", blink());
+    executor.add_task_named("echo", echo());
+    unsafe { executor.run() }
+}
@@ -0,0 +1,15 @@
+#![no_std]
+#![no_main]
+
+use cntrlr::prelude::*;
+
+/// Echo every byte received on the USB serial port
+#[entry]
+async fn main() -> ! {
+    let mut port = usb_serial().await;
+    let mut buf = [0; 64];
+    loop {
+        let count = port.read(&mut buf).await.unwrap();
+        port.write_all(&buf[..count]).await.unwrap();
+    }
+}
@@ -5,7 +5,7 @@
 
 #![deny(missing_docs)]
 
-use std::{env, str::FromStr};
+use std::{env, process::Command, str::FromStr};
 
 /// Set up the rust build environment for the selected board.
 ///
@@ -13,17 +13,68 @@ use std::{env, str::FromStr};
 /// will set the `board=` and `mcu=` rust configurations, which can be
 /// used to customize your application behavior based on the target
 /// environment. It also returns information about the selected board.
+///
+/// Returns `None`, with a warning, if `CNTRLR_BOARD` is not set.
+///
+/// # Panics
+/// This function is intended to be called from a build script, and
+/// panics to fail the build if:
+/// * `CNTRLR_BOARD` is not a supported board
+/// * The rust target is not compatible with the selected board
+/// * The compiler does not support the unstable features Cntrlr uses
 pub fn configure_board() -> Option<Board> {
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-env-changed=CNTRLR_BOARD");
-    env::var("CNTRLR_BOARD")
-        .ok()
-        .and_then(|board| Board::from_str(&board).ok())
-        .map(|board| {
-            println!("cargo:rustc-cfg=board=\"{}\"", board.name);
-            println!("cargo:rustc-cfg=mcu=\"{}\"", board.mcu);
-            board
-        })
+    let board_name = match env::var("CNTRLR_BOARD") {
+        Ok(board_name) => board_name,
+        Err(_) => {
+            println!("cargo:warning=CNTRLR_BOARD not specified, no board will be configured");
+            return None;
+        }
+    };
+    let board = Board::from_str(&board_name)
+        .unwrap_or_else(|_| panic!("CNTRLR_BOARD `{}` is not a supported board", board_name));
+
+    if let Ok(target) = env::var("TARGET") {
+        if !board.validate_target(&target) {
+            panic!(
+                "Target `{}` is not compatible with board `{}`. Compatible targets are: {}",
+                target,
+                board.name,
+                board.targets.join(", ")
+            );
+        }
+    }
+
+    if !unstable_features_available() {
+        panic!("Cntrlr requires a nightly compiler, or `RUSTC_BOOTSTRAP=1`");
+    }
+
+    println!("cargo:rustc-cfg=board=\"{}\"", board.name);
+    println!("cargo:rustc-cfg=mcu=\"{}\"", board.mcu);
+    Some(board)
+}
+
+/// Check whether the compiler allows unstable features
+///
+/// Cntrlr relies on several unstable language features, which are
+/// only accepted by nightly and development compilers, or when
+/// `RUSTC_BOOTSTRAP` is set. If the compiler version cannot be
+/// determined, it is assumed to be capable.
+fn unstable_features_available() -> bool {
+    println!("cargo:rerun-if-env-changed=RUSTC_BOOTSTRAP");
+    if env::var_os("RUSTC_BOOTSTRAP").is_some() {
+        return true;
+    }
+
+    let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    match Command::new(rustc).arg("--version").output() {
+        Ok(output) => {
+            let version = String::from_utf8_lossy(&output.stdout);
+            version.contains("-nightly") || version.contains("-dev")
+        }
+        Err(_) => true,
+    }
 }
 
 /// The utility used to flash a board
@@ -55,6 +106,19 @@ pub struct Board {
 
     /// The preferred method for flashing an image to the board
     pub flash: Flash,
+
+    /// The size of the board's program flash, in bytes
+    pub flash_size: usize,
+
+    /// The size of the board's RAM, in bytes
+    pub ram_size: usize,
+
+    /// The examples in the Cntrlr crate which run on this board
+    pub examples: Vec<&'static str>,
+
+    /// The serial port which is connected to the host, as named in
+    /// the Cntrlr prelude
+    pub console: &'static str,
 }
 
 impl FromStr for Board {
@@ -71,6 +135,10 @@ impl FromStr for Board {
                 ],
                 rustflags: "-C target-cpu=cortex-m4 -C target-feature=-fpregs",
                 flash: Flash::TeensyLoader,
+                flash_size: 128 * 1024,
+                ram_size: 16 * 1024,
+                examples: vec!["blink", "hw_tests", "serial_echo", "spi_flash"],
+                console: "serial_1",
             }),
             "teensy31" | "teensy32" => Ok(Self {
                 name: "teensy_32",
@@ -82,6 +150,10 @@ impl FromStr for Board {
                 ],
                 rustflags: "-C target-cpu=cortex-m4 -C target-feature=-fpregs",
                 flash: Flash::TeensyLoader,
+                flash_size: 256 * 1024,
+                ram_size: 64 * 1024,
+                examples: vec!["blink", "hw_tests", "serial_echo", "spi_flash"],
+                console: "serial_1",
             }),
             "teensy35" => Ok(Self {
                 name: "teensy_35",
@@ -94,6 +166,10 @@ impl FromStr for Board {
                 ],
                 rustflags: "-C target-cpu=cortex-m4",
                 flash: Flash::TeensyLoader,
+                flash_size: 512 * 1024,
+                ram_size: 256 * 1024,
+                examples: vec!["blink", "hw_tests", "serial_echo", "spi_flash"],
+                console: "serial_1",
             }),
             "teensy36" => Ok(Self {
                 name: "teensy_36",
@@ -106,6 +182,10 @@ impl FromStr for Board {
                 ],
                 rustflags: "-C target-cpu=cortex-m4",
                 flash: Flash::TeensyLoader,
+                flash_size: 1024 * 1024,
+                ram_size: 256 * 1024,
+                examples: vec!["blink", "hw_tests", "serial_echo", "spi_flash"],
+                console: "serial_1",
             }),
             "teensy40" | "teensy4" => Ok(Self {
                 name: "teensy_40",
@@ -118,6 +198,10 @@ impl FromStr for Board {
                 ],
                 rustflags: "-C target-cpu=cortex-m7",
                 flash: Flash::TeensyLoader,
+                flash_size: 2048 * 1024,
+                ram_size: 1024 * 1024,
+                examples: vec!["blink", "serial_echo"],
+                console: "serial_1",
             }),
             "teensy41" => Ok(Self {
                 name: "teensy_41",
@@ -130,6 +214,10 @@ impl FromStr for Board {
                 ],
                 rustflags: "-C target-cpu=cortex-m7",
                 flash: Flash::TeensyLoader,
+                flash_size: 8192 * 1024,
+                ram_size: 1024 * 1024,
+                examples: vec!["blink", "serial_echo"],
+                console: "serial_1",
             }),
             "teensylc" => Ok(Self {
                 name: "teensy_lc",
@@ -137,6 +225,10 @@ impl FromStr for Board {
                 targets: vec!["thumbv6m-none-eabi"],
                 rustflags: "-C target-cpu=cortex-m0",
                 flash: Flash::TeensyLoader,
+                flash_size: 64 * 1024,
+                ram_size: 8 * 1024,
+                examples: vec!["blink", "hw_tests", "serial_echo"],
+                console: "serial_1",
             }),
             "arduinouno" => Ok(Self {
                 name: "arduino_uno",
@@ -144,6 +236,10 @@ impl FromStr for Board {
                 targets: vec!["avr-none-none"],
                 rustflags: "-C target-cpu=atmega328p",
                 flash: Flash::AvrDude("arduino"),
+                flash_size: 32 * 1024,
+                ram_size: 2 * 1024,
+                examples: vec![],
+                console: "serial_1",
             }),
             "redv" => Ok(Self {
                 name: "red_v",
@@ -155,6 +251,10 @@ impl FromStr for Board {
                 ],
                 rustflags: "-C target-cpu=sifive-e31",
                 flash: Flash::OpenOcd("board/sifive-hifive1-revb.cfg"),
+                flash_size: 4096 * 1024,
+                ram_size: 16 * 1024,
+                examples: vec!["blink", "hw_tests", "serial_echo"],
+                console: "pc_serial",
             }),
             _ => Err(()),
         }
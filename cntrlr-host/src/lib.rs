@@ -0,0 +1,362 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Host-side communication with Cntrlr boards
+//!
+//! A [`Client`] calls the RPC handlers registered with a board's
+//! `cntrlr::protocol::rpc::Server`. The wire format is described in
+//! the documentation for that module.
+//!
+//! ```no_run
+//! use cntrlr_host::Client;
+//!
+//! let mut client = Client::open("/dev/ttyACM0").unwrap();
+//! let response = client.call(1, b"ping").unwrap();
+//! assert_eq!(response, b"ping");
+//! ```
+
+#![deny(missing_docs)]
+
+use std::{
+    error,
+    fmt::{self, Display, Formatter},
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    path::Path,
+};
+
+/// The largest request or response payload, in bytes
+///
+/// This must match `cntrlr::protocol::rpc::MAX_PAYLOAD`.
+pub const MAX_PAYLOAD: usize = 1024;
+
+const STATUS_OK: u8 = 0;
+const STATUS_UNKNOWN_HANDLER: u8 = 1;
+const STATUS_HANDLER_FAILED: u8 = 2;
+const STATUS_MALFORMED: u8 = 3;
+
+/// An error from an RPC call
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CallError {
+    /// The call cannot be made because the connection failed
+    Io(io::Error),
+
+    /// The call cannot be made because its payload is too long
+    TooLong,
+
+    /// The call cannot be made because the board has no handler with its ID
+    UnknownHandler,
+
+    /// The call cannot be made because the board rejected the request
+    Malformed,
+
+    /// The handler failed, with a description of its error
+    HandlerFailed(String),
+
+    /// The call cannot be completed because the response was corrupt
+    BadResponse,
+}
+
+impl Display for CallError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CallError::Io(err) => write!(f, "I/O error: {}", err),
+            CallError::TooLong => write!(f, "payload longer than {} bytes", MAX_PAYLOAD),
+            CallError::UnknownHandler => write!(f, "no handler registered with this ID"),
+            CallError::Malformed => write!(f, "the board rejected the request"),
+            CallError::HandlerFailed(err) => write!(f, "handler failed: {}", err),
+            CallError::BadResponse => write!(f, "the response was corrupt"),
+        }
+    }
+}
+
+impl error::Error for CallError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            CallError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CallError {
+    fn from(err: io::Error) -> Self {
+        CallError::Io(err)
+    }
+}
+
+/// A client for a board's RPC server
+pub struct Client<T> {
+    inner: T,
+    seq: u8,
+    read_buf: Vec<u8>,
+}
+
+impl Client<File> {
+    /// Open a serial port
+    ///
+    /// The port is used with its current settings. For a board's
+    /// hardware UART, the baud rate must be set beforehand, such as
+    /// with `stty`. USB serial ports need no configuration.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let port = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Self::new(port))
+    }
+}
+
+impl<T> Client<T> {
+    /// Create a client on a byte stream
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            seq: 0,
+            read_buf: Vec::new(),
+        }
+    }
+
+    /// Unwrap the byte stream
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T> Client<T>
+where
+    T: Read + Write,
+{
+    /// Call a handler on the board
+    ///
+    /// Blocks until the board responds. Responses to earlier calls,
+    /// and corrupt frames, are skipped.
+    pub fn call(&mut self, id: u16, payload: &[u8]) -> Result<Vec<u8>, CallError> {
+        if payload.len() > MAX_PAYLOAD {
+            return Err(CallError::TooLong);
+        }
+        self.seq = self.seq.wrapping_add(1);
+
+        let mut request = Vec::with_capacity(payload.len() + 3);
+        request.push(self.seq);
+        request.extend_from_slice(&id.to_le_bytes());
+        request.extend_from_slice(payload);
+        self.inner.write_all(&encode(&request))?;
+        self.inner.flush()?;
+
+        loop {
+            let response = match self.read_frame()? {
+                Some(response) => response,
+                None => continue,
+            };
+            if response.len() < 2 || response[0] != self.seq {
+                continue;
+            }
+
+            let payload = response[2..].to_vec();
+            return match response[1] {
+                STATUS_OK => Ok(payload),
+                STATUS_UNKNOWN_HANDLER => Err(CallError::UnknownHandler),
+                STATUS_HANDLER_FAILED => Err(CallError::HandlerFailed(
+                    String::from_utf8_lossy(&payload).into_owned(),
+                )),
+                STATUS_MALFORMED => Err(CallError::Malformed),
+                _ => Err(CallError::BadResponse),
+            };
+        }
+    }
+
+    /// Read a frame and check its CRC
+    ///
+    /// Returns `None` if the frame was corrupt.
+    fn read_frame(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let end = loop {
+            if let Some(end) = self.read_buf.iter().position(|&byte| byte == 0) {
+                break end;
+            }
+            let mut chunk = [0; 64];
+            let count = self.inner.read(&mut chunk)?;
+            if count == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            self.read_buf.extend_from_slice(&chunk[..count]);
+        };
+
+        let encoded: Vec<u8> = self.read_buf.drain(..=end).collect();
+        Ok(decode(&encoded[..end]))
+    }
+}
+
+/// COBS-encode a message with its CRC, including the frame delimiter
+fn encode(message: &[u8]) -> Vec<u8> {
+    let mut frame = message.to_vec();
+    frame.extend_from_slice(&crc32(message).to_le_bytes());
+
+    let mut encoded = Vec::with_capacity(frame.len() + frame.len() / 254 + 2);
+    let mut code_idx = 0;
+    encoded.push(0);
+    let mut code = 1;
+    for byte in frame {
+        if byte != 0 {
+            encoded.push(byte);
+            code += 1;
+        }
+        if byte == 0 || code == 0xFF {
+            encoded[code_idx] = code;
+            code_idx = encoded.len();
+            encoded.push(0);
+            code = 1;
+        }
+    }
+    encoded[code_idx] = code;
+    encoded.push(0);
+    encoded
+}
+
+/// Decode a COBS frame, without its delimiter, and check its CRC
+fn decode(encoded: &[u8]) -> Option<Vec<u8>> {
+    let mut frame = Vec::with_capacity(encoded.len());
+    let mut idx = 0;
+    while idx < encoded.len() {
+        let code = encoded[idx] as usize;
+        if code == 0 || idx + code > encoded.len() {
+            return None;
+        }
+        frame.extend_from_slice(&encoded[idx + 1..idx + code]);
+        idx += code;
+        if code < 0xFF && idx < encoded.len() {
+            frame.push(0);
+        }
+    }
+
+    if frame.len() < 4 {
+        return None;
+    }
+    let split = frame.len() - 4;
+    let mut expected = [0; 4];
+    expected.copy_from_slice(&frame[split..]);
+    frame.truncate(split);
+    if crc32(&frame) == u32::from_le_bytes(expected) {
+        Some(frame)
+    } else {
+        None
+    }
+}
+
+/// The CRC-32 used by `cntrlr::io::codec::CrcKind::Crc32`
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode and decode a message, checking that the frame has no
+    /// zeros except its delimiter
+    fn round_trip(message: &[u8]) -> Option<Vec<u8>> {
+        let encoded = encode(message);
+        let (delimiter, frame) = encoded.split_last().unwrap();
+        assert_eq!(*delimiter, 0);
+        assert!(!frame.contains(&0));
+        decode(frame)
+    }
+
+    #[test]
+    fn crc32_known_vector() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn cobs_round_trip() {
+        let messages: &[&[u8]] = &[b"", b"ping", &[1, 0, 2, 0, 0, 3], &[0xFF; 16]];
+        for message in messages {
+            assert_eq!(round_trip(message).as_deref(), Some(*message));
+        }
+    }
+
+    #[test]
+    fn cobs_zeros() {
+        for len in 1..4 {
+            let message = vec![0; len];
+            assert_eq!(round_trip(&message), Some(message));
+        }
+    }
+
+    #[test]
+    fn cobs_long_runs() {
+        // Runs of 254 non-zero bytes fill a COBS block exactly, so
+        // test them on either side of the CRC and around a zero.
+        for len in 250..260 {
+            let message = vec![0x5A; len];
+            assert_eq!(round_trip(&message), Some(message));
+
+            let mut message = vec![0x5A; len];
+            message.push(0);
+            message.extend(vec![0xA5; len]);
+            assert_eq!(round_trip(&message), Some(message));
+        }
+        let message: Vec<u8> = (1..=254).collect();
+        assert_eq!(round_trip(&message), Some(message));
+    }
+
+    #[test]
+    fn decode_rejects_corruption() {
+        let mut encoded = encode(b"ping");
+        encoded.pop();
+        encoded[2] ^= 1;
+        assert_eq!(decode(&encoded), None);
+        assert_eq!(decode(&[]), None);
+        assert_eq!(decode(&[5, 1]), None);
+    }
+
+    /// A byte stream which replays canned responses
+    struct Loopback {
+        responses: io::Cursor<Vec<u8>>,
+        written: Vec<u8>,
+    }
+
+    impl Read for Loopback {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.responses.read(buf)
+        }
+    }
+
+    impl Write for Loopback {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn call_skips_stale_and_corrupt_responses() {
+        let mut responses = encode(&[0, STATUS_OK, b'x']);
+        responses.extend_from_slice(&[3, 1, 2, 0]);
+        responses.extend(encode(&[1, STATUS_OK, b'o', b'k']));
+        let mut client = Client::new(Loopback {
+            responses: io::Cursor::new(responses),
+            written: Vec::new(),
+        });
+        assert_eq!(client.call(0x102, b"ping").unwrap(), b"ok");
+
+        let written = client.into_inner().written;
+        assert_eq!(
+            decode(&written[..written.len() - 1]),
+            Some(vec![1, 0x02, 0x01, b'p', b'i', b'n', b'g'])
+        );
+    }
+}
@@ -13,7 +13,7 @@ use syn::{
     punctuated::Punctuated,
     spanned::Spanned,
     token::Comma,
-    FnArg, Ident, ItemFn, ItemUse, Pat, ReturnType, Type,
+    FnArg, Ident, ItemFn, ItemUse, Pat, Path, ReturnType, Type,
 };
 
 struct IdentList {
@@ -28,6 +28,18 @@ impl Parse for IdentList {
     }
 }
 
+struct PathList {
+    paths: Punctuated<Path, Comma>,
+}
+
+impl Parse for PathList {
+    fn parse(input: ParseStream) -> Result<Self> {
+        Ok(PathList {
+            paths: input.parse_terminated(Path::parse)?,
+        })
+    }
+}
+
 /// Add a function to the prelude
 ///
 /// This macro generates the appropriate attributes for a function to
@@ -124,9 +136,33 @@ pub fn board_fn(args: TokenStream, input: TokenStream) -> TokenStream {
 /// executor and adds the marked function as a task. If any enabled
 /// Cntrlr features require background tasks (such as USB), those
 /// tasks will also be added to the executor.
+///
+/// Other tasks can be listed in the attribute, and are added to the
+/// executor after the main task. Each must be an `async fn() -> !`,
+/// like the main task. Every task is named after its function, for
+/// the executor's diagnostics.
+///
+/// ```ignore
+/// #[entry(blink)]
+/// async fn main() -> ! {
+///     loop {
+///         // ...
+///     }
+/// }
+///
+/// async fn blink() -> ! {
+///     loop {
+///         led_write(true);
+///         sleep_millis(500).await;
+///         led_write(false);
+///         sleep_millis(500).await;
+///     }
+/// }
+/// ```
 #[proc_macro_attribute]
-pub fn entry(_args: TokenStream, input: TokenStream) -> TokenStream {
+pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
     let input_fn = parse_macro_input!(input as ItemFn);
+    let tasks = parse_macro_input!(args as PathList);
     let sig = &input_fn.sig;
     let fn_name = &sig.ident;
 
@@ -150,6 +186,17 @@ pub fn entry(_args: TokenStream, input: TokenStream) -> TokenStream {
         .into();
     }
 
+    let fn_name_str = format!("{}", fn_name);
+    let task_names = tasks
+        .paths
+        .iter()
+        .map(|task| match task.segments.last() {
+            Some(segment) => format!("{}", segment.ident),
+            None => String::new(),
+        })
+        .collect::<Vec<_>>();
+    let tasks = tasks.paths.iter();
+
     quote!(
         #[export_name = "__cntrlr_main"]
         // This is flagged as unsafe just in case the input_fn is
@@ -158,8 +205,9 @@ pub fn entry(_args: TokenStream, input: TokenStream) -> TokenStream {
             #input_fn
 
             let mut executor =  ::cntrlr::task::Executor::new();
-            executor.add_task(#fn_name());
-            // TODO: Add tasks for device drivers as needed.
+            executor.add_task_named(#fn_name_str, #fn_name());
+            #(executor.add_task_named(#task_names, #tasks());)*
+            executor.add_driver_tasks();
             executor.run()
         }
     )
@@ -170,7 +218,8 @@ pub fn entry(_args: TokenStream, input: TokenStream) -> TokenStream {
 ///
 /// This allows you control of Cntrlr application startup, including
 /// whether or not to use an async executor and which tasks are added
-/// to it.
+/// to it. Call `Executor::add_driver_tasks` on an executor to keep
+/// drivers such as the USB serial port running.
 #[proc_macro_attribute]
 pub fn raw_entry(_args: TokenStream, input: TokenStream) -> TokenStream {
     let input_fn = parse_macro_input!(input as ItemFn);
@@ -1,6 +1,10 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 // Copyright 2020 Branan Riley <me@branan.info>
 
+//! Blink the board's LED once a second
+//!
+//! Run with `cargo cntrlr run-example blink --board=<board>`
+
 #![no_std]
 #![no_main]
 
@@ -8,11 +12,11 @@ use cntrlr::prelude::*;
 
 #[entry]
 async fn main() -> ! {
-    pin_mode(13, PinMode::Output);
+    let led = led();
     loop {
-        digital_write(13, true);
+        led.on();
         sleep_millis(500).await;
-        digital_write(13, false);
+        led.off();
         sleep_millis(500).await;
     }
 }
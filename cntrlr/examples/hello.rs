@@ -13,15 +13,19 @@ use core::future::pending;
 #[entry]
 async fn main() -> ! {
     let mut name = String::new();
-    serial_1().enable(9600).expect("Could not initalize serial");
-    write!(serial_1(), "Enter your name: ")
+    serial_1()
+        .await
+        .enable(9600)
+        .expect("Could not initalize serial");
+    write!(serial_1().await, "Enter your name: ")
         .await
         .expect("Could not write prompt to serial");
     serial_1()
+        .await
         .read_line(&mut name)
         .await
         .expect("Could not read name from serial");
-    writeln!(serial_1(), "Hello, {}", name)
+    writeln!(serial_1().await, "Hello, {}", name)
         .await
         .expect("Could not write response to serial");
     pending().await
@@ -0,0 +1,317 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Hardware tests of the Cntrlr HAL
+//!
+//! Run with `cargo cntrlr run-example hw_tests --board=<board>`, after
+//! wiring the board's jumpers as listed in `WIRING.md` next to this
+//! file. Each test prints `PASS`, `FAIL`, or `SKIP` to the board's
+//! console at 115200 baud, and a summary line is printed once every
+//! test has run. The LED is lit if every test passed.
+//!
+//! These are meant to be run by maintainers on real boards before a
+//! release, or after a change to a board's HAL, to catch regressions
+//! which the host-side tests cannot.
+
+#![no_std]
+#![no_main]
+#![feature(future_poll_fn)]
+
+use cntrlr::{io::SpiTransfer, post, prelude::*, time::delay_us_blocking};
+use core::{
+    future::{poll_fn, Future},
+    pin::Pin,
+    task::Poll,
+};
+
+#[cfg(board = "red_v")]
+use cntrlr::io::pc_serial as console;
+#[cfg(not(board = "red_v"))]
+use cntrlr::io::serial_1 as console;
+
+/// The jumpers and clock settings for one board
+///
+/// These must match `WIRING.md`.
+struct Wiring {
+    /// The two ends of the GPIO strap
+    strap: (usize, usize),
+
+    /// The core clock to switch to for the clock change test
+    test_clock: usize,
+
+    /// The core clock the board starts at
+    default_clock: usize,
+}
+
+#[cfg(any(
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36"
+))]
+const WIRING: Wiring = Wiring {
+    strap: (5, 6),
+    test_clock: 24_000_000,
+    default_clock: DEFAULT_CLOCK,
+};
+
+#[cfg(board = "teensy_lc")]
+const WIRING: Wiring = Wiring {
+    strap: (5, 6),
+    test_clock: 24_000_000,
+    default_clock: 48_000_000,
+};
+
+#[cfg(board = "red_v")]
+const WIRING: Wiring = Wiring {
+    strap: (5, 6),
+    test_clock: 384_000_000,
+    default_clock: 256_000_000,
+};
+
+#[cfg(board = "teensy_30")]
+const DEFAULT_CLOCK: usize = 48_000_000;
+#[cfg(board = "teensy_32")]
+const DEFAULT_CLOCK: usize = 72_000_000;
+#[cfg(any(board = "teensy_35", board = "teensy_36"))]
+const DEFAULT_CLOCK: usize = 120_000_000;
+
+#[cfg(board = "red_v")]
+use cntrlr::hw::board::red_v::set_clock;
+#[cfg(board = "teensy_30")]
+use cntrlr::hw::board::teensy_30::set_clock;
+#[cfg(board = "teensy_32")]
+use cntrlr::hw::board::teensy_32::set_clock;
+#[cfg(board = "teensy_35")]
+use cntrlr::hw::board::teensy_35::set_clock;
+#[cfg(board = "teensy_36")]
+use cntrlr::hw::board::teensy_36::set_clock;
+#[cfg(board = "teensy_lc")]
+use cntrlr::hw::board::teensy_lc::set_clock;
+
+/// The pin connected to the board's LED
+const LED: usize = 13;
+
+/// The baud rate of the console and of the serial loopback
+const BAUD: usize = 115200;
+
+/// How long a loopback waits for data before failing, in milliseconds
+const TIMEOUT: usize = 100;
+
+/// The outcome of one test
+enum Outcome {
+    Pass,
+    Fail(&'static str),
+    Skip(&'static str),
+}
+
+#[entry]
+async fn main() -> ! {
+    console().await.enable(BAUD).unwrap();
+    writeln!(console().await, "Cntrlr hardware tests")
+        .await
+        .unwrap();
+
+    let mut failures = 0;
+    failures += report("serial loopback", serial_loopback().await).await;
+    failures += report("spi loopback", spi_loopback().await).await;
+    failures += report("gpio strap", gpio_strap().await).await;
+    failures += report("timer accuracy", timer_accuracy().await).await;
+    failures += report("clock change", clock_change().await).await;
+
+    if failures == 0 {
+        writeln!(console().await, "All tests passed").await.unwrap();
+    } else {
+        writeln!(console().await, "{} tests failed", failures)
+            .await
+            .unwrap();
+    }
+    pin_mode(LED, PinMode::Output);
+    digital_write(LED, failures == 0);
+    core::future::pending().await
+}
+
+/// Print the outcome of a test, and return 1 if it failed
+async fn report(name: &str, outcome: Outcome) -> usize {
+    let mut console = console().await;
+    match outcome {
+        Outcome::Pass => {
+            writeln!(console, "PASS {}", name).await.unwrap();
+            0
+        }
+        Outcome::Fail(reason) => {
+            writeln!(console, "FAIL {}: {}", name, reason)
+                .await
+                .unwrap();
+            1
+        }
+        Outcome::Skip(reason) => {
+            writeln!(console, "SKIP {}: {}", name, reason)
+                .await
+                .unwrap();
+            0
+        }
+    }
+}
+
+/// Send bytes out of serial 2, and read them back on its jumpered receive pin
+async fn serial_loopback() -> Outcome {
+    const MESSAGE: &[u8] = b"The quick brown fox jumps over the lazy dog";
+
+    let mut serial = serial_2().await;
+    if serial.enable(BAUD).is_err() {
+        return Outcome::Fail("could not enable serial 2");
+    }
+    let mut received = [0; MESSAGE.len()];
+    let result = with_timeout(TIMEOUT, async {
+        serial.write_all(MESSAGE).await?;
+        serial.read_exact(&mut received).await
+    })
+    .await;
+    let _ = serial.disable();
+
+    match result {
+        None => Outcome::Fail("timed out"),
+        Some(Err(_)) => Outcome::Fail("serial error"),
+        Some(Ok(())) if received != *MESSAGE => Outcome::Fail("wrong data received"),
+        Some(Ok(())) => Outcome::Pass,
+    }
+}
+
+/// Transfer bytes over SPI 1, with its data output jumpered to its data input
+#[cfg(any(
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36"
+))]
+async fn spi_loopback() -> Outcome {
+    const MESSAGE: &[u8] = &[0x00, 0xFF, 0xA5, 0x5A, 0x01, 0x80, 0x3C, 0xC3];
+
+    // The chip select is left unconnected
+    const CS: usize = 15;
+
+    pin_mode(CS, PinMode::Output);
+    digital_write(CS, true);
+
+    let mut spi = spi_1().await;
+    if spi.enable().is_err() {
+        return Outcome::Fail("could not enable spi 1");
+    }
+    let mut received = [0; MESSAGE.len()];
+    let result = with_timeout(TIMEOUT, async {
+        spi.transfer(1_000_000, CS, 8)
+            .await?
+            .transfer(MESSAGE, &mut received)
+            .await
+    })
+    .await;
+    let _ = spi.disable();
+
+    match result {
+        None => Outcome::Fail("timed out"),
+        Some(Err(_)) => Outcome::Fail("spi error"),
+        Some(Ok(_)) if received != *MESSAGE => Outcome::Fail("wrong data received"),
+        Some(Ok(_)) => Outcome::Pass,
+    }
+}
+
+#[cfg(any(board = "teensy_lc", board = "red_v"))]
+async fn spi_loopback() -> Outcome {
+    Outcome::Skip("no SPI port")
+}
+
+/// Drive each end of the strap, and read it from the other end
+///
+/// The reading end is pulled against the driven level, so that a
+/// missing jumper is caught.
+async fn gpio_strap() -> Outcome {
+    let (a, b) = WIRING.strap;
+    let mut outcome = Outcome::Pass;
+    for &(out, input) in &[(a, b), (b, a)] {
+        pin_mode(out, PinMode::Output);
+        for &level in &[false, true] {
+            let pull = if level { Pull::Down } else { Pull::Up };
+            pin_mode(input, PinMode::PulledInput(pull));
+            digital_write(out, level);
+            delay_us_blocking(10);
+            if digital_read(input) != level {
+                outcome = Outcome::Fail("strap did not follow the driven level");
+            }
+        }
+        pin_mode(out, PinMode::Input);
+        pin_mode(input, PinMode::Input);
+    }
+    outcome
+}
+
+/// Check sleeps and busy-waits against the millisecond clock
+///
+/// The millisecond clock itself is checked against an independent
+/// reference by the clock change test.
+async fn timer_accuracy() -> Outcome {
+    let start = millis();
+    sleep_millis(500).await;
+    let slept = millis().wrapping_sub(start);
+    if !(500..=502).contains(&slept) {
+        return Outcome::Fail("sleep_millis(500) was not 500ms");
+    }
+
+    let start = millis();
+    delay_us_blocking(100_000);
+    let delayed = millis().wrapping_sub(start);
+    if !(100..=105).contains(&delayed) {
+        return Outcome::Fail("delay_us_blocking(100000) was not 100ms");
+    }
+    Outcome::Pass
+}
+
+/// Switch the core clock, and check the clock and serial 2 at the new rate
+///
+/// The console keeps the baud rate it was enabled with on some
+/// boards, so nothing is printed until the default clock is restored.
+async fn clock_change() -> Outcome {
+    if post::check_clock(10).is_err() {
+        return Outcome::Fail("default clock is out of tolerance");
+    }
+
+    let _ = console().await.flush().await;
+    if set_clock(WIRING.test_clock).is_err() {
+        return Outcome::Fail("could not set the test clock");
+    }
+    let outcome = if post::check_clock(10).is_err() {
+        Outcome::Fail("test clock is out of tolerance")
+    } else if let Outcome::Fail(_) = serial_loopback().await {
+        Outcome::Fail("serial loopback failed at the test clock")
+    } else {
+        Outcome::Pass
+    };
+    if set_clock(WIRING.default_clock).is_err() {
+        // The console is likely unusable, so the LED is the only
+        // report left.
+        pin_mode(LED, PinMode::Output);
+        digital_write(LED, false);
+    }
+    outcome
+}
+
+/// Run a future until it completes, or until `ms` milliseconds pass
+async fn with_timeout<F: Future>(ms: usize, future: F) -> Option<F::Output> {
+    let mut future = future;
+    let mut sleep = sleep_millis(ms);
+
+    // Safety: Both futures are shadowed, so they cannot be moved
+    // after they are pinned.
+    let mut future = unsafe { Pin::new_unchecked(&mut future) };
+    let mut sleep = unsafe { Pin::new_unchecked(&mut sleep) };
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            Poll::Ready(Some(output))
+        } else if sleep.as_mut().poll(cx).is_ready() {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
@@ -98,9 +98,11 @@ async fn write_note<S: Spi>(spi: &mut S, pitch: u8, velocity: u8) -> Result<(),
 #[entry]
 async fn main() -> ! {
     serial_1()
+        .await
         .enable(MIDI_BAUD)
         .expect("Could not initialize MIDI serial port");
     spi_1()
+        .await
         .enable_with_options(&[
             SpiOption::HardwareCs(PITCH_DAC_CS),
             SpiOption::HardwareCs(VELOCITY_DAC_CS),
@@ -110,24 +112,24 @@ async fn main() -> ! {
     pin_mode(DAC_LOAD, PinMode::Output);
     digital_write(DAC_LOAD, true);
 
-    write_note(&mut *spi_1(), 0, 0)
+    write_note(&mut *spi_1().await, 0, 0)
         .await
         .expect("Could not write note do DACs");
     let mut last_pitch: u8 = 0;
     loop {
-        match get_midi_message(&mut *serial_1())
+        match get_midi_message(&mut *serial_1().await)
             .await
             .expect("Error reading MIDI message")
         {
             MidiMessage::NoteOn(pitch, velocity) => {
                 last_pitch = pitch;
-                write_note(&mut *spi_1(), pitch, velocity)
+                write_note(&mut *spi_1().await, pitch, velocity)
                     .await
                     .expect("Could not write note do DACs");
             }
             MidiMessage::NoteOff(pitch, _velocity) => {
                 if last_pitch == pitch {
-                    write_note(&mut *spi_1(), pitch, 0)
+                    write_note(&mut *spi_1().await, pitch, 0)
                         .await
                         .expect("Could not write note do DACs");
                 }
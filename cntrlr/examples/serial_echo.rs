@@ -0,0 +1,27 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Echo every byte received on serial 1 back to the sender
+//!
+//! Run with `cargo cntrlr run-example serial_echo --board=<board>`,
+//! and connect a USB serial adapter to the board's serial 1 pins at
+//! 115200 baud.
+
+#![no_std]
+#![no_main]
+
+use cntrlr::prelude::*;
+
+#[entry]
+async fn main() -> ! {
+    serial_1().await.enable(115200).unwrap();
+    writeln!(serial_1().await, "Cntrlr serial echo")
+        .await
+        .unwrap();
+
+    let mut buf = [0; 64];
+    loop {
+        let count = serial_1().await.read(&mut buf).await.unwrap();
+        serial_1().await.write_all(&buf[..count]).await.unwrap();
+    }
+}
@@ -21,11 +21,13 @@ use cntrlr::prelude::*;
 async fn main() -> ! {
     pin_mode(CHIP_SELECT, PinMode::Output);
     spi_1()
+        .await
         .enable()
         //       .enable_with_options(&[SpiOption::HardwareCs(CHIP_SELECT)])
         .expect("Error enabling SPI");
     loop {
         spi_1()
+            .await
             .transfer(2_000_000, CHIP_SELECT, 12 * 8)
             .await
             .expect("Error starting SPI transfer")
@@ -35,6 +37,7 @@ async fn main() -> ! {
 
         let mut msg_in: [u8; 12] = [0xFF; 12];
         spi_1()
+            .await
             .transfer(2_000_000, CHIP_SELECT, 12 * 8)
             .await
             .expect("Error starting SPI transfer")
@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Read the JEDEC ID of an SPI NOR flash chip
+//!
+//! Run with `cargo cntrlr run-example spi_flash --board=<board>`. The
+//! flash chip should be connected to SPI 1, with its chip select on
+//! the pin below. The ID is printed to serial 1 at 115200 baud once
+//! a second.
+
+#![no_std]
+#![no_main]
+
+use cntrlr::{io::SpiTransfer, prelude::*};
+
+/// The chip select pin for the flash
+#[cfg(any(
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36"
+))]
+const CS: usize = 10;
+
+#[entry]
+async fn main() -> ! {
+    serial_1().await.enable(115200).unwrap();
+    spi_1().await.enable().unwrap();
+    pin_mode(CS, PinMode::Output);
+    digital_write(CS, true);
+
+    loop {
+        let mut id = [0; 4];
+        {
+            let mut spi = spi_1().await;
+            let mut transfer = spi.transfer(1_000_000, CS, 8).await.unwrap();
+            transfer.transfer(&[0x9F, 0, 0, 0], &mut id).await.unwrap();
+            transfer.flush().await.unwrap();
+        }
+        digital_write(CS, true);
+
+        writeln!(
+            serial_1().await,
+            "Manufacturer {:02x}, device {:02x}{:02x}",
+            id[1],
+            id[2],
+            id[3]
+        )
+        .await
+        .unwrap();
+        sleep_millis(1000).await;
+    }
+}
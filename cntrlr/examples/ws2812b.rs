@@ -182,13 +182,14 @@ async fn main() -> ! {
     let mut panel = Panel::<PANEL_LENGTH, PANEL_HEIGHT>::new();
     let mut frame = 0;
     serial_1()
+        .await
         .enable_with_options(4_000_000, &[SerialOption::Invert(true)])
         .expect("Could not enable serial port");
     loop {
         let frame_start = millis();
         panel.render(frame);
         panel
-            .display(&mut *serial_1())
+            .display(&mut *serial_1().await)
             .await
             .expect("Failed to display frame");
         frame += 1;
@@ -0,0 +1,92 @@
+/* SPDX-License-Identifier: AGPL-3.0-or-later */
+/* Copyright 2020 Branan Riley <me@branan.info> */
+
+#ifndef CNTRLR_H
+#define CNTRLR_H
+
+/* Generated from src/ffi.rs by cbindgen. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The pin is an input, without a pull resistor
+#define CNTRLR_PIN_INPUT 0
+
+// The pin is a push-pull output
+#define CNTRLR_PIN_OUTPUT 1
+
+// The pin is an input, with a pull-up resistor
+#define CNTRLR_PIN_INPUT_PULLUP 2
+
+// The pin is an input, with a pull-down resistor
+#define CNTRLR_PIN_INPUT_PULLDOWN 3
+
+// The pin is an open-drain output
+#define CNTRLR_PIN_OPEN_DRAIN 4
+
+// The serial port does not exist on this board
+#define CNTRLR_ERROR_INVALID_PORT -1
+
+// The serial port reported an error, such as not being enabled
+#define CNTRLR_ERROR_IO -2
+
+// The serial port is held by a Rust task
+#define CNTRLR_ERROR_IN_USE -3
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Set a pin as a digital input or output
+//
+// `mode` is one of the `CNTRLR_PIN_` constants. Returns false if it
+// is not.
+bool cntrlr_pin_mode(size_t pin, uint32_t mode);
+
+// Read the state of a digital pin
+bool cntrlr_digital_read(size_t pin);
+
+// Set the state of a digital pin
+void cntrlr_digital_write(size_t pin, bool value);
+
+// The number of milliseconds the device has been running
+size_t cntrlr_millis(void);
+
+// Busy-wait for at least some number of milliseconds
+//
+// Unlike [`sleep_millis`](crate::time::sleep_millis), this blocks
+// the whole executor.
+void cntrlr_delay(size_t ms);
+
+// Enable a serial port at the given baud rate
+//
+// Ports are numbered from 1, as in [`serial_1`](crate::io::serial_1).
+// Returns 0, or a negative `CNTRLR_ERROR_` code.
+ptrdiff_t cntrlr_serial_enable(size_t port, size_t baud);
+
+// Read from a serial port
+//
+// Waits until at least one byte is available, and returns the
+// number of bytes read, or a negative `CNTRLR_ERROR_` code.
+//
+// # Safety
+// `buf` must be valid for writes of `len` bytes.
+ptrdiff_t cntrlr_serial_read(size_t port, uint8_t *buf, size_t len);
+
+// Write to a serial port
+//
+// Waits until every byte is queued, and returns the number of bytes
+// written, or a negative `CNTRLR_ERROR_` code.
+//
+// # Safety
+// `data` must be valid for reads of `len` bytes.
+ptrdiff_t cntrlr_serial_write(size_t port, const uint8_t *data, size_t len);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif // CNTRLR_H
@@ -143,14 +143,15 @@ unsafe impl GlobalAlloc for GlobalAllocator {
     }
 }
 
-#[global_allocator]
+#[cfg_attr(not(test), global_allocator)]
 static ALLOCATOR: GlobalAllocator = GlobalAllocator::new();
 
 pub unsafe fn init(brk: *mut u8) {
     ALLOCATOR.init(brk);
 }
 
-#[alloc_error_handler]
+#[cfg_attr(not(test), alloc_error_handler)]
 fn handle_alloc_error(layout: Layout) -> ! {
+    crate::runtime::set_fault(crate::runtime::Fault::OutOfMemory);
     panic!("Allocation error for {:?}", layout);
 }
@@ -0,0 +1,303 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Analog input for Cntrlr boards
+//!
+//! [`analog_read`] converts the voltage on one of a board's analog
+//! pins, like Arduino's `analogRead`:
+//!
+//! ```
+//! use cntrlr::prelude::*;
+//!
+//! async fn read_sensor() -> u16 {
+//!     analog_read(14).await.expect("Failed to read pin")
+//! }
+//! ```
+//!
+//! The ADC is configured with [`AdcOptions`], which provide the same
+//! controls as Teensyduino's `analogReadResolution` and
+//! `analogReadAveraging`, along with the conversion and sampling
+//! speed.
+//!
+//! ```
+//! use cntrlr::analog::{self, AdcOptions, ConversionSpeed};
+//!
+//! let mut options = AdcOptions::default();
+//! options.resolution = 12;
+//! options.averaging = 16;
+//! options.conversion_speed = ConversionSpeed::Low;
+//! analog::set_adc_options(options).expect("Failed to configure ADC");
+//! ```
+//!
+//! On the Teensy 3.x boards, conversions can also be started by
+//! hardware, with a fixed delay from a trigger, using
+//! [`start_triggered`].
+//!
+//! The Teensy 3.5 and 3.6 also have analog outputs, set with
+//! [`dac_write`], and analog comparators, which [`wait_above`] uses
+//! to sleep until an input crosses a threshold.
+
+use cntrlr_macros::board_fn;
+#[cfg(any(
+    doc,
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+use core::future::Future;
+
+/// The speed of the ADC clock, or of each sample
+///
+/// Slower speeds give more accurate results, especially with
+/// high-impedance sources. The exact clock used for each speed
+/// depends on the board's bus clock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConversionSpeed {
+    /// The slowest speed
+    VeryLow,
+
+    /// A slow speed
+    Low,
+
+    /// The default speed
+    Medium,
+
+    /// A fast speed
+    High,
+
+    /// The fastest speed
+    VeryHigh,
+}
+
+/// Configuration for the ADC
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AdcOptions {
+    /// The number of bits in each result
+    ///
+    /// Teensy boards support 8, 10, 12, and 16 bits.
+    pub resolution: u32,
+
+    /// The number of conversions averaged into each result
+    ///
+    /// Teensy boards support 1, 4, 8, 16, and 32 samples.
+    pub averaging: u32,
+
+    /// The speed of the ADC clock
+    pub conversion_speed: ConversionSpeed,
+
+    /// The time taken to sample the input for each conversion
+    pub sampling_speed: ConversionSpeed,
+}
+
+impl AdcOptions {
+    /// The default ADC configuration
+    ///
+    /// This is a 10-bit resolution, the Arduino default, with 4
+    /// samples averaged into each result.
+    pub const DEFAULT: Self = Self {
+        resolution: 10,
+        averaging: 4,
+        conversion_speed: ConversionSpeed::Medium,
+        sampling_speed: ConversionSpeed::Medium,
+    };
+}
+
+impl Default for AdcOptions {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Options for hardware-triggered conversions
+///
+/// Each trigger starts a sequence of one or two conversions, each
+/// after a fixed delay. The ADC channel numbers are MCU-specific.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct TriggerOptions {
+    /// The ADC channel converted first in each sequence
+    pub channel_a: u32,
+
+    /// The ADC channel converted second in each sequence, if any
+    pub channel_b: Option<u32>,
+
+    /// The ADC 1 channels converted at the same time as `channel_a` and `channel_b`
+    ///
+    /// Paired channels are only supported on boards with two ADCs,
+    /// the Teensy 3.5 and 3.6. There must be a second paired channel
+    /// exactly when there is a `channel_b`.
+    pub paired_channels: Option<(u32, Option<u32>)>,
+
+    /// The trigger input which starts each sequence
+    ///
+    /// The meaning of each input is MCU-specific, such as a timer's
+    /// initialization trigger. If this is `None`, sequences are
+    /// started periodically.
+    pub input: Option<u32>,
+
+    /// The length of each sequence, in microseconds
+    ///
+    /// When no input is used, this is the time between the start of
+    /// each sequence.
+    pub period: u32,
+
+    /// The delay from the start of a sequence to each conversion, in microseconds
+    ///
+    /// The second conversion must not start until the first has
+    /// completed.
+    pub delays: [u32; 2],
+}
+
+impl TriggerOptions {
+    /// Periodically convert one channel, once each millisecond
+    pub const fn new(channel_a: u32) -> Self {
+        Self {
+            channel_a,
+            channel_b: None,
+            paired_channels: None,
+            input: None,
+            period: 1000,
+            delays: [0, 0],
+        }
+    }
+}
+
+/// The results of one hardware-triggered sequence
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct Samples {
+    /// The results of the first and, if any, second conversions on ADC 0
+    pub adc_0: (u16, Option<u16>),
+
+    /// The results of the paired conversions on ADC 1, if any
+    ///
+    /// These were sampled at the same time as the matching results
+    /// from ADC 0.
+    pub adc_1: Option<(u16, Option<u16>)>,
+}
+
+impl Samples {
+    #[allow(dead_code)]
+    pub(crate) const fn new(adc_0: (u16, Option<u16>), adc_1: Option<(u16, Option<u16>)>) -> Self {
+        Self { adc_0, adc_1 }
+    }
+}
+
+/// An error from configuring the ADC
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AnalogError {
+    /// The ADC cannot be configured because the resolution is not supported
+    InvalidResolution,
+
+    /// The ADC cannot be configured because the number of samples to average is not supported
+    InvalidAveraging,
+
+    /// The ADC cannot be configured because it, or the integration module, is in use
+    AdcInUse,
+
+    /// The ADC cannot be configured because its calibration failed
+    CalibrationFailed,
+
+    /// The conversions cannot be triggered because the delay block is in use
+    PdbInUse,
+
+    /// The conversions cannot be triggered, or the DAC written, because a channel does not exist
+    InvalidChannel,
+
+    /// The conversions cannot be triggered because the period or a delay is out of range
+    InvalidTiming,
+
+    /// The pin cannot be read because it is not an analog input on ADC 0, or a comparator input
+    InvalidPin,
+
+    /// The DAC cannot be written because it, or the integration module, is in use
+    DacInUse,
+
+    /// The pin cannot be compared because its comparator, or the integration module, is in use
+    ComparatorInUse,
+
+    /// The pin cannot be compared because the threshold is outside the comparator's range
+    InvalidThreshold,
+}
+
+/// Configure the ADC
+///
+/// The ADC is recalibrated with the new options.
+#[board_fn(analog, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn set_adc_options(options: AdcOptions) -> Result<(), AnalogError> {}
+
+/// The current ADC configuration
+#[board_fn(analog, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn adc_options() -> AdcOptions {}
+
+/// Read an analog pin
+///
+/// The pin is disconnected from its digital functions, and one
+/// conversion is made with the current [`AdcOptions`]. The result is
+/// at the configured resolution, so with the default options it is
+/// between 0 and 1023. The ADC is enabled and calibrated by the first
+/// read if it has not been configured.
+///
+/// Reads wait for each other, and fail while hardware-triggered
+/// conversions are running. Only pins connected to ADC 0 can be
+/// read.
+///
+/// The FE310 has no ADC, so this is not available on the Red-V.
+#[board_fn(analog, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn analog_read(pin: usize) -> impl Future<Output = Result<u16, AnalogError>> {}
+
+/// Start hardware-triggered conversions
+///
+/// The results of each sequence are collected through the returned
+/// [`TriggeredSampler`]. Conversions stop when it is dropped.
+#[board_fn(analog, teensy_30, teensy_32, teensy_35, teensy_36)]
+pub fn start_triggered(options: TriggerOptions) -> Result<TriggeredSampler, AnalogError> {}
+
+/// Set the output of a DAC
+///
+/// The value is 12 bits, scaled to the 3.3V analog supply, and
+/// larger values are clamped. DAC 0 drives pin A21, and DAC 1 drives
+/// pin A22. Each DAC is enabled by its first write, and keeps its
+/// output until the next.
+///
+/// Only the Teensy 3.5 and 3.6 have DACs supported by this function.
+#[board_fn(analog, teensy_35, teensy_36)]
+pub fn dac_write(channel: usize, value: u16) -> Result<(), AnalogError> {}
+
+/// Wait until the voltage on a pin rises above a threshold
+///
+/// The pin is compared against the threshold, in millivolts, by one
+/// of the MCU's analog comparators. The threshold is rounded to one
+/// of 64 steps of the 3.3V supply, from about 52mV to 3.3V. If the
+/// pin is already above the threshold, this completes straight away.
+/// Otherwise the task sleeps until the comparator interrupts.
+///
+/// Pins 11, 12, 35, and 36 share comparator 0, and pins 9 and 10
+/// share comparator 1. Only one task can wait on each comparator at
+/// a time, and the others fail with
+/// [`AnalogError::ComparatorInUse`]. This is only available on the
+/// Teensy 3.5 and 3.6.
+#[board_fn(analog, teensy_35, teensy_36)]
+pub fn wait_above(pin: usize, millivolts: u32) -> impl Future<Output = Result<(), AnalogError>> {}
+
+#[cfg(any(
+    doc,
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36"
+    )))
+)]
+pub use crate::hw::board::teensy_common::analog::TriggeredSampler;
@@ -0,0 +1,130 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! A black box recorder
+//!
+//! The recorder keeps the most recent events of a run in a ring in
+//! RAM which is not cleared at startup, alongside the
+//! [crash log](crate::runtime::last_crash). Once the ring is full, the
+//! oldest events are overwritten. Panic messages are recorded too.
+//!
+//! After a reset, [`previous`] returns the events which led up to
+//! it. Like the crash log, the events survive a reset by the watchdog
+//! or the reset button, but not a loss of power.
+//!
+//! The events are not written to flash, as there is no flash driver
+//! yet, so there is also no host-side tool to read them from a board
+//! which has been powered off.
+//!
+//! ```no_run
+//! use cntrlr::blackbox;
+//!
+//! blackbox::record(format_args!("sensor {} timed out", 3));
+//! ```
+
+use crate::sync::without_interrupts;
+use core::{
+    fmt::{self, Write},
+    mem::MaybeUninit,
+    str,
+};
+
+/// The number of bytes of events kept in the ring
+const BLACKBOX_LEN: usize = 1024;
+
+/// Marks a ring which was started by a previous run
+const BLACKBOX_MAGIC: u32 = 0xB1AC_B0C5;
+
+/// The ring of events, as it is kept in RAM
+#[repr(C)]
+struct Ring {
+    magic: u32,
+    head: u32,
+    wrapped: u32,
+    data: [u8; BLACKBOX_LEN],
+}
+
+impl Ring {
+    fn push(&mut self, byte: u8) {
+        let head = self.head as usize % BLACKBOX_LEN;
+        self.data[head] = byte;
+        if head + 1 == BLACKBOX_LEN {
+            self.wrapped = 1;
+        }
+        self.head = ((head + 1) % BLACKBOX_LEN) as u32;
+    }
+}
+
+impl Write for Ring {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for &byte in s.as_bytes() {
+            self.push(byte);
+        }
+        Ok(())
+    }
+}
+
+/// The events of this run
+///
+/// This is not initialized at startup, so that it survives a reset.
+#[link_section = ".noinit"]
+static mut RING: MaybeUninit<Ring> = MaybeUninit::uninit();
+
+/// The events of the previous run, oldest first, read at startup
+static mut PREVIOUS: [u8; BLACKBOX_LEN] = [0; BLACKBOX_LEN];
+static mut PREVIOUS_LEN: usize = 0;
+
+/// Record an event
+///
+/// Each event is kept as one line of text. This can be called from
+/// interrupt handlers.
+pub fn record(event: fmt::Arguments) {
+    without_interrupts(|| unsafe {
+        let ring = &mut *RING.as_mut_ptr();
+        let _ = ring.write_fmt(event);
+        ring.push(b'\n');
+    });
+}
+
+/// The events recorded by the previous run of the program
+///
+/// The events are one per line, oldest first. This is empty if the
+/// previous run recorded nothing, or if the board was powered on.
+pub fn previous() -> &'static str {
+    // Safety: PREVIOUS is only written at startup
+    unsafe { str::from_utf8(&PREVIOUS[..PREVIOUS_LEN]).unwrap_or("") }
+}
+
+/// Keep the previous run's events, and start a new recording
+///
+/// # Safety
+/// This must be called once at startup, after `.bss` is initialized.
+pub(crate) unsafe fn load() {
+    let ring = &mut *RING.as_mut_ptr();
+    if ring.magic == BLACKBOX_MAGIC && (ring.head as usize) < BLACKBOX_LEN {
+        let head = ring.head as usize;
+        let older: &[u8] = if ring.wrapped != 0 {
+            &ring.data[head..]
+        } else {
+            &[]
+        };
+        let len = older.len() + head;
+        PREVIOUS[..older.len()].copy_from_slice(older);
+        PREVIOUS[older.len()..len].copy_from_slice(&ring.data[..head]);
+
+        // The oldest event may have been partly overwritten
+        let start = if ring.wrapped != 0 {
+            PREVIOUS[..len]
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .map_or(len, |newline| newline + 1)
+        } else {
+            0
+        };
+        PREVIOUS.copy_within(start..len, 0);
+        PREVIOUS_LEN = len - start;
+    }
+    ring.magic = BLACKBOX_MAGIC;
+    ring.head = 0;
+    ring.wrapped = 0;
+}
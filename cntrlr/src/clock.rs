@@ -0,0 +1,70 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Clock fault notification
+//!
+//! The Teensy boards run from a crystal, multiplied up by a PLL. If
+//! the crystal stops, or the PLL loses lock, the board falls back to
+//! its internal reference clock instead of hanging or resetting. The
+//! core then runs at about 21MHz, and the bus clock is divided down
+//! from that by the same dividers as before.
+//!
+//! Everything timed from the core or bus clock slows down with it:
+//! timekeeping, serial baud rates, and SPI clocks. [`on_clock_fault`]
+//! registers a handler, so the application can find out and react,
+//! such as by logging the fault and shutting down cleanly.
+//!
+//! ```
+//! use cntrlr::clock::{self, ClockFault};
+//!
+//! fn fault(fault: ClockFault) {
+//!     cntrlr::digital::led_write(true);
+//! }
+//!
+//! clock::on_clock_fault(fault);
+//! ```
+
+use crate::sync::without_interrupts;
+
+/// A failure of the board's clock
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ClockFault {
+    /// The external oscillator stopped
+    OscillatorLost,
+
+    /// The PLL lost lock
+    PllUnlocked,
+}
+
+static mut HANDLER: Option<fn(ClockFault)> = None;
+
+/// Register a function to be called when the clock fails
+///
+/// The handler is called from an interrupt, after the board has
+/// switched to its internal reference clock. It replaces any handler
+/// registered before. Setting the board clock again from the handler
+/// will hang, since the crystal is no longer running.
+///
+/// Only the Teensy boards detect clock faults.
+pub fn on_clock_fault(handler: fn(ClockFault)) {
+    // Safety: The handler is only read by the clock fault interrupt,
+    // which cannot run while interrupts are disabled.
+    without_interrupts(|| unsafe { HANDLER = Some(handler) });
+}
+
+/// Report a clock fault to the application's handler
+#[cfg(any(
+    doc,
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+pub(crate) fn notify(fault: ClockFault) {
+    // Safety: This is only called from the clock fault interrupt.
+    if let Some(handler) = unsafe { HANDLER } {
+        handler(fault);
+    }
+}
@@ -0,0 +1,298 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Settings which survive a reboot
+//!
+//! A [`Store`] keeps a [`Settings`] struct in two blocks of a
+//! [`BlockDevice`]. Each save goes to the block which does not hold
+//! the newest copy, so a save interrupted by a reset or power loss
+//! leaves the previous settings intact. Each copy carries a sequence
+//! number, the version of the settings struct, and a CRC, and
+//! loading picks the newest copy which passes its CRC.
+//!
+//! When the settings struct changes, its version is raised, and
+//! [`Settings::migrate`] converts copies saved by older firmware.
+//!
+//! ```
+//! use cntrlr::config::{Settings, Store};
+//!
+//! #[derive(Clone, Copy, Default)]
+//! #[repr(C)]
+//! struct Calibration {
+//!     offset: i32,
+//!     gain: u32,
+//! }
+//!
+//! unsafe impl Settings for Calibration {
+//!     const VERSION: u16 = 2;
+//!
+//!     fn migrate(version: u16, data: &[u8]) -> Option<Self> {
+//!         // Version 1 had no gain
+//!         if version == 1 && data.len() == 4 {
+//!             let offset = i32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+//!             Some(Self { offset, gain: 1 })
+//!         } else {
+//!             None
+//!         }
+//!     }
+//! }
+//!
+//! async fn calibrate(device: impl cntrlr::io::BlockDevice) {
+//!     let mut store = Store::new(device, 0);
+//!     let mut calibration: Calibration = store.load_or_default().await;
+//!     calibration.offset += 1;
+//!     store.save(&calibration).await.unwrap();
+//! }
+//! ```
+
+use crate::io::{
+    codec::{crc, CrcKind},
+    BlockDevice, Error, ErrorKind,
+};
+use alloc::{vec, vec::Vec};
+use core::{future::Future, mem::size_of};
+
+/// A struct of settings, stored as raw bytes
+///
+/// # Safety
+/// The struct is saved and loaded as its in-memory bytes. It must be
+/// `#[repr(C)]` with no padding, and every bit pattern must be a
+/// valid value. Integers, and arrays of integers, meet these
+/// requirements. References, pointers, `bool`, `char`, and most
+/// enums do not.
+pub unsafe trait Settings: Copy + Default {
+    /// The version of the struct's layout
+    ///
+    /// This must be raised whenever a field is added, removed, or
+    /// changed.
+    const VERSION: u16;
+
+    /// Convert settings saved with an older version
+    ///
+    /// `data` is the struct's bytes as saved by that version. Returns
+    /// `None` if the settings cannot be converted. By default, no
+    /// older version can be converted.
+    fn migrate(version: u16, data: &[u8]) -> Option<Self> {
+        let _ = (version, data);
+        None
+    }
+}
+
+/// An error from a [`Store`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigError<E> {
+    /// Error from the underlying device
+    Device(E),
+
+    /// No copy of the settings passed its CRC
+    Missing,
+
+    /// The settings were saved with a version which cannot be migrated
+    Incompatible(u16),
+
+    /// The settings are too large to fit in one block of the device
+    TooLarge,
+}
+
+impl<E> From<ConfigError<E>> for Error
+where
+    E: Into<Error>,
+{
+    fn from(err: ConfigError<E>) -> Self {
+        match err {
+            ConfigError::Device(err) => err.into(),
+            ConfigError::Missing | ConfigError::Incompatible(_) => ErrorKind::InvalidData.into(),
+            ConfigError::TooLarge => ErrorKind::InvalidSetting.into(),
+        }
+    }
+}
+
+const MAGIC: [u8; 4] = *b"CNFG";
+
+/// The magic, version, length, and sequence number
+const HEADER: usize = 12;
+
+/// The CRC-32 after the data
+const TRAILER: usize = 4;
+
+/// Settings stored in two blocks of a device
+pub struct Store<D> {
+    device: D,
+    first_block: usize,
+
+    /// The block and sequence number of the newest copy, once scanned
+    newest: Option<Option<(usize, u32)>>,
+}
+
+impl<D> Store<D> {
+    /// Keep settings in blocks `first_block` and `first_block + 1` of a device
+    ///
+    /// Nothing else may use these blocks.
+    pub fn new(device: D, first_block: usize) -> Self {
+        Self {
+            device,
+            first_block,
+            newest: None,
+        }
+    }
+
+    /// Get the underlying device back
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+}
+
+impl<D> Store<D>
+where
+    D: BlockDevice,
+{
+    /// Load the settings
+    ///
+    /// The newest copy which passes its CRC is used, and is migrated
+    /// if it was saved with an older version. If it cannot be
+    /// migrated, the older copy is tried.
+    pub fn load<T: Settings>(
+        &mut self,
+    ) -> impl Future<Output = Result<T, ConfigError<D::Error>>> + '_ {
+        async move {
+            let mut copies = Vec::with_capacity(2);
+            for slot in 0..2 {
+                if let Some(copy) = self.read_copy(slot).await? {
+                    copies.push((slot, copy));
+                }
+            }
+            // The newest copy is tried first
+            if copies.len() == 2 && newer(copies[1].1.sequence, copies[0].1.sequence) {
+                copies.swap(0, 1);
+            }
+            self.newest = Some(copies.first().map(|(slot, copy)| (*slot, copy.sequence)));
+
+            let mut incompatible = None;
+            for (_, copy) in &copies {
+                if copy.version == T::VERSION && copy.data.len() == size_of::<T>() {
+                    // Safety: Settings are valid for any bytes, and the
+                    // length was checked.
+                    return Ok(unsafe {
+                        core::ptr::read_unaligned(copy.data.as_ptr() as *const T)
+                    });
+                }
+                match T::migrate(copy.version, &copy.data) {
+                    Some(settings) => return Ok(settings),
+                    None => incompatible = incompatible.or(Some(copy.version)),
+                }
+            }
+            Err(incompatible.map_or(ConfigError::Missing, ConfigError::Incompatible))
+        }
+    }
+
+    /// Load the settings, or their defaults if they cannot be loaded
+    pub fn load_or_default<T: Settings>(&mut self) -> impl Future<Output = T> + '_ {
+        async move { self.load().await.unwrap_or_default() }
+    }
+
+    /// Save the settings
+    ///
+    /// The settings are written over the older copy, so the newest
+    /// copy is kept until this succeeds.
+    pub fn save<'a, T: Settings>(
+        &'a mut self,
+        settings: &'a T,
+    ) -> impl Future<Output = Result<(), ConfigError<D::Error>>> + 'a {
+        async move {
+            let block_size = self.device.block_size();
+            if HEADER + size_of::<T>() + TRAILER > block_size {
+                return Err(ConfigError::TooLarge);
+            }
+
+            let newest = match self.newest {
+                Some(newest) => newest,
+                None => {
+                    let mut newest = None;
+                    for slot in 0..2 {
+                        if let Some(copy) = self.read_copy(slot).await? {
+                            if newest.map_or(true, |(_, newest)| newer(copy.sequence, newest)) {
+                                newest = Some((slot, copy.sequence));
+                            }
+                        }
+                    }
+                    newest
+                }
+            };
+            let (slot, sequence) = match newest {
+                Some((slot, sequence)) => (1 - slot, sequence.wrapping_add(1)),
+                None => (0, 0),
+            };
+
+            // Safety: Settings have no padding, so every byte is
+            // initialized.
+            let data = unsafe {
+                core::slice::from_raw_parts(settings as *const T as *const u8, size_of::<T>())
+            };
+            let mut block = vec![0xFF; block_size];
+            block[0..4].copy_from_slice(&MAGIC);
+            block[4..6].copy_from_slice(&T::VERSION.to_le_bytes());
+            block[6..8].copy_from_slice(&(data.len() as u16).to_le_bytes());
+            block[8..12].copy_from_slice(&sequence.to_le_bytes());
+            block[HEADER..HEADER + data.len()].copy_from_slice(data);
+            let end = HEADER + data.len();
+            let check = crc(CrcKind::Crc32, &block[..end]);
+            block[end..end + TRAILER].copy_from_slice(&check.to_le_bytes());
+
+            // The copy being replaced is no longer valid once its
+            // block starts to be written.
+            self.newest = None;
+            self.device
+                .write_block(self.first_block + slot, &block)
+                .await
+                .map_err(ConfigError::Device)?;
+            self.newest = Some(Some((slot, sequence)));
+            Ok(())
+        }
+    }
+
+    /// Read one copy of the settings
+    ///
+    /// Returns `None` if the copy does not pass its CRC.
+    async fn read_copy(&mut self, slot: usize) -> Result<Option<Saved>, ConfigError<D::Error>> {
+        let mut block = vec![0; self.device.block_size()];
+        self.device
+            .read_block(self.first_block + slot, &mut block)
+            .await
+            .map_err(ConfigError::Device)?;
+        if block.len() < HEADER + TRAILER || block[0..4] != MAGIC {
+            return Ok(None);
+        }
+        let version = u16::from_le_bytes([block[4], block[5]]);
+        let len = u16::from_le_bytes([block[6], block[7]]) as usize;
+        let sequence = u32::from_le_bytes([block[8], block[9], block[10], block[11]]);
+        let end = HEADER + len;
+        if end + TRAILER > block.len() {
+            return Ok(None);
+        }
+        let check =
+            u32::from_le_bytes([block[end], block[end + 1], block[end + 2], block[end + 3]]);
+        if crc(CrcKind::Crc32, &block[..end]) != check {
+            return Ok(None);
+        }
+        Ok(Some(Saved {
+            sequence,
+            version,
+            data: block[HEADER..end].to_vec(),
+        }))
+    }
+}
+
+/// One copy of the settings, as read from the device
+struct Saved {
+    sequence: u32,
+    version: u16,
+    data: Vec<u8>,
+}
+
+/// Whether sequence number `a` was written after `b`
+///
+/// Sequence numbers wrap, so this compares them by their distance.
+fn newer(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) > 0
+}
@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Pulse counting for Cntrlr boards
+//!
+//! A [`PulseCounter`] counts edges on a digital pin in the
+//! background, for tachometers, flow meters, and similar sensors.
+//!
+//! ```
+//! use cntrlr::{counter::{CounterOption, PulseCounter}, digital::Edge, prelude::*};
+//!
+//! #[entry]
+//! async fn main() -> ! {
+//!     let mut counter = PulseCounter::new(2, Edge::Falling, &[CounterOption::Debounce(5)])
+//!         .expect("Failed to start counter");
+//!     loop {
+//!         counter.wait_for_count(100).await;
+//!         let pulses = counter.reset();
+//!         writeln!(serial_1().await, "{} pulses", pulses).await.ok();
+//!     }
+//! }
+//! ```
+
+/// Options for a pulse counter
+#[non_exhaustive]
+pub enum CounterOption {
+    /// Ignore edges less than this many milliseconds after the last counted edge
+    Debounce(usize),
+}
+
+#[cfg(any(
+    doc,
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_lc"
+    )))
+)]
+pub use crate::hw::board::teensy_common::counter::{CounterError, PulseCounter};
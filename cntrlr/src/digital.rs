@@ -4,8 +4,19 @@
 //! Digital pin functionality for Cntrlr boards
 
 use cntrlr_macros::board_fn;
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+use core::future::Future;
 
 /// Mode of a digital pin
+#[derive(Clone, Copy)]
 #[non_exhaustive]
 pub enum PinMode {
     /// The pin is an input, without any pull-up or pull-down resistors
@@ -22,6 +33,7 @@ pub enum PinMode {
 }
 
 /// Pull-up or -down configuration
+#[derive(Clone, Copy, Debug)]
 #[non_exhaustive]
 pub enum Pull {
     /// The pin is pulled up when no signal is applied
@@ -31,6 +43,134 @@ pub enum Pull {
     Down,
 }
 
+/// Signal edges which can trigger a pin interrupt
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Edge {
+    /// The pin changes from low to high
+    Rising,
+
+    /// The pin changes from high to low
+    Falling,
+
+    /// The pin changes in either direction
+    Both,
+}
+
+/// Output drive strength of a pin
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DriveStrength {
+    /// The pin's normal drive strength
+    Low,
+
+    /// The pin's high drive strength, for driving LEDs and other current-hungry loads
+    High,
+}
+
+/// Output slew rate of a pin
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SlewRate {
+    /// Fast edges, for high-speed signals
+    Fast,
+
+    /// Slow edges, which ring less and radiate less noise
+    Slow,
+}
+
+/// An option for [`pin_mode_with_options`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PinOption {
+    /// Set the output drive strength, as with [`set_drive_strength`]
+    DriveStrength(DriveStrength),
+
+    /// Set the output slew rate, as with [`set_slew_rate`]
+    SlewRate(SlewRate),
+}
+
+/// Input filtering of a pin
+///
+/// Filters clean up noisy inputs, such as bouncing mechanical
+/// switches, before they reach [`digital_read`] or a pin interrupt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FilterConfig {
+    /// The pin's input is not filtered
+    None,
+
+    /// The pin's passive filter removes glitches of a few tens of nanoseconds
+    Passive,
+
+    /// The pin ignores pulses shorter than `width` cycles of `clock`
+    ///
+    /// `width` must be from 1 to 31. On Kinetis boards, the clock and
+    /// width are shared by every pin on an MCU port, so setting one
+    /// pin's filter changes the filter of every other filtered pin on
+    /// its port.
+    Digital {
+        /// The clock which times the filter
+        clock: FilterClock,
+
+        /// The shortest pulse which passes the filter, in cycles of `clock`
+        width: u32,
+    },
+}
+
+/// The clock which times a digital input filter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FilterClock {
+    /// The bus clock, for filtering pulses of up to a few microseconds
+    Bus,
+
+    /// The 1kHz low-power oscillator, for filtering pulses of up to 31ms
+    Lpo,
+}
+
+/// The decoded configuration of a pin
+///
+/// This is returned by [`dump_pin_config`], for debugging pins which
+/// do not behave as expected.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct PinConfig {
+    /// The MCU port (or GPIO bank) and pin number
+    pub mcu_pin: (usize, usize),
+
+    /// The function the pin is assigned to
+    ///
+    /// On Kinetis boards, this is the PCR mux value. 0 disables the
+    /// pin, and 1 is GPIO. On the Red-V, 0 is GPIO, and 1 and 2 are
+    /// IOF0 and IOF1.
+    pub function: usize,
+
+    /// Whether the pin's GPIO is set as an output
+    pub output: bool,
+
+    /// The pin's pull resistor, if it is enabled
+    pub pull: Option<Pull>,
+
+    /// Whether the pin has an open-drain output
+    pub open_drain: bool,
+
+    /// The pin's output drive strength
+    pub drive_strength: DriveStrength,
+
+    /// The pin's output slew rate
+    ///
+    /// The Red-V has no slew rate control, and always reports
+    /// [`SlewRate::Fast`].
+    pub slew_rate: SlewRate,
+
+    /// The edges which trigger the pin's interrupt
+    ///
+    /// Other interrupt and DMA request configurations are reported
+    /// as `None`.
+    pub interrupt: Option<Edge>,
+}
+
 /// Set a digital pin high or low
 ///
 /// If `pin` is not a valid pin, does nothing.
@@ -40,7 +180,9 @@ pub enum Pull {
 /// particular, whether the write takes effect when the pin
 /// becomes a digital output is MCU-specific and should not be
 /// relied upon.
-#[board_fn(digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+#[board_fn(
+    digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+)]
 #[inline]
 pub fn digital_write(pin: usize, value: bool) {}
 
@@ -53,7 +195,9 @@ pub fn digital_write(pin: usize, value: bool) {}
 /// * The pin is not set as a digital input
 /// * The pin is in use by a different module
 /// * The pin number is outside the range of pins on the board.
-#[board_fn(digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+#[board_fn(
+    digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+)]
 #[inline]
 pub fn digital_read(pin: usize) -> bool {}
 
@@ -66,6 +210,799 @@ pub fn digital_read(pin: usize) -> bool {}
 /// changes made by this method will take effect when the other
 /// module releases the pin is implementation specific and should
 /// not be relied upon.
-#[board_fn(digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+#[board_fn(
+    digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+)]
 #[inline]
 pub fn pin_mode(pin: usize, mode: PinMode) {}
+
+/// Get the MCU port and pin for a board pin
+///
+/// Board pins are numbered as they are labeled on the board. This
+/// returns the port (or GPIO bank) and pin number within that port
+/// which `pin` is connected to on the MCU.
+///
+/// Returns `None` if `pin` is not a valid digital pin.
+#[board_fn(
+    digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+)]
+#[inline]
+pub fn mcu_pin(pin: usize) -> Option<(usize, usize)> {}
+
+/// The pin connected to the board's built-in LED
+///
+/// This is equivalent to `LED_BUILTIN` in Arduino, and can be passed
+/// to any function which takes a pin number.
+#[board_fn(
+    digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+)]
+#[inline]
+pub fn led_builtin() -> usize {}
+
+/// Turn the board's built-in LED on or off
+///
+/// This sets the LED pin as an output, so it does not need to be
+/// configured with [`pin_mode`] first.
+#[board_fn(
+    digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+)]
+#[inline]
+pub fn led_write(on: bool) {}
+
+/// Check whether the board's built-in LED is on
+///
+/// This reads the state the LED pin is set to output, so it sees
+/// writes made with [`led_write`] or [`digital_write`].
+#[board_fn(
+    digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+)]
+#[inline]
+pub fn led_read() -> bool {}
+
+/// Get the board's built-in LED
+///
+/// This is a handle to the LED on [`led_builtin`], which is also
+/// named [`pins::LED_PIN`](crate::pins::LED_PIN). Every handle
+/// controls the same LED.
+///
+/// ```no_run
+/// use cntrlr::prelude::*;
+///
+/// #[entry]
+/// async fn main() -> ! {
+///     loop {
+///         led().toggle();
+///         sleep_millis(500).await;
+///     }
+/// }
+/// ```
+#[board_fn(
+    digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+)]
+#[inline]
+pub fn led() -> Led {}
+
+/// The board's built-in LED
+///
+/// See [`led`].
+pub struct Led(pub(crate) ());
+
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+impl Led {
+    /// Turn the LED on or off
+    ///
+    /// This sets the LED pin as an output, like [`led_write`].
+    pub fn set(&self, on: bool) {
+        led_write(on);
+    }
+
+    /// Turn the LED on
+    pub fn on(&self) {
+        self.set(true);
+    }
+
+    /// Turn the LED off
+    pub fn off(&self) {
+        self.set(false);
+    }
+
+    /// Turn the LED on if it is off, or off if it is on
+    pub fn toggle(&self) {
+        self.set(!self.is_on());
+    }
+
+    /// Check whether the LED is on
+    ///
+    /// See [`led_read`].
+    pub fn is_on(&self) -> bool {
+        led_read()
+    }
+}
+
+/// Set which edges on a pin trigger its interrupt
+///
+/// If `edge` is `None`, the pin's interrupt is disabled. Returns
+/// `false` if `pin` is not a valid pin, cannot generate interrupts,
+/// or is in use by another module.
+#[board_fn(digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+#[inline]
+pub(crate) fn set_pin_interrupt(pin: usize, edge: Option<Edge>) -> bool {}
+
+/// An error waiting for an edge on a pin
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum EdgeError {
+    /// The pin does not exist
+    InvalidPin,
+
+    /// The pin's interrupt is in use, or the pin cannot generate interrupts
+    PinUnavailable,
+}
+
+/// Wait for an edge on a pin
+///
+/// The pin is set as a digital input, and its interrupt is enabled
+/// until the edge arrives. Any pull resistor set by [`pin_mode`] is
+/// kept. Only edges after the first poll are seen.
+///
+/// Only one task can wait on a pin at a time, and a pin whose
+/// interrupt is in use, such as by a
+/// [`PulseCounter`](crate::counter::PulseCounter), cannot be waited
+/// on. Both return [`EdgeError::PinUnavailable`].
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_lc"
+    )))
+)]
+pub fn wait_for_edge(pin: usize, edge: Edge) -> impl Future<Output = Result<(), EdgeError>> {
+    edge::EdgeWait {
+        pin,
+        edge,
+        armed: None,
+    }
+}
+
+/// Wait for a rising edge on a pin
+///
+/// See [`wait_for_edge`] for details.
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_lc"
+    )))
+)]
+pub fn wait_for_rising_edge(pin: usize) -> impl Future<Output = Result<(), EdgeError>> {
+    wait_for_edge(pin, Edge::Rising)
+}
+
+/// Wait for a falling edge on a pin
+///
+/// See [`wait_for_edge`] for details.
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_lc"
+    )))
+)]
+pub fn wait_for_falling_edge(pin: usize) -> impl Future<Output = Result<(), EdgeError>> {
+    wait_for_edge(pin, Edge::Falling)
+}
+
+/// Read back the configuration of a pin
+///
+/// This reads the pin's hardware configuration directly, so it works
+/// even while the pin is in use by another module.
+///
+/// Returns `None` if `pin` is not a valid pin.
+#[board_fn(digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn dump_pin_config(pin: usize) -> Option<PinConfig> {}
+
+/// Set the output drive strength of a pin
+///
+/// Returns `false` if `pin` is not a valid pin, does not support the
+/// requested drive strength, or is in use by another module. On
+/// Kinetis boards, only some pins support high drive strength.
+#[board_fn(digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn set_drive_strength(pin: usize, strength: DriveStrength) -> bool {}
+
+/// Set the output slew rate of a pin
+///
+/// Returns `false` if `pin` is not a valid pin, does not support the
+/// requested slew rate, or is in use by another module.
+#[board_fn(digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn set_slew_rate(pin: usize, rate: SlewRate) -> bool {}
+
+/// Set the mode of a digital pin, along with its output options
+///
+/// This is [`pin_mode`], followed by each of `options`. Every option
+/// is applied, even if an earlier one fails. Returns `false` if any
+/// of them could not be set.
+///
+/// ```
+/// use cntrlr::digital::{pin_mode_with_options, DriveStrength, PinMode, PinOption, SlewRate};
+///
+/// pin_mode_with_options(
+///     5,
+///     PinMode::Output,
+///     &[
+///         PinOption::DriveStrength(DriveStrength::High),
+///         PinOption::SlewRate(SlewRate::Slow),
+///     ],
+/// );
+/// ```
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_lc"
+    )))
+)]
+pub fn pin_mode_with_options(pin: usize, mode: PinMode, options: &[PinOption]) -> bool {
+    pin_mode(pin, mode);
+    options.iter().fold(true, |ok, option| {
+        let set = match *option {
+            PinOption::DriveStrength(strength) => set_drive_strength(pin, strength),
+            PinOption::SlewRate(rate) => set_slew_rate(pin, rate),
+        };
+        set && ok
+    })
+}
+
+/// Set the input filter of a pin
+///
+/// Returns `false` if `pin` is not a valid pin, does not support the
+/// requested filter, or is in use by another module. Only some
+/// Kinetis pins have a passive filter, and the Teensy LC has no
+/// digital filters. The Red-V synchronizes every input to its clock,
+/// but has no configurable filters, so only [`FilterConfig::None`] is
+/// accepted.
+#[board_fn(digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn set_input_filter(pin: usize, config: FilterConfig) -> bool {}
+
+/// Read the input state of every pin on an MCU port
+#[board_fn(digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+#[inline]
+pub(crate) fn port_read(port: usize) -> u32 {}
+
+/// Set the output state of the pins in `mask` on an MCU port
+///
+/// All pins are updated by a single store.
+#[board_fn(digital, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+#[inline]
+pub(crate) fn port_write(port: usize, mask: u32, value: u32) {}
+
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+pub(crate) use edge::edge_intr;
+
+/// Tasks waiting for pin edges
+///
+/// Each MCU port has a mask of the pins with a waiting task, and a
+/// mask of the pins whose edge has arrived. The board's port
+/// interrupt passes each pin to [`edge_intr`], and disables the pin's
+/// interrupt if a task was waiting for it.
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+mod edge {
+    use super::{dump_pin_config, mcu_pin, set_pin_interrupt, Edge, EdgeError};
+    use crate::{
+        sync::{without_interrupts, Value},
+        task::WakerSet,
+    };
+    use core::{
+        future::Future,
+        pin::Pin,
+        sync::atomic::Ordering,
+        task::{Context, Poll},
+    };
+
+    const PORTS: usize = 5;
+
+    static WAITING: [Value; PORTS] = [
+        Value::new(0),
+        Value::new(0),
+        Value::new(0),
+        Value::new(0),
+        Value::new(0),
+    ];
+    static SEEN: [Value; PORTS] = [
+        Value::new(0),
+        Value::new(0),
+        Value::new(0),
+        Value::new(0),
+        Value::new(0),
+    ];
+    static WAKERS: [WakerSet; PORTS] = [
+        WakerSet::new(),
+        WakerSet::new(),
+        WakerSet::new(),
+        WakerSet::new(),
+        WakerSet::new(),
+    ];
+
+    /// Note an interrupt on a pin
+    ///
+    /// Returns `true` if a task was waiting for an edge on the pin,
+    /// in which case the caller must disable the pin's interrupt. A
+    /// pin which receives for a [`SoftSerial`](crate::io::soft::SoftSerial)
+    /// is passed on to it, and its interrupt is left enabled.
+    pub(crate) fn edge_intr(port: usize, pin: usize) -> bool {
+        if crate::io::soft::rx_intr(port, pin) {
+            return false;
+        }
+        let bit = 1 << pin;
+        if port >= PORTS || WAITING[port].load(Ordering::Relaxed) & bit == 0 {
+            return false;
+        }
+        let seen = SEEN[port].load(Ordering::Relaxed);
+        SEEN[port].store(seen | bit, Ordering::Relaxed);
+        WAKERS[port].wake();
+        true
+    }
+
+    pub(super) struct EdgeWait {
+        pub(super) pin: usize,
+        pub(super) edge: Edge,
+        pub(super) armed: Option<(usize, usize)>,
+    }
+
+    impl EdgeWait {
+        /// Claim the pin and enable its interrupt
+        fn arm(&mut self, ctx: &Context) -> Result<(), EdgeError> {
+            let (port, mcu_pin) = mcu_pin(self.pin).ok_or(EdgeError::InvalidPin)?;
+            if port >= PORTS {
+                return Err(EdgeError::PinUnavailable);
+            }
+            let bit = 1 << mcu_pin;
+            let in_use =
+                dump_pin_config(self.pin).map_or(true, |config| config.interrupt.is_some());
+            without_interrupts(|| {
+                let waiting = WAITING[port].load(Ordering::Relaxed);
+                if in_use || waiting & bit != 0 {
+                    return Err(EdgeError::PinUnavailable);
+                }
+                WAITING[port].store(waiting | bit, Ordering::Relaxed);
+                let seen = SEEN[port].load(Ordering::Relaxed);
+                SEEN[port].store(seen & !bit, Ordering::Relaxed);
+                Ok(())
+            })?;
+
+            WAKERS[port].add(ctx.waker().clone());
+            if !set_pin_interrupt(self.pin, Some(self.edge)) {
+                release(port, bit);
+                return Err(EdgeError::PinUnavailable);
+            }
+            self.armed = Some((port, bit));
+            Ok(())
+        }
+    }
+
+    impl Future for EdgeWait {
+        type Output = Result<(), EdgeError>;
+
+        fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+            let this = self.get_mut();
+            let (port, bit) = match this.armed {
+                Some(armed) => armed,
+                None => {
+                    this.arm(ctx)?;
+                    return Poll::Pending;
+                }
+            };
+
+            if SEEN[port].load(Ordering::Relaxed) & bit != 0 {
+                release(port, bit);
+                this.armed = None;
+                Poll::Ready(Ok(()))
+            } else {
+                WAKERS[port].add(ctx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    impl Drop for EdgeWait {
+        fn drop(&mut self) {
+            if let Some((port, bit)) = self.armed {
+                set_pin_interrupt(self.pin, None);
+                release(port, bit);
+            }
+        }
+    }
+
+    /// Stop waiting on a pin
+    fn release(port: usize, bit: usize) {
+        without_interrupts(|| {
+            let waiting = WAITING[port].load(Ordering::Relaxed);
+            WAITING[port].store(waiting & !bit, Ordering::Relaxed);
+            let seen = SEEN[port].load(Ordering::Relaxed);
+            SEEN[port].store(seen & !bit, Ordering::Relaxed);
+        });
+    }
+}
+
+/// An error creating a [`Bus`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BusError {
+    /// The bus cannot be created because one of its pins does not exist
+    InvalidPin,
+
+    /// The bus cannot be created because its pins are not all on the same MCU port
+    MixedPorts,
+
+    /// The bus cannot be created because a pin is included more than once
+    DuplicatePin,
+
+    /// The bus cannot be created because it has no pins, or more than 32
+    InvalidWidth,
+}
+
+/// A group of pins which are read and written together
+///
+/// All pins in a bus must be on the same MCU port, so that they can
+/// be accessed through the port-wide registers. Writes change every
+/// pin with a single store, and reads sample every pin at once. This
+/// is suitable for parallel LCD interfaces and for capturing
+/// parallel buses.
+///
+/// Bit `n` of each value read or written corresponds to the `n`th
+/// pin passed to [`Bus::new`]. Buses whose pins are consecutive on
+/// their port are faster, as no bit shuffling is needed.
+///
+/// As with [`digital_write`], the bus does not take ownership of its
+/// pins.
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_lc"
+    )))
+)]
+pub struct Bus {
+    pins: [usize; 32],
+    port: usize,
+    width: usize,
+    mask: u32,
+    shift: Option<usize>,
+}
+
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+impl Bus {
+    /// Create a bus from a set of pins
+    ///
+    /// The pins are set to `mode`.
+    pub fn new(pins: &[usize], mode: PinMode) -> Result<Self, BusError> {
+        if pins.is_empty() || pins.len() > 32 {
+            return Err(BusError::InvalidWidth);
+        }
+
+        let mut bus = Self {
+            pins: [0; 32],
+            port: 0,
+            width: pins.len(),
+            mask: 0,
+            shift: None,
+        };
+        for (idx, &pin) in pins.iter().enumerate() {
+            let (port, mcu_pin) = mcu_pin(pin).ok_or(BusError::InvalidPin)?;
+            if idx == 0 {
+                bus.port = port;
+                bus.shift = Some(mcu_pin);
+            } else if port != bus.port {
+                return Err(BusError::MixedPorts);
+            }
+            if bus.mask & (1 << mcu_pin) != 0 {
+                return Err(BusError::DuplicatePin);
+            }
+            if bus.shift.map(|shift| shift + idx) != Some(mcu_pin) {
+                bus.shift = None;
+            }
+            bus.pins[idx] = mcu_pin;
+            bus.mask |= 1 << mcu_pin;
+        }
+
+        for &pin in pins {
+            pin_mode(pin, mode);
+        }
+        Ok(bus)
+    }
+
+    /// The number of pins in this bus
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Set the bus pins high or low
+    ///
+    /// Bits of `value` beyond the width of the bus are ignored.
+    pub fn write(&mut self, value: u32) {
+        let value = match self.shift {
+            Some(shift) => value << shift,
+            None => {
+                let mut port_value = 0;
+                for (idx, &pin) in self.pins[..self.width].iter().enumerate() {
+                    port_value |= (value >> idx & 1) << pin;
+                }
+                port_value
+            }
+        };
+        port_write(self.port, self.mask, value);
+    }
+
+    /// Read the state of the bus pins
+    pub fn read(&self) -> u32 {
+        let value = port_read(self.port) & self.mask;
+        match self.shift {
+            Some(shift) => value >> shift,
+            None => self.pins[..self.width]
+                .iter()
+                .enumerate()
+                .fold(0, |acc, (idx, &pin)| acc | (value >> pin & 1) << idx),
+        }
+    }
+}
+
+/// The startup configuration of one pin in a pin table
+///
+/// Tables of these are applied by [`configure_pin_table`], and are
+/// usually generated by [`configure_pins!`](crate::configure_pins).
+#[derive(Clone, Copy)]
+pub struct PinSetup {
+    /// The pin to configure
+    pub pin: usize,
+
+    /// The mode to set the pin to
+    pub mode: PinMode,
+
+    /// The level an output pin starts at
+    ///
+    /// This is written before the pin's mode is set, so that the pin
+    /// never drives the other level. It is ignored for inputs.
+    pub level: bool,
+}
+
+/// An error applying a pin table
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PinTableError {
+    /// The pin does not exist on this board
+    InvalidPin(usize),
+
+    /// The pin is listed more than once
+    DuplicatePin(usize),
+}
+
+/// Configure every pin in a table
+///
+/// The whole table is checked before any pin is changed, so an
+/// invalid table leaves every pin as it was.
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    )))
+)]
+pub fn configure_pin_table(table: &[PinSetup]) -> Result<(), PinTableError> {
+    for (idx, setup) in table.iter().enumerate() {
+        if mcu_pin(setup.pin).is_none() {
+            return Err(PinTableError::InvalidPin(setup.pin));
+        }
+        if table[..idx].iter().any(|other| other.pin == setup.pin) {
+            return Err(PinTableError::DuplicatePin(setup.pin));
+        }
+    }
+
+    for setup in table {
+        if let PinMode::Output | PinMode::OpenDrainOutput = setup.mode {
+            digital_write(setup.pin, setup.level);
+        }
+        pin_mode(setup.pin, setup.mode);
+    }
+    Ok(())
+}
+
+/// Declare a board's pins as a table, and configure them together
+///
+/// This defines a struct with a field for each pin, holding its pin
+/// number, and a `configure` function which applies the table with
+/// [`configure_pin_table`] and returns the struct. Each entry gives
+/// the field name, the pin, and its mode. Outputs give the level
+/// they start at, and pulled inputs give their pull.
+///
+/// ```
+/// use cntrlr::{digital::Pull, pins};
+///
+/// cntrlr::digital::configure_pins! {
+///     /// The pins of the weather station
+///     struct StationPins {
+///         /// The status LED, off at startup
+///         led: pins::D13 => Output(false),
+///
+///         /// The rain gauge's reed switch
+///         rain: pins::D2 => PulledInput(Pull::Up),
+///
+///         /// The anemometer's pulse output
+///         wind: pins::D3 => Input,
+///
+///         /// The shared one-wire bus, released at startup
+///         one_wire: pins::D4 => OpenDrainOutput(true),
+///     }
+/// }
+///
+/// let pins = StationPins::configure().unwrap();
+/// cntrlr::digital::digital_write(pins.led, true);
+/// ```
+#[macro_export]
+macro_rules! configure_pins {
+    (@setup $pin:expr, Input) => {
+        $crate::configure_pins!(@setup $pin, $crate::digital::PinMode::Input, false)
+    };
+    (@setup $pin:expr, PulledInput($pull:expr)) => {
+        $crate::configure_pins!(@setup $pin, $crate::digital::PinMode::PulledInput($pull), false)
+    };
+    (@setup $pin:expr, Output($level:expr)) => {
+        $crate::configure_pins!(@setup $pin, $crate::digital::PinMode::Output, $level)
+    };
+    (@setup $pin:expr, OpenDrainOutput($level:expr)) => {
+        $crate::configure_pins!(@setup $pin, $crate::digital::PinMode::OpenDrainOutput, $level)
+    };
+    (@setup $pin:expr, $mode:expr, $level:expr) => {
+        $crate::digital::PinSetup {
+            pin: $pin,
+            mode: $mode,
+            level: $level,
+        }
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                $field:ident: $pin:expr => $mode:ident $(($($arg:tt)*))?,
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                pub $field: usize,
+            )*
+        }
+
+        impl $name {
+            /// Configure every pin in the table
+            $vis fn configure() -> Result<Self, $crate::digital::PinTableError> {
+                $crate::digital::configure_pin_table(&[
+                    $($crate::configure_pins!(@setup $pin, $mode $(($($arg)*))?),)*
+                ])?;
+                Ok(Self {
+                    $($field: $pin,)*
+                })
+            }
+        }
+    };
+}
+
+#[doc(inline)]
+pub use crate::configure_pins;
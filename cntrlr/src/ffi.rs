@@ -0,0 +1,247 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! C bindings for mixed C and Rust firmware
+//!
+//! These functions wrap the core APIs for C code linked into a
+//! Cntrlr application, so that existing C drivers can be moved to
+//! Rust one at a time. Their prototypes are in `include/cntrlr.h`,
+//! which is generated from this module with
+//! [cbindgen](https://github.com/eqrion/cbindgen):
+//!
+//! ```text
+//! cbindgen --config cbindgen.toml --output include/cntrlr.h
+//! ```
+//!
+//! C code is synchronous, so every function here blocks. The serial
+//! functions and [`cntrlr_delay`] spin until they finish, and no
+//! other task runs in the meantime. A serial port held by a Rust
+//! task is reported as in use, rather than waited for.
+
+use crate::{
+    digital::{self, PinMode, Pull},
+    io::{self, Read, Serial, Write},
+    time,
+};
+use core::{
+    future::Future,
+    ops::DerefMut,
+    pin::Pin,
+    ptr, slice,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+/// The pin is an input, without a pull resistor
+pub const CNTRLR_PIN_INPUT: u32 = 0;
+
+/// The pin is a push-pull output
+pub const CNTRLR_PIN_OUTPUT: u32 = 1;
+
+/// The pin is an input, with a pull-up resistor
+pub const CNTRLR_PIN_INPUT_PULLUP: u32 = 2;
+
+/// The pin is an input, with a pull-down resistor
+pub const CNTRLR_PIN_INPUT_PULLDOWN: u32 = 3;
+
+/// The pin is an open-drain output
+pub const CNTRLR_PIN_OPEN_DRAIN: u32 = 4;
+
+/// The serial port does not exist on this board
+pub const CNTRLR_ERROR_INVALID_PORT: isize = -1;
+
+/// The serial port reported an error, such as not being enabled
+pub const CNTRLR_ERROR_IO: isize = -2;
+
+/// The serial port is held by a Rust task
+pub const CNTRLR_ERROR_IN_USE: isize = -3;
+
+/// Lock a serial port by number, and run `$op` with it
+///
+/// The port is only locked if it is free right away.
+macro_rules! with_port {
+    ($port:expr, |$serial:ident| $op:expr) => {
+        match $port {
+            1 => with_lock(io::serial_1(), |mut $serial| $op),
+            #[cfg(any(
+                board = "red_v",
+                board = "teensy_30",
+                board = "teensy_32",
+                board = "teensy_35",
+                board = "teensy_36",
+                board = "teensy_lc"
+            ))]
+            2 => with_lock(io::serial_2(), |mut $serial| $op),
+            #[cfg(any(
+                board = "teensy_30",
+                board = "teensy_32",
+                board = "teensy_35",
+                board = "teensy_36",
+                board = "teensy_lc"
+            ))]
+            3 => with_lock(io::serial_3(), |mut $serial| $op),
+            #[cfg(any(board = "teensy_35", board = "teensy_36"))]
+            4 => with_lock(io::serial_4(), |mut $serial| $op),
+            #[cfg(any(board = "teensy_35", board = "teensy_36"))]
+            5 => with_lock(io::serial_5(), |mut $serial| $op),
+            #[cfg(board = "teensy_35")]
+            6 => with_lock(io::serial_6(), |mut $serial| $op),
+            _ => CNTRLR_ERROR_INVALID_PORT,
+        }
+    };
+}
+
+/// Set a pin as a digital input or output
+///
+/// `mode` is one of the `CNTRLR_PIN_` constants. Returns false if it
+/// is not.
+#[no_mangle]
+pub extern "C" fn cntrlr_pin_mode(pin: usize, mode: u32) -> bool {
+    let mode = match mode {
+        CNTRLR_PIN_INPUT => PinMode::Input,
+        CNTRLR_PIN_OUTPUT => PinMode::Output,
+        CNTRLR_PIN_INPUT_PULLUP => PinMode::PulledInput(Pull::Up),
+        CNTRLR_PIN_INPUT_PULLDOWN => PinMode::PulledInput(Pull::Down),
+        CNTRLR_PIN_OPEN_DRAIN => PinMode::OpenDrainOutput,
+        _ => return false,
+    };
+    digital::pin_mode(pin, mode);
+    true
+}
+
+/// Read the state of a digital pin
+#[no_mangle]
+pub extern "C" fn cntrlr_digital_read(pin: usize) -> bool {
+    digital::digital_read(pin)
+}
+
+/// Set the state of a digital pin
+#[no_mangle]
+pub extern "C" fn cntrlr_digital_write(pin: usize, value: bool) {
+    digital::digital_write(pin, value);
+}
+
+/// The number of milliseconds the device has been running
+#[no_mangle]
+pub extern "C" fn cntrlr_millis() -> usize {
+    time::millis()
+}
+
+/// Busy-wait for at least some number of milliseconds
+///
+/// Unlike [`sleep_millis`](crate::time::sleep_millis), this blocks
+/// the whole executor.
+#[no_mangle]
+pub extern "C" fn cntrlr_delay(ms: usize) {
+    for _ in 0..ms {
+        time::delay_us_blocking(1000);
+    }
+}
+
+/// Enable a serial port at the given baud rate
+///
+/// Ports are numbered from 1, as in [`serial_1`](crate::io::serial_1).
+/// Returns 0, or a negative `CNTRLR_ERROR_` code.
+#[no_mangle]
+pub extern "C" fn cntrlr_serial_enable(port: usize, baud: usize) -> isize {
+    with_port!(port, |serial| match serial.enable(baud) {
+        Ok(()) => 0,
+        Err(_) => CNTRLR_ERROR_IO,
+    })
+}
+
+/// Read from a serial port
+///
+/// Waits until at least one byte is available, and returns the
+/// number of bytes read, or a negative `CNTRLR_ERROR_` code.
+///
+/// # Safety
+/// `buf` must be valid for writes of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cntrlr_serial_read(port: usize, buf: *mut u8, len: usize) -> isize {
+    let buf: &mut [u8] = if len == 0 {
+        &mut []
+    } else {
+        slice::from_raw_parts_mut(buf, len)
+    };
+    with_port!(port, |serial| match block_on(serial.read(buf)) {
+        Ok(count) => count as isize,
+        Err(_) => CNTRLR_ERROR_IO,
+    })
+}
+
+/// Write to a serial port
+///
+/// Waits until every byte is queued, and returns the number of bytes
+/// written, or a negative `CNTRLR_ERROR_` code.
+///
+/// # Safety
+/// `data` must be valid for reads of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cntrlr_serial_write(port: usize, data: *const u8, len: usize) -> isize {
+    let data: &[u8] = if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len)
+    };
+    with_port!(port, |serial| {
+        let mut written = 0;
+        while written < data.len() {
+            match block_on(serial.write(&data[written..])) {
+                Ok(count) => written += count,
+                Err(_) => return CNTRLR_ERROR_IO,
+            }
+        }
+        written as isize
+    })
+}
+
+fn with_lock<F, G, S>(lock: F, op: impl FnOnce(G) -> isize) -> isize
+where
+    F: Future<Output = G>,
+    G: DerefMut<Target = S>,
+    S: Serial,
+{
+    match poll_once(lock) {
+        Some(guard) => op(guard),
+        None => CNTRLR_ERROR_IN_USE,
+    }
+}
+
+/// Poll a future once, without a task to wake
+fn poll_once<F: Future>(future: F) -> Option<F::Output> {
+    let mut future = future;
+    // Safety: The future is not moved after it is pinned here.
+    let future = unsafe { Pin::new_unchecked(&mut future) };
+    match future.poll(&mut Context::from_waker(&noop_waker())) {
+        Poll::Ready(output) => Some(output),
+        Poll::Pending => None,
+    }
+}
+
+/// Poll a future until it finishes
+///
+/// The drivers' interrupts still run while this spins, so futures
+/// which are woken from interrupts make progress.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = future;
+    // Safety: The future is not moved after it is pinned here.
+    let mut future = unsafe { Pin::new_unchecked(&mut future) };
+    let waker = noop_waker();
+    let mut ctx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut ctx) {
+            return output;
+        }
+    }
+}
+
+fn noop_waker() -> Waker {
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(
+        |_| RawWaker::new(ptr::null(), &VTABLE),
+        |_| {},
+        |_| {},
+        |_| {},
+    );
+    // Safety: The vtable does nothing with the data pointer.
+    unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) }
+}
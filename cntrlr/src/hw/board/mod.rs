@@ -3,6 +3,104 @@
 
 //! Board support
 
+/// Define the pin table for a board
+///
+/// Each entry names a board pin, gives its number, and maps it to the
+/// function returning its port, the port number, and the pin within
+/// that port. This is the single source of the pin mapping for a
+/// board: it generates the `pin_op` and `mcu_pin` functions, and the
+/// `pins` module of named pin constants. Additional names for pins,
+/// such as analog input numbers, can be listed after a `;`.
+///
+/// This must be invoked in a module which has the board's `PinOp`
+/// trait in scope.
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+macro_rules! pin_table {
+    (
+        $($name:ident = $num:literal => $port:ident($port_num:literal, $pin:literal),)*
+        $(; $($alias:ident = $target:expr,)*)?
+    ) => {
+        /// Invoke an operation on a pin.
+        ///
+        /// This abstracts the mapping of board pins to MCU pins,
+        /// allowing operations to be defined generically and then
+        /// invoked for any given pin.
+        #[inline]
+        pub fn pin_op<Op: PinOp>(pin: usize, arg: Op::Arg) -> Option<Op::Result> {
+            match pin {
+                $($num => Op::do_op($port().and_then(|port| port.pin::<$pin>()), arg),)*
+                _ => None,
+            }
+        }
+
+        /// Get the MCU port and pin for a board pin
+        ///
+        /// Returns `None` if `pin` is not a valid digital pin.
+        #[inline]
+        pub fn mcu_pin(pin: usize) -> Option<(usize, usize)> {
+            match pin {
+                $($num => Some(($port_num, $pin)),)*
+                _ => None,
+            }
+        }
+
+        /// Named pins for this board
+        ///
+        /// `Dn` is digital pin `n`. Where the board has analog
+        /// inputs, `An` is analog input `n`. Some analog inputs do
+        /// not have a digital function, and so have no `Dn` name.
+        ///
+        /// Each `Dn` is also a type, for APIs which check at compile
+        /// time that a pin can serve a function.
+        #[allow(missing_docs)]
+        pub mod pins {
+            $(pub const $name: usize = $num;)*
+            $($(pub const $alias: usize = $target;)*)?
+
+            /// A board pin, as a type
+            pub struct Pin<const N: usize>;
+
+            impl<const N: usize> crate::pins::BoardPin for Pin<N> {
+                const NUMBER: usize = N;
+            }
+
+            $(pub type $name = Pin<$num>;)*
+        }
+    };
+}
+
+/// Define the pin function table for a board
+///
+/// Each entry lists the pins which can serve one of the functions in
+/// [`crate::pins`], implementing that function's trait for their
+/// types. APIs bound by these traits then reject any other pin at
+/// compile time.
+///
+/// This must be invoked in the same module as the board's
+/// `pin_table`.
+#[cfg(any(
+    doc,
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36"
+))]
+macro_rules! pin_functions {
+    ($($function:ident<$arg:literal> = [$($pin:ident),* $(,)?],)*) => {
+        $($(impl crate::pins::$function<$arg> for pins::$pin {})*)*
+    };
+}
+
 #[cfg(any(doc, board = "red_v"))]
 #[cfg_attr(feature = "doc-cfg", doc(cfg(board = "red_v")))]
 pub mod red_v;
@@ -23,6 +121,14 @@ pub mod teensy_35;
 #[cfg_attr(feature = "doc-cfg", doc(cfg(board = "teensy_36")))]
 pub mod teensy_36;
 
+#[cfg(any(doc, board = "teensy_40"))]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(board = "teensy_40")))]
+pub mod teensy_40;
+
+#[cfg(any(doc, board = "teensy_41"))]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(board = "teensy_41")))]
+pub mod teensy_41;
+
 #[cfg(any(doc, board = "teensy_lc"))]
 #[cfg_attr(feature = "doc-cfg", doc(cfg(board = "teensy_lc")))]
 pub mod teensy_lc;
@@ -46,3 +152,10 @@ pub mod teensy_lc;
     ))
 )]
 pub mod teensy_common;
+
+#[cfg(any(doc, board = "teensy_40", board = "teensy_41"))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(board = "teensy_40", board = "teensy_41")))
+)]
+pub mod teensy_4_common;
@@ -4,10 +4,12 @@
 //! Digital pin support specific to the Sparkfun Red V
 
 use crate::{
-    digital::{PinMode, Pull},
+    digital::{DriveStrength, Edge, FilterConfig, Led, PinConfig, PinMode, Pull, SlewRate},
     hw::mcu::sifive::fe310g002::{Gpio, Pin},
-    sync::Once,
+    sync::{without_interrupts, Once},
 };
+use bit_field::BitField;
+use core::ptr::{read_volatile, write_volatile};
 
 /// An operation on a pin.
 ///
@@ -62,6 +64,18 @@ impl PinOp for ReadOp {
     }
 }
 
+/// An operation to read the state a pin is set to output
+pub struct OutputOp;
+impl PinOp for OutputOp {
+    type Arg = ();
+    type Result = bool;
+
+    #[inline(always)]
+    fn op<const N: usize, const P: usize>(pin: Pin<'_, N, P>, _: ()) -> bool {
+        pin.into_gpio().read_output()
+    }
+}
+
 /// An operation to set a pin's [mode](`PinMode`)
 pub struct ModeOp;
 impl PinOp for ModeOp {
@@ -94,38 +108,96 @@ impl PinOp for ModeOp {
     }
 }
 
-/// Invoke an operation on a pin.
+/// An operation to set which edges trigger a pin's interrupt
 ///
-/// This abstracts the mapping of Teensy pins to MCU pins, allowing
-/// operations to be defined generically and then invoked for any
-/// given pin.
-#[inline]
-pub fn pin_op<Op: PinOp>(pin: usize, arg: Op::Arg) -> Option<Op::Result> {
-    match pin {
-        0 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<16>()), arg),
-        1 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<17>()), arg),
-        2 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<18>()), arg),
-        3 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<19>()), arg),
-        4 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<20>()), arg),
-        5 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<21>()), arg),
-        6 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<22>()), arg),
-        7 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<23>()), arg),
-        8 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<0>()), arg),
-        9 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<1>()), arg),
-        10 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<2>()), arg),
-        11 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<3>()), arg),
-        12 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<4>()), arg),
-        13 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<5>()), arg),
-        // Pin 14 is absent on this board
-        15 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<9>()), arg),
-        16 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<10>()), arg),
-        17 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<11>()), arg),
-        18 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<12>()), arg),
-        19 => Op::do_op(gpio().and_then(|gpio| gpio.pin::<13>()), arg),
-        _ => None,
+/// The pin is set as a digital input. Its pull-up is left unchanged.
+pub struct InterruptOp;
+impl PinOp for InterruptOp {
+    type Arg = Option<Edge>;
+    type Result = ();
+
+    #[inline(always)]
+    fn op<const N: usize, const P: usize>(pin: Pin<'_, N, P>, edge: Option<Edge>) {
+        let mut pin = pin.into_gpio();
+        pin.set_output(false);
+        pin.set_interrupt(edge);
+    }
+}
+
+/// An operation to set a pin's output drive strength
+pub struct DriveOp;
+impl PinOp for DriveOp {
+    type Arg = DriveStrength;
+    type Result = bool;
+
+    #[inline(always)]
+    fn op<const N: usize, const P: usize>(mut pin: Pin<'_, N, P>, strength: DriveStrength) -> bool {
+        pin.set_high_drive(strength == DriveStrength::High);
+        true
     }
 }
 
+/// An operation to set a pin's output slew rate
+pub struct SlewOp;
+impl PinOp for SlewOp {
+    type Arg = SlewRate;
+    type Result = bool;
+
+    #[inline(always)]
+    fn op<const N: usize, const P: usize>(_pin: Pin<'_, N, P>, rate: SlewRate) -> bool {
+        rate == SlewRate::Fast
+    }
+}
+
+/// An operation to set a pin's input filter
+pub struct FilterOp;
+impl PinOp for FilterOp {
+    type Arg = FilterConfig;
+    type Result = bool;
+
+    #[inline(always)]
+    fn op<const N: usize, const P: usize>(_pin: Pin<'_, N, P>, config: FilterConfig) -> bool {
+        config == FilterConfig::None
+    }
+}
+
+/// An operation to connect a pin to its PWM output
+pub struct PwmOp;
+impl PinOp for PwmOp {
+    type Arg = ();
+    type Result = ();
+
+    #[inline(always)]
+    fn op<const N: usize, const P: usize>(mut pin: Pin<'_, N, P>, _: ()) {
+        pin.set_iof(true);
+    }
+}
+
+pin_table! {
+    D0 = 0 => gpio(0, 16),
+    D1 = 1 => gpio(0, 17),
+    D2 = 2 => gpio(0, 18),
+    D3 = 3 => gpio(0, 19),
+    D4 = 4 => gpio(0, 20),
+    D5 = 5 => gpio(0, 21),
+    D6 = 6 => gpio(0, 22),
+    D7 = 7 => gpio(0, 23),
+    D8 = 8 => gpio(0, 0),
+    D9 = 9 => gpio(0, 1),
+    D10 = 10 => gpio(0, 2),
+    D11 = 11 => gpio(0, 3),
+    D12 = 12 => gpio(0, 4),
+    D13 = 13 => gpio(0, 5),
+    // Pin 14 is absent on this board
+    D15 = 15 => gpio(0, 9),
+    D16 = 16 => gpio(0, 10),
+    D17 = 17 => gpio(0, 11),
+    D18 = 18 => gpio(0, 12),
+    D19 = 19 => gpio(0, 13),
+    ;
+    LED_PIN = D13,
+}
+
 /// Set a digital pin high or low.
 ///
 /// The digital pins on the Red-V are 5V tolerant, but use
@@ -178,6 +250,167 @@ pub fn pin_mode(pin: usize, mode: PinMode) {
     pin_op::<ModeOp>(pin, mode);
 }
 
+/// The pin connected to the on-board LED
+///
+/// On the Red-V, the LED is on pin 13, which is GPIO 5 on the FE310.
+#[inline]
+pub fn led_builtin() -> usize {
+    pins::LED_PIN
+}
+
+/// Turn the on-board LED on or off
+///
+/// This sets the LED pin as an output, so it does not need to be
+/// configured with [`pin_mode`] first.
+#[inline]
+pub fn led_write(on: bool) {
+    pin_mode(led_builtin(), PinMode::Output);
+    digital_write(led_builtin(), on);
+}
+
+/// Check whether the on-board LED is on
+///
+/// This reads the state the LED pin is set to output.
+#[inline]
+pub fn led_read() -> bool {
+    pin_op::<OutputOp>(led_builtin(), ()).unwrap_or(false)
+}
+
+/// Get the on-board LED
+#[inline]
+pub fn led() -> Led {
+    Led(())
+}
+
+/// Read back the configuration of a pin
+///
+/// The Red-V has no pull-down or open-drain pin configurations, so
+/// these are never reported.
+///
+/// Returns `None` if `pin` is not a valid pin.
+pub fn dump_pin_config(pin: usize) -> Option<PinConfig> {
+    let (port, mcu_pin) = mcu_pin(pin)?;
+    let reg = |offset: usize| unsafe { read_volatile((0x1001_2000 + offset) as *const u32) };
+
+    let function = if reg(0x38).get_bit(mcu_pin) {
+        1 + reg(0x3C).get_bit(mcu_pin) as usize
+    } else {
+        0
+    };
+    Some(PinConfig {
+        mcu_pin: (port, mcu_pin),
+        function,
+        output: reg(0x08).get_bit(mcu_pin),
+        pull: if reg(0x10).get_bit(mcu_pin) {
+            Some(Pull::Up)
+        } else {
+            None
+        },
+        open_drain: false,
+        drive_strength: if reg(0x14).get_bit(mcu_pin) {
+            DriveStrength::High
+        } else {
+            DriveStrength::Low
+        },
+        slew_rate: SlewRate::Fast,
+        interrupt: match (reg(0x18).get_bit(mcu_pin), reg(0x20).get_bit(mcu_pin)) {
+            (true, true) => Some(Edge::Both),
+            (true, false) => Some(Edge::Rising),
+            (false, true) => Some(Edge::Falling),
+            (false, false) => None,
+        },
+    })
+}
+
+/// Set the output drive strength of a pin
+///
+/// Every pin on the Red-V supports high drive strength.
+///
+/// Returns `false` if `pin` is not a valid pin or is in use by
+/// another module.
+#[inline]
+pub fn set_drive_strength(pin: usize, strength: DriveStrength) -> bool {
+    pin_op::<DriveOp>(pin, strength).unwrap_or(false)
+}
+
+/// Set the output slew rate of a pin
+///
+/// The FE310 has no slew rate control, so only [`SlewRate::Fast`] is
+/// accepted.
+///
+/// Returns `false` if `pin` is not a valid pin, the rate is not
+/// [`SlewRate::Fast`], or the pin is in use by another module.
+#[inline]
+pub fn set_slew_rate(pin: usize, rate: SlewRate) -> bool {
+    pin_op::<SlewOp>(pin, rate).unwrap_or(false)
+}
+
+/// Set the input filter of a pin
+///
+/// The FE310 synchronizes every input to its clock, but has no
+/// configurable filters, so only [`FilterConfig::None`] is accepted.
+///
+/// Returns `false` if `pin` is not a valid pin, the filter is not
+/// [`FilterConfig::None`], or the pin is in use by another module.
+#[inline]
+pub fn set_input_filter(pin: usize, config: FilterConfig) -> bool {
+    pin_op::<FilterOp>(pin, config).unwrap_or(false)
+}
+
+/// Set which edges on a pin trigger its interrupt
+///
+/// Every pin on the Red-V can generate interrupts.
+///
+/// Returns `false` if `pin` is not a valid pin or is in use by
+/// another module.
+#[inline]
+pub(crate) fn set_pin_interrupt(pin: usize, edge: Option<Edge>) -> bool {
+    pin_op::<InterruptOp>(pin, edge).is_some()
+}
+
+/// Interrupt function for the GPIO pins
+///
+/// Each GPIO pin has its own PLIC source, and all of them share this
+/// function. A pin which a task was waiting on has its interrupts
+/// disabled, until the task waits again.
+pub extern "C" fn gpio_intr() {
+    const RISE_IE: *mut u32 = 0x1001_2018 as _;
+    const RISE_IP: *mut u32 = 0x1001_201C as _;
+    const FALL_IE: *mut u32 = 0x1001_2020 as _;
+    const FALL_IP: *mut u32 = 0x1001_2024 as _;
+
+    unsafe {
+        let pending = read_volatile(RISE_IP) & read_volatile(RISE_IE)
+            | read_volatile(FALL_IP) & read_volatile(FALL_IE);
+        let mut waited = 0;
+        for pin in 0..32 {
+            if pending & (1 << pin) != 0 && crate::digital::edge_intr(0, pin) {
+                waited |= 1 << pin;
+            }
+        }
+        write_volatile(RISE_IE, read_volatile(RISE_IE) & !waited);
+        write_volatile(FALL_IE, read_volatile(FALL_IE) & !waited);
+        write_volatile(RISE_IP, pending);
+        write_volatile(FALL_IP, pending);
+    }
+}
+
+/// Read the input state of every pin on the GPIO
+pub(crate) fn port_read(_port: usize) -> u32 {
+    unsafe { read_volatile(0x1001_2000 as *const u32) }
+}
+
+/// Set the output state of the pins in `mask` on the GPIO
+pub(crate) fn port_write(_port: usize, mask: u32, value: u32) {
+    let output_val = 0x1001_200C as *mut u32;
+    unsafe {
+        without_interrupts(|| {
+            let current = read_volatile(output_val);
+            write_volatile(output_val, current & !mask | value & mask);
+        });
+    }
+}
+
 /// The GPIO
 ///
 /// The global instance of the GPIO, used to share ownership among
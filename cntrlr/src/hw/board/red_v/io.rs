@@ -9,13 +9,14 @@ use crate::{
         peripheral::{uart, Peripheral},
         Fe310G002,
     },
-    io::{self, Read, Write},
-    sync::{Mutex, MutexGuard},
+    io::{self, IoSlice, Read, Write},
+    sync::{AsyncMutex, AsyncMutexLock},
     task::WakerSet,
 };
 use core::{
     future::{poll_fn, ready, Future},
-    sync::atomic::Ordering,
+    ptr::write_volatile,
+    sync::atomic::{AtomicUsize, Ordering},
     task::Poll,
 };
 
@@ -42,10 +43,27 @@ pub enum SerialError {
     InvalidOption,
 }
 
+impl From<SerialError> for io::Error {
+    fn from(err: SerialError) -> Self {
+        let kind = match err {
+            SerialError::NotEnabled => io::ErrorKind::NotEnabled,
+            SerialError::PinInUse | SerialError::UartInUse | SerialError::GpioInUse => {
+                io::ErrorKind::InUse
+            }
+            SerialError::InvalidBaud | SerialError::InvalidOption => io::ErrorKind::InvalidSetting,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
 /// A serial interface
 ///
 /// This wraps a UART and provides application-level functionality.
-pub struct Serial<T, R, const N: usize>(Option<Uart<T, R, N>>, Option<&'static WakerSet>);
+pub struct Serial<T, R, const N: usize>(
+    Option<Uart<T, R, N>>,
+    Option<&'static WakerSet>,
+    io::SerialStats,
+);
 
 impl<T, R, const N: usize> Read for Serial<T, R, N>
 where
@@ -74,6 +92,9 @@ where
                 }
             }
             if count > 0 {
+                // The FE310 UART has no overrun detection, so only
+                // the burst size can be tracked here.
+                self.2.max_burst = self.2.max_burst.max(count);
                 Poll::Ready(Ok(count))
             } else {
                 if let Some(wakers) = self.1.as_ref() {
@@ -94,6 +115,7 @@ where
     type Error = SerialError;
     type Future<'a> = impl Future<Output = Result<usize, Self::Error>>;
     type FlushFuture<'a> = impl Future<Output = Result<(), Self::Error>>;
+    type VectoredFuture<'a> = impl Future<Output = Result<usize, Self::Error>>;
 
     fn write<'a>(&'a mut self, buf: &'a [u8]) -> <Self as Write>::Future<'a>
     where
@@ -126,6 +148,40 @@ where
         })
     }
 
+    fn write_vectored<'a>(
+        &'a mut self,
+        bufs: &'a [IoSlice<'a>],
+    ) -> <Self as Write>::VectoredFuture<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            if bufs.iter().all(|buf| buf.is_empty()) {
+                return Poll::Ready(Ok(0));
+            }
+
+            let mut count = 0;
+            let uart = self.0.as_mut().ok_or(SerialError::NotEnabled)?;
+            'bufs: for buf in bufs {
+                for &byte in buf.iter() {
+                    if !uart.write_data(byte) {
+                        break 'bufs;
+                    }
+                    count += 1;
+                }
+            }
+            if count > 0 {
+                Poll::Ready(Ok(count))
+            } else {
+                if let Some(wakers) = self.1.as_ref() {
+                    wakers.add(ctx.waker().clone());
+                }
+                uart.enable_tx_intr();
+                Poll::Pending
+            }
+        })
+    }
+
     fn flush<'a>(&'a mut self) -> <Self as Write>::FlushFuture<'a>
     where
         Self: 'a,
@@ -136,6 +192,69 @@ where
     }
 }
 
+impl<T, R, const N: usize> Serial<T, R, N>
+where
+    R: uart::UartRx<Fe310G002, N>,
+{
+    /// Read whatever data the UART has already received
+    ///
+    /// This drains the receive FIFO once, without waiting or
+    /// registering a waker, so it can be used outside the executor,
+    /// such as from an interrupt or panic handler. Returns the number
+    /// of bytes read, which is zero if the port is disabled.
+    pub fn try_read(&mut self, buf: &mut [u8]) -> usize {
+        let uart = match self.0.as_mut() {
+            Some(uart) => uart,
+            None => return 0,
+        };
+        let mut count = 0;
+        while count < buf.len() {
+            match uart.read_data() {
+                Some(byte) => buf[count] = byte,
+                None => break,
+            }
+            count += 1;
+        }
+        self.2.max_burst = self.2.max_burst.max(count);
+        count
+    }
+}
+
+impl<T, R, const N: usize> Serial<T, R, N>
+where
+    T: uart::UartTx<Fe310G002, N>,
+{
+    /// Write as much data as the UART can accept right now
+    ///
+    /// This fills the transmit FIFO once, without waiting or
+    /// registering a waker, so it can be used outside the executor,
+    /// such as from an interrupt or panic handler. Returns the number
+    /// of bytes written, which is zero if the port is disabled.
+    pub fn try_write(&mut self, buf: &[u8]) -> usize {
+        let uart = match self.0.as_mut() {
+            Some(uart) => uart,
+            None => return 0,
+        };
+        buf.iter()
+            .take_while(|&&byte| uart.write_data(byte))
+            .count()
+    }
+
+    /// Write all of a buffer, busy-waiting while the UART is full
+    ///
+    /// This is for the panic handler, which cannot wait on the
+    /// executor. It returns immediately if the port is disabled.
+    pub(crate) fn write_blocking(&mut self, mut buf: &[u8]) {
+        if self.0.is_none() {
+            return;
+        }
+        while !buf.is_empty() {
+            let count = self.try_write(buf);
+            buf = &buf[count..];
+        }
+    }
+}
+
 /// The pin used to recieve for serial 1
 pub type Serial1Rx = UartRx<Pin<'static, 0, 16>>;
 
@@ -162,20 +281,59 @@ where
         rx: R,
         wakers: &'static WakerSet,
     ) -> Result<(), SerialError> {
-        let divisor = super::CPU_FREQ.load(Ordering::Relaxed) as usize / baud;
+        let divisor = divisor(super::CPU_FREQ.load(Ordering::Relaxed), baud);
         if divisor < 16 {
             return Err(SerialError::InvalidBaud);
         }
-        if !options.is_empty() {
-            return Err(SerialError::InvalidOption);
+        let mut two_stop_bits = false;
+        for option in options {
+            match option {
+                io::SerialOption::StopBits(1) => two_stop_bits = false,
+                io::SerialOption::StopBits(2) => two_stop_bits = true,
+                io::SerialOption::DataBits(8) | io::SerialOption::Parity(io::Parity::None) => {}
+                _ => return Err(SerialError::InvalidOption),
+            }
         }
         let mut uart = Uart::<(), (), N>::get().ok_or(SerialError::UartInUse)?;
         uart.set_divisor(divisor);
+        uart.set_two_stop_bits(two_stop_bits);
         uart.set_watermarks(7, 0);
         self.0 = Some(uart.enable_tx(tx).enable_rx(rx));
         self.1 = Some(wakers);
+        SERIAL_BAUDS[N].store(baud, Ordering::Relaxed);
         Ok(())
     }
+
+    fn do_disable(&mut self) {
+        SERIAL_BAUDS[N].store(0, Ordering::Relaxed);
+        self.0 = None;
+        self.1 = None;
+    }
+}
+
+/// Calculate the UART divisor for a baud rate
+///
+/// The UARTs are clocked from the core clock. The divisor is rounded
+/// to the nearest integer to minimize the baud rate error.
+fn divisor(clock: usize, baud: usize) -> usize {
+    (clock + baud / 2) / baud
+}
+
+/// Update the divisors of the enabled serial ports for a new core clock
+///
+/// This is called by [`set_clock`](super::set_clock) after the core
+/// clock has changed, so that each serial port keeps the baud rate it
+/// was enabled with.
+pub(crate) fn update_divisors(clock: usize) {
+    const UART_DIV: [*mut u32; 2] = [0x1001_3018 as _, 0x1002_3018 as _];
+    for (baud, div) in SERIAL_BAUDS.iter().zip(UART_DIV.iter()) {
+        let baud = baud.load(Ordering::Relaxed);
+        if baud != 0 {
+            unsafe {
+                write_volatile(*div, divisor(clock, baud) as u32 - 1);
+            }
+        }
+    }
 }
 
 impl io::Serial for Serial<Serial1Tx, Serial1Rx, 0> {
@@ -199,10 +357,21 @@ impl io::Serial for Serial<Serial1Tx, Serial1Rx, 0> {
     }
 
     fn disable(&mut self) -> Result<(), <Self as io::Serial>::Error> {
-        self.0 = None;
-        self.1 = None;
+        self.do_disable();
         Ok(())
     }
+
+    fn stats(&self) -> io::SerialStats {
+        self.2
+    }
+
+    fn reset_stats(&mut self) {
+        self.2 = io::SerialStats::new();
+    }
+
+    fn alt_pin_sets(&self) -> &'static [io::SerialPins] {
+        &[io::SerialPins { tx: 1, rx: 0 }]
+    }
 }
 
 impl io::Serial for Serial<Serial2Tx, Serial2Rx, 1> {
@@ -226,10 +395,21 @@ impl io::Serial for Serial<Serial2Tx, Serial2Rx, 1> {
     }
 
     fn disable(&mut self) -> Result<(), <Self as io::Serial>::Error> {
-        self.0 = None;
-        self.1 = None;
+        self.do_disable();
         Ok(())
     }
+
+    fn stats(&self) -> io::SerialStats {
+        self.2
+    }
+
+    fn reset_stats(&mut self) {
+        self.2 = io::SerialStats::new();
+    }
+
+    fn alt_pin_sets(&self) -> &'static [io::SerialPins] {
+        &[io::SerialPins { tx: 2, rx: 7 }]
+    }
 }
 
 /// The serial connection to a host PC
@@ -238,7 +418,7 @@ impl io::Serial for Serial<Serial2Tx, Serial2Rx, 1> {
 /// use the serial port to communicate with outside hardware via pins
 /// 0 and 1, you should prefer to use [`serial_1`] for compatibility
 /// with board which differentiate those serial ports.
-pub fn pc_serial() -> MutexGuard<'static, Serial<Serial1Tx, Serial1Rx, 0>> {
+pub fn pc_serial() -> AsyncMutexLock<'static, Serial<Serial1Tx, Serial1Rx, 0>> {
     serial_1()
 }
 
@@ -248,17 +428,37 @@ pub fn pc_serial() -> MutexGuard<'static, Serial<Serial1Tx, Serial1Rx, 0>> {
 /// to use the serial port to communicate with a hose PC, you should
 /// prefer to use [`pc_serial`] for compatibility with boards which
 /// differentiate those serial ports.
-pub fn serial_1() -> MutexGuard<'static, Serial<Serial1Tx, Serial1Rx, 0>> {
-    static SERIAL: Mutex<Serial<Serial1Tx, Serial1Rx, 0>> = Mutex::new(Serial(None, None));
+pub fn serial_1() -> AsyncMutexLock<'static, Serial<Serial1Tx, Serial1Rx, 0>> {
+    static SERIAL: AsyncMutex<Serial<Serial1Tx, Serial1Rx, 0>> =
+        AsyncMutex::new(Serial(None, None, io::SerialStats::new()));
     SERIAL.lock()
 }
 
 /// The second hardware serial port
-pub fn serial_2() -> MutexGuard<'static, Serial<Serial2Tx, Serial2Rx, 1>> {
-    static SERIAL: Mutex<Serial<Serial2Tx, Serial2Rx, 1>> = Mutex::new(Serial(None, None));
+///
+/// This is UART1, with its transmit pin on pin 2 and its receive pin
+/// on pin 7.
+pub fn serial_2() -> AsyncMutexLock<'static, Serial<Serial2Tx, Serial2Rx, 1>> {
+    static SERIAL: AsyncMutex<Serial<Serial2Tx, Serial2Rx, 1>> =
+        AsyncMutex::new(Serial(None, None, io::SerialStats::new()));
     SERIAL.lock()
 }
 
+/// Write to a serial port from the panic handler
+///
+/// The port is used even if a task holds it, since that task will
+/// never run again. Nothing is written if the port is disabled.
+pub fn panic_write(port: usize, bytes: &[u8]) {
+    unsafe {
+        match port {
+            1 => serial_1().steal().write_blocking(bytes),
+            2 => serial_2().steal().write_blocking(bytes),
+            _ => {}
+        }
+    }
+}
+
+static SERIAL_BAUDS: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
 static SERIAL_1_WAKERS: WakerSet = WakerSet::new();
 static SERIAL_2_WAKERS: WakerSet = WakerSet::new();
 
@@ -3,7 +3,10 @@
 
 //! Board-specific functionality for the Sparkfun Red V
 
-use crate::{hw::mcu::sifive::fe310g002::Plic, sync::enable_interrupts};
+use crate::{
+    hw::mcu::sifive::{fe310g002::Plic, peripheral::plic::Handler},
+    sync::enable_interrupts,
+};
 use core::{
     ptr::write_volatile,
     sync::atomic::{AtomicUsize, Ordering},
@@ -11,7 +14,9 @@ use core::{
 
 pub mod digital;
 pub mod io;
+pub mod pwm;
 pub mod time;
+pub mod watchdog;
 
 static CPU_FREQ: AtomicUsize = AtomicUsize::new(0);
 
@@ -32,6 +37,8 @@ pub enum SetClockError {
 /// Set the clock for the board, in Hz.
 ///
 /// Valid valies are 256 or 384 MHz
+///
+/// Enabled serial ports are updated to keep their baud rates.
 pub fn set_clock(clock: usize) -> Result<(), SetClockError> {
     use crate::hw::mcu::sifive::fe310g002::{Prci, Spi};
 
@@ -45,7 +52,11 @@ pub fn set_clock(clock: usize) -> Result<(), SetClockError> {
         // If SPI is in use we have no choice but to hope the user did
         // the right thing before invoking us.
         if let Some(mut spi) = Spi::<(), (), 0>::get() {
-            spi.set_divisor(spi_div);
+            // Safety: The divisors used here keep the flash clock
+            // within the limits of the standard read command.
+            unsafe {
+                spi.set_flash_divisor(spi_div);
+            }
         }
     }
 
@@ -54,14 +65,30 @@ pub fn set_clock(clock: usize) -> Result<(), SetClockError> {
 
     if clock < old_clock {
         if let Some(mut spi) = Spi::<(), (), 0>::get() {
-            spi.set_divisor(spi_div);
+            // Safety: As above
+            unsafe {
+                spi.set_flash_divisor(spi_div);
+            }
         }
     }
 
     CPU_FREQ.store(clock, Ordering::Relaxed);
+    io::update_divisors(clock);
     Ok(())
 }
 
+/// Reboot the board
+///
+/// This resets the MCU through the watchdog, whether or not the
+/// watchdog is in use elsewhere.
+pub fn reboot() -> ! {
+    use crate::{hw::mcu::sifive::fe310g002::Wdog, sync::without_interrupts};
+
+    // Safety: Interrupts are disabled, and the handle is never
+    // dropped, as resetting does not return.
+    without_interrupts(|| unsafe { Wdog::steal().reset() })
+}
+
 /// Early init for the Red V board.
 ///
 /// This is a noop for this board.
@@ -88,7 +115,8 @@ pub extern "C" fn start() {}
 pub unsafe extern "C" fn init() {
     set_clock(256_000_000).expect("Could not set core clock at init");
 
-    // external oscillator runs at 32.768KHz. Set up mtimecmp to fire every 1ms
+    // external oscillator runs at 32.768KHz. Set up mtimecmp to fire
+    // after 1ms. The timer interrupt schedules itself from then on.
     const MTIMECMP_LO: *mut u32 = 0x0200_4000 as _;
     const MTIMECMP_HI: *mut u32 = 0x0200_4004 as _;
     const MTIME_LO: *mut u32 = 0x0200_BFF8 as _;
@@ -101,9 +129,22 @@ pub unsafe extern "C" fn init() {
     let mut plic = Plic::get();
     plic.mask_all();
     plic.set_threshold(0);
-    for intr in &[3, 4] {
-        plic.enable(*intr);
-        plic.set_priority(*intr, 1);
+    let handlers: [(usize, Handler); 3] = [
+        (2, time::rtc_intr),
+        (3, io::serial_1_intr),
+        (4, io::serial_2_intr),
+    ];
+    for &(intr, handler) in &handlers {
+        plic.set_handler(intr, Some(handler));
+        plic.enable(intr);
+        plic.set_priority(intr, 1);
+    }
+
+    // Each GPIO pin has its own interrupt source, from 8 to 39
+    for intr in 8..40 {
+        plic.set_handler(intr, Some(digital::gpio_intr));
+        plic.enable(intr);
+        plic.set_priority(intr, 1);
     }
     // Enable all interrupt sources and set up the runtime trap vec.
     #[cfg(board = "red_v")]
@@ -175,6 +216,7 @@ pub unsafe extern "C" fn early_trap() {
 /// This function should never be called by user code directly. It is
 /// intended only to be written to `mtvec`.
 #[cfg_attr(board = "red_v", link_section = ".__CNTRLR_TRAP")]
+#[cfg_attr(board = "red_v", export_name = "__cntrlr_trap")]
 #[cfg_attr(board = "red_v", naked)]
 #[allow(dead_code)]
 pub unsafe extern "C" fn trap_vec() {
@@ -225,6 +267,9 @@ pub unsafe extern "C" fn trap_vec() {
 
 #[allow(dead_code)]
 unsafe extern "C" fn handle_trap(mcause: u32, mepc: u32, mtval: u32) {
+    if mcause < 0x8000_0000 {
+        crate::runtime::set_fault(crate::runtime::Fault::Exception);
+    }
     match mcause {
         0 => panic!("Misaligned Instruction at 0x{:8X}", mepc),
         1 => panic!("Instruction access fault at 0x{:8X}", mepc),
@@ -246,18 +291,9 @@ unsafe extern "C" fn handle_trap(mcause: u32, mepc: u32, mtval: u32) {
             "Store or atmoic fault of 0x{:8X} by instruction at 0x{:8X}",
             mtval, mepc
         ),
+        0x8000_0003 => crate::task::clear_wake_signal(),
         0x8000_0007 => time::timer_intr(),
-        0x8000_000B => loop {
-            let mut plic = Plic::get();
-            let intr = plic.claim();
-            match intr {
-                0 => break,
-                3 => io::serial_1_intr(),
-                4 => io::serial_2_intr(),
-                _ => {}
-            }
-            plic.complete(intr);
-        },
+        0x8000_000B => Plic::get().dispatch(),
         _ => panic!("Unknown trap"),
     }
 }
@@ -0,0 +1,165 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! PWM functionality specific to the Red-V board
+
+use super::digital::{pin_op, PwmOp};
+use crate::{
+    hw::mcu::sifive::fe310g002::Pwm as PwmBlock,
+    pwm::{self, Pwm, PwmChannel, PwmError},
+    sync::Mutex,
+};
+use core::sync::atomic::Ordering;
+
+/// Timers 0, 1, and 2 are PWM0, PWM1, and PWM2. Channel 0 of each
+/// timer sets the PWM period, so only channels 1 through 3 drive pins.
+static PWM_CHANNELS: [PwmChannel; 9] = [
+    PwmChannel::new(9, 0, 1),
+    PwmChannel::new(10, 0, 2),
+    PwmChannel::new(11, 0, 3),
+    PwmChannel::new(3, 1, 1),
+    PwmChannel::new(5, 1, 2),
+    PwmChannel::new(6, 1, 3),
+    PwmChannel::new(17, 2, 1),
+    PwmChannel::new(18, 2, 2),
+    PwmChannel::new(19, 2, 3),
+];
+
+static PWM_0: Mutex<Option<PwmBlock<0>>> = Mutex::new(None);
+static PWM_1: Mutex<Option<PwmBlock<1>>> = Mutex::new(None);
+static PWM_2: Mutex<Option<PwmBlock<2>>> = Mutex::new(None);
+
+/// Every PWM-capable pin on this board
+pub fn pwm_channels() -> &'static [PwmChannel] {
+    &PWM_CHANNELS
+}
+
+/// Get the hardware PWM output of a pin
+pub fn pwm_pin(pin: usize) -> Result<PwmPin, PwmError> {
+    Ok(PwmPin {
+        channel: pwm::pwm_channel(pin).ok_or(PwmError::InvalidPin)?,
+        period: 0,
+    })
+}
+
+/// The hardware PWM output of a pin
+pub struct PwmPin {
+    channel: PwmChannel,
+    period: u32,
+}
+
+impl PwmPin {
+    fn stop(&mut self) {
+        let channel = self.channel.channel;
+        with_timer(self.channel.timer, |timer| timer.set_duty(channel, 0));
+        pwm::release(self.channel.pin);
+        self.period = 0;
+    }
+}
+
+impl Pwm for PwmPin {
+    type Error = PwmError;
+
+    fn enable(&mut self, frequency: usize) -> Result<(), PwmError> {
+        if frequency == 0 {
+            return Err(PwmError::InvalidFrequency);
+        }
+
+        // Use the smallest scale which fits the period in the
+        // comparators, for the finest duty cycle.
+        let clock = super::CPU_FREQ.load(Ordering::Relaxed);
+        let limit = if self.channel.timer == 0 {
+            0x100
+        } else {
+            0x1_0000
+        };
+        let (scale, period) = (0..16)
+            .map(|scale| (scale, (clock >> scale) / frequency))
+            .find(|&(_, period)| period < limit)
+            .filter(|&(_, period)| period >= 2)
+            .ok_or(PwmError::InvalidFrequency)?;
+        let period = period as u32;
+
+        pwm::claim(self.channel.pin, frequency)?;
+        let enabled = with_timer(self.channel.timer, |timer| {
+            if timer.period() != Some((scale, period)) {
+                timer.set_period(scale, period);
+            }
+        });
+        if enabled.is_none() {
+            pwm::release(self.channel.pin);
+            return Err(PwmError::TimerUnavailable);
+        }
+        if pin_op::<PwmOp>(self.channel.pin, ()).is_none() {
+            self.stop();
+            return Err(PwmError::PinInUse);
+        }
+        self.period = period;
+        Ok(())
+    }
+
+    fn disable(&mut self) -> Result<(), PwmError> {
+        self.stop();
+        Ok(())
+    }
+
+    fn max_duty(&self) -> usize {
+        self.period as usize
+    }
+
+    fn set_duty(&mut self, duty: usize) -> Result<(), PwmError> {
+        if self.period == 0 {
+            return Err(PwmError::NotEnabled);
+        }
+        let channel = self.channel.channel;
+        let duty = duty.min(self.period as usize) as u32;
+        with_timer(self.channel.timer, |timer| timer.set_duty(channel, duty))
+            .ok_or(PwmError::TimerUnavailable)
+    }
+}
+
+/// The operations common to the three PWM blocks
+trait PwmTimer {
+    fn set_period(&mut self, scale: u32, period: u32);
+    fn period(&self) -> Option<(u32, u32)>;
+    fn set_duty(&mut self, output: usize, duty: u32);
+}
+
+impl<const N: usize> PwmTimer for PwmBlock<N> {
+    fn set_period(&mut self, scale: u32, period: u32) {
+        PwmBlock::set_period(self, scale, period);
+    }
+
+    fn period(&self) -> Option<(u32, u32)> {
+        PwmBlock::period(self)
+    }
+
+    fn set_duty(&mut self, output: usize, duty: u32) {
+        PwmBlock::set_duty(self, output, duty);
+    }
+}
+
+/// Run an operation on one of the PWM blocks
+///
+/// The block is taken the first time it is used. Returns `None` if
+/// it is in use elsewhere.
+fn with_timer<R>(timer: usize, f: impl FnOnce(&mut dyn PwmTimer) -> R) -> Option<R> {
+    fn with<T: PwmTimer, R>(
+        timer: &Mutex<Option<T>>,
+        get: fn() -> Option<T>,
+        f: impl FnOnce(&mut dyn PwmTimer) -> R,
+    ) -> Option<R> {
+        let mut timer = timer.lock();
+        if timer.is_none() {
+            *timer = get();
+        }
+        timer.as_mut().map(|timer| f(timer))
+    }
+
+    match timer {
+        0 => with(&PWM_0, PwmBlock::<0>::get, f),
+        1 => with(&PWM_1, PwmBlock::<1>::get, f),
+        2 => with(&PWM_2, PwmBlock::<2>::get, f),
+        _ => None,
+    }
+}
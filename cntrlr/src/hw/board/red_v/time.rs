@@ -3,9 +3,15 @@
 
 //! Time functionality specific to the Red-V board
 
-use crate::{sync::Value, task::WakerSet};
+use crate::{
+    hw::mcu::sifive::fe310g002::{Aon, Wdog},
+    sync::{without_interrupts, Flag, Mutex, MutexGuard, Value},
+    task::WakerSet,
+    time::{self, DeadlineError, TickMode, TickModeError},
+};
 use core::{
     future::{poll_fn, Future},
+    ptr::{read_volatile, write_volatile},
     sync::atomic::Ordering,
     task::Poll,
 };
@@ -14,11 +20,22 @@ use core::{
 ///
 /// This is a wrapping counter. On the Red-V board, it is 32-bits
 ///
-/// # Note
-/// This count may become inaccurate if the sytem clock is modified
-/// after startup.
+/// The count is taken from the CLINT timer, which counts the
+/// 32.768kHz low-frequency clock and is unaffected by changes to the
+/// system clock.
 pub fn millis() -> usize {
-    MILLIS.load(Ordering::Relaxed)
+    ticks_to_millis(mtime()) as usize
+}
+
+/// Retrieve the number of microseconds the device has been running
+///
+/// This is a wrapping counter. On the Red-V board, it is 32-bits,
+/// and wraps after about 71 minutes.
+///
+/// The count is taken from the CLINT timer, like [`millis`], so it
+/// advances in steps of about 31 microseconds.
+pub fn micros() -> usize {
+    (mtime() * 1_000_000 / TICKS_PER_SEC) as usize
 }
 
 /// Sleep this task for some number of milliseconds
@@ -36,33 +53,322 @@ pub fn sleep_millis(mut duration: usize) -> impl Future<Output = ()> {
             duration -= elapsed;
             start = current;
             TIMER_WAKERS.add(ctx.waker().clone());
+            note_deadline(current.wrapping_add(duration));
             Poll::Pending
         }
     })
 }
 
-static MILLIS: Value = Value::new(0);
-static FRACT: Value = Value::new(0);
-static TIMER_WAKERS: WakerSet = WakerSet::new();
+/// Make sure the timer interrupt wakes the board at a timer's deadline
+pub fn note_timer(deadline: usize) {
+    note_deadline(deadline);
+}
 
-/// Interrupt functino for the clint timer
-pub extern "C" fn timer_intr() {
+/// Set how the timer interrupt is scheduled
+///
+/// The CLINT timer is 64 bits, so any period can be used, and
+/// tickless mode does not interrupt at all while no task is
+/// sleeping.
+pub fn set_tick_mode(mode: TickMode) -> Result<(), TickModeError> {
+    without_interrupts(|| {
+        match mode {
+            TickMode::Periodic(0) => return Err(TickModeError::InvalidPeriod),
+            TickMode::Periodic(period) => {
+                PERIOD.store(period, Ordering::Relaxed);
+                TICKLESS.store(false, Ordering::Relaxed);
+            }
+            TickMode::Tickless => TICKLESS.store(true, Ordering::Relaxed),
+        }
+        schedule_tick(ticks_to_millis(mtime()));
+        Ok(())
+    })
+}
+
+/// Busy-wait for at least some number of core clock cycles
+///
+/// This counts cycles with the `mcycle` CSR, and does not depend on
+/// interrupts or the CLINT timer, so it can be used from board init
+/// code and interrupt handlers.
+pub fn delay_cycles(cycles: usize) {
     #[cfg(mcu = "fe310g002")]
     unsafe {
-        // We don't bother with MTIMEHI - if we overflow that far
-        // things are pretty messed up anyway.
-        const MTIMELO: *mut u32 = 0x0200_BFF8 as _;
-        asm!("amoadd.w {}, {}, ({})", out(reg) _, in(reg) -33, in(reg) MTIMELO);
+        let start: usize;
+        asm!("csrr {}, mcycle", out(reg) start);
+        loop {
+            let current: usize;
+            asm!("csrr {}, mcycle", out(reg) current);
+            if current.wrapping_sub(start) >= cycles {
+                break;
+            }
+        }
+    }
+}
+
+/// Busy-wait for at least some number of microseconds
+///
+/// Before the core clock is set, this assumes the 16MHz external
+/// oscillator selected by the bootloader.
+pub fn delay_us_blocking(us: usize) {
+    let clock = match super::CPU_FREQ.load(Ordering::Relaxed) {
+        0 => 16_000_000,
+        clock => clock,
+    };
+    delay_cycles(us.saturating_mul(clock / 1_000_000));
+}
+
+/// Measure the core clock against the clock which drives `mtime`
+///
+/// Returns `None` if the core clock has not been set. Otherwise,
+/// returns the configured and measured core clock.
+pub fn measure_core_clock() -> Option<(usize, usize)> {
+    let clock = super::CPU_FREQ.load(Ordering::Relaxed);
+    if clock == 0 {
+        return None;
     }
-    let mut millis = MILLIS.load(Ordering::Relaxed);
-    millis = millis.wrapping_add(1);
-    let mut fract = FRACT.load(Ordering::Relaxed);
-    fract += 33000 - 32768;
-    if fract >= 1000 {
-        millis = millis.wrapping_add(1);
-        fract -= 1000;
+
+    // Start counting on an mtime edge
+    let start = mtime();
+    let mut edge = mtime();
+    while edge == start {
+        edge = mtime();
     }
-    FRACT.store(fract, Ordering::Relaxed);
-    MILLIS.store(millis, Ordering::Relaxed);
+
+    #[cfg(mcu = "fe310g002")]
+    unsafe {
+        let start: usize;
+        let end: usize;
+        asm!("csrr {}, mcycle", out(reg) start);
+        while mtime() - edge < TICKS_PER_SEC / 10 {}
+        asm!("csrr {}, mcycle", out(reg) end);
+        Some((clock, end.wrapping_sub(start) * 10))
+    }
+
+    #[cfg(not(mcu = "fe310g002"))]
+    None
+}
+
+/// Arm the watchdog to reset the board after some number of milliseconds
+///
+/// The watchdog counts the 32.768kHz low-frequency clock, so the
+/// deadline is rounded up to a tick of that clock, or of its scaled
+/// count for deadlines longer than about two seconds. Returns
+/// [`DeadlineError::InUse`] if the watchdog's handle is held
+/// elsewhere.
+pub fn arm_strict_deadline(duration: usize) -> Result<(), DeadlineError> {
+    let (scale, compare) = watchdog_compare(duration).ok_or(DeadlineError::TooLong)?;
+    let mut strict = STRICT_DEADLINE.lock();
+    if strict.is_some() {
+        return Err(DeadlineError::InUse);
+    }
+    let mut wdog = Wdog::get().ok_or(DeadlineError::InUse)?;
+    wdog.enable(scale, compare);
+    *strict = Some(wdog);
+    Ok(())
+}
+
+/// The watchdog scale and compare value for a timeout in milliseconds
+///
+/// The timeout is rounded up to a tick of the scaled count. Returns
+/// `None` if it is too long for the watchdog to count.
+pub(crate) fn watchdog_compare(duration: usize) -> Option<(u32, u16)> {
+    let ticks = millis_to_ticks(duration as u64);
+    let scale = (0..16).find(|scale| (ticks + (1 << scale) - 1) >> scale <= u16::MAX as u64)?;
+    Some((scale, ((ticks + (1 << scale) - 1) >> scale) as u16))
+}
+
+/// Disarm the watchdog armed by [`arm_strict_deadline`]
+pub fn disarm_strict_deadline() {
+    if let Some(mut wdog) = STRICT_DEADLINE.lock().take() {
+        wdog.disable();
+    }
+}
+
+/// Whether the last reset was caused by the watchdog
+///
+/// This reads the reset cause from the PMU. [`reboot`](super::reboot)
+/// also resets through the watchdog.
+pub fn reset_by_watchdog() -> bool {
+    const PMUCAUSE: *const u32 = 0x1000_0144 as _;
+    unsafe { (read_volatile(PMUCAUSE) >> 8) & 0x3 == 2 }
+}
+
+static STRICT_DEADLINE: Mutex<Option<Wdog>> = Mutex::new(None);
+
+/// The real-time clock of the Red-V
+///
+/// This is the RTC in the always-on domain, scaled to count
+/// seconds. It reads as zero, and cannot be set, while the always-on
+/// domain's handle is held elsewhere.
+pub struct Rtc(Option<Aon>);
+
+/// The board's real-time clock
+pub fn rtc() -> MutexGuard<'static, Rtc> {
+    static RTC: Mutex<Rtc> = Mutex::new(Rtc(None));
+    let mut rtc = RTC.lock();
+    if rtc.0.is_none() {
+        rtc.0 = Aon::get().map(|mut aon| {
+            aon.set_rtc_compare(u32::MAX);
+            if !aon.rtc_enabled() {
+                aon.enable_rtc(true, RTC_SCALE);
+            }
+            aon
+        });
+    }
+    rtc
+}
+
+impl time::Rtc for Rtc {
+    type AlarmFuture<'a> = impl Future<Output = ()> + 'a;
+
+    fn seconds(&self) -> u64 {
+        self.0
+            .as_ref()
+            .map_or(0, |aon| aon.rtc_count() >> RTC_SCALE)
+    }
+
+    fn set_seconds(&mut self, seconds: u64) {
+        if let Some(aon) = self.0.as_mut() {
+            aon.enable_rtc(false, RTC_SCALE);
+            aon.set_rtc_count(seconds << RTC_SCALE);
+            aon.enable_rtc(true, RTC_SCALE);
+        }
+    }
+
+    fn alarm<'a>(&'a mut self, seconds: u64) -> Self::AlarmFuture<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            if self.seconds() >= seconds {
+                return Poll::Ready(());
+            }
+            RTC_WAKERS.add(ctx.waker().clone());
+            if let Some(aon) = self.0.as_mut() {
+                // The scaled count is the low 32 bits of the seconds
+                aon.set_rtc_compare(seconds as u32);
+            }
+            Poll::Pending
+        })
+    }
+}
+
+/// The RTC counts the 32.768kHz low-frequency clock, so this scales it to seconds
+const RTC_SCALE: u32 = 15;
+
+static RTC_WAKERS: WakerSet = WakerSet::new();
+
+/// Interrupt function for the RTC
+pub extern "C" fn rtc_intr() {
+    const RTCCMP0: *mut u32 = 0x1000_0060 as _;
+
+    // The interrupt stays pending until the compare value is above
+    // the count. The woken alarm sets it again if it is not done.
+    unsafe { write_volatile(RTCCMP0, u32::MAX) };
+    RTC_WAKERS.wake();
+}
+
+const MTIMECMP_LO: *mut u32 = 0x0200_4000 as _;
+const MTIMECMP_HI: *mut u32 = 0x0200_4004 as _;
+const MTIME_LO: *mut u32 = 0x0200_BFF8 as _;
+const MTIME_HI: *mut u32 = 0x0200_BFFC as _;
+
+const TICKS_PER_SEC: u64 = 32768;
+
+fn mtime() -> u64 {
+    unsafe {
+        loop {
+            let hi = read_volatile(MTIME_HI);
+            let lo = read_volatile(MTIME_LO);
+            if read_volatile(MTIME_HI) == hi {
+                return (hi as u64) << 32 | lo as u64;
+            }
+        }
+    }
+}
+
+fn mtimecmp() -> u64 {
+    unsafe { (read_volatile(MTIMECMP_HI) as u64) << 32 | read_volatile(MTIMECMP_LO) as u64 }
+}
+
+fn set_mtimecmp(ticks: u64) {
+    unsafe {
+        // Keep the compare value in the future while it is only
+        // partly written.
+        write_volatile(MTIMECMP_HI, u32::MAX);
+        write_volatile(MTIMECMP_LO, ticks as u32);
+        write_volatile(MTIMECMP_HI, (ticks >> 32) as u32);
+    }
+}
+
+fn ticks_to_millis(ticks: u64) -> u64 {
+    ticks * 1000 / TICKS_PER_SEC
+}
+
+fn millis_to_ticks(millis: u64) -> u64 {
+    (millis * TICKS_PER_SEC + 999) / 1000
+}
+
+/// Set the next timer interrupt in the current tick mode
+///
+/// `now` is the current time, in milliseconds. This must be called
+/// with interrupts disabled.
+fn schedule_tick(now: u64) {
+    let next = if TICKLESS.load(Ordering::Relaxed) {
+        match NEXT_DEADLINE.load(Ordering::Relaxed) {
+            NO_WAKE => None,
+            // A deadline in the past wraps around to a very long time.
+            deadline => match deadline.wrapping_sub(now as usize) {
+                remaining if remaining > usize::MAX / 2 => Some(now),
+                remaining => Some(now + remaining as u64),
+            },
+        }
+    } else {
+        let period = PERIOD.load(Ordering::Relaxed) as u64;
+        Some((now / period + 1) * period)
+    };
+    set_mtimecmp(next.map_or(u64::MAX, millis_to_ticks));
+}
+
+/// Record the end of a sleep
+///
+/// In tickless mode, the next interrupt is moved earlier if the
+/// sleep ends before it.
+fn note_deadline(deadline: usize) {
+    without_interrupts(|| {
+        let now = ticks_to_millis(mtime());
+        let next = NEXT_DEADLINE.load(Ordering::Relaxed);
+        if next != NO_WAKE && next.wrapping_sub(now as usize) <= deadline.wrapping_sub(now as usize)
+        {
+            return;
+        }
+        NEXT_DEADLINE.store(deadline, Ordering::Relaxed);
+
+        if TICKLESS.load(Ordering::Relaxed) {
+            let at = now + deadline.wrapping_sub(now as usize) as u64;
+            if millis_to_ticks(at) < mtimecmp() {
+                set_mtimecmp(millis_to_ticks(at));
+            }
+        }
+    });
+}
+
+static PERIOD: Value = Value::new(1);
+static TICKLESS: Flag = Flag::new(false);
+static NEXT_DEADLINE: Value = Value::new(NO_WAKE);
+static TIMER_WAKERS: WakerSet = WakerSet::new();
+
+const NO_WAKE: usize = usize::MAX;
+
+/// Interrupt functino for the clint timer
+pub extern "C" fn timer_intr() {
+    // Every sleeping task is woken, and notes its deadline again
+    // when it is next polled. Timers stay queued until they expire,
+    // so the earliest is noted here instead.
+    let now = ticks_to_millis(mtime());
+    NEXT_DEADLINE.store(
+        crate::time::wake_timers(now as usize).unwrap_or(NO_WAKE),
+        Ordering::Relaxed,
+    );
+    schedule_tick(now);
     TIMER_WAKERS.wake();
 }
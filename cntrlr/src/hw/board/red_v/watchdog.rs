@@ -0,0 +1,57 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Watchdog functionality specific to the Red-V board
+//!
+//! The watchdog in the always-on domain counts the 32.768kHz
+//! low-frequency clock.
+
+use crate::{
+    hw::mcu::sifive::fe310g002::Wdog,
+    sync::{without_interrupts, Mutex},
+    time::Duration,
+    watchdog::WatchdogError,
+};
+
+/// Enable the watchdog, or change its timeout
+///
+/// The timeout is rounded up to a tick of the low-frequency clock,
+/// or of its scaled count for timeouts longer than about two
+/// seconds. Returns [`WatchdogError::InUse`] if the watchdog's handle
+/// is held elsewhere.
+pub fn enable(timeout: Duration) -> Result<(), WatchdogError> {
+    let (scale, compare) =
+        super::time::watchdog_compare(timeout.as_millis()).ok_or(WatchdogError::TooLong)?;
+    without_interrupts(|| {
+        let mut watchdog = WATCHDOG.lock();
+        if watchdog.is_none() {
+            *watchdog = Some(Wdog::get().ok_or(WatchdogError::InUse)?);
+        }
+        if let Some(wdog) = watchdog.as_mut() {
+            wdog.enable(scale, compare);
+        }
+        Ok(())
+    })
+}
+
+/// Disable the watchdog
+pub fn disable() {
+    without_interrupts(|| {
+        if let Some(mut wdog) = WATCHDOG.lock().take() {
+            wdog.disable();
+        }
+    });
+}
+
+/// Feed the watchdog, restarting its count
+pub fn feed() {
+    // Interrupts are disabled while the lock is held, so feeding
+    // from an interrupt cannot deadlock.
+    without_interrupts(|| {
+        if let Some(wdog) = WATCHDOG.lock().as_mut() {
+            wdog.feed();
+        }
+    });
+}
+
+static WATCHDOG: Mutex<Option<Wdog>> = Mutex::new(None);
@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Encoder input specific to the Teensy 3.0 board
+
+use super::pwm;
+use crate::{
+    hw::{
+        board::teensy_common::{
+            digital::MuxOp,
+            input::{self, EncoderError},
+        },
+        mcu::kinetis::mk20dx128::Ftm,
+    },
+    sync::Mutex,
+};
+
+/// Encoder 0 is FTM1. This MCU has no FTM2.
+static FTM_1: Mutex<Option<Ftm<1>>> = Mutex::new(None);
+
+/// Start decoding an encoder on a pair of pins
+pub(crate) fn start_encoder(
+    pin_a: usize,
+    pin_b: usize,
+    filter: u32,
+) -> Result<usize, EncoderError> {
+    let encoder = match (pin_a, pin_b) {
+        (3, 4) | (16, 17) => 0,
+        _ => return Err(EncoderError::InvalidPins),
+    };
+    input::start(&FTM_1, &pwm::FTM_1, filter)?;
+
+    // PTA12 and PTA13 use mux 7, and PTB0 and PTB1 use mux 6
+    let mux = if pin_a == 3 { 7 } else { 6 };
+    if super::digital::pin_op::<MuxOp>(pin_a, mux).is_none()
+        || super::digital::pin_op::<MuxOp>(pin_b, mux).is_none()
+    {
+        stop_encoder(encoder);
+        return Err(EncoderError::PinInUse);
+    }
+    Ok(encoder)
+}
+
+/// The low 16 bits of an encoder's position
+pub(crate) fn encoder_count(_encoder: usize) -> u16 {
+    input::count(&FTM_1)
+}
+
+/// Reset an encoder's counter to zero
+pub(crate) fn reset_encoder(_encoder: usize) {
+    input::reset(&FTM_1);
+}
+
+/// Stop decoding an encoder, and release its timer
+pub(crate) fn stop_encoder(_encoder: usize) {
+    input::stop(&FTM_1, &pwm::FTM_1);
+}
+
+/// Interrupt function for FTM1
+pub extern "C" fn ftm_1_intr() {
+    input::overflow_intr(0, 0x4003_9000);
+}
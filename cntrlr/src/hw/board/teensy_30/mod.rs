@@ -5,16 +5,23 @@
 
 use super::teensy_common::SetClockError;
 use crate::hw::mcu::kinetis::mk20dx128::{
-    Clock, Mcg, Osc, OscRange, PeripheralClockSource, Sim, SysTick, UsbClockSource, Watchdog,
+    Clock, Lptmr, Mcg, Osc, OscRange, PeripheralClockSource, Pit, Sim, SysTick, UsbClockSource,
+    Watchdog,
 };
 use core::{
     ptr::write_volatile,
     sync::atomic::{AtomicUsize, Ordering},
 };
 
+pub mod analog;
 pub mod digital;
+pub mod input;
 pub mod io;
+pub mod pwm;
 pub mod time;
+pub mod touch;
+pub mod vbat;
+pub mod watchdog;
 
 static CPU_FREQ: AtomicUsize = AtomicUsize::new(0);
 static BUS_FREQ: AtomicUsize = AtomicUsize::new(0);
@@ -39,6 +46,10 @@ pub fn set_clock(clock: usize) -> Result<(), SetClockError> {
     let mut mcg = Mcg::get().ok_or(SetClockError::McgInUse)?;
     let mut sim = Sim::get().ok_or(SetClockError::SimInUse)?;
 
+    // The monitors would see the PLL stop while it is reconfigured
+    mcg.enable_clock_monitor(false);
+    mcg.enable_loss_of_lock_interrupt(false);
+
     // First, switch back to a slow clock so it's safe to update dividers
     let fbe = match mcg.clock() {
         Clock::Fei(fei) => {
@@ -67,23 +78,61 @@ pub fn set_clock(clock: usize) -> Result<(), SetClockError> {
     sim.set_usb_source(UsbClockSource::PllFll);
     sim.set_peripheral_source(PeripheralClockSource::Pll);
 
+    // Fall back to the internal reference if the crystal or PLL fails
+    mcg.clear_clock_lost();
+    mcg.clear_lock_lost();
+    mcg.enable_clock_monitor(true);
+    mcg.enable_loss_of_lock_interrupt(true);
+
     // Reset SysTick for new clock rate.
     if let Some(mut systick) = SysTick::get() {
         systick.enable(false);
-        let reload = (clock / 1000) - 1;
-        systick.set_reload_value(reload as u32);
+        let reload = super::teensy_common::time::set_tick_clock(clock);
+        systick.set_reload_value(reload);
         systick.set_current_value(0);
         systick.enable(true);
     }
 
     CPU_FREQ.store(clock, Ordering::Relaxed);
+    super::teensy_common::time::set_micros_clock(clock * core as usize / bus as usize);
     BUS_FREQ.store(clock * core as usize / bus as usize, Ordering::Relaxed);
     Ok(())
 }
 
+/// Interrupt function for the MCG
+///
+/// If the crystal or the PLL has failed, this switches to the
+/// internal reference clock and reports the fault. See
+/// [`crate::clock`].
+pub extern "C" fn mcg_intr() {
+    use super::teensy_common::clock::{fall_back, FALLBACK_FREQ};
+
+    let (fault, core) = match fall_back() {
+        Some(fault) => fault,
+        None => return,
+    };
+    let clock = FALLBACK_FREQ / core;
+    let old_clock = CPU_FREQ.load(Ordering::Relaxed) as u64;
+    let bus = BUS_FREQ.load(Ordering::Relaxed) as u64 * clock as u64 / old_clock;
+
+    if let Some(mut systick) = SysTick::get() {
+        systick.enable(false);
+        let reload = super::teensy_common::time::set_tick_clock(clock);
+        systick.set_reload_value(reload);
+        systick.set_current_value(0);
+        systick.enable(true);
+    }
+
+    CPU_FREQ.store(clock, Ordering::Relaxed);
+    super::teensy_common::time::set_micros_clock(bus as usize);
+    BUS_FREQ.store(bus as usize, Ordering::Relaxed);
+    crate::clock::notify(fault);
+}
+
 /// Early startup for the Teensy 3.0 board
 ///
-/// Disables the watchdog.
+/// Disables the watchdog. Applications can enable it again with
+/// [`crate::watchdog::enable`].
 ///
 /// This will be included automatically if you are using the standard
 /// Cntrlr runtime. It should be invoked directly as part of startup
@@ -119,9 +168,26 @@ pub unsafe extern "C" fn init() {
 
     set_clock(48_000_000).expect("Could not set core clock at init");
 
+    // Keep time across low-power modes which halt the systick
+    if let Some(lptmr) = Sim::get()
+        .as_mut()
+        .and_then(Sim::enable_peripheral::<Lptmr>)
+    {
+        super::teensy_common::time::enable_backup_tick(lptmr);
+    }
+
+    // Count microseconds on the PIT. Software PWM takes the handle
+    // from here to start its own channel.
+    if let Some(mut pit) = Sim::get().as_mut().and_then(Sim::enable_peripheral::<Pit>) {
+        super::teensy_common::time::enable_micros(&mut pit);
+        *time::PIT.lock() = Some(pit);
+    }
+
     /// TODO: Create an NVIC peripheral
     const NVIC_ISER: *mut u32 = 0xE000_E100 as *mut _;
-    for intr in &[12, 16, 18, 20] {
+    for intr in &[
+        0, 1, 2, 4, 11, 12, 16, 18, 20, 22, 26, 28, 30, 35, 38, 39, 40, 41, 42, 43, 44,
+    ] {
         let reg = intr / 32;
         let bit = intr % 32;
 
@@ -129,7 +195,7 @@ pub unsafe extern "C" fn init() {
     }
 }
 
-use crate::runtime::unused_interrupt;
+use crate::runtime::{arm_fault, unused_interrupt};
 
 /// The Teensy 3.0 interrupt table
 ///
@@ -138,52 +204,52 @@ use crate::runtime::unused_interrupt;
 #[cfg_attr(board = "teensy_30", link_section = ".__CNTRLR_INTERRUPTS")]
 #[cfg_attr(board = "teensy_30", export_name = "__cntrlr_interrupts")]
 pub static INTERRUPTS: [unsafe extern "C" fn(); 46] = [
-    unused_interrupt,  // 000
-    unused_interrupt,  // 001
-    unused_interrupt,  // 002
-    unused_interrupt,  // 003
-    unused_interrupt,  // 004
-    unused_interrupt,  // 005
-    unused_interrupt,  // 006
-    unused_interrupt,  // 007
-    unused_interrupt,  // 008
-    unused_interrupt,  // 009
-    unused_interrupt,  // 010
-    unused_interrupt,  // 011
-    io::spi_1_intr,    // 012
-    unused_interrupt,  // 013
-    unused_interrupt,  // 014
-    unused_interrupt,  // 015
-    io::serial_1_intr, // 016
-    unused_interrupt,  // 017
-    io::serial_2_intr, // 018
-    unused_interrupt,  // 019
-    io::serial_3_intr, // 020
-    unused_interrupt,  // 021
-    unused_interrupt,  // 022
-    unused_interrupt,  // 023
-    unused_interrupt,  // 024
-    unused_interrupt,  // 025
-    unused_interrupt,  // 026
-    unused_interrupt,  // 027
-    unused_interrupt,  // 028
-    unused_interrupt,  // 029
-    unused_interrupt,  // 030
-    unused_interrupt,  // 031
-    unused_interrupt,  // 032
-    unused_interrupt,  // 033
-    unused_interrupt,  // 034
-    unused_interrupt,  // 035
-    unused_interrupt,  // 036
-    unused_interrupt,  // 037
-    unused_interrupt,  // 038
-    unused_interrupt,  // 039
-    unused_interrupt,  // 040
-    unused_interrupt,  // 041
-    unused_interrupt,  // 042
-    unused_interrupt,  // 043
-    unused_interrupt,  // 044
-    unused_interrupt,  // 045
+    io::dma_0_intr,       // 000
+    io::dma_1_intr,       // 001
+    io::dma_2_intr,       // 002
+    unused_interrupt,     // 003
+    io::dma_error_intr,   // 004
+    unused_interrupt,     // 005
+    unused_interrupt,     // 006
+    unused_interrupt,     // 007
+    unused_interrupt,     // 008
+    unused_interrupt,     // 009
+    unused_interrupt,     // 010
+    io::i2c_1_intr,       // 011
+    io::spi_1_intr,       // 012
+    unused_interrupt,     // 013
+    unused_interrupt,     // 014
+    unused_interrupt,     // 015
+    io::serial_1_intr,    // 016
+    unused_interrupt,     // 017
+    io::serial_2_intr,    // 018
+    unused_interrupt,     // 019
+    io::serial_3_intr,    // 020
+    unused_interrupt,     // 021
+    analog::adc_0_intr,   // 022
+    unused_interrupt,     // 023
+    unused_interrupt,     // 024
+    unused_interrupt,     // 025
+    input::ftm_1_intr,    // 026
+    unused_interrupt,     // 027
+    time::rtc_intr,       // 028
+    unused_interrupt,     // 029
+    pwm::pit_intr,        // 030
+    unused_interrupt,     // 031
+    unused_interrupt,     // 032
+    unused_interrupt,     // 033
+    unused_interrupt,     // 034
+    io::usb_intr,         // 035
+    unused_interrupt,     // 036
+    unused_interrupt,     // 037
+    mcg_intr,             // 038
+    time::lptmr_intr,     // 039
+    digital::port_a_intr, // 040
+    digital::port_b_intr, // 041
+    digital::port_c_intr, // 042
+    digital::port_d_intr, // 043
+    digital::port_e_intr, // 044
+    unused_interrupt,     // 045
 ];
 
 /// The Teensy 3.0 exception table
@@ -194,10 +260,10 @@ pub static INTERRUPTS: [unsafe extern "C" fn(); 46] = [
 #[cfg_attr(board = "teensy_30", export_name = "__cntrlr_exceptions")]
 pub static ARM_EXCEPTIONS: [unsafe extern "C" fn(); 14] = [
     unused_interrupt,
-    unused_interrupt,
-    unused_interrupt,
-    unused_interrupt,
-    unused_interrupt,
+    arm_fault,
+    arm_fault,
+    arm_fault,
+    arm_fault,
     unused_interrupt,
     unused_interrupt,
     unused_interrupt,
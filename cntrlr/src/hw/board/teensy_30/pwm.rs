@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! PWM functionality specific to the Teensy 3.0 board
+
+use crate::{
+    hw::{
+        board::teensy_common::{
+            digital::MuxOp,
+            pwm::{self, PwmBoard, PwmPin, PwmTimer},
+            soft_pwm,
+        },
+        mcu::kinetis::{
+            mk20dx128::{Ftm, Sim},
+            peripheral::sim::GatedPeripheral,
+            Mk20Dx128,
+        },
+    },
+    pwm::{PwmChannel, PwmError},
+    sync::Mutex,
+};
+use core::sync::atomic::Ordering;
+
+pub use crate::hw::board::teensy_common::soft_pwm::pit_intr;
+
+/// Timers 0, 1, and so on are FTM0, FTM1, and so on.
+static PWM_CHANNELS: [PwmChannel; 10] = [
+    PwmChannel::new(22, 0, 0),
+    PwmChannel::new(23, 0, 1),
+    PwmChannel::new(9, 0, 2),
+    PwmChannel::new(10, 0, 3),
+    PwmChannel::new(6, 0, 4),
+    PwmChannel::new(20, 0, 5),
+    PwmChannel::new(21, 0, 6),
+    PwmChannel::new(5, 0, 7),
+    PwmChannel::new(3, 1, 0),
+    PwmChannel::new(4, 1, 1),
+];
+
+static FTM_0: Mutex<Option<Ftm<0>>> = Mutex::new(None);
+// Encoders take FTM1 from here while no channel is in use
+pub(super) static FTM_1: Mutex<Option<Ftm<1>>> = Mutex::new(None);
+
+/// Every PWM-capable pin on this board
+pub fn pwm_channels() -> &'static [PwmChannel] {
+    &PWM_CHANNELS
+}
+
+/// Get the hardware PWM output of a pin
+pub fn pwm_pin(pin: usize) -> Result<PwmPin<Timers>, PwmError> {
+    PwmPin::new(pin)
+}
+
+/// The hardware PWM timers of this board
+pub struct Timers;
+
+impl PwmBoard for Timers {
+    fn with_timer<R>(timer: usize, f: impl FnOnce(&mut dyn PwmTimer) -> R) -> Option<R> {
+        match timer {
+            0 => pwm::with_timer(&FTM_0, enable, f),
+            1 => pwm::with_timer(&FTM_1, enable, f),
+            _ => None,
+        }
+    }
+
+    fn timer_clock(_: usize) -> usize {
+        super::BUS_FREQ.load(Ordering::Relaxed)
+    }
+
+    fn connect_pin(pin: usize) -> bool {
+        // Pins on ports A and B use mux 3, and pins on ports C and D
+        // use mux 4.
+        let mux = match pin {
+            3 | 4 => 3,
+            _ => 4,
+        };
+        super::digital::pin_op::<MuxOp>(pin, mux).is_some()
+    }
+}
+
+fn enable<P: GatedPeripheral<Mk20Dx128>>() -> Option<P> {
+    Sim::get()?.enable_peripheral()
+}
+
+/// Set the duty cycle of any digital pin, with software PWM
+///
+/// See [`crate::pwm::soft_pwm_write`] for details.
+pub fn soft_pwm_write(pin: usize, duty: usize) -> Result<(), PwmError> {
+    soft_pwm::write(pin, duty, super::BUS_FREQ.load(Ordering::Relaxed), || {
+        super::time::PIT.lock().take()
+    })
+}
+
+/// Stop driving a pin with software PWM
+pub fn soft_pwm_release(pin: usize) {
+    soft_pwm::release(pin, super::BUS_FREQ.load(Ordering::Relaxed));
+}
+
+/// Set the frequency of every software PWM pin, in Hz
+pub fn set_soft_pwm_frequency(frequency: usize) -> Result<(), PwmError> {
+    soft_pwm::set_frequency(frequency, super::BUS_FREQ.load(Ordering::Relaxed))
+}
+
+/// Set the resolution of software PWM duty cycles, in bits
+pub fn set_soft_pwm_resolution(bits: u32) -> Result<(), PwmError> {
+    soft_pwm::set_resolution(bits, super::BUS_FREQ.load(Ordering::Relaxed))
+}
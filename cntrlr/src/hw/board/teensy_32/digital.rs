@@ -4,59 +4,75 @@
 //! Digital pin support specific to the Teensy 3.2
 
 use crate::{
-    digital::PinMode,
+    digital::{DriveStrength, Edge, FilterConfig, Led, PinConfig, PinMode, SlewRate},
     hw::{
-        board::teensy_common::digital::{ModeOp, PinOp, ReadOp, WriteOp},
+        board::teensy_common::digital::{
+            self, DriveOp, FilterOp, InterruptOp, ModeOp, OutputOp, PinOp, ReadOp, SlewOp, WriteOp,
+        },
         mcu::kinetis::mk20dx256::{Port, Sim},
     },
     sync::Once,
 };
 
-/// Invoke an operation on a pin.
-///
-/// This abstracts the mapping of Teensy pins to MCU pins, allowing
-/// operations to be defined generically and then invoked for any
-/// given pin.
-#[inline]
-pub fn pin_op<Op: PinOp>(pin: usize, arg: Op::Arg) -> Option<Op::Result> {
-    match pin {
-        0 => Op::do_op(port_b().and_then(|port| port.pin::<16>()), arg),
-        1 => Op::do_op(port_b().and_then(|port| port.pin::<17>()), arg),
-        2 => Op::do_op(port_d().and_then(|port| port.pin::<0>()), arg),
-        3 => Op::do_op(port_a().and_then(|port| port.pin::<12>()), arg),
-        4 => Op::do_op(port_a().and_then(|port| port.pin::<13>()), arg),
-        5 => Op::do_op(port_d().and_then(|port| port.pin::<7>()), arg),
-        6 => Op::do_op(port_d().and_then(|port| port.pin::<4>()), arg),
-        7 => Op::do_op(port_d().and_then(|port| port.pin::<2>()), arg),
-        8 => Op::do_op(port_d().and_then(|port| port.pin::<3>()), arg),
-        9 => Op::do_op(port_c().and_then(|port| port.pin::<3>()), arg),
-        10 => Op::do_op(port_c().and_then(|port| port.pin::<4>()), arg),
-        11 => Op::do_op(port_c().and_then(|port| port.pin::<6>()), arg),
-        12 => Op::do_op(port_c().and_then(|port| port.pin::<7>()), arg),
-        13 => Op::do_op(port_c().and_then(|port| port.pin::<5>()), arg),
-        14 => Op::do_op(port_d().and_then(|port| port.pin::<1>()), arg),
-        15 => Op::do_op(port_c().and_then(|port| port.pin::<0>()), arg),
-        16 => Op::do_op(port_b().and_then(|port| port.pin::<0>()), arg),
-        17 => Op::do_op(port_b().and_then(|port| port.pin::<1>()), arg),
-        18 => Op::do_op(port_b().and_then(|port| port.pin::<3>()), arg),
-        19 => Op::do_op(port_b().and_then(|port| port.pin::<2>()), arg),
-        20 => Op::do_op(port_d().and_then(|port| port.pin::<5>()), arg),
-        21 => Op::do_op(port_d().and_then(|port| port.pin::<6>()), arg),
-        22 => Op::do_op(port_c().and_then(|port| port.pin::<1>()), arg),
-        23 => Op::do_op(port_c().and_then(|port| port.pin::<2>()), arg),
-        24 => Op::do_op(port_a().and_then(|port| port.pin::<5>()), arg),
-        25 => Op::do_op(port_b().and_then(|port| port.pin::<19>()), arg),
-        26 => Op::do_op(port_e().and_then(|port| port.pin::<1>()), arg),
-        27 => Op::do_op(port_c().and_then(|port| port.pin::<9>()), arg),
-        28 => Op::do_op(port_c().and_then(|port| port.pin::<8>()), arg),
-        29 => Op::do_op(port_c().and_then(|port| port.pin::<10>()), arg),
-        30 => Op::do_op(port_c().and_then(|port| port.pin::<11>()), arg),
-        31 => Op::do_op(port_e().and_then(|port| port.pin::<0>()), arg),
-        32 => Op::do_op(port_b().and_then(|port| port.pin::<18>()), arg),
-        33 => Op::do_op(port_a().and_then(|port| port.pin::<4>()), arg),
-        34 => Op::do_op(port_a().and_then(|port| port.pin::<1>()), arg),
-        _ => None,
-    }
+pub(crate) use crate::hw::board::teensy_common::digital::{port_read, port_write};
+
+pin_table! {
+    D0 = 0 => port_b(1, 16),
+    D1 = 1 => port_b(1, 17),
+    D2 = 2 => port_d(3, 0),
+    D3 = 3 => port_a(0, 12),
+    D4 = 4 => port_a(0, 13),
+    D5 = 5 => port_d(3, 7),
+    D6 = 6 => port_d(3, 4),
+    D7 = 7 => port_d(3, 2),
+    D8 = 8 => port_d(3, 3),
+    D9 = 9 => port_c(2, 3),
+    D10 = 10 => port_c(2, 4),
+    D11 = 11 => port_c(2, 6),
+    D12 = 12 => port_c(2, 7),
+    D13 = 13 => port_c(2, 5),
+    D14 = 14 => port_d(3, 1),
+    D15 = 15 => port_c(2, 0),
+    D16 = 16 => port_b(1, 0),
+    D17 = 17 => port_b(1, 1),
+    D18 = 18 => port_b(1, 3),
+    D19 = 19 => port_b(1, 2),
+    D20 = 20 => port_d(3, 5),
+    D21 = 21 => port_d(3, 6),
+    D22 = 22 => port_c(2, 1),
+    D23 = 23 => port_c(2, 2),
+    D24 = 24 => port_a(0, 5),
+    D25 = 25 => port_b(1, 19),
+    D26 = 26 => port_e(4, 1),
+    D27 = 27 => port_c(2, 9),
+    D28 = 28 => port_c(2, 8),
+    D29 = 29 => port_c(2, 10),
+    D30 = 30 => port_c(2, 11),
+    D31 = 31 => port_e(4, 0),
+    D32 = 32 => port_b(1, 18),
+    D33 = 33 => port_a(0, 4),
+    D34 = 34 => port_a(0, 1),
+    ;
+    A0 = D14,
+    A1 = D15,
+    A2 = D16,
+    A3 = D17,
+    A4 = D18,
+    A5 = D19,
+    A6 = D20,
+    A7 = D21,
+    A8 = D22,
+    A9 = D23,
+    A10 = 34,
+    A11 = 35,
+    A12 = 36,
+    A13 = 37,
+    A14 = 40,
+    LED_PIN = D13,
+}
+
+pin_functions! {
+    HardwareCs<0> = [D9, D10, D15, D20, D21],
 }
 
 /// Set a digital pin high or low.
@@ -106,6 +122,120 @@ pub fn pin_mode(pin: usize, mode: PinMode) {
     pin_op::<ModeOp>(pin, mode);
 }
 
+/// The pin connected to the on-board LED
+///
+/// On the Teensy 3.2, the LED is on pin 13.
+#[inline]
+pub fn led_builtin() -> usize {
+    pins::LED_PIN
+}
+
+/// Turn the on-board LED on or off
+///
+/// This sets the LED pin as an output, so it does not need to be
+/// configured with [`pin_mode`] first.
+#[inline]
+pub fn led_write(on: bool) {
+    pin_mode(led_builtin(), PinMode::Output);
+    digital_write(led_builtin(), on);
+}
+
+/// Check whether the on-board LED is on
+///
+/// This reads the state the LED pin is set to output.
+#[inline]
+pub fn led_read() -> bool {
+    pin_op::<OutputOp>(led_builtin(), ()).unwrap_or(false)
+}
+
+/// Get the on-board LED
+#[inline]
+pub fn led() -> Led {
+    Led(())
+}
+
+/// Read back the configuration of a pin
+///
+/// Returns `None` if `pin` is not a valid pin, or its port cannot be
+/// enabled.
+pub fn dump_pin_config(pin: usize) -> Option<PinConfig> {
+    let (port, mcu_pin) = mcu_pin(pin)?;
+    let enabled = match port {
+        0 => port_a().is_some(),
+        1 => port_b().is_some(),
+        2 => port_c().is_some(),
+        3 => port_d().is_some(),
+        4 => port_e().is_some(),
+        _ => false,
+    };
+    if enabled {
+        Some(digital::pin_config(port, mcu_pin))
+    } else {
+        None
+    }
+}
+
+/// Set the output drive strength of a pin
+///
+/// Returns `false` if `pin` is not a valid pin, does not support the
+/// requested drive strength, or is in use by another module.
+#[inline]
+pub fn set_drive_strength(pin: usize, strength: DriveStrength) -> bool {
+    pin_op::<DriveOp>(pin, strength).unwrap_or(false)
+}
+
+/// Set the output slew rate of a pin
+///
+/// Returns `false` if `pin` is not a valid pin, does not support the
+/// requested slew rate, or is in use by another module.
+#[inline]
+pub fn set_slew_rate(pin: usize, rate: SlewRate) -> bool {
+    pin_op::<SlewOp>(pin, rate).unwrap_or(false)
+}
+
+/// Set the input filter of a pin
+///
+/// Returns `false` if `pin` is not a valid pin, does not support the
+/// requested filter, or is in use by another module.
+#[inline]
+pub fn set_input_filter(pin: usize, config: FilterConfig) -> bool {
+    pin_op::<FilterOp>(pin, config).unwrap_or(false)
+}
+
+/// Set which edges on a pin trigger its interrupt
+///
+/// Returns `false` if `pin` is not a valid pin or is in use by
+/// another module.
+#[inline]
+pub(crate) fn set_pin_interrupt(pin: usize, edge: Option<Edge>) -> bool {
+    pin_op::<InterruptOp>(pin, edge).is_some()
+}
+
+/// Interrupt function for port A
+pub extern "C" fn port_a_intr() {
+    digital::port_intr(0);
+}
+
+/// Interrupt function for port B
+pub extern "C" fn port_b_intr() {
+    digital::port_intr(1);
+}
+
+/// Interrupt function for port C
+pub extern "C" fn port_c_intr() {
+    digital::port_intr(2);
+}
+
+/// Interrupt function for port D
+pub extern "C" fn port_d_intr() {
+    digital::port_intr(3);
+}
+
+/// Interrupt function for port E
+pub extern "C" fn port_e_intr() {
+    digital::port_intr(4);
+}
+
 /// Port A
 ///
 /// The global instance of PORT A, used to share port ownership among
@@ -5,17 +5,33 @@
 
 use crate::{
     hw::{
-        board::teensy_common::io::{Serial, SerialError, Spi, SpiBoard, SpiError},
+        board::teensy_common::io::{
+            self as common, Can, CanBoard, CanError, I2c, I2cBoard, I2cError, Serial, SerialDma,
+            SerialError, Spi, SpiBoard, SpiError,
+        },
         mcu::kinetis::{
-            mk20dx256::{Cs, Pin, Sck, Sdi, Sdo, UartRx, UartTx},
+            mk20dx256::{
+                CanRx, CanTx, Cs, Dma, DmaMux, I2cScl, I2cSda, Pin, Sck, Sdi, Sdo, Sim, UartRx,
+                UartTx,
+            },
             Mk20Dx256,
         },
     },
-    io::{self, SpiOption},
-    sync::{Mutex, MutexGuard},
+    io::{self, codec::CrcKind, SpiOption},
+    sync::{AsyncMutex, AsyncMutexLock, Mutex, MutexGuard},
     task::WakerSet,
 };
-use core::{ptr::write_volatile, sync::atomic::Ordering};
+use core::{
+    ptr::{read_volatile, write_volatile},
+    sync::atomic::Ordering,
+};
+
+#[cfg(feature = "usb-serial")]
+use crate::hw::board::teensy_common::usb::{self, UsbSerial};
+#[cfg(feature = "usb-serial")]
+use core::future::Future;
+
+pub use crate::hw::board::teensy_common::usb::usb_intr;
 
 /// The pin used to recieve for serial 1
 pub type Serial1Rx = UartRx<Pin<'static, 1, 16>>;
@@ -68,6 +84,24 @@ pub type SpiCs = (
     Option<SpiCs4>,
 );
 
+/// The pin used as SCL for I2C 1
+pub type I2c1Scl = I2cScl<Pin<'static, 1, 2>>;
+
+/// The pin used as SDA for I2C 1
+pub type I2c1Sda = I2cSda<Pin<'static, 1, 3>>;
+
+/// The pin used as SCL for I2C 2
+pub type I2c2Scl = I2cScl<Pin<'static, 2, 10>>;
+
+/// The pin used as SDA for I2C 2
+pub type I2c2Sda = I2cSda<Pin<'static, 2, 11>>;
+
+/// The pin used to transmit for CAN 1
+pub type Can1Tx = CanTx<Pin<'static, 0, 12>>;
+
+/// The pin used to receive for CAN 1
+pub type Can1Rx = CanRx<Pin<'static, 0, 13>>;
+
 impl io::Serial for Serial<Mk20Dx256, Serial1Tx, Serial1Rx, 0> {
     type Error = SerialError;
 
@@ -108,6 +142,18 @@ impl io::Serial for Serial<Mk20Dx256, Serial1Tx, Serial1Rx, 0> {
         self.1 = None;
         Ok(())
     }
+
+    fn stats(&self) -> io::SerialStats {
+        self.2
+    }
+
+    fn reset_stats(&mut self) {
+        self.2 = io::SerialStats::new();
+    }
+
+    fn alt_pin_sets(&self) -> &'static [io::SerialPins] {
+        &[io::SerialPins { tx: 1, rx: 0 }]
+    }
 }
 
 impl io::Serial for Serial<Mk20Dx256, Serial2Tx, Serial2Rx, 1> {
@@ -150,6 +196,18 @@ impl io::Serial for Serial<Mk20Dx256, Serial2Tx, Serial2Rx, 1> {
         self.1 = None;
         Ok(())
     }
+
+    fn stats(&self) -> io::SerialStats {
+        self.2
+    }
+
+    fn reset_stats(&mut self) {
+        self.2 = io::SerialStats::new();
+    }
+
+    fn alt_pin_sets(&self) -> &'static [io::SerialPins] {
+        &[io::SerialPins { tx: 10, rx: 9 }]
+    }
 }
 
 impl io::Serial for Serial<Mk20Dx256, Serial3Tx, Serial3Rx, 2> {
@@ -192,6 +250,47 @@ impl io::Serial for Serial<Mk20Dx256, Serial3Tx, Serial3Rx, 2> {
         self.1 = None;
         Ok(())
     }
+
+    fn stats(&self) -> io::SerialStats {
+        self.2
+    }
+
+    fn reset_stats(&mut self) {
+        self.2 = io::SerialStats::new();
+    }
+
+    fn alt_pin_sets(&self) -> &'static [io::SerialPins] {
+        &[io::SerialPins { tx: 8, rx: 7 }]
+    }
+}
+
+impl SerialDma for Mk20Dx256 {
+    type Dma = Dma;
+
+    fn with_dma<F, U>(f: F) -> Result<U, SerialError>
+    where
+        F: FnOnce(&mut Dma, &mut DmaMux) -> U,
+    {
+        static DMA: Mutex<Option<(Dma, DmaMux)>> = Mutex::new(None);
+
+        let mut dma = DMA.lock();
+        if dma.is_none() {
+            let mut sim = Sim::get().ok_or(SerialError::SimInUse)?;
+            let controller = sim
+                .enable_peripheral::<Dma>()
+                .ok_or(SerialError::DmaInUse)?;
+            let mux = sim
+                .enable_peripheral::<DmaMux>()
+                .ok_or(SerialError::DmaInUse)?;
+            *dma = Some((controller, mux));
+        }
+        let (dma, mux) = dma.as_mut().ok_or(SerialError::DmaInUse)?;
+        Ok(f(dma, mux))
+    }
+
+    fn dma_wakers(channel: usize) -> &'static WakerSet {
+        &DMA_WAKERS[channel]
+    }
 }
 
 impl SpiBoard<SpiSdi, SpiSdo, SpiSck, SpiCs> for Spi<Mk20Dx256, SpiSdi, SpiSdo, SpiSck, SpiCs, 0> {
@@ -285,26 +384,132 @@ impl SpiBoard<SpiSdi, SpiSdo, SpiSck, SpiCs> for Spi<Mk20Dx256, SpiSdi, SpiSdo,
             _ => None,
         }
     }
+
+    fn hardware_cs_pins() -> &'static [usize] {
+        &[9, 10, 15, 20, 21]
+    }
+}
+
+impl I2cBoard<I2c1Scl, I2c1Sda> for I2c<Mk20Dx256, I2c1Scl, I2c1Sda, 0> {
+    fn scl() -> Result<I2c1Scl, I2cError> {
+        super::digital::port_b()
+            .ok_or(I2cError::PortInUse)
+            .and_then(|port| port.pin::<2>().ok_or(I2cError::PinInUse))
+            .map(|pin| pin.into_i2c_scl())
+    }
+
+    fn sda() -> Result<I2c1Sda, I2cError> {
+        super::digital::port_b()
+            .ok_or(I2cError::PortInUse)
+            .and_then(|port| port.pin::<3>().ok_or(I2cError::PinInUse))
+            .map(|pin| pin.into_i2c_sda())
+    }
+
+    fn clock_source() -> usize {
+        super::BUS_FREQ.load(Ordering::Relaxed)
+    }
+
+    fn wakers() -> &'static WakerSet {
+        &I2C_1_WAKERS
+    }
+}
+
+impl I2cBoard<I2c2Scl, I2c2Sda> for I2c<Mk20Dx256, I2c2Scl, I2c2Sda, 1> {
+    fn scl() -> Result<I2c2Scl, I2cError> {
+        super::digital::port_c()
+            .ok_or(I2cError::PortInUse)
+            .and_then(|port| port.pin::<10>().ok_or(I2cError::PinInUse))
+            .map(|pin| pin.into_i2c_scl())
+    }
+
+    fn sda() -> Result<I2c2Sda, I2cError> {
+        super::digital::port_c()
+            .ok_or(I2cError::PortInUse)
+            .and_then(|port| port.pin::<11>().ok_or(I2cError::PinInUse))
+            .map(|pin| pin.into_i2c_sda())
+    }
+
+    fn clock_source() -> usize {
+        super::BUS_FREQ.load(Ordering::Relaxed)
+    }
+
+    fn wakers() -> &'static WakerSet {
+        &I2C_2_WAKERS
+    }
+}
+
+impl CanBoard<Can1Tx, Can1Rx> for Can<Mk20Dx256, Can1Tx, Can1Rx, 0> {
+    fn tx() -> Result<Can1Tx, CanError> {
+        super::digital::port_a()
+            .ok_or(CanError::PortInUse)
+            .and_then(|port| port.pin::<12>().ok_or(CanError::PinInUse))
+            .map(|pin| pin.into_can_tx())
+    }
+
+    fn rx() -> Result<Can1Rx, CanError> {
+        super::digital::port_a()
+            .ok_or(CanError::PortInUse)
+            .and_then(|port| port.pin::<13>().ok_or(CanError::PinInUse))
+            .map(|pin| pin.into_can_rx())
+    }
+
+    fn clock_source() -> usize {
+        super::BUS_FREQ.load(Ordering::Relaxed)
+    }
+
+    fn wakers() -> &'static WakerSet {
+        &CAN_1_WAKERS
+    }
 }
 
 /// The first hardware serial port
-pub fn serial_1() -> MutexGuard<'static, Serial<Mk20Dx256, Serial1Tx, Serial1Rx, 0>> {
-    static SERIAL: Mutex<Serial<Mk20Dx256, Serial1Tx, Serial1Rx, 0>> = Mutex::new(Serial::new());
+pub fn serial_1() -> AsyncMutexLock<'static, Serial<Mk20Dx256, Serial1Tx, Serial1Rx, 0>> {
+    static SERIAL: AsyncMutex<Serial<Mk20Dx256, Serial1Tx, Serial1Rx, 0>> =
+        AsyncMutex::new(Serial::new());
     SERIAL.lock()
 }
 
 /// The second hardware serial port
-pub fn serial_2() -> MutexGuard<'static, Serial<Mk20Dx256, Serial2Tx, Serial2Rx, 1>> {
-    static SERIAL: Mutex<Serial<Mk20Dx256, Serial2Tx, Serial2Rx, 1>> = Mutex::new(Serial::new());
+pub fn serial_2() -> AsyncMutexLock<'static, Serial<Mk20Dx256, Serial2Tx, Serial2Rx, 1>> {
+    static SERIAL: AsyncMutex<Serial<Mk20Dx256, Serial2Tx, Serial2Rx, 1>> =
+        AsyncMutex::new(Serial::new());
     SERIAL.lock()
 }
 
 /// The third hardware serial port
-pub fn serial_3() -> MutexGuard<'static, Serial<Mk20Dx256, Serial3Tx, Serial3Rx, 2>> {
-    static SERIAL: Mutex<Serial<Mk20Dx256, Serial3Tx, Serial3Rx, 2>> = Mutex::new(Serial::new());
+pub fn serial_3() -> AsyncMutexLock<'static, Serial<Mk20Dx256, Serial3Tx, Serial3Rx, 2>> {
+    static SERIAL: AsyncMutex<Serial<Mk20Dx256, Serial3Tx, Serial3Rx, 2>> =
+        AsyncMutex::new(Serial::new());
     SERIAL.lock()
 }
 
+/// The USB serial port
+#[cfg(feature = "usb-serial")]
+pub fn usb_serial() -> AsyncMutexLock<'static, UsbSerial> {
+    usb::serial()
+}
+
+/// Run the USB serial port
+#[cfg(feature = "usb-serial")]
+pub(crate) fn usb_serial_task() -> impl Future<Output = !> {
+    usb::serial_task::<Mk20Dx256>()
+}
+
+/// Write to a serial port from the panic handler
+///
+/// The port is used even if a task holds it, since that task will
+/// never run again. Nothing is written if the port is disabled.
+pub fn panic_write(port: usize, bytes: &[u8]) {
+    unsafe {
+        match port {
+            1 => serial_1().steal().write_blocking(bytes),
+            2 => serial_2().steal().write_blocking(bytes),
+            3 => serial_3().steal().write_blocking(bytes),
+            _ => {}
+        }
+    }
+}
+
 /// The first hardware spi port
 ///
 /// On the Teensy 3.2, the SPI uses the following pins:
@@ -312,15 +517,50 @@ pub fn serial_3() -> MutexGuard<'static, Serial<Mk20Dx256, Serial3Tx, Serial3Rx,
 /// * 12: Data In
 /// * 13: Clock
 /// * Hardware chip selects on pins 9, 10, 15, 20, and 21
-pub fn spi_1() -> MutexGuard<'static, Spi<Mk20Dx256, SpiSdi, SpiSdo, SpiSck, SpiCs, 0>> {
-    static SPI: Mutex<Spi<Mk20Dx256, SpiSdi, SpiSdo, SpiSck, SpiCs, 0>> = Mutex::new(Spi::new());
+pub fn spi_1() -> AsyncMutexLock<'static, Spi<Mk20Dx256, SpiSdi, SpiSdo, SpiSck, SpiCs, 0>> {
+    static SPI: AsyncMutex<Spi<Mk20Dx256, SpiSdi, SpiSdo, SpiSck, SpiCs, 0>> =
+        AsyncMutex::new(Spi::new());
     SPI.lock()
 }
 
+/// The first hardware I2C bus
+///
+/// On the Teensy 3.2, this bus uses the following pins:
+/// * 19: Clock
+/// * 18: Data
+pub fn i2c_1() -> MutexGuard<'static, I2c<Mk20Dx256, I2c1Scl, I2c1Sda, 0>> {
+    static I2C: Mutex<I2c<Mk20Dx256, I2c1Scl, I2c1Sda, 0>> = Mutex::new(I2c::new());
+    I2C.lock()
+}
+
+/// The second hardware I2C bus
+///
+/// On the Teensy 3.2, this bus uses the following pins:
+/// * 29: Clock
+/// * 30: Data
+pub fn i2c_2() -> MutexGuard<'static, I2c<Mk20Dx256, I2c2Scl, I2c2Sda, 1>> {
+    static I2C: Mutex<I2c<Mk20Dx256, I2c2Scl, I2c2Sda, 1>> = Mutex::new(I2c::new());
+    I2C.lock()
+}
+
+/// The first CAN bus
+///
+/// On the Teensy 3.2, this bus uses the following pins:
+/// * 3: Transmit
+/// * 4: Receive
+pub fn can_1() -> MutexGuard<'static, Can<Mk20Dx256, Can1Tx, Can1Rx, 0>> {
+    static CAN: Mutex<Can<Mk20Dx256, Can1Tx, Can1Rx, 0>> = Mutex::new(Can::new());
+    CAN.lock()
+}
+
 static SERIAL_1_WAKERS: WakerSet = WakerSet::new();
 static SERIAL_2_WAKERS: WakerSet = WakerSet::new();
 static SERIAL_3_WAKERS: WakerSet = WakerSet::new();
+static DMA_WAKERS: [WakerSet; 3] = [WakerSet::new(), WakerSet::new(), WakerSet::new()];
 static SPI_WAKERS: WakerSet = WakerSet::new();
+static I2C_1_WAKERS: WakerSet = WakerSet::new();
+static I2C_2_WAKERS: WakerSet = WakerSet::new();
+static CAN_1_WAKERS: WakerSet = WakerSet::new();
 
 /// The interrupt function for serial 1
 pub extern "C" fn serial_1_intr() {
@@ -328,9 +568,16 @@ pub extern "C" fn serial_1_intr() {
         const UART_TX_INTR: *mut u8 = bitband_address(0x4006_A003, 7);
         const UART_TC_INTR: *mut u8 = bitband_address(0x4006_A003, 6);
         const UART_RX_INTR: *mut u8 = bitband_address(0x4006_A003, 5);
-        write_volatile(UART_TX_INTR, 0);
+        const UART_IDLE_INTR: *mut u8 = bitband_address(0x4006_A003, 4);
+        const UART_TX_DMA: *mut u8 = bitband_address(0x4006_A00B, 7);
+        // While a DMA write is running, transmit requests go to the
+        // DMA controller and must stay enabled.
+        if read_volatile(UART_TX_DMA) == 0 {
+            write_volatile(UART_TX_INTR, 0);
+        }
         write_volatile(UART_TC_INTR, 0);
         write_volatile(UART_RX_INTR, 0);
+        write_volatile(UART_IDLE_INTR, 0);
         SERIAL_1_WAKERS.wake();
     }
 }
@@ -341,9 +588,16 @@ pub extern "C" fn serial_2_intr() {
         const UART_TX_INTR: *mut u8 = bitband_address(0x4006_B003, 7);
         const UART_TC_INTR: *mut u8 = bitband_address(0x4006_B003, 6);
         const UART_RX_INTR: *mut u8 = bitband_address(0x4006_B003, 5);
-        write_volatile(UART_TX_INTR, 0);
+        const UART_IDLE_INTR: *mut u8 = bitband_address(0x4006_B003, 4);
+        const UART_TX_DMA: *mut u8 = bitband_address(0x4006_B00B, 7);
+        // While a DMA write is running, transmit requests go to the
+        // DMA controller and must stay enabled.
+        if read_volatile(UART_TX_DMA) == 0 {
+            write_volatile(UART_TX_INTR, 0);
+        }
         write_volatile(UART_TC_INTR, 0);
         write_volatile(UART_RX_INTR, 0);
+        write_volatile(UART_IDLE_INTR, 0);
         SERIAL_2_WAKERS.wake();
     }
 }
@@ -354,13 +608,48 @@ pub extern "C" fn serial_3_intr() {
         const UART_TX_INTR: *mut u8 = bitband_address(0x4006_C003, 7);
         const UART_TC_INTR: *mut u8 = bitband_address(0x4006_C003, 6);
         const UART_RX_INTR: *mut u8 = bitband_address(0x4006_C003, 5);
-        write_volatile(UART_TX_INTR, 0);
+        const UART_IDLE_INTR: *mut u8 = bitband_address(0x4006_C003, 4);
+        const UART_TX_DMA: *mut u8 = bitband_address(0x4006_C00B, 7);
+        // While a DMA write is running, transmit requests go to the
+        // DMA controller and must stay enabled.
+        if read_volatile(UART_TX_DMA) == 0 {
+            write_volatile(UART_TX_INTR, 0);
+        }
         write_volatile(UART_TC_INTR, 0);
         write_volatile(UART_RX_INTR, 0);
+        write_volatile(UART_IDLE_INTR, 0);
         SERIAL_3_WAKERS.wake();
     }
 }
 
+/// The interrupt function for DMA channel 0
+pub extern "C" fn dma_0_intr() {
+    dma_intr(0);
+}
+
+/// The interrupt function for DMA channel 1
+pub extern "C" fn dma_1_intr() {
+    dma_intr(1);
+}
+
+/// The interrupt function for DMA channel 2
+pub extern "C" fn dma_2_intr() {
+    dma_intr(2);
+}
+
+/// The interrupt function for DMA errors
+pub extern "C" fn dma_error_intr() {
+    unsafe {
+        // The error flags are left for the waiting writes to read, so
+        // error interrupts are disabled on every channel instead.
+        const DMA_CEEI: *mut u8 = 0x4000_8018 as *mut _;
+        write_volatile(DMA_CEEI, 1 << 6);
+    }
+    for wakers in &DMA_WAKERS {
+        wakers.wake();
+    }
+}
+
 /// The interrupt function for spi 1
 pub extern "C" fn spi_1_intr() {
     unsafe {
@@ -374,6 +663,48 @@ pub extern "C" fn spi_1_intr() {
     }
 }
 
+/// The interrupt function for i2c 1
+pub extern "C" fn i2c_1_intr() {
+    unsafe {
+        const I2C_INTR: *mut u8 = bitband_address(0x4006_6002, 6);
+        write_volatile(I2C_INTR, 0);
+        I2C_1_WAKERS.wake();
+    }
+}
+
+/// The interrupt function for i2c 2
+pub extern "C" fn i2c_2_intr() {
+    unsafe {
+        const I2C_INTR: *mut u8 = bitband_address(0x4006_7002, 6);
+        write_volatile(I2C_INTR, 0);
+        I2C_2_WAKERS.wake();
+    }
+}
+
+/// The interrupt function for CAN 1
+pub extern "C" fn can_1_intr() {
+    unsafe {
+        const CAN_IMASK1: *mut u32 = 0x4002_4028 as *mut _;
+        write_volatile(CAN_IMASK1, 0);
+        CAN_1_WAKERS.wake();
+    }
+}
+
+fn dma_intr(channel: usize) {
+    unsafe {
+        // The done flag is left for the waiting write to read, and
+        // only the interrupt request is cleared.
+        const DMA_CINT: *mut u8 = 0x4000_801F as *mut _;
+        write_volatile(DMA_CINT, channel as u8);
+        DMA_WAKERS[channel].wake();
+    }
+}
+
 const fn bitband_address<T>(addr: u32, bit: u32) -> *mut T {
     (0x4200_0000 + (addr - 0x4000_0000) * 32 + bit * 4) as _
 }
+
+/// Compute a CRC with the CRC module
+pub(crate) fn hardware_crc(kind: CrcKind, data: &[u8]) -> Option<u32> {
+    common::hardware_crc::<Mk20Dx256>(kind, data)
+}
@@ -4,3 +4,41 @@
 //! Time functionality specific to the Teensy 3.2 board
 
 pub use crate::hw::board::teensy_common::time::*;
+
+use crate::{
+    hw::mcu::kinetis::{
+        mk20dx256::{Pit, Sim},
+        Mk20Dx256,
+    },
+    sync::{Mutex, MutexGuard},
+};
+use core::sync::atomic::Ordering;
+
+/// The PIT, once [`micros`] is counting on it
+///
+/// Software PWM takes the handle from here.
+pub(crate) static PIT: Mutex<Option<Pit>> = Mutex::new(None);
+
+/// Busy-wait for at least some number of microseconds
+///
+/// See [`delay_cycles`] for details.
+pub fn delay_us_blocking(us: usize) {
+    delay_us_at(us, super::CPU_FREQ.load(Ordering::Relaxed));
+}
+
+/// Measure the core clock against the 1kHz LPO
+///
+/// See [`crate::time::measure_core_clock`] for details.
+pub fn measure_core_clock() -> Option<(usize, usize)> {
+    measure_core_clock_at(super::CPU_FREQ.load(Ordering::Relaxed))
+}
+
+/// The board's real-time clock
+///
+/// See [`Rtc`] for details.
+pub fn rtc() -> MutexGuard<'static, Rtc<Mk20Dx256>> {
+    static RTC: Mutex<Rtc<Mk20Dx256>> = Mutex::new(Rtc::new());
+    let mut rtc = RTC.lock();
+    rtc.init(|| Sim::get().as_mut().and_then(Sim::enable_peripheral));
+    rtc
+}
@@ -5,17 +5,33 @@
 
 use crate::{
     hw::{
-        board::teensy_common::io::{Serial, SerialError, Spi, SpiBoard, SpiError},
+        board::teensy_common::io::{
+            self as common, AudioBoard, AudioBuffer, AudioError, AudioOut, Can, CanBoard, CanError,
+            I2c, I2cBoard, I2cError, Serial, SerialDma, SerialError, Spi, SpiBoard, SpiError,
+        },
         mcu::kinetis::{
-            mk64fx512::{Cs, Pin, Sck, Sdi, Sdo, UartRx, UartTx},
+            mk64fx512::{
+                CanRx, CanTx, Cs, Dma, DmaMux, I2cScl, I2cSda, I2sBclk, I2sFs, I2sMclk, I2sTxd,
+                Pin, Sck, Sdi, Sdo, Sim, UartRx, UartTx,
+            },
             Mk64Fx512,
         },
     },
-    io::{self, SpiOption},
-    sync::{Mutex, MutexGuard},
+    io::{self, codec::CrcKind, SpiOption},
+    sync::{AsyncMutex, AsyncMutexLock, Mutex, MutexGuard},
     task::WakerSet,
 };
-use core::{ptr::write_volatile, sync::atomic::Ordering};
+use core::{
+    ptr::{read_volatile, write_volatile},
+    sync::atomic::Ordering,
+};
+
+#[cfg(feature = "usb-serial")]
+use crate::hw::board::teensy_common::usb::{self, UsbSerial};
+#[cfg(feature = "usb-serial")]
+use core::future::Future;
+
+pub use crate::hw::board::teensy_common::usb::usb_intr;
 
 /// The pin used to recieve for serial 1
 pub type Serial1Rx = UartRx<Pin<'static, 1, 16>>;
@@ -116,6 +132,36 @@ pub type Spi3Cs1 = Cs<Pin<'static, 3, 15>>;
 /// The Chip Selects for SPI 3
 pub type Spi3Cs = (Option<Spi3Cs0>, Option<Spi3Cs1>);
 
+/// The pin used as SCL for I2C 1
+pub type I2c1Scl = I2cScl<Pin<'static, 1, 2>>;
+
+/// The pin used as SDA for I2C 1
+pub type I2c1Sda = I2cSda<Pin<'static, 1, 3>>;
+
+/// The pin used as SCL for I2C 2
+pub type I2c2Scl = I2cScl<Pin<'static, 2, 10>>;
+
+/// The pin used as SDA for I2C 2
+pub type I2c2Sda = I2cSda<Pin<'static, 2, 11>>;
+
+/// The pin used to transmit for CAN 1
+pub type Can1Tx = CanTx<Pin<'static, 0, 12>>;
+
+/// The pin used to receive for CAN 1
+pub type Can1Rx = CanRx<Pin<'static, 0, 13>>;
+
+/// The pin used as the master clock for the audio output
+pub type AudioMclk = I2sMclk<Pin<'static, 2, 6>>;
+
+/// The pin used as the bit clock for the audio output
+pub type AudioBclk = I2sBclk<Pin<'static, 2, 3>>;
+
+/// The pin used as the frame sync for the audio output
+pub type AudioFs = I2sFs<Pin<'static, 2, 2>>;
+
+/// The pin used to transmit for the audio output
+pub type AudioTxd = I2sTxd<Pin<'static, 2, 1>>;
+
 impl io::Serial for Serial<Mk64Fx512, Serial1Tx, Serial1Rx, 0> {
     type Error = SerialError;
 
@@ -156,6 +202,18 @@ impl io::Serial for Serial<Mk64Fx512, Serial1Tx, Serial1Rx, 0> {
         self.1 = None;
         Ok(())
     }
+
+    fn stats(&self) -> io::SerialStats {
+        self.2
+    }
+
+    fn reset_stats(&mut self) {
+        self.2 = io::SerialStats::new();
+    }
+
+    fn alt_pin_sets(&self) -> &'static [io::SerialPins] {
+        &[io::SerialPins { tx: 1, rx: 0 }]
+    }
 }
 
 impl io::Serial for Serial<Mk64Fx512, Serial2Tx, Serial2Rx, 1> {
@@ -198,6 +256,18 @@ impl io::Serial for Serial<Mk64Fx512, Serial2Tx, Serial2Rx, 1> {
         self.1 = None;
         Ok(())
     }
+
+    fn stats(&self) -> io::SerialStats {
+        self.2
+    }
+
+    fn reset_stats(&mut self) {
+        self.2 = io::SerialStats::new();
+    }
+
+    fn alt_pin_sets(&self) -> &'static [io::SerialPins] {
+        &[io::SerialPins { tx: 10, rx: 9 }]
+    }
 }
 
 impl io::Serial for Serial<Mk64Fx512, Serial3Tx, Serial3Rx, 2> {
@@ -240,6 +310,18 @@ impl io::Serial for Serial<Mk64Fx512, Serial3Tx, Serial3Rx, 2> {
         self.1 = None;
         Ok(())
     }
+
+    fn stats(&self) -> io::SerialStats {
+        self.2
+    }
+
+    fn reset_stats(&mut self) {
+        self.2 = io::SerialStats::new();
+    }
+
+    fn alt_pin_sets(&self) -> &'static [io::SerialPins] {
+        &[io::SerialPins { tx: 8, rx: 7 }]
+    }
 }
 
 impl io::Serial for Serial<Mk64Fx512, Serial4Tx, Serial4Rx, 3> {
@@ -282,6 +364,18 @@ impl io::Serial for Serial<Mk64Fx512, Serial4Tx, Serial4Rx, 3> {
         self.1 = None;
         Ok(())
     }
+
+    fn stats(&self) -> io::SerialStats {
+        self.2
+    }
+
+    fn reset_stats(&mut self) {
+        self.2 = io::SerialStats::new();
+    }
+
+    fn alt_pin_sets(&self) -> &'static [io::SerialPins] {
+        &[io::SerialPins { tx: 32, rx: 31 }]
+    }
 }
 
 impl io::Serial for Serial<Mk64Fx512, Serial5Tx, Serial5Rx, 4> {
@@ -324,6 +418,18 @@ impl io::Serial for Serial<Mk64Fx512, Serial5Tx, Serial5Rx, 4> {
         self.1 = None;
         Ok(())
     }
+
+    fn stats(&self) -> io::SerialStats {
+        self.2
+    }
+
+    fn reset_stats(&mut self) {
+        self.2 = io::SerialStats::new();
+    }
+
+    fn alt_pin_sets(&self) -> &'static [io::SerialPins] {
+        &[io::SerialPins { tx: 33, rx: 34 }]
+    }
 }
 
 impl io::Serial for Serial<Mk64Fx512, Serial6Tx, Serial6Rx, 5> {
@@ -366,6 +472,47 @@ impl io::Serial for Serial<Mk64Fx512, Serial6Tx, Serial6Rx, 5> {
         self.1 = None;
         Ok(())
     }
+
+    fn stats(&self) -> io::SerialStats {
+        self.2
+    }
+
+    fn reset_stats(&mut self) {
+        self.2 = io::SerialStats::new();
+    }
+
+    fn alt_pin_sets(&self) -> &'static [io::SerialPins] {
+        &[io::SerialPins { tx: 47, rx: 48 }]
+    }
+}
+
+impl SerialDma for Mk64Fx512 {
+    type Dma = Dma;
+
+    fn with_dma<F, U>(f: F) -> Result<U, SerialError>
+    where
+        F: FnOnce(&mut Dma, &mut DmaMux) -> U,
+    {
+        static DMA: Mutex<Option<(Dma, DmaMux)>> = Mutex::new(None);
+
+        let mut dma = DMA.lock();
+        if dma.is_none() {
+            let mut sim = Sim::get().ok_or(SerialError::SimInUse)?;
+            let controller = sim
+                .enable_peripheral::<Dma>()
+                .ok_or(SerialError::DmaInUse)?;
+            let mux = sim
+                .enable_peripheral::<DmaMux>()
+                .ok_or(SerialError::DmaInUse)?;
+            *dma = Some((controller, mux));
+        }
+        let (dma, mux) = dma.as_mut().ok_or(SerialError::DmaInUse)?;
+        Ok(f(dma, mux))
+    }
+
+    fn dma_wakers(channel: usize) -> &'static WakerSet {
+        &DMA_WAKERS[channel]
+    }
 }
 
 impl SpiBoard<Spi1Sdi, Spi1Sdo, Spi1Sck, Spi1Cs>
@@ -461,6 +608,10 @@ impl SpiBoard<Spi1Sdi, Spi1Sdo, Spi1Sck, Spi1Cs>
             _ => None,
         }
     }
+
+    fn hardware_cs_pins() -> &'static [usize] {
+        &[9, 10, 15, 20, 21]
+    }
 }
 
 impl SpiBoard<Spi2Sdi, Spi2Sdo, Spi2Sck, Spi2Cs>
@@ -520,6 +671,10 @@ impl SpiBoard<Spi2Sdi, Spi2Sdo, Spi2Sck, Spi2Cs>
             _ => None,
         }
     }
+
+    fn hardware_cs_pins() -> &'static [usize] {
+        &[31]
+    }
 }
 
 impl SpiBoard<Spi3Sdi, Spi3Sdo, Spi3Sck, Spi3Cs>
@@ -588,44 +743,204 @@ impl SpiBoard<Spi3Sdi, Spi3Sdo, Spi3Sck, Spi3Cs>
             _ => None,
         }
     }
+
+    fn hardware_cs_pins() -> &'static [usize] {
+        &[43, 54]
+    }
+}
+
+impl I2cBoard<I2c1Scl, I2c1Sda> for I2c<Mk64Fx512, I2c1Scl, I2c1Sda, 0> {
+    fn scl() -> Result<I2c1Scl, I2cError> {
+        super::digital::port_b()
+            .ok_or(I2cError::PortInUse)
+            .and_then(|port| port.pin::<2>().ok_or(I2cError::PinInUse))
+            .map(|pin| pin.into_i2c_scl())
+    }
+
+    fn sda() -> Result<I2c1Sda, I2cError> {
+        super::digital::port_b()
+            .ok_or(I2cError::PortInUse)
+            .and_then(|port| port.pin::<3>().ok_or(I2cError::PinInUse))
+            .map(|pin| pin.into_i2c_sda())
+    }
+
+    fn clock_source() -> usize {
+        super::BUS_FREQ.load(Ordering::Relaxed)
+    }
+
+    fn wakers() -> &'static WakerSet {
+        &I2C_1_WAKERS
+    }
+}
+
+impl I2cBoard<I2c2Scl, I2c2Sda> for I2c<Mk64Fx512, I2c2Scl, I2c2Sda, 1> {
+    fn scl() -> Result<I2c2Scl, I2cError> {
+        super::digital::port_c()
+            .ok_or(I2cError::PortInUse)
+            .and_then(|port| port.pin::<10>().ok_or(I2cError::PinInUse))
+            .map(|pin| pin.into_i2c_scl())
+    }
+
+    fn sda() -> Result<I2c2Sda, I2cError> {
+        super::digital::port_c()
+            .ok_or(I2cError::PortInUse)
+            .and_then(|port| port.pin::<11>().ok_or(I2cError::PinInUse))
+            .map(|pin| pin.into_i2c_sda())
+    }
+
+    fn clock_source() -> usize {
+        super::BUS_FREQ.load(Ordering::Relaxed)
+    }
+
+    fn wakers() -> &'static WakerSet {
+        &I2C_2_WAKERS
+    }
+}
+
+impl CanBoard<Can1Tx, Can1Rx> for Can<Mk64Fx512, Can1Tx, Can1Rx, 0> {
+    fn tx() -> Result<Can1Tx, CanError> {
+        super::digital::port_a()
+            .ok_or(CanError::PortInUse)
+            .and_then(|port| port.pin::<12>().ok_or(CanError::PinInUse))
+            .map(|pin| pin.into_can_tx())
+    }
+
+    fn rx() -> Result<Can1Rx, CanError> {
+        super::digital::port_a()
+            .ok_or(CanError::PortInUse)
+            .and_then(|port| port.pin::<13>().ok_or(CanError::PinInUse))
+            .map(|pin| pin.into_can_rx())
+    }
+
+    fn clock_source() -> usize {
+        super::BUS_FREQ.load(Ordering::Relaxed)
+    }
+
+    fn wakers() -> &'static WakerSet {
+        &CAN_1_WAKERS
+    }
+}
+
+impl AudioBoard<AudioMclk, AudioBclk, AudioFs, AudioTxd>
+    for AudioOut<Mk64Fx512, AudioMclk, AudioBclk, AudioFs, AudioTxd>
+{
+    fn mclk() -> Result<AudioMclk, AudioError> {
+        super::digital::port_c()
+            .ok_or(AudioError::PortInUse)
+            .and_then(|port| port.pin::<6>().ok_or(AudioError::PinInUse))
+            .map(Pin::into_i2s_mclk)
+    }
+
+    fn bclk() -> Result<AudioBclk, AudioError> {
+        super::digital::port_c()
+            .ok_or(AudioError::PortInUse)
+            .and_then(|port| port.pin::<3>().ok_or(AudioError::PinInUse))
+            .map(Pin::into_i2s_bclk)
+    }
+
+    fn fs() -> Result<AudioFs, AudioError> {
+        super::digital::port_c()
+            .ok_or(AudioError::PortInUse)
+            .and_then(|port| port.pin::<2>().ok_or(AudioError::PinInUse))
+            .map(Pin::into_i2s_fs)
+    }
+
+    fn txd() -> Result<AudioTxd, AudioError> {
+        super::digital::port_c()
+            .ok_or(AudioError::PortInUse)
+            .and_then(|port| port.pin::<1>().ok_or(AudioError::PinInUse))
+            .map(Pin::into_i2s_txd)
+    }
+
+    fn clock_source() -> usize {
+        super::CPU_FREQ.load(Ordering::Relaxed)
+    }
+
+    fn buffer() -> &'static AudioBuffer {
+        &AUDIO_BUFFER
+    }
+
+    fn wakers() -> &'static WakerSet {
+        &AUDIO_WAKERS
+    }
 }
 
 /// The first hardware serial port
-pub fn serial_1() -> MutexGuard<'static, Serial<Mk64Fx512, Serial1Tx, Serial1Rx, 0>> {
-    static SERIAL: Mutex<Serial<Mk64Fx512, Serial1Tx, Serial1Rx, 0>> = Mutex::new(Serial::new());
+pub fn serial_1() -> AsyncMutexLock<'static, Serial<Mk64Fx512, Serial1Tx, Serial1Rx, 0>> {
+    static SERIAL: AsyncMutex<Serial<Mk64Fx512, Serial1Tx, Serial1Rx, 0>> =
+        AsyncMutex::new(Serial::new());
     SERIAL.lock()
 }
 
 /// The second hardware serial port
-pub fn serial_2() -> MutexGuard<'static, Serial<Mk64Fx512, Serial2Tx, Serial2Rx, 1>> {
-    static SERIAL: Mutex<Serial<Mk64Fx512, Serial2Tx, Serial2Rx, 1>> = Mutex::new(Serial::new());
+pub fn serial_2() -> AsyncMutexLock<'static, Serial<Mk64Fx512, Serial2Tx, Serial2Rx, 1>> {
+    static SERIAL: AsyncMutex<Serial<Mk64Fx512, Serial2Tx, Serial2Rx, 1>> =
+        AsyncMutex::new(Serial::new());
     SERIAL.lock()
 }
 
 /// The third hardware serial port
-pub fn serial_3() -> MutexGuard<'static, Serial<Mk64Fx512, Serial3Tx, Serial3Rx, 2>> {
-    static SERIAL: Mutex<Serial<Mk64Fx512, Serial3Tx, Serial3Rx, 2>> = Mutex::new(Serial::new());
+pub fn serial_3() -> AsyncMutexLock<'static, Serial<Mk64Fx512, Serial3Tx, Serial3Rx, 2>> {
+    static SERIAL: AsyncMutex<Serial<Mk64Fx512, Serial3Tx, Serial3Rx, 2>> =
+        AsyncMutex::new(Serial::new());
     SERIAL.lock()
 }
 
 /// The fourth hardware serial port
-pub fn serial_4() -> MutexGuard<'static, Serial<Mk64Fx512, Serial4Tx, Serial4Rx, 3>> {
-    static SERIAL: Mutex<Serial<Mk64Fx512, Serial4Tx, Serial4Rx, 3>> = Mutex::new(Serial::new());
+pub fn serial_4() -> AsyncMutexLock<'static, Serial<Mk64Fx512, Serial4Tx, Serial4Rx, 3>> {
+    static SERIAL: AsyncMutex<Serial<Mk64Fx512, Serial4Tx, Serial4Rx, 3>> =
+        AsyncMutex::new(Serial::new());
     SERIAL.lock()
 }
 
 /// The fifth hardware serial port
-pub fn serial_5() -> MutexGuard<'static, Serial<Mk64Fx512, Serial5Tx, Serial5Rx, 4>> {
-    static SERIAL: Mutex<Serial<Mk64Fx512, Serial5Tx, Serial5Rx, 4>> = Mutex::new(Serial::new());
+pub fn serial_5() -> AsyncMutexLock<'static, Serial<Mk64Fx512, Serial5Tx, Serial5Rx, 4>> {
+    static SERIAL: AsyncMutex<Serial<Mk64Fx512, Serial5Tx, Serial5Rx, 4>> =
+        AsyncMutex::new(Serial::new());
     SERIAL.lock()
 }
 
 /// The sixth hardware serial port
-pub fn serial_6() -> MutexGuard<'static, Serial<Mk64Fx512, Serial6Tx, Serial6Rx, 5>> {
-    static SERIAL: Mutex<Serial<Mk64Fx512, Serial6Tx, Serial6Rx, 5>> = Mutex::new(Serial::new());
+pub fn serial_6() -> AsyncMutexLock<'static, Serial<Mk64Fx512, Serial6Tx, Serial6Rx, 5>> {
+    static SERIAL: AsyncMutex<Serial<Mk64Fx512, Serial6Tx, Serial6Rx, 5>> =
+        AsyncMutex::new(Serial::new());
     SERIAL.lock()
 }
 
+/// The USB serial port
+#[cfg(feature = "usb-serial")]
+pub fn usb_serial() -> AsyncMutexLock<'static, UsbSerial> {
+    usb::serial()
+}
+
+/// Run the USB serial port
+#[cfg(feature = "usb-serial")]
+pub(crate) fn usb_serial_task() -> impl Future<Output = !> {
+    // The MPU blocks the USB controller, bus master 4, from memory
+    // until it is given access.
+    const MPU_RGDAAC0: *mut u32 = 0x4000_D800 as *mut _;
+    unsafe { write_volatile(MPU_RGDAAC0, read_volatile(MPU_RGDAAC0) | 0x0300_0000) };
+    usb::serial_task::<Mk64Fx512>()
+}
+
+/// Write to a serial port from the panic handler
+///
+/// The port is used even if a task holds it, since that task will
+/// never run again. Nothing is written if the port is disabled.
+pub fn panic_write(port: usize, bytes: &[u8]) {
+    unsafe {
+        match port {
+            1 => serial_1().steal().write_blocking(bytes),
+            2 => serial_2().steal().write_blocking(bytes),
+            3 => serial_3().steal().write_blocking(bytes),
+            4 => serial_4().steal().write_blocking(bytes),
+            5 => serial_5().steal().write_blocking(bytes),
+            6 => serial_6().steal().write_blocking(bytes),
+            _ => {}
+        }
+    }
+}
+
 /// The first hardware spi port
 ///
 /// On the Teensy 3.5, the SPI uses the following pins:
@@ -633,9 +948,9 @@ pub fn serial_6() -> MutexGuard<'static, Serial<Mk64Fx512, Serial6Tx, Serial6Rx,
 /// * 12: Data In
 /// * 13: Clock
 /// * Hardware chip selects on pins 9, 10, 15, 20, and 21
-pub fn spi_1() -> MutexGuard<'static, Spi<Mk64Fx512, Spi1Sdi, Spi1Sdo, Spi1Sck, Spi1Cs, 0>> {
-    static SPI: Mutex<Spi<Mk64Fx512, Spi1Sdi, Spi1Sdo, Spi1Sck, Spi1Cs, 0>> =
-        Mutex::new(Spi::new());
+pub fn spi_1() -> AsyncMutexLock<'static, Spi<Mk64Fx512, Spi1Sdi, Spi1Sdo, Spi1Sck, Spi1Cs, 0>> {
+    static SPI: AsyncMutex<Spi<Mk64Fx512, Spi1Sdi, Spi1Sdo, Spi1Sck, Spi1Cs, 0>> =
+        AsyncMutex::new(Spi::new());
     SPI.lock()
 }
 
@@ -646,9 +961,9 @@ pub fn spi_1() -> MutexGuard<'static, Spi<Mk64Fx512, Spi1Sdi, Spi1Sdo, Spi1Sck,
 /// * 1: Data In
 /// * 32: Clock
 /// * A single hardware chip select on pin 31
-pub fn spi_2() -> MutexGuard<'static, Spi<Mk64Fx512, Spi2Sdi, Spi2Sdo, Spi2Sck, Spi2Cs, 1>> {
-    static SPI: Mutex<Spi<Mk64Fx512, Spi2Sdi, Spi2Sdo, Spi2Sck, Spi2Cs, 1>> =
-        Mutex::new(Spi::new());
+pub fn spi_2() -> AsyncMutexLock<'static, Spi<Mk64Fx512, Spi2Sdi, Spi2Sdo, Spi2Sck, Spi2Cs, 1>> {
+    static SPI: AsyncMutex<Spi<Mk64Fx512, Spi2Sdi, Spi2Sdo, Spi2Sck, Spi2Cs, 1>> =
+        AsyncMutex::new(Spi::new());
     SPI.lock()
 }
 
@@ -659,21 +974,78 @@ pub fn spi_2() -> MutexGuard<'static, Spi<Mk64Fx512, Spi2Sdi, Spi2Sdo, Spi2Sck,
 /// * 45: Data In
 /// * 46: Clock
 /// * Hardware chip selects on pins 43 and 54
-pub fn spi_3() -> MutexGuard<'static, Spi<Mk64Fx512, Spi3Sdi, Spi3Sdo, Spi3Sck, Spi3Cs, 2>> {
-    static SPI: Mutex<Spi<Mk64Fx512, Spi3Sdi, Spi3Sdo, Spi3Sck, Spi3Cs, 2>> =
-        Mutex::new(Spi::new());
+pub fn spi_3() -> AsyncMutexLock<'static, Spi<Mk64Fx512, Spi3Sdi, Spi3Sdo, Spi3Sck, Spi3Cs, 2>> {
+    static SPI: AsyncMutex<Spi<Mk64Fx512, Spi3Sdi, Spi3Sdo, Spi3Sck, Spi3Cs, 2>> =
+        AsyncMutex::new(Spi::new());
     SPI.lock()
 }
 
+/// The first hardware I2C bus
+///
+/// On the Teensy 3.5, this bus uses the following pins:
+/// * 19: Clock
+/// * 18: Data
+pub fn i2c_1() -> MutexGuard<'static, I2c<Mk64Fx512, I2c1Scl, I2c1Sda, 0>> {
+    static I2C: Mutex<I2c<Mk64Fx512, I2c1Scl, I2c1Sda, 0>> = Mutex::new(I2c::new());
+    I2C.lock()
+}
+
+/// The second hardware I2C bus
+///
+/// On the Teensy 3.5, this bus uses the following pins:
+/// * 37: Clock
+/// * 38: Data
+pub fn i2c_2() -> MutexGuard<'static, I2c<Mk64Fx512, I2c2Scl, I2c2Sda, 1>> {
+    static I2C: Mutex<I2c<Mk64Fx512, I2c2Scl, I2c2Sda, 1>> = Mutex::new(I2c::new());
+    I2C.lock()
+}
+
+/// The first CAN bus
+///
+/// On the Teensy 3.5, this bus uses the following pins:
+/// * 3: Transmit
+/// * 4: Receive
+pub fn can_1() -> MutexGuard<'static, Can<Mk64Fx512, Can1Tx, Can1Rx, 0>> {
+    static CAN: Mutex<Can<Mk64Fx512, Can1Tx, Can1Rx, 0>> = Mutex::new(Can::new());
+    CAN.lock()
+}
+
+/// The I2S audio output
+///
+/// On the Teensy 3.5, the output uses the following pins:
+/// * 9: Bit clock
+/// * 11: Master clock
+/// * 22: Data
+/// * 23: Frame sync
+pub fn audio_out(
+) -> MutexGuard<'static, AudioOut<Mk64Fx512, AudioMclk, AudioBclk, AudioFs, AudioTxd>> {
+    static AUDIO: Mutex<AudioOut<Mk64Fx512, AudioMclk, AudioBclk, AudioFs, AudioTxd>> =
+        Mutex::new(AudioOut::new());
+    AUDIO.lock()
+}
+
 static SERIAL_1_WAKERS: WakerSet = WakerSet::new();
 static SERIAL_2_WAKERS: WakerSet = WakerSet::new();
 static SERIAL_3_WAKERS: WakerSet = WakerSet::new();
 static SERIAL_4_WAKERS: WakerSet = WakerSet::new();
 static SERIAL_5_WAKERS: WakerSet = WakerSet::new();
 static SERIAL_6_WAKERS: WakerSet = WakerSet::new();
+static DMA_WAKERS: [WakerSet; 6] = [
+    WakerSet::new(),
+    WakerSet::new(),
+    WakerSet::new(),
+    WakerSet::new(),
+    WakerSet::new(),
+    WakerSet::new(),
+];
 static SPI_1_WAKERS: WakerSet = WakerSet::new();
 static SPI_2_WAKERS: WakerSet = WakerSet::new();
 static SPI_3_WAKERS: WakerSet = WakerSet::new();
+static I2C_1_WAKERS: WakerSet = WakerSet::new();
+static I2C_2_WAKERS: WakerSet = WakerSet::new();
+static CAN_1_WAKERS: WakerSet = WakerSet::new();
+static AUDIO_WAKERS: WakerSet = WakerSet::new();
+static AUDIO_BUFFER: AudioBuffer = AudioBuffer::new();
 
 /// The interrupt function for serial 1
 pub extern "C" fn serial_1_intr() {
@@ -681,9 +1053,16 @@ pub extern "C" fn serial_1_intr() {
         const UART_TX_INTR: *mut u8 = bitband_address(0x4006_A003, 7);
         const UART_TC_INTR: *mut u8 = bitband_address(0x4006_A003, 6);
         const UART_RX_INTR: *mut u8 = bitband_address(0x4006_A003, 5);
-        write_volatile(UART_TX_INTR, 0);
+        const UART_IDLE_INTR: *mut u8 = bitband_address(0x4006_A003, 4);
+        const UART_TX_DMA: *mut u8 = bitband_address(0x4006_A00B, 7);
+        // While a DMA write is running, transmit requests go to the
+        // DMA controller and must stay enabled.
+        if read_volatile(UART_TX_DMA) == 0 {
+            write_volatile(UART_TX_INTR, 0);
+        }
         write_volatile(UART_TC_INTR, 0);
         write_volatile(UART_RX_INTR, 0);
+        write_volatile(UART_IDLE_INTR, 0);
         SERIAL_1_WAKERS.wake();
     }
 }
@@ -694,9 +1073,16 @@ pub extern "C" fn serial_2_intr() {
         const UART_TX_INTR: *mut u8 = bitband_address(0x4006_B003, 7);
         const UART_TC_INTR: *mut u8 = bitband_address(0x4006_B003, 6);
         const UART_RX_INTR: *mut u8 = bitband_address(0x4006_B003, 5);
-        write_volatile(UART_TX_INTR, 0);
+        const UART_IDLE_INTR: *mut u8 = bitband_address(0x4006_B003, 4);
+        const UART_TX_DMA: *mut u8 = bitband_address(0x4006_B00B, 7);
+        // While a DMA write is running, transmit requests go to the
+        // DMA controller and must stay enabled.
+        if read_volatile(UART_TX_DMA) == 0 {
+            write_volatile(UART_TX_INTR, 0);
+        }
         write_volatile(UART_TC_INTR, 0);
         write_volatile(UART_RX_INTR, 0);
+        write_volatile(UART_IDLE_INTR, 0);
         SERIAL_2_WAKERS.wake();
     }
 }
@@ -707,9 +1093,16 @@ pub extern "C" fn serial_3_intr() {
         const UART_TX_INTR: *mut u8 = bitband_address(0x4006_C003, 7);
         const UART_TC_INTR: *mut u8 = bitband_address(0x4006_C003, 6);
         const UART_RX_INTR: *mut u8 = bitband_address(0x4006_C003, 5);
-        write_volatile(UART_TX_INTR, 0);
+        const UART_IDLE_INTR: *mut u8 = bitband_address(0x4006_C003, 4);
+        const UART_TX_DMA: *mut u8 = bitband_address(0x4006_C00B, 7);
+        // While a DMA write is running, transmit requests go to the
+        // DMA controller and must stay enabled.
+        if read_volatile(UART_TX_DMA) == 0 {
+            write_volatile(UART_TX_INTR, 0);
+        }
         write_volatile(UART_TC_INTR, 0);
         write_volatile(UART_RX_INTR, 0);
+        write_volatile(UART_IDLE_INTR, 0);
         SERIAL_3_WAKERS.wake();
     }
 }
@@ -720,9 +1113,16 @@ pub extern "C" fn serial_4_intr() {
         const UART_TX_INTR: *mut u8 = bitband_address(0x4006_D003, 7);
         const UART_TC_INTR: *mut u8 = bitband_address(0x4006_D003, 6);
         const UART_RX_INTR: *mut u8 = bitband_address(0x4006_D003, 5);
-        write_volatile(UART_TX_INTR, 0);
+        const UART_IDLE_INTR: *mut u8 = bitband_address(0x4006_D003, 4);
+        const UART_TX_DMA: *mut u8 = bitband_address(0x4006_D00B, 7);
+        // While a DMA write is running, transmit requests go to the
+        // DMA controller and must stay enabled.
+        if read_volatile(UART_TX_DMA) == 0 {
+            write_volatile(UART_TX_INTR, 0);
+        }
         write_volatile(UART_TC_INTR, 0);
         write_volatile(UART_RX_INTR, 0);
+        write_volatile(UART_IDLE_INTR, 0);
         SERIAL_1_WAKERS.wake();
     }
 }
@@ -733,9 +1133,16 @@ pub extern "C" fn serial_5_intr() {
         const UART_TX_INTR: *mut u8 = bitband_address(0x400E_A003, 7);
         const UART_TC_INTR: *mut u8 = bitband_address(0x400E_A003, 6);
         const UART_RX_INTR: *mut u8 = bitband_address(0x400E_A003, 5);
-        write_volatile(UART_TX_INTR, 0);
+        const UART_IDLE_INTR: *mut u8 = bitband_address(0x400E_A003, 4);
+        const UART_TX_DMA: *mut u8 = bitband_address(0x400E_A00B, 7);
+        // While a DMA write is running, transmit requests go to the
+        // DMA controller and must stay enabled.
+        if read_volatile(UART_TX_DMA) == 0 {
+            write_volatile(UART_TX_INTR, 0);
+        }
         write_volatile(UART_TC_INTR, 0);
         write_volatile(UART_RX_INTR, 0);
+        write_volatile(UART_IDLE_INTR, 0);
         SERIAL_2_WAKERS.wake();
     }
 }
@@ -746,13 +1153,76 @@ pub extern "C" fn serial_6_intr() {
         const UART_TX_INTR: *mut u8 = bitband_address(0x400E_B003, 7);
         const UART_TC_INTR: *mut u8 = bitband_address(0x400E_B003, 6);
         const UART_RX_INTR: *mut u8 = bitband_address(0x400E_B003, 5);
-        write_volatile(UART_TX_INTR, 0);
+        const UART_IDLE_INTR: *mut u8 = bitband_address(0x400E_B003, 4);
+        const UART_TX_DMA: *mut u8 = bitband_address(0x400E_B00B, 7);
+        // While a DMA write is running, transmit requests go to the
+        // DMA controller and must stay enabled.
+        if read_volatile(UART_TX_DMA) == 0 {
+            write_volatile(UART_TX_INTR, 0);
+        }
         write_volatile(UART_TC_INTR, 0);
         write_volatile(UART_RX_INTR, 0);
+        write_volatile(UART_IDLE_INTR, 0);
         SERIAL_3_WAKERS.wake();
     }
 }
 
+/// The interrupt function for DMA channel 0
+pub extern "C" fn dma_0_intr() {
+    dma_intr(0);
+}
+
+/// The interrupt function for DMA channel 1
+pub extern "C" fn dma_1_intr() {
+    dma_intr(1);
+}
+
+/// The interrupt function for DMA channel 2
+pub extern "C" fn dma_2_intr() {
+    dma_intr(2);
+}
+
+/// The interrupt function for DMA channel 3
+pub extern "C" fn dma_3_intr() {
+    dma_intr(3);
+}
+
+/// The interrupt function for DMA channel 4
+pub extern "C" fn dma_4_intr() {
+    dma_intr(4);
+}
+
+/// The interrupt function for DMA channel 5
+pub extern "C" fn dma_5_intr() {
+    dma_intr(5);
+}
+
+/// The interrupt function for DMA channel 8
+///
+/// This channel feeds the audio output, and interrupts each time it
+/// finishes half of the audio buffer.
+pub extern "C" fn dma_8_intr() {
+    unsafe {
+        const DMA_CINT: *mut u8 = 0x4000_801F as *mut _;
+        write_volatile(DMA_CINT, common::AUDIO_DMA_CHANNEL as u8);
+    }
+    AUDIO_BUFFER.half_played();
+    AUDIO_WAKERS.wake();
+}
+
+/// The interrupt function for DMA errors
+pub extern "C" fn dma_error_intr() {
+    unsafe {
+        // The error flags are left for the waiting writes to read, so
+        // error interrupts are disabled on every channel instead.
+        const DMA_CEEI: *mut u8 = 0x4000_8018 as *mut _;
+        write_volatile(DMA_CEEI, 1 << 6);
+    }
+    for wakers in &DMA_WAKERS {
+        wakers.wake();
+    }
+}
+
 /// The interrupt function for spi 1
 pub extern "C" fn spi_1_intr() {
     unsafe {
@@ -792,6 +1262,48 @@ pub extern "C" fn spi_3_intr() {
     }
 }
 
+/// The interrupt function for i2c 1
+pub extern "C" fn i2c_1_intr() {
+    unsafe {
+        const I2C_INTR: *mut u8 = bitband_address(0x4006_6002, 6);
+        write_volatile(I2C_INTR, 0);
+        I2C_1_WAKERS.wake();
+    }
+}
+
+/// The interrupt function for i2c 2
+pub extern "C" fn i2c_2_intr() {
+    unsafe {
+        const I2C_INTR: *mut u8 = bitband_address(0x4006_7002, 6);
+        write_volatile(I2C_INTR, 0);
+        I2C_2_WAKERS.wake();
+    }
+}
+
+/// The interrupt function for CAN 1
+pub extern "C" fn can_1_intr() {
+    unsafe {
+        const CAN_IMASK1: *mut u32 = 0x4002_4028 as *mut _;
+        write_volatile(CAN_IMASK1, 0);
+        CAN_1_WAKERS.wake();
+    }
+}
+
+fn dma_intr(channel: usize) {
+    unsafe {
+        // The done flag is left for the waiting write to read, and
+        // only the interrupt request is cleared.
+        const DMA_CINT: *mut u8 = 0x4000_801F as *mut _;
+        write_volatile(DMA_CINT, channel as u8);
+        DMA_WAKERS[channel].wake();
+    }
+}
+
 const fn bitband_address<T>(addr: u32, bit: u32) -> *mut T {
     (0x4200_0000 + (addr - 0x4000_0000) * 32 + bit * 4) as _
 }
+
+/// Compute a CRC with the CRC module
+pub(crate) fn hardware_crc(kind: CrcKind, data: &[u8]) -> Option<u32> {
+    common::hardware_crc::<Mk64Fx512>(kind, data)
+}
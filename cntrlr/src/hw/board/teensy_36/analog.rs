@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Analog functionality specific to the Teensy 3.6 board
+
+use crate::{
+    analog::{AdcOptions, AnalogError, TriggerOptions},
+    hw::{
+        board::teensy_common::{
+            analog::{self, AnalogState, ComparatorState, TriggeredSampler},
+            digital::AnalogOp,
+        },
+        mcu::kinetis::{peripheral::dac::Dac, Mk66Fx1M0},
+    },
+    sync::Mutex,
+};
+use core::{future::Future, sync::atomic::Ordering};
+
+pub use crate::hw::board::teensy_common::analog::{adc_0_intr, adc_1_intr, cmp_0_intr, cmp_1_intr};
+
+static ADC_0: Mutex<AnalogState<Mk66Fx1M0, 0>> = Mutex::new(AnalogState::new());
+static ADC_1: Mutex<AnalogState<Mk66Fx1M0, 1>> = Mutex::new(AnalogState::new());
+static DAC_0: Mutex<Option<Dac<Mk66Fx1M0, 0>>> = Mutex::new(None);
+static DAC_1: Mutex<Option<Dac<Mk66Fx1M0, 1>>> = Mutex::new(None);
+static COMPARATORS: Mutex<ComparatorState<Mk66Fx1M0>> = Mutex::new(ComparatorState::new());
+
+/// Configure the ADC
+pub fn set_adc_options(options: AdcOptions) -> Result<(), AnalogError> {
+    ADC_0
+        .lock()
+        .set_options(options, super::BUS_FREQ.load(Ordering::Relaxed))
+}
+
+/// The current ADC configuration
+pub fn adc_options() -> AdcOptions {
+    ADC_0.lock().options()
+}
+
+/// Read an analog pin on ADC 0
+pub fn analog_read(pin: usize) -> impl Future<Output = Result<u16, AnalogError>> {
+    async move {
+        let (channel, mux_b) = adc_channel(pin).ok_or(AnalogError::InvalidPin)?;
+        // The dedicated analog pin 64 has no digital functions
+        if pin < 64 {
+            super::digital::pin_op::<AnalogOp>(pin, ());
+        }
+        analog::read(
+            &ADC_0,
+            channel,
+            mux_b,
+            super::BUS_FREQ.load(Ordering::Relaxed),
+        )
+        .await
+    }
+}
+
+/// The ADC 0 channel of a pin, and whether it is on the `B` multiplexer
+fn adc_channel(pin: usize) -> Option<(u32, bool)> {
+    let channel = match pin {
+        14 => (5, true),
+        15 => (14, false),
+        16 => (8, false),
+        17 => (9, false),
+        18 => (13, false),
+        19 => (12, false),
+        20 => (6, true),
+        21 => (7, true),
+        22 => (15, false),
+        23 => (4, true),
+        33 => (17, false),
+        34 => (18, false),
+        64 => (3, false),
+        _ => return None,
+    };
+    Some(channel)
+}
+
+/// Start hardware-triggered conversions
+///
+/// ADC 1 is used only for paired channels.
+pub fn start_triggered(options: TriggerOptions) -> Result<TriggeredSampler, AnalogError> {
+    ADC_0.lock().start_paired(
+        options,
+        &mut ADC_1.lock(),
+        super::BUS_FREQ.load(Ordering::Relaxed),
+        stop_triggered,
+    )
+}
+
+fn stop_triggered() {
+    ADC_0.lock().stop_triggered(Some(&mut ADC_1.lock()));
+}
+
+/// Set the output of a DAC
+pub fn dac_write(channel: usize, value: u16) -> Result<(), AnalogError> {
+    match channel {
+        0 => analog::dac_write(&DAC_0, value),
+        1 => analog::dac_write(&DAC_1, value),
+        _ => Err(AnalogError::InvalidChannel),
+    }
+}
+
+/// Wait until the voltage on a pin rises above a threshold
+pub fn wait_above(pin: usize, millivolts: u32) -> impl Future<Output = Result<(), AnalogError>> {
+    async move {
+        let (comparator, input) = comparator_input(pin).ok_or(AnalogError::InvalidPin)?;
+        // The DAC outputs `level + 1` 64ths of the supply
+        let steps = millivolts.saturating_mul(64).saturating_add(1650) / 3300;
+        if !(1..=64).contains(&steps) {
+            return Err(AnalogError::InvalidThreshold);
+        }
+        super::digital::pin_op::<AnalogOp>(pin, ());
+        analog::wait_above(&COMPARATORS, comparator, input, steps as u8 - 1).await
+    }
+}
+
+/// The comparator and input channel of a pin
+fn comparator_input(pin: usize) -> Option<(usize, u8)> {
+    let input = match pin {
+        9 => (1, 0),
+        10 => (1, 1),
+        11 => (0, 0),
+        12 => (0, 1),
+        35 => (0, 2),
+        36 => (0, 3),
+        _ => return None,
+    };
+    Some(input)
+}
@@ -0,0 +1,134 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! PWM functionality specific to the Teensy 3.6 board
+
+use crate::{
+    hw::{
+        board::teensy_common::{
+            digital::MuxOp,
+            pwm::{self, PwmBoard, PwmPin, PwmTimer},
+            soft_pwm,
+        },
+        mcu::kinetis::{
+            mk66fx1m0::{Ftm, Sim, Tpm, UartClockSource},
+            peripheral::sim::GatedPeripheral,
+            Mk66Fx1M0,
+        },
+    },
+    pwm::{PwmChannel, PwmError},
+    sync::Mutex,
+};
+use core::sync::atomic::Ordering;
+
+pub use crate::hw::board::teensy_common::soft_pwm::pit_intr;
+
+/// Timers 0 through 3 are FTM0 through FTM3, and timer 4 is TPM1.
+static PWM_CHANNELS: [PwmChannel; 22] = [
+    PwmChannel::new(22, 0, 0),
+    PwmChannel::new(23, 0, 1),
+    PwmChannel::new(9, 0, 2),
+    PwmChannel::new(10, 0, 3),
+    PwmChannel::new(6, 0, 4),
+    PwmChannel::new(20, 0, 5),
+    PwmChannel::new(21, 0, 6),
+    PwmChannel::new(5, 0, 7),
+    PwmChannel::new(3, 1, 0),
+    PwmChannel::new(4, 1, 1),
+    PwmChannel::new(29, 2, 0),
+    PwmChannel::new(30, 2, 1),
+    PwmChannel::new(2, 3, 0),
+    PwmChannel::new(14, 3, 1),
+    PwmChannel::new(7, 3, 2),
+    PwmChannel::new(8, 3, 3),
+    PwmChannel::new(35, 3, 4),
+    PwmChannel::new(36, 3, 5),
+    PwmChannel::new(37, 3, 6),
+    PwmChannel::new(38, 3, 7),
+    PwmChannel::new(16, 4, 0),
+    PwmChannel::new(17, 4, 1),
+];
+
+static FTM_0: Mutex<Option<Ftm<0>>> = Mutex::new(None);
+// Encoders take FTM1 and FTM2 from here while no channel is in use
+pub(super) static FTM_1: Mutex<Option<Ftm<1>>> = Mutex::new(None);
+pub(super) static FTM_2: Mutex<Option<Ftm<2>>> = Mutex::new(None);
+static FTM_3: Mutex<Option<Ftm<3>>> = Mutex::new(None);
+static TPM_1: Mutex<Option<Tpm<1>>> = Mutex::new(None);
+
+/// Every PWM-capable pin on this board
+pub fn pwm_channels() -> &'static [PwmChannel] {
+    &PWM_CHANNELS
+}
+
+/// Get the hardware PWM output of a pin
+pub fn pwm_pin(pin: usize) -> Result<PwmPin<Timers>, PwmError> {
+    PwmPin::new(pin)
+}
+
+/// The hardware PWM timers of this board
+pub struct Timers;
+
+impl PwmBoard for Timers {
+    fn with_timer<R>(timer: usize, f: impl FnOnce(&mut dyn PwmTimer) -> R) -> Option<R> {
+        match timer {
+            0 => pwm::with_timer(&FTM_0, enable, f),
+            1 => pwm::with_timer(&FTM_1, enable, f),
+            2 => pwm::with_timer(&FTM_2, enable, f),
+            3 => pwm::with_timer(&FTM_3, enable, f),
+            4 => pwm::with_timer(&TPM_1, enable_tpm, f),
+            _ => None,
+        }
+    }
+
+    fn timer_clock(timer: usize) -> usize {
+        // The TPM counts the 16MHz crystal
+        match timer {
+            4 => 16_000_000,
+            _ => super::BUS_FREQ.load(Ordering::Relaxed),
+        }
+    }
+
+    fn connect_pin(pin: usize) -> bool {
+        let mux = match pin {
+            3 | 4 | 29 | 30 | 35..=38 => 3,
+            16 | 17 => 6,
+            _ => 4,
+        };
+        super::digital::pin_op::<MuxOp>(pin, mux).is_some()
+    }
+}
+
+fn enable<P: GatedPeripheral<Mk66Fx1M0>>() -> Option<P> {
+    Sim::get()?.enable_peripheral()
+}
+
+fn enable_tpm<P: GatedPeripheral<Mk66Fx1M0>>() -> Option<P> {
+    let mut sim = Sim::get()?;
+    sim.set_tpm_source(Some(UartClockSource::Oscer));
+    sim.enable_peripheral()
+}
+
+/// Set the duty cycle of any digital pin, with software PWM
+///
+/// See [`crate::pwm::soft_pwm_write`] for details.
+pub fn soft_pwm_write(pin: usize, duty: usize) -> Result<(), PwmError> {
+    soft_pwm::write(pin, duty, super::BUS_FREQ.load(Ordering::Relaxed), || {
+        super::time::PIT.lock().take()
+    })
+}
+
+/// Stop driving a pin with software PWM
+pub fn soft_pwm_release(pin: usize) {
+    soft_pwm::release(pin, super::BUS_FREQ.load(Ordering::Relaxed));
+}
+
+/// Set the frequency of every software PWM pin, in Hz
+pub fn set_soft_pwm_frequency(frequency: usize) -> Result<(), PwmError> {
+    soft_pwm::set_frequency(frequency, super::BUS_FREQ.load(Ordering::Relaxed))
+}
+
+/// Set the resolution of software PWM duty cycles, in bits
+pub fn set_soft_pwm_resolution(bits: u32) -> Result<(), PwmError> {
+    soft_pwm::set_resolution(bits, super::BUS_FREQ.load(Ordering::Relaxed))
+}
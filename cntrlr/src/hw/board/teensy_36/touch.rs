@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Touch sensing specific to the Teensy 3.6 board
+
+use crate::{
+    hw::{
+        board::teensy_common::{
+            digital::AnalogOp,
+            touch::{self, TouchState},
+        },
+        mcu::kinetis::{mk66fx1m0::Tsi, Mk66Fx1M0},
+    },
+    sync::Mutex,
+    touch::{TouchError, TouchOptions},
+};
+use core::future::Future;
+
+static TOUCH: Mutex<TouchState<Tsi>> = Mutex::new(TouchState::new());
+
+/// Configure touch sensing
+pub fn set_touch_options(options: TouchOptions) -> Result<(), TouchError> {
+    TOUCH.lock().set_options(options)
+}
+
+/// The current touch sensing configuration
+pub fn touch_options() -> TouchOptions {
+    TOUCH.lock().options()
+}
+
+/// Measure the capacitance of a pin
+pub fn touch_read(pin: usize) -> impl Future<Output = Result<u16, TouchError>> {
+    async move {
+        let channel = tsi_channel(pin).ok_or(TouchError::InvalidPin)?;
+        super::digital::pin_op::<AnalogOp>(pin, ());
+        touch::read::<Mk66Fx1M0, _>(&TOUCH, channel).await
+    }
+}
+
+fn tsi_channel(pin: usize) -> Option<u32> {
+    match pin {
+        0 => Some(9),
+        1 => Some(10),
+        15 => Some(13),
+        16 => Some(0),
+        17 => Some(6),
+        18 => Some(8),
+        19 => Some(7),
+        22 => Some(14),
+        23 => Some(15),
+        29 => Some(11),
+        30 => Some(12),
+        _ => None,
+    }
+}
@@ -0,0 +1,19 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Battery-backed storage specific to the Teensy 3.6 board
+
+use crate::{
+    hw::{board::teensy_common::vbat as common, mcu::kinetis::Mk66Fx1M0},
+    vbat::VbatError,
+};
+
+/// Read from battery-backed storage
+pub fn read(offset: usize, buf: &mut [u8]) -> Result<(), VbatError> {
+    common::read::<Mk66Fx1M0>(offset, buf)
+}
+
+/// Write to battery-backed storage
+pub fn write(offset: usize, data: &[u8]) -> Result<(), VbatError> {
+    common::write::<Mk66Fx1M0>(offset, data)
+}
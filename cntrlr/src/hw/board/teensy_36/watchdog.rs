@@ -0,0 +1,6 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Watchdog functionality specific to the Teensy 3.6 board
+
+pub use crate::hw::board::teensy_common::watchdog::*;
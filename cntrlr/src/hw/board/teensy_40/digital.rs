@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Digital pin support specific to the Teensy 4.0
+
+use crate::{
+    digital::{Led, PinMode},
+    hw::board::teensy_4_common::digital::{ModeOp, OutputOp, PinOp, ReadOp, WriteOp},
+};
+
+pub use crate::hw::board::teensy_4_common::digital::{gpio_1, gpio_2, gpio_3, gpio_4};
+
+pin_table! {
+    D0 = 0 => gpio_1(1, 3),
+    D1 = 1 => gpio_1(1, 2),
+    D2 = 2 => gpio_4(4, 4),
+    D3 = 3 => gpio_4(4, 5),
+    D4 = 4 => gpio_4(4, 6),
+    D5 = 5 => gpio_4(4, 8),
+    D6 = 6 => gpio_2(2, 10),
+    D7 = 7 => gpio_2(2, 17),
+    D8 = 8 => gpio_2(2, 16),
+    D9 = 9 => gpio_2(2, 11),
+    D10 = 10 => gpio_2(2, 0),
+    D11 = 11 => gpio_2(2, 2),
+    D12 = 12 => gpio_2(2, 1),
+    D13 = 13 => gpio_2(2, 3),
+    D14 = 14 => gpio_1(1, 18),
+    D15 = 15 => gpio_1(1, 19),
+    D16 = 16 => gpio_1(1, 23),
+    D17 = 17 => gpio_1(1, 22),
+    D18 = 18 => gpio_1(1, 17),
+    D19 = 19 => gpio_1(1, 16),
+    D20 = 20 => gpio_1(1, 26),
+    D21 = 21 => gpio_1(1, 27),
+    D22 = 22 => gpio_1(1, 24),
+    D23 = 23 => gpio_1(1, 25),
+    D24 = 24 => gpio_1(1, 12),
+    D25 = 25 => gpio_1(1, 13),
+    D26 = 26 => gpio_1(1, 30),
+    D27 = 27 => gpio_1(1, 31),
+    D28 = 28 => gpio_3(3, 18),
+    D29 = 29 => gpio_4(4, 31),
+    D30 = 30 => gpio_3(3, 23),
+    D31 = 31 => gpio_3(3, 22),
+    D32 = 32 => gpio_2(2, 12),
+    D33 = 33 => gpio_4(4, 7),
+    D34 = 34 => gpio_3(3, 15),
+    D35 = 35 => gpio_3(3, 14),
+    D36 = 36 => gpio_3(3, 13),
+    D37 = 37 => gpio_3(3, 12),
+    D38 = 38 => gpio_3(3, 17),
+    D39 = 39 => gpio_3(3, 16),
+    ;
+    A0 = D14,
+    A1 = D15,
+    A2 = D16,
+    A3 = D17,
+    A4 = D18,
+    A5 = D19,
+    A6 = D20,
+    A7 = D21,
+    A8 = D22,
+    A9 = D23,
+    A10 = D24,
+    A11 = D25,
+    A12 = D26,
+    A13 = D27,
+    LED_PIN = D13,
+}
+
+/// Set a digital pin high or low.
+///
+/// The digital pins on the Teensy 4.0 use 3.3V, and are not 5V
+/// tolerant.
+///
+/// If `pin` is not a valid pin, does nothing.
+///
+/// Interactions with this method may be unpredictable if the pin
+/// is not in an output mode or is in use by another module. In
+/// particular, whether the write takes effect when the pin
+/// becomes a digital output is MCU-specific and should not be
+/// relied upon.
+#[inline]
+pub fn digital_write(pin: usize, value: bool) {
+    pin_op::<WriteOp>(pin, value);
+}
+
+/// Read the state of a digital pin.
+///
+/// The digital pins on the Teensy 4.0 output 3.3V.
+///
+/// If `pin` is not a valid pin, returns `false`
+///
+/// The return value is implementation-specific and should not be
+/// relied upon in the following cases:
+/// * The pin is not set as a digital input
+/// * The pin is in use by a different module
+/// * The pin number is outside the range of pins on the board.
+#[inline]
+pub fn digital_read(pin: usize) -> bool {
+    pin_op::<ReadOp>(pin, ()).unwrap_or(false)
+}
+
+/// Set a pin as a digital input or output
+///
+/// If `pin` is not a valid pin, does nothing.
+///
+/// Pull-ups are 22kΩ, and pull-downs are 100kΩ.
+///
+/// Interactions with this method may be unpredictable if the pin
+/// is in use by another module. In particular, whether or not
+/// changes made by this method will take effect when the other
+/// module releases the pin is implementation specific and should
+/// not be relied upon.
+#[inline]
+pub fn pin_mode(pin: usize, mode: PinMode) {
+    pin_op::<ModeOp>(pin, mode);
+}
+
+/// The pin connected to the on-board LED
+///
+/// On the Teensy 4.0, the LED is on pin 13, which is GPIO2 pin 3 on the
+/// i.MX RT1062.
+#[inline]
+pub fn led_builtin() -> usize {
+    pins::LED_PIN
+}
+
+/// Turn the on-board LED on or off
+///
+/// This sets the LED pin as an output, so it does not need to be
+/// configured with [`pin_mode`] first.
+#[inline]
+pub fn led_write(on: bool) {
+    pin_mode(led_builtin(), PinMode::Output);
+    digital_write(led_builtin(), on);
+}
+
+/// Check whether the on-board LED is on
+///
+/// This reads the state the LED pin is set to output.
+#[inline]
+pub fn led_read() -> bool {
+    pin_op::<OutputOp>(led_builtin(), ()).unwrap_or(false)
+}
+
+/// Get the on-board LED
+#[inline]
+pub fn led() -> Led {
+    Led(())
+}
@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Board-specific functionality for the Teensy 4.0
+
+pub use super::teensy_4_common::{set_clock, SetClockError};
+
+pub mod digital;
+pub mod io;
+pub mod time;
+
+/// The FlexSPI configuration
+///
+/// This describes the Teensy 4.0's 2MB flash chip to the boot ROM. It
+/// will automatically be included as the standard FlexSPI
+/// configuration when this board is selected.
+#[cfg_attr(board = "teensy_40", link_section = ".__CNTRLR_FLEXSPI_CONFIG")]
+#[cfg_attr(board = "teensy_40", export_name = "__cntrlr_flexspi_configuration")]
+pub static FLEXSPI_CONFIGURATION: [u32; 128] =
+    super::teensy_4_common::flexspi_configuration(0x0020_0000);
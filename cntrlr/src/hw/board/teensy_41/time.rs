@@ -0,0 +1,6 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Time functionality specific to the Teensy 4.1 board
+
+pub use crate::hw::board::teensy_4_common::time::*;
@@ -0,0 +1,144 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Digital pin functionality shared between the Teensy 4.x boards
+
+use crate::{
+    digital::PinMode,
+    hw::mcu::imxrt::imxrt1062::{Gpio, Pin},
+    sync::Once,
+};
+
+/// An operation on a pin.
+///
+/// This abstracts the mapping of Teensy pins to MCU pins, allowing
+/// operations to be defined generically and then invoked for any
+/// given pin.
+pub trait PinOp {
+    /// The type of argument the operation expects
+    type Arg;
+
+    /// The result of the operation
+    type Result;
+
+    /// The operation, performed on a single pin
+    fn op<const N: usize, const P: usize>(pin: Pin<'_, N, P>, arg: Self::Arg) -> Self::Result;
+
+    /// The operation, optionally performed on an optional pin.
+    #[inline(always)]
+    fn do_op<const N: usize, const P: usize>(
+        pin: Option<Pin<'_, N, P>>,
+        arg: Self::Arg,
+    ) -> Option<Self::Result> {
+        if let Some(pin) = pin {
+            Some(Self::op(pin, arg))
+        } else {
+            None
+        }
+    }
+}
+
+/// An operation to write a pin as high or low
+pub struct WriteOp;
+impl PinOp for WriteOp {
+    type Arg = bool;
+    type Result = ();
+
+    #[inline(always)]
+    fn op<const N: usize, const P: usize>(pin: Pin<'_, N, P>, value: bool) {
+        pin.into_gpio().write(value);
+    }
+}
+
+/// An operation to read a pin as high or low
+pub struct ReadOp;
+impl PinOp for ReadOp {
+    type Arg = ();
+    type Result = bool;
+
+    #[inline(always)]
+    fn op<const N: usize, const P: usize>(pin: Pin<'_, N, P>, _: ()) -> bool {
+        pin.into_gpio().read()
+    }
+}
+
+/// An operation to read the state a pin is set to output
+pub struct OutputOp;
+impl PinOp for OutputOp {
+    type Arg = ();
+    type Result = bool;
+
+    #[inline(always)]
+    fn op<const N: usize, const P: usize>(pin: Pin<'_, N, P>, _: ()) -> bool {
+        pin.into_gpio().read_output()
+    }
+}
+
+/// An operation to set a pin's [mode](`PinMode`)
+pub struct ModeOp;
+impl PinOp for ModeOp {
+    type Arg = PinMode;
+    type Result = ();
+
+    #[inline(always)]
+    fn op<const N: usize, const P: usize>(pin: Pin<'_, N, P>, mode: PinMode) {
+        let mut pin = pin.into_gpio();
+        pin.set_drive_strength(7);
+        match mode {
+            PinMode::Input => {
+                pin.set_pull(None);
+                pin.set_output(false);
+            }
+            PinMode::PulledInput(pull) => {
+                pin.set_pull(Some(pull));
+                pin.set_output(false);
+            }
+            PinMode::Output => {
+                pin.set_open_drain(false);
+                pin.set_output(true);
+                pin.set_pull(None);
+            }
+            PinMode::OpenDrainOutput => {
+                pin.set_open_drain(true);
+                pin.set_output(true);
+                pin.set_pull(None);
+            }
+        }
+    }
+}
+
+/// GPIO1
+///
+/// The global instance of GPIO1, used to share ownership among
+/// different board modules.
+pub fn gpio_1() -> Option<&'static Gpio<1>> {
+    static PORT: Once<Gpio<1>> = Once::new();
+    PORT.get_or_try_init(Gpio::get)
+}
+
+/// GPIO2
+///
+/// The global instance of GPIO2, used to share ownership among
+/// different board modules.
+pub fn gpio_2() -> Option<&'static Gpio<2>> {
+    static PORT: Once<Gpio<2>> = Once::new();
+    PORT.get_or_try_init(Gpio::get)
+}
+
+/// GPIO3
+///
+/// The global instance of GPIO3, used to share ownership among
+/// different board modules.
+pub fn gpio_3() -> Option<&'static Gpio<3>> {
+    static PORT: Once<Gpio<3>> = Once::new();
+    PORT.get_or_try_init(Gpio::get)
+}
+
+/// GPIO4
+///
+/// The global instance of GPIO4, used to share ownership among
+/// different board modules.
+pub fn gpio_4() -> Option<&'static Gpio<4>> {
+    static PORT: Once<Gpio<4>> = Once::new();
+    PORT.get_or_try_init(Gpio::get)
+}
@@ -0,0 +1,595 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! IO functionality shared between the Teensy 4.x boards
+
+use super::digital::{gpio_1, gpio_2};
+use crate::{
+    hw::mcu::imxrt::{
+        imxrt1062::{Flexio, FlexioPin, Lpuart, Pin, ProgrammableIo, UartRx, UartTx},
+        peripheral::{lpuart, Peripheral},
+        Imxrt1062,
+    },
+    io::{self, IoSlice, Read, Write},
+    sync::{AsyncMutex, AsyncMutexLock},
+    task::WakerSet,
+};
+use core::{
+    future::{poll_fn, Future},
+    ptr::{read_volatile, write_volatile},
+    task::Poll,
+};
+
+/// An error from a serial interface
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SerialError {
+    /// The serial port cannot be read or written because it is disabled
+    NotEnabled,
+
+    /// The serial port cannot be enabled because its TX or RX pin is in use
+    PinInUse,
+
+    /// The serial port cannot be enabled because its UART is in use
+    UartInUse,
+
+    /// The serial port cannot be enabled because its FlexIO module is in use
+    FlexioInUse,
+
+    /// The serial port cannot be enabled because the GPIO is in use
+    GpioInUse,
+
+    /// The serial port cannot be enabled because the selected baud rate is invalid
+    InvalidBaud,
+
+    /// The serial port cannot be enabled because a requested option is invalid
+    InvalidOption,
+}
+
+impl From<SerialError> for io::Error {
+    fn from(err: SerialError) -> Self {
+        let kind = match err {
+            SerialError::NotEnabled => io::ErrorKind::NotEnabled,
+            SerialError::PinInUse
+            | SerialError::UartInUse
+            | SerialError::FlexioInUse
+            | SerialError::GpioInUse => io::ErrorKind::InUse,
+            SerialError::InvalidBaud | SerialError::InvalidOption => io::ErrorKind::InvalidSetting,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+/// A serial interface
+///
+/// This wraps an LPUART and provides application-level functionality.
+pub struct Serial<T, R, const N: usize>(
+    Option<Lpuart<T, R, N>>,
+    Option<&'static WakerSet>,
+    io::SerialStats,
+);
+
+impl<T, R, const N: usize> Read for Serial<T, R, N>
+where
+    T: 'static,
+    R: lpuart::UartRx<Imxrt1062, N> + 'static,
+{
+    type Error = SerialError;
+    type Future<'a> = impl Future<Output = Result<usize, Self::Error>>;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> <Self as Read>::Future<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            if buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+
+            let mut count = 0;
+            let uart = self.0.as_mut().ok_or(SerialError::NotEnabled)?;
+            if uart.check_overrun() {
+                self.2.overruns = self.2.overruns.wrapping_add(1);
+            }
+            while let Some(byte) = uart.read_data() {
+                buf[count] = byte;
+                count += 1;
+                if count >= buf.len() {
+                    break;
+                }
+            }
+            if count > 0 {
+                self.2.max_burst = self.2.max_burst.max(count);
+                Poll::Ready(Ok(count))
+            } else {
+                if let Some(wakers) = self.1.as_ref() {
+                    wakers.add(ctx.waker().clone());
+                }
+                uart.enable_rx_intr();
+                Poll::Pending
+            }
+        })
+    }
+}
+
+impl<T, R, const N: usize> Write for Serial<T, R, N>
+where
+    T: lpuart::UartTx<Imxrt1062, N> + 'static,
+    R: 'static,
+{
+    type Error = SerialError;
+    type Future<'a> = impl Future<Output = Result<usize, Self::Error>>;
+    type FlushFuture<'a> = impl Future<Output = Result<(), Self::Error>>;
+    type VectoredFuture<'a> = impl Future<Output = Result<usize, Self::Error>>;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> <Self as Write>::Future<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            if buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+
+            let mut count = 0;
+            let mut buf = buf;
+            let uart = self.0.as_mut().ok_or(SerialError::NotEnabled)?;
+            while uart.write_data(buf[0]) {
+                count += 1;
+                buf = &buf[1..];
+                if buf.is_empty() {
+                    break;
+                }
+            }
+            if count > 0 {
+                Poll::Ready(Ok(count))
+            } else {
+                if let Some(wakers) = self.1.as_ref() {
+                    wakers.add(ctx.waker().clone());
+                }
+                uart.enable_tx_intr();
+                Poll::Pending
+            }
+        })
+    }
+
+    fn write_vectored<'a>(
+        &'a mut self,
+        bufs: &'a [IoSlice<'a>],
+    ) -> <Self as Write>::VectoredFuture<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            if bufs.iter().all(|buf| buf.is_empty()) {
+                return Poll::Ready(Ok(0));
+            }
+
+            let mut count = 0;
+            let uart = self.0.as_mut().ok_or(SerialError::NotEnabled)?;
+            'bufs: for buf in bufs {
+                for &byte in buf.iter() {
+                    if !uart.write_data(byte) {
+                        break 'bufs;
+                    }
+                    count += 1;
+                }
+            }
+            if count > 0 {
+                Poll::Ready(Ok(count))
+            } else {
+                if let Some(wakers) = self.1.as_ref() {
+                    wakers.add(ctx.waker().clone());
+                }
+                uart.enable_tx_intr();
+                Poll::Pending
+            }
+        })
+    }
+
+    fn flush<'a>(&'a mut self) -> <Self as Write>::FlushFuture<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            let uart = self.0.as_mut().ok_or(SerialError::NotEnabled)?;
+            if uart.is_transmit_complete() {
+                Poll::Ready(Ok(()))
+            } else {
+                if let Some(wakers) = self.1.as_ref() {
+                    wakers.add(ctx.waker().clone());
+                }
+                uart.enable_complete_intr();
+                Poll::Pending
+            }
+        })
+    }
+}
+
+impl<T, R, const N: usize> Serial<T, R, N>
+where
+    R: lpuart::UartRx<Imxrt1062, N>,
+{
+    /// Read whatever data the UART has already received
+    ///
+    /// This drains the receive FIFO once, without waiting or
+    /// registering a waker, so it can be used outside the executor,
+    /// such as from an interrupt or panic handler. Returns the number
+    /// of bytes read, which is zero if the port is disabled.
+    pub fn try_read(&mut self, buf: &mut [u8]) -> usize {
+        let uart = match self.0.as_mut() {
+            Some(uart) => uart,
+            None => return 0,
+        };
+        if uart.check_overrun() {
+            self.2.overruns = self.2.overruns.wrapping_add(1);
+        }
+        let mut count = 0;
+        while count < buf.len() {
+            match uart.read_data() {
+                Some(byte) => buf[count] = byte,
+                None => break,
+            }
+            count += 1;
+        }
+        self.2.max_burst = self.2.max_burst.max(count);
+        count
+    }
+}
+
+impl<T, R, const N: usize> Serial<T, R, N>
+where
+    T: lpuart::UartTx<Imxrt1062, N>,
+{
+    /// Write as much data as the UART can accept right now
+    ///
+    /// This fills the transmit FIFO once, without waiting or
+    /// registering a waker, so it can be used outside the executor,
+    /// such as from an interrupt or panic handler. Returns the number
+    /// of bytes written, which is zero if the port is disabled.
+    pub fn try_write(&mut self, buf: &[u8]) -> usize {
+        let uart = match self.0.as_mut() {
+            Some(uart) => uart,
+            None => return 0,
+        };
+        buf.iter()
+            .take_while(|&&byte| uart.write_data(byte))
+            .count()
+    }
+
+    /// Write all of a buffer, busy-waiting while the UART is full
+    ///
+    /// This is for the panic handler, which cannot wait on the
+    /// executor. It returns immediately if the port is disabled.
+    pub(crate) fn write_blocking(&mut self, mut buf: &[u8]) {
+        if self.0.is_none() {
+            return;
+        }
+        while !buf.is_empty() {
+            let count = self.try_write(buf);
+            buf = &buf[count..];
+        }
+    }
+}
+
+/// The pin used to recieve for serial 1
+pub type Serial1Rx = UartRx<Pin<'static, 1, 3>>;
+
+/// The pin used to transmit for serial 1
+pub type Serial1Tx = UartTx<Pin<'static, 1, 2>>;
+
+impl<T, R, const N: usize> Serial<T, R, N>
+where
+    T: lpuart::UartTx<Imxrt1062, N>,
+    R: lpuart::UartRx<Imxrt1062, N>,
+    Lpuart<(), (), N>: Peripheral,
+{
+    fn do_enable(
+        &mut self,
+        baud: usize,
+        options: &[io::SerialOption],
+        tx: T,
+        rx: R,
+        wakers: &'static WakerSet,
+    ) -> Result<(), SerialError> {
+        for option in options {
+            match option {
+                io::SerialOption::StopBits(1)
+                | io::SerialOption::DataBits(8)
+                | io::SerialOption::Parity(io::Parity::None) => {}
+                _ => return Err(SerialError::InvalidOption),
+            }
+        }
+        let mut uart = Lpuart::<(), (), N>::get().ok_or(SerialError::UartInUse)?;
+        uart.set_baud(super::UART_FREQ, baud)
+            .map_err(|_| SerialError::InvalidBaud)?;
+        uart.set_watermarks(2, 0);
+        self.0 = Some(uart.enable_tx(tx).enable_rx(rx));
+        self.1 = Some(wakers);
+        Ok(())
+    }
+
+    fn do_disable(&mut self) {
+        self.0 = None;
+        self.1 = None;
+    }
+}
+
+impl io::Serial for Serial<Serial1Tx, Serial1Rx, 6> {
+    type Error = SerialError;
+    fn enable_with_options(
+        &mut self,
+        baud: usize,
+        options: &[io::SerialOption],
+    ) -> Result<(), <Self as io::Serial>::Error> {
+        let tx = gpio_1()
+            .ok_or(SerialError::GpioInUse)?
+            .pin::<2>()
+            .ok_or(SerialError::PinInUse)?
+            .into_uart_tx();
+        let rx = gpio_1()
+            .ok_or(SerialError::GpioInUse)?
+            .pin::<3>()
+            .ok_or(SerialError::PinInUse)?
+            .into_uart_rx();
+        self.do_enable(baud, options, tx, rx, &SERIAL_1_WAKERS)
+    }
+
+    fn disable(&mut self) -> Result<(), <Self as io::Serial>::Error> {
+        self.do_disable();
+        Ok(())
+    }
+
+    fn stats(&self) -> io::SerialStats {
+        self.2
+    }
+
+    fn reset_stats(&mut self) {
+        self.2 = io::SerialStats::new();
+    }
+
+    fn alt_pin_sets(&self) -> &'static [io::SerialPins] {
+        &[io::SerialPins { tx: 1, rx: 0 }]
+    }
+}
+
+/// The first hardware serial port
+///
+/// This is LPUART6, with its transmit pin on pin 1 and its receive
+/// pin on pin 0.
+pub fn serial_1() -> AsyncMutexLock<'static, Serial<Serial1Tx, Serial1Rx, 6>> {
+    static SERIAL: AsyncMutex<Serial<Serial1Tx, Serial1Rx, 6>> =
+        AsyncMutex::new(Serial(None, None, io::SerialStats::new()));
+    SERIAL.lock()
+}
+
+/// The pin used to transmit for FlexIO serial 1
+pub type FlexioSerial1Tx = FlexioPin<Pin<'static, 2, 10>>;
+
+/// The pin used to recieve for FlexIO serial 1
+pub type FlexioSerial1Rx = FlexioPin<Pin<'static, 2, 11>>;
+
+/// A serial interface built from FlexIO
+///
+/// The UART is made from shifter and timer 0, which transmit, and
+/// shifter and timer 1, which recieve. It has a single word of
+/// buffering in each direction.
+pub struct FlexioSerial(
+    Option<(Flexio<2>, FlexioSerial1Tx, FlexioSerial1Rx)>,
+    io::SerialStats,
+);
+
+/// The shifter and timer which transmit for a FlexIO serial port
+const FLEXIO_TX: usize = 0;
+
+/// The shifter and timer which recieve for a FlexIO serial port
+const FLEXIO_RX: usize = 1;
+
+impl Read for FlexioSerial {
+    type Error = SerialError;
+    type Future<'a> = impl Future<Output = Result<usize, Self::Error>>;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> <Self as Read>::Future<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            if buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+
+            let (flexio, _, _) = self.0.as_mut().ok_or(SerialError::NotEnabled)?;
+            if flexio.check_shifter_error(FLEXIO_RX) {
+                self.1.overruns = self.1.overruns.wrapping_add(1);
+            }
+            // Each byte is shifted in at the top of the word
+            match flexio.read_data(FLEXIO_RX) {
+                Some(word) => {
+                    buf[0] = (word >> 24) as u8;
+                    self.1.max_burst = self.1.max_burst.max(1);
+                    Poll::Ready(Ok(1))
+                }
+                None => {
+                    FLEXIO_SERIAL_1_WAKERS.add(ctx.waker().clone());
+                    flexio.enable_shifter_intr(FLEXIO_RX);
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
+
+impl Write for FlexioSerial {
+    type Error = SerialError;
+    type Future<'a> = impl Future<Output = Result<usize, Self::Error>>;
+    type FlushFuture<'a> = impl Future<Output = Result<(), Self::Error>>;
+    type VectoredFuture<'a> = impl Future<Output = Result<usize, Self::Error>>;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> <Self as Write>::Future<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            if buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+
+            let (flexio, _, _) = self.0.as_mut().ok_or(SerialError::NotEnabled)?;
+            if flexio.write_data(FLEXIO_TX, buf[0] as u32) {
+                Poll::Ready(Ok(1))
+            } else {
+                FLEXIO_SERIAL_1_WAKERS.add(ctx.waker().clone());
+                flexio.enable_shifter_intr(FLEXIO_TX);
+                Poll::Pending
+            }
+        })
+    }
+
+    fn write_vectored<'a>(
+        &'a mut self,
+        bufs: &'a [IoSlice<'a>],
+    ) -> <Self as Write>::VectoredFuture<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            let byte = match bufs.iter().find_map(|buf| buf.first()) {
+                Some(&byte) => byte,
+                None => return Poll::Ready(Ok(0)),
+            };
+
+            let (flexio, _, _) = self.0.as_mut().ok_or(SerialError::NotEnabled)?;
+            if flexio.write_data(FLEXIO_TX, byte as u32) {
+                Poll::Ready(Ok(1))
+            } else {
+                FLEXIO_SERIAL_1_WAKERS.add(ctx.waker().clone());
+                flexio.enable_shifter_intr(FLEXIO_TX);
+                Poll::Pending
+            }
+        })
+    }
+
+    fn flush<'a>(&'a mut self) -> <Self as Write>::FlushFuture<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            let (flexio, _, _) = self.0.as_mut().ok_or(SerialError::NotEnabled)?;
+            // The buffer reads as empty once the shifter has taken
+            // the last byte, which may still be being sent.
+            if flexio.shifter_ready(FLEXIO_TX) {
+                Poll::Ready(Ok(()))
+            } else {
+                FLEXIO_SERIAL_1_WAKERS.add(ctx.waker().clone());
+                flexio.enable_shifter_intr(FLEXIO_TX);
+                Poll::Pending
+            }
+        })
+    }
+}
+
+impl io::Serial for FlexioSerial {
+    type Error = SerialError;
+    fn enable_with_options(
+        &mut self,
+        baud: usize,
+        options: &[io::SerialOption],
+    ) -> Result<(), <Self as io::Serial>::Error> {
+        for option in options {
+            match option {
+                io::SerialOption::StopBits(1)
+                | io::SerialOption::DataBits(8)
+                | io::SerialOption::Parity(io::Parity::None) => {}
+                _ => return Err(SerialError::InvalidOption),
+            }
+        }
+        let mut flexio = Flexio::<2>::get().ok_or(SerialError::FlexioInUse)?;
+        flexio
+            .set_uart_tx(FLEXIO_TX, FLEXIO_TX, 10, super::FLEXIO_FREQ, baud)
+            .map_err(|_| SerialError::InvalidBaud)?;
+        flexio
+            .set_uart_rx(FLEXIO_RX, FLEXIO_RX, 11, super::FLEXIO_FREQ, baud)
+            .map_err(|_| SerialError::InvalidBaud)?;
+        let tx = gpio_2()
+            .ok_or(SerialError::GpioInUse)?
+            .pin::<10>()
+            .ok_or(SerialError::PinInUse)?
+            .into_flexio();
+        let rx = gpio_2()
+            .ok_or(SerialError::GpioInUse)?
+            .pin::<11>()
+            .ok_or(SerialError::PinInUse)?
+            .into_flexio();
+        flexio.enable();
+        self.0 = Some((flexio, tx, rx));
+        Ok(())
+    }
+
+    fn disable(&mut self) -> Result<(), <Self as io::Serial>::Error> {
+        self.0 = None;
+        Ok(())
+    }
+
+    fn stats(&self) -> io::SerialStats {
+        self.1
+    }
+
+    fn reset_stats(&mut self) {
+        self.1 = io::SerialStats::new();
+    }
+
+    fn alt_pin_sets(&self) -> &'static [io::SerialPins] {
+        &[io::SerialPins { tx: 6, rx: 9 }]
+    }
+}
+
+/// The first FlexIO serial port
+///
+/// This is a UART built from FlexIO2, with its transmit pin on pin 6
+/// and its recieve pin on pin 9. Baud rates from about 15kbaud to
+/// 3.75Mbaud can be used.
+pub fn flexio_serial_1() -> AsyncMutexLock<'static, FlexioSerial> {
+    static SERIAL: AsyncMutex<FlexioSerial> =
+        AsyncMutex::new(FlexioSerial(None, io::SerialStats::new()));
+    SERIAL.lock()
+}
+
+/// Write to a serial port from the panic handler
+///
+/// The port is used even if a task holds it, since that task will
+/// never run again. Nothing is written if the port is disabled.
+pub fn panic_write(port: usize, bytes: &[u8]) {
+    if port == 1 {
+        unsafe { serial_1().steal() }.write_blocking(bytes);
+    }
+}
+
+static SERIAL_1_WAKERS: WakerSet = WakerSet::new();
+
+/// The interrupt function for serial 1
+///
+/// This disables the UART interrupts, which are enabled again by the
+/// next read or write which cannot complete, and wakes the waiting
+/// tasks.
+pub extern "C" fn serial_1_intr() {
+    const LPUART6_CTRL: *mut u32 = 0x4019_8018 as _;
+    unsafe {
+        // RIE, TCIE, and TIE
+        let ctrl = read_volatile(LPUART6_CTRL);
+        write_volatile(LPUART6_CTRL, ctrl & !(0b111 << 21));
+    }
+    SERIAL_1_WAKERS.wake();
+}
+
+static FLEXIO_SERIAL_1_WAKERS: WakerSet = WakerSet::new();
+
+/// The interrupt function for FlexIO serial 1
+///
+/// This disables the shifter interrupts, which are enabled again by
+/// the next read or write which cannot complete, and wakes the
+/// waiting tasks.
+pub extern "C" fn flexio_serial_1_intr() {
+    const FLEXIO2_SHIFTSIEN: *mut u32 = 0x401B_0020 as _;
+    unsafe { write_volatile(FLEXIO2_SHIFTSIEN, 0) };
+    FLEXIO_SERIAL_1_WAKERS.wake();
+}
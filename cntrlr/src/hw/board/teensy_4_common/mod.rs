@@ -0,0 +1,267 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Common board functionality for the Teensy 4.x series
+
+use crate::hw::mcu::imxrt::imxrt1062::{Ccm, Dcdc, Flexio, Gpio, Lpuart, UartClockSource};
+use core::{
+    ptr::write_volatile,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+pub mod digital;
+pub mod io;
+pub mod time;
+
+static CPU_FREQ: AtomicUsize = AtomicUsize::new(0);
+
+/// The frequency of the UART clock
+///
+/// The UARTs are clocked from the 24MHz crystal, so their baud rates
+/// do not change with the core clock.
+const UART_FREQ: usize = 24_000_000;
+
+/// The frequency of the FlexIO2 clock
+///
+/// FlexIO2 is clocked from the USB PLL, divided by 64, so its baud
+/// rates do not change with the core clock either.
+const FLEXIO_FREQ: usize = 7_500_000;
+
+/// Error type for Teensy 4.x clock setting functions.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SetClockError {
+    /// The core clock cannot be changed because the clock controller is in use.
+    CcmInUse,
+
+    /// The core clock cannot be changed because the DC-DC converter is in use.
+    DcdcInUse,
+
+    /// The core clock cannot be set because the requested speed is invalid.
+    InvalidClockRate,
+}
+
+/// Set the clock for this board, in Hz.
+///
+/// Valid values are 600, 528, 450, or 396 MHz
+///
+/// The core voltage is raised for clocks above 528MHz.
+pub fn set_clock(clock: usize) -> Result<(), SetClockError> {
+    let (mult, div_arm, div_ahb, div_ipg) = match clock {
+        600_000_000 => (100, 2, 1, 4),
+        528_000_000 => (88, 2, 1, 4),
+        450_000_000 => (75, 2, 1, 3),
+        396_000_000 => (66, 2, 1, 3),
+        _ => return Err(SetClockError::InvalidClockRate),
+    };
+    let voltage = if clock > 528_000_000 { 1250 } else { 1150 };
+
+    let mut ccm = Ccm::get().ok_or(SetClockError::CcmInUse)?;
+    let mut dcdc = Dcdc::get().ok_or(SetClockError::DcdcInUse)?;
+
+    // The voltage must be high enough for the faster of the two
+    // clocks while switching between them.
+    if voltage > dcdc.voltage() {
+        dcdc.set_voltage(voltage);
+    }
+    ccm.use_arm_pll(mult, div_arm, div_ahb, div_ipg);
+    if voltage < dcdc.voltage() {
+        dcdc.set_voltage(voltage);
+    }
+
+    CPU_FREQ.store(clock, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Early startup for the Teensy 4.x boards
+///
+/// Points the core at the Cntrlr vector table.
+///
+/// This will be included automatically if you are using the standard
+/// Cntrlr runtime. It should be invoked directly as part of startup
+/// only if you are overriding Cntrlr runtime behavior.
+///
+/// # Safety
+/// This function unsafely accesses the system control block.
+#[cfg_attr(
+    any(board = "teensy_40", board = "teensy_41"),
+    export_name = "__cntrlr_board_start"
+)]
+pub unsafe extern "C" fn start() {
+    extern "C" {
+        static __cntrlr_vectors: u8;
+    }
+
+    // TODO: Create a peripheral for the SCB
+    const SCB_VTOR: *mut u32 = 0xE000_ED08 as *mut _;
+    write_volatile(SCB_VTOR, &__cntrlr_vectors as *const _ as u32);
+}
+
+/// Late startup for the Teensy 4.x boards.
+///
+/// Sets the processor clock, turns on the clocks to the GPIO banks,
+/// the serial ports, and FlexIO2, and enables interrupts and
+/// exceptions.
+///
+/// This will be included automatically if you are using the standard
+/// Cntrlr runtime. It should be invoked directly as part of startup
+/// only if you are overriding Cntrlr runtime behavior.
+///
+/// # Safety
+/// This function unsafely accesses the NVIC peripheral.
+#[cfg_attr(
+    any(board = "teensy_40", board = "teensy_41"),
+    export_name = "__cntrlr_board_init"
+)]
+pub unsafe extern "C" fn init() {
+    set_clock(600_000_000).expect("Could not set core clock at init");
+
+    if let Some(mut ccm) = Ccm::get() {
+        ccm.set_uart_clock(UartClockSource::Oscillator, 1);
+        ccm.enable_gate::<Gpio<1>>();
+        ccm.enable_gate::<Gpio<2>>();
+        ccm.enable_gate::<Gpio<3>>();
+        ccm.enable_gate::<Gpio<4>>();
+        ccm.enable_gate::<Lpuart<(), (), 6>>();
+        ccm.set_flexio2_clock(8, 8);
+        ccm.enable_gate::<Flexio<2>>();
+    }
+
+    time::enable_tick();
+
+    // TODO: Create a peripheral for the NVIC
+    const NVIC_ISER: *mut u32 = 0xE000_E100 as *mut _;
+    for intr in &[25, 91] {
+        let reg = intr / 32;
+        let bit = intr % 32;
+
+        write_volatile(NVIC_ISER.add(reg), 1 << bit);
+    }
+}
+
+/// Teensy 4.x reset stub
+///
+/// This is the entry point named in the boot image. It gives all of
+/// the FlexRAM to the DTCM, where the stack and data live, enables
+/// the FPU, and initializes the stack pointer before invoking the
+/// Cntrlr reset function.
+///
+/// # Safety
+/// This function should never be called by user code; it is public
+/// only for linking reasons.
+#[cfg_attr(
+    any(board = "teensy_40", board = "teensy_41"),
+    export_name = "__cntrlr_imxrt_reset"
+)]
+#[cfg_attr(any(board = "teensy_40", board = "teensy_41"), naked)]
+pub unsafe extern "C" fn reset() {
+    extern "C" {
+        fn __cntrlr_reset();
+        static __cntrlr_stack_top: u8;
+    }
+    #[cfg(any(board = "teensy_40", board = "teensy_41"))]
+    asm!("
+        ldr r0, =0x400AC044
+        ldr r1, =0xAAAAAAAA
+        str r1, [r0]
+        ldr r0, =0x400AC040
+        ldr r1, =0x00200007
+        str r1, [r0]
+        ldr r0, =0x400AC038
+        ldr r1, =0x00AA0000
+        str r1, [r0]
+        ldr r0, =0xE000ED88
+        ldr r1, [r0]
+        orr r1, r1, #0x00F00000
+        str r1, [r0]
+        dsb
+        isb
+        ldr r0, ={}
+        mov sp, r0
+        b {}",
+         sym __cntrlr_stack_top, sym __cntrlr_reset, options(noreturn)
+    );
+}
+
+/// The FlexSPI configuration block for a board's flash chip
+///
+/// The boot ROM reads this from the start of flash to learn how to
+/// read the rest of it. The flash chips on the Teensy 4.x boards
+/// differ only in size.
+pub const fn flexspi_configuration(flash_size: u32) -> [u32; 128] {
+    let mut config = [0; 128];
+    config[0x00 / 4] = 0x4246_4346;
+    config[0x04 / 4] = 0x5601_0000;
+    config[0x0C / 4] = 0x0003_0301;
+    config[0x44 / 4] = 0x0008_0401;
+    config[0x50 / 4] = flash_size;
+
+    // The lookup table of flash commands. The first is the quad
+    // read used for execution in place.
+    config[0x80 / 4] = 0x0A18_04EB;
+    config[0x84 / 4] = 0x2604_3206;
+    config[0x90 / 4] = 0x2404_0405;
+    config[0xB0 / 4] = 0x0000_0406;
+    config[0xD0 / 4] = 0x0818_0420;
+    config[0x100 / 4] = 0x0818_04D8;
+    config[0x110 / 4] = 0x0818_0402;
+    config[0x114 / 4] = 0x0000_2004;
+    config[0x130 / 4] = 0x0000_0460;
+
+    // Page size, sector size, and serial clock frequency
+    config[0x1C0 / 4] = 256;
+    config[0x1C4 / 4] = 4096;
+    config[0x1C8 / 4] = 1;
+    config[0x1D0 / 4] = 0x0001_0000;
+    config
+}
+
+use crate::runtime::{arm_fault, unused_interrupt};
+
+/// The Teensy 4.x interrupt table
+///
+/// This will automatically be included as the standard interrupt
+/// table when one of these boards is selected.
+#[cfg_attr(
+    any(board = "teensy_40", board = "teensy_41"),
+    link_section = ".__CNTRLR_INTERRUPTS"
+)]
+#[cfg_attr(
+    any(board = "teensy_40", board = "teensy_41"),
+    export_name = "__cntrlr_interrupts"
+)]
+pub static INTERRUPTS: [unsafe extern "C" fn(); 160] = {
+    let mut interrupts = [unused_interrupt as unsafe extern "C" fn(); 160];
+    interrupts[25] = io::serial_1_intr;
+    interrupts[91] = io::flexio_serial_1_intr;
+    interrupts
+};
+
+/// The Teensy 4.x exception table
+///
+/// This will automatically be included as the standard exception
+/// table when one of these boards is selected.
+#[cfg_attr(
+    any(board = "teensy_40", board = "teensy_41"),
+    link_section = ".__CNTRLR_EXCEPTIONS"
+)]
+#[cfg_attr(
+    any(board = "teensy_40", board = "teensy_41"),
+    export_name = "__cntrlr_exceptions"
+)]
+pub static ARM_EXCEPTIONS: [unsafe extern "C" fn(); 14] = [
+    unused_interrupt,
+    arm_fault,
+    arm_fault,
+    arm_fault,
+    arm_fault,
+    unused_interrupt,
+    unused_interrupt,
+    unused_interrupt,
+    unused_interrupt,
+    unused_interrupt,
+    unused_interrupt,
+    unused_interrupt,
+    unused_interrupt,
+    time::systick_intr,
+];
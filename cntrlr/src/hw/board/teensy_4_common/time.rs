@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Time functionality shared between the Teensy 4.x boards
+
+use crate::{
+    sync::{without_interrupts, Value},
+    task::WakerSet,
+};
+use core::{
+    future::{poll_fn, Future},
+    ptr::{read_volatile, write_volatile},
+    sync::atomic::Ordering,
+    task::Poll,
+};
+
+/// Retreive the number of milliseconds the device has been running
+///
+/// This is a wrapping counter. On the Teensy boards, it is 32-bits
+///
+/// The SysTick which drives this counter runs from the 100kHz
+/// reference derived from the crystal, so the count is unaffected by
+/// changes to the core clock.
+pub fn millis() -> usize {
+    MILLIS.load(Ordering::Relaxed)
+}
+
+/// Retrieve the number of microseconds the device has been running
+///
+/// This is a wrapping counter. On the Teensy boards, it is 32-bits,
+/// and wraps after about 71 minutes.
+///
+/// The count is taken from the SysTick's 100kHz reference, so it
+/// advances in steps of 10 microseconds.
+pub fn micros() -> usize {
+    const RELOAD: usize = REFERENCE_FREQ / 1000 - 1;
+    const MICROS_PER_TICK: usize = 1_000_000 / REFERENCE_FREQ;
+
+    without_interrupts(|| unsafe {
+        let millis = MILLIS.load(Ordering::Relaxed);
+        let mut ticks = RELOAD - read_volatile(SYST_CVR) as usize;
+
+        // If the counter has wrapped without the interrupt running
+        // yet, a whole millisecond has passed in addition to the
+        // current count.
+        if read_volatile(ICSR) & ICSR_PENDSTSET != 0 {
+            ticks = RELOAD + 1 + RELOAD - read_volatile(SYST_CVR) as usize;
+        }
+        millis
+            .wrapping_mul(1000)
+            .wrapping_add(ticks * MICROS_PER_TICK)
+    })
+}
+
+/// Sleep this task for some number of milliseconds
+///
+/// This task will be slept, and awoken once the number of
+/// milliseconds has pased.
+pub fn sleep_millis(mut duration: usize) -> impl Future<Output = ()> {
+    let mut start = millis();
+    poll_fn(move |ctx| {
+        let current = millis();
+        let elapsed = current.wrapping_sub(start);
+        if elapsed >= duration {
+            Poll::Ready(())
+        } else {
+            duration -= elapsed;
+            start = current;
+            SYSTICK_WAKERS.add(ctx.waker().clone());
+            note_deadline(current.wrapping_add(duration));
+            Poll::Pending
+        }
+    })
+}
+
+/// Make sure the SysTick wakes the board at a timer's deadline
+pub fn note_timer(deadline: usize) {
+    note_deadline(deadline);
+}
+
+/// Busy-wait for at least some number of core clock cycles
+///
+/// This counts cycles with the DWT cycle counter, which is enabled
+/// on first use. It does not depend on interrupts or the SysTick, so
+/// it can be used from board init code and interrupt handlers.
+pub fn delay_cycles(cycles: usize) {
+    unsafe {
+        let counter = cycle_counter();
+        let start = read_volatile(counter);
+        while (read_volatile(counter).wrapping_sub(start) as usize) < cycles {}
+    }
+}
+
+/// Busy-wait for at least some number of microseconds
+///
+/// Before the core clock is set, this assumes the 396MHz clock
+/// selected by the boot ROM.
+pub fn delay_us_blocking(us: usize) {
+    let clock = match super::CPU_FREQ.load(Ordering::Relaxed) {
+        0 => 396_000_000,
+        clock => clock,
+    };
+    delay_cycles(us.saturating_mul(clock / 1_000_000));
+}
+
+/// Measure the core clock against the 100kHz SysTick reference
+///
+/// Returns `None` if the SysTick is not running or the core clock has
+/// not been set. Otherwise, returns the configured and measured core
+/// clock.
+///
+/// The reference is derived from the crystal, so the measurement is
+/// accurate to well under a percent.
+pub fn measure_core_clock() -> Option<(usize, usize)> {
+    const WRAPS: usize = 100;
+
+    let clock = super::CPU_FREQ.load(Ordering::Relaxed);
+    if clock == 0 || unsafe { read_volatile(SYST_CSR) } & CSR_ENABLE == 0 {
+        return None;
+    }
+
+    unsafe {
+        let counter = cycle_counter();
+
+        // Reading the CSR clears the count flag, so the first wrap
+        // seen here is the start of a whole period.
+        read_volatile(SYST_CSR);
+        while read_volatile(SYST_CSR) & CSR_COUNTFLAG == 0 {}
+        let start = read_volatile(counter);
+        for _ in 0..WRAPS {
+            while read_volatile(SYST_CSR) & CSR_COUNTFLAG == 0 {}
+        }
+        let cycles = read_volatile(counter).wrapping_sub(start) as usize;
+        Some((clock, cycles * (1000 / WRAPS)))
+    }
+}
+
+/// Start the SysTick interrupting every millisecond
+pub(crate) fn enable_tick() {
+    unsafe {
+        write_volatile(SYST_CSR, 0);
+        write_volatile(SYST_RVR, (REFERENCE_FREQ / 1000 - 1) as u32);
+        write_volatile(SYST_CVR, 0);
+        write_volatile(SYST_CSR, CSR_ENABLE | CSR_TICKINT);
+    }
+}
+
+/// Enable the DWT cycle counter, and get its address
+unsafe fn cycle_counter() -> *mut u32 {
+    const DEMCR: *mut u32 = 0xE000_EDFC as *mut _;
+    const DWT_CTRL: *mut u32 = 0xE000_1000 as *mut _;
+    const DWT_CYCCNT: *mut u32 = 0xE000_1004 as *mut _;
+
+    write_volatile(DEMCR, read_volatile(DEMCR) | 1 << 24);
+    write_volatile(DWT_CTRL, read_volatile(DWT_CTRL) | 1);
+    DWT_CYCCNT
+}
+
+/// Record the end of a sleep, so that the SysTick wakes the sleeping tasks then
+fn note_deadline(deadline: usize) {
+    without_interrupts(|| {
+        let now = MILLIS.load(Ordering::Relaxed);
+        let next = NEXT_DEADLINE.load(Ordering::Relaxed);
+        if next != NO_WAKE && next.wrapping_sub(now) <= deadline.wrapping_sub(now) {
+            return;
+        }
+        NEXT_DEADLINE.store(deadline, Ordering::Relaxed);
+    });
+}
+
+/// The frequency of the SysTick's external reference clock
+const REFERENCE_FREQ: usize = 100_000;
+
+const SYST_CSR: *mut u32 = 0xE000_E010 as *mut _;
+const SYST_RVR: *mut u32 = 0xE000_E014 as *mut _;
+const SYST_CVR: *mut u32 = 0xE000_E018 as *mut _;
+const ICSR: *mut u32 = 0xE000_ED04 as *mut _;
+
+const CSR_ENABLE: u32 = 1 << 0;
+const CSR_TICKINT: u32 = 1 << 1;
+const CSR_COUNTFLAG: u32 = 1 << 16;
+
+const ICSR_PENDSTSET: u32 = 1 << 26;
+
+const NO_WAKE: usize = usize::MAX;
+
+static MILLIS: Value = Value::new(0);
+static NEXT_DEADLINE: Value = Value::new(NO_WAKE);
+static SYSTICK_WAKERS: WakerSet = WakerSet::new();
+
+/// Interrupt function for the SysTick
+///
+/// Sleeping tasks are woken once the earliest of their deadlines has
+/// passed, and note their deadlines again when they are next polled.
+/// Timers stay queued until they expire, so the earliest is noted
+/// here instead.
+pub extern "C" fn systick_intr() {
+    let millis = MILLIS.load(Ordering::Relaxed).wrapping_add(1);
+    MILLIS.store(millis, Ordering::Relaxed);
+    let deadline = NEXT_DEADLINE.load(Ordering::Relaxed);
+    // A deadline which was noted just as a tick passed may already be
+    // behind the count.
+    if deadline != NO_WAKE && millis.wrapping_sub(deadline) <= usize::MAX / 2 {
+        NEXT_DEADLINE.store(NO_WAKE, Ordering::Relaxed);
+        SYSTICK_WAKERS.wake();
+        if let Some(deadline) = crate::time::wake_timers(millis) {
+            note_deadline(deadline);
+        }
+    }
+}
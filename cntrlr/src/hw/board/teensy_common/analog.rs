@@ -0,0 +1,706 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Analog input shared between the various Teensy 3.x boards
+
+use crate::{
+    analog::{AdcOptions, AnalogError, ConversionSpeed, Samples, TriggerOptions},
+    hw::mcu::kinetis::peripheral::{
+        adc::{Adc, ClockSource, Resolution},
+        cmp::{Cmp, COMPARATORS, DAC_INPUT},
+        dac::{Dac, Reference},
+        pdb::{Pdb, SOFTWARE_TRIGGER},
+        sim::{AdcTrigger, GatedPeripheral, Sim},
+        Peripheral,
+    },
+    sync::{without_interrupts, Flag, Mutex, Value},
+    task::WakerSet,
+    time::delay_us_blocking,
+};
+use bit_field::BitField;
+use core::{
+    future::{poll_fn, Future},
+    ptr::{read_volatile, write_volatile},
+    sync::atomic::Ordering,
+    task::Poll,
+};
+
+/// The state of a board's ADC
+pub struct AnalogState<M, const N: usize> {
+    adc: Option<Adc<M, N>>,
+    pdb: Option<Pdb<M>>,
+    options: AdcOptions,
+    reading: bool,
+}
+
+impl<M, const N: usize> AnalogState<M, N> {
+    /// Create the state for a disabled ADC
+    pub const fn new() -> Self {
+        Self {
+            adc: None,
+            pdb: None,
+            options: AdcOptions::DEFAULT,
+            reading: false,
+        }
+    }
+
+    fn arm_triggered(
+        &mut self,
+        channels: (u32, Option<u32>),
+        interrupt: bool,
+    ) -> Result<(), AnalogError> {
+        let adc = self.adc.as_mut().ok_or(AnalogError::AdcInUse)?;
+        adc.set_hardware_trigger(true);
+        adc.set_trigger_channel(0, channels.0, interrupt && channels.1.is_none());
+        if let Some(channel) = channels.1 {
+            adc.set_trigger_channel(1, channel, interrupt);
+        }
+        Ok(())
+    }
+
+    fn disarm_triggered(&mut self) {
+        if let Some(adc) = self.adc.as_mut() {
+            adc.set_hardware_trigger(false);
+            adc.set_trigger_channel(0, 31, false);
+            adc.set_trigger_channel(1, 31, false);
+        }
+    }
+}
+
+impl<M> AnalogState<M, 0> {
+    /// Stop hardware-triggered conversions
+    ///
+    /// `paired` is the state of ADC 1, on boards which have it.
+    pub fn stop_triggered(&mut self, paired: Option<&mut AnalogState<M, 1>>) {
+        if let Some(mut pdb) = self.pdb.take() {
+            pdb.enable(false);
+        }
+        self.disarm_triggered();
+        if let Some(paired) = paired {
+            paired.disarm_triggered();
+        }
+        TRIGGERED.store(false, Ordering::Release);
+    }
+}
+
+impl<M, const N: usize> AnalogState<M, N>
+where
+    Adc<M, N>: GatedPeripheral<M>,
+    Sim<M>: Peripheral,
+{
+    /// The current ADC configuration
+    pub fn options(&self) -> AdcOptions {
+        self.options
+    }
+
+    /// Configure and calibrate the ADC, enabling it if needed
+    pub fn set_options(&mut self, options: AdcOptions, bus_freq: usize) -> Result<(), AnalogError> {
+        let resolution = match options.resolution {
+            8 => Resolution::Bits8,
+            10 => Resolution::Bits10,
+            12 => Resolution::Bits12,
+            16 => Resolution::Bits16,
+            _ => return Err(AnalogError::InvalidResolution),
+        };
+        let averaging = match options.averaging {
+            1 => None,
+            4 => Some(0),
+            8 => Some(1),
+            16 => Some(2),
+            32 => Some(3),
+            _ => return Err(AnalogError::InvalidAveraging),
+        };
+        if self.reading {
+            return Err(AnalogError::AdcInUse);
+        }
+
+        if self.adc.is_none() {
+            self.adc = Some(
+                Sim::<M>::get()
+                    .and_then(|mut sim| sim.enable_peripheral::<Adc<M, N>>())
+                    .ok_or(AnalogError::AdcInUse)?,
+            );
+        }
+        let adc = self.adc.as_mut().ok_or(AnalogError::AdcInUse)?;
+
+        // The ADC clock must be at most 12MHz in 16-bit mode, and
+        // 18MHz otherwise.
+        let max_clock = match options.conversion_speed {
+            ConversionSpeed::VeryLow => 2_000_000,
+            ConversionSpeed::Low => 4_000_000,
+            ConversionSpeed::Medium => 8_000_000,
+            ConversionSpeed::High => 12_000_000,
+            ConversionSpeed::VeryHigh if resolution == Resolution::Bits16 => 12_000_000,
+            ConversionSpeed::VeryHigh => 18_000_000,
+        };
+        let bus_freq = if bus_freq == 0 { 21_000_000 } else { bus_freq };
+        let (source, divide) = (0..5)
+            .map(|divide| match divide {
+                4 => (ClockSource::HalfBus, 3),
+                divide => (ClockSource::Bus, divide),
+            })
+            .find(|(source, divide)| {
+                let clock = bus_freq >> divide;
+                let clock = if *source == ClockSource::HalfBus {
+                    clock / 2
+                } else {
+                    clock
+                };
+                clock <= max_clock
+            })
+            .unwrap_or((ClockSource::HalfBus, 3));
+
+        let sample_time = match options.sampling_speed {
+            ConversionSpeed::VeryLow => Some(0),
+            ConversionSpeed::Low => Some(1),
+            ConversionSpeed::Medium => Some(2),
+            ConversionSpeed::High => Some(3),
+            ConversionSpeed::VeryHigh => None,
+        };
+
+        // Calibrate with maximum averaging and a slow clock, then
+        // switch to the requested configuration.
+        adc.set_resolution(Resolution::Bits16);
+        adc.set_clock(ClockSource::HalfBus, 3);
+        adc.set_sample_time(Some(0));
+        adc.set_high_speed(false);
+        adc.set_averaging(Some(3));
+        if !adc.calibrate() {
+            return Err(AnalogError::CalibrationFailed);
+        }
+
+        adc.set_resolution(resolution);
+        adc.set_clock(source, divide);
+        adc.set_sample_time(sample_time);
+        adc.set_high_speed(max_clock > 8_000_000);
+        adc.set_averaging(averaging);
+        self.options = options;
+        Ok(())
+    }
+}
+
+impl<M> AnalogState<M, 0>
+where
+    Adc<M, 0>: GatedPeripheral<M>,
+    Pdb<M>: GatedPeripheral<M>,
+    Sim<M>: Peripheral,
+{
+    /// Start conversions triggered through the PDB
+    ///
+    /// `stop` is called when the returned sampler is dropped, and
+    /// should call [`stop_triggered()`](Self::stop_triggered).
+    pub fn start_triggered(
+        &mut self,
+        options: TriggerOptions,
+        bus_freq: usize,
+        stop: fn(),
+    ) -> Result<TriggeredSampler, AnalogError> {
+        if options.paired_channels.is_some() {
+            return Err(AnalogError::InvalidChannel);
+        }
+        if self.reading {
+            return Err(AnalogError::AdcInUse);
+        }
+        if TRIGGERED.swap(true, Ordering::Acquire) {
+            return Err(AnalogError::PdbInUse);
+        }
+        let sampler = self.configure_triggered(options, None, bus_freq, stop);
+        if sampler.is_err() {
+            TRIGGERED.store(false, Ordering::Release);
+        }
+        sampler
+    }
+
+    /// Start conversions triggered through the PDB, on both ADCs
+    ///
+    /// If the options include paired channels, ADC 1 is configured
+    /// with the same options as ADC 0, and converts them at the same
+    /// time as ADC 0. `stop` is called when the returned sampler is
+    /// dropped, and should call
+    /// [`stop_triggered()`](Self::stop_triggered).
+    pub fn start_paired(
+        &mut self,
+        options: TriggerOptions,
+        paired: &mut AnalogState<M, 1>,
+        bus_freq: usize,
+        stop: fn(),
+    ) -> Result<TriggeredSampler, AnalogError>
+    where
+        Adc<M, 1>: GatedPeripheral<M>,
+    {
+        if self.reading {
+            return Err(AnalogError::AdcInUse);
+        }
+        if TRIGGERED.swap(true, Ordering::Acquire) {
+            return Err(AnalogError::PdbInUse);
+        }
+        let sampler = (|| {
+            if options.paired_channels.is_none() {
+                return self.configure_triggered(options, None, bus_freq, stop);
+            }
+            if self.adc.is_none() {
+                self.set_options(self.options, bus_freq)?;
+            }
+            if paired.adc.is_none() || paired.options != self.options {
+                paired.set_options(self.options, bus_freq)?;
+            }
+            self.configure_triggered(options, Some(paired), bus_freq, stop)
+        })();
+        if sampler.is_err() {
+            paired.disarm_triggered();
+            TRIGGERED.store(false, Ordering::Release);
+        }
+        sampler
+    }
+
+    fn configure_triggered(
+        &mut self,
+        options: TriggerOptions,
+        paired: Option<&mut AnalogState<M, 1>>,
+        bus_freq: usize,
+        stop: fn(),
+    ) -> Result<TriggeredSampler, AnalogError> {
+        let channels = (options.channel_a, options.channel_b);
+        let valid = |channels: (u32, Option<u32>)| {
+            channels.0 < 31 && channels.1.map_or(true, |channel| channel < 31)
+        };
+        if !valid(channels) || !options.paired_channels.map_or(true, valid) {
+            return Err(AnalogError::InvalidChannel);
+        }
+        if let Some(paired_channels) = options.paired_channels {
+            if paired_channels.1.is_some() != channels.1.is_some() {
+                return Err(AnalogError::InvalidChannel);
+            }
+        }
+        if options.period == 0 || options.delays.iter().any(|delay| *delay >= options.period) {
+            return Err(AnalogError::InvalidTiming);
+        }
+
+        // The PDB counts the bus clock, divided by 2^prescale * mult.
+        let bus_freq = if bus_freq == 0 { 21_000_000 } else { bus_freq };
+        let ticks = |micros: u32| micros as u64 * bus_freq as u64 / 1_000_000;
+        let period = ticks(options.period);
+        let (prescale, mult) = [1, 10, 20, 40]
+            .iter()
+            .flat_map(|&mult| (0..8).map(move |prescale| (prescale, mult)))
+            .filter(|&(prescale, mult)| period / ((1 << prescale) * mult) <= 0x1_0000)
+            .min_by_key(|&(prescale, mult)| (1 << prescale) * mult)
+            .ok_or(AnalogError::InvalidTiming)?;
+        let divide = (1 << prescale) * mult;
+        let modulus = (period / divide).saturating_sub(1) as u16;
+        let delays = [
+            (ticks(options.delays[0]) / divide) as u16,
+            (ticks(options.delays[1]) / divide) as u16,
+        ];
+
+        if self.adc.is_none() {
+            self.set_options(self.options, bus_freq)?;
+        }
+
+        let mut sim = Sim::<M>::get().ok_or(AnalogError::AdcInUse)?;
+        let mut pdb = sim
+            .enable_peripheral::<Pdb<M>>()
+            .ok_or(AnalogError::PdbInUse)?;
+        sim.set_adc_trigger(0, AdcTrigger::Pdb);
+        if paired.is_some() {
+            sim.set_adc_trigger(1, AdcTrigger::Pdb);
+        }
+        drop(sim);
+
+        pdb.set_trigger_input(options.input.unwrap_or(SOFTWARE_TRIGGER));
+        pdb.set_prescaler(prescale, mult as u32);
+        pdb.set_continuous(options.input.is_none());
+        pdb.set_modulus(modulus);
+        for adc in 0..if paired.is_some() { 2 } else { 1 } {
+            pdb.set_pretrigger(adc, 0, Some(delays[0]));
+            pdb.set_pretrigger(adc, 1, channels.1.map(|_| delays[1]));
+        }
+
+        // When sampling in pairs, the ADC 1 interrupt collects the
+        // results from both ADCs.
+        self.arm_triggered(channels, paired.is_none())?;
+        if let (Some(paired), Some(paired_channels)) = (paired, options.paired_channels) {
+            paired.arm_triggered(paired_channels, true)?;
+        }
+
+        SECOND.store(channels.1.is_some(), Ordering::Relaxed);
+        pdb.enable(true);
+        pdb.load();
+        if options.input.is_none() {
+            pdb.trigger();
+        }
+        self.pdb = Some(pdb);
+
+        Ok(TriggeredSampler {
+            stop,
+            second: channels.1.is_some(),
+            paired: options.paired_channels.is_some(),
+            seen: SEQUENCE.load(Ordering::Acquire),
+        })
+    }
+}
+
+impl<M> AnalogState<M, 0>
+where
+    Adc<M, 0>: GatedPeripheral<M>,
+    Sim<M>: Peripheral,
+{
+    /// Start a software-triggered conversion of one channel
+    ///
+    /// The ADC is enabled with its current options if needed.
+    /// Returns `false` if another conversion is in progress.
+    fn start_read(
+        &mut self,
+        channel: u32,
+        mux_b: bool,
+        bus_freq: usize,
+    ) -> Result<bool, AnalogError> {
+        if TRIGGERED.load(Ordering::Acquire) {
+            return Err(AnalogError::AdcInUse);
+        }
+        if self.reading {
+            return Ok(false);
+        }
+        if self.adc.is_none() {
+            self.set_options(self.options, bus_freq)?;
+        }
+        let adc = self.adc.as_mut().ok_or(AnalogError::AdcInUse)?;
+        adc.set_mux_b(mux_b);
+        adc.start(channel, false);
+        self.reading = true;
+        Ok(true)
+    }
+
+    /// Take the result of the conversion started by `start_read`, once it completes
+    fn finish_read(&mut self) -> Option<u16> {
+        let adc = self.adc.as_mut()?;
+        if !adc.is_complete() {
+            return None;
+        }
+        self.reading = false;
+        Some(adc.result())
+    }
+}
+
+/// Convert one channel of ADC 0
+///
+/// If another read is converting, this waits for it to finish. The
+/// conversion is polled, rather than interrupting on completion, as
+/// the ADC interrupt belongs to triggered conversions.
+pub fn read<M>(
+    state: &'static Mutex<AnalogState<M, 0>>,
+    channel: u32,
+    mux_b: bool,
+    bus_freq: usize,
+) -> impl Future<Output = Result<u16, AnalogError>>
+where
+    Adc<M, 0>: GatedPeripheral<M>,
+    Sim<M>: Peripheral,
+{
+    // Releases the ADC if the read is dropped before it completes
+    struct Reading<M: 'static>(&'static Mutex<AnalogState<M, 0>>);
+    impl<M: 'static> Drop for Reading<M> {
+        fn drop(&mut self) {
+            self.0.lock().reading = false;
+        }
+    }
+
+    async move {
+        poll_fn(
+            |ctx| match state.lock().start_read(channel, mux_b, bus_freq) {
+                Ok(false) => {
+                    ctx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+                Ok(true) => Poll::Ready(Ok(())),
+                Err(err) => Poll::Ready(Err(err)),
+            },
+        )
+        .await?;
+
+        let _reading = Reading(state);
+        let result = poll_fn(|ctx| match state.lock().finish_read() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                ctx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await;
+        Ok(result)
+    }
+}
+
+/// A source of hardware-triggered ADC results
+///
+/// Only the latest results are kept, so a task which falls behind
+/// the trigger will skip sequences. Conversions stop when this is
+/// dropped.
+pub struct TriggeredSampler {
+    stop: fn(),
+    second: bool,
+    paired: bool,
+    seen: usize,
+}
+
+impl TriggeredSampler {
+    /// Wait for the results of the next sequence
+    pub fn next(&mut self) -> impl Future<Output = Samples> + '_ {
+        poll_fn(move |ctx| {
+            if let Some(samples) = self.take_samples() {
+                return Poll::Ready(samples);
+            }
+
+            // Check again after registering the waker, in case a
+            // sequence completed in between.
+            WAKERS.add(ctx.waker().clone());
+            match self.take_samples() {
+                Some(samples) => Poll::Ready(samples),
+                None => Poll::Pending,
+            }
+        })
+    }
+
+    /// The number of sequences skipped since the last results were taken
+    pub fn skipped(&self) -> usize {
+        SEQUENCE
+            .load(Ordering::Acquire)
+            .wrapping_sub(self.seen)
+            .saturating_sub(1)
+    }
+
+    fn take_samples(&mut self) -> Option<Samples> {
+        let (sequence, results) = without_interrupts(|| {
+            (
+                SEQUENCE.load(Ordering::Acquire),
+                [
+                    RESULTS[0].load(Ordering::Relaxed) as u16,
+                    RESULTS[1].load(Ordering::Relaxed) as u16,
+                    RESULTS[2].load(Ordering::Relaxed) as u16,
+                    RESULTS[3].load(Ordering::Relaxed) as u16,
+                ],
+            )
+        });
+        if sequence == self.seen {
+            return None;
+        }
+
+        self.seen = sequence;
+        let second = self.second;
+        Some(Samples::new(
+            (results[0], Some(results[1]).filter(|_| second)),
+            Some((results[2], Some(results[3]).filter(|_| second))).filter(|_| self.paired),
+        ))
+    }
+}
+
+impl Drop for TriggeredSampler {
+    fn drop(&mut self) {
+        (self.stop)();
+    }
+}
+
+const ADC0_SC1A: *const u32 = 0x4003_B000 as _;
+const ADC0_SC1B: *const u32 = 0x4003_B004 as _;
+const ADC0_RA: *const u32 = 0x4003_B010 as _;
+const ADC0_RB: *const u32 = 0x4003_B014 as _;
+const ADC1_RA: *const u32 = 0x400B_B010 as _;
+const ADC1_RB: *const u32 = 0x400B_B014 as _;
+
+/// The interrupt function for ADC 0
+///
+/// This records the results of a triggered sequence.
+pub extern "C" fn adc_0_intr() {
+    unsafe {
+        RESULTS[0].store(read_volatile(ADC0_RA) as usize, Ordering::Relaxed);
+        RESULTS[1].store(read_volatile(ADC0_RB) as usize, Ordering::Relaxed);
+    }
+    finish_sequence();
+}
+
+/// The interrupt function for ADC 1
+///
+/// This records the results of a triggered sequence on both ADCs.
+pub extern "C" fn adc_1_intr() {
+    unsafe {
+        // ADC 0 started its last conversion at the same time as ADC
+        // 1, with the same settings, but may not quite have finished.
+        let last = if SECOND.load(Ordering::Relaxed) {
+            ADC0_SC1B
+        } else {
+            ADC0_SC1A
+        };
+        while !read_volatile(last).get_bit(7) {}
+
+        RESULTS[0].store(read_volatile(ADC0_RA) as usize, Ordering::Relaxed);
+        RESULTS[1].store(read_volatile(ADC0_RB) as usize, Ordering::Relaxed);
+        RESULTS[2].store(read_volatile(ADC1_RA) as usize, Ordering::Relaxed);
+        RESULTS[3].store(read_volatile(ADC1_RB) as usize, Ordering::Relaxed);
+    }
+    finish_sequence();
+}
+
+fn finish_sequence() {
+    SEQUENCE.store(
+        SEQUENCE.load(Ordering::Relaxed).wrapping_add(1),
+        Ordering::Release,
+    );
+    WAKERS.wake();
+}
+
+static TRIGGERED: Flag = Flag::new(false);
+static SECOND: Flag = Flag::new(false);
+static SEQUENCE: Value = Value::new(0);
+static RESULTS: [Value; 4] = [Value::new(0), Value::new(0), Value::new(0), Value::new(0)];
+static WAKERS: WakerSet = WakerSet::new();
+
+/// Set the output of a DAC, enabling it on first use
+///
+/// The DAC uses the analog supply as its reference, so the output
+/// spans the full 3.3V. Values above 4095 are clamped.
+pub fn dac_write<M, const N: usize>(
+    dac: &Mutex<Option<Dac<M, N>>>,
+    value: u16,
+) -> Result<(), AnalogError>
+where
+    Dac<M, N>: GatedPeripheral<M>,
+    Sim<M>: Peripheral,
+{
+    let mut dac = dac.lock();
+    if dac.is_none() {
+        let mut new = Sim::<M>::get()
+            .ok_or(AnalogError::DacInUse)?
+            .enable_peripheral::<Dac<M, N>>()
+            .ok_or(AnalogError::DacInUse)?;
+        new.enable(Reference::Vdda);
+        *dac = Some(new);
+    }
+    if let Some(dac) = dac.as_mut() {
+        dac.write(value.min(4095));
+    }
+    Ok(())
+}
+
+/// The state of a board's comparators
+pub struct ComparatorState<M> {
+    cmp: Option<Cmp<M>>,
+    waiting: [bool; COMPARATORS],
+}
+
+impl<M> ComparatorState<M> {
+    /// Create the state for disabled comparators
+    pub const fn new() -> Self {
+        Self {
+            cmp: None,
+            waiting: [false; COMPARATORS],
+        }
+    }
+
+    fn release(&mut self, comparator: usize) {
+        self.waiting[comparator] = false;
+        if let Some(cmp) = self.cmp.as_mut() {
+            cmp.disable(comparator);
+        }
+    }
+}
+
+impl<M> ComparatorState<M>
+where
+    Cmp<M>: GatedPeripheral<M>,
+    Sim<M>: Peripheral,
+{
+    /// Compare an input against a DAC level, enabling the comparators on first use
+    fn claim(&mut self, comparator: usize, input: u8, level: u8) -> Result<(), AnalogError> {
+        if self.waiting[comparator] {
+            return Err(AnalogError::ComparatorInUse);
+        }
+        if self.cmp.is_none() {
+            self.cmp = Some(
+                Sim::<M>::get()
+                    .ok_or(AnalogError::ComparatorInUse)?
+                    .enable_peripheral::<Cmp<M>>()
+                    .ok_or(AnalogError::ComparatorInUse)?,
+            );
+        }
+        if let Some(cmp) = self.cmp.as_mut() {
+            cmp.set_dac(comparator, Some(level));
+            cmp.enable(comparator, input, DAC_INPUT);
+        }
+        self.waiting[comparator] = true;
+        Ok(())
+    }
+}
+
+/// Wait until a comparator input rises above a DAC level
+///
+/// The comparator interrupts on its rising output, and is released
+/// when the wait completes or is dropped.
+pub fn wait_above<M>(
+    state: &'static Mutex<ComparatorState<M>>,
+    comparator: usize,
+    input: u8,
+    level: u8,
+) -> impl Future<Output = Result<(), AnalogError>>
+where
+    Cmp<M>: GatedPeripheral<M>,
+    Sim<M>: Peripheral,
+{
+    // Releases the comparator once the wait is finished or dropped
+    struct Waiting<M: 'static>(&'static Mutex<ComparatorState<M>>, usize);
+    impl<M: 'static> Drop for Waiting<M> {
+        fn drop(&mut self) {
+            self.0.lock().release(self.1);
+        }
+    }
+
+    async move {
+        state.lock().claim(comparator, input, level)?;
+        let _waiting = Waiting(state, comparator);
+        // Let the DAC and comparator settle on the new inputs
+        delay_us_blocking(1);
+
+        poll_fn(|ctx| {
+            let mut state = state.lock();
+            let cmp = match state.cmp.as_mut() {
+                Some(cmp) => cmp,
+                None => return Poll::Ready(Err(AnalogError::ComparatorInUse)),
+            };
+            if cmp.output(comparator) {
+                return Poll::Ready(Ok(()));
+            }
+
+            // Check again after enabling the interrupt, in case the
+            // input rose in between.
+            CMP_WAKERS[comparator].add(ctx.waker().clone());
+            cmp.enable_interrupt(comparator, true, false);
+            if cmp.output(comparator) {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+/// The interrupt function for comparator 0
+pub extern "C" fn cmp_0_intr() {
+    cmp_intr(0);
+}
+
+/// The interrupt function for comparator 1
+pub extern "C" fn cmp_1_intr() {
+    cmp_intr(1);
+}
+
+/// Disable a comparator's interrupts, and wake its waiting task
+fn cmp_intr(comparator: usize) {
+    let scr = (0x4007_3003 + 8 * comparator) as *mut u8;
+    unsafe {
+        // Clear CFR and CFF, and disable both interrupts
+        write_volatile(scr, 0b110);
+    }
+    CMP_WAKERS[comparator].wake();
+}
+
+static CMP_WAKERS: [WakerSet; COMPARATORS] = [WakerSet::new(), WakerSet::new()];
@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Clock fault handling shared between the various Teensy 3.x boards
+//!
+//! The MCG's clock monitor and loss-of-lock interrupt are enabled
+//! each time the board clock is set. When either fires, the MCG is
+//! switched to the FLL, referenced to the slow internal clock. This
+//! is FEI mode, as at reset, and needs no crystal.
+
+use crate::clock::ClockFault;
+use core::ptr::{read_volatile, write_volatile};
+
+/// The MCG output in FEI mode, in Hz
+///
+/// This is the slow internal reference multiplied by the FLL's
+/// default factor of 640.
+pub const FALLBACK_FREQ: usize = 20_971_520;
+
+const MCG_C1: *mut u8 = 0x4006_4000 as _;
+const MCG_C6: *mut u8 = 0x4006_4005 as _;
+const MCG_S: *mut u8 = 0x4006_4006 as _;
+const MCG_SC: *mut u8 = 0x4006_4008 as _;
+const SIM_SOPT2: *mut u32 = 0x4004_8004 as _;
+const SIM_CLKDIV1: *const u32 = 0x4004_8044 as _;
+
+/// Switch to the internal reference, if the clock has failed
+///
+/// Returns the fault, and the divider from the MCG output to the
+/// core clock, or `None` if neither the clock monitor nor the PLL
+/// reported a fault. Peripherals clocked from the PLL are switched to
+/// the FLL.
+pub(crate) fn fall_back() -> Option<(ClockFault, usize)> {
+    unsafe {
+        let s = read_volatile(MCG_S);
+        let sc = read_volatile(MCG_SC);
+        let c6 = read_volatile(MCG_C6);
+        let fault = if sc & 0x01 != 0 {
+            ClockFault::OscillatorLost
+        } else if s & 0x80 != 0 && c6 & 0x80 != 0 {
+            ClockFault::PllUnlocked
+        } else {
+            return None;
+        };
+
+        // Select the FLL output with the internal reference, then
+        // deselect the PLL and disable both monitors, which would
+        // otherwise keep firing for the dead crystal.
+        let c1 = read_volatile(MCG_C1);
+        write_volatile(MCG_C1, (c1 & !0xC0) | 0x04);
+        write_volatile(MCG_C6, c6 & !0xE0);
+        while read_volatile(MCG_S) & 0x10 == 0 {}
+        while read_volatile(MCG_S) & 0x2C != 0 {}
+
+        // The auto-trim fail flag is also cleared by writing a 1
+        write_volatile(MCG_SC, (read_volatile(MCG_SC) & !0x20) | 0x01);
+        write_volatile(MCG_S, 0x80);
+
+        let sopt2 = read_volatile(SIM_SOPT2);
+        write_volatile(SIM_SOPT2, sopt2 & !0x0003_0000);
+
+        let core = (read_volatile(SIM_CLKDIV1) >> 28) as usize + 1;
+        Some((fault, core))
+    }
+}
@@ -0,0 +1,192 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Pulse counting shared between the various Teensy 3.x boards
+
+use super::time::millis;
+use crate::{
+    counter::CounterOption,
+    digital::{self, Edge},
+    sync::{without_interrupts, Flag, Value},
+    task::WakerSet,
+};
+use core::{
+    future::{poll_fn, Future},
+    sync::atomic::Ordering,
+    task::Poll,
+};
+
+/// An error from a pulse counter
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CounterError {
+    /// The counter cannot be created because its pin does not exist
+    InvalidPin,
+
+    /// The counter cannot be created because its pin is in use or cannot generate interrupts
+    PinUnavailable,
+
+    /// The counter cannot be created because every counter is in use
+    NoFreeCounter,
+}
+
+/// A counter of edges on a pin
+///
+/// Edges are counted by the pin's port interrupt. The LPTMR's pulse
+/// counting mode is not used, as the LPTMR is reserved as the backup
+/// for the millisecond tick.
+///
+/// Up to four counters can exist at once. The pin's interrupt is
+/// disabled when the counter is dropped.
+pub struct PulseCounter {
+    slot: &'static Slot,
+    pin: usize,
+}
+
+impl PulseCounter {
+    /// Start counting edges on a pin
+    ///
+    /// The pin is set as a digital input. Any pull resistor set by
+    /// [`pin_mode`](crate::digital::pin_mode) is kept.
+    pub fn new(pin: usize, edge: Edge, options: &[CounterOption]) -> Result<Self, CounterError> {
+        let (port, mcu_pin) = digital::mcu_pin(pin).ok_or(CounterError::InvalidPin)?;
+        let id = port * 32 + mcu_pin;
+
+        let mut debounce = 0;
+        for option in options {
+            match option {
+                CounterOption::Debounce(millis) => debounce = *millis,
+            }
+        }
+
+        // The pin is checked and the slot claimed together, so that
+        // two counters cannot be created on one pin at once.
+        let slot = without_interrupts(|| {
+            if SLOTS
+                .iter()
+                .any(|slot| slot.pin.load(Ordering::Relaxed) == id)
+            {
+                return Err(CounterError::PinUnavailable);
+            }
+
+            let slot = SLOTS
+                .iter()
+                .find(|slot| !slot.claimed.load(Ordering::Relaxed))
+                .ok_or(CounterError::NoFreeCounter)?;
+            slot.claimed.store(true, Ordering::Relaxed);
+            slot.count.store(0, Ordering::Relaxed);
+            slot.debounce.store(debounce, Ordering::Relaxed);
+            slot.last_edge
+                .store(millis().wrapping_sub(debounce), Ordering::Relaxed);
+            slot.pin.store(id, Ordering::Release);
+            Ok(slot)
+        })?;
+
+        if !digital::set_pin_interrupt(pin, Some(edge)) {
+            slot.pin.store(NO_PIN, Ordering::Relaxed);
+            slot.claimed.store(false, Ordering::Release);
+            return Err(CounterError::PinUnavailable);
+        }
+
+        Ok(Self { slot, pin })
+    }
+
+    /// The number of edges counted so far
+    ///
+    /// This is a wrapping counter.
+    pub fn count(&self) -> usize {
+        self.slot.count.load(Ordering::Relaxed)
+    }
+
+    /// Reset the count to zero
+    ///
+    /// Returns the count from before the reset. No edges are lost
+    /// between reading and resetting the count.
+    pub fn reset(&mut self) -> usize {
+        self.slot.count.swap(0, Ordering::Relaxed)
+    }
+
+    /// Wait until at least `count` edges have been counted
+    ///
+    /// Resolves to the count at the time it is woken.
+    pub fn wait_for_count(&mut self, count: usize) -> impl Future<Output = usize> + '_ {
+        poll_fn(move |ctx| {
+            let current = self.count();
+            if current >= count {
+                return Poll::Ready(current);
+            }
+
+            // Check again after registering the waker, in case an
+            // edge was counted in between.
+            self.slot.wakers.add(ctx.waker().clone());
+            let current = self.count();
+            if current >= count {
+                Poll::Ready(current)
+            } else {
+                Poll::Pending
+            }
+        })
+    }
+}
+
+impl Drop for PulseCounter {
+    fn drop(&mut self) {
+        // The slot is only released once the pin can no longer
+        // interrupt, so that a new counter in the slot never sees
+        // this pin's edges or wakers.
+        self.slot.wakers.clear();
+        digital::set_pin_interrupt(self.pin, None);
+        self.slot.pin.store(NO_PIN, Ordering::Relaxed);
+        self.slot.claimed.store(false, Ordering::Release);
+    }
+}
+
+/// Count an edge on an MCU pin
+///
+/// This is called from the port interrupt for each pin with a
+/// pending interrupt flag.
+pub(crate) fn pin_intr(port: usize, pin: usize) {
+    let id = port * 32 + pin;
+    for slot in &SLOTS {
+        if slot.pin.load(Ordering::Acquire) != id {
+            continue;
+        }
+
+        let now = millis();
+        let debounce = slot.debounce.load(Ordering::Relaxed);
+        if now.wrapping_sub(slot.last_edge.load(Ordering::Relaxed)) < debounce {
+            continue;
+        }
+
+        let count = slot.count.load(Ordering::Relaxed);
+        slot.count.store(count.wrapping_add(1), Ordering::Relaxed);
+        slot.last_edge.store(now, Ordering::Relaxed);
+        slot.wakers.wake();
+    }
+}
+
+struct Slot {
+    claimed: Flag,
+    pin: Value,
+    count: Value,
+    debounce: Value,
+    last_edge: Value,
+    wakers: WakerSet,
+}
+
+impl Slot {
+    const fn new() -> Self {
+        Self {
+            claimed: Flag::new(false),
+            pin: Value::new(NO_PIN),
+            count: Value::new(0),
+            debounce: Value::new(0),
+            last_edge: Value::new(0),
+            wakers: WakerSet::new(),
+        }
+    }
+}
+
+static SLOTS: [Slot; 4] = [Slot::new(), Slot::new(), Slot::new(), Slot::new()];
+
+const NO_PIN: usize = usize::MAX;
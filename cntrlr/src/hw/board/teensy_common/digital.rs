@@ -3,7 +3,13 @@
 
 //! Digital pin functionality shared between the various Teensy 3.x boards
 
-use crate::{digital::PinMode, hw::mcu::kinetis::peripheral::port};
+use crate::{
+    digital::{DriveStrength, Edge, FilterConfig, PinConfig, PinMode, Pull, SlewRate},
+    hw::mcu::kinetis::peripheral::port,
+    sync::without_interrupts,
+};
+use bit_field::BitField;
+use core::ptr::{read_volatile, write_volatile};
 
 /// An operation on a pin.
 ///
@@ -61,6 +67,18 @@ impl PinOp for ReadOp {
     }
 }
 
+/// An operation to read the state a pin is set to output
+pub struct OutputOp;
+impl PinOp for OutputOp {
+    type Arg = ();
+    type Result = bool;
+
+    #[inline(always)]
+    fn op<M, const N: usize, const P: usize>(pin: port::Pin<'_, M, N, P>, _: ()) -> bool {
+        pin.into_gpio().read_output()
+    }
+}
+
 /// An operation to set a pin's [mode](`PinMode`)
 pub struct ModeOp;
 impl PinOp for ModeOp {
@@ -92,3 +110,187 @@ impl PinOp for ModeOp {
         }
     }
 }
+
+/// An operation to set which edges trigger a pin's interrupt
+///
+/// The pin is set as a digital input. Its pull resistor is left
+/// unchanged.
+pub struct InterruptOp;
+impl PinOp for InterruptOp {
+    type Arg = Option<Edge>;
+    type Result = ();
+
+    #[inline(always)]
+    fn op<M, const N: usize, const P: usize>(pin: port::Pin<'_, M, N, P>, edge: Option<Edge>) {
+        let mut pin = pin.into_gpio();
+        pin.set_output(false);
+        pin.set_interrupt(edge);
+    }
+}
+
+/// An operation to set a pin's output drive strength
+pub struct DriveOp;
+impl PinOp for DriveOp {
+    type Arg = DriveStrength;
+    type Result = bool;
+
+    #[inline(always)]
+    fn op<M, const N: usize, const P: usize>(
+        mut pin: port::Pin<'_, M, N, P>,
+        strength: DriveStrength,
+    ) -> bool {
+        pin.set_drive_strength(strength)
+    }
+}
+
+/// An operation to set a pin's output slew rate
+pub struct SlewOp;
+impl PinOp for SlewOp {
+    type Arg = SlewRate;
+    type Result = bool;
+
+    #[inline(always)]
+    fn op<M, const N: usize, const P: usize>(
+        mut pin: port::Pin<'_, M, N, P>,
+        rate: SlewRate,
+    ) -> bool {
+        pin.set_slew_rate(rate)
+    }
+}
+
+/// An operation to set a pin's input filter
+pub struct FilterOp;
+impl PinOp for FilterOp {
+    type Arg = FilterConfig;
+    type Result = bool;
+
+    #[inline(always)]
+    fn op<M, const N: usize, const P: usize>(
+        mut pin: port::Pin<'_, M, N, P>,
+        config: FilterConfig,
+    ) -> bool {
+        match config {
+            FilterConfig::None => {
+                #[cfg(not(mcu = "mkl26z64"))]
+                pin.enable_digital_filter(false);
+                pin.set_passive_filter(false)
+            }
+            FilterConfig::Passive => {
+                #[cfg(not(mcu = "mkl26z64"))]
+                pin.enable_digital_filter(false);
+                pin.set_passive_filter(true)
+            }
+            #[cfg(not(mcu = "mkl26z64"))]
+            FilterConfig::Digital { clock, width } if (1..32).contains(&width) => {
+                pin.set_passive_filter(false);
+                pin.port().set_digital_filter(clock, width);
+                pin.enable_digital_filter(true);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// An operation to use a pin as an analog input
+pub struct AnalogOp;
+impl PinOp for AnalogOp {
+    type Arg = ();
+    type Result = ();
+
+    #[inline(always)]
+    fn op<M, const N: usize, const P: usize>(mut pin: port::Pin<'_, M, N, P>, _: ()) {
+        pin.set_analog();
+    }
+}
+
+/// An operation to connect a pin to one of its functions, by mux number
+pub struct MuxOp;
+impl PinOp for MuxOp {
+    type Arg = u32;
+    type Result = ();
+
+    #[inline(always)]
+    fn op<M, const N: usize, const P: usize>(mut pin: port::Pin<'_, M, N, P>, mux: u32) {
+        pin.set_mux(mux);
+    }
+}
+
+/// Decode the configuration of an MCU pin
+///
+/// The port's clock gate must be enabled.
+pub(crate) fn pin_config(port: usize, pin: usize) -> PinConfig {
+    let pcr = (0x4004_9000 + 0x1000 * port + 4 * pin) as *const u32;
+    let pddr = (0x400F_F014 + 0x40 * port) as *const u32;
+    let (pcr, pddr) = unsafe { (read_volatile(pcr), read_volatile(pddr)) };
+
+    PinConfig {
+        mcu_pin: (port, pin),
+        function: pcr.get_bits(8..11) as usize,
+        output: pddr.get_bit(pin),
+        pull: match (pcr.get_bit(1), pcr.get_bit(0)) {
+            (false, _) => None,
+            (true, false) => Some(Pull::Down),
+            (true, true) => Some(Pull::Up),
+        },
+        open_drain: pcr.get_bit(5),
+        drive_strength: if pcr.get_bit(6) {
+            DriveStrength::High
+        } else {
+            DriveStrength::Low
+        },
+        slew_rate: if pcr.get_bit(2) {
+            SlewRate::Slow
+        } else {
+            SlewRate::Fast
+        },
+        interrupt: match pcr.get_bits(16..20) {
+            9 => Some(Edge::Rising),
+            10 => Some(Edge::Falling),
+            11 => Some(Edge::Both),
+            _ => None,
+        },
+    }
+}
+
+/// Read the input state of every pin on a port
+pub(crate) fn port_read(port: usize) -> u32 {
+    let pdir = (0x400F_F010 + 0x40 * port) as *const u32;
+    unsafe { read_volatile(pdir) }
+}
+
+/// Set the output state of the pins in `mask` on a port
+pub(crate) fn port_write(port: usize, mask: u32, value: u32) {
+    let pdor = (0x400F_F000 + 0x40 * port) as *mut u32;
+    unsafe {
+        without_interrupts(|| {
+            let current = read_volatile(pdor);
+            write_volatile(pdor, current & !mask | value & mask);
+        });
+    }
+}
+
+/// Handle the interrupt for a port
+///
+/// Each pin with a pending interrupt flag is dispatched to the
+/// modules which use pin interrupts, and its flag is cleared. A pin
+/// which a task was waiting on has its interrupt disabled, until the
+/// task waits again.
+pub(crate) fn port_intr(port: usize) {
+    let isfr = (0x4004_90A0 + 0x1000 * port) as *mut u32;
+    unsafe {
+        let flags = read_volatile(isfr);
+        write_volatile(isfr, flags);
+        for pin in 0..32 {
+            if flags & (1 << pin) != 0 {
+                super::counter::pin_intr(port, pin);
+                if crate::digital::edge_intr(port, pin) {
+                    let pcr = (0x4004_9000 + 0x1000 * port + 4 * pin) as *mut u32;
+                    // IRQC, and leave ISF alone
+                    let value = read_volatile(pcr) & !(0xF << 16) & !(1 << 24);
+                    write_volatile(pcr, value);
+                }
+            }
+        }
+    }
+}
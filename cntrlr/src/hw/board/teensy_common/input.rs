@@ -0,0 +1,213 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Encoder input shared between the various Teensy 3.x boards
+
+use crate::{
+    hw::mcu::kinetis::peripheral::{
+        ftm::Ftm,
+        sim::{GatedPeripheral, Sim},
+        Peripheral,
+    },
+    input::{self, EncoderOption},
+    pwm,
+    sync::{without_interrupts, Mutex, Value},
+};
+use bit_field::BitField;
+use core::{
+    future::{poll_fn, Future},
+    ptr::{read_volatile, write_volatile},
+    sync::atomic::Ordering,
+    task::Poll,
+};
+
+/// An error from an encoder
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum EncoderError {
+    /// The encoder cannot be created because its pins are not a quadrature decoder pair
+    InvalidPins,
+
+    /// The encoder cannot be created because one of its pins is in use
+    PinInUse,
+
+    /// The encoder cannot be created because its timer is in use by PWM or another encoder
+    TimerInUse,
+
+    /// The encoder cannot be created because the SIM is in use
+    SimInUse,
+
+    /// An option is out of range
+    InvalidOptions,
+}
+
+/// A quadrature rotary encoder
+///
+/// The position is counted by a FlexTimer, and extended from 16 to
+/// 32 bits by its overflow interrupt. Turning from phase A towards
+/// phase B counts up. The timer is returned to hardware PWM when the
+/// encoder is dropped.
+pub struct Encoder {
+    encoder: usize,
+}
+
+impl Encoder {
+    /// Start following an encoder
+    ///
+    /// See the [module documentation](crate::input) for the pairs of
+    /// pins which can be used. Any pull resistor set by
+    /// [`pin_mode`](crate::digital::pin_mode) is kept. The position
+    /// starts at zero.
+    pub fn new(
+        pin_a: usize,
+        pin_b: usize,
+        options: &[EncoderOption],
+    ) -> Result<Self, EncoderError> {
+        let mut filter = 0;
+        for option in options {
+            match option {
+                EncoderOption::Filter(value) => filter = *value,
+            }
+        }
+        if filter > 15 {
+            return Err(EncoderError::InvalidOptions);
+        }
+
+        let encoder = input::start_encoder(pin_a, pin_b, filter)?;
+        OVERFLOWS[encoder].store(0, Ordering::Relaxed);
+        Ok(Self { encoder })
+    }
+
+    /// The current position of the encoder
+    ///
+    /// This is a wrapping counter.
+    pub fn position(&self) -> i32 {
+        let overflows = &OVERFLOWS[self.encoder];
+        loop {
+            let high = overflows.load(Ordering::Acquire);
+            let low = input::encoder_count(self.encoder);
+            // An overflow between the two reads would pair the old
+            // high half with the new low half.
+            if overflows.load(Ordering::Acquire) == high {
+                return ((high << 16) | low as usize) as i32;
+            }
+        }
+    }
+
+    /// Reset the position to zero
+    ///
+    /// Returns the position from before the reset.
+    pub fn reset(&mut self) -> i32 {
+        without_interrupts(|| {
+            let position = self.position();
+            input::reset_encoder(self.encoder);
+            OVERFLOWS[self.encoder].store(0, Ordering::Relaxed);
+            position
+        })
+    }
+
+    /// Wait until the position reaches `count`
+    ///
+    /// Resolves to the position once it is at or past `count`, coming
+    /// from the position at the time this was called. The counter
+    /// does not interrupt on each step, so the position is polled
+    /// while waiting.
+    pub fn wait_for_count(&mut self, count: i32) -> impl Future<Output = i32> + '_ {
+        let start = self.position();
+        poll_fn(move |ctx| {
+            let position = self.position();
+            if (start <= count && position >= count) || (start >= count && position <= count) {
+                Poll::Ready(position)
+            } else {
+                ctx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+    }
+}
+
+impl Drop for Encoder {
+    fn drop(&mut self) {
+        input::stop_encoder(self.encoder);
+    }
+}
+
+/// Take an FTM from hardware PWM, and start it decoding an encoder
+///
+/// The FTM is stored in `encoder` while it is decoding. Fails if any
+/// PWM channel of the timer is in use.
+pub fn start<M, const N: usize>(
+    encoder: &Mutex<Option<Ftm<M, N>>>,
+    pwm_timer: &Mutex<Option<Ftm<M, N>>>,
+    filter: u32,
+) -> Result<(), EncoderError>
+where
+    Ftm<M, N>: GatedPeripheral<M>,
+    Sim<M>: Peripheral,
+{
+    if pwm::timer_frequency(N).is_some() {
+        return Err(EncoderError::TimerInUse);
+    }
+    let mut ftm = match pwm_timer.lock().take() {
+        Some(ftm) => ftm,
+        None => Sim::<M>::get()
+            .ok_or(EncoderError::SimInUse)?
+            .enable_peripheral()
+            .ok_or(EncoderError::TimerInUse)?,
+    };
+    ftm.enable_quadrature(filter);
+    *encoder.lock() = Some(ftm);
+    Ok(())
+}
+
+/// The counter of an encoder's FTM
+pub fn count<M, const N: usize>(encoder: &Mutex<Option<Ftm<M, N>>>) -> u16 {
+    encoder.lock().as_ref().map_or(0, Ftm::count)
+}
+
+/// Reset the counter of an encoder's FTM
+pub fn reset<M, const N: usize>(encoder: &Mutex<Option<Ftm<M, N>>>) {
+    if let Some(ftm) = encoder.lock().as_mut() {
+        ftm.reset_count();
+    }
+}
+
+/// Stop an FTM decoding, and return it to hardware PWM
+pub fn stop<M, const N: usize>(
+    encoder: &Mutex<Option<Ftm<M, N>>>,
+    pwm_timer: &Mutex<Option<Ftm<M, N>>>,
+) {
+    if let Some(mut ftm) = encoder.lock().take() {
+        ftm.disable_quadrature();
+        *pwm_timer.lock() = Some(ftm);
+    }
+}
+
+/// Count an overflow of an encoder's FTM
+///
+/// This is called from the FTM interrupt, with the base address of
+/// the FTM's registers.
+pub fn overflow_intr(encoder: usize, base: usize) {
+    let sc = base as *mut u32;
+    let qdctrl = (base + 0x80) as *const u32;
+    unsafe {
+        let mut status = read_volatile(sc);
+        if !status.get_bit(7) {
+            return;
+        }
+        // TOF is cleared by writing 0 after reading it as 1
+        status.set_bit(7, false);
+        write_volatile(sc, status);
+
+        let overflows = &OVERFLOWS[encoder];
+        let high = overflows.load(Ordering::Relaxed);
+        if read_volatile(qdctrl).get_bit(1) {
+            overflows.store(high.wrapping_add(1), Ordering::Release);
+        } else {
+            overflows.store(high.wrapping_sub(1), Ordering::Release);
+        }
+    }
+}
+
+/// The high half of each encoder's position
+static OVERFLOWS: [Value; 2] = [Value::new(0), Value::new(0)];
@@ -3,24 +3,42 @@
 
 //! IO functionality shared between the various Teensy 3.x boards
 
+#[cfg(feature = "owners")]
+use crate::owners;
 use crate::{
     hw::mcu::kinetis::peripheral::{
+        crc::{self, Crc, Transpose},
+        dma::{Controller, DmaMux, Edma, Size, Transfer},
+        flexcan::{self, Rx, Tx},
+        i2c::{self, Scl, Sda},
+        i2s::{self, I2s, Mclk, TxBclk, TxData, TxFs},
         sim::{GatedPeripheral, Sim},
         spi::{self, Cs, Fifo, Sck, Sdi, Sdo},
-        uart::{Uart, UartRx, UartTx},
+        uart::{AddressMatch, Oversample, TwoStopBits, TxDma, Uart, UartRx, UartTx},
         Peripheral,
     },
-    io::{self, SerialOption, SpiOption},
+    io::{self, codec::CrcKind, CanFrame, Parity, SerialOption, SpiOption},
+    pins::HardwareCs,
     task::WakerSet,
 };
 use bit_field::BitField;
+#[cfg(feature = "owners")]
+use core::fmt;
 use core::{
+    cell::UnsafeCell,
     future::{poll_fn, Future},
+    mem::size_of,
+    ptr,
+    sync::atomic::{AtomicUsize, Ordering},
     task::Poll,
 };
 
 /// An error from a serial instance
-#[derive(Debug)]
+///
+/// With the `owners` feature, the `Debug` output of the "in use"
+/// errors includes the holder of the resource, from
+/// [`owners::last_conflict`](crate::owners::last_conflict).
+#[cfg_attr(not(feature = "owners"), derive(Debug))]
 #[non_exhaustive]
 pub enum SerialError {
     /// The serial port cannot be read or written because it is disabled
@@ -40,10 +58,20 @@ pub enum SerialError {
 
     /// The serial port cannot be enabled because the selected baud rate is invalid
     InvalidBaud,
+
+    /// The serial port cannot be written with DMA because the DMA controller is in use
+    DmaInUse,
+
+    /// The serial port cannot be enabled because a selected option is not supported by its UART
+    InvalidOption,
 }
 
 /// An error from a SPI instance
-#[derive(Debug)]
+///
+/// With the `owners` feature, the `Debug` output of the "in use"
+/// errors includes the holder of the resource, from
+/// [`owners::last_conflict`](crate::owners::last_conflict).
+#[cfg_attr(not(feature = "owners"), derive(Debug))]
 #[non_exhaustive]
 pub enum SpiError {
     /// The SPI cannot be read or written because it is disabled
@@ -65,25 +93,352 @@ pub enum SpiError {
     InvalidBaud,
 
     /// The SPI cannot be enabled because a selected option is invalid
+    ///
+    /// This is returned for a [`SpiOption::HardwareCs`] pin which is
+    /// not in [`io::Spi::hardware_cs_pins()`].
     InvalidOption,
 
     /// The SPI packet cannot be written because not enough bytes were provided
     InsufficientData,
 }
 
+/// An error from an I2C instance
+///
+/// With the `owners` feature, the `Debug` output of the "in use"
+/// errors includes the holder of the resource, from
+/// [`owners::last_conflict`](crate::owners::last_conflict).
+#[cfg_attr(not(feature = "owners"), derive(Debug))]
+#[non_exhaustive]
+pub enum I2cError {
+    /// The I2C cannot be used because it is disabled
+    NotEnabled,
+
+    /// The I2C cannot be enabled because its clock or data pin is in use
+    PinInUse,
+
+    /// The I2C cannot be enabled because its PORT is in use
+    PortInUse,
+
+    /// The I2C cannot be enabled because the I2C is in use
+    I2cInUse,
+
+    /// The I2C cannot be enabled because the SIM is in use
+    SimInUse,
+
+    /// The I2C cannot be enabled because the selected clock rate is too slow
+    InvalidRate,
+
+    /// The device cannot be addressed because its address is above 0x7F
+    InvalidAddress,
+
+    /// No device acknowledged the address
+    AddressNack,
+
+    /// The device did not acknowledge a byte written to it
+    DataNack,
+
+    /// Another master took the bus while this one was sending
+    ArbitrationLost,
+}
+
+/// An error from a CAN instance
+///
+/// With the `owners` feature, the `Debug` output of the "in use"
+/// errors includes the holder of the resource, from
+/// [`owners::last_conflict`](crate::owners::last_conflict).
+#[cfg_attr(not(feature = "owners"), derive(Debug))]
+#[non_exhaustive]
+pub enum CanError {
+    /// The CAN cannot be used because it is disabled
+    NotEnabled,
+
+    /// The CAN cannot be enabled because its transmit or receive pin is in use
+    PinInUse,
+
+    /// The CAN cannot be enabled because its PORT is in use
+    PortInUse,
+
+    /// The CAN cannot be enabled because the FlexCAN is in use
+    CanInUse,
+
+    /// The CAN cannot be enabled because the SIM is in use
+    SimInUse,
+
+    /// The CAN cannot be enabled because the bus clock cannot be divided to the selected bit rate
+    InvalidBitrate,
+
+    /// The frame cannot be sent because the controller has left the bus after too many errors
+    BusOff,
+}
+
+/// An error from the audio output
+///
+/// With the `owners` feature, the `Debug` output of the "in use"
+/// errors includes the holder of the resource, from
+/// [`owners::last_conflict`](crate::owners::last_conflict).
+#[cfg_attr(not(feature = "owners"), derive(Debug))]
+#[non_exhaustive]
+pub enum AudioError {
+    /// The audio output cannot be written because it is disabled
+    NotEnabled,
+
+    /// The audio output cannot be enabled because one of its pins is in use
+    PinInUse,
+
+    /// The audio output cannot be enabled because its PORT is in use
+    PortInUse,
+
+    /// The audio output cannot be enabled because the I2S is in use
+    I2sInUse,
+
+    /// The audio output cannot be enabled because the SIM is in use
+    SimInUse,
+
+    /// The audio output cannot be enabled because the DMA controller is in use
+    DmaInUse,
+
+    /// The audio output cannot be enabled because the CPU clock cannot be divided to the selected sample rate
+    InvalidSampleRate,
+}
+
+#[cfg(feature = "owners")]
+impl fmt::Debug for SerialError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SerialError::NotEnabled => f.write_str("NotEnabled"),
+            SerialError::PinInUse => debug_conflict(f, "PinInUse", true),
+            SerialError::PortInUse => debug_conflict(f, "PortInUse", false),
+            SerialError::UartInUse => debug_conflict(f, "UartInUse", false),
+            SerialError::SimInUse => f.write_str("SimInUse"),
+            SerialError::InvalidBaud => f.write_str("InvalidBaud"),
+            SerialError::DmaInUse => debug_conflict(f, "DmaInUse", false),
+            SerialError::InvalidOption => f.write_str("InvalidOption"),
+        }
+    }
+}
+
+#[cfg(feature = "owners")]
+impl fmt::Debug for SpiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpiError::NotEnabled => f.write_str("NotEnabled"),
+            SpiError::PinInUse => debug_conflict(f, "PinInUse", true),
+            SpiError::PortInUse => debug_conflict(f, "PortInUse", false),
+            SpiError::SpiInUse => debug_conflict(f, "SpiInUse", false),
+            SpiError::SimInUse => f.write_str("SimInUse"),
+            SpiError::InvalidBaud => f.write_str("InvalidBaud"),
+            SpiError::InvalidOption => f.write_str("InvalidOption"),
+            SpiError::InsufficientData => f.write_str("InsufficientData"),
+        }
+    }
+}
+
+#[cfg(feature = "owners")]
+impl fmt::Debug for I2cError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            I2cError::NotEnabled => f.write_str("NotEnabled"),
+            I2cError::PinInUse => debug_conflict(f, "PinInUse", true),
+            I2cError::PortInUse => debug_conflict(f, "PortInUse", false),
+            I2cError::I2cInUse => debug_conflict(f, "I2cInUse", false),
+            I2cError::SimInUse => f.write_str("SimInUse"),
+            I2cError::InvalidRate => f.write_str("InvalidRate"),
+            I2cError::InvalidAddress => f.write_str("InvalidAddress"),
+            I2cError::AddressNack => f.write_str("AddressNack"),
+            I2cError::DataNack => f.write_str("DataNack"),
+            I2cError::ArbitrationLost => f.write_str("ArbitrationLost"),
+        }
+    }
+}
+
+#[cfg(feature = "owners")]
+impl fmt::Debug for CanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CanError::NotEnabled => f.write_str("NotEnabled"),
+            CanError::PinInUse => debug_conflict(f, "PinInUse", true),
+            CanError::PortInUse => debug_conflict(f, "PortInUse", false),
+            CanError::CanInUse => debug_conflict(f, "CanInUse", false),
+            CanError::SimInUse => f.write_str("SimInUse"),
+            CanError::InvalidBitrate => f.write_str("InvalidBitrate"),
+            CanError::BusOff => f.write_str("BusOff"),
+        }
+    }
+}
+
+#[cfg(feature = "owners")]
+impl fmt::Debug for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AudioError::NotEnabled => f.write_str("NotEnabled"),
+            AudioError::PinInUse => debug_conflict(f, "PinInUse", true),
+            AudioError::PortInUse => debug_conflict(f, "PortInUse", false),
+            AudioError::I2sInUse => debug_conflict(f, "I2sInUse", false),
+            AudioError::SimInUse => f.write_str("SimInUse"),
+            AudioError::DmaInUse => debug_conflict(f, "DmaInUse", false),
+            AudioError::InvalidSampleRate => f.write_str("InvalidSampleRate"),
+        }
+    }
+}
+
+/// Format an "in use" error with the last conflict over a pin or a peripheral
+///
+/// The conflict is left out if it was over the other kind of
+/// resource, since it cannot be the one which caused the error.
+#[cfg(feature = "owners")]
+fn debug_conflict(f: &mut fmt::Formatter, name: &str, pin: bool) -> fmt::Result {
+    let conflict = owners::last_conflict()
+        .filter(|conflict| matches!(conflict.resource, owners::Resource::Pin { .. }) == pin);
+    f.debug_struct(name).field("conflict", &conflict).finish()
+}
+
+impl From<SerialError> for io::Error {
+    fn from(err: SerialError) -> Self {
+        let kind = match err {
+            SerialError::NotEnabled => io::ErrorKind::NotEnabled,
+            SerialError::PinInUse
+            | SerialError::PortInUse
+            | SerialError::UartInUse
+            | SerialError::SimInUse
+            | SerialError::DmaInUse => io::ErrorKind::InUse,
+            SerialError::InvalidBaud | SerialError::InvalidOption => io::ErrorKind::InvalidSetting,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+impl From<SpiError> for io::Error {
+    fn from(err: SpiError) -> Self {
+        let kind = match err {
+            SpiError::NotEnabled => io::ErrorKind::NotEnabled,
+            SpiError::PinInUse | SpiError::PortInUse | SpiError::SpiInUse | SpiError::SimInUse => {
+                io::ErrorKind::InUse
+            }
+            SpiError::InvalidBaud | SpiError::InvalidOption => io::ErrorKind::InvalidSetting,
+            SpiError::InsufficientData => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+impl From<I2cError> for io::Error {
+    fn from(err: I2cError) -> Self {
+        let kind = match err {
+            I2cError::NotEnabled => io::ErrorKind::NotEnabled,
+            I2cError::PinInUse | I2cError::PortInUse | I2cError::I2cInUse | I2cError::SimInUse => {
+                io::ErrorKind::InUse
+            }
+            I2cError::InvalidRate => io::ErrorKind::InvalidSetting,
+            I2cError::InvalidAddress => io::ErrorKind::InvalidData,
+            I2cError::AddressNack | I2cError::DataNack => io::ErrorKind::NotAcknowledged,
+            I2cError::ArbitrationLost => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+impl From<CanError> for io::Error {
+    fn from(err: CanError) -> Self {
+        let kind = match err {
+            CanError::NotEnabled => io::ErrorKind::NotEnabled,
+            CanError::PinInUse | CanError::PortInUse | CanError::CanInUse | CanError::SimInUse => {
+                io::ErrorKind::InUse
+            }
+            CanError::InvalidBitrate => io::ErrorKind::InvalidSetting,
+            CanError::BusOff => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+impl From<AudioError> for io::Error {
+    fn from(err: AudioError) -> Self {
+        let kind = match err {
+            AudioError::NotEnabled => io::ErrorKind::NotEnabled,
+            AudioError::PinInUse
+            | AudioError::PortInUse
+            | AudioError::I2sInUse
+            | AudioError::SimInUse
+            | AudioError::DmaInUse => io::ErrorKind::InUse,
+            AudioError::InvalidSampleRate => io::ErrorKind::InvalidSetting,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+/// Compute a CRC with the CRC module
+///
+/// Returns `None` if the CRC module or the SIM is in use.
+#[cfg_attr(board = "teensy_lc", allow(dead_code))]
+pub(crate) fn hardware_crc<M>(kind: CrcKind, data: &[u8]) -> Option<u32>
+where
+    Crc<M>: GatedPeripheral<M>,
+    Sim<M>: Peripheral,
+{
+    let mut crc = Sim::<M>::get()?.enable_peripheral::<Crc<M>>()?;
+    match kind {
+        CrcKind::Crc16 => {
+            crc.start(
+                crc::Width::Bits16,
+                0x1021,
+                0xFFFF,
+                Transpose::None,
+                Transpose::None,
+                false,
+            );
+            crc.write(data);
+            Some(crc.result() & 0xFFFF)
+        }
+        CrcKind::Crc32 => {
+            crc.start(
+                crc::Width::Bits32,
+                0x04C1_1DB7,
+                0xFFFF_FFFF,
+                Transpose::Bits,
+                Transpose::BitsAndBytes,
+                true,
+            );
+            crc.write(data);
+            Some(crc.result())
+        }
+    }
+}
+
 /// A serial instance
 ///
 /// This wraps a UART and provides application-level functionality.
 pub struct Serial<M, T, R, const N: usize>(
     pub(crate) Option<Uart<M, T, R, N>>,
     pub(crate) Option<&'static WakerSet>,
+    pub(crate) io::SerialStats,
+    pub(crate) Option<usize>,
 );
 
+/// An MCU whose serial ports can write through DMA
+///
+/// Each serial port uses the DMA channel with the same index as its
+/// UART, so serial 1 uses channel 0.
+/// The audio output shares the controller, on
+/// [`AUDIO_DMA_CHANNEL`].
+pub trait SerialDma: Sized {
+    /// The DMA controller
+    type Dma: Controller;
+
+    /// Run a function with the DMA controller and multiplexer,
+    /// enabling them on first use
+    fn with_dma<F, U>(f: F) -> Result<U, SerialError>
+    where
+        F: FnOnce(&mut Self::Dma, &mut DmaMux<Self>) -> U;
+
+    /// The wakers for a DMA channel
+    fn dma_wakers(channel: usize) -> &'static WakerSet;
+}
+
 impl<M, T, R, const N: usize> Serial<M, T, R, N>
 where
     T: UartTx<M, N>,
     R: UartRx<M, N>,
-    Uart<M, (), (), N>: GatedPeripheral<M>,
+    Uart<M, (), (), N>: GatedPeripheral<M> + AddressMatch + Oversample + TwoStopBits,
     Sim<M>: Peripheral,
 {
     pub(crate) fn do_enable(
@@ -95,24 +450,92 @@ where
         source_clock: usize,
         wakers: &'static WakerSet,
     ) -> Result<(), SerialError> {
-        let divisor = (source_clock * 32) / (baud * 16);
-        if divisor < 32 {
-            return Err(SerialError::InvalidBaud);
+        let oversample = <Uart<M, (), (), N> as Oversample>::OVERSAMPLE;
+        let mut ratio = 16;
+        let mut parity = Parity::None;
+        let mut two_stop_bits = false;
+        let mut nine_bit = false;
+        let mut address_mark = false;
+        for option in options {
+            match option {
+                SerialOption::Oversample(oversample_ratio) => {
+                    if !oversample || !(4..=32).contains(oversample_ratio) {
+                        return Err(SerialError::InvalidOption);
+                    }
+                    ratio = *oversample_ratio;
+                }
+                SerialOption::Parity(option_parity) => parity = *option_parity,
+                SerialOption::StopBits(1) => two_stop_bits = false,
+                SerialOption::StopBits(2) if <Uart<M, (), (), N> as TwoStopBits>::TWO_STOP_BITS => {
+                    two_stop_bits = true
+                }
+                SerialOption::DataBits(8) => nine_bit = false,
+                SerialOption::DataBits(9) => nine_bit = true,
+                SerialOption::StopBits(_) | SerialOption::DataBits(_) => {
+                    return Err(SerialError::InvalidOption)
+                }
+                SerialOption::AddressMark(_) => address_mark = true,
+                _ => {}
+            }
+        }
+        // Parity and 9 data bits both take the ninth bit, as does the
+        // address mark.
+        let ninth_bit_uses = [parity != Parity::None, nine_bit, address_mark];
+        if ninth_bit_uses.iter().filter(|&&used| used).count() > 1 {
+            return Err(SerialError::InvalidOption);
         }
+
+        let divisor = if oversample {
+            let rate = baud * ratio as usize;
+            let divisor = (source_clock + rate / 2) / rate;
+            if !(1..0x2000).contains(&divisor) {
+                return Err(SerialError::InvalidBaud);
+            }
+            divisor
+        } else {
+            let divisor = (source_clock * 32) / (baud * 16);
+            if divisor < 32 {
+                return Err(SerialError::InvalidBaud);
+            }
+            divisor
+        };
         let mut uart = Sim::<M>::get()
             .ok_or(SerialError::SimInUse)?
             .enable_peripheral::<Uart<M, (), (), N>>()
             .ok_or(SerialError::UartInUse)?;
-        uart.set_divisor(divisor);
+        if oversample {
+            uart.set_oversampled_divisor(divisor, ratio);
+        } else {
+            uart.set_divisor(divisor);
+        }
+        uart.set_idle_after_stop(true);
+        uart.set_nine_bit(nine_bit || parity != Parity::None);
+        uart.set_parity(parity);
+        uart.set_two_stop_bits(two_stop_bits);
 
+        let mut dma_threshold = None;
         for option in options {
             match option {
                 SerialOption::Invert(invert) => uart.invert(*invert),
+                SerialOption::Loopback(loopback) => uart.set_loopback(*loopback),
+                SerialOption::AddressMark(address) => {
+                    if !<Uart<M, (), (), N> as AddressMatch>::ADDRESS_MATCH {
+                        return Err(SerialError::InvalidOption);
+                    }
+                    uart.set_address_mark(true);
+                    uart.set_match_address(Some(*address));
+                    uart.sleep_until_address();
+                }
+                SerialOption::Oversample(_) => {}
+                SerialOption::DmaThreshold(threshold) => dma_threshold = Some(*threshold),
+                SerialOption::Parity(_) | SerialOption::StopBits(_) | SerialOption::DataBits(_) => {
+                }
             }
         }
 
         self.0 = Some(uart.enable_tx(tx).enable_rx(rx));
         self.1 = Some(wakers);
+        self.3 = dma_threshold;
         Ok(())
     }
 }
@@ -137,6 +560,9 @@ where
 
             let mut count = 0;
             let uart = self.0.as_mut().ok_or(SerialError::NotEnabled)?;
+            if uart.check_overrun() {
+                self.2.overruns = self.2.overruns.wrapping_add(1);
+            }
             while let Some(byte) = uart.read_data() {
                 buf[count] = byte;
                 count += 1;
@@ -145,6 +571,7 @@ where
                 }
             }
             if count > 0 {
+                self.2.max_burst = self.2.max_burst.max(count);
                 Poll::Ready(Ok(count))
             } else {
                 if let Some(wakers) = self.1.as_ref() {
@@ -159,31 +586,45 @@ where
 
 impl<M, T, R, const N: usize> io::Write for Serial<M, T, R, N>
 where
-    M: 'static,
+    M: SerialDma + 'static,
     T: UartTx<M, N> + 'static,
     R: 'static,
+    Uart<M, T, R, N>: TxDma,
 {
     type Error = SerialError;
     type Future<'a> = impl Future<Output = Result<usize, Self::Error>> + 'a;
     type FlushFuture<'a> = impl Future<Output = Result<(), Self::Error>> + 'a;
+    type VectoredFuture<'a> = impl Future<Output = Result<usize, Self::Error>> + 'a;
 
     fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Future<'a>
+    where
+        Self: 'a,
+    {
+        async move {
+            match self.3 {
+                Some(threshold) if buf.len() >= threshold => self.write_dma(buf).await,
+                _ => self.write_intr(buf).await,
+            }
+        }
+    }
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [io::IoSlice<'a>]) -> Self::VectoredFuture<'a>
     where
         Self: 'a,
     {
         poll_fn(move |ctx| {
-            if buf.is_empty() {
+            if bufs.iter().all(|buf| buf.is_empty()) {
                 return Poll::Ready(Ok(0));
             }
 
             let mut count = 0;
-            let mut buf = buf;
             let uart = self.0.as_mut().ok_or(SerialError::NotEnabled)?;
-            while uart.write_data(buf[0]) {
-                count += 1;
-                buf = &buf[1..];
-                if buf.is_empty() {
-                    break;
+            'bufs: for buf in bufs {
+                for &byte in buf.iter() {
+                    if !uart.write_data(byte) {
+                        break 'bufs;
+                    }
+                    count += 1;
                 }
             }
             if count > 0 {
@@ -220,7 +661,256 @@ where
 impl<M, T, R, const N: usize> Serial<M, T, R, N> {
     /// Create a new instance of a serial port, in a disabled state.
     pub const fn new() -> Self {
-        Self(None, None)
+        Self(None, None, io::SerialStats::new(), None)
+    }
+
+    /// Ignore received data until this node is addressed again
+    ///
+    /// This is for ports enabled with [`SerialOption::AddressMark`].
+    /// Call it once a message addressed to this node has been read,
+    /// so that messages to other nodes are never received.
+    pub fn sleep_until_addressed(&mut self) -> Result<(), SerialError> {
+        let uart = self.0.as_mut().ok_or(SerialError::NotEnabled)?;
+        uart.sleep_until_address();
+        Ok(())
+    }
+}
+
+impl<M, T, R, const N: usize> Serial<M, T, R, N>
+where
+    T: UartTx<M, N>,
+{
+    /// Write bytes from the transmit interrupt
+    fn write_intr<'a>(
+        &'a mut self,
+        buf: &'a [u8],
+    ) -> impl Future<Output = Result<usize, SerialError>> + 'a {
+        poll_fn(move |ctx| {
+            if buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+
+            let mut count = 0;
+            let mut buf = buf;
+            let uart = self.0.as_mut().ok_or(SerialError::NotEnabled)?;
+            while uart.write_data(buf[0]) {
+                count += 1;
+                buf = &buf[1..];
+                if buf.is_empty() {
+                    break;
+                }
+            }
+            if count > 0 {
+                Poll::Ready(Ok(count))
+            } else {
+                if let Some(wakers) = self.1.as_ref() {
+                    wakers.add(ctx.waker().clone());
+                }
+                uart.enable_tx_intr();
+                Poll::Pending
+            }
+        })
+    }
+
+    /// Write as much data as the UART can accept right now
+    ///
+    /// This fills the transmitter once, without waiting or
+    /// registering a waker, so it can be used outside the executor,
+    /// such as from an interrupt or panic handler. Returns the number
+    /// of bytes written, which is zero if the port is disabled.
+    pub fn try_write(&mut self, buf: &[u8]) -> usize {
+        let uart = match self.0.as_mut() {
+            Some(uart) => uart,
+            None => return 0,
+        };
+        buf.iter()
+            .take_while(|&&byte| uart.write_data(byte))
+            .count()
+    }
+
+    /// Write all of a buffer, busy-waiting while the UART is full
+    ///
+    /// This is for the panic handler, which cannot wait on the
+    /// executor. It returns immediately if the port is disabled.
+    pub(crate) fn write_blocking(&mut self, mut buf: &[u8]) {
+        if self.0.is_none() {
+            return;
+        }
+        while !buf.is_empty() {
+            let count = self.try_write(buf);
+            buf = &buf[count..];
+        }
+    }
+
+    /// Send an address, selecting a node on a multi-drop network
+    ///
+    /// The address is sent with the address mark set, waking the
+    /// node with that address. Bytes sent with
+    /// [`io::Write::write()`] are data for the addressed node.
+    pub fn write_address(
+        &mut self,
+        address: u8,
+    ) -> impl Future<Output = Result<(), SerialError>> + '_ {
+        poll_fn(move |ctx| {
+            let uart = self.0.as_mut().ok_or(SerialError::NotEnabled)?;
+            if uart.write_address(address) {
+                Poll::Ready(Ok(()))
+            } else {
+                if let Some(wakers) = self.1.as_ref() {
+                    wakers.add(ctx.waker().clone());
+                }
+                uart.enable_tx_intr();
+                Poll::Pending
+            }
+        })
+    }
+}
+
+impl<M, T, R, const N: usize> Serial<M, T, R, N>
+where
+    M: SerialDma,
+    T: UartTx<M, N>,
+    Uart<M, T, R, N>: TxDma,
+{
+    /// Write bytes from a buffer using DMA
+    ///
+    /// Rather than interrupting for each byte, the UART requests each
+    /// byte from the DMA controller, which leaves the CPU free while
+    /// a long buffer is sent. Each serial port uses the DMA channel
+    /// with the same index as its UART, so serial 1 uses channel 0.
+    ///
+    /// At most `0xF_FFFF` bytes are written on the Teensy LC, and
+    /// `0x7FFF` on the Teensy 3.x. Returns the number of bytes
+    /// written.
+    pub fn write_dma<'a>(
+        &'a mut self,
+        buf: &'a [u8],
+    ) -> impl Future<Output = Result<usize, SerialError>> + 'a {
+        async move {
+            if buf.is_empty() {
+                return Ok(0);
+            }
+
+            let count = buf.len().min(<M::Dma as Controller>::MAX_COUNT);
+            let uart = self.0.as_mut().ok_or(SerialError::NotEnabled)?;
+            M::with_dma(|dma, mux| {
+                mux.set_source(N, Some(<Uart<M, T, R, N> as TxDma>::TX_REQUEST));
+                // Safety: the write below does not complete until the
+                // transfer is done, and the transfer is stopped if the
+                // write is dropped, so the buffer outlives the transfer.
+                unsafe {
+                    dma.start(
+                        N,
+                        &Transfer {
+                            source: buf.as_ptr() as usize,
+                            source_increment: true,
+                            destination: uart.data_address(),
+                            destination_increment: false,
+                            size: Size::Byte,
+                            count,
+                            peripheral_request: true,
+                            interrupt: true,
+                        },
+                    );
+                }
+            })?;
+            let write = DmaWrite(uart);
+            write.0.enable_tx_dma(true);
+
+            let remaining = poll_fn(|ctx| {
+                M::dma_wakers(N).add(ctx.waker().clone());
+                match M::with_dma(|dma, _| dma.is_done(N)) {
+                    Ok(true) => Poll::Ready(M::with_dma(|dma, _| dma.remaining(N))),
+                    Ok(false) => Poll::Pending,
+                    Err(err) => Poll::Ready(Err(err)),
+                }
+            })
+            .await?;
+            drop(write);
+            Ok(count - remaining)
+        }
+    }
+}
+
+/// A DMA write in progress
+///
+/// This stops the transfer and returns the UART to interrupt-driven
+/// writes when the write completes or is dropped.
+struct DmaWrite<'a, M, T, R, const N: usize>(&'a mut Uart<M, T, R, N>)
+where
+    M: SerialDma,
+    T: UartTx<M, N>,
+    Uart<M, T, R, N>: TxDma;
+
+impl<'a, M, T, R, const N: usize> Drop for DmaWrite<'a, M, T, R, N>
+where
+    M: SerialDma,
+    T: UartTx<M, N>,
+    Uart<M, T, R, N>: TxDma,
+{
+    fn drop(&mut self) {
+        self.0.enable_tx_dma(false);
+        let _ = M::with_dma(|dma, mux| {
+            dma.stop(N);
+            dma.clear(N);
+            mux.set_source(N, None);
+        });
+    }
+}
+
+impl<M, T, R, const N: usize> Serial<M, T, R, N>
+where
+    R: UartRx<M, N>,
+{
+    /// Read whatever data the UART has already received
+    ///
+    /// This drains the receiver once, without waiting or registering
+    /// a waker, so it can be used outside the executor, such as from
+    /// an interrupt or panic handler. Returns the number of bytes
+    /// read, which is zero if the port is disabled.
+    pub fn try_read(&mut self, buf: &mut [u8]) -> usize {
+        let uart = match self.0.as_mut() {
+            Some(uart) => uart,
+            None => return 0,
+        };
+        if uart.check_overrun() {
+            self.2.overruns = self.2.overruns.wrapping_add(1);
+        }
+        let mut count = 0;
+        while count < buf.len() {
+            match uart.read_data() {
+                Some(byte) => buf[count] = byte,
+                None => break,
+            }
+            count += 1;
+        }
+        self.2.max_burst = self.2.max_burst.max(count);
+        count
+    }
+
+    /// Wait for the receive line to go idle
+    ///
+    /// This resolves once the line has been idle for a character time
+    /// after receiving data, which marks the end of a message in
+    /// protocols framed by gaps on the line. The message can then be
+    /// read without guessing at a timeout between bytes.
+    ///
+    /// The idle line is latched by the hardware until it is waited
+    /// for, or the received data is read, so a message which ended
+    /// before this is called resolves it immediately.
+    pub fn wait_idle(&mut self) -> impl Future<Output = Result<(), SerialError>> + '_ {
+        poll_fn(move |ctx| {
+            let uart = self.0.as_mut().ok_or(SerialError::NotEnabled)?;
+            if uart.check_idle() {
+                Poll::Ready(Ok(()))
+            } else {
+                if let Some(wakers) = self.1.as_ref() {
+                    wakers.add(ctx.waker().clone());
+                }
+                uart.enable_idle_intr();
+                Poll::Pending
+            }
+        })
     }
 }
 
@@ -558,6 +1248,20 @@ impl<M, I, O, C, CS, const N: usize> Spi<M, I, O, C, CS, N> {
     }
 }
 
+impl<M, I, O, C, CS, const N: usize> Spi<M, I, O, C, CS, N>
+where
+    Spi<M, I, O, C, CS, N>: io::Spi<Error = SpiError>,
+{
+    /// Enable the SPI port with a hardware chip select
+    ///
+    /// This is [`SpiOption::HardwareCs`] with the pin checked at
+    /// compile time: passing a pin which cannot be a chip select
+    /// for this port fails to compile.
+    pub fn enable_with_cs<P: HardwareCs<N>>(&mut self) -> Result<(), SpiError> {
+        io::Spi::enable_with_options(self, &[SpiOption::HardwareCs(P::NUMBER)])
+    }
+}
+
 impl<M, I, O, C, CS, const N: usize> io::Spi for Spi<M, I, O, C, CS, N>
 where
     I: Sdi<M, N>,
@@ -603,6 +1307,10 @@ where
         Ok(())
     }
 
+    fn hardware_cs_pins(&self) -> &'static [usize] {
+        <Self as SpiBoard<I, O, C, CS>>::hardware_cs_pins()
+    }
+
     fn transfer<'a>(&'a mut self, baud: usize, cs: usize, packet: usize) -> Self::TransferFuture<'a>
     where
         Self: 'a,
@@ -735,4 +1443,550 @@ pub trait SpiBoard<I, O, C, CS> {
     fn clock_source() -> usize;
     fn wakers() -> &'static WakerSet;
     fn hardware_cs(cs: usize) -> Option<usize>;
+    fn hardware_cs_pins() -> &'static [usize];
+}
+
+/// An I2C bus master
+pub struct I2c<M, C, D, const N: usize> {
+    pub(crate) i2c: Option<i2c::I2c<M, C, D, N>>,
+    pub(crate) wakers: Option<&'static WakerSet>,
+}
+
+impl<M, C, D, const N: usize> I2c<M, C, D, N> {
+    /// Create a new instance of an I2C, in a disabled state.
+    pub const fn new() -> Self {
+        Self {
+            i2c: None,
+            wakers: None,
+        }
+    }
+}
+
+impl<M, C, D, const N: usize> I2c<M, C, D, N>
+where
+    C: Scl<M, N>,
+    D: Sda<M, N>,
+{
+    /// Run a transfer, leaving the bus held if it is still held
+    async fn transfer(
+        &mut self,
+        address: u8,
+        buf_in: &[u8],
+        buf_out: &mut [u8],
+    ) -> Result<(), I2cError> {
+        if address > 0x7F {
+            return Err(I2cError::InvalidAddress);
+        }
+        let wakers = self.wakers;
+        let i2c = self.i2c.as_mut().ok_or(I2cError::NotEnabled)?;
+
+        // Another master releasing the bus does not interrupt, so
+        // keep checking until it is idle.
+        poll_fn(|ctx| {
+            if i2c.is_busy() {
+                ctx.waker().wake_by_ref();
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await;
+
+        if !buf_in.is_empty() || buf_out.is_empty() {
+            i2c.start();
+            i2c.write_data(address << 1);
+            Self::wait(i2c, wakers).await?;
+            if !i2c.received_ack() {
+                return Err(I2cError::AddressNack);
+            }
+            for &byte in buf_in {
+                i2c.write_data(byte);
+                Self::wait(i2c, wakers).await?;
+                if !i2c.received_ack() {
+                    return Err(I2cError::DataNack);
+                }
+            }
+        }
+
+        if !buf_out.is_empty() {
+            if i2c.is_master() {
+                i2c.repeated_start();
+            } else {
+                i2c.start();
+            }
+            i2c.write_data(address << 1 | 1);
+            Self::wait(i2c, wakers).await?;
+            if !i2c.received_ack() {
+                return Err(I2cError::AddressNack);
+            }
+
+            // Each read of the data register starts receiving the
+            // next byte. The first read only starts the transfer,
+            // and the stop is sent before the last byte is read.
+            let last = buf_out.len() - 1;
+            i2c.set_transmit(false);
+            i2c.set_ack(last > 0);
+            i2c.read_data();
+            for (idx, byte) in buf_out.iter_mut().enumerate() {
+                Self::wait(i2c, wakers).await?;
+                if idx == last {
+                    i2c.stop();
+                } else if idx + 1 == last {
+                    // The last byte is not acknowledged, to end the read
+                    i2c.set_ack(false);
+                }
+                *byte = i2c.read_data();
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait for a byte to be transferred
+    async fn wait(
+        i2c: &mut i2c::I2c<M, C, D, N>,
+        wakers: Option<&'static WakerSet>,
+    ) -> Result<(), I2cError> {
+        poll_fn(|ctx| {
+            if i2c.check_intr() {
+                Poll::Ready(())
+            } else {
+                if let Some(wakers) = wakers {
+                    wakers.add(ctx.waker().clone());
+                }
+                i2c.enable_intr();
+                Poll::Pending
+            }
+        })
+        .await;
+        if i2c.check_arbitration_lost() {
+            Err(I2cError::ArbitrationLost)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<M, C, D, const N: usize> io::I2c for I2c<M, C, D, N>
+where
+    C: Scl<M, N>,
+    D: Sda<M, N>,
+    i2c::I2c<M, (), (), N>: GatedPeripheral<M>,
+    Sim<M>: Peripheral,
+    I2c<M, C, D, N>: I2cBoard<C, D>,
+{
+    type Error = I2cError;
+    #[rustfmt::skip]
+    type Future<'a> where Self: 'a = impl Future<Output = Result<(), Self::Error>>;
+
+    fn enable(&mut self, rate: usize) -> Result<(), Self::Error> {
+        let scl = Self::scl()?;
+        let sda = Self::sda()?;
+
+        let mut i2c = Sim::<M>::get()
+            .ok_or(I2cError::SimInUse)?
+            .enable_peripheral::<i2c::I2c<M, (), (), N>>()
+            .ok_or(I2cError::I2cInUse)?
+            .enable(scl, sda);
+        if !i2c.set_rate(Self::clock_source(), rate) {
+            return Err(I2cError::InvalidRate);
+        }
+
+        self.i2c = Some(i2c);
+        self.wakers = Some(Self::wakers());
+        Ok(())
+    }
+
+    fn disable(&mut self) -> Result<(), I2cError> {
+        self.i2c = None;
+        self.wakers = None;
+        Ok(())
+    }
+
+    fn write_read<'a>(
+        &'a mut self,
+        address: u8,
+        buf_in: &'a [u8],
+        buf_out: &'a mut [u8],
+    ) -> Self::Future<'a>
+    where
+        Self: 'a,
+    {
+        async move {
+            let result = self.transfer(address, buf_in, buf_out).await;
+            if let Some(i2c) = self.i2c.as_mut() {
+                if i2c.is_master() {
+                    i2c.stop();
+                }
+            }
+            result
+        }
+    }
+}
+
+#[allow(missing_docs)]
+pub trait I2cBoard<C, D> {
+    fn scl() -> Result<C, I2cError>;
+    fn sda() -> Result<D, I2cError>;
+    fn clock_source() -> usize;
+    fn wakers() -> &'static WakerSet;
+}
+
+/// A CAN bus controller
+pub struct Can<M, T, R, const N: usize> {
+    pub(crate) can: Option<flexcan::Can<M, T, R, N>>,
+    pub(crate) wakers: Option<&'static WakerSet>,
+}
+
+impl<M, T, R, const N: usize> Can<M, T, R, N> {
+    /// Create a new instance of a CAN, in a disabled state.
+    pub const fn new() -> Self {
+        Self {
+            can: None,
+            wakers: None,
+        }
+    }
+}
+
+impl<M, T, R, const N: usize> io::Can for Can<M, T, R, N>
+where
+    T: Tx<M, N>,
+    R: Rx<M, N>,
+    flexcan::Can<M, (), (), N>: GatedPeripheral<M>,
+    Sim<M>: Peripheral,
+    Can<M, T, R, N>: CanBoard<T, R>,
+{
+    type Error = CanError;
+    #[rustfmt::skip]
+    type SendFuture<'a> where Self: 'a = impl Future<Output = Result<(), Self::Error>>;
+    #[rustfmt::skip]
+    type RecvFuture<'a> where Self: 'a = impl Future<Output = Result<CanFrame, Self::Error>>;
+
+    fn enable(&mut self, bitrate: usize) -> Result<(), Self::Error> {
+        let tx = Self::tx()?;
+        let rx = Self::rx()?;
+
+        let mut can = Sim::<M>::get()
+            .ok_or(CanError::SimInUse)?
+            .enable_peripheral::<flexcan::Can<M, (), (), N>>()
+            .ok_or(CanError::CanInUse)?;
+        if !can.set_bitrate(Self::clock_source(), bitrate) {
+            return Err(CanError::InvalidBitrate);
+        }
+
+        self.can = Some(can.enable(tx, rx));
+        self.wakers = Some(Self::wakers());
+        Ok(())
+    }
+
+    fn disable(&mut self) -> Result<(), CanError> {
+        if let Some(can) = self.can.as_mut() {
+            can.disable();
+        }
+        self.can = None;
+        self.wakers = None;
+        Ok(())
+    }
+
+    fn send<'a>(&'a mut self, frame: CanFrame) -> Self::SendFuture<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            let can = self.can.as_mut().ok_or(CanError::NotEnabled)?;
+            if can.is_bus_off() {
+                return Poll::Ready(Err(CanError::BusOff));
+            }
+            if can.transmit(&frame) {
+                Poll::Ready(Ok(()))
+            } else {
+                if let Some(wakers) = self.wakers {
+                    wakers.add(ctx.waker().clone());
+                }
+                can.enable_tx_intr();
+                Poll::Pending
+            }
+        })
+    }
+
+    fn recv<'a>(&'a mut self) -> Self::RecvFuture<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            let can = self.can.as_mut().ok_or(CanError::NotEnabled)?;
+            match can.receive() {
+                Some(frame) => Poll::Ready(Ok(frame)),
+                None => {
+                    if let Some(wakers) = self.wakers {
+                        wakers.add(ctx.waker().clone());
+                    }
+                    can.enable_rx_intr();
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
+
+#[allow(missing_docs)]
+pub trait CanBoard<T, R> {
+    fn tx() -> Result<T, CanError>;
+    fn rx() -> Result<R, CanError>;
+    fn clock_source() -> usize;
+    fn wakers() -> &'static WakerSet;
+}
+
+/// The DMA channel which feeds the audio output
+pub const AUDIO_DMA_CHANNEL: usize = 8;
+
+/// The number of frames in each half of the audio buffer
+pub const AUDIO_BLOCK: usize = 128;
+
+/// The double buffer of an audio output
+///
+/// The DMA controller plays one half of the buffer while the other
+/// half is filled.
+pub struct AudioBuffer {
+    frames: UnsafeCell<[[i16; 2]; 2 * AUDIO_BLOCK]>,
+    played: AtomicUsize,
+}
+
+// The DMA interrupt only touches the half which was just played, and
+// the output only writes to the other half.
+unsafe impl Sync for AudioBuffer {}
+
+impl AudioBuffer {
+    /// Create a silent buffer
+    pub const fn new() -> Self {
+        Self {
+            frames: UnsafeCell::new([[0; 2]; 2 * AUDIO_BLOCK]),
+            played: AtomicUsize::new(0),
+        }
+    }
+
+    /// Record that the DMA controller has finished playing a half
+    ///
+    /// This is called from the DMA interrupt. The half is cleared, so
+    /// that it plays as silence if it is not refilled in time.
+    pub fn half_played(&self) {
+        let played = self.played.load(Ordering::Relaxed);
+        unsafe {
+            let half = (self.frames.get() as *mut [i16; 2]).add(played % 2 * AUDIO_BLOCK);
+            ptr::write_bytes(half, 0, AUDIO_BLOCK);
+        }
+        self.played.store(played.wrapping_add(1), Ordering::Release);
+    }
+
+    /// The number of halves which have been played
+    fn played(&self) -> usize {
+        self.played.load(Ordering::Acquire)
+    }
+
+    /// Silence the whole buffer, and restart the count of played halves
+    ///
+    /// # Safety
+    /// The buffer must not be playing.
+    unsafe fn reset(&self) {
+        ptr::write_bytes(self.frames.get(), 0, 1);
+        self.played.store(0, Ordering::Release);
+    }
+
+    /// Copy frames into the buffer
+    ///
+    /// # Safety
+    /// The half must not be playing.
+    unsafe fn fill(&self, half: usize, offset: usize, frames: &[[i16; 2]]) {
+        assert!(offset + frames.len() <= AUDIO_BLOCK);
+        let dst = (self.frames.get() as *mut [i16; 2]).add(half * AUDIO_BLOCK + offset);
+        ptr::copy_nonoverlapping(frames.as_ptr(), dst, frames.len());
+    }
+}
+
+/// An I2S audio output
+pub struct AudioOut<M, C, B, F, D> {
+    i2s: Option<I2s<M, C, B, F, D>>,
+    sample_rate: usize,
+
+    /// The sequence number of the half being filled
+    next: usize,
+
+    /// The number of frames written to the half being filled
+    fill: usize,
+}
+
+impl<M, C, B, F, D> AudioOut<M, C, B, F, D> {
+    /// Create a new instance of an audio output, in a disabled state.
+    pub const fn new() -> Self {
+        Self {
+            i2s: None,
+            sample_rate: 0,
+            next: 0,
+            fill: 0,
+        }
+    }
+}
+
+impl<M, C, B, F, D> io::AudioOut for AudioOut<M, C, B, F, D>
+where
+    M: SerialDma<Dma = Edma<M>>,
+    C: Mclk<M>,
+    B: TxBclk<M>,
+    F: TxFs<M>,
+    D: TxData<M>,
+    I2s<M, (), (), (), ()>: GatedPeripheral<M>,
+    Sim<M>: Peripheral,
+    AudioOut<M, C, B, F, D>: AudioBoard<C, B, F, D>,
+{
+    type Error = AudioError;
+    #[rustfmt::skip]
+    type WriteFuture<'a> where Self: 'a = impl Future<Output = Result<usize, Self::Error>>;
+    #[rustfmt::skip]
+    type FlushFuture<'a> where Self: 'a = impl Future<Output = Result<(), Self::Error>>;
+
+    fn enable(&mut self, sample_rate: usize) -> Result<(), Self::Error> {
+        self.disable()?;
+        let mclk = Self::mclk()?;
+        let bclk = Self::bclk()?;
+        let fs = Self::fs()?;
+        let txd = Self::txd()?;
+
+        let mut i2s = Sim::<M>::get()
+            .ok_or(AudioError::SimInUse)?
+            .enable_peripheral::<I2s<M, (), (), (), ()>>()
+            .ok_or(AudioError::I2sInUse)?;
+        let sample_rate = i2s
+            .set_sample_rate(Self::clock_source(), sample_rate)
+            .ok_or(AudioError::InvalidSampleRate)?;
+        let mut i2s = i2s.enable_tx(mclk, bclk, fs, txd);
+
+        let buffer = Self::buffer();
+        unsafe {
+            buffer.reset();
+        }
+        let transfer = Transfer {
+            source: buffer.frames.get() as usize,
+            source_increment: true,
+            destination: i2s.tx_data_address(),
+            destination_increment: false,
+            size: Size::HalfWord,
+            count: size_of::<[[i16; 2]; 2 * AUDIO_BLOCK]>(),
+            peripheral_request: true,
+            interrupt: true,
+        };
+        M::with_dma(|dma, mux| {
+            mux.set_source(AUDIO_DMA_CHANNEL, Some(i2s::TX_REQUEST));
+            // The buffer is static, and the transfer is stopped
+            // before the output is disabled.
+            unsafe {
+                dma.start_circular(AUDIO_DMA_CHANNEL, &transfer);
+            }
+        })
+        .map_err(|_| AudioError::DmaInUse)?;
+        i2s.start();
+
+        self.i2s = Some(i2s);
+        self.sample_rate = sample_rate;
+        // The first half plays as silence while the second is filled
+        self.next = 1;
+        self.fill = 0;
+        Ok(())
+    }
+
+    fn disable(&mut self) -> Result<(), AudioError> {
+        if let Some(mut i2s) = self.i2s.take() {
+            let _ = M::with_dma(|dma, mux| {
+                dma.stop(AUDIO_DMA_CHANNEL);
+                dma.clear(AUDIO_DMA_CHANNEL);
+                mux.set_source(AUDIO_DMA_CHANNEL, None);
+            });
+            i2s.stop();
+        }
+        Ok(())
+    }
+
+    fn sample_rate(&self) -> Option<usize> {
+        self.i2s.as_ref().map(|_| self.sample_rate)
+    }
+
+    fn block_size(&self) -> usize {
+        AUDIO_BLOCK
+    }
+
+    fn write<'a>(&'a mut self, frames: &'a [[i16; 2]]) -> Self::WriteFuture<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            if self.i2s.is_none() {
+                return Poll::Ready(Err(AudioError::NotEnabled));
+            }
+            if frames.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+
+            let buffer = Self::buffer();
+            if self.next.wrapping_sub(buffer.played()) as isize > 1 {
+                // Both halves are full. The waker is added before
+                // checking again, so that an interrupt between the
+                // two is not missed.
+                Self::wakers().add(ctx.waker().clone());
+                if self.next.wrapping_sub(buffer.played()) as isize > 1 {
+                    return Poll::Pending;
+                }
+            }
+            let played = buffer.played();
+            if self.next.wrapping_sub(played) as isize <= 0 {
+                // The half being filled has already started to play,
+                // so start again after it.
+                self.next = played.wrapping_add(1);
+                self.fill = 0;
+            }
+
+            let count = frames.len().min(AUDIO_BLOCK - self.fill);
+            unsafe {
+                buffer.fill(self.next % 2, self.fill, &frames[..count]);
+            }
+            self.fill += count;
+            if self.fill == AUDIO_BLOCK {
+                self.next = self.next.wrapping_add(1);
+                self.fill = 0;
+            }
+            Poll::Ready(Ok(count))
+        })
+    }
+
+    fn flush<'a>(&'a mut self) -> Self::FlushFuture<'a>
+    where
+        Self: 'a,
+    {
+        // The rest of a partly-filled half is already silent, as it
+        // was cleared when it was last played.
+        if self.fill > 0 {
+            self.next = self.next.wrapping_add(1);
+            self.fill = 0;
+        }
+        poll_fn(move |ctx| {
+            if self.i2s.is_none() {
+                return Poll::Ready(Err(AudioError::NotEnabled));
+            }
+            let buffer = Self::buffer();
+            Self::wakers().add(ctx.waker().clone());
+            if self.next.wrapping_sub(buffer.played()) as isize > 0 {
+                Poll::Pending
+            } else {
+                Poll::Ready(Ok(()))
+            }
+        })
+    }
+}
+
+#[allow(missing_docs)]
+pub trait AudioBoard<C, B, F, D> {
+    fn mclk() -> Result<C, AudioError>;
+    fn bclk() -> Result<B, AudioError>;
+    fn fs() -> Result<F, AudioError>;
+    fn txd() -> Result<D, AudioError>;
+    fn clock_source() -> usize;
+    fn buffer() -> &'static AudioBuffer;
+    fn wakers() -> &'static WakerSet;
 }
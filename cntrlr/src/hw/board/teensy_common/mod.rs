@@ -3,9 +3,35 @@
 
 //! Common board functionality for the Teensy 3.x series
 
+pub mod analog;
+pub mod clock;
+pub mod counter;
 pub mod digital;
+// The Teensy LC has no quadrature decoder
+#[cfg(any(
+    doc,
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36"
+))]
+pub mod input;
 pub mod io;
+pub mod pwm;
+pub mod soft_pwm;
 pub mod time;
+pub mod touch;
+pub mod usb;
+// The Teensy LC has no VBAT domain
+#[cfg(any(
+    doc,
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36"
+))]
+pub mod vbat;
+pub mod watchdog;
 
 /// Error type for Teensy 3.x clock setting functions.
 #[derive(Debug)]
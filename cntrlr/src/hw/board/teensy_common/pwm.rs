@@ -0,0 +1,179 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Hardware PWM shared between the various Teensy 3.x boards
+//!
+//! Each PWM pin is driven by a channel of an FTM or, on the Teensy LC
+//! and 3.6, a TPM. The timers are enabled the first time one of
+//! their channels is used, and stay enabled for the rest of the
+//! program.
+
+use crate::{
+    hw::mcu::kinetis::peripheral::{ftm::Ftm, tpm::Tpm},
+    pwm::{self, PwmChannel, PwmError},
+    sync::Mutex,
+};
+use core::marker::PhantomData;
+
+/// A timer which drives PWM channels
+pub trait PwmTimer {
+    /// Start the counter with a prescale and period
+    fn set_period(&mut self, prescale: u32, period: u32);
+
+    /// The prescale and period of the counter, if it is running
+    fn period(&self) -> Option<(u32, u32)>;
+
+    /// Enable or disable the PWM output of a channel
+    fn enable_pwm(&mut self, channel: usize, enabled: bool);
+
+    /// Set the number of counts in each period for which a channel is high
+    fn set_duty(&mut self, channel: usize, duty: u32);
+}
+
+impl<M, const N: usize> PwmTimer for Ftm<M, N> {
+    fn set_period(&mut self, prescale: u32, period: u32) {
+        Ftm::set_period(self, prescale, period);
+    }
+
+    fn period(&self) -> Option<(u32, u32)> {
+        Ftm::period(self)
+    }
+
+    fn enable_pwm(&mut self, channel: usize, enabled: bool) {
+        Ftm::enable_pwm(self, channel, enabled);
+    }
+
+    fn set_duty(&mut self, channel: usize, duty: u32) {
+        Ftm::set_duty(self, channel, duty);
+    }
+}
+
+impl<M, const N: usize> PwmTimer for Tpm<M, N> {
+    fn set_period(&mut self, prescale: u32, period: u32) {
+        Tpm::set_period(self, prescale, period);
+    }
+
+    fn period(&self) -> Option<(u32, u32)> {
+        Tpm::period(self)
+    }
+
+    fn enable_pwm(&mut self, channel: usize, enabled: bool) {
+        Tpm::enable_pwm(self, channel, enabled);
+    }
+
+    fn set_duty(&mut self, channel: usize, duty: u32) {
+        Tpm::set_duty(self, channel, duty);
+    }
+}
+
+/// Run an operation on one of a board's timers
+///
+/// The timer is enabled with `enable` if it is not already. Returns
+/// `None` if it cannot be enabled.
+pub fn with_timer<T: PwmTimer, R>(
+    timer: &Mutex<Option<T>>,
+    enable: impl FnOnce() -> Option<T>,
+    f: impl FnOnce(&mut dyn PwmTimer) -> R,
+) -> Option<R> {
+    let mut timer = timer.lock();
+    if timer.is_none() {
+        *timer = enable();
+    }
+    timer.as_mut().map(|timer| f(timer))
+}
+
+/// The hardware PWM output of a pin
+pub struct PwmPin<B> {
+    channel: PwmChannel,
+    period: u32,
+    _board: PhantomData<B>,
+}
+
+impl<B: PwmBoard> PwmPin<B> {
+    /// Get the PWM output of a pin
+    ///
+    /// Returns [`PwmError::InvalidPin`] if the pin has no PWM output.
+    pub fn new(pin: usize) -> Result<Self, PwmError> {
+        Ok(Self {
+            channel: pwm::pwm_channel(pin).ok_or(PwmError::InvalidPin)?,
+            period: 0,
+            _board: PhantomData,
+        })
+    }
+
+    fn stop(&mut self) {
+        let channel = self.channel.channel;
+        B::with_timer(self.channel.timer, |timer| {
+            timer.set_duty(channel, 0);
+            timer.enable_pwm(channel, false);
+        });
+        pwm::release(self.channel.pin);
+        self.period = 0;
+    }
+}
+
+impl<B: PwmBoard> pwm::Pwm for PwmPin<B> {
+    type Error = PwmError;
+
+    fn enable(&mut self, frequency: usize) -> Result<(), PwmError> {
+        if frequency == 0 {
+            return Err(PwmError::InvalidFrequency);
+        }
+
+        // Use the smallest prescaler which fits the period in 16
+        // bits, for the finest duty cycle.
+        let clock = B::timer_clock(self.channel.timer);
+        let (prescale, period) = (0..8)
+            .map(|prescale| (prescale, (clock >> prescale) / frequency))
+            .find(|&(_, period)| period < 0x1_0000)
+            .filter(|&(_, period)| period >= 2)
+            .ok_or(PwmError::InvalidFrequency)?;
+        let period = period as u32;
+
+        pwm::claim(self.channel.pin, frequency)?;
+        let channel = self.channel.channel;
+        let enabled = B::with_timer(self.channel.timer, |timer| {
+            if timer.period() != Some((prescale, period)) {
+                timer.set_period(prescale, period);
+            }
+            timer.enable_pwm(channel, true);
+        });
+        if enabled.is_none() {
+            pwm::release(self.channel.pin);
+            return Err(PwmError::TimerUnavailable);
+        }
+        if !B::connect_pin(self.channel.pin) {
+            self.stop();
+            return Err(PwmError::PinInUse);
+        }
+        self.period = period;
+        Ok(())
+    }
+
+    fn disable(&mut self) -> Result<(), PwmError> {
+        self.stop();
+        Ok(())
+    }
+
+    fn max_duty(&self) -> usize {
+        self.period as usize
+    }
+
+    fn set_duty(&mut self, duty: usize) -> Result<(), PwmError> {
+        if self.period == 0 {
+            return Err(PwmError::NotEnabled);
+        }
+        let channel = self.channel.channel;
+        let duty = duty.min(self.period as usize) as u32;
+        B::with_timer(self.channel.timer, |timer| timer.set_duty(channel, duty))
+            .ok_or(PwmError::TimerUnavailable)
+    }
+}
+
+/// The board-specific parts of hardware PWM
+#[allow(missing_docs)]
+pub trait PwmBoard {
+    fn with_timer<R>(timer: usize, f: impl FnOnce(&mut dyn PwmTimer) -> R) -> Option<R>;
+    fn timer_clock(timer: usize) -> usize;
+    fn connect_pin(pin: usize) -> bool;
+}
@@ -0,0 +1,324 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Software PWM shared between the various Teensy 3.x boards
+//!
+//! Up to 16 pins are driven from channel 0 of the PIT. At the start
+//! of each period, every pin with a non-zero duty cycle is set high
+//! through its port's set register. The timer then interrupts at
+//! each falling edge, to clear the pins whose pulse ends there. The
+//! schedule of edges is rebuilt whenever a pin or setting changes,
+//! and swapped in at the end of a period so that no pulse is cut
+//! short.
+//!
+//! The interrupt needs a few microseconds to run, so edges are kept
+//! at least 4µs apart, and 4µs from either end of the period. An
+//! edge closer than that to the one before it is merged with it, and
+//! falls early. Every edge is also late by the latency of the
+//! interrupt, which grows while other interrupts run or interrupts
+//! are disabled.
+
+use crate::{
+    digital::{self, PinMode},
+    hw::mcu::kinetis::peripheral::pit::Pit,
+    pwm::PwmError,
+    sync::without_interrupts,
+};
+use core::{cell::UnsafeCell, mem::forget, ptr::write_volatile};
+
+/// The most pins which can be driven at once
+pub const MAX_PINS: usize = 16;
+
+/// The highest supported frequency, in Hz
+pub const MAX_FREQUENCY: usize = 1000;
+
+/// Set the duty cycle of a pin
+///
+/// The pin is set as a digital output the first time it is
+/// written. The PIT is enabled with `pit` on the first write to any
+/// pin, and stays enabled for the rest of the program.
+pub(crate) fn write<M>(
+    pin: usize,
+    duty: usize,
+    bus_freq: usize,
+    pit: impl FnOnce() -> Option<Pit<M>>,
+) -> Result<(), PwmError> {
+    let (port, mcu_pin) = digital::mcu_pin(pin).ok_or(PwmError::InvalidPin)?;
+    let id = port * 32 + mcu_pin;
+    without_interrupts(|| {
+        // Safety: The thread-side state is only accessed with
+        // interrupts disabled, so the PIT interrupt cannot run.
+        let state = unsafe { &mut *STATE.0.get() };
+        let slot = match state.pins.iter().position(|&pin| pin == id) {
+            Some(slot) => slot,
+            None => {
+                let slot = state
+                    .pins
+                    .iter()
+                    .position(|&pin| pin == NO_PIN)
+                    .ok_or(PwmError::TooManyPins)?;
+                if !state.started {
+                    start(
+                        pit().ok_or(PwmError::TimerUnavailable)?,
+                        bus_freq / state.frequency,
+                    );
+                    state.started = true;
+                }
+                digital::pin_mode(pin, PinMode::Output);
+                state.pins[slot] = id;
+                state.owned[port] |= 1 << mcu_pin;
+                slot
+            }
+        };
+        state.duties[slot] = duty;
+        state.rebuild(bus_freq);
+        Ok(())
+    })
+}
+
+/// Stop driving a pin
+///
+/// The pin is left as a digital output, driven low. Does nothing if
+/// the pin is not being driven.
+pub(crate) fn release(pin: usize, bus_freq: usize) {
+    let (port, mcu_pin) = match digital::mcu_pin(pin) {
+        Some(pin) => pin,
+        None => return,
+    };
+    let id = port * 32 + mcu_pin;
+    without_interrupts(|| {
+        let state = unsafe { &mut *STATE.0.get() };
+        if let Some(slot) = state.pins.iter().position(|&pin| pin == id) {
+            state.pins[slot] = NO_PIN;
+            state.owned[port] &= !(1 << mcu_pin);
+            state.rebuild(bus_freq);
+            unsafe { write_volatile(gpio_pcor(port), 1 << mcu_pin) };
+        }
+    });
+}
+
+/// Set the frequency of every pin, in Hz
+pub(crate) fn set_frequency(frequency: usize, bus_freq: usize) -> Result<(), PwmError> {
+    if frequency == 0 || frequency > MAX_FREQUENCY {
+        return Err(PwmError::InvalidFrequency);
+    }
+    without_interrupts(|| {
+        let state = unsafe { &mut *STATE.0.get() };
+        state.frequency = frequency;
+        state.rebuild(bus_freq);
+    });
+    Ok(())
+}
+
+/// Set the resolution of duty cycles, in bits
+pub(crate) fn set_resolution(bits: u32, bus_freq: usize) -> Result<(), PwmError> {
+    if !(1..=16).contains(&bits) {
+        return Err(PwmError::InvalidResolution);
+    }
+    without_interrupts(|| {
+        let state = unsafe { &mut *STATE.0.get() };
+        state.resolution = bits;
+        state.rebuild(bus_freq);
+    });
+    Ok(())
+}
+
+/// Start the PIT, with an empty schedule
+///
+/// The first interrupt swaps in the schedule built for the pin being
+/// written.
+fn start<M>(mut pit: Pit<M>, period: usize) {
+    pit.enable(true);
+    pit.enable_channel(0, false);
+    pit.set_load_value(0, (period - 1) as u32);
+    pit.clear_interrupt_flag(0);
+    pit.enable_interrupt(0, true);
+    pit.enable_channel(0, true);
+
+    // The interrupt writes the timer directly, so the handle is
+    // leaked to keep the timer's clock gate enabled.
+    forget(pit);
+}
+
+/// One period of output
+#[derive(Clone, Copy)]
+struct Schedule {
+    /// Pins set at the start of the period, for each port
+    high: [u32; PORTS],
+
+    /// Pins cleared at the start of the period, for each port
+    low: [u32; PORTS],
+
+    /// The number of falling edges
+    edges: usize,
+
+    /// Pins cleared at each falling edge, for each port
+    clears: [[u32; PORTS]; MAX_PINS],
+
+    /// Bus clocks from the start of the period to the first edge,
+    /// then from each edge to the next, and from the last edge to
+    /// the end of the period
+    intervals: [u32; MAX_PINS + 1],
+}
+
+impl Schedule {
+    const fn new() -> Self {
+        Self {
+            high: [0; PORTS],
+            low: [0; PORTS],
+            edges: 0,
+            clears: [[0; PORTS]; MAX_PINS],
+            intervals: [0; MAX_PINS + 1],
+        }
+    }
+}
+
+struct State {
+    pins: [usize; MAX_PINS],
+    duties: [usize; MAX_PINS],
+    owned: [u32; PORTS],
+    frequency: usize,
+    resolution: u32,
+    started: bool,
+    schedules: [Schedule; 2],
+    active: usize,
+    pending: bool,
+    event: usize,
+}
+
+impl State {
+    /// Build the schedule for the current pins and settings
+    ///
+    /// The schedule is swapped in by the interrupt at the end of the
+    /// current period. Replaces any schedule which has not been
+    /// swapped in yet.
+    fn rebuild(&mut self, bus_freq: usize) {
+        let period = bus_freq / self.frequency;
+        let min_gap = bus_freq / 250_000;
+        let full = 1 << self.resolution;
+
+        // Falling edges as (time, port, mask), sorted by time
+        let mut edges = [(0, 0, 0); MAX_PINS];
+        let mut count = 0;
+
+        let mut schedule = Schedule::new();
+        for (&id, &duty) in self.pins.iter().zip(self.duties.iter()) {
+            if id == NO_PIN {
+                continue;
+            }
+            let port = id / 32;
+            let mask = 1 << (id % 32);
+            if duty == 0 {
+                schedule.low[port] |= mask;
+                continue;
+            }
+            schedule.high[port] |= mask;
+            if duty >= full {
+                continue;
+            }
+
+            let time = (duty as u64 * period as u64 / full as u64) as usize;
+            let time = time.max(min_gap).min(period - min_gap);
+            let mut index = count;
+            while index > 0 && edges[index - 1].0 > time {
+                edges[index] = edges[index - 1];
+                index -= 1;
+            }
+            edges[index] = (time, port, mask);
+            count += 1;
+        }
+
+        let mut last = 0;
+        for &(time, port, mask) in &edges[..count] {
+            if schedule.edges == 0 || time - last >= min_gap {
+                schedule.intervals[schedule.edges] = (time - last) as u32;
+                schedule.edges += 1;
+                last = time;
+            }
+            schedule.clears[schedule.edges - 1][port] |= mask;
+        }
+        schedule.intervals[schedule.edges] = (period - last) as u32;
+
+        self.schedules[1 - self.active] = schedule;
+        self.pending = true;
+    }
+}
+
+struct SharedState(UnsafeCell<State>);
+
+// Safety: The state is accessed from the PIT interrupt, and
+// elsewhere only with interrupts disabled.
+unsafe impl Sync for SharedState {}
+
+static STATE: SharedState = SharedState(UnsafeCell::new(State {
+    pins: [NO_PIN; MAX_PINS],
+    duties: [0; MAX_PINS],
+    owned: [0; PORTS],
+    frequency: DEFAULT_FREQUENCY,
+    resolution: DEFAULT_RESOLUTION,
+    started: false,
+    schedules: [Schedule::new(), Schedule::new()],
+    active: 0,
+    pending: false,
+    event: 0,
+}));
+
+const NO_PIN: usize = usize::MAX;
+const PORTS: usize = 5;
+const DEFAULT_FREQUENCY: usize = 500;
+const DEFAULT_RESOLUTION: u32 = 8;
+
+const PIT_LDVAL0: *mut u32 = 0x4003_7100 as _;
+const PIT_TFLG0: *mut u32 = 0x4003_710C as _;
+
+fn gpio_psor(port: usize) -> *mut u32 {
+    (0x400F_F004 + port * 0x40) as _
+}
+
+fn gpio_pcor(port: usize) -> *mut u32 {
+    (0x400F_F008 + port * 0x40) as _
+}
+
+/// Interrupt function for PIT channel 0
+///
+/// Each interrupt runs one event of the schedule, and loads the
+/// interval after the next event. The interval to the next event was
+/// loaded by the interrupt before, so the timer never waits for this
+/// one to finish.
+pub extern "C" fn pit_intr() {
+    unsafe {
+        write_volatile(PIT_TFLG0, 1);
+
+        let state = &mut *STATE.0.get();
+        let schedule = &state.schedules[state.active];
+        let event = state.event;
+
+        // Pins which were released since the schedule was built are
+        // masked out, so they are never driven again.
+        for (port, &owned) in state.owned.iter().enumerate() {
+            if event == 0 {
+                if schedule.low[port] & owned != 0 {
+                    write_volatile(gpio_pcor(port), schedule.low[port] & owned);
+                }
+                if schedule.high[port] & owned != 0 {
+                    write_volatile(gpio_psor(port), schedule.high[port] & owned);
+                }
+            } else if schedule.clears[event - 1][port] & owned != 0 {
+                write_volatile(gpio_pcor(port), schedule.clears[event - 1][port] & owned);
+            }
+        }
+
+        let next = if event == schedule.edges {
+            if state.pending {
+                state.active = 1 - state.active;
+                state.pending = false;
+            }
+            state.event = 0;
+            state.schedules[state.active].intervals[0]
+        } else {
+            state.event = event + 1;
+            schedule.intervals[event + 1]
+        };
+        write_volatile(PIT_LDVAL0, next - 1);
+    }
+}
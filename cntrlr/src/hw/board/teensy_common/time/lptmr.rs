@@ -0,0 +1,207 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! The backup tick, kept on the LPTMR
+//!
+//! The LPTMR counts the 1kHz LPO, which keeps running in the STOP
+//! modes that halt the SysTick.
+
+#[cfg(not(mcu = "mkl26z64"))]
+use super::cycle_counter;
+#[cfg(mcu = "mkl26z64")]
+use super::delay_cycles;
+use super::{advance, wake_timers, NO_WAKE, SYSTICK_WAKERS};
+use crate::{
+    hw::mcu::kinetis::peripheral::lptmr::{ClockSource, Lptmr},
+    sync::{without_interrupts, Mutex, Value},
+};
+#[cfg(not(mcu = "mkl26z64"))]
+use core::ptr::read_volatile;
+use core::sync::atomic::Ordering;
+
+#[cfg(mcu = "mk20dx128")]
+type Mcu = crate::hw::mcu::kinetis::Mk20Dx128;
+#[cfg(mcu = "mk20dx256")]
+type Mcu = crate::hw::mcu::kinetis::Mk20Dx256;
+#[cfg(mcu = "mk64fx512")]
+type Mcu = crate::hw::mcu::kinetis::Mk64Fx512;
+#[cfg(mcu = "mkl26z64")]
+type Mcu = crate::hw::mcu::kinetis::Mkl26Z64;
+#[cfg(not(any(
+    mcu = "mk20dx128",
+    mcu = "mk20dx256",
+    mcu = "mk64fx512",
+    mcu = "mkl26z64"
+)))]
+type Mcu = crate::hw::mcu::kinetis::Mk66Fx1M0;
+
+/// Use the LPTMR as a backup tick source
+///
+/// The LPTMR is run from the 1kHz LPO, which keeps counting in the
+/// STOP modes that halt the SysTick. Each SysTick interrupt compares
+/// the two, and adds any time the SysTick missed to
+/// [`millis`](super::millis). The LPTMR compare interrupt is also
+/// used to wake tasks at the end of a
+/// [`sleep_millis`](super::sleep_millis), in case the SysTick is
+/// halted.
+///
+/// The LPTMR is kept for the rest of the program. Its 16-bit counter
+/// means sleeps longer than about 65 seconds cannot be recovered.
+pub(crate) fn enable_backup_tick(mut lptmr: Lptmr<Mcu>) {
+    lptmr.enable(false);
+    lptmr.set_clock_source(ClockSource::Lpo);
+    lptmr.set_prescaler(None);
+    lptmr.set_pulse_input(None);
+    lptmr.set_free_running(true);
+
+    // A compare value of zero sets the compare flag on the first
+    // tick, after which the compare value can be changed by the first
+    // sleep.
+    lptmr.set_compare(0);
+    lptmr.enable(true);
+    without_interrupts(|| {
+        BACKUP_COUNT.store(lptmr.counter() as usize, Ordering::Relaxed);
+        *LPTMR.lock() = Some(lptmr);
+    });
+}
+
+/// Run `f` with the LPTMR, if the backup tick is running
+///
+/// The LPTMR is used from its own interrupt and the SysTick's, so it
+/// is only locked with interrupts disabled.
+fn with_lptmr<R>(f: impl FnOnce(&mut Lptmr<Mcu>) -> R) -> Option<R> {
+    without_interrupts(|| LPTMR.lock().as_mut().map(f))
+}
+
+/// The LPTMR's current count
+fn backup_count() -> Option<usize> {
+    with_lptmr(|lptmr| lptmr.counter() as usize)
+}
+
+/// The milliseconds which passed while the SysTick counted
+/// `regular`
+///
+/// If the SysTick was halted, the time counted by the LPTMR is
+/// returned instead.
+pub(super) fn recover(regular: usize) -> usize {
+    let last = BACKUP_COUNT.load(Ordering::Relaxed);
+    let current = match backup_count() {
+        Some(current) if last != NO_BACKUP => current,
+        _ => return regular,
+    };
+    BACKUP_COUNT.store(current, Ordering::Relaxed);
+
+    // The LPO is only accurate to a few percent, so a regular tick
+    // will see up to 2 more backup ticks than it counted itself.
+    // Anything more than that means the SysTick was halted while the
+    // device slept.
+    let backup_elapsed = current.wrapping_sub(last) & 0xFFFF;
+    if backup_elapsed > regular + 2 {
+        backup_elapsed
+    } else {
+        regular
+    }
+}
+
+/// Schedule the LPTMR interrupt for `duration` milliseconds from now
+///
+/// If an earlier interrupt is already scheduled, this does nothing.
+/// Durations longer than the LPTMR can count wake the task early,
+/// and it is rescheduled when it is polled.
+pub(super) fn schedule_wake(duration: usize) {
+    let duration = duration.min(0xFFFF);
+    with_lptmr(|lptmr| {
+        let mut now = lptmr.counter() as usize;
+        let scheduled = WAKE_AT.load(Ordering::Relaxed);
+        if scheduled != NO_WAKE && scheduled.wrapping_sub(now) & 0xFFFF <= duration {
+            return;
+        }
+
+        // The compare value can only be changed while the compare
+        // flag is set, or while the timer is disabled. Disabling the
+        // timer resets the counter, so the backup count is rebased to
+        // keep the time elapsed since the last tick.
+        if !lptmr.compare_flag() {
+            lptmr.enable(false);
+            let last = BACKUP_COUNT.load(Ordering::Relaxed);
+            BACKUP_COUNT.store(last.wrapping_sub(now) & 0xFFFF, Ordering::Relaxed);
+            now = 0;
+        }
+
+        // The compare flag is set as the counter increments past the
+        // compare value. It is cleared before the interrupt is
+        // enabled, so that a stale flag does not fire it early.
+        let wake_at = now.wrapping_add(duration) & 0xFFFF;
+        lptmr.set_compare(wake_at.wrapping_sub(1) as u16);
+        lptmr.clear_compare_flag();
+        lptmr.enable(true);
+        lptmr.enable_interrupt(true);
+        WAKE_AT.store(wake_at, Ordering::Relaxed);
+    });
+}
+
+/// Interrupt function for the LPTMR
+///
+/// This fires at the end of the earliest sleep, which only matters
+/// when the SysTick was halted in a STOP mode.
+pub extern "C" fn lptmr_intr() {
+    // Leave the compare flag set, so that the next sleep can change
+    // the compare value without disabling the timer.
+    with_lptmr(|lptmr| lptmr.enable_interrupt(false));
+    WAKE_AT.store(NO_WAKE, Ordering::Relaxed);
+    advance(0);
+    SYSTICK_WAKERS.wake();
+    wake_timers();
+}
+
+/// Measure the core clock against the 1kHz LPO
+///
+/// The LPO is counted by the backup tick, so this returns `None` if
+/// the backup tick is not running or the core clock has not been
+/// set. Otherwise, returns the configured and measured core clock.
+///
+/// The LPO is only accurate to a few tens of percent, so this can
+/// catch a core clock which is running from the wrong source, but
+/// not one which is slightly off. The Teensy LC has no cycle counter,
+/// and times a delay loop instead, which reads low if it is
+/// interrupted.
+pub(crate) fn measure_core_clock_at(clock: usize) -> Option<(usize, usize)> {
+    const LPO_TICKS: usize = 100;
+
+    if clock == 0 {
+        return None;
+    }
+
+    // Start counting on an LPO edge
+    let start = backup_count()?;
+    let mut edge = start;
+    while edge == start {
+        edge = backup_count()?;
+    }
+
+    #[cfg(not(mcu = "mkl26z64"))]
+    let (cycles, ticks) = unsafe {
+        let counter = cycle_counter();
+        let start = read_volatile(counter);
+        while backup_count()?.wrapping_sub(edge) & 0xFFFF < LPO_TICKS {}
+        (read_volatile(counter).wrapping_sub(start) as u64, LPO_TICKS)
+    };
+
+    #[cfg(mcu = "mkl26z64")]
+    let (cycles, ticks) = {
+        let cycles = clock / 1000 * LPO_TICKS;
+        delay_cycles(cycles);
+        (cycles as u64, backup_count()?.wrapping_sub(edge) & 0xFFFF)
+    };
+
+    if ticks == 0 {
+        return None;
+    }
+    Some((clock, (cycles * 1000 / ticks as u64) as usize))
+}
+
+static LPTMR: Mutex<Option<Lptmr<Mcu>>> = Mutex::new(None);
+static BACKUP_COUNT: Value = Value::new(NO_BACKUP);
+static WAKE_AT: Value = Value::new(NO_WAKE);
+
+const NO_BACKUP: usize = usize::MAX;
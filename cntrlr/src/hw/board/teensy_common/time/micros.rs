@@ -0,0 +1,111 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! The microsecond count, kept on the last channel of the PIT
+
+use super::millis;
+use crate::{
+    hw::mcu::kinetis::peripheral::pit::Pit,
+    sync::{without_interrupts, Value},
+};
+use core::{ptr::read_volatile, sync::atomic::Ordering};
+
+const PIT_CVAL: usize = 0x4003_7104;
+
+/// Retrieve the number of microseconds the device has been running
+///
+/// This is a wrapping counter. On the Teensy boards, it is 32-bits,
+/// and wraps after about 71 minutes.
+///
+/// The count is taken from the last channel of the PIT, which
+/// free-runs from the bus clock. If the PIT was in use at startup,
+/// this falls back to [`millis`], and only counts whole milliseconds.
+///
+/// # Note
+/// The PIT halts in STOP modes, and unlike [`millis`], the time spent
+/// in those modes is not recovered.
+pub fn micros() -> usize {
+    without_interrupts(|| {
+        if MICROS_CHANNEL.load(Ordering::Relaxed) == NO_CHANNEL {
+            millis().wrapping_mul(1000)
+        } else {
+            unsafe { count_micros() }
+        }
+    })
+}
+
+/// Start counting microseconds on the last channel of the PIT
+///
+/// The channel free-runs through its whole 32-bit range, and is
+/// folded into [`micros`] whenever it is read and on every SysTick
+/// interrupt, which is often enough that it never wraps unseen. The
+/// other channels are left for the rest of the program.
+pub(crate) fn enable_micros<M>(pit: &mut Pit<M>) {
+    let channel = pit.channels() - 1;
+    pit.enable(true);
+    pit.enable_channel(channel, false);
+    pit.set_load_value(channel, u32::MAX);
+    pit.enable_channel(channel, true);
+    without_interrupts(|| {
+        MICROS_COUNT.store(pit.current_value(channel) as usize, Ordering::Relaxed);
+        MICROS_CHANNEL.store(channel, Ordering::Relaxed);
+    });
+}
+
+/// Prepare the microsecond count for a new bus clock
+///
+/// Counts since the last read are added at the old clock before the
+/// new one is used.
+pub(crate) fn set_micros_clock(clock: usize) {
+    without_interrupts(|| {
+        unsafe { tick() };
+        BUS_CLOCK.store(clock, Ordering::Relaxed);
+    });
+}
+
+/// Add the PIT counts since the last call to the microsecond count,
+/// if it is being kept
+///
+/// # Safety
+/// This must be called with interrupts disabled.
+pub(super) unsafe fn tick() {
+    if MICROS_CHANNEL.load(Ordering::Relaxed) != NO_CHANNEL {
+        count_micros();
+    }
+}
+
+/// Add the PIT counts since the last call to the microsecond count
+///
+/// Counts which do not make up a whole microsecond are kept for the
+/// next call. Returns the new count.
+///
+/// # Safety
+/// This must be called with interrupts disabled, after the PIT
+/// channel has been started.
+unsafe fn count_micros() -> usize {
+    let channel = MICROS_CHANNEL.load(Ordering::Relaxed);
+    let current = read_volatile((PIT_CVAL + channel * 0x10) as *const u32);
+
+    // The channel counts down through its whole range, so the
+    // wrapping difference is right even across a reload.
+    let last = MICROS_COUNT.load(Ordering::Relaxed) as u32;
+    MICROS_COUNT.store(current as usize, Ordering::Relaxed);
+    let ticks = last.wrapping_sub(current) as u64;
+
+    let clock = BUS_CLOCK.load(Ordering::Relaxed).max(1) as u64;
+    let scaled = MICROS_FRACT.load(Ordering::Relaxed) as u64 + ticks * 1_000_000;
+    MICROS_FRACT.store((scaled % clock) as usize, Ordering::Relaxed);
+    let micros = MICROS
+        .load(Ordering::Relaxed)
+        .wrapping_add((scaled / clock) as usize);
+    MICROS.store(micros, Ordering::Relaxed);
+    micros
+}
+
+static MICROS: Value = Value::new(0);
+static MICROS_FRACT: Value = Value::new(0);
+static MICROS_COUNT: Value = Value::new(0);
+static MICROS_CHANNEL: Value = Value::new(NO_CHANNEL);
+static BUS_CLOCK: Value = Value::new(0);
+
+const NO_CHANNEL: usize = usize::MAX;
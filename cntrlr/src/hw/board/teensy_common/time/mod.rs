@@ -0,0 +1,226 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Time functionality shared between the various Teensy 3.x boards
+//!
+//! The millisecond count is kept by the SysTick, with the LPTMR as a
+//! backup tick for the STOP modes which halt it. The microsecond
+//! count is kept on the PIT.
+
+mod lptmr;
+mod micros;
+#[cfg(not(mcu = "mkl26z64"))]
+mod rtc;
+mod systick;
+mod tickless;
+
+#[cfg(not(mcu = "mkl26z64"))]
+pub use self::rtc::{rtc_intr, Rtc};
+pub use self::{lptmr::lptmr_intr, micros::micros, systick::systick_intr, tickless::set_tick_mode};
+pub(crate) use self::{
+    lptmr::{enable_backup_tick, measure_core_clock_at},
+    micros::{enable_micros, set_micros_clock},
+    systick::set_tick_clock,
+};
+
+#[cfg(not(mcu = "mkl26z64"))]
+use crate::sync::Flag;
+use crate::{
+    sync::{without_interrupts, Value},
+    task::WakerSet,
+    time::DeadlineError,
+};
+#[cfg(not(mcu = "mkl26z64"))]
+use core::ptr::write_volatile;
+use core::{
+    future::{poll_fn, Future},
+    ptr::read_volatile,
+    sync::atomic::Ordering,
+    task::Poll,
+};
+
+/// Retreive the number of milliseconds the device has been running
+///
+/// This is a wrapping counter. On the Teensy boards, it is 32-bits
+///
+/// # Note
+/// This count may become inaccurate if the sytem clock is modified
+/// after startup.
+///
+/// The SysTick which drives this counter halts in STOP modes. Time
+/// spent in those modes is recovered from the LPTMR on the first tick
+/// after waking, so the count may jump forward at that point.
+pub fn millis() -> usize {
+    without_interrupts(|| {
+        let millis = MILLIS.load(Ordering::Relaxed);
+        millis.wrapping_add(unsafe { systick::uncounted_millis() })
+    })
+}
+
+/// Sleep this task for some number of milliseconds
+///
+/// This task will be slept, and awoken once the number of
+/// milliseconds has pased.
+///
+/// The LPTMR compare interrupt is scheduled for the end of the
+/// sleep, so the task is still woken on time if the device enters a
+/// STOP mode in the meantime.
+pub fn sleep_millis(mut duration: usize) -> impl Future<Output = ()> {
+    let mut start = millis();
+    poll_fn(move |ctx| {
+        let current = millis();
+        let elapsed = current.wrapping_sub(start);
+        if elapsed >= duration {
+            Poll::Ready(())
+        } else {
+            duration -= elapsed;
+            start = current;
+            SYSTICK_WAKERS.add(ctx.waker().clone());
+            lptmr::schedule_wake(duration);
+            tickless::note_deadline(current.wrapping_add(duration));
+            Poll::Pending
+        }
+    })
+}
+
+/// Make sure the board wakes at a timer's deadline
+///
+/// As with [`sleep_millis`], the LPTMR compare interrupt is also
+/// scheduled, in case the SysTick is halted.
+pub fn note_timer(deadline: usize) {
+    let remaining = deadline.wrapping_sub(millis());
+    if remaining <= usize::MAX / 2 {
+        lptmr::schedule_wake(remaining);
+    }
+    tickless::note_deadline(deadline);
+}
+
+/// Wake the expired timers, and note the deadline of the next
+fn wake_timers() {
+    if let Some(deadline) = crate::time::wake_timers(MILLIS.load(Ordering::Relaxed)) {
+        note_timer(deadline);
+    }
+}
+
+/// Advance the millisecond count
+///
+/// `regular` is the number of milliseconds to add when the SysTick
+/// has been running normally. If it was halted, the time recovered
+/// from the LPTMR is added instead.
+fn advance(regular: usize) {
+    let elapsed = lptmr::recover(regular);
+    let millis = MILLIS.load(Ordering::Relaxed);
+    MILLIS.store(millis.wrapping_add(elapsed), Ordering::Relaxed);
+}
+
+/// Busy-wait for at least some number of core clock cycles
+///
+/// This does not depend on interrupts or the SysTick, so it can be
+/// used from board init code and interrupt handlers.
+///
+/// The Cortex-M4 boards count cycles with the DWT cycle counter,
+/// which is enabled on first use. The Teensy LC has no cycle counter,
+/// and uses a calibrated loop instead. Flash wait states can make the
+/// loop run longer than requested.
+pub fn delay_cycles(cycles: usize) {
+    #[cfg(not(mcu = "mkl26z64"))]
+    unsafe {
+        let counter = cycle_counter();
+        let start = read_volatile(counter);
+        while (read_volatile(counter).wrapping_sub(start) as usize) < cycles {}
+    }
+
+    #[cfg(mcu = "mkl26z64")]
+    unsafe {
+        // Each iteration is a single-cycle SUBS and a two-cycle
+        // taken BNE.
+        let iterations = cycles / 3;
+        if iterations > 0 {
+            asm!("
+                1:
+                subs {0}, #1
+                bne 1b",
+                inout(reg) iterations => _,
+                options(nomem, nostack)
+            );
+        }
+    }
+}
+
+/// Enable the DWT cycle counter, and get its address
+#[cfg(not(mcu = "mkl26z64"))]
+unsafe fn cycle_counter() -> *mut u32 {
+    const DEMCR: *mut u32 = 0xE000_EDFC as *mut _;
+    const DWT_CTRL: *mut u32 = 0xE000_1000 as *mut _;
+    const DWT_CYCCNT: *mut u32 = 0xE000_1004 as *mut _;
+
+    write_volatile(DEMCR, read_volatile(DEMCR) | 1 << 24);
+    write_volatile(DWT_CTRL, read_volatile(DWT_CTRL) | 1);
+    DWT_CYCCNT
+}
+
+/// Busy-wait for at least some number of microseconds at a core clock
+///
+/// A `clock` of zero means the core clock has not been set yet, and
+/// the FLL's reset frequency of about 21MHz is used.
+pub(crate) fn delay_us_at(us: usize, clock: usize) {
+    let clock = if clock == 0 { 21_000_000 } else { clock };
+    delay_cycles(us.saturating_mul(clock / 1_000_000));
+}
+
+/// Arm the watchdog to reset the board after some number of milliseconds
+///
+/// The Teensy 3.x boards use the WDOG, which counts the 1kHz LPO.
+/// Deadlines shorter than 4 milliseconds are lengthened to 4. The
+/// Teensy LC's watchdog is disabled at startup, and cannot be
+/// enabled again until the next reset, so this always returns
+/// [`DeadlineError::Unsupported`] there.
+pub fn arm_strict_deadline(duration: usize) -> Result<(), DeadlineError> {
+    #[cfg(not(mcu = "mkl26z64"))]
+    {
+        use crate::hw::mcu::kinetis::peripheral::wdog::Watchdog;
+
+        if STRICT_DEADLINE.swap(true, Ordering::Acquire) {
+            return Err(DeadlineError::InUse);
+        }
+        // Safety: The flag ensures the watchdog is only used here,
+        // after it was disabled at startup. The timeout register is
+        // as wide as usize, so any duration fits.
+        without_interrupts(|| unsafe { Watchdog::get().enable((duration as u32).max(4)) });
+        Ok(())
+    }
+
+    #[cfg(mcu = "mkl26z64")]
+    {
+        let _ = duration;
+        Err(DeadlineError::Unsupported)
+    }
+}
+
+/// Disarm the watchdog armed by [`arm_strict_deadline`]
+pub fn disarm_strict_deadline() {
+    #[cfg(not(mcu = "mkl26z64"))]
+    {
+        use crate::hw::mcu::kinetis::peripheral::wdog::Watchdog;
+
+        // Safety: As in arm_strict_deadline
+        without_interrupts(|| unsafe { Watchdog::get().disable() });
+        STRICT_DEADLINE.store(false, Ordering::Release);
+    }
+}
+
+/// Whether the last reset was caused by the watchdog
+///
+/// This reads the reset cause from the RCM.
+pub fn reset_by_watchdog() -> bool {
+    const RCM_SRS0: *const u8 = 0x4007_F000 as _;
+    unsafe { read_volatile(RCM_SRS0) & 1 << 5 != 0 }
+}
+
+#[cfg(not(mcu = "mkl26z64"))]
+pub(crate) static STRICT_DEADLINE: Flag = Flag::new(false);
+
+static MILLIS: Value = Value::new(0);
+static SYSTICK_WAKERS: WakerSet = WakerSet::new();
+
+const NO_WAKE: usize = usize::MAX;
@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! The RTC, which keeps counting seconds from the VBAT supply
+
+use crate::{hw::mcu::kinetis::peripheral::rtc, task::WakerSet, time};
+use core::{
+    future::{poll_fn, Future},
+    ptr::write_volatile,
+    task::Poll,
+};
+
+/// The real-time clock of the Teensy 3.x boards
+///
+/// This is the RTC, which counts a 32.768kHz crystal. It is powered
+/// from the VBAT pin, so with a coin cell on that pin, the count
+/// survives while the board is unpowered. The Teensy 3.5 and 3.6
+/// have the crystal fitted, and the Teensy 3.0 and 3.2 need one
+/// soldered to their crystal pads.
+///
+/// A count which was running before startup is kept. The count
+/// reads as zero, and cannot be set, while the RTC's handle is held
+/// elsewhere.
+pub struct Rtc<M>(Option<rtc::Rtc<M>>);
+
+impl<M> Rtc<M> {
+    pub(crate) const fn new() -> Self {
+        Self(None)
+    }
+
+    /// Get the RTC's handle with `get`, if it has not been got yet
+    ///
+    /// The oscillator is started, and a count which was lost with
+    /// the VBAT supply is restarted from zero.
+    pub(crate) fn init(&mut self, get: impl FnOnce() -> Option<rtc::Rtc<M>>) {
+        if self.0.is_some() {
+            return;
+        }
+        self.0 = get().map(|mut rtc| {
+            rtc.enable_interrupt(false);
+            if !rtc.oscillator_enabled() {
+                rtc.enable_oscillator(20);
+            }
+            if rtc.time_invalid() {
+                rtc.set_seconds(0);
+            }
+            rtc.enable(true);
+            rtc
+        });
+    }
+}
+
+impl<M: 'static> time::Rtc for Rtc<M> {
+    type AlarmFuture<'a> = impl Future<Output = ()> + 'a;
+
+    fn seconds(&self) -> u64 {
+        self.0.as_ref().map_or(0, |rtc| rtc.seconds() as u64)
+    }
+
+    fn set_seconds(&mut self, seconds: u64) {
+        if let Some(rtc) = self.0.as_mut() {
+            // The counter is 32 bits
+            rtc.set_seconds(seconds as u32);
+            rtc.enable(true);
+        }
+    }
+
+    fn alarm<'a>(&'a mut self, seconds: u64) -> Self::AlarmFuture<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            if self.seconds() >= seconds {
+                return Poll::Ready(());
+            }
+            RTC_WAKERS.add(ctx.waker().clone());
+            if let Some(rtc) = self.0.as_mut() {
+                // The alarm flag is set as the counter increments
+                // past the alarm.
+                rtc.set_alarm((seconds - 1).min(u32::MAX as u64) as u32);
+                rtc.enable_interrupt(true);
+            }
+            Poll::Pending
+        })
+    }
+}
+
+static RTC_WAKERS: WakerSet = WakerSet::new();
+
+/// Interrupt function for the RTC alarm
+///
+/// The alarm interrupt is disabled until the woken alarm sets it
+/// again.
+pub extern "C" fn rtc_intr() {
+    const RTC_IER: *mut u32 = 0x4003_D01C as _;
+    unsafe { write_volatile(RTC_IER, 0) };
+    RTC_WAKERS.wake();
+}
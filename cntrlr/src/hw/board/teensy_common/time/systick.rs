@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! The SysTick, which drives the millisecond count
+
+use super::{advance, micros, tickless, wake_timers, MILLIS, NO_WAKE, SYSTICK_WAKERS};
+use crate::sync::Value;
+use core::{
+    ptr::{read_volatile, write_volatile},
+    sync::atomic::Ordering,
+};
+
+const SYST_RVR: *mut u32 = 0xE000_E014 as *mut _;
+const SYST_CVR: *mut u32 = 0xE000_E018 as *mut _;
+const ICSR: *mut u32 = 0xE000_ED04 as *mut _;
+
+const ICSR_PENDSTCLR: u32 = 1 << 25;
+const ICSR_PENDSTSET: u32 = 1 << 26;
+
+const MAX_RELOAD: usize = 0xFF_FFFF;
+
+/// Prepare the tick for a new core clock
+///
+/// This is called while the SysTick is disabled for a clock change,
+/// and returns the reload value for the SysTick.
+pub(crate) fn set_tick_clock(clock: usize) -> u32 {
+    TICKS_PER_MS.store(clock / 1000, Ordering::Relaxed);
+    FRACT.store(0, Ordering::Relaxed);
+    let period = tickless::first_period();
+    (period * (clock / 1000) - 1) as u32
+}
+
+/// Whether the core clock has been set, and the SysTick started
+pub(super) fn running() -> bool {
+    TICKS_PER_MS.load(Ordering::Relaxed) != 0
+}
+
+/// The longest SysTick period, in milliseconds
+pub(super) fn max_period() -> usize {
+    match TICKS_PER_MS.load(Ordering::Relaxed) {
+        0 => usize::MAX,
+        per_ms => (MAX_RELOAD + 1) / per_ms,
+    }
+}
+
+/// The number of whole milliseconds counted by the SysTick since the
+/// last interrupt
+///
+/// # Safety
+/// This must be called with interrupts disabled.
+pub(super) unsafe fn uncounted_millis() -> usize {
+    match TICKS_PER_MS.load(Ordering::Relaxed) {
+        0 => 0,
+        per_ms => (FRACT.load(Ordering::Relaxed) + uncounted_ticks()) / per_ms,
+    }
+}
+
+/// The number of SysTick counts since the last interrupt
+///
+/// # Safety
+/// This must be called with interrupts disabled.
+unsafe fn uncounted_ticks() -> usize {
+    let reload = read_volatile(SYST_RVR) as usize;
+    let current = read_volatile(SYST_CVR) as usize;
+
+    // If the counter has wrapped without the interrupt running yet,
+    // a whole period has been counted in addition to the current
+    // count.
+    if read_volatile(ICSR) & ICSR_PENDSTSET != 0 {
+        let current = read_volatile(SYST_CVR) as usize;
+        reload + 1 + reload.saturating_sub(current)
+    } else {
+        reload.saturating_sub(current)
+    }
+}
+
+/// Add a number of SysTick counts to the millisecond count
+///
+/// Counts which do not make up a whole millisecond are kept for the
+/// next call.
+fn count_ticks(ticks: usize) {
+    let per_ms = TICKS_PER_MS.load(Ordering::Relaxed).max(1);
+    let ticks = FRACT.load(Ordering::Relaxed) + ticks;
+    FRACT.store(ticks % per_ms, Ordering::Relaxed);
+    advance(ticks / per_ms);
+}
+
+/// Restart the SysTick with a period of `millis` milliseconds
+///
+/// # Safety
+/// This must be called with interrupts disabled, after the core
+/// clock has been set.
+pub(super) unsafe fn restart_tick(millis: usize) {
+    count_ticks(uncounted_ticks());
+
+    // The partial millisecond already counted is taken off the first
+    // period, so that the tick lands on a millisecond boundary.
+    let per_ms = TICKS_PER_MS.load(Ordering::Relaxed);
+    let ticks = (millis * per_ms).saturating_sub(FRACT.load(Ordering::Relaxed));
+    let reload = ticks.max(1).min(MAX_RELOAD + 1) - 1;
+    write_volatile(SYST_RVR, reload as u32);
+    write_volatile(SYST_CVR, 0);
+    write_volatile(ICSR, ICSR_PENDSTCLR);
+    tickless::TICK_END.store(
+        MILLIS.load(Ordering::Relaxed).wrapping_add(millis),
+        Ordering::Relaxed,
+    );
+}
+
+/// Interrupt function for the ARM systick
+pub extern "C" fn systick_intr() {
+    unsafe {
+        count_ticks(read_volatile(SYST_RVR) as usize + 1);
+        micros::tick();
+
+        // Every sleeping task is woken, and notes its deadline again
+        // when it is next polled. Timers stay queued until they
+        // expire, so the earliest is noted here instead.
+        tickless::NEXT_DEADLINE.store(NO_WAKE, Ordering::Relaxed);
+        wake_timers();
+        tickless::next_tick();
+    }
+    SYSTICK_WAKERS.wake();
+}
+
+static FRACT: Value = Value::new(0);
+static TICKS_PER_MS: Value = Value::new(0);
@@ -0,0 +1,128 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Scheduling of the SysTick interrupt
+//!
+//! In periodic mode, the SysTick fires every few milliseconds. In
+//! tickless mode, it is restarted to fire at the earliest deadline.
+
+use super::{systick, MILLIS, NO_WAKE};
+use crate::{
+    sync::{without_interrupts, Flag, Value},
+    time::{TickMode, TickModeError},
+};
+use core::sync::atomic::Ordering;
+
+/// Set how the SysTick interrupt is scheduled
+///
+/// The SysTick is a 24-bit counter of the core clock, so the longest
+/// period is about 174ms at 96MHz, and shorter at faster clocks.
+pub fn set_tick_mode(mode: TickMode) -> Result<(), TickModeError> {
+    without_interrupts(|| {
+        match mode {
+            TickMode::Periodic(period) if period == 0 || period > systick::max_period() => {
+                return Err(TickModeError::InvalidPeriod);
+            }
+            TickMode::Periodic(period) => {
+                PERIOD.store(period, Ordering::Relaxed);
+                TICKLESS.store(false, Ordering::Relaxed);
+            }
+            TickMode::Tickless => TICKLESS.store(true, Ordering::Relaxed),
+        }
+
+        if systick::running() {
+            unsafe { schedule_tick() };
+        }
+        Ok(())
+    })
+}
+
+/// The period of the first tick at a new core clock
+///
+/// A periodic tick is shortened if it no longer fits in the SysTick.
+pub(super) fn first_period() -> usize {
+    let period = if TICKLESS.load(Ordering::Relaxed) {
+        1
+    } else {
+        let period = PERIOD.load(Ordering::Relaxed).min(systick::max_period());
+        PERIOD.store(period, Ordering::Relaxed);
+        period
+    };
+    TICK_END.store(
+        MILLIS.load(Ordering::Relaxed).wrapping_add(period),
+        Ordering::Relaxed,
+    );
+    period
+}
+
+/// Schedule the tick after the one which just fired
+///
+/// # Safety
+/// This must be called from the SysTick interrupt.
+pub(super) unsafe fn next_tick() {
+    if TICKLESS.load(Ordering::Relaxed) {
+        schedule_tick();
+    } else {
+        TICK_END.store(
+            MILLIS
+                .load(Ordering::Relaxed)
+                .wrapping_add(PERIOD.load(Ordering::Relaxed)),
+            Ordering::Relaxed,
+        );
+    }
+}
+
+/// Restart the SysTick in the current tick mode
+///
+/// In tickless mode, the next tick is at the earliest deadline.
+///
+/// # Safety
+/// This must be called with interrupts disabled, after the core
+/// clock has been set.
+unsafe fn schedule_tick() {
+    if !TICKLESS.load(Ordering::Relaxed) {
+        systick::restart_tick(PERIOD.load(Ordering::Relaxed));
+        return;
+    }
+
+    let now = MILLIS.load(Ordering::Relaxed);
+    let deadline = NEXT_DEADLINE.load(Ordering::Relaxed);
+    let remaining = if deadline == NO_WAKE {
+        systick::max_period()
+    } else {
+        // A deadline in the past wraps around to a very long time.
+        match deadline.wrapping_sub(now) {
+            remaining if remaining > usize::MAX / 2 => 1,
+            remaining => remaining.max(1).min(systick::max_period()),
+        }
+    };
+    systick::restart_tick(remaining);
+}
+
+/// Record the end of a sleep
+///
+/// In tickless mode, the SysTick is restarted if the sleep ends
+/// before the next tick.
+pub(super) fn note_deadline(deadline: usize) {
+    without_interrupts(|| {
+        let now = MILLIS.load(Ordering::Relaxed);
+        let next = NEXT_DEADLINE.load(Ordering::Relaxed);
+        if next != NO_WAKE && next.wrapping_sub(now) <= deadline.wrapping_sub(now) {
+            return;
+        }
+        NEXT_DEADLINE.store(deadline, Ordering::Relaxed);
+
+        let tick_end = TICK_END.load(Ordering::Relaxed);
+        if TICKLESS.load(Ordering::Relaxed)
+            && systick::running()
+            && deadline.wrapping_sub(now) < tick_end.wrapping_sub(now)
+        {
+            unsafe { schedule_tick() };
+        }
+    });
+}
+
+static PERIOD: Value = Value::new(1);
+static TICKLESS: Flag = Flag::new(false);
+pub(super) static TICK_END: Value = Value::new(0);
+pub(super) static NEXT_DEADLINE: Value = Value::new(NO_WAKE);
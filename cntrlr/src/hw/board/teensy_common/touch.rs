@@ -0,0 +1,139 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Touch sensing shared between the various Teensy 3.x boards
+
+use crate::{
+    hw::mcu::kinetis::peripheral::{
+        sim::{GatedPeripheral, Sim},
+        tsi::{Scan, TouchSense},
+        Peripheral,
+    },
+    sync::Mutex,
+    touch::{TouchError, TouchOptions},
+};
+use core::{
+    future::{poll_fn, Future},
+    task::Poll,
+};
+
+/// The state of a board's TSI
+pub struct TouchState<T> {
+    tsi: Option<T>,
+    options: TouchOptions,
+    reading: bool,
+}
+
+impl<T> TouchState<T> {
+    /// Create the state for a disabled TSI
+    pub const fn new() -> Self {
+        Self {
+            tsi: None,
+            options: TouchOptions::DEFAULT,
+            reading: false,
+        }
+    }
+
+    /// The current touch sensing configuration
+    pub fn options(&self) -> TouchOptions {
+        self.options
+    }
+}
+
+impl<T: TouchSense> TouchState<T> {
+    /// Configure touch sensing
+    pub fn set_options(&mut self, options: TouchOptions) -> Result<(), TouchError> {
+        if options.current > T::MAX_CURRENT
+            || !(1..=32).contains(&options.scans)
+            || options.prescale > 7
+        {
+            return Err(TouchError::InvalidOptions);
+        }
+        self.options = options;
+        Ok(())
+    }
+
+    /// Start a reading, if no other reading is in progress
+    fn start_read<M>(&mut self, channel: u32) -> Result<bool, TouchError>
+    where
+        T: GatedPeripheral<M>,
+        Sim<M>: Peripheral,
+    {
+        if self.reading {
+            return Ok(false);
+        }
+        if self.tsi.is_none() {
+            self.tsi = Some(
+                Sim::<M>::get()
+                    .and_then(|mut sim| sim.enable_peripheral::<T>())
+                    .ok_or(TouchError::TsiInUse)?,
+            );
+        }
+        let tsi = self.tsi.as_mut().ok_or(TouchError::TsiInUse)?;
+        tsi.start(
+            channel,
+            Scan {
+                current: self.options.current,
+                scans: self.options.scans,
+                prescale: self.options.prescale,
+            },
+        );
+        self.reading = true;
+        Ok(true)
+    }
+
+    /// Take the result of the reading started by `start_read`, once it completes
+    fn finish_read(&mut self, channel: u32) -> Option<u16> {
+        let tsi = self.tsi.as_mut()?;
+        if !tsi.is_complete() {
+            return None;
+        }
+        self.reading = false;
+        Some(tsi.result(channel))
+    }
+}
+
+/// Read one TSI channel
+///
+/// The scan is polled, like an ADC read, rather than interrupting
+/// on completion.
+pub fn read<M, T>(
+    state: &'static Mutex<TouchState<T>>,
+    channel: u32,
+) -> impl Future<Output = Result<u16, TouchError>>
+where
+    M: 'static,
+    T: TouchSense + GatedPeripheral<M> + 'static,
+    Sim<M>: Peripheral,
+{
+    // Releases the TSI if the read is dropped before it completes
+    struct Reading<T: 'static>(&'static Mutex<TouchState<T>>);
+    impl<T: 'static> Drop for Reading<T> {
+        fn drop(&mut self) {
+            self.0.lock().reading = false;
+        }
+    }
+
+    async move {
+        poll_fn(|ctx| match state.lock().start_read::<M>(channel) {
+            Ok(false) => {
+                ctx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Ok(true) => Poll::Ready(Ok(())),
+            Err(err) => Poll::Ready(Err(err)),
+        })
+        .await?;
+
+        let _reading = Reading(state);
+        let result = poll_fn(|ctx| match state.lock().finish_read(channel) {
+            Some(result) => Poll::Ready(result),
+            None => {
+                ctx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await;
+        Ok(result)
+    }
+}
@@ -0,0 +1,821 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! USB serial shared between the various Teensy 3.x boards
+//!
+//! The USB controller implements [`Bus`], and the USB serial port is
+//! a [CDC class](Cdc) run by a background task. The task waits until
+//! the port is first requested before it connects to the bus, so
+//! that boards which never use it do not appear to the host.
+
+use crate::{
+    hw::mcu::kinetis::peripheral::{
+        sim::{GatedPeripheral, Sim},
+        usb::{self, Token, Usb, ENDPOINTS},
+        Peripheral,
+    },
+    io::{Error, ErrorKind, IoSlice, Read, Write},
+    sync::{AsyncMutex, AsyncMutexLock, Value},
+    task::WakerSet,
+    usb::{
+        cdc::{self, Cdc},
+        Allocator, Bus, Class, Device, DeviceInfo, Direction, EndpointAddress, EndpointType, Event,
+        CONTROL_PACKET_SIZE,
+    },
+};
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{
+    cell::{Cell, RefCell, UnsafeCell},
+    future::{poll_fn, Future},
+    ptr::write_volatile,
+    sync::atomic::Ordering,
+    task::{Context, Poll},
+};
+
+/// An error from the USB controller
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum UsbError {
+    /// The endpoint number is larger than the controller supports
+    InvalidEndpoint,
+
+    /// The packet is larger than the endpoint's maximum packet size
+    PacketTooLarge,
+}
+
+/// One direction of an endpoint
+struct Pipe {
+    /// The packet buffers of the even and odd descriptors
+    ///
+    /// These are empty while the pipe is disabled.
+    buffers: RefCell<[Vec<u8>; 2]>,
+
+    /// The descriptor which the controller uses next
+    odd: Cell<bool>,
+
+    /// The data toggle of the next packet
+    data1: Cell<bool>,
+
+    /// Whether a packet is waiting to be sent
+    busy: Cell<bool>,
+
+    /// The length of the packet received into each buffer, until it is read
+    received: Cell<[Option<usize>; 2]>,
+}
+
+impl Pipe {
+    fn new() -> Self {
+        Self {
+            buffers: RefCell::new([Vec::new(), Vec::new()]),
+            odd: Cell::new(false),
+            data1: Cell::new(false),
+            busy: Cell::new(false),
+            received: Cell::new([None, None]),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        !self.buffers.borrow()[0].is_empty()
+    }
+
+    fn disable(&self) {
+        *self.buffers.borrow_mut() = [Vec::new(), Vec::new()];
+        self.odd.set(false);
+        self.data1.set(false);
+        self.busy.set(false);
+        self.received.set([None, None]);
+    }
+}
+
+/// The USB controller, as a [`Bus`]
+///
+/// Each OUT endpoint keeps both of its descriptors armed, so that a
+/// packet can be received while the last one is waiting to be
+/// read. Each IN endpoint sends one packet at a time.
+pub struct UsbBus<M> {
+    usb: RefCell<Usb<M>>,
+    pipes_in: Vec<Pipe>,
+    pipes_out: Vec<Pipe>,
+    setup: Cell<Option<[u8; 8]>>,
+    reset: Cell<bool>,
+    suspend: Cell<bool>,
+    resume: Cell<bool>,
+    suspended: Cell<bool>,
+    resets: Cell<usize>,
+    setups: Cell<usize>,
+}
+
+impl<M> UsbBus<M> {
+    /// Start the controller, and connect to the bus
+    pub fn new(mut usb: Usb<M>) -> Self {
+        usb.enable_device();
+        Self {
+            usb: RefCell::new(usb),
+            pipes_in: (0..ENDPOINTS).map(|_| Pipe::new()).collect(),
+            pipes_out: (0..ENDPOINTS).map(|_| Pipe::new()).collect(),
+            setup: Cell::new(None),
+            reset: Cell::new(false),
+            suspend: Cell::new(false),
+            resume: Cell::new(false),
+            suspended: Cell::new(false),
+            resets: Cell::new(0),
+            setups: Cell::new(0),
+        }
+    }
+
+    fn pipe(&self, endpoint: usize, direction: Direction) -> &Pipe {
+        match direction {
+            Direction::In => &self.pipes_in[endpoint],
+            Direction::Out => &self.pipes_out[endpoint],
+        }
+    }
+
+    /// Handle the controller's interrupt flags and finished transactions
+    fn service(&self) {
+        let mut usb = self.usb.borrow_mut();
+        let flags = usb.interrupts();
+        if flags & usb::INTR_RESET != 0 {
+            usb.reset_endpoints();
+            usb.clear_interrupts(0xFF);
+            for pipe in self.pipes_in.iter().chain(self.pipes_out.iter()) {
+                pipe.disable();
+            }
+            self.enable(&mut usb, 0, Direction::In, CONTROL_PACKET_SIZE, true);
+            self.enable(&mut usb, 0, Direction::Out, CONTROL_PACKET_SIZE, true);
+            self.setup.set(None);
+            self.suspend.set(false);
+            self.resume.set(false);
+            self.suspended.set(false);
+            self.reset.set(true);
+            self.resets.set(self.resets.get().wrapping_add(1));
+            return;
+        }
+        if flags & usb::INTR_STALL != 0 {
+            // The control endpoint is only stalled until the request
+            // is refused.
+            usb.set_stalled(0, false);
+            usb.clear_interrupts(usb::INTR_STALL);
+        }
+        if flags & usb::INTR_SLEEP != 0 {
+            usb.clear_interrupts(usb::INTR_SLEEP);
+            if !self.suspended.replace(true) {
+                self.suspend.set(true);
+            }
+        }
+        while let Some(token) = usb.token() {
+            if self.suspended.replace(false) {
+                self.resume.set(true);
+            }
+            if token.tx {
+                self.pipes_in[token.endpoint].busy.set(false);
+            } else if token.pid == usb::PID_SETUP {
+                self.setup_received(&mut usb, token);
+            } else {
+                let pipe = &self.pipes_out[token.endpoint];
+                let mut received = pipe.received.get();
+                received[token.odd as usize] = Some(token.len);
+                pipe.received.set(received);
+                pipe.odd.set(!token.odd);
+                pipe.data1.set(!pipe.data1.get());
+            }
+        }
+    }
+
+    /// Start a new control request
+    ///
+    /// Whatever is left of the last request is abandoned. The data
+    /// and status stages of the new one both start with DATA1.
+    fn setup_received(&self, usb: &mut Usb<M>, token: Token) {
+        let out = &self.pipes_out[0];
+        let mut setup = [0; 8];
+        setup.copy_from_slice(&out.buffers.borrow()[token.odd as usize][..8]);
+
+        let input = &self.pipes_in[0];
+        if input.busy.replace(false) {
+            // The packet was never sent, so its descriptor is used next
+            input.odd.set(!input.odd.get());
+            usb.release(0, true, input.odd.get());
+        }
+        input.data1.set(true);
+
+        usb.release(0, false, !token.odd);
+        out.odd.set(!token.odd);
+        out.data1.set(true);
+        out.received.set([None, None]);
+        self.arm_out(usb, 0, false);
+        self.arm_out(usb, 0, true);
+
+        usb.set_stalled(0, false);
+        self.setup.set(Some(setup));
+        self.setups.set(self.setups.get().wrapping_add(1));
+        usb.resume_tokens();
+    }
+
+    fn enable(
+        &self,
+        usb: &mut Usb<M>,
+        endpoint: usize,
+        direction: Direction,
+        max_packet_size: u16,
+        handshake: bool,
+    ) {
+        let pipe = self.pipe(endpoint, direction);
+        if pipe.is_enabled() {
+            return;
+        }
+        let size = max_packet_size as usize;
+        *pipe.buffers.borrow_mut() = [vec![0; size], vec![0; size]];
+        match direction {
+            Direction::In => usb.enable_endpoint(endpoint, true, false, handshake),
+            Direction::Out => {
+                usb.enable_endpoint(endpoint, false, true, handshake);
+                self.arm_out(usb, endpoint, false);
+                self.arm_out(usb, endpoint, true);
+            }
+        }
+    }
+
+    /// Give one of an OUT pipe's buffers to the controller
+    ///
+    /// The two descriptors alternate, so each one's data toggle
+    /// follows from the toggle of the descriptor used next.
+    fn arm_out(&self, usb: &mut Usb<M>, endpoint: usize, odd: bool) {
+        let pipe = &self.pipes_out[endpoint];
+        let data1 = pipe.data1.get() ^ (odd != pipe.odd.get());
+        let mut buffers = pipe.buffers.borrow_mut();
+        let buffer = &mut buffers[odd as usize];
+        // Safety: The buffer is not touched until its token is taken,
+        // and is only freed after a bus reset releases it.
+        unsafe {
+            usb.arm(
+                endpoint,
+                false,
+                odd,
+                data1,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+            )
+        };
+    }
+
+    /// Whether a transfer started at `resets` and `setups` has been abandoned
+    fn is_abandoned(&self, endpoint: usize, resets: usize, setups: usize) -> bool {
+        self.resets.get() != resets || (endpoint == 0 && self.setups.get() != setups)
+    }
+
+    fn wait<T>(&self, ctx: &mut Context) -> Poll<T> {
+        WAKERS.add(ctx.waker().clone());
+        self.usb.borrow_mut().enable_interrupts(
+            usb::INTR_RESET | usb::INTR_TOKEN | usb::INTR_SLEEP | usb::INTR_STALL,
+        );
+        Poll::Pending
+    }
+}
+
+impl<M> Drop for UsbBus<M> {
+    fn drop(&mut self) {
+        self.usb.get_mut().disable_device();
+    }
+}
+
+impl<M: 'static> Bus for UsbBus<M> {
+    type Error = UsbError;
+    #[rustfmt::skip]
+    type EventFuture<'a> where Self: 'a = impl Future<Output = Result<Event, Self::Error>> + 'a;
+    #[rustfmt::skip]
+    type ReadFuture<'a> where Self: 'a = impl Future<Output = Result<usize, Self::Error>> + 'a;
+    #[rustfmt::skip]
+    type WriteFuture<'a> where Self: 'a = impl Future<Output = Result<(), Self::Error>> + 'a;
+
+    fn endpoint_count(&self) -> u8 {
+        ENDPOINTS as u8
+    }
+
+    fn enable_endpoint(
+        &self,
+        address: EndpointAddress,
+        kind: EndpointType,
+        max_packet_size: u16,
+    ) -> Result<(), Self::Error> {
+        let endpoint = address.number() as usize;
+        if endpoint >= ENDPOINTS {
+            return Err(UsbError::InvalidEndpoint);
+        }
+        let handshake = kind != EndpointType::Isochronous;
+        let mut usb = self.usb.borrow_mut();
+        self.enable(
+            &mut usb,
+            endpoint,
+            address.direction(),
+            max_packet_size,
+            handshake,
+        );
+        Ok(())
+    }
+
+    fn set_address(&self, address: u8) {
+        self.usb.borrow_mut().set_address(address);
+    }
+
+    /// Both directions of an endpoint are stalled together
+    fn set_stalled(&self, address: EndpointAddress, stalled: bool) {
+        let endpoint = address.number() as usize;
+        if endpoint < ENDPOINTS {
+            self.usb.borrow_mut().set_stalled(endpoint, stalled);
+        }
+    }
+
+    fn is_stalled(&self, address: EndpointAddress) -> bool {
+        let endpoint = address.number() as usize;
+        endpoint < ENDPOINTS && self.usb.borrow().is_stalled(endpoint)
+    }
+
+    fn event<'a>(&'a self) -> Self::EventFuture<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            self.service();
+            if self.reset.replace(false) {
+                Poll::Ready(Ok(Event::Reset))
+            } else if self.resume.replace(false) {
+                Poll::Ready(Ok(Event::Resume))
+            } else if let Some(setup) = self.setup.take() {
+                Poll::Ready(Ok(Event::Setup(setup)))
+            } else if self.suspend.replace(false) {
+                Poll::Ready(Ok(Event::Suspend))
+            } else {
+                self.wait(ctx)
+            }
+        })
+    }
+
+    fn read<'a>(&'a self, endpoint: u8, buf: &'a mut [u8]) -> Self::ReadFuture<'a>
+    where
+        Self: 'a,
+    {
+        let endpoint = endpoint as usize;
+        let resets = self.resets.get();
+        let setups = self.setups.get();
+        poll_fn(move |ctx| {
+            if endpoint >= ENDPOINTS {
+                return Poll::Ready(Err(UsbError::InvalidEndpoint));
+            }
+            self.service();
+            if self.is_abandoned(endpoint, resets, setups) {
+                return Poll::Ready(Ok(0));
+            }
+
+            let pipe = &self.pipes_out[endpoint];
+            let mut received = pipe.received.get();
+            // When both buffers hold a packet, the older one is in the
+            // descriptor the controller uses next.
+            let next = pipe.odd.get();
+            let odd = if received[next as usize].is_some() {
+                next
+            } else {
+                !next
+            };
+            let len = match received[odd as usize] {
+                Some(len) => len,
+                None => return self.wait(ctx),
+            };
+            if len > buf.len() {
+                return Poll::Ready(Err(UsbError::PacketTooLarge));
+            }
+            buf[..len].copy_from_slice(&pipe.buffers.borrow()[odd as usize][..len]);
+            received[odd as usize] = None;
+            pipe.received.set(received);
+            self.arm_out(&mut self.usb.borrow_mut(), endpoint, odd);
+            Poll::Ready(Ok(len))
+        })
+    }
+
+    fn write<'a>(&'a self, endpoint: u8, buf: &'a [u8]) -> Self::WriteFuture<'a>
+    where
+        Self: 'a,
+    {
+        let endpoint = endpoint as usize;
+        let resets = self.resets.get();
+        let setups = self.setups.get();
+        let mut started = false;
+        poll_fn(move |ctx| {
+            if endpoint >= ENDPOINTS {
+                return Poll::Ready(Err(UsbError::InvalidEndpoint));
+            }
+            self.service();
+            if self.is_abandoned(endpoint, resets, setups) {
+                return Poll::Ready(Ok(()));
+            }
+
+            let pipe = &self.pipes_in[endpoint];
+            if started {
+                return if pipe.busy.get() {
+                    self.wait(ctx)
+                } else {
+                    Poll::Ready(Ok(()))
+                };
+            }
+            if !pipe.is_enabled() || pipe.busy.get() {
+                return self.wait(ctx);
+            }
+
+            let odd = pipe.odd.get();
+            let mut buffers = pipe.buffers.borrow_mut();
+            let buffer = &mut buffers[odd as usize];
+            if buf.len() > buffer.len() {
+                return Poll::Ready(Err(UsbError::PacketTooLarge));
+            }
+            buffer[..buf.len()].copy_from_slice(buf);
+            // Safety: The buffer is not touched until its token is
+            // taken or it is released.
+            unsafe {
+                self.usb.borrow_mut().arm(
+                    endpoint,
+                    true,
+                    odd,
+                    pipe.data1.get(),
+                    buffer.as_mut_ptr(),
+                    buf.len(),
+                )
+            };
+            pipe.odd.set(!odd);
+            pipe.data1.set(!pipe.data1.get());
+            pipe.busy.set(true);
+            started = true;
+            self.wait(ctx)
+        })
+    }
+}
+
+/// The USB interrupt function
+///
+/// The interrupt is masked until the task waiting on the controller
+/// has handled its flags.
+pub extern "C" fn usb_intr() {
+    const USB_INTEN: *mut u8 = 0x4007_2084 as *mut _;
+    unsafe { write_volatile(USB_INTEN, 0) };
+    WAKERS.wake();
+}
+
+static WAKERS: WakerSet = WakerSet::new();
+
+/// An error from the USB serial port
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum UsbSerialError {
+    /// The USB stack could not start because the USB controller or the SIM is in use
+    UsbInUse,
+
+    /// The USB stack stopped after a controller error
+    Stopped,
+}
+
+impl From<UsbSerialError> for Error {
+    fn from(err: UsbSerialError) -> Self {
+        let kind = match err {
+            UsbSerialError::UsbInUse => ErrorKind::InUse,
+            UsbSerialError::Stopped => ErrorKind::Other,
+        };
+        Error::new(kind, err)
+    }
+}
+
+/// The USB serial port
+///
+/// The board appears to the host as a USB serial port once this is
+/// first requested. Data written while no program on the host has
+/// the port open is discarded; programs open it by setting DTR, as
+/// terminals do.
+pub struct UsbSerial {
+    _private: (),
+}
+
+impl Read for UsbSerial {
+    type Error = UsbSerialError;
+    type Future<'a> = impl Future<Output = Result<usize, Self::Error>> + 'a;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Future<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            if buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            check_state()?;
+            let count = RX.pop(buf);
+            if count > 0 {
+                return Poll::Ready(Ok(count));
+            }
+            RX.wakers.add(ctx.waker().clone());
+            // Data may have arrived before the waker was added.
+            if !RX.is_empty() {
+                ctx.waker().wake_by_ref();
+            }
+            Poll::Pending
+        })
+    }
+}
+
+impl Write for UsbSerial {
+    type Error = UsbSerialError;
+    type Future<'a> = impl Future<Output = Result<usize, Self::Error>> + 'a;
+    type FlushFuture<'a> = impl Future<Output = Result<(), Self::Error>> + 'a;
+    type VectoredFuture<'a> = impl Future<Output = Result<usize, Self::Error>> + 'a;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Future<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            if buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            check_state()?;
+            let count = TX.push(buf);
+            if count > 0 {
+                return Poll::Ready(Ok(count));
+            }
+            TX.wakers.add(ctx.waker().clone());
+            // Space may have been freed before the waker was added.
+            if !TX.is_full() {
+                ctx.waker().wake_by_ref();
+            }
+            Poll::Pending
+        })
+    }
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'a>]) -> Self::VectoredFuture<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            check_state()?;
+            let mut count = 0;
+            for buf in bufs {
+                let pushed = TX.push(buf);
+                count += pushed;
+                if pushed < buf.len() {
+                    break;
+                }
+            }
+            if count > 0 || bufs.iter().all(|buf| buf.is_empty()) {
+                return Poll::Ready(Ok(count));
+            }
+            TX.wakers.add(ctx.waker().clone());
+            if !TX.is_full() {
+                ctx.waker().wake_by_ref();
+            }
+            Poll::Pending
+        })
+    }
+
+    /// Wait until the host has received everything written
+    fn flush<'a>(&'a mut self) -> Self::FlushFuture<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            check_state()?;
+            if TX.is_empty() && SENDING.load(Ordering::Acquire) == 0 {
+                return Poll::Ready(Ok(()));
+            }
+            TX.wakers.add(ctx.waker().clone());
+            if TX.is_empty() && SENDING.load(Ordering::Acquire) == 0 {
+                ctx.waker().wake_by_ref();
+            }
+            Poll::Pending
+        })
+    }
+}
+
+fn check_state() -> Result<(), UsbSerialError> {
+    match STATE.load(Ordering::Acquire) {
+        STATE_IN_USE => Err(UsbSerialError::UsbInUse),
+        STATE_STOPPED => Err(UsbSerialError::Stopped),
+        _ => Ok(()),
+    }
+}
+
+/// Get the USB serial port
+///
+/// The USB stack is started the first time this is called.
+pub fn serial() -> AsyncMutexLock<'static, UsbSerial> {
+    static SERIAL: AsyncMutex<UsbSerial> = AsyncMutex::new(UsbSerial { _private: () });
+
+    if STATE.load(Ordering::Relaxed) == STATE_IDLE {
+        STATE.store(STATE_REQUESTED, Ordering::Release);
+        START_WAKERS.wake();
+    }
+    SERIAL.lock()
+}
+
+/// Run the USB serial port
+///
+/// This waits for the port to be requested, then runs the USB stack
+/// and moves data between it and the port's buffers.
+pub fn serial_task<M>() -> impl Future<Output = !>
+where
+    M: 'static,
+    Usb<M>: GatedPeripheral<M>,
+    Sim<M>: Peripheral,
+{
+    async {
+        poll_fn(|ctx| {
+            if STATE.load(Ordering::Acquire) != STATE_IDLE {
+                return Poll::Ready(());
+            }
+            START_WAKERS.add(ctx.waker().clone());
+            if STATE.load(Ordering::Acquire) != STATE_IDLE {
+                ctx.waker().wake_by_ref();
+            }
+            Poll::Pending
+        })
+        .await;
+
+        match Sim::<M>::get().and_then(|mut sim| sim.enable_peripheral::<Usb<M>>()) {
+            Some(usb) => {
+                run(usb).await;
+                stop(STATE_STOPPED);
+            }
+            None => stop(STATE_IN_USE),
+        }
+        poll_fn(|_| Poll::Pending).await
+    }
+}
+
+/// Run the USB stack until the controller reports an error
+async fn run<M: 'static>(usb: Usb<M>) {
+    let allocator = Allocator::new(UsbBus::new(usb));
+    let serial = Cdc::new(&allocator);
+    let info = DeviceInfo {
+        manufacturer: Some("Cntrlr"),
+        product: Some("USB Serial"),
+        ..DeviceInfo::new(0x16C0, 0x0483)
+    };
+    let classes: [&dyn Class; 1] = [&serial];
+    let device = Device::new(&allocator, info, &classes);
+
+    let mut control = Box::pin(device.run());
+    let mut receive = Box::pin(receive(&serial));
+    let mut transmit = Box::pin(transmit(&serial));
+    poll_fn(|ctx| {
+        // The pumps only stop at a bus error, which the control
+        // endpoint also sees.
+        if control.as_mut().poll(ctx).is_ready() {
+            return Poll::Ready(());
+        }
+        let _ = receive.as_mut().poll(ctx);
+        let _ = transmit.as_mut().poll(ctx);
+        Poll::Pending
+    })
+    .await
+}
+
+/// Move packets from the host into the receive buffer
+async fn receive<B: Bus>(serial: &Cdc<'_, B>) {
+    let mut packet = [0; cdc::PACKET_SIZE as usize];
+    loop {
+        let len = match serial.read(&mut packet).await {
+            Ok(len) => len,
+            Err(_) => return,
+        };
+        let mut count = 0;
+        while count < len {
+            count += poll_fn(|ctx| {
+                let pushed = RX.push(&packet[count..len]);
+                if pushed > 0 {
+                    return Poll::Ready(pushed);
+                }
+                RX.wakers.add(ctx.waker().clone());
+                if !RX.is_full() {
+                    ctx.waker().wake_by_ref();
+                }
+                Poll::Pending
+            })
+            .await;
+        }
+    }
+}
+
+/// Move packets from the transmit buffer to the host
+async fn transmit<B: Bus>(serial: &Cdc<'_, B>) {
+    let mut packet = [0; cdc::PACKET_SIZE as usize];
+    loop {
+        let len = poll_fn(|ctx| {
+            let len = TX.pop(&mut packet);
+            if len > 0 {
+                SENDING.store(1, Ordering::Release);
+                return Poll::Ready(len);
+            }
+            TX.wakers.add(ctx.waker().clone());
+            if !TX.is_empty() {
+                ctx.waker().wake_by_ref();
+            }
+            Poll::Pending
+        })
+        .await;
+
+        if serial.is_configured() && serial.dtr() {
+            if serial.write(&packet[..len]).await.is_err() {
+                return;
+            }
+            // A full packet does not end a transfer, so the host
+            // would wait for more data without a zero-length packet.
+            if len == packet.len() && TX.is_empty() && serial.write(&[]).await.is_err() {
+                return;
+            }
+        }
+        SENDING.store(0, Ordering::Release);
+        TX.wakers.wake();
+    }
+}
+
+fn stop(state: usize) {
+    STATE.store(state, Ordering::Release);
+    RX.wakers.wake();
+    TX.wakers.wake();
+}
+
+const STATE_IDLE: usize = 0;
+const STATE_REQUESTED: usize = 1;
+const STATE_IN_USE: usize = 2;
+const STATE_STOPPED: usize = 3;
+
+static STATE: Value = Value::new(STATE_IDLE);
+static START_WAKERS: WakerSet = WakerSet::new();
+
+/// Whether a packet taken from the transmit buffer is being sent
+static SENDING: Value = Value::new(0);
+
+const BUFFER_LEN: usize = 256;
+
+static RX: Buffer = Buffer::new();
+static TX: Buffer = Buffer::new();
+
+/// A buffer of bytes between the port and the USB stack
+struct Buffer {
+    head: Value,
+    tail: Value,
+    buffer: UnsafeCell<[u8; BUFFER_LEN]>,
+    wakers: WakerSet,
+}
+
+// Safety: Each buffer has one writer and one reader. Each byte is
+// written before the head is advanced past it, and only read after
+// that.
+unsafe impl Sync for Buffer {}
+
+impl Buffer {
+    const fn new() -> Self {
+        Self {
+            head: Value::new(0),
+            tail: Value::new(0),
+            buffer: UnsafeCell::new([0; BUFFER_LEN]),
+            wakers: WakerSet::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.head
+            .load(Ordering::Acquire)
+            .wrapping_sub(self.tail.load(Ordering::Acquire))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn is_full(&self) -> bool {
+        self.len() >= BUFFER_LEN
+    }
+
+    /// Add as many bytes as fit, and return how many were added
+    fn push(&self, data: &[u8]) -> usize {
+        let head = self.head.load(Ordering::Relaxed);
+        let count = data.len().min(BUFFER_LEN - self.len());
+        for (i, &byte) in data[..count].iter().enumerate() {
+            unsafe { (*self.buffer.get())[head.wrapping_add(i) % BUFFER_LEN] = byte };
+        }
+        if count > 0 {
+            self.head.store(head.wrapping_add(count), Ordering::Release);
+            self.wakers.wake();
+        }
+        count
+    }
+
+    /// Take as many bytes as fit in `buf`, and return how many were taken
+    fn pop(&self, buf: &mut [u8]) -> usize {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let count = buf.len().min(self.len());
+        for (i, byte) in buf[..count].iter_mut().enumerate() {
+            *byte = unsafe { (*self.buffer.get())[tail.wrapping_add(i) % BUFFER_LEN] };
+        }
+        if count > 0 {
+            self.tail.store(tail.wrapping_add(count), Ordering::Release);
+            self.wakers.wake();
+        }
+        count
+    }
+}
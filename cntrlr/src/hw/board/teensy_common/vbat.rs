@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Battery-backed storage shared between the various Teensy 3.x boards
+
+use crate::{
+    hw::mcu::kinetis::peripheral::{rfvbat::Rfvbat, Peripheral},
+    vbat::{VbatError, SIZE},
+};
+
+/// Read bytes from the VBAT register file
+///
+/// The registers are read as little-endian words.
+pub fn read<M>(offset: usize, buf: &mut [u8]) -> Result<(), VbatError>
+where
+    Rfvbat<M>: Peripheral,
+{
+    check_range(offset, buf.len())?;
+    let rfvbat = Rfvbat::<M>::get().ok_or(VbatError::InUse)?;
+    for (i, byte) in buf.iter_mut().enumerate() {
+        let address = offset + i;
+        *byte = rfvbat.read(address / 4).to_le_bytes()[address % 4];
+    }
+    Ok(())
+}
+
+/// Write bytes to the VBAT register file
+///
+/// Words which are only partly written keep their other bytes.
+pub fn write<M>(offset: usize, data: &[u8]) -> Result<(), VbatError>
+where
+    Rfvbat<M>: Peripheral,
+{
+    check_range(offset, data.len())?;
+    let mut rfvbat = Rfvbat::<M>::get().ok_or(VbatError::InUse)?;
+    for (i, &byte) in data.iter().enumerate() {
+        let address = offset + i;
+        let mut word = rfvbat.read(address / 4).to_le_bytes();
+        word[address % 4] = byte;
+        rfvbat.write(address / 4, u32::from_le_bytes(word));
+    }
+    Ok(())
+}
+
+fn check_range(offset: usize, len: usize) -> Result<(), VbatError> {
+    match offset.checked_add(len) {
+        Some(end) if end <= SIZE => Ok(()),
+        _ => Err(VbatError::OutOfRange),
+    }
+}
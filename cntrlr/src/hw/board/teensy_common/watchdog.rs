@@ -0,0 +1,73 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Watchdog functionality shared between the Teensy 3.x and LC boards
+//!
+//! The Teensy 3.x boards use the WDOG, which counts the 1kHz LPO. The
+//! Teensy LC's watchdog is disabled at startup, and cannot be enabled
+//! again until the next reset.
+
+#[cfg(not(mcu = "mkl26z64"))]
+use crate::{
+    hw::mcu::kinetis::peripheral::wdog::Watchdog,
+    sync::{without_interrupts, Value},
+};
+use crate::{time::Duration, watchdog::WatchdogError};
+#[cfg(not(mcu = "mkl26z64"))]
+use core::sync::atomic::Ordering;
+
+/// Enable the watchdog, or change its timeout
+///
+/// Timeouts shorter than 4 milliseconds are lengthened to 4. This
+/// always returns [`WatchdogError::Unsupported`] on the Teensy LC.
+pub fn enable(timeout: Duration) -> Result<(), WatchdogError> {
+    #[cfg(not(mcu = "mkl26z64"))]
+    {
+        without_interrupts(|| {
+            if ENABLED.load(Ordering::Relaxed) == 0 {
+                if super::time::STRICT_DEADLINE.swap(true, Ordering::Acquire) {
+                    return Err(WatchdogError::InUse);
+                }
+                ENABLED.store(1, Ordering::Relaxed);
+            }
+            // Safety: The strict deadline flag ensures the watchdog
+            // is only used here. The timeout register is as wide as
+            // usize, so any timeout fits.
+            unsafe { Watchdog::get().enable((timeout.as_millis() as u32).max(4)) };
+            Ok(())
+        })
+    }
+
+    #[cfg(mcu = "mkl26z64")]
+    {
+        let _ = timeout;
+        Err(WatchdogError::Unsupported)
+    }
+}
+
+/// Disable the watchdog
+pub fn disable() {
+    #[cfg(not(mcu = "mkl26z64"))]
+    without_interrupts(|| {
+        if ENABLED.swap(0, Ordering::Relaxed) != 0 {
+            // Safety: As in enable
+            unsafe { Watchdog::get().disable() };
+            super::time::STRICT_DEADLINE.store(false, Ordering::Release);
+        }
+    });
+}
+
+/// Feed the watchdog, restarting its count
+pub fn feed() {
+    #[cfg(not(mcu = "mkl26z64"))]
+    without_interrupts(|| {
+        if ENABLED.load(Ordering::Relaxed) != 0 {
+            // Safety: As in enable
+            unsafe { Watchdog::get().refresh() };
+        }
+    });
+}
+
+/// Whether the watchdog is enabled by [`enable`]
+#[cfg(not(mcu = "mkl26z64"))]
+static ENABLED: Value = Value::new(0);
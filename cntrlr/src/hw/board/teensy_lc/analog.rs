@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Analog functionality specific to the Teensy LC board
+
+use crate::{
+    analog::{AdcOptions, AnalogError},
+    hw::{
+        board::teensy_common::{
+            analog::{self, AnalogState},
+            digital::AnalogOp,
+        },
+        mcu::kinetis::Mkl26Z64,
+    },
+    sync::Mutex,
+};
+use core::{future::Future, sync::atomic::Ordering};
+
+static ADC_0: Mutex<AnalogState<Mkl26Z64, 0>> = Mutex::new(AnalogState::new());
+
+/// Configure the ADC
+pub fn set_adc_options(options: AdcOptions) -> Result<(), AnalogError> {
+    ADC_0
+        .lock()
+        .set_options(options, super::BUS_FREQ.load(Ordering::Relaxed))
+}
+
+/// The current ADC configuration
+pub fn adc_options() -> AdcOptions {
+    ADC_0.lock().options()
+}
+
+/// Read an analog pin on ADC 0
+pub fn analog_read(pin: usize) -> impl Future<Output = Result<u16, AnalogError>> {
+    async move {
+        let (channel, mux_b) = adc_channel(pin).ok_or(AnalogError::InvalidPin)?;
+        super::digital::pin_op::<AnalogOp>(pin, ());
+        analog::read(
+            &ADC_0,
+            channel,
+            mux_b,
+            super::BUS_FREQ.load(Ordering::Relaxed),
+        )
+        .await
+    }
+}
+
+/// The ADC 0 channel of a pin, and whether it is on the `B` multiplexer
+fn adc_channel(pin: usize) -> Option<(u32, bool)> {
+    let channel = match pin {
+        14 => (5, true),
+        15 => (14, false),
+        16 => (8, false),
+        17 => (9, false),
+        18 => (13, false),
+        19 => (12, false),
+        20 => (6, true),
+        21 => (7, true),
+        22 => (15, false),
+        23 => (11, false),
+        24 => (0, false),
+        25 => (4, false),
+        26 => (23, false),
+        _ => return None,
+    };
+    Some(channel)
+}
@@ -4,51 +4,61 @@
 //! Digital pin support specific to the Teensy LC
 
 use crate::{
-    digital::PinMode,
+    digital::{DriveStrength, Edge, FilterConfig, Led, PinConfig, PinMode, SlewRate},
     hw::{
-        board::teensy_common::digital::{ModeOp, PinOp, ReadOp, WriteOp},
+        board::teensy_common::digital::{
+            self, DriveOp, FilterOp, InterruptOp, ModeOp, OutputOp, PinOp, ReadOp, SlewOp, WriteOp,
+        },
         mcu::kinetis::mkl26z64::{Port, Sim},
     },
     sync::Once,
 };
 
-/// Invoke an operation on a pin.
-///
-/// This abstracts the mapping of Teensy pins to MCU pins, allowing
-/// operations to be defined generically and then invoked for any
-/// given pin.
-#[inline]
-pub fn pin_op<Op: PinOp>(pin: usize, arg: Op::Arg) -> Option<Op::Result> {
-    match pin {
-        0 => Op::do_op(port_b().and_then(|port| port.pin::<16>()), arg),
-        1 => Op::do_op(port_b().and_then(|port| port.pin::<17>()), arg),
-        2 => Op::do_op(port_d().and_then(|port| port.pin::<0>()), arg),
-        3 => Op::do_op(port_a().and_then(|port| port.pin::<1>()), arg),
-        4 => Op::do_op(port_a().and_then(|port| port.pin::<2>()), arg),
-        5 => Op::do_op(port_d().and_then(|port| port.pin::<7>()), arg),
-        6 => Op::do_op(port_d().and_then(|port| port.pin::<4>()), arg),
-        7 => Op::do_op(port_d().and_then(|port| port.pin::<2>()), arg),
-        8 => Op::do_op(port_d().and_then(|port| port.pin::<3>()), arg),
-        9 => Op::do_op(port_c().and_then(|port| port.pin::<3>()), arg),
-        10 => Op::do_op(port_c().and_then(|port| port.pin::<4>()), arg),
-        11 => Op::do_op(port_c().and_then(|port| port.pin::<6>()), arg),
-        12 => Op::do_op(port_c().and_then(|port| port.pin::<7>()), arg),
-        13 => Op::do_op(port_c().and_then(|port| port.pin::<5>()), arg),
-        14 => Op::do_op(port_d().and_then(|port| port.pin::<1>()), arg),
-        15 => Op::do_op(port_c().and_then(|port| port.pin::<0>()), arg),
-        16 => Op::do_op(port_b().and_then(|port| port.pin::<0>()), arg),
-        17 => Op::do_op(port_b().and_then(|port| port.pin::<1>()), arg),
-        18 => Op::do_op(port_b().and_then(|port| port.pin::<3>()), arg),
-        19 => Op::do_op(port_b().and_then(|port| port.pin::<2>()), arg),
-        20 => Op::do_op(port_d().and_then(|port| port.pin::<5>()), arg),
-        21 => Op::do_op(port_d().and_then(|port| port.pin::<6>()), arg),
-        22 => Op::do_op(port_c().and_then(|port| port.pin::<1>()), arg),
-        23 => Op::do_op(port_c().and_then(|port| port.pin::<2>()), arg),
-        24 => Op::do_op(port_e().and_then(|port| port.pin::<20>()), arg),
-        25 => Op::do_op(port_e().and_then(|port| port.pin::<21>()), arg),
-        26 => Op::do_op(port_e().and_then(|port| port.pin::<30>()), arg),
-        _ => None,
-    }
+pub(crate) use crate::hw::board::teensy_common::digital::{port_read, port_write};
+
+pin_table! {
+    D0 = 0 => port_b(1, 16),
+    D1 = 1 => port_b(1, 17),
+    D2 = 2 => port_d(3, 0),
+    D3 = 3 => port_a(0, 1),
+    D4 = 4 => port_a(0, 2),
+    D5 = 5 => port_d(3, 7),
+    D6 = 6 => port_d(3, 4),
+    D7 = 7 => port_d(3, 2),
+    D8 = 8 => port_d(3, 3),
+    D9 = 9 => port_c(2, 3),
+    D10 = 10 => port_c(2, 4),
+    D11 = 11 => port_c(2, 6),
+    D12 = 12 => port_c(2, 7),
+    D13 = 13 => port_c(2, 5),
+    D14 = 14 => port_d(3, 1),
+    D15 = 15 => port_c(2, 0),
+    D16 = 16 => port_b(1, 0),
+    D17 = 17 => port_b(1, 1),
+    D18 = 18 => port_b(1, 3),
+    D19 = 19 => port_b(1, 2),
+    D20 = 20 => port_d(3, 5),
+    D21 = 21 => port_d(3, 6),
+    D22 = 22 => port_c(2, 1),
+    D23 = 23 => port_c(2, 2),
+    D24 = 24 => port_e(4, 20),
+    D25 = 25 => port_e(4, 21),
+    D26 = 26 => port_e(4, 30),
+    ;
+    A0 = D14,
+    A1 = D15,
+    A2 = D16,
+    A3 = D17,
+    A4 = D18,
+    A5 = D19,
+    A6 = D20,
+    A7 = D21,
+    A8 = D22,
+    A9 = D23,
+    A10 = D24,
+    A11 = D25,
+    A12 = D26,
+    LED_PIN = D13,
 }
 
 /// Set a digital pin high or low.
@@ -98,6 +108,112 @@ pub fn pin_mode(pin: usize, mode: PinMode) {
     pin_op::<ModeOp>(pin, mode);
 }
 
+/// The pin connected to the on-board LED
+///
+/// On the Teensy LC, the LED is on pin 13.
+#[inline]
+pub fn led_builtin() -> usize {
+    pins::LED_PIN
+}
+
+/// Turn the on-board LED on or off
+///
+/// This sets the LED pin as an output, so it does not need to be
+/// configured with [`pin_mode`] first.
+#[inline]
+pub fn led_write(on: bool) {
+    pin_mode(led_builtin(), PinMode::Output);
+    digital_write(led_builtin(), on);
+}
+
+/// Check whether the on-board LED is on
+///
+/// This reads the state the LED pin is set to output.
+#[inline]
+pub fn led_read() -> bool {
+    pin_op::<OutputOp>(led_builtin(), ()).unwrap_or(false)
+}
+
+/// Get the on-board LED
+#[inline]
+pub fn led() -> Led {
+    Led(())
+}
+
+/// Read back the configuration of a pin
+///
+/// Returns `None` if `pin` is not a valid pin, or its port cannot be
+/// enabled.
+pub fn dump_pin_config(pin: usize) -> Option<PinConfig> {
+    let (port, mcu_pin) = mcu_pin(pin)?;
+    let enabled = match port {
+        0 => port_a().is_some(),
+        1 => port_b().is_some(),
+        2 => port_c().is_some(),
+        3 => port_d().is_some(),
+        4 => port_e().is_some(),
+        _ => false,
+    };
+    if enabled {
+        Some(digital::pin_config(port, mcu_pin))
+    } else {
+        None
+    }
+}
+
+/// Set the output drive strength of a pin
+///
+/// Returns `false` if `pin` is not a valid pin, does not support the
+/// requested drive strength, or is in use by another module.
+#[inline]
+pub fn set_drive_strength(pin: usize, strength: DriveStrength) -> bool {
+    pin_op::<DriveOp>(pin, strength).unwrap_or(false)
+}
+
+/// Set the output slew rate of a pin
+///
+/// Returns `false` if `pin` is not a valid pin, does not support the
+/// requested slew rate, or is in use by another module.
+#[inline]
+pub fn set_slew_rate(pin: usize, rate: SlewRate) -> bool {
+    pin_op::<SlewOp>(pin, rate).unwrap_or(false)
+}
+
+/// Set the input filter of a pin
+///
+/// Returns `false` if `pin` is not a valid pin, does not support the
+/// requested filter, or is in use by another module.
+#[inline]
+pub fn set_input_filter(pin: usize, config: FilterConfig) -> bool {
+    pin_op::<FilterOp>(pin, config).unwrap_or(false)
+}
+
+/// Set which edges on a pin trigger its interrupt
+///
+/// On the Teensy LC, only pins on ports A, C, and D can generate
+/// interrupts.
+///
+/// Returns `false` if `pin` is not a valid pin, cannot generate
+/// interrupts, or is in use by another module.
+#[inline]
+pub(crate) fn set_pin_interrupt(pin: usize, edge: Option<Edge>) -> bool {
+    match mcu_pin(pin) {
+        Some((0, _)) | Some((2, _)) | Some((3, _)) => pin_op::<InterruptOp>(pin, edge).is_some(),
+        _ => false,
+    }
+}
+
+/// Interrupt function for port A
+pub extern "C" fn port_a_intr() {
+    digital::port_intr(0);
+}
+
+/// Interrupt function for ports C and D
+pub extern "C" fn port_cd_intr() {
+    digital::port_intr(2);
+    digital::port_intr(3);
+}
+
 /// Port A
 ///
 /// The global instance of PORT A, used to share port ownership among
@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Register listings for [`dump_state`](super::dump_state)
+
+use core::{fmt, ptr::read_volatile};
+
+/// The width of a register
+///
+/// Not every MCU has registers of every width.
+#[derive(Clone, Copy)]
+#[allow(dead_code)]
+pub enum Size {
+    U8,
+    U32,
+}
+
+/// A register, by its offset from its block's base address
+pub struct Reg(pub &'static str, pub usize, pub Size);
+
+/// A group of registers, usually one peripheral
+pub struct Block {
+    pub name: &'static str,
+    pub base: usize,
+    pub regs: &'static [Reg],
+}
+
+impl Block {
+    /// Write the name of the block, then each register's name, address, and value
+    ///
+    /// The registers are read directly, so the block must be clocked.
+    pub fn write(&self, writer: &mut dyn fmt::Write) -> fmt::Result {
+        writeln!(writer, "{}:", self.name)?;
+        for Reg(name, offset, size) in self.regs {
+            let address = self.base + offset;
+            let (value, digits) = unsafe {
+                match size {
+                    Size::U8 => (read_volatile(address as *const u8) as u32, 2),
+                    Size::U32 => (read_volatile(address as *const u32), 8),
+                }
+            };
+            writeln!(
+                writer,
+                "  {:<8} {:#010x} = {:#0width$x}",
+                name,
+                address,
+                value,
+                width = digits + 2
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write the name of a block whose clock is off
+    ///
+    /// Not every MCU can gate its peripherals' clocks.
+    #[allow(dead_code)]
+    pub fn write_off(&self, writer: &mut dyn fmt::Write) -> fmt::Result {
+        writeln!(writer, "{}: clock off", self.name)
+    }
+}
@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Register state report for i.MX RT MCUs
+
+use crate::hw::dump::{Block, Reg, Size::U32};
+use bit_field::BitField;
+use core::{fmt, ptr::read_volatile};
+
+const CCM: Block = Block {
+    name: "CCM",
+    base: 0x400F_C000,
+    regs: &[
+        Reg("CBCDR", 0x14, U32),
+        Reg("CBCMR", 0x18, U32),
+        Reg("CSCMR1", 0x1C, U32),
+        Reg("CSCDR1", 0x24, U32),
+        Reg("CCGR0", 0x68, U32),
+        Reg("CCGR1", 0x6C, U32),
+        Reg("CCGR2", 0x70, U32),
+        Reg("CCGR3", 0x74, U32),
+        Reg("CCGR4", 0x78, U32),
+        Reg("CCGR5", 0x7C, U32),
+        Reg("CCGR6", 0x80, U32),
+        Reg("CCGR7", 0x84, U32),
+    ],
+};
+
+const CCM_ANALOG: Block = Block {
+    name: "CCM_ANALOG",
+    base: 0x400D_8000,
+    regs: &[
+        Reg("PLL_ARM", 0x0, U32),
+        Reg("PLL_USB1", 0x10, U32),
+        Reg("PLL_SYS", 0x30, U32),
+    ],
+};
+
+const LPUART_REGS: &[Reg] = &[
+    Reg("BAUD", 0x10, U32),
+    Reg("STAT", 0x14, U32),
+    Reg("CTRL", 0x18, U32),
+    Reg("FIFO", 0x28, U32),
+    Reg("WATER", 0x2C, U32),
+];
+
+const LPUARTS: &[(Block, (usize, usize))] = &[
+    lpuart("LPUART1", 0x4018_4000, (5, 12)),
+    lpuart("LPUART2", 0x4018_8000, (0, 14)),
+    lpuart("LPUART3", 0x4018_C000, (0, 6)),
+    lpuart("LPUART4", 0x4019_0000, (1, 12)),
+    lpuart("LPUART5", 0x4019_4000, (3, 1)),
+    lpuart("LPUART6", 0x4019_8000, (3, 3)),
+    lpuart("LPUART7", 0x4019_C000, (5, 13)),
+    lpuart("LPUART8", 0x401A_0000, (6, 7)),
+];
+
+const fn lpuart(name: &'static str, base: usize, gate: (usize, usize)) -> (Block, (usize, usize)) {
+    (
+        Block {
+            name,
+            base,
+            regs: LPUART_REGS,
+        },
+        gate,
+    )
+}
+
+const LPSPI_REGS: &[Reg] = &[
+    Reg("CR", 0x10, U32),
+    Reg("SR", 0x14, U32),
+    Reg("IER", 0x18, U32),
+    Reg("CFGR1", 0x24, U32),
+    Reg("CCR", 0x40, U32),
+    Reg("TCR", 0x60, U32),
+];
+
+const LPSPIS: &[(Block, (usize, usize))] = &[
+    lpspi("LPSPI1", 0x4039_4000, (1, 0)),
+    lpspi("LPSPI2", 0x4039_8000, (1, 1)),
+    lpspi("LPSPI3", 0x4039_C000, (1, 2)),
+    lpspi("LPSPI4", 0x403A_0000, (1, 3)),
+];
+
+const fn lpspi(name: &'static str, base: usize, gate: (usize, usize)) -> (Block, (usize, usize)) {
+    (
+        Block {
+            name,
+            base,
+            regs: LPSPI_REGS,
+        },
+        gate,
+    )
+}
+
+const NVIC: Block = Block {
+    name: "NVIC",
+    base: 0xE000_E100,
+    regs: &[
+        Reg("ISER0", 0x0, U32),
+        Reg("ISER1", 0x4, U32),
+        Reg("ISER2", 0x8, U32),
+        Reg("ISER3", 0xC, U32),
+        Reg("ISER4", 0x10, U32),
+    ],
+};
+
+/// Whether a clock gate is on, in any mode
+fn gate_enabled((reg, gate): (usize, usize)) -> bool {
+    let ccgr = unsafe { read_volatile((0x400F_C068 + reg * 4) as *const u32) };
+    ccgr.get_bits(gate * 2..gate * 2 + 2) != 0
+}
+
+/// Write the clock, LPUART, LPSPI, and NVIC registers
+///
+/// Peripherals whose clock gates are off are only listed, since
+/// reading their registers would fault.
+pub(crate) fn dump_state(writer: &mut dyn fmt::Write) -> fmt::Result {
+    CCM.write(writer)?;
+    CCM_ANALOG.write(writer)?;
+    for (block, gate) in LPUARTS.iter().chain(LPSPIS) {
+        if gate_enabled(*gate) {
+            block.write(writer)?;
+        } else {
+            block.write_off(writer)?;
+        }
+    }
+    NVIC.write(writer)
+}
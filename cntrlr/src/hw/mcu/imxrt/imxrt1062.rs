@@ -0,0 +1,35 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! The NXP i.MX RT1062 MCU
+//!
+//! This is an ARM Cortex-M7 microcontroller produced by NXP. It is
+//! used on the [`Teensy 4.0`](`crate::hw::board::teensy_40`) and
+//! [`Teensy 4.1`](`crate::hw::board::teensy_41`) boards.
+
+pub use super::{
+    peripheral::ccm::UartClockSource,
+    peripheral::gpio::{FlexioPin, GpioPin, UartRx, UartTx},
+    peripheral::lpuart::BaudError,
+    Imxrt1062,
+};
+pub use crate::hw::mcu::pio::{FrameBit, ProgrammableIo, Shifter, ShifterMode, Timer, TimerMode};
+
+/// The clock controller
+pub type Ccm = super::peripheral::ccm::Ccm<Imxrt1062>;
+
+/// The DC-DC converter
+pub type Dcdc = super::peripheral::dcdc::Dcdc<Imxrt1062>;
+
+/// A FlexIO module
+pub type Flexio<const N: usize> = super::peripheral::flexio::Flexio<Imxrt1062, N>;
+
+/// A GPIO bank
+pub type Gpio<const N: usize> = super::peripheral::gpio::Gpio<Imxrt1062, N>;
+
+/// A GPIO pin
+pub type Pin<'a, const N: usize, const P: usize> =
+    super::peripheral::gpio::Pin<'a, Imxrt1062, N, P>;
+
+/// An LPUART instance
+pub type Lpuart<T, R, const N: usize> = super::peripheral::lpuart::Lpuart<Imxrt1062, T, R, N>;
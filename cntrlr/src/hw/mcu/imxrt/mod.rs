@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! NXP i.MX RT family microcontrollers
+
+/// Marker for peripheral instances configured for the imxrt1062
+pub struct Imxrt1062;
+
+pub mod peripheral;
+
+#[cfg(mcu = "imxrt1062")]
+pub(crate) mod dump;
+
+pub mod imxrt1062;
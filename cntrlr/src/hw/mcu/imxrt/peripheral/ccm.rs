@@ -0,0 +1,204 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! The Clock Controller Module
+
+use crate::{
+    register::{Register, Reserved},
+    sync::Flag,
+};
+use bit_field::BitField;
+use core::{marker::PhantomData, sync::atomic::Ordering};
+
+#[repr(C)]
+struct CcmRegs {
+    ccr: Register<u32>,
+    _reserved0: Reserved<u32>,
+    csr: Register<u32>,
+    ccsr: Register<u32>,
+    cacrr: Register<u32>,
+    cbcdr: Register<u32>,
+    cbcmr: Register<u32>,
+    cscmr1: Register<u32>,
+    cscmr2: Register<u32>,
+    cscdr1: Register<u32>,
+    cs1cdr: Register<u32>,
+    _reserved1: [Reserved<u32>; 7],
+    cdhipr: Register<u32>,
+    _reserved2: [Reserved<u32>; 7],
+    ccgr: [Register<u32>; 8],
+}
+
+#[repr(C)]
+struct CcmAnalogRegs {
+    pll_arm: Register<u32>,
+}
+
+/// The handle to the CCM
+pub struct Ccm<M> {
+    regs: &'static mut CcmRegs,
+    analog: &'static mut CcmAnalogRegs,
+    _mcu: PhantomData<M>,
+}
+
+/// A peripheral whose clock is gated by the CCM
+pub trait Gated {
+    /// The CCGR register, and the gate within that register
+    const GATE: (usize, usize);
+}
+
+/// The source of the UART clock
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UartClockSource {
+    /// The 80MHz output of the USB PLL
+    Pll3,
+
+    /// The 24MHz crystal oscillator
+    Oscillator,
+}
+
+static LOCK: Flag = Flag::new(false);
+
+#[cfg(any(doc, mcu = "imxrt1062"))]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(mcu = "imxrt1062")))]
+impl super::Peripheral for Ccm<super::super::Imxrt1062> {
+    fn get() -> Option<Self> {
+        unsafe {
+            if LOCK.swap(true, Ordering::Acquire) {
+                None
+            } else {
+                Some(Self {
+                    regs: &mut *(0x400F_C000 as *mut _),
+                    analog: &mut *(0x400D_8000 as *mut _),
+                    _mcu: PhantomData,
+                })
+            }
+        }
+    }
+}
+
+impl<M> Ccm<M>
+where
+    Ccm<M>: super::Peripheral,
+{
+    /// Get the handle to the CCM
+    ///
+    /// Returns `None` if the CCM is already in use.
+    pub fn get() -> Option<Self> {
+        super::Peripheral::get()
+    }
+}
+
+impl<M> Ccm<M> {
+    /// Turn on the clock to a peripheral
+    ///
+    /// The clock is left on in every mode except STOP.
+    pub fn enable_gate<P: Gated>(&mut self) {
+        let (reg, gate) = P::GATE;
+        self.regs.ccgr[reg].update(|ccgr| {
+            ccgr.set_bits(gate * 2..gate * 2 + 2, 0b11);
+        });
+    }
+
+    /// Turn off the clock to a peripheral
+    pub fn disable_gate<P: Gated>(&mut self) {
+        let (reg, gate) = P::GATE;
+        self.regs.ccgr[reg].update(|ccgr| {
+            ccgr.set_bits(gate * 2..gate * 2 + 2, 0b00);
+        });
+    }
+
+    /// Run the core from the ARM PLL
+    ///
+    /// The PLL runs at `24MHz * mult / 2`, which is divided by
+    /// `div_arm` for the ARM clock and `div_ahb` for the AHB clock,
+    /// which clocks the core. The IPG clock, which clocks most
+    /// peripherals, is the AHB clock divided by `div_ipg`.
+    ///
+    /// While the PLL is reconfigured, the core runs from the 24MHz
+    /// oscillator. The core voltage must already be high enough for
+    /// the new clock.
+    pub fn use_arm_pll(&mut self, mult: u32, div_arm: u32, div_ahb: u32, div_ipg: u32) {
+        assert!((54..=108).contains(&mult));
+        assert!((1..=8).contains(&div_arm));
+        assert!((1..=8).contains(&div_ahb));
+        assert!((1..=4).contains(&div_ipg));
+
+        // Switch to the oscillator through PERIPH_CLK2
+        self.regs.cbcdr.update(|cbcdr| {
+            cbcdr.set_bits(27..30, 0);
+        });
+        self.regs.cbcmr.update(|cbcmr| {
+            cbcmr.set_bits(12..14, 1);
+        });
+        while self.regs.cdhipr.read().get_bit(3) {}
+        self.regs.cbcdr.update(|cbcdr| {
+            cbcdr.set_bit(25, true);
+        });
+        while self.regs.cdhipr.read().get_bit(5) {}
+
+        // Relock the PLL at its new rate
+        let mut pll_arm = 0;
+        pll_arm.set_bits(0..7, mult);
+        pll_arm.set_bit(13, true);
+        if self.analog.pll_arm.read() & 0x8000_207F != pll_arm | 1 << 31 {
+            self.analog.pll_arm.write(1 << 12);
+            self.analog.pll_arm.write(pll_arm);
+            while !self.analog.pll_arm.read().get_bit(31) {}
+        }
+
+        self.regs.cacrr.write(div_arm - 1);
+        while self.regs.cdhipr.read().get_bit(16) {}
+        self.regs.cbcdr.update(|cbcdr| {
+            cbcdr.set_bits(10..13, div_ahb - 1);
+        });
+        while self.regs.cdhipr.read().get_bit(1) {}
+        self.regs.cbcdr.update(|cbcdr| {
+            cbcdr.set_bits(8..10, div_ipg - 1);
+        });
+
+        // Switch back to the ARM PLL through PRE_PERIPH_CLK
+        self.regs.cbcmr.update(|cbcmr| {
+            cbcmr.set_bits(18..20, 3);
+        });
+        self.regs.cbcdr.update(|cbcdr| {
+            cbcdr.set_bit(25, false);
+        });
+        while self.regs.cdhipr.read().get_bit(5) {}
+    }
+
+    /// Set the dividers of the FlexIO2 clock
+    ///
+    /// The clock is taken from the 480MHz output of the USB PLL, and
+    /// divided by `pred` and then `podf`, which can each be 1 to 8.
+    /// The FlexIO2 gate should be off while the clock is changed.
+    pub fn set_flexio2_clock(&mut self, pred: u32, podf: u32) {
+        assert!((1..=8).contains(&pred));
+        assert!((1..=8).contains(&podf));
+        self.regs.cs1cdr.update(|cs1cdr| {
+            cs1cdr.set_bits(9..12, pred - 1);
+            cs1cdr.set_bits(25..28, podf - 1);
+        });
+        self.regs.cscmr2.update(|cscmr2| {
+            cscmr2.set_bits(19..21, 3);
+        });
+    }
+
+    /// Set the source and divider of the UART clock
+    ///
+    /// The clock which reaches the UARTs is the source divided by
+    /// `div`, which can be 1 to 64.
+    pub fn set_uart_clock(&mut self, source: UartClockSource, div: u32) {
+        assert!((1..=64).contains(&div));
+        self.regs.cscdr1.update(|cscdr1| {
+            cscdr1.set_bits(0..6, div - 1);
+            cscdr1.set_bit(6, source == UartClockSource::Oscillator);
+        });
+    }
+}
+
+impl<M> Drop for Ccm<M> {
+    fn drop(&mut self) {
+        LOCK.store(false, Ordering::Release);
+    }
+}
@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! The DC-DC converter which supplies the core
+
+use crate::{
+    register::{Register, Reserved},
+    sync::Flag,
+};
+use bit_field::BitField;
+use core::{marker::PhantomData, sync::atomic::Ordering};
+
+#[repr(C)]
+struct DcdcRegs {
+    reg0: Register<u32>,
+    _reserved0: [Reserved<u32>; 2],
+    reg3: Register<u32>,
+}
+
+/// The handle to the DCDC
+pub struct Dcdc<M> {
+    regs: &'static mut DcdcRegs,
+    _mcu: PhantomData<M>,
+}
+
+static LOCK: Flag = Flag::new(false);
+
+#[cfg(any(doc, mcu = "imxrt1062"))]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(mcu = "imxrt1062")))]
+impl super::Peripheral for Dcdc<super::super::Imxrt1062> {
+    fn get() -> Option<Self> {
+        unsafe {
+            if LOCK.swap(true, Ordering::Acquire) {
+                None
+            } else {
+                Some(Self {
+                    regs: &mut *(0x4008_0000 as *mut _),
+                    _mcu: PhantomData,
+                })
+            }
+        }
+    }
+}
+
+impl<M> Dcdc<M>
+where
+    Dcdc<M>: super::Peripheral,
+{
+    /// Get the handle to the DCDC
+    ///
+    /// Returns `None` if the DCDC is already in use.
+    pub fn get() -> Option<Self> {
+        super::Peripheral::get()
+    }
+}
+
+impl<M> Dcdc<M> {
+    /// The target core voltage, in millivolts
+    pub fn voltage(&self) -> u32 {
+        800 + self.regs.reg3.read().get_bits(0..5) * 25
+    }
+
+    /// Set the target core voltage, in millivolts
+    ///
+    /// The voltage is rounded down to a step of 25mV, from 800mV to
+    /// 1575mV. This waits for the output to settle.
+    pub fn set_voltage(&mut self, millivolts: u32) {
+        assert!((800..=1575).contains(&millivolts));
+        self.regs.reg3.update(|reg3| {
+            reg3.set_bits(0..5, (millivolts - 800) / 25);
+        });
+        while !self.regs.reg0.read().get_bit(31) {}
+    }
+}
+
+impl<M> Drop for Dcdc<M> {
+    fn drop(&mut self) {
+        LOCK.store(false, Ordering::Release);
+    }
+}
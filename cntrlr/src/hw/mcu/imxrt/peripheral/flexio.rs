@@ -0,0 +1,307 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! The flexible IO module
+//!
+//! FlexIO is the i.MX RT's [programmable IO](crate::hw::mcu::pio).
+//! The modules differ in how many shifters, timers, and pins they
+//! have, which can be read from the module.
+
+use super::ccm::Gated;
+use crate::{
+    hw::mcu::pio::{
+        FrameBit, ProgrammableIo, Shifter, ShifterMode, Timer, TimerDisable, TimerEnable,
+        TimerMode, TimerOutput, Trigger,
+    },
+    register::{Register, Reserved},
+    sync::Flag,
+};
+use bit_field::BitField;
+use core::{marker::PhantomData, sync::atomic::Ordering};
+
+#[repr(C)]
+struct FlexioRegs {
+    verid: Register<u32>,
+    param: Register<u32>,
+    ctrl: Register<u32>,
+    pin: Register<u32>,
+    shiftstat: Register<u32>,
+    shifterr: Register<u32>,
+    timstat: Register<u32>,
+    _reserved0: Reserved<u32>,
+    shiftsien: Register<u32>,
+    shifteien: Register<u32>,
+    timien: Register<u32>,
+    _reserved1: Reserved<u32>,
+    shiftsden: Register<u32>,
+    _reserved2: [Reserved<u32>; 19],
+    shiftctl: [Register<u32>; 8],
+    _reserved3: [Reserved<u32>; 24],
+    shiftcfg: [Register<u32>; 8],
+    _reserved4: [Reserved<u32>; 56],
+    shiftbuf: [Register<u32>; 8],
+    _reserved5: [Reserved<u32>; 24],
+    shiftbufbis: [Register<u32>; 8],
+    _reserved6: [Reserved<u32>; 88],
+    timctl: [Register<u32>; 8],
+    _reserved7: [Reserved<u32>; 24],
+    timcfg: [Register<u32>; 8],
+    _reserved8: [Reserved<u32>; 24],
+    timcmp: [Register<u32>; 8],
+}
+
+/// A FlexIO module
+///
+/// The modules are numbered from 1, as they are in the reference
+/// manual. Pins are numbered as FlexIO pins (`FLEXIOn_FLEXIOmm`
+/// in the reference manual), and must be muxed to the module with
+/// [`Pin::set_mux`](super::gpio::Pin::set_mux), or `into_flexio` for
+/// FlexIO2.
+pub struct Flexio<M, const N: usize> {
+    regs: &'static mut FlexioRegs,
+    _mcu: PhantomData<M>,
+}
+
+static LOCKS: [Flag; 3] = [Flag::new(false), Flag::new(false), Flag::new(false)];
+
+macro_rules! get {
+    ($i:literal, $a:literal, $gate:expr) => {
+        #[cfg(any(doc, mcu = "imxrt1062"))]
+        #[cfg_attr(feature = "doc-cfg", doc(cfg(mcu = "imxrt1062")))]
+        impl super::Peripheral for Flexio<super::super::Imxrt1062, $i> {
+            fn get() -> Option<Self> {
+                unsafe {
+                    if LOCKS[$i - 1].swap(true, Ordering::Acquire) {
+                        None
+                    } else {
+                        Some(Self {
+                            regs: &mut *($a as *mut _),
+                            _mcu: PhantomData,
+                        })
+                    }
+                }
+            }
+        }
+
+        impl Gated for Flexio<super::super::Imxrt1062, $i> {
+            const GATE: (usize, usize) = $gate;
+        }
+    };
+}
+
+get!(1, 0x401A_C000, (5, 1));
+get!(2, 0x401B_0000, (3, 0));
+get!(3, 0x4202_0000, (7, 3));
+
+impl<M, const N: usize> Flexio<M, N>
+where
+    Flexio<M, N>: super::Peripheral,
+{
+    /// Get the handle to a FlexIO module
+    ///
+    /// Returns `None` if the module is already in use. Its clock must
+    /// already be enabled in the CCM.
+    pub fn get() -> Option<Self> {
+        super::Peripheral::get()
+    }
+}
+
+impl<M, const N: usize> Flexio<M, N> {
+    /// Enable the module to interrupt the CPU when a shifter's buffer
+    /// is ready to be written or read
+    pub fn enable_shifter_intr(&mut self, shifter: usize) {
+        self.regs.shiftsien.update(|shiftsien| {
+            shiftsien.set_bit(shifter, true);
+        });
+    }
+
+    /// Disable the interrupt from a shifter
+    pub fn disable_shifter_intr(&mut self, shifter: usize) {
+        self.regs.shiftsien.update(|shiftsien| {
+            shiftsien.set_bit(shifter, false);
+        });
+    }
+
+    /// Whether a shifter's buffer is ready to be written or read
+    ///
+    /// A transmitting shifter's buffer is ready once the shifter has
+    /// taken the last word from it, and a recieving shifter's once it
+    /// holds a word.
+    pub fn shifter_ready(&self, shifter: usize) -> bool {
+        self.regs.shiftstat.read().get_bit(shifter)
+    }
+
+    /// Check for and clear an error on a shifter
+    ///
+    /// A recieving shifter has an error when a word is recieved
+    /// before the last was read, or a word's stop bit is wrong.
+    pub fn check_shifter_error(&mut self, shifter: usize) -> bool {
+        let error = self.regs.shifterr.read().get_bit(shifter);
+        if error {
+            self.regs.shifterr.write(1 << shifter);
+        }
+        error
+    }
+}
+
+impl<M, const N: usize> ProgrammableIo for Flexio<M, N> {
+    fn shifters(&self) -> usize {
+        self.regs.param.read().get_bits(0..8) as usize
+    }
+
+    fn timers(&self) -> usize {
+        self.regs.param.read().get_bits(8..16) as usize
+    }
+
+    fn enable(&mut self) {
+        self.regs.ctrl.update(|ctrl| {
+            ctrl.set_bit(0, true);
+        });
+    }
+
+    fn set_shifter(&mut self, index: usize, shifter: Shifter) {
+        assert!(index < self.shifters());
+        assert!(shifter.timer < self.timers());
+
+        let frame_bit = |bit| match bit {
+            FrameBit::None => 0,
+            FrameBit::Low => 2,
+            FrameBit::High => 3,
+        };
+        let mut cfg = 0;
+        cfg.set_bits(0..2, frame_bit(shifter.start));
+        if shifter.start == FrameBit::None && shifter.load_on_shift {
+            cfg.set_bits(0..2, 1);
+        }
+        cfg.set_bits(4..6, frame_bit(shifter.stop));
+        self.regs.shiftcfg[index].write(cfg);
+
+        let mut ctl = 0;
+        ctl.set_bits(
+            0..3,
+            match shifter.mode {
+                ShifterMode::Disabled => 0,
+                ShifterMode::Receive => 1,
+                ShifterMode::Transmit => 2,
+            },
+        );
+        ctl.set_bits(8..13, shifter.pin as u32);
+        if shifter.mode == ShifterMode::Transmit {
+            ctl.set_bits(16..18, 3);
+        }
+        ctl.set_bit(23, shifter.falling_edge);
+        ctl.set_bits(24..27, shifter.timer as u32);
+        self.regs.shiftctl[index].write(ctl);
+    }
+
+    fn set_timer(&mut self, index: usize, timer: Timer) {
+        assert!(index < self.timers());
+
+        let mut cfg = 0;
+        cfg.set_bit(1, timer.start_bit);
+        cfg.set_bits(4..6, if timer.stop_bit { 2 } else { 0 });
+        cfg.set_bits(
+            8..11,
+            match timer.enable {
+                TimerEnable::Always => 0,
+                TimerEnable::Previous => 1,
+                TimerEnable::Trigger => 2,
+                TimerEnable::PinRising => 4,
+            },
+        );
+        cfg.set_bits(
+            12..15,
+            match timer.disable {
+                TimerDisable::Never => 0,
+                TimerDisable::Previous => 1,
+                TimerDisable::Compare => 2,
+            },
+        );
+        cfg.set_bits(16..19, if timer.reset_on_pin { 4 } else { 0 });
+        cfg.set_bits(
+            24..26,
+            match timer.output {
+                TimerOutput::High => 0,
+                TimerOutput::Low => 1,
+                TimerOutput::HighOnReset => 2,
+            },
+        );
+        self.regs.timcfg[index].write(cfg);
+        self.regs.timcmp[index].write(timer.compare as u32);
+
+        let mut ctl = 0;
+        ctl.set_bits(
+            0..2,
+            match timer.mode {
+                TimerMode::Disabled => 0,
+                TimerMode::Baud => 1,
+                TimerMode::Pwm => 2,
+                TimerMode::Counter => 3,
+            },
+        );
+        if let Some(pin) = timer.pin {
+            ctl.set_bits(8..13, pin as u32);
+            ctl.set_bit(7, timer.pin_inverted);
+            if timer.pin_output {
+                ctl.set_bits(16..18, 3);
+            }
+        }
+        match timer.trigger {
+            // The shifter's status flag is set while its buffer is
+            // empty, so it triggers when low.
+            Some(Trigger::Shifter(shifter)) => {
+                assert!(shifter < self.shifters());
+                ctl.set_bit(22, true);
+                ctl.set_bit(23, true);
+                ctl.set_bits(24..30, shifter as u32 * 4 + 1);
+            }
+            Some(Trigger::Pin(pin)) => {
+                ctl.set_bit(22, true);
+                ctl.set_bits(24..30, pin as u32 * 2);
+            }
+            None => {}
+        }
+        self.regs.timctl[index].write(ctl);
+    }
+
+    fn write_data(&mut self, shifter: usize, data: u32) -> bool {
+        if self.regs.shiftstat.read().get_bit(shifter) {
+            self.regs.shiftbuf[shifter].write(data);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn write_data_msb_first(&mut self, shifter: usize, data: u32) -> bool {
+        if self.regs.shiftstat.read().get_bit(shifter) {
+            self.regs.shiftbufbis[shifter].write(data);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn read_data(&mut self, shifter: usize) -> Option<u32> {
+        if self.regs.shiftstat.read().get_bit(shifter) {
+            Some(self.regs.shiftbuf[shifter].read())
+        } else {
+            None
+        }
+    }
+
+    fn read_data_msb_first(&mut self, shifter: usize) -> Option<u32> {
+        if self.regs.shiftstat.read().get_bit(shifter) {
+            Some(self.regs.shiftbufbis[shifter].read())
+        } else {
+            None
+        }
+    }
+}
+
+impl<M, const N: usize> Drop for Flexio<M, N> {
+    fn drop(&mut self) {
+        self.regs.ctrl.write(0);
+        LOCKS[N - 1].store(false, Ordering::Release);
+    }
+}
@@ -0,0 +1,336 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! GPIO banks and pin muxing
+//!
+//! On the i.MX RT family, each pin is muxed to a peripheral by the
+//! IOMUXC, which also sets its pad's electrical configuration. The
+//! GPIO banks here are the standard-speed banks, GPIO1 through GPIO4.
+
+use super::{super::Imxrt1062, ccm::Gated};
+use crate::{
+    digital::Pull,
+    register::{Register, Reserved},
+    sync::{without_interrupts, Flag},
+};
+use bit_field::BitField;
+use core::{marker::PhantomData, sync::atomic::Ordering};
+
+#[repr(C)]
+struct GpioRegs {
+    dr: Register<u32>,
+    gdir: Register<u32>,
+    psr: Register<u32>,
+    icr1: Register<u32>,
+    icr2: Register<u32>,
+    imr: Register<u32>,
+    isr: Register<u32>,
+    edge_sel: Register<u32>,
+    _reserved0: [Reserved<u32>; 25],
+    dr_set: Register<u32>,
+    dr_clear: Register<u32>,
+    dr_toggle: Register<u32>,
+}
+
+/// The base address of the IOMUXC
+const IOMUXC: usize = 0x401F_8000;
+
+/// The offset from a pin's mux register to its pad control register
+const PAD_OFFSET: usize = 0x1F0;
+
+/// The mux register offset of the first pad in each pad group
+const EMC: usize = 0x014;
+const AD_B0: usize = 0x0BC;
+const AD_B1: usize = 0x0FC;
+const B0: usize = 0x13C;
+const B1: usize = 0x17C;
+const SD_B0: usize = 0x1BC;
+const SD_B1: usize = 0x1D4;
+
+/// The IOMUXC mux register offset for a GPIO pin
+const fn mux_offset(gpio: usize, pin: usize) -> usize {
+    let (group, pad) = match (gpio, pin) {
+        (1, 0..=15) => (AD_B0, pin),
+        (1, _) => (AD_B1, pin - 16),
+        (2, 0..=15) => (B0, pin),
+        (2, _) => (B1, pin - 16),
+        (3, 0..=11) => (SD_B1, pin),
+        (3, 12..=17) => (SD_B0, pin - 12),
+        (3, _) => (EMC, pin + 14),
+        (_, _) => (EMC, pin),
+    };
+    group + pad * 4
+}
+
+/// A GPIO bank
+///
+/// GPIO1 is `Gpio<1>`, GPIO2 is `Gpio<2>`, etc.
+pub struct Gpio<M, const N: usize> {
+    pins: [Flag; 32],
+    regs: *mut GpioRegs,
+    _mcu: PhantomData<M>,
+}
+
+unsafe impl<M, const N: usize> Send for Gpio<M, N> {}
+unsafe impl<M, const N: usize> Sync for Gpio<M, N> {}
+
+/// A single pin from a GPIO bank
+pub struct Pin<'a, M, const N: usize, const P: usize> {
+    mux: &'static mut Register<u32>,
+    pad: &'static mut Register<u32>,
+    port: &'a Gpio<M, N>,
+}
+
+static LOCKS: [Flag; 4] = [
+    Flag::new(false),
+    Flag::new(false),
+    Flag::new(false),
+    Flag::new(false),
+];
+
+macro_rules! get {
+    ($i:literal, $a:literal, $gate:expr) => {
+        #[cfg(any(doc, mcu = "imxrt1062"))]
+        #[cfg_attr(feature = "doc-cfg", doc(cfg(mcu = "imxrt1062")))]
+        impl super::Peripheral for Gpio<Imxrt1062, $i> {
+            fn get() -> Option<Self> {
+                if LOCKS[$i - 1].swap(true, Ordering::Acquire) {
+                    None
+                } else {
+                    Some(Self {
+                        pins: Default::default(),
+                        regs: $a as *mut _,
+                        _mcu: PhantomData,
+                    })
+                }
+            }
+        }
+
+        impl Gated for Gpio<Imxrt1062, $i> {
+            const GATE: (usize, usize) = $gate;
+        }
+    };
+}
+
+get!(1, 0x401B_8000, (1, 13));
+get!(2, 0x401B_C000, (0, 15));
+get!(3, 0x401C_0000, (2, 13));
+get!(4, 0x401C_4000, (3, 6));
+
+impl<M, const N: usize> Gpio<M, N>
+where
+    Gpio<M, N>: super::Peripheral,
+{
+    /// Get the handle to a GPIO bank
+    ///
+    /// Returns `None` if the bank is already in use.
+    pub fn get() -> Option<Self> {
+        super::Peripheral::get()
+    }
+}
+
+impl<M, const N: usize> Gpio<M, N> {
+    /// Get a pin from this GPIO bank
+    ///
+    /// Returns `None` if the pin is already in use
+    pub fn pin<const P: usize>(&self) -> Option<Pin<M, N, P>> {
+        if P >= 32 || self.pins[P].swap(true, Ordering::Acquire) {
+            None
+        } else {
+            let mux = IOMUXC + mux_offset(N, P);
+            unsafe {
+                Some(Pin {
+                    mux: &mut *(mux as *mut _),
+                    pad: &mut *((mux + PAD_OFFSET) as *mut _),
+                    port: self,
+                })
+            }
+        }
+    }
+
+    /// Read the input state of every pin in the bank
+    pub fn read_all(&self) -> u32 {
+        unsafe { (*self.regs).psr.read() }
+    }
+
+    /// Set the output state of the pins in `mask`
+    ///
+    /// All pins are updated by a single store.
+    pub fn write_all(&self, mask: u32, value: u32) {
+        without_interrupts(|| unsafe {
+            (*self.regs).dr.update(|dr| {
+                *dr = *dr & !mask | value & mask;
+            });
+        });
+    }
+}
+
+impl<M, const N: usize> Drop for Gpio<M, N> {
+    fn drop(&mut self) {
+        LOCKS[N - 1].store(false, Ordering::Release);
+    }
+}
+
+impl<M, const N: usize, const P: usize> Pin<'_, M, N, P> {
+    /// Use this pin as a GPIO
+    pub fn into_gpio(mut self) -> GpioPin<Self> {
+        self.set_mux(5);
+        GpioPin(self)
+    }
+
+    /// Select one of this pin's functions by its mux number
+    ///
+    /// The function of each mux number is specific to the pin. Where
+    /// the pin has a type-checked conversion for a function, that
+    /// should be used instead.
+    pub fn set_mux(&mut self, mux: u32) {
+        assert!(mux < 8);
+        self.mux.write(mux);
+    }
+
+    /// Set the output drive strength of this pin
+    ///
+    /// The strength is given as the DSE field, from 1 (weakest) to 7
+    /// (strongest). Zero disables the output driver.
+    pub fn set_drive_strength(&mut self, strength: u32) {
+        assert!(strength < 8);
+        self.pad.update(|pad| {
+            pad.set_bits(3..6, strength);
+        });
+    }
+
+    /// The GPIO bank this pin belongs to
+    pub fn port(&self) -> &Gpio<M, N> {
+        self.port
+    }
+}
+
+impl<M, const N: usize, const P: usize> Drop for Pin<'_, M, N, P> {
+    fn drop(&mut self) {
+        self.port.pins[P].store(false, Ordering::Release);
+    }
+}
+
+/// Route a UART pin through its input select register
+///
+/// Pins which can serve the same UART input are chosen between by a
+/// daisy chain register in the IOMUXC.
+fn select_input(offset: usize, daisy: u32) {
+    unsafe {
+        (*((IOMUXC + offset) as *mut Register<u32>)).write(daisy);
+    }
+}
+
+impl Pin<'_, Imxrt1062, 1, 2> {
+    /// Use this pin as a UART transmit pin
+    pub fn into_uart_tx(mut self) -> UartTx<Self> {
+        self.set_mux(2);
+        select_input(0x554, 1);
+        UartTx(self)
+    }
+}
+
+impl Pin<'_, Imxrt1062, 1, 3> {
+    /// Use this pin as a UART recieve pin
+    pub fn into_uart_rx(mut self) -> UartRx<Self> {
+        self.set_mux(2);
+        select_input(0x550, 1);
+        UartRx(self)
+    }
+}
+
+impl<const P: usize> Pin<'_, Imxrt1062, 2, P> {
+    /// Use this pin as a FlexIO2 pin
+    ///
+    /// GPIO2 pin `P` is FlexIO2 pin `P`.
+    pub fn into_flexio(mut self) -> FlexioPin<Self> {
+        self.set_mux(4);
+        FlexioPin(self)
+    }
+}
+
+/// A GPIO pin which is configured for UART recieve
+pub struct UartRx<P>(P);
+
+/// A GPIO pin which is configured for UART transmit
+pub struct UartTx<P>(P);
+
+/// A GPIO pin which is configured as a GPIO
+pub struct GpioPin<P>(P);
+
+/// A GPIO pin which is configured for FlexIO
+pub struct FlexioPin<P>(P);
+
+impl<M, const N: usize, const P: usize> GpioPin<Pin<'_, M, N, P>> {
+    /// Set this pin as high or low
+    pub fn write(&mut self, value: bool) {
+        unsafe {
+            if value {
+                (*self.0.port.regs).dr_set.write(1 << P);
+            } else {
+                (*self.0.port.regs).dr_clear.write(1 << P);
+            }
+        }
+    }
+
+    /// Read the status of this pin
+    pub fn read(&self) -> bool {
+        unsafe { (*self.0.port.regs).psr.read().get_bit(P) }
+    }
+
+    /// Read the state this pin is set to output
+    pub fn read_output(&self) -> bool {
+        unsafe { (*self.0.port.regs).dr.read().get_bit(P) }
+    }
+
+    /// Set whether this pin is an output or an input
+    pub fn set_output(&mut self, output: bool) {
+        without_interrupts(|| unsafe {
+            (*self.0.port.regs).gdir.update(|gdir| {
+                gdir.set_bit(P, output);
+            });
+        });
+    }
+
+    /// Set pullup/down resistors on this pin
+    ///
+    /// Pull-ups are 22kΩ, and pull-downs are 100kΩ. Input hysteresis
+    /// is enabled along with either.
+    pub fn set_pull(&mut self, pull: Option<Pull>) {
+        self.0.pad.update(|pad| {
+            match pull {
+                Some(pull) => {
+                    let pus = match pull {
+                        Pull::Up => 3,
+                        Pull::Down => 0,
+                    };
+                    pad.set_bits(14..16, pus);
+                    pad.set_bit(12, true);
+                    pad.set_bit(13, true);
+                    pad.set_bit(16, true);
+                }
+                None => {
+                    pad.set_bit(12, false);
+                    pad.set_bit(16, false);
+                }
+            };
+        });
+    }
+
+    /// Set whether this pin is open-drain.
+    pub fn set_open_drain(&mut self, open_drain: bool) {
+        self.0.pad.update(|pad| {
+            pad.set_bit(11, open_drain);
+        });
+    }
+
+    /// Set the output drive strength of this pin
+    ///
+    /// See [`Pin::set_drive_strength`].
+    pub fn set_drive_strength(&mut self, strength: u32) {
+        self.0.set_drive_strength(strength);
+    }
+}
+
+impl super::lpuart::UartRx<Imxrt1062, 6> for UartRx<Pin<'_, Imxrt1062, 1, 3>> {}
+impl super::lpuart::UartTx<Imxrt1062, 6> for UartTx<Pin<'_, Imxrt1062, 1, 2>> {}
@@ -0,0 +1,289 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! The low-power UART
+
+use super::ccm::Gated;
+use crate::{register::Register, sync::Flag};
+use bit_field::BitField;
+use core::{marker::PhantomData, mem::ManuallyDrop, sync::atomic::Ordering};
+
+#[repr(C)]
+struct LpuartRegs {
+    verid: Register<u32>,
+    param: Register<u32>,
+    global: Register<u32>,
+    pincfg: Register<u32>,
+    baud: Register<u32>,
+    stat: Register<u32>,
+    ctrl: Register<u32>,
+    data: Register<u32>,
+    match_: Register<u32>,
+    modir: Register<u32>,
+    fifo: Register<u32>,
+    water: Register<u32>,
+}
+
+/// An LPUART
+///
+/// The LPUARTs are numbered from 1, as they are in the reference
+/// manual.
+pub struct Lpuart<M, T, R, const N: usize> {
+    regs: ManuallyDrop<&'static mut LpuartRegs>,
+    tx: ManuallyDrop<T>,
+    rx: ManuallyDrop<R>,
+    mcu: PhantomData<M>,
+}
+
+/// A pin which can be used as an LPUART transmit pin
+pub trait UartTx<M, const N: usize>: Unpin {}
+
+/// A pin which can be used as an LPUART recieve pin
+pub trait UartRx<M, const N: usize>: Unpin {}
+
+/// An error from [`Lpuart::set_baud`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BaudError {
+    /// The baud rate cannot be reached from the UART clock
+    InvalidRate,
+}
+
+static LOCKS: [Flag; 8] = [
+    Flag::new(false),
+    Flag::new(false),
+    Flag::new(false),
+    Flag::new(false),
+    Flag::new(false),
+    Flag::new(false),
+    Flag::new(false),
+    Flag::new(false),
+];
+
+macro_rules! get {
+    ($i:literal, $a:literal, $gate:expr) => {
+        #[cfg(any(doc, mcu = "imxrt1062"))]
+        #[cfg_attr(feature = "doc-cfg", doc(cfg(mcu = "imxrt1062")))]
+        impl super::Peripheral for Lpuart<super::super::Imxrt1062, (), (), $i> {
+            fn get() -> Option<Self> {
+                unsafe {
+                    if LOCKS[$i - 1].swap(true, Ordering::Acquire) {
+                        None
+                    } else {
+                        Some(Self {
+                            regs: ManuallyDrop::new(&mut *($a as *mut _)),
+                            tx: ManuallyDrop::new(()),
+                            rx: ManuallyDrop::new(()),
+                            mcu: PhantomData,
+                        })
+                    }
+                }
+            }
+        }
+
+        impl<T, R> Gated for Lpuart<super::super::Imxrt1062, T, R, $i> {
+            const GATE: (usize, usize) = $gate;
+        }
+    };
+}
+
+get!(1, 0x4018_4000, (5, 12));
+get!(2, 0x4018_8000, (0, 14));
+get!(3, 0x4018_C000, (0, 6));
+get!(4, 0x4019_0000, (1, 12));
+get!(5, 0x4019_4000, (3, 1));
+get!(6, 0x4019_8000, (3, 3));
+get!(7, 0x4019_C000, (5, 13));
+get!(8, 0x401A_0000, (6, 7));
+
+impl<M, const N: usize> Lpuart<M, (), (), N>
+where
+    Lpuart<M, (), (), N>: super::Peripheral,
+{
+    /// Get the handle to an LPUART
+    ///
+    /// Returns `None` if the LPUART is already in use. Its clock must
+    /// already be enabled in the CCM.
+    pub fn get() -> Option<Self> {
+        super::Peripheral::get()
+    }
+}
+
+impl<M, const N: usize> Lpuart<M, (), (), N> {
+    /// Set the baud rate, given the frequency of the UART clock
+    ///
+    /// The oversampling ratio is chosen to bring the rate as close to
+    /// `baud` as possible.
+    pub fn set_baud(&mut self, clock: usize, baud: usize) -> Result<(), BaudError> {
+        if baud == 0 {
+            return Err(BaudError::InvalidRate);
+        }
+
+        let (osr, sbr) = (4..=32)
+            .map(|osr| {
+                let sbr = ((clock + osr * baud / 2) / (osr * baud)).max(1).min(8191);
+                (osr, sbr)
+            })
+            .min_by_key(|&(osr, sbr)| {
+                let actual = clock / (osr * sbr);
+                if actual > baud {
+                    actual - baud
+                } else {
+                    baud - actual
+                }
+            })
+            .unwrap();
+
+        // The actual rate must be within 3% for reliable transfers
+        let actual = clock / (osr * sbr);
+        if actual * 100 < baud * 97 || actual * 100 > baud * 103 {
+            return Err(BaudError::InvalidRate);
+        }
+
+        let mut reg = 0;
+        reg.set_bits(0..13, sbr as u32);
+        reg.set_bit(17, osr < 8);
+        reg.set_bits(24..29, osr as u32 - 1);
+        self.regs.baud.write(reg);
+        Ok(())
+    }
+}
+
+impl<M, T, const N: usize> Lpuart<M, T, (), N> {
+    /// Enable this LPUART as a reciever
+    pub fn enable_rx<R: UartRx<M, N>>(mut self, rx: R) -> Lpuart<M, T, R, N> {
+        self.regs.fifo.update(|fifo| {
+            fifo.set_bit(3, true);
+            fifo.set_bit(14, true);
+        });
+        self.regs.ctrl.update(|ctrl| {
+            ctrl.set_bit(18, true);
+        });
+        unsafe {
+            let regs = ManuallyDrop::new(ManuallyDrop::take(&mut self.regs));
+            let tx = ManuallyDrop::new(ManuallyDrop::take(&mut self.tx));
+            let rx = ManuallyDrop::new(rx);
+            let mcu = self.mcu;
+            ManuallyDrop::drop(&mut self.rx);
+            core::mem::forget(self);
+            Lpuart { regs, tx, rx, mcu }
+        }
+    }
+}
+
+impl<M, R, const N: usize> Lpuart<M, (), R, N> {
+    /// Enable this LPUART as a transmitter
+    pub fn enable_tx<T: UartTx<M, N>>(mut self, tx: T) -> Lpuart<M, T, R, N> {
+        self.regs.fifo.update(|fifo| {
+            fifo.set_bit(7, true);
+            fifo.set_bit(15, true);
+        });
+        self.regs.ctrl.update(|ctrl| {
+            ctrl.set_bit(19, true);
+        });
+        unsafe {
+            let regs = ManuallyDrop::new(ManuallyDrop::take(&mut self.regs));
+            let tx = ManuallyDrop::new(tx);
+            let rx = ManuallyDrop::new(ManuallyDrop::take(&mut self.rx));
+            let mcu = self.mcu;
+            ManuallyDrop::drop(&mut self.tx);
+            core::mem::forget(self);
+            Lpuart { regs, tx, rx, mcu }
+        }
+    }
+}
+
+impl<M, T, R: UartRx<M, N>, const N: usize> Lpuart<M, T, R, N> {
+    /// Read a byte from the LPUART.
+    ///
+    /// Returns `None` if no data is available to be read
+    pub fn read_data(&mut self) -> Option<u8> {
+        let data = self.regs.data.read();
+        if data.get_bit(12) {
+            None
+        } else {
+            Some(data.get_bits(0..8) as u8)
+        }
+    }
+
+    /// Check and clear the overrun flag
+    ///
+    /// The reciever stops while the flag is set, so this must be
+    /// checked before reading.
+    pub fn check_overrun(&mut self) -> bool {
+        if self.regs.stat.read().get_bit(19) {
+            self.regs.stat.write(1 << 19);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Enable the LPUART to interrupt the CPU when the recieve FIFO is
+    /// above the watermark.
+    pub fn enable_rx_intr(&mut self) {
+        self.regs.ctrl.update(|ctrl| {
+            ctrl.set_bit(21, true);
+        });
+    }
+}
+
+impl<M, T: UartTx<M, N>, R, const N: usize> Lpuart<M, T, R, N> {
+    /// Write a byte to the LPUART
+    ///
+    /// Returns `false` if the byte cannot be written
+    pub fn write_data(&mut self, data: u8) -> bool {
+        if self.regs.water.read().get_bits(8..11) >= 4 {
+            false
+        } else {
+            self.regs.data.write(data as u32);
+            true
+        }
+    }
+
+    /// Check whether every byte written has been transmitted
+    pub fn is_transmit_complete(&self) -> bool {
+        self.regs.stat.read().get_bit(22)
+    }
+
+    /// Enable the LPUART to interrupt the CPU when the transmit FIFO
+    /// is at or below the watermark.
+    pub fn enable_tx_intr(&mut self) {
+        self.regs.ctrl.update(|ctrl| {
+            ctrl.set_bit(23, true);
+        });
+    }
+
+    /// Enable the LPUART to interrupt the CPU when transmission is
+    /// complete.
+    pub fn enable_complete_intr(&mut self) {
+        self.regs.ctrl.update(|ctrl| {
+            ctrl.set_bit(22, true);
+        });
+    }
+}
+
+impl<M, T, R, const N: usize> Lpuart<M, T, R, N> {
+    /// Set the FIFO interrupt watermarks. Both the TX and RX
+    /// watermarks must be in the range `0..4`.
+    pub fn set_watermarks(&mut self, tx: u32, rx: u32) {
+        assert!(tx <= 3);
+        assert!(rx <= 3);
+
+        self.regs.water.update(|water| {
+            water.set_bits(0..2, tx);
+            water.set_bits(16..18, rx);
+        });
+    }
+}
+
+impl<M, T, R, const N: usize> Drop for Lpuart<M, T, R, N> {
+    fn drop(&mut self) {
+        self.regs.ctrl.write(0);
+        unsafe {
+            ManuallyDrop::drop(&mut self.tx);
+            ManuallyDrop::drop(&mut self.rx);
+            LOCKS[N - 1].store(false, Ordering::Release);
+        }
+    }
+}
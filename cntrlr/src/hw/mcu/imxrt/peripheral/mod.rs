@@ -0,0 +1,18 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Peripherals for i.MX RT family microcontrollers
+
+pub mod ccm;
+pub mod dcdc;
+pub mod flexio;
+pub mod gpio;
+pub mod lpuart;
+
+/// An i.MX RT peripheral
+pub trait Peripheral: Sized {
+    /// Get the instance of this peripheral
+    ///
+    /// Returns `None` if the peripheral is already in use.
+    fn get() -> Option<Self>;
+}
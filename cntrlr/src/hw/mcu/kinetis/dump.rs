@@ -0,0 +1,202 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Register state report for Kinetis MCUs
+
+use super::peripheral::sim::gate_enabled;
+use crate::hw::dump::{
+    Block, Reg,
+    Size::{U32, U8},
+};
+use core::fmt;
+
+const MCG: Block = Block {
+    name: "MCG",
+    base: 0x4006_4000,
+    regs: &[
+        Reg("C1", 0x0, U8),
+        Reg("C2", 0x1, U8),
+        Reg("C3", 0x2, U8),
+        Reg("C4", 0x3, U8),
+        Reg("C5", 0x4, U8),
+        Reg("C6", 0x5, U8),
+        Reg("S", 0x6, U8),
+    ],
+};
+
+const OSC: Block = Block {
+    name: "OSC",
+    base: 0x4006_5000,
+    regs: &[Reg("CR", 0x0, U8)],
+};
+
+#[cfg(not(mcu = "mkl26z64"))]
+const SIM: Block = Block {
+    name: "SIM",
+    base: 0x4004_7000,
+    regs: &[
+        Reg("SOPT2", 0x1004, U32),
+        Reg("SCGC1", 0x1028, U32),
+        Reg("SCGC2", 0x102C, U32),
+        Reg("SCGC3", 0x1030, U32),
+        Reg("SCGC4", 0x1034, U32),
+        Reg("SCGC5", 0x1038, U32),
+        Reg("SCGC6", 0x103C, U32),
+        Reg("SCGC7", 0x1040, U32),
+        Reg("CLKDIV1", 0x1044, U32),
+        Reg("CLKDIV2", 0x1048, U32),
+    ],
+};
+
+#[cfg(mcu = "mkl26z64")]
+const SIM: Block = Block {
+    name: "SIM",
+    base: 0x4004_7000,
+    regs: &[
+        Reg("SOPT2", 0x1004, U32),
+        Reg("SCGC4", 0x1034, U32),
+        Reg("SCGC5", 0x1038, U32),
+        Reg("SCGC6", 0x103C, U32),
+        Reg("SCGC7", 0x1040, U32),
+        Reg("CLKDIV1", 0x1044, U32),
+    ],
+};
+
+#[cfg(not(mcu = "mkl26z64"))]
+const UART_REGS: &[Reg] = &[
+    Reg("BDH", 0x0, U8),
+    Reg("BDL", 0x1, U8),
+    Reg("C1", 0x2, U8),
+    Reg("C2", 0x3, U8),
+    Reg("S1", 0x4, U8),
+    Reg("S2", 0x5, U8),
+    Reg("C3", 0x6, U8),
+    Reg("C4", 0xA, U8),
+];
+
+#[cfg(mcu = "mkl26z64")]
+const UART_REGS: &[Reg] = &[
+    Reg("BDH", 0x0, U8),
+    Reg("BDL", 0x1, U8),
+    Reg("C1", 0x2, U8),
+    Reg("C2", 0x3, U8),
+    Reg("S1", 0x4, U8),
+    Reg("S2", 0x5, U8),
+    Reg("C3", 0x6, U8),
+];
+
+const UART0: (Block, (usize, usize)) = uart("UART0", 0x4006_A000, (4, 10));
+const UART1: (Block, (usize, usize)) = uart("UART1", 0x4006_B000, (4, 11));
+const UART2: (Block, (usize, usize)) = uart("UART2", 0x4006_C000, (4, 12));
+#[cfg(any(mcu = "mk64fx512", mcu = "mk66fx1m0"))]
+const UART3: (Block, (usize, usize)) = uart("UART3", 0x4006_D000, (4, 13));
+#[cfg(any(mcu = "mk64fx512", mcu = "mk66fx1m0"))]
+const UART4: (Block, (usize, usize)) = uart("UART4", 0x400E_A000, (1, 10));
+#[cfg(mcu = "mk64fx512")]
+const UART5: (Block, (usize, usize)) = uart("UART5", 0x400E_B000, (1, 11));
+
+#[cfg(any(mcu = "mk20dx128", mcu = "mk20dx256", mcu = "mkl26z64"))]
+const UARTS: &[(Block, (usize, usize))] = &[UART0, UART1, UART2];
+#[cfg(mcu = "mk64fx512")]
+const UARTS: &[(Block, (usize, usize))] = &[UART0, UART1, UART2, UART3, UART4, UART5];
+#[cfg(mcu = "mk66fx1m0")]
+const UARTS: &[(Block, (usize, usize))] = &[UART0, UART1, UART2, UART3, UART4];
+
+const fn uart(name: &'static str, base: usize, gate: (usize, usize)) -> (Block, (usize, usize)) {
+    (
+        Block {
+            name,
+            base,
+            regs: UART_REGS,
+        },
+        gate,
+    )
+}
+
+#[cfg(not(mcu = "mkl26z64"))]
+const SPI_REGS: &[Reg] = &[
+    Reg("MCR", 0x0, U32),
+    Reg("TCR", 0x8, U32),
+    Reg("CTAR0", 0xC, U32),
+    Reg("CTAR1", 0x10, U32),
+    Reg("SR", 0x2C, U32),
+    Reg("RSER", 0x30, U32),
+];
+
+#[cfg(mcu = "mkl26z64")]
+const SPI_REGS: &[Reg] = &[
+    Reg("S", 0x0, U8),
+    Reg("BR", 0x1, U8),
+    Reg("C2", 0x2, U8),
+    Reg("C1", 0x3, U8),
+];
+
+#[cfg(not(mcu = "mkl26z64"))]
+const SPI0: (Block, (usize, usize)) = spi("SPI0", 0x4002_C000, (6, 12));
+#[cfg(any(mcu = "mk64fx512", mcu = "mk66fx1m0"))]
+const SPI1: (Block, (usize, usize)) = spi("SPI1", 0x4002_D000, (6, 13));
+#[cfg(any(mcu = "mk64fx512", mcu = "mk66fx1m0"))]
+const SPI2: (Block, (usize, usize)) = spi("SPI2", 0x400A_C000, (3, 12));
+
+#[cfg(any(mcu = "mk20dx128", mcu = "mk20dx256"))]
+const SPIS: &[(Block, (usize, usize))] = &[SPI0];
+#[cfg(any(mcu = "mk64fx512", mcu = "mk66fx1m0"))]
+const SPIS: &[(Block, (usize, usize))] = &[SPI0, SPI1, SPI2];
+#[cfg(mcu = "mkl26z64")]
+const SPIS: &[(Block, (usize, usize))] = &[
+    spi("SPI0", 0x4007_6000, (4, 22)),
+    spi("SPI1", 0x4007_7000, (4, 23)),
+];
+
+const fn spi(name: &'static str, base: usize, gate: (usize, usize)) -> (Block, (usize, usize)) {
+    (
+        Block {
+            name,
+            base,
+            regs: SPI_REGS,
+        },
+        gate,
+    )
+}
+
+#[cfg(mcu = "mk20dx128")]
+const NVIC_REGS: &[Reg] = &[Reg("ISER0", 0x0, U32), Reg("ISER1", 0x4, U32)];
+#[cfg(any(mcu = "mk20dx256", mcu = "mk64fx512"))]
+const NVIC_REGS: &[Reg] = &[
+    Reg("ISER0", 0x0, U32),
+    Reg("ISER1", 0x4, U32),
+    Reg("ISER2", 0x8, U32),
+];
+#[cfg(mcu = "mk66fx1m0")]
+const NVIC_REGS: &[Reg] = &[
+    Reg("ISER0", 0x0, U32),
+    Reg("ISER1", 0x4, U32),
+    Reg("ISER2", 0x8, U32),
+    Reg("ISER3", 0xC, U32),
+];
+#[cfg(mcu = "mkl26z64")]
+const NVIC_REGS: &[Reg] = &[Reg("ISER", 0x0, U32)];
+
+const NVIC: Block = Block {
+    name: "NVIC",
+    base: 0xE000_E100,
+    regs: NVIC_REGS,
+};
+
+/// Write the clock, UART, SPI, and NVIC registers
+///
+/// Peripherals whose clock gates are off are only listed, since
+/// reading their registers would fault.
+pub(crate) fn dump_state(writer: &mut dyn fmt::Write) -> fmt::Result {
+    MCG.write(writer)?;
+    OSC.write(writer)?;
+    SIM.write(writer)?;
+    for (block, gate) in UARTS.iter().chain(SPIS) {
+        if gate_enabled(*gate) {
+            block.write(writer)?;
+        } else {
+            block.write_off(writer)?;
+        }
+    }
+    NVIC.write(writer)
+}
@@ -6,18 +6,55 @@
 //! This is an ARM Cortex-M4 microcontroller produced by NXP. It is
 //! used on the [`Teensy 3.0`](`crate::hw::board::teensy_30`) board.
 
+pub use super::peripheral::dma::{Size as DmaSize, Transfer as DmaTransfer};
 pub use super::peripheral::mcg::OscRange;
 pub use super::peripheral::osc::Osc;
-pub use super::peripheral::port::{Cs, Sck, Sdi, Sdo, UartRx, UartTx};
+pub use super::peripheral::port::{Cs, I2cScl, I2cSda, Sck, Sdi, Sdo, UartRx, UartTx};
 pub use super::peripheral::sim::{PeripheralClockSource, UsbClockSource};
 pub use super::peripheral::wdog::Watchdog;
 
 /// The handle to the SysTick
 pub type SysTick = super::peripheral::systick::SysTick<super::Mk20Dx128>;
 
+/// The handle to an ADC
+pub type Adc<const N: usize> = super::peripheral::adc::Adc<super::Mk20Dx128, N>;
+
+/// The handle to the DMA controller
+pub type Dma = super::peripheral::dma::Edma<super::Mk20Dx128>;
+
+/// The handle to the DMA multiplexer
+pub type DmaMux = super::peripheral::dma::DmaMux<super::Mk20Dx128>;
+
+/// The handle to an FTM
+pub type Ftm<const N: usize> = super::peripheral::ftm::Ftm<super::Mk20Dx128, N>;
+
+/// The handle to the CRC module
+pub type Crc = super::peripheral::crc::Crc<super::Mk20Dx128>;
+
+/// The handle to the LPTMR
+pub type Lptmr = super::peripheral::lptmr::Lptmr<super::Mk20Dx128>;
+
+/// The handle to the TSI
+pub type Tsi = super::peripheral::tsi::Tsi<super::Mk20Dx128>;
+
+/// All of the ungated peripherals
+pub type Peripherals = super::peripheral::Peripherals<super::Mk20Dx128>;
+
 /// The handle to the MCG
 pub type Mcg = super::peripheral::mcg::Mcg<super::Mk20Dx128>;
 
+/// The handle to the PIT
+pub type Pit = super::peripheral::pit::Pit<super::Mk20Dx128>;
+
+/// The handle to the VBAT register file
+pub type Rfvbat = super::peripheral::rfvbat::Rfvbat<super::Mk20Dx128>;
+
+/// The handle to the RTC
+pub type Rtc = super::peripheral::rtc::Rtc<super::Mk20Dx128>;
+
+/// The handle to the PDB
+pub type Pdb = super::peripheral::pdb::Pdb<super::Mk20Dx128>;
+
 /// The current mode of the system clock
 pub type Clock<'a> = super::peripheral::mcg::Clock<'a, super::Mk20Dx128>;
 
@@ -25,6 +62,9 @@ pub type Clock<'a> = super::peripheral::mcg::Clock<'a, super::Mk20Dx128>;
 pub type Pin<'a, const N: usize, const M: usize> =
     super::peripheral::port::Pin<'a, super::Mk20Dx128, N, M>;
 
+/// An I2C instance
+pub type I2c<C, D, const N: usize> = super::peripheral::i2c::I2c<super::Mk20Dx128, C, D, N>;
+
 /// A Port instance
 pub type Port<const N: usize> = super::peripheral::port::Port<super::Mk20Dx128, N>;
 
@@ -33,3 +73,35 @@ pub type Sim = super::peripheral::sim::Sim<super::Mk20Dx128>;
 
 /// A UART instance
 pub type Uart<T, R, const N: usize> = super::peripheral::uart::Uart<super::Mk20Dx128, T, R, N>;
+
+/// The USB controller
+pub type Usb = super::peripheral::usb::Usb<super::Mk20Dx128>;
+
+/// Explicit peripheral clock gating
+///
+/// See [the shared implementation](super::peripheral::power) for how
+/// these interact with peripheral handles.
+pub mod power {
+    pub use super::super::peripheral::power::Error;
+    use super::super::{peripheral::sim::GatedPeripheral, Mk20Dx128};
+
+    /// Turn off the clock to a peripheral
+    pub fn gate_off<P: GatedPeripheral<Mk20Dx128>>() -> Result<(), Error> {
+        super::super::peripheral::power::gate_off::<Mk20Dx128, P>()
+    }
+
+    /// Turn on the clock to a peripheral
+    pub fn gate_on<P: GatedPeripheral<Mk20Dx128>>() {
+        super::super::peripheral::power::gate_on::<Mk20Dx128, P>()
+    }
+
+    /// Whether the clock to a peripheral is on
+    pub fn is_gated_on<P: GatedPeripheral<Mk20Dx128>>() -> bool {
+        super::super::peripheral::power::is_gated_on::<Mk20Dx128, P>()
+    }
+
+    /// Whether a handle to a peripheral is alive
+    pub fn is_in_use<P: GatedPeripheral<Mk20Dx128>>() -> bool {
+        super::super::peripheral::power::is_in_use::<Mk20Dx128, P>()
+    }
+}
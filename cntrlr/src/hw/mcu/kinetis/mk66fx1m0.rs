@@ -6,18 +6,74 @@
 //! This is an ARM Cortex-M4F microcontroller produced by NXP. It is
 //! used on the [`Teensy 3.6`](`crate::hw::board::teensy_36`) board.
 
+pub use super::peripheral::dac::Reference as DacReference;
+pub use super::peripheral::dma::{Size as DmaSize, Transfer as DmaTransfer};
+pub use super::peripheral::flexbus::{
+    ChipSelect, ChipSelectConfig, Error as FlexBusError, PortSize as FlexBusPortSize,
+};
 pub use super::peripheral::mcg::OscRange;
 pub use super::peripheral::osc::Osc;
-pub use super::peripheral::port::{Cs, Sck, Sdi, Sdo, UartRx, UartTx};
-pub use super::peripheral::sim::{PeripheralClockSource, UsbClockSource};
+pub use super::peripheral::port::{
+    CanRx, CanTx, Cs, FlexBusPin, I2cScl, I2cSda, I2sBclk, I2sFs, I2sMclk, I2sTxd, Sck, Sdi, Sdo,
+    UartRx, UartTx,
+};
+pub use super::peripheral::sim::{PeripheralClockSource, UartClockSource, UsbClockSource};
 pub use super::peripheral::wdog::Watchdog;
 
 /// The handle to the SysTick
 pub type SysTick = super::peripheral::systick::SysTick<super::Mk66Fx1M0>;
 
+/// The handle to an ADC
+pub type Adc<const N: usize> = super::peripheral::adc::Adc<super::Mk66Fx1M0, N>;
+
+/// The handle to the comparators
+pub type Cmp = super::peripheral::cmp::Cmp<super::Mk66Fx1M0>;
+
+/// The handle to a DAC
+pub type Dac<const N: usize> = super::peripheral::dac::Dac<super::Mk66Fx1M0, N>;
+
+/// The handle to the DMA controller
+pub type Dma = super::peripheral::dma::Edma<super::Mk66Fx1M0>;
+
+/// The handle to the DMA multiplexer
+pub type DmaMux = super::peripheral::dma::DmaMux<super::Mk66Fx1M0>;
+
+/// The handle to an FTM
+pub type Ftm<const N: usize> = super::peripheral::ftm::Ftm<super::Mk66Fx1M0, N>;
+
+/// The handle to a TPM
+pub type Tpm<const N: usize> = super::peripheral::tpm::Tpm<super::Mk66Fx1M0, N>;
+
+/// The handle to the CRC module
+pub type Crc = super::peripheral::crc::Crc<super::Mk66Fx1M0>;
+
+/// The handle to the LPTMR
+pub type Lptmr = super::peripheral::lptmr::Lptmr<super::Mk66Fx1M0>;
+
+/// The handle to the TSI
+pub type Tsi = super::peripheral::tsi::TsiLite<super::Mk66Fx1M0>;
+
+/// The handle to the FlexBus
+pub type FlexBus = super::peripheral::flexbus::FlexBus<super::Mk66Fx1M0>;
+
+/// All of the ungated peripherals
+pub type Peripherals = super::peripheral::Peripherals<super::Mk66Fx1M0>;
+
 /// The handle to the MCG
 pub type Mcg = super::peripheral::mcg::Mcg<super::Mk66Fx1M0>;
 
+/// The handle to the PIT
+pub type Pit = super::peripheral::pit::Pit<super::Mk66Fx1M0>;
+
+/// The handle to the VBAT register file
+pub type Rfvbat = super::peripheral::rfvbat::Rfvbat<super::Mk66Fx1M0>;
+
+/// The handle to the RTC
+pub type Rtc = super::peripheral::rtc::Rtc<super::Mk66Fx1M0>;
+
+/// The handle to the PDB
+pub type Pdb = super::peripheral::pdb::Pdb<super::Mk66Fx1M0>;
+
 /// The current mode of the system clock
 pub type Clock<'a> = super::peripheral::mcg::Clock<'a, super::Mk66Fx1M0>;
 
@@ -25,6 +81,15 @@ pub type Clock<'a> = super::peripheral::mcg::Clock<'a, super::Mk66Fx1M0>;
 pub type Pin<'a, const N: usize, const M: usize> =
     super::peripheral::port::Pin<'a, super::Mk66Fx1M0, N, M>;
 
+/// A FlexCAN instance
+pub type Can<T, R, const N: usize> = super::peripheral::flexcan::Can<super::Mk66Fx1M0, T, R, N>;
+
+/// An I2C instance
+pub type I2c<C, D, const N: usize> = super::peripheral::i2c::I2c<super::Mk66Fx1M0, C, D, N>;
+
+/// The handle to the I2S module
+pub type I2s<C, B, F, D> = super::peripheral::i2s::I2s<super::Mk66Fx1M0, C, B, F, D>;
+
 /// A Port instance
 pub type Port<const N: usize> = super::peripheral::port::Port<super::Mk66Fx1M0, N>;
 
@@ -36,3 +101,35 @@ pub type Smc = super::peripheral::smc::Smc<super::Mk66Fx1M0>;
 
 /// A UART instance
 pub type Uart<T, R, const N: usize> = super::peripheral::uart::Uart<super::Mk66Fx1M0, T, R, N>;
+
+/// The USB controller
+pub type Usb = super::peripheral::usb::Usb<super::Mk66Fx1M0>;
+
+/// Explicit peripheral clock gating
+///
+/// See [the shared implementation](super::peripheral::power) for how
+/// these interact with peripheral handles.
+pub mod power {
+    pub use super::super::peripheral::power::Error;
+    use super::super::{peripheral::sim::GatedPeripheral, Mk66Fx1M0};
+
+    /// Turn off the clock to a peripheral
+    pub fn gate_off<P: GatedPeripheral<Mk66Fx1M0>>() -> Result<(), Error> {
+        super::super::peripheral::power::gate_off::<Mk66Fx1M0, P>()
+    }
+
+    /// Turn on the clock to a peripheral
+    pub fn gate_on<P: GatedPeripheral<Mk66Fx1M0>>() {
+        super::super::peripheral::power::gate_on::<Mk66Fx1M0, P>()
+    }
+
+    /// Whether the clock to a peripheral is on
+    pub fn is_gated_on<P: GatedPeripheral<Mk66Fx1M0>>() -> bool {
+        super::super::peripheral::power::is_gated_on::<Mk66Fx1M0, P>()
+    }
+
+    /// Whether a handle to a peripheral is alive
+    pub fn is_in_use<P: GatedPeripheral<Mk66Fx1M0>>() -> bool {
+        super::super::peripheral::power::is_in_use::<Mk66Fx1M0, P>()
+    }
+}
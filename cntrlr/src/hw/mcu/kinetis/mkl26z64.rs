@@ -6,17 +6,42 @@
 //! This is an ARM Cortex-M0 microcontroller produced by NXP. It is
 //! used on the [`Teensy LC`](`crate::hw::board::teensy_lc`) board.
 
+pub use super::peripheral::dma::{Size as DmaSize, Transfer as DmaTransfer};
 pub use super::peripheral::mcg::OscRange;
 pub use super::peripheral::osc::Osc;
-pub use super::peripheral::port::{UartRx, UartTx};
+pub use super::peripheral::port::{I2cScl, I2cSda, UartRx, UartTx};
 pub use super::peripheral::sim::{PeripheralClockSource, UartClockSource, UsbClockSource};
 
 /// The handle to the SysTick
 pub type SysTick = super::peripheral::systick::SysTick<super::Mkl26Z64>;
 
+/// The handle to an ADC
+pub type Adc<const N: usize> = super::peripheral::adc::Adc<super::Mkl26Z64, N>;
+
+/// The handle to the DMA controller
+pub type Dma = super::peripheral::dma::Dma<super::Mkl26Z64>;
+
+/// The handle to the DMA multiplexer
+pub type DmaMux = super::peripheral::dma::DmaMux<super::Mkl26Z64>;
+
+/// The handle to the LPTMR
+pub type Lptmr = super::peripheral::lptmr::Lptmr<super::Mkl26Z64>;
+
+/// The handle to the TSI
+pub type Tsi = super::peripheral::tsi::TsiLite<super::Mkl26Z64>;
+
+/// The handle to a TPM
+pub type Tpm<const N: usize> = super::peripheral::tpm::Tpm<super::Mkl26Z64, N>;
+
+/// All of the ungated peripherals
+pub type Peripherals = super::peripheral::Peripherals<super::Mkl26Z64>;
+
 /// The handle to the MCG
 pub type Mcg = super::peripheral::mcg::Mcg<super::Mkl26Z64>;
 
+/// The handle to the PIT
+pub type Pit = super::peripheral::pit::Pit<super::Mkl26Z64>;
+
 /// The current mode of the system clock
 pub type Clock<'a> = super::peripheral::mcg::Clock<'a, super::Mkl26Z64>;
 
@@ -24,6 +49,9 @@ pub type Clock<'a> = super::peripheral::mcg::Clock<'a, super::Mkl26Z64>;
 pub type Pin<'a, const N: usize, const M: usize> =
     super::peripheral::port::Pin<'a, super::Mkl26Z64, N, M>;
 
+/// An I2C instance
+pub type I2c<C, D, const N: usize> = super::peripheral::i2c::I2c<super::Mkl26Z64, C, D, N>;
+
 /// A Port instance
 pub type Port<const N: usize> = super::peripheral::port::Port<super::Mkl26Z64, N>;
 
@@ -32,3 +60,35 @@ pub type Sim = super::peripheral::sim::Sim<super::Mkl26Z64>;
 
 /// A UART instance
 pub type Uart<T, R, const N: usize> = super::peripheral::uart::Uart<super::Mkl26Z64, T, R, N>;
+
+/// The USB controller
+pub type Usb = super::peripheral::usb::Usb<super::Mkl26Z64>;
+
+/// Explicit peripheral clock gating
+///
+/// See [the shared implementation](super::peripheral::power) for how
+/// these interact with peripheral handles.
+pub mod power {
+    pub use super::super::peripheral::power::Error;
+    use super::super::{peripheral::sim::GatedPeripheral, Mkl26Z64};
+
+    /// Turn off the clock to a peripheral
+    pub fn gate_off<P: GatedPeripheral<Mkl26Z64>>() -> Result<(), Error> {
+        super::super::peripheral::power::gate_off::<Mkl26Z64, P>()
+    }
+
+    /// Turn on the clock to a peripheral
+    pub fn gate_on<P: GatedPeripheral<Mkl26Z64>>() {
+        super::super::peripheral::power::gate_on::<Mkl26Z64, P>()
+    }
+
+    /// Whether the clock to a peripheral is on
+    pub fn is_gated_on<P: GatedPeripheral<Mkl26Z64>>() -> bool {
+        super::super::peripheral::power::is_gated_on::<Mkl26Z64, P>()
+    }
+
+    /// Whether a handle to a peripheral is alive
+    pub fn is_in_use<P: GatedPeripheral<Mkl26Z64>>() -> bool {
+        super::super::peripheral::power::is_in_use::<Mkl26Z64, P>()
+    }
+}
@@ -20,6 +20,15 @@ pub struct Mkl26Z64;
 
 pub mod peripheral;
 
+#[cfg(any(
+    mcu = "mk20dx128",
+    mcu = "mk20dx256",
+    mcu = "mk64fx512",
+    mcu = "mk66fx1m0",
+    mcu = "mkl26z64"
+))]
+pub(crate) mod dump;
+
 pub mod mk20dx128;
 pub mod mk20dx256;
 pub mod mk64fx512;
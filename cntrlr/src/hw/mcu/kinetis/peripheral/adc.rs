@@ -0,0 +1,353 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Analog-to-Digital Converter
+//!
+//! The Kinetis ADCs are 16-bit successive-approximation converters,
+//! with hardware averaging of up to 32 samples. The Kinetis K20 and
+//! KL26 have one ADC, and the K64 and K66 have two.
+
+use super::{
+    super::{Mk20Dx128, Mk20Dx256, Mk64Fx512, Mk66Fx1M0, Mkl26Z64},
+    sim::{Gate, GatedPeripheral},
+};
+use crate::register::Register;
+use bit_field::BitField;
+use core::marker::PhantomData;
+
+#[repr(C)]
+struct AdcRegs {
+    sc1a: Register<u32>,
+    sc1b: Register<u32>,
+    cfg1: Register<u32>,
+    cfg2: Register<u32>,
+    ra: Register<u32>,
+    rb: Register<u32>,
+    cv1: Register<u32>,
+    cv2: Register<u32>,
+    sc2: Register<u32>,
+    sc3: Register<u32>,
+    ofs: Register<u32>,
+    pg: Register<u32>,
+    mg: Register<u32>,
+    clpd: Register<u32>,
+    clps: Register<u32>,
+    clp4: Register<u32>,
+    clp3: Register<u32>,
+    clp2: Register<u32>,
+    clp1: Register<u32>,
+    clp0: Register<u32>,
+    _reserved: Register<u32>,
+    clmd: Register<u32>,
+    clms: Register<u32>,
+    clm4: Register<u32>,
+    clm3: Register<u32>,
+    clm2: Register<u32>,
+    clm1: Register<u32>,
+    clm0: Register<u32>,
+}
+
+/// The handle to an ADC
+pub struct Adc<M, const N: usize> {
+    regs: &'static mut AdcRegs,
+    _gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+/// The resolution of a conversion
+#[derive(Clone, Copy, PartialEq)]
+pub enum Resolution {
+    /// An 8-bit conversion
+    Bits8,
+
+    /// A 10-bit conversion
+    Bits10,
+
+    /// A 12-bit conversion
+    Bits12,
+
+    /// A 16-bit conversion
+    Bits16,
+}
+
+/// The clock used to drive the ADC
+#[derive(Clone, Copy, PartialEq)]
+pub enum ClockSource {
+    /// The bus clock
+    Bus,
+
+    /// Half of the bus clock
+    HalfBus,
+
+    /// The alternate clock, which is MCU-specific
+    Alternate,
+
+    /// The ADC's internal asynchronous clock
+    Async,
+}
+
+impl<M, const N: usize> Adc<M, N> {
+    /// Set the resolution of each conversion
+    pub fn set_resolution(&mut self, resolution: Resolution) {
+        let mode = match resolution {
+            Resolution::Bits8 => 0,
+            Resolution::Bits12 => 1,
+            Resolution::Bits10 => 2,
+            Resolution::Bits16 => 3,
+        };
+        self.regs.cfg1.update(|cfg1| {
+            cfg1.set_bits(2..4, mode);
+        });
+    }
+
+    /// Set the ADC clock
+    ///
+    /// The clock source is divided by `2^divide`. Valid divide
+    /// values are `0..4`.
+    pub fn set_clock(&mut self, source: ClockSource, divide: u32) {
+        assert!(divide < 4);
+        let source = match source {
+            ClockSource::Bus => 0,
+            ClockSource::HalfBus => 1,
+            ClockSource::Alternate => 2,
+            ClockSource::Async => 3,
+        };
+        self.regs.cfg1.update(|cfg1| {
+            cfg1.set_bits(0..2, source);
+            cfg1.set_bits(5..7, divide);
+        });
+    }
+
+    /// Set the sample time
+    ///
+    /// If `long` is `None`, the short sample time is used. Otherwise,
+    /// the long sample time is lengthened by 20, 12, 6, or 2 extra
+    /// ADC clocks for values `0..4`.
+    pub fn set_sample_time(&mut self, long: Option<u32>) {
+        self.regs.cfg1.update(|cfg1| {
+            cfg1.set_bit(4, long.is_some());
+        });
+        if let Some(long) = long {
+            assert!(long < 4);
+            self.regs.cfg2.update(|cfg2| {
+                cfg2.set_bits(0..2, long);
+            });
+        }
+    }
+
+    /// Enable or disable the high-speed configuration
+    ///
+    /// High-speed mode adds two ADC clocks to each conversion, but
+    /// allows a faster ADC clock.
+    pub fn set_high_speed(&mut self, high_speed: bool) {
+        self.regs.cfg2.update(|cfg2| {
+            cfg2.set_bit(2, high_speed);
+        });
+    }
+
+    /// Set hardware averaging
+    ///
+    /// Each result is the average of `2^(samples + 2)` conversions,
+    /// for samples values `0..4`. If `samples` is `None`, each result
+    /// is a single conversion.
+    pub fn set_averaging(&mut self, samples: Option<u32>) {
+        self.regs.sc3.update(|sc3| {
+            if let Some(samples) = samples {
+                assert!(samples < 4);
+                sc3.set_bit(2, true);
+                sc3.set_bits(0..2, samples);
+            } else {
+                sc3.set_bit(2, false);
+            }
+            // Preserve the calibration flags
+            sc3.set_bit(6, false);
+            sc3.set_bit(7, false);
+        });
+    }
+
+    /// Calibrate the ADC
+    ///
+    /// Calibration should be run after the clock and averaging are
+    /// configured, and blocks until it is complete. For the best
+    /// results, use the maximum averaging and a clock of at most
+    /// 4MHz. Returns `false` if calibration failed.
+    pub fn calibrate(&mut self) -> bool {
+        self.regs.sc3.update(|sc3| {
+            sc3.set_bit(6, true);
+            sc3.set_bit(7, true);
+        });
+        while self.regs.sc3.read().get_bit(7) {}
+        if self.regs.sc3.read().get_bit(6) {
+            return false;
+        }
+
+        let plus = self.regs.clps.read()
+            + self.regs.clp4.read()
+            + self.regs.clp3.read()
+            + self.regs.clp2.read()
+            + self.regs.clp1.read()
+            + self.regs.clp0.read();
+        self.regs.pg.write((plus / 2) | 0x8000);
+
+        let minus = self.regs.clms.read()
+            + self.regs.clm4.read()
+            + self.regs.clm3.read()
+            + self.regs.clm2.read()
+            + self.regs.clm1.read()
+            + self.regs.clm0.read();
+        self.regs.mg.write((minus / 2) | 0x8000);
+        true
+    }
+
+    /// Start a single-ended conversion on a channel
+    ///
+    /// Valid channels are `0..32`, though many channels are not
+    /// connected on any given MCU. Starting a conversion on channel
+    /// 31 stops the ADC. If `interrupt` is set, the ADC interrupt is
+    /// raised when the conversion completes.
+    pub fn start(&mut self, channel: u32, interrupt: bool) {
+        assert!(channel < 32);
+        let mut sc1 = 0;
+        sc1.set_bits(0..5, channel);
+        sc1.set_bit(6, interrupt);
+        self.regs.sc1a.write(sc1);
+    }
+
+    /// Use hardware triggers to start conversions
+    ///
+    /// While hardware triggers are in use, [`start()`](Self::start)
+    /// does not start a conversion. Conversions are instead started
+    /// by the trigger selected in the SIM.
+    pub fn set_hardware_trigger(&mut self, hardware: bool) {
+        self.regs.sc2.update(|sc2| {
+            sc2.set_bit(6, hardware);
+        });
+    }
+
+    /// Set the channel for a hardware-triggered conversion
+    ///
+    /// `slot` is the `A` (0) or `B` (1) conversion, which is started
+    /// by the matching pre-trigger. If `interrupt` is set, the ADC
+    /// interrupt is raised when the conversion completes.
+    pub fn set_trigger_channel(&mut self, slot: usize, channel: u32, interrupt: bool) {
+        assert!(channel < 32);
+        let mut sc1 = 0;
+        sc1.set_bits(0..5, channel);
+        sc1.set_bit(6, interrupt);
+        match slot {
+            0 => self.regs.sc1a.write(sc1),
+            1 => self.regs.sc1b.write(sc1),
+            _ => panic!("Invalid ADC conversion slot"),
+        }
+    }
+
+    /// Select the `a` or `b` input for channels with two inputs
+    pub fn set_mux_b(&mut self, b: bool) {
+        self.regs.cfg2.update(|cfg2| {
+            cfg2.set_bit(4, b);
+        });
+    }
+
+    /// Check whether the conversion has completed
+    pub fn is_complete(&self) -> bool {
+        self.regs.sc1a.read().get_bit(7)
+    }
+
+    /// Read the result of the last conversion
+    ///
+    /// This clears the conversion complete flag.
+    pub fn result(&self) -> u16 {
+        self.regs.ra.read() as u16
+    }
+
+    /// Read the result of the last `B` conversion
+    ///
+    /// This clears the `B` conversion complete flag.
+    pub fn result_b(&self) -> u16 {
+        self.regs.rb.read() as u16
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx128> for Adc<Mk20Dx128, 0> {
+    const GATE: (usize, usize) = (6, 27);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_B000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx256> for Adc<Mk20Dx256, 0> {
+    const GATE: (usize, usize) = (6, 27);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_B000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for Adc<Mk64Fx512, 0> {
+    const GATE: (usize, usize) = (6, 27);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_B000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for Adc<Mk64Fx512, 1> {
+    const GATE: (usize, usize) = (3, 27);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x400B_B000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Adc<Mk66Fx1M0, 0> {
+    const GATE: (usize, usize) = (6, 27);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_B000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Adc<Mk66Fx1M0, 1> {
+    const GATE: (usize, usize) = (3, 27);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x400B_B000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mkl26Z64> for Adc<Mkl26Z64, 0> {
+    const GATE: (usize, usize) = (6, 27);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_B000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,132 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! High-Speed Analog Comparators
+//!
+//! Every comparator shares one clock gate, so one handle controls
+//! all of them. Each comparator selects its plus and minus inputs
+//! from eight channels, where channel 7 is its own 6-bit DAC. Only
+//! comparators 0 and 1, which the MK64 and MK66 both have, are
+//! supported.
+
+use super::{
+    super::{Mk64Fx512, Mk66Fx1M0},
+    sim::{Gate, GatedPeripheral},
+};
+use crate::register::{Register, Reserved};
+use bit_field::BitField;
+use core::marker::PhantomData;
+
+#[repr(C)]
+struct CmpRegs {
+    cr0: Register<u8>,
+    cr1: Register<u8>,
+    fpr: Register<u8>,
+    scr: Register<u8>,
+    daccr: Register<u8>,
+    muxcr: Register<u8>,
+    _reserved: [Reserved<u8>; 2],
+}
+
+/// The number of supported comparators
+pub const COMPARATORS: usize = 2;
+
+/// The input channel connected to a comparator's DAC
+pub const DAC_INPUT: u8 = 7;
+
+/// The handle to the comparators
+pub struct Cmp<M> {
+    regs: &'static mut [CmpRegs; COMPARATORS],
+    _gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+impl<M> Cmp<M> {
+    /// Enable a comparator on two input channels
+    ///
+    /// The comparator runs continuously, with the smallest amount of
+    /// hysteresis and without filtering.
+    pub fn enable(&mut self, comparator: usize, plus: u8, minus: u8) {
+        assert!(plus < 8 && minus < 8);
+        let regs = &mut self.regs[comparator];
+        regs.cr0.write(0);
+        regs.fpr.write(0);
+        regs.muxcr.update(|muxcr| {
+            muxcr.set_bits(3..6, plus);
+            muxcr.set_bits(0..3, minus);
+        });
+        regs.cr1.update(|cr1| {
+            // EN
+            cr1.set_bit(0, true);
+        });
+    }
+
+    /// Disable a comparator, and its DAC and interrupts
+    pub fn disable(&mut self, comparator: usize) {
+        let regs = &mut self.regs[comparator];
+        regs.cr1.write(0);
+        regs.daccr.write(0);
+        self.enable_interrupt(comparator, false, false);
+    }
+
+    /// Set the level of a comparator's DAC
+    ///
+    /// The DAC divides the supply voltage into 64 steps, and outputs
+    /// `level + 1` of them. Pass `None` to disable it.
+    pub fn set_dac(&mut self, comparator: usize, level: Option<u8>) {
+        let mut daccr: u8 = 0;
+        if let Some(level) = level {
+            assert!(level < 64);
+            // DACEN
+            daccr.set_bit(7, true);
+            // VRSEL, for the supply voltage
+            daccr.set_bit(6, true);
+            daccr.set_bits(0..6, level);
+        }
+        self.regs[comparator].daccr.write(daccr);
+    }
+
+    /// Whether a comparator's plus input is above its minus input
+    pub fn output(&self, comparator: usize) -> bool {
+        self.regs[comparator].scr.read().get_bit(0)
+    }
+
+    /// Enable a comparator's interrupts on rising or falling outputs
+    ///
+    /// Any pending edge is cleared, so that only edges after this
+    /// call interrupt.
+    pub fn enable_interrupt(&mut self, comparator: usize, rising: bool, falling: bool) {
+        let mut scr: u8 = 0;
+        // IER and IEF
+        scr.set_bit(4, rising);
+        scr.set_bit(3, falling);
+        // CFR and CFF are cleared by writing ones
+        scr.set_bit(2, true);
+        scr.set_bit(1, true);
+        self.regs[comparator].scr.write(scr);
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for Cmp<Mk64Fx512> {
+    const GATE: (usize, usize) = (4, 19);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4007_3000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Cmp<Mk66Fx1M0> {
+    const GATE: (usize, usize) = (4, 19);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4007_3000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
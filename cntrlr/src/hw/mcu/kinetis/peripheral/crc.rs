@@ -0,0 +1,164 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Cyclic Redundancy Check
+//!
+//! The CRC module computes 16- or 32-bit CRCs with a programmable
+//! polynomial. It is present on the Kinetis K family, but not on the
+//! Kinetis L family.
+
+use super::{
+    super::{Mk20Dx128, Mk20Dx256, Mk64Fx512, Mk66Fx1M0},
+    sim::{Gate, GatedPeripheral},
+};
+use crate::register::Register;
+use bit_field::BitField;
+use core::{marker::PhantomData, ptr::write_volatile};
+
+#[repr(C)]
+struct CrcRegs {
+    data: Register<u32>,
+    gpoly: Register<u32>,
+    ctrl: Register<u32>,
+}
+
+/// The handle to the CRC module
+pub struct Crc<M> {
+    regs: &'static mut CrcRegs,
+    _gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+/// The width of a CRC
+#[derive(Clone, Copy, PartialEq)]
+pub enum Width {
+    /// A 16-bit CRC
+    Bits16,
+
+    /// A 32-bit CRC
+    Bits32,
+}
+
+/// Bit and byte reordering applied to CRC input or output
+#[derive(Clone, Copy)]
+pub enum Transpose {
+    /// No reordering
+    None,
+
+    /// The bits in each byte are reversed
+    Bits,
+
+    /// The bits in each byte are reversed, and the bytes are reversed
+    BitsAndBytes,
+
+    /// The bytes are reversed
+    Bytes,
+}
+
+impl Transpose {
+    fn bits(self) -> u32 {
+        match self {
+            Transpose::None => 0,
+            Transpose::Bits => 1,
+            Transpose::BitsAndBytes => 2,
+            Transpose::Bytes => 3,
+        }
+    }
+}
+
+impl<M> Crc<M> {
+    /// Configure the CRC and start a new calculation from `seed`
+    ///
+    /// Reflected CRCs should transpose the bits of their input and
+    /// the bits and bytes of their output. For 16-bit CRCs with a
+    /// transposed output, the result is in the upper half of
+    /// [`Self::result()`].
+    pub fn start(
+        &mut self,
+        width: Width,
+        polynomial: u32,
+        seed: u32,
+        input: Transpose,
+        output: Transpose,
+        final_xor: bool,
+    ) {
+        self.regs.ctrl.update(|ctrl| {
+            ctrl.set_bits(30..32, input.bits());
+            ctrl.set_bits(28..30, output.bits());
+            ctrl.set_bit(26, final_xor);
+            ctrl.set_bit(24, width == Width::Bits32);
+        });
+        self.regs.gpoly.write(polynomial);
+        self.regs.ctrl.update(|ctrl| {
+            ctrl.set_bit(25, true);
+        });
+        self.regs.data.write(seed);
+        self.regs.ctrl.update(|ctrl| {
+            ctrl.set_bit(25, false);
+        });
+    }
+
+    /// Add bytes to the current calculation
+    pub fn write(&mut self, data: &[u8]) {
+        // Single-byte writes go to the low byte of the data register.
+        let data_ll = &mut *self.regs as *mut CrcRegs as *mut u8;
+        for &byte in data {
+            unsafe {
+                write_volatile(data_ll, byte);
+            }
+        }
+    }
+
+    /// The result of the current calculation
+    pub fn result(&self) -> u32 {
+        self.regs.data.read()
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx128> for Crc<Mk20Dx128> {
+    const GATE: (usize, usize) = (6, 18);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_2000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx256> for Crc<Mk20Dx256> {
+    const GATE: (usize, usize) = (6, 18);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_2000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for Crc<Mk64Fx512> {
+    const GATE: (usize, usize) = (6, 18);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_2000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Crc<Mk66Fx1M0> {
+    const GATE: (usize, usize) = (6, 18);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_2000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! 12-bit Digital-to-Analog Converter
+//!
+//! Each DAC drives its own dedicated output pin. The data buffer is
+//! not used, so the output follows the first data word. Only the
+//! MK64 and MK66 are supported.
+
+use super::{
+    super::{Mk64Fx512, Mk66Fx1M0},
+    sim::{Gate, GatedPeripheral},
+};
+use crate::register::Register;
+use bit_field::BitField;
+use core::marker::PhantomData;
+
+#[repr(C)]
+struct DacRegs {
+    dat: [[Register<u8>; 2]; 16],
+    sr: Register<u8>,
+    c0: Register<u8>,
+    c1: Register<u8>,
+    c2: Register<u8>,
+}
+
+/// The reference voltage of a DAC
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reference {
+    /// The VREF module's output, about 1.2V
+    Vref,
+
+    /// The analog supply voltage
+    Vdda,
+}
+
+/// The handle to a DAC
+pub struct Dac<M, const N: usize> {
+    regs: &'static mut DacRegs,
+    _gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+impl<M, const N: usize> Dac<M, N> {
+    /// Enable the DAC, with a reference voltage
+    ///
+    /// The output is the reference voltage, scaled by the data
+    /// value over 4096.
+    pub fn enable(&mut self, reference: Reference) {
+        self.regs.c1.write(0);
+        self.regs.c0.update(|c0| {
+            // DACRFS
+            c0.set_bit(6, reference == Reference::Vdda);
+            // DACEN
+            c0.set_bit(7, true);
+        });
+    }
+
+    /// Disable the DAC
+    ///
+    /// The output pin is left floating.
+    pub fn disable(&mut self) {
+        self.regs.c0.update(|c0| {
+            c0.set_bit(7, false);
+        });
+    }
+
+    /// Set the output value
+    ///
+    /// Only the low 12 bits are used.
+    pub fn write(&mut self, value: u16) {
+        // The low byte is written first, as the output only changes
+        // when the high byte is written.
+        self.regs.dat[0][0].write(value.get_bits(0..8) as u8);
+        self.regs.dat[0][1].write(value.get_bits(8..12) as u8);
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for Dac<Mk64Fx512, 0> {
+    const GATE: (usize, usize) = (2, 12);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x400C_C000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for Dac<Mk64Fx512, 1> {
+    const GATE: (usize, usize) = (2, 13);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x400C_D000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Dac<Mk66Fx1M0, 0> {
+    const GATE: (usize, usize) = (2, 12);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x400C_C000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Dac<Mk66Fx1M0, 1> {
+    const GATE: (usize, usize) = (2, 13);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x400C_D000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,548 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Direct Memory Access
+//!
+//! The Kinetis-L MCUs have a simple DMA controller with four
+//! channels. There are no transfer descriptors: each channel has a
+//! single source, destination, and byte count, and stops when the
+//! count reaches zero.
+//!
+//! The K-series MCUs have the enhanced DMA controller, which keeps a
+//! transfer control descriptor for each channel. Only single
+//! descriptors are used here, without minor loop offsets or
+//! scatter-gather, so that both controllers run the same
+//! [`Transfer`]s through the [`Controller`] trait. An eDMA channel
+//! can also repeat a transfer until it is stopped, with
+//! [`Edma::start_circular()`], to stream a double buffer to a
+//! peripheral.
+//!
+//! On both families, the DMA multiplexer selects the peripheral
+//! which requests transfers on each channel.
+
+use super::{
+    super::{Mk20Dx128, Mk20Dx256, Mk64Fx512, Mk66Fx1M0, Mkl26Z64},
+    sim::{Gate, GatedPeripheral},
+};
+use crate::register::{Register, Reserved};
+use bit_field::BitField;
+use core::marker::PhantomData;
+
+#[repr(C)]
+struct ChannelRegs {
+    sar: Register<u32>,
+    dar: Register<u32>,
+    dsr_bcr: Register<u32>,
+    dcr: Register<u32>,
+}
+
+#[repr(C)]
+struct DmaRegs {
+    channels: [ChannelRegs; 4],
+}
+
+#[repr(C)]
+struct EdmaRegs {
+    cr: Register<u32>,
+    es: Register<u32>,
+    _reserved_0: Reserved<u32>,
+    erq: Register<u32>,
+    _reserved_1: Reserved<u32>,
+    eei: Register<u32>,
+    ceei: Register<u8>,
+    seei: Register<u8>,
+    cerq: Register<u8>,
+    serq: Register<u8>,
+    cdne: Register<u8>,
+    ssrt: Register<u8>,
+    cerr: Register<u8>,
+    cint: Register<u8>,
+    _reserved_2: Reserved<u32>,
+    int: Register<u32>,
+    _reserved_3: Reserved<u32>,
+    err: Register<u32>,
+    _reserved_4: Reserved<u32>,
+    hrs: Register<u32>,
+}
+
+/// An eDMA transfer control descriptor
+#[repr(C)]
+struct TcdRegs {
+    saddr: Register<u32>,
+    soff: Register<u16>,
+    attr: Register<u16>,
+    nbytes: Register<u32>,
+    slast: Register<u32>,
+    daddr: Register<u32>,
+    doff: Register<u16>,
+    citer: Register<u16>,
+    dlast_sga: Register<u32>,
+    csr: Register<u16>,
+    biter: Register<u16>,
+}
+
+#[repr(C)]
+struct DmaMuxRegs {
+    chcfg: [Register<u8>; 16],
+}
+
+/// The handle to the Kinetis-L DMA controller
+pub struct Dma<M> {
+    regs: &'static mut DmaRegs,
+    _gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+/// The handle to the K-series enhanced DMA controller
+pub struct Edma<M> {
+    regs: &'static mut EdmaRegs,
+    tcds: &'static mut [TcdRegs; 32],
+    channels: usize,
+    _gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+/// The handle to the DMA multiplexer
+pub struct DmaMux<M> {
+    regs: &'static mut DmaMuxRegs,
+    channels: usize,
+    _gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+/// The size of each read and write in a DMA transfer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Size {
+    /// 8-bit accesses
+    Byte,
+
+    /// 16-bit accesses
+    HalfWord,
+
+    /// 32-bit accesses
+    Word,
+}
+
+impl Size {
+    fn bytes(self) -> usize {
+        match self {
+            Size::Byte => 1,
+            Size::HalfWord => 2,
+            Size::Word => 4,
+        }
+    }
+}
+
+/// The configuration of a DMA transfer
+pub struct Transfer {
+    /// The address data is read from
+    pub source: usize,
+
+    /// Whether the source address is incremented after each read
+    pub source_increment: bool,
+
+    /// The address data is written to
+    pub destination: usize,
+
+    /// Whether the destination address is incremented after each write
+    pub destination_increment: bool,
+
+    /// The size of each read and write
+    pub size: Size,
+
+    /// The number of bytes to transfer
+    ///
+    /// This must be a multiple of the access size, and no more than
+    /// the controller's [`MAX_COUNT`](Controller::MAX_COUNT).
+    pub count: usize,
+
+    /// Transfer one unit each time the channel's request source asks
+    ///
+    /// If false, the whole transfer runs as soon as it is started.
+    pub peripheral_request: bool,
+
+    /// Interrupt when the transfer completes or fails
+    pub interrupt: bool,
+}
+
+/// A DMA controller
+///
+/// Each channel runs one [`Transfer`] at a time.
+pub trait Controller {
+    /// The most bytes a transfer of [`Size::Byte`] accesses can move
+    const MAX_COUNT: usize;
+
+    /// Start a transfer on a channel
+    ///
+    /// Any transfer already running on the channel is stopped.
+    ///
+    /// # Safety
+    /// The DMA controller writes to the destination without regard
+    /// for Rust's borrowing rules. Both address ranges must stay
+    /// valid until the transfer completes or is stopped.
+    unsafe fn start(&mut self, channel: usize, transfer: &Transfer);
+
+    /// Stop the transfer on a channel
+    ///
+    /// The number of bytes which were not transferred is kept, and
+    /// can be read with [`Self::remaining()`].
+    fn stop(&mut self, channel: usize);
+
+    /// Whether the transfer on a channel has completed or failed
+    fn is_done(&self, channel: usize) -> bool;
+
+    /// Whether the transfer on a channel failed
+    ///
+    /// A transfer fails on a bus error, or if it was configured with
+    /// an invalid size or alignment.
+    fn is_error(&self, channel: usize) -> bool;
+
+    /// The number of bytes remaining in the transfer on a channel
+    fn remaining(&self, channel: usize) -> usize;
+
+    /// Clear the done and error flags of a channel
+    fn clear(&mut self, channel: usize);
+}
+
+impl<M> Controller for Dma<M> {
+    const MAX_COUNT: usize = 0xF_FFFF;
+
+    unsafe fn start(&mut self, channel: usize, transfer: &Transfer) {
+        assert!(transfer.count <= Self::MAX_COUNT);
+        let size = match transfer.size {
+            Size::Word => 0,
+            Size::Byte => 1,
+            Size::HalfWord => 2,
+        };
+
+        let regs = &mut self.regs.channels[channel];
+        regs.dcr.write(0);
+        // Writing DONE clears the status flags of any previous
+        // transfer.
+        regs.dsr_bcr.write(1 << 24);
+        regs.sar.write(transfer.source as u32);
+        regs.dar.write(transfer.destination as u32);
+        regs.dsr_bcr.write(transfer.count as u32);
+        regs.dcr.update(|dcr| {
+            dcr.set_bit(31, transfer.interrupt);
+            dcr.set_bit(29, transfer.peripheral_request);
+            dcr.set_bit(22, transfer.source_increment);
+            dcr.set_bits(20..22, size);
+            dcr.set_bit(19, transfer.destination_increment);
+            dcr.set_bits(17..19, size);
+            dcr.set_bit(7, true);
+            if transfer.peripheral_request {
+                dcr.set_bit(30, true);
+            } else {
+                dcr.set_bit(16, true);
+            }
+        });
+    }
+
+    fn stop(&mut self, channel: usize) {
+        self.regs.channels[channel].dcr.update(|dcr| {
+            dcr.set_bit(30, false);
+            dcr.set_bit(31, false);
+        });
+    }
+
+    fn is_done(&self, channel: usize) -> bool {
+        self.regs.channels[channel].dsr_bcr.read().get_bit(24)
+    }
+
+    fn is_error(&self, channel: usize) -> bool {
+        let dsr = self.regs.channels[channel].dsr_bcr.read();
+        dsr.get_bit(28) || dsr.get_bit(29) || dsr.get_bit(30)
+    }
+
+    fn remaining(&self, channel: usize) -> usize {
+        self.regs.channels[channel].dsr_bcr.read().get_bits(0..24) as usize
+    }
+
+    fn clear(&mut self, channel: usize) {
+        self.regs.channels[channel].dsr_bcr.write(1 << 24);
+    }
+}
+
+impl<M> Controller for Edma<M> {
+    // Peripheral requests each move one unit, and the major loop
+    // count has 15 bits.
+    const MAX_COUNT: usize = 0x7FFF;
+
+    unsafe fn start(&mut self, channel: usize, transfer: &Transfer) {
+        self.configure(channel, transfer);
+        self.request(channel, transfer);
+    }
+
+    fn stop(&mut self, channel: usize) {
+        self.regs.cerq.write(channel as u8);
+        self.regs.ceei.write(channel as u8);
+        self.tcds[channel].csr.update(|csr| {
+            csr.set_bit(1, false);
+        });
+    }
+
+    fn is_done(&self, channel: usize) -> bool {
+        self.tcds[channel].csr.read().get_bit(7) || self.is_error(channel)
+    }
+
+    fn is_error(&self, channel: usize) -> bool {
+        self.regs.err.read().get_bit(channel)
+    }
+
+    fn remaining(&self, channel: usize) -> usize {
+        let tcd = &self.tcds[channel];
+        if tcd.csr.read().get_bit(7) {
+            0
+        } else {
+            tcd.citer.read().get_bits(0..15) as usize * tcd.nbytes.read() as usize
+        }
+    }
+
+    fn clear(&mut self, channel: usize) {
+        self.regs.cdne.write(channel as u8);
+        self.regs.cerr.write(channel as u8);
+        self.regs.cint.write(channel as u8);
+    }
+}
+
+impl<M> Edma<M> {
+    /// Start a transfer which repeats until it is stopped
+    ///
+    /// Each time the count runs out, both addresses go back to where
+    /// they started. If the transfer interrupts, it does so at the
+    /// half-way point as well as at the end, so that one half of a
+    /// buffer can be refilled while the other half is read. The
+    /// transfer must be started by peripheral requests.
+    ///
+    /// # Safety
+    /// As with [`Controller::start()`], both address ranges must stay
+    /// valid until the transfer is stopped.
+    pub unsafe fn start_circular(&mut self, channel: usize, transfer: &Transfer) {
+        assert!(transfer.peripheral_request);
+        self.configure(channel, transfer);
+        let rewind = |increment| {
+            if increment {
+                (transfer.count as u32).wrapping_neg()
+            } else {
+                0
+            }
+        };
+        let tcd = &mut self.tcds[channel];
+        tcd.slast.write(rewind(transfer.source_increment));
+        tcd.dlast_sga.write(rewind(transfer.destination_increment));
+        tcd.csr.update(|csr| {
+            csr.set_bit(3, false);
+            csr.set_bit(2, transfer.interrupt);
+        });
+        self.request(channel, transfer);
+    }
+
+    /// Write the descriptor of a transfer, without starting it
+    unsafe fn configure(&mut self, channel: usize, transfer: &Transfer) {
+        assert!(channel < self.channels);
+        let unit = transfer.size.bytes();
+        assert!(transfer.count % unit == 0);
+        let (minor, major) = if transfer.peripheral_request {
+            (unit, transfer.count / unit)
+        } else {
+            (transfer.count, 1)
+        };
+        assert!(major <= Self::MAX_COUNT);
+        let size = match transfer.size {
+            Size::Byte => 0,
+            Size::HalfWord => 1,
+            Size::Word => 2,
+        };
+
+        self.regs.cerq.write(channel as u8);
+        self.regs.ceei.write(channel as u8);
+        let tcd = &mut self.tcds[channel];
+        tcd.csr.write(0);
+        self.regs.cdne.write(channel as u8);
+        self.regs.cerr.write(channel as u8);
+        self.regs.cint.write(channel as u8);
+
+        let offset = |increment| if increment { unit as u16 } else { 0 };
+        tcd.saddr.write(transfer.source as u32);
+        tcd.soff.write(offset(transfer.source_increment));
+        tcd.attr.write(size << 8 | size);
+        tcd.nbytes.write(minor as u32);
+        tcd.slast.write(0);
+        tcd.daddr.write(transfer.destination as u32);
+        tcd.doff.write(offset(transfer.destination_increment));
+        tcd.citer.write(major as u16);
+        tcd.biter.write(major as u16);
+        tcd.dlast_sga.write(0);
+        tcd.csr.update(|csr| {
+            csr.set_bit(1, transfer.interrupt);
+            // Stop taking requests once the count runs out
+            csr.set_bit(3, true);
+        });
+
+        if transfer.interrupt {
+            self.regs.seei.write(channel as u8);
+        }
+    }
+
+    /// Start a configured transfer
+    fn request(&mut self, channel: usize, transfer: &Transfer) {
+        if transfer.peripheral_request {
+            self.regs.serq.write(channel as u8);
+        } else {
+            self.regs.ssrt.write(channel as u8);
+        }
+    }
+}
+
+impl<M> DmaMux<M> {
+    /// Set the request source for a channel
+    ///
+    /// The meaning of each source is MCU-specific. If `source` is
+    /// `None`, the channel has no request source, and only transfers
+    /// which are not started by peripheral requests can run on it.
+    pub fn set_source(&mut self, channel: usize, source: Option<u8>) {
+        assert!(channel < self.channels);
+        let chcfg = &mut self.regs.chcfg[channel];
+        chcfg.write(0);
+        if let Some(source) = source {
+            assert!(source < 64);
+            chcfg.write(source | 0x80);
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mkl26Z64> for Dma<Mkl26Z64> {
+    const GATE: (usize, usize) = (7, 8);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4000_8100 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx128> for Edma<Mk20Dx128> {
+    const GATE: (usize, usize) = (7, 1);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4000_8000 as *mut _),
+            tcds: &mut *(0x4000_9000 as *mut _),
+            channels: 4,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx256> for Edma<Mk20Dx256> {
+    const GATE: (usize, usize) = (7, 1);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4000_8000 as *mut _),
+            tcds: &mut *(0x4000_9000 as *mut _),
+            channels: 16,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for Edma<Mk64Fx512> {
+    const GATE: (usize, usize) = (7, 1);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4000_8000 as *mut _),
+            tcds: &mut *(0x4000_9000 as *mut _),
+            channels: 16,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Edma<Mk66Fx1M0> {
+    const GATE: (usize, usize) = (7, 1);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4000_8000 as *mut _),
+            tcds: &mut *(0x4000_9000 as *mut _),
+            channels: 32,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mkl26Z64> for DmaMux<Mkl26Z64> {
+    const GATE: (usize, usize) = (6, 1);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4002_1000 as *mut _),
+            channels: 4,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx128> for DmaMux<Mk20Dx128> {
+    const GATE: (usize, usize) = (6, 1);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4002_1000 as *mut _),
+            channels: 4,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx256> for DmaMux<Mk20Dx256> {
+    const GATE: (usize, usize) = (6, 1);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4002_1000 as *mut _),
+            channels: 16,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for DmaMux<Mk64Fx512> {
+    const GATE: (usize, usize) = (6, 1);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4002_1000 as *mut _),
+            channels: 16,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for DmaMux<Mk66Fx1M0> {
+    const GATE: (usize, usize) = (6, 1);
+
+    unsafe fn new(gate: Gate) -> Self {
+        // The multiplexer has a channel for each of the 32 DMA
+        // channels, but only the first 16 are mapped here.
+        Self {
+            regs: &mut *(0x4002_1000 as *mut _),
+            channels: 16,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
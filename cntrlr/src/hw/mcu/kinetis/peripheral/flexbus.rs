@@ -0,0 +1,358 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! The FlexBus external bus interface
+//!
+//! FlexBus connects external memories and parallel peripherals to
+//! the K64 and K66 MCUs. Each of its six chip selects maps a region
+//! of the external address space, from `0x6000_0000` to
+//! `0xDFFF_FFFF`, onto the bus with its own port size and
+//! timing. Accesses to an enabled region are then ordinary loads and
+//! stores.
+//!
+//! The bus pins must be switched to FlexBus with `into_flexbus` on
+//! each of the pins used, and off-chip data accesses must be allowed
+//! with `Sim::set_flexbus_security`.
+//!
+//! FlexBus is an asynchronous bus, so it can drive SRAM, flash and
+//! memory-mapped peripherals, but not SDRAM, which needs a clocked
+//! controller with refresh.
+
+use super::{
+    super::{Mk64Fx512, Mk66Fx1M0},
+    sim::{Gate, GatedPeripheral},
+};
+use crate::{
+    register::{Register, Reserved},
+    sync::Flag,
+};
+use bit_field::BitField;
+use core::{
+    default::Default,
+    marker::PhantomData,
+    mem::{align_of, forget, size_of},
+    slice,
+    sync::atomic::Ordering,
+};
+
+#[repr(C)]
+struct ChipSelectRegs {
+    csar: Register<u32>,
+    csmr: Register<u32>,
+    cscr: Register<u32>,
+}
+
+#[repr(C)]
+struct FlexBusRegs {
+    cs: [ChipSelectRegs; 6],
+    _reserved: [Reserved<u32>; 6],
+    cspmcr: Register<u32>,
+}
+
+/// The handle to the FlexBus
+pub struct FlexBus<M> {
+    chip_selects: [Flag; 6],
+    regs: *mut FlexBusRegs,
+    _gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+unsafe impl<M> Send for FlexBus<M> {}
+unsafe impl<M> Sync for FlexBus<M> {}
+
+/// Error type for FlexBus operations
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The chip select cannot be enabled because its number is not
+    /// in `0..6`
+    InvalidChipSelect,
+
+    /// The region cannot be mapped because its size or alignment is
+    /// invalid, or it is outside the external address space
+    InvalidRegion,
+
+    /// The chip select cannot be enabled because a timing value is
+    /// out of range
+    InvalidTiming,
+}
+
+/// The width of the data bus for a chip select
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PortSize {
+    /// An 8-bit data bus
+    Bits8,
+
+    /// A 16-bit data bus
+    Bits16,
+
+    /// A 32-bit data bus
+    Bits32,
+}
+
+/// The configuration of a chip select
+#[derive(Clone, Copy, Debug)]
+pub struct ChipSelectConfig {
+    /// The start of the region, in the external address space
+    pub base: usize,
+
+    /// The size of the region, in bytes
+    ///
+    /// This must be a power of two, and at least 64KiB.
+    pub size: usize,
+
+    /// The width of the device's data bus
+    pub port_size: PortSize,
+
+    /// The number of wait states inserted after the chip select is
+    /// asserted, up to 63
+    pub wait_states: u8,
+
+    /// End each transfer after the wait states, rather than waiting
+    /// for the device to assert `FB_TA`
+    pub auto_acknowledge: bool,
+
+    /// The number of clocks from the address being driven to the chip
+    /// select being asserted, up to 3
+    pub address_setup: u8,
+
+    /// The number of clocks the address is held after a read, up to 3
+    pub read_hold: u8,
+
+    /// The number of clocks the address and data are held after a
+    /// write, up to 3
+    pub write_hold: u8,
+
+    /// Drive data on the low lanes of the bus, rather than the high
+    /// lanes
+    pub byte_lanes_shifted: bool,
+
+    /// Use burst transfers for reads larger than the port size
+    pub burst_read: bool,
+
+    /// Use burst transfers for writes larger than the port size
+    pub burst_write: bool,
+
+    /// Fault writes to the region
+    pub write_protect: bool,
+}
+
+impl ChipSelectConfig {
+    /// A configuration for a region, with conservative timing
+    ///
+    /// Transfers take the maximum number of wait states, and end
+    /// without waiting for the device.
+    pub const fn new(base: usize, size: usize, port_size: PortSize) -> Self {
+        Self {
+            base,
+            size,
+            port_size,
+            wait_states: 63,
+            auto_acknowledge: true,
+            address_setup: 0,
+            read_hold: 0,
+            write_hold: 0,
+            byte_lanes_shifted: false,
+            burst_read: false,
+            burst_write: false,
+            write_protect: false,
+        }
+    }
+}
+
+/// The start of the address space FlexBus chip selects can map
+pub const EXTERNAL_START: usize = 0x6000_0000;
+
+/// The end of the address space FlexBus chip selects can map
+pub const EXTERNAL_END: usize = 0xE000_0000;
+
+/// An enabled chip select, and the region it maps
+///
+/// The chip select is disabled when this is dropped.
+pub struct ChipSelect<'a, M> {
+    regs: &'static mut ChipSelectRegs,
+    bus: &'a FlexBus<M>,
+    cs: usize,
+    base: usize,
+    size: usize,
+}
+
+impl<M> FlexBus<M> {
+    /// Enable a chip select
+    ///
+    /// Chip select 0 is the only one with a dedicated pin. The
+    /// others share pins with other FlexBus signals, as set by
+    /// [`Self::set_pin_groups()`]. Returns `Ok(None)` if the chip
+    /// select is already enabled.
+    pub fn enable(
+        &self,
+        cs: usize,
+        config: &ChipSelectConfig,
+    ) -> Result<Option<ChipSelect<'_, M>>, Error> {
+        if cs >= 6 {
+            return Err(Error::InvalidChipSelect);
+        }
+        if config.size < 0x1_0000
+            || !config.size.is_power_of_two()
+            || config.base % config.size != 0
+            || config.base < EXTERNAL_START
+            || config.base >= EXTERNAL_END
+            || EXTERNAL_END - config.base < config.size
+        {
+            return Err(Error::InvalidRegion);
+        }
+        if config.wait_states > 63
+            || config.address_setup > 3
+            || config.read_hold > 3
+            || config.write_hold > 3
+        {
+            return Err(Error::InvalidTiming);
+        }
+
+        if self.chip_selects[cs].swap(true, Ordering::Acquire) {
+            return Ok(None);
+        }
+
+        let port_size = match config.port_size {
+            PortSize::Bits32 => 0,
+            PortSize::Bits8 => 1,
+            PortSize::Bits16 => 2,
+        };
+
+        let regs = unsafe { &mut (*self.regs).cs[cs] };
+        regs.csmr.write(0);
+        regs.csar.write(config.base as u32);
+        regs.cscr.update(|cscr| {
+            cscr.set_bits(26..32, 0);
+            cscr.set_bit(23, false);
+            cscr.set_bit(22, false);
+            cscr.set_bits(20..22, config.address_setup as u32);
+            cscr.set_bits(18..20, config.read_hold as u32);
+            cscr.set_bits(16..18, config.write_hold as u32);
+            cscr.set_bits(10..16, config.wait_states as u32);
+            cscr.set_bit(9, config.byte_lanes_shifted);
+            cscr.set_bit(8, config.auto_acknowledge);
+            cscr.set_bits(6..8, port_size);
+            cscr.set_bit(5, false);
+            cscr.set_bit(4, config.burst_read);
+            cscr.set_bit(3, config.burst_write);
+        });
+        regs.csmr.update(|csmr| {
+            csmr.set_bits(16..32, ((config.size - 1) >> 16) as u32);
+            csmr.set_bit(8, config.write_protect);
+            csmr.set_bit(0, true);
+        });
+
+        Ok(Some(ChipSelect {
+            regs,
+            bus: self,
+            cs,
+            base: config.base,
+            size: config.size,
+        }))
+    }
+
+    /// Select the functions of the shared FlexBus pins
+    ///
+    /// Each of the five groups selects between three signals:
+    ///
+    /// | Group | 0         | 1          | 2            |
+    /// |-------|-----------|------------|--------------|
+    /// | 1     | `FB_ALE`  | `FB_CS1`   | `FB_TS`      |
+    /// | 2     | `FB_CS4`  | `FB_TSIZ0` | `FB_BE31_24` |
+    /// | 3     | `FB_CS5`  | `FB_TSIZ1` | `FB_BE23_16` |
+    /// | 4     | `FB_TBST` | `FB_CS2`   | `FB_BE15_8`  |
+    /// | 5     | `FB_TA`   | `FB_CS3`   | `FB_BE7_0`   |
+    pub fn set_pin_groups(&mut self, groups: [u8; 5]) {
+        let cspmcr = unsafe { &mut (*self.regs).cspmcr };
+        cspmcr.update(|cspmcr| {
+            for (group, &function) in groups.iter().enumerate() {
+                assert!(function < 3);
+                let shift = 28 - group * 4;
+                cspmcr.set_bits(shift..shift + 4, function as u32);
+            }
+        });
+    }
+}
+
+impl<'a, M> ChipSelect<'a, M> {
+    /// The start of the region
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    /// The size of the region, in bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The region as bytes
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.base as *mut u8, self.size) }
+    }
+
+    /// Keep the chip select enabled, and get the region as bytes
+    ///
+    /// The region lives as long as the borrow of the FlexBus. With a
+    /// `'static` FlexBus handle, this is `&'static mut [u8]`.
+    pub fn into_slice(self) -> &'a mut [u8] {
+        let region = unsafe { slice::from_raw_parts_mut(self.base as *mut u8, self.size) };
+        forget(self);
+        region
+    }
+
+    /// Keep the chip select enabled, and get the start of the region
+    /// as a `T`
+    ///
+    /// Returns `None` if `T` is larger than the region, or needs more
+    /// alignment than its base address has.
+    ///
+    /// # Safety
+    /// Every bit pattern the device can return must be a valid
+    /// `T`. Ordinary loads and stores may be merged or reordered, so
+    /// the fields of a register block for a device with side effects
+    /// should use volatile accesses.
+    pub unsafe fn into_registers<T>(self) -> Option<&'a mut T> {
+        if size_of::<T>() > self.size || self.base % align_of::<T>() != 0 {
+            return None;
+        }
+        let registers = &mut *(self.base as *mut T);
+        forget(self);
+        Some(registers)
+    }
+}
+
+impl<M> Drop for ChipSelect<'_, M> {
+    fn drop(&mut self) {
+        self.regs.csmr.update(|csmr| {
+            csmr.set_bit(0, false);
+        });
+        self.bus.chip_selects[self.cs].store(false, Ordering::Release);
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for FlexBus<Mk64Fx512> {
+    const GATE: (usize, usize) = (7, 0);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            chip_selects: Default::default(),
+            regs: 0x4000_C000 as *mut _,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for FlexBus<Mk66Fx1M0> {
+    const GATE: (usize, usize) = (7, 0);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            chip_selects: Default::default(),
+            regs: 0x4000_C000 as *mut _,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,366 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! FlexCAN controller area network controller
+//!
+//! Each controller has 16 message buffers. Buffers 0 through 7
+//! receive frames with standard identifiers, buffers 8 through 14
+//! receive frames with extended identifiers, and buffer 15 transmits.
+//! Every receive buffer accepts every identifier. Only the K-series
+//! MCUs which are used on the Teensy 3.2, 3.5, and 3.6 are supported.
+
+use super::{
+    super::{Mk20Dx256, Mk64Fx512, Mk66Fx1M0},
+    sim::{Gate, GatedPeripheral},
+};
+use crate::{
+    io::{CanFrame, CanId},
+    register::{Register, Reserved},
+};
+use bit_field::BitField;
+use core::marker::PhantomData;
+
+#[repr(C)]
+struct MessageBuffer {
+    cs: Register<u32>,
+    id: Register<u32>,
+    data: [Register<u32>; 2],
+}
+
+#[repr(C)]
+struct FlexCanRegs {
+    mcr: Register<u32>,
+    ctrl1: Register<u32>,
+    timer: Register<u32>,
+    _reserved_0: Reserved<u32>,
+    rxmgmask: Register<u32>,
+    rx14mask: Register<u32>,
+    rx15mask: Register<u32>,
+    ecr: Register<u32>,
+    esr1: Register<u32>,
+    _reserved_1: Reserved<u32>,
+    imask1: Register<u32>,
+    _reserved_2: Reserved<u32>,
+    iflag1: Register<u32>,
+    ctrl2: Register<u32>,
+    esr2: Register<u32>,
+    _reserved_3: [Reserved<u32>; 2],
+    crcr: Register<u32>,
+    rxfgmask: Register<u32>,
+    rxfir: Register<u32>,
+    _reserved_4: [Reserved<u32>; 12],
+    mb: [MessageBuffer; 16],
+}
+
+/// The first buffer which receives extended frames
+const EXTENDED_RX: usize = 8;
+
+/// The buffer which transmits
+const TX: usize = 15;
+
+/// The interrupt flags of the receive buffers
+const RX_FLAGS: u32 = (1 << TX) - 1;
+
+const CODE_RX_EMPTY: u32 = 0b0100;
+const CODE_TX_INACTIVE: u32 = 0b1000;
+const CODE_TX_DATA: u32 = 0b1100;
+
+/// The handle to a FlexCAN controller
+#[allow(dead_code)]
+pub struct Can<M, T, R, const N: usize> {
+    regs: &'static mut FlexCanRegs,
+    tx: T,
+    rx: R,
+    gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+impl<M, const N: usize> Can<M, (), (), N> {
+    /// Configure the controller for a bit rate
+    ///
+    /// This resets the controller, and holds it off the bus until it
+    /// is enabled. The bit is divided into between 8 and 20 time
+    /// quanta, sampled at about 85% of the way through, and the
+    /// source clock must divide evenly into one of those. Returns
+    /// false if no division of `source_clock` reaches `bitrate`.
+    pub fn set_bitrate(&mut self, source_clock: usize, bitrate: usize) -> bool {
+        let timing = (8..=20).rev().find_map(|quanta| {
+            let prescale = source_clock / (bitrate * quanta);
+            if prescale == 0 || prescale > 256 || prescale * bitrate * quanta != source_clock {
+                None
+            } else {
+                Some((prescale, quanta))
+            }
+        });
+        let (prescale, quanta) = match timing {
+            Some(timing) => timing,
+            None => return false,
+        };
+        let pseg2 = ((quanta + 4) / 8).max(2);
+        let pseg1 = (quanta - 1 - pseg2) / 2;
+        let propseg = quanta - 1 - pseg2 - pseg1;
+
+        // The clock source can only be selected while the module is
+        // disabled. The bus clock is used, as the oscillator clock
+        // is not always running.
+        self.regs.mcr.update(|mcr| {
+            mcr.set_bit(31, true);
+        });
+        while !self.regs.mcr.read().get_bit(20) {}
+        self.regs.ctrl1.update(|ctrl1| {
+            ctrl1.set_bit(13, true);
+        });
+        self.regs.mcr.update(|mcr| {
+            mcr.set_bit(31, false);
+        });
+        while self.regs.mcr.read().get_bit(20) {}
+
+        self.regs.mcr.update(|mcr| {
+            mcr.set_bit(25, true);
+        });
+        while self.regs.mcr.read().get_bit(25) {}
+        self.regs.mcr.update(|mcr| {
+            mcr.set_bit(30, true);
+            mcr.set_bit(28, true);
+        });
+        while !self.regs.mcr.read().get_bit(24) {}
+
+        self.regs.mcr.update(|mcr| {
+            // Disable self reception, and use all 16 buffers
+            mcr.set_bit(17, true);
+            mcr.set_bits(0..7, 15);
+        });
+        self.regs.ctrl1.update(|ctrl1| {
+            ctrl1.set_bits(24..32, prescale as u32 - 1);
+            ctrl1.set_bits(22..24, pseg2.min(4) as u32 - 1);
+            ctrl1.set_bits(19..22, pseg1 as u32 - 1);
+            ctrl1.set_bits(16..19, pseg2 as u32 - 1);
+            ctrl1.set_bits(0..3, propseg as u32 - 1);
+        });
+
+        // A zero mask accepts every identifier
+        self.regs.rxmgmask.write(0);
+        self.regs.rx14mask.write(0);
+        self.regs.rx15mask.write(0);
+        for (idx, mb) in self.regs.mb.iter_mut().enumerate() {
+            mb.id.write(0);
+            mb.data[0].write(0);
+            mb.data[1].write(0);
+            if idx == TX {
+                mb.cs.write(CODE_TX_INACTIVE << 24);
+            } else {
+                mb.cs.write(empty_rx_cs(idx));
+            }
+        }
+        self.regs.imask1.write(0);
+        self.regs.iflag1.write(u32::MAX);
+        true
+    }
+
+    /// Enable this controller, and join the bus
+    ///
+    /// The bit rate must already be set.
+    pub fn enable<T, R>(self, tx: T, rx: R) -> Can<M, T, R, N>
+    where
+        T: Tx<M, N>,
+        R: Rx<M, N>,
+    {
+        self.regs.mcr.update(|mcr| {
+            mcr.set_bit(28, false);
+        });
+        while self.regs.mcr.read().get_bit(24) {}
+        while self.regs.mcr.read().get_bit(27) {}
+
+        Can {
+            regs: self.regs,
+            tx,
+            rx,
+            gate: self.gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+impl<M, T, R, const N: usize> Can<M, T, R, N>
+where
+    T: Tx<M, N>,
+    R: Rx<M, N>,
+{
+    /// Queue a frame in the transmit buffer
+    ///
+    /// Returns false if the previous frame has not been sent yet.
+    pub fn transmit(&mut self, frame: &CanFrame) -> bool {
+        if self.regs.mb[TX].cs.read().get_bits(24..28) == CODE_TX_DATA {
+            return false;
+        }
+        self.regs.iflag1.write(1 << TX);
+
+        let mut cs: u32 = 0;
+        cs.set_bits(24..28, CODE_TX_DATA);
+        cs.set_bit(20, frame.is_remote());
+        cs.set_bits(16..20, frame.dlc() as u32);
+        let id = match frame.id() {
+            CanId::Standard(id) => (id as u32) << 18,
+            CanId::Extended(id) => {
+                // Extended frames send SRR recessive
+                cs.set_bit(22, true);
+                cs.set_bit(21, true);
+                id
+            }
+        };
+        let mut data = [0; 8];
+        data[..frame.data().len()].copy_from_slice(frame.data());
+
+        let mb = &mut self.regs.mb[TX];
+        mb.cs.write(CODE_TX_INACTIVE << 24);
+        mb.id.write(id);
+        mb.data[0].write(u32::from_be_bytes([data[0], data[1], data[2], data[3]]));
+        mb.data[1].write(u32::from_be_bytes([data[4], data[5], data[6], data[7]]));
+        mb.cs.write(cs);
+        true
+    }
+
+    /// Take the oldest frame from the receive buffers
+    ///
+    /// Frames are ordered by the time stamp the controller gives
+    /// each one as it is received.
+    pub fn receive(&mut self) -> Option<CanFrame> {
+        let full = self.regs.iflag1.read() & RX_FLAGS;
+        if full == 0 {
+            return None;
+        }
+
+        // Reading a buffer's control word locks it until the timer
+        // is read, so the timer is read first.
+        let now = self.regs.timer.read() as u16;
+        let oldest = (0..TX)
+            .filter(|&idx| full.get_bit(idx))
+            .max_by_key(|&idx| now.wrapping_sub(self.regs.mb[idx].cs.read() as u16))?;
+
+        let mb = &self.regs.mb[oldest];
+        let cs = mb.cs.read();
+        let id = mb.id.read();
+        let mut data = [0; 8];
+        data[..4].copy_from_slice(&mb.data[0].read().to_be_bytes());
+        data[4..].copy_from_slice(&mb.data[1].read().to_be_bytes());
+        self.regs.timer.read();
+        self.regs.mb[oldest].cs.write(empty_rx_cs(oldest));
+        self.regs.iflag1.write(1 << oldest);
+
+        let id = if cs.get_bit(21) {
+            CanId::Extended(id.get_bits(0..29))
+        } else {
+            CanId::Standard(id.get_bits(18..29) as u16)
+        };
+        let len = (cs.get_bits(16..20) as usize).min(8);
+        if cs.get_bit(20) {
+            CanFrame::new_remote(id, len)
+        } else {
+            CanFrame::new(id, &data[..len])
+        }
+    }
+
+    /// Check whether the controller has left the bus after too many errors
+    ///
+    /// The controller rejoins the bus on its own once the bus has
+    /// been idle for long enough.
+    pub fn is_bus_off(&self) -> bool {
+        self.regs.esr1.read().get_bit(5)
+    }
+
+    /// Enable the controller to interrupt when a frame is received
+    pub fn enable_rx_intr(&mut self) {
+        self.regs.imask1.update(|imask1| {
+            *imask1 |= RX_FLAGS;
+        });
+    }
+
+    /// Enable the controller to interrupt when a frame is sent
+    pub fn enable_tx_intr(&mut self) {
+        self.regs.imask1.update(|imask1| {
+            imask1.set_bit(TX, true);
+        });
+    }
+
+    /// Leave the bus, and disable the controller
+    ///
+    /// This should be done before the handle is dropped, since
+    /// gating the controller's clock while it is on the bus can
+    /// leave the transmit pin driven.
+    pub fn disable(&mut self) {
+        self.regs.mcr.update(|mcr| {
+            mcr.set_bit(31, true);
+        });
+        while !self.regs.mcr.read().get_bit(20) {}
+    }
+}
+
+/// The control word of an empty receive buffer
+fn empty_rx_cs(idx: usize) -> u32 {
+    let mut cs: u32 = 0;
+    cs.set_bits(24..28, CODE_RX_EMPTY);
+    cs.set_bit(21, idx >= EXTENDED_RX);
+    cs
+}
+
+/// A pin which is appropriate for use as a CAN transmitter
+pub trait Tx<M, const N: usize>: Unpin {}
+
+/// A pin which is appropriate for use as a CAN receiver
+pub trait Rx<M, const N: usize>: Unpin {}
+
+unsafe impl GatedPeripheral<Mk20Dx256> for Can<Mk20Dx256, (), (), 0> {
+    const GATE: (usize, usize) = (6, 4);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4002_4000 as *mut _),
+            tx: (),
+            rx: (),
+            gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for Can<Mk64Fx512, (), (), 0> {
+    const GATE: (usize, usize) = (6, 4);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4002_4000 as *mut _),
+            tx: (),
+            rx: (),
+            gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Can<Mk66Fx1M0, (), (), 0> {
+    const GATE: (usize, usize) = (6, 4);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4002_4000 as *mut _),
+            tx: (),
+            rx: (),
+            gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Can<Mk66Fx1M0, (), (), 1> {
+    const GATE: (usize, usize) = (3, 4);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x400A_4000 as *mut _),
+            tx: (),
+            rx: (),
+            gate,
+            _mcu: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,320 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! FlexTimer
+//!
+//! Each FTM is a 16-bit counter with up to 8 channels. Two modes are
+//! supported. In edge-aligned PWM, each enabled channel's output is
+//! high from the start of each period until the counter reaches the
+//! channel's value. In quadrature decoder mode, the counter follows
+//! an encoder's phase A and B inputs instead of a clock.
+
+use super::{
+    super::{Mk20Dx128, Mk20Dx256, Mk64Fx512, Mk66Fx1M0},
+    sim::{Gate, GatedPeripheral},
+};
+use crate::register::Register;
+use bit_field::BitField;
+use core::marker::PhantomData;
+
+#[repr(C)]
+struct FtmChannelRegs {
+    csc: Register<u32>,
+    cv: Register<u32>,
+}
+
+#[repr(C)]
+struct FtmRegs {
+    sc: Register<u32>,
+    cnt: Register<u32>,
+    mod_: Register<u32>,
+    ch: [FtmChannelRegs; 8],
+    cntin: Register<u32>,
+    status: Register<u32>,
+    mode: Register<u32>,
+    sync: Register<u32>,
+    outinit: Register<u32>,
+    outmask: Register<u32>,
+    combine: Register<u32>,
+    deadtime: Register<u32>,
+    exttrig: Register<u32>,
+    pol: Register<u32>,
+    fms: Register<u32>,
+    filter: Register<u32>,
+    fltctrl: Register<u32>,
+    qdctrl: Register<u32>,
+}
+
+/// The handle to an FTM
+pub struct Ftm<M, const N: usize> {
+    regs: &'static mut FtmRegs,
+    _gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+impl<M, const N: usize> Ftm<M, N> {
+    /// Start the counter with a period
+    ///
+    /// The counter counts the bus clock divided by `2^prescale`, and
+    /// restarts after `period` counts. Valid prescale values are
+    /// `0..8`, and periods are `1..0x1_0000`.
+    pub fn set_period(&mut self, prescale: u32, period: u32) {
+        assert!(prescale < 8);
+        assert!(period > 0 && period < 0x1_0000);
+        self.regs.cntin.write(0);
+        self.regs.mod_.write(period - 1);
+        self.regs.sc.update(|sc| {
+            sc.set_bits(0..3, prescale);
+            sc.set_bits(3..5, 1);
+        });
+    }
+
+    /// The prescale and period of the counter
+    ///
+    /// Returns `None` if the counter is stopped.
+    pub fn period(&self) -> Option<(u32, u32)> {
+        let sc = self.regs.sc.read();
+        if sc.get_bits(3..5) == 0 {
+            None
+        } else {
+            Some((sc.get_bits(0..3), self.regs.mod_.read() + 1))
+        }
+    }
+
+    /// Enable or disable the PWM output of a channel
+    ///
+    /// A disabled channel releases its pin.
+    pub fn enable_pwm(&mut self, channel: usize, enabled: bool) {
+        let mut csc = 0;
+        csc.set_bit(5, enabled);
+        csc.set_bit(3, enabled);
+        self.regs.ch[channel].csc.write(csc);
+    }
+
+    /// Set the number of counts in each period for which a channel is high
+    ///
+    /// The new value takes effect at the start of the next period. A
+    /// value of the period or more holds the output high.
+    pub fn set_duty(&mut self, channel: usize, duty: u32) {
+        self.regs.ch[channel].cv.write(duty.min(0xFFFF));
+    }
+
+    /// Start counting the edges of a quadrature encoder
+    ///
+    /// The counter runs through its full 16-bit range, and interrupts
+    /// each time it wraps. Edges on either phase input shorter than
+    /// `4 * filter` bus clock cycles are ignored. Valid filter values
+    /// are `0..16`, where 0 disables the filter.
+    pub fn enable_quadrature(&mut self, filter: u32) {
+        assert!(filter < 16);
+        self.regs.sc.write(0);
+        // The quadrature registers are write-protected until the
+        // extended features are enabled.
+        self.regs.mode.update(|mode| {
+            mode.set_bit(2, true);
+            mode.set_bit(0, true);
+        });
+        self.regs.cntin.write(0);
+        self.regs.mod_.write(0xFFFF);
+        self.regs.cnt.write(0);
+        self.regs.filter.update(|filters| {
+            filters.set_bits(0..4, filter);
+            filters.set_bits(4..8, filter);
+        });
+        self.regs.qdctrl.update(|qdctrl| {
+            qdctrl.set_bit(7, filter > 0);
+            qdctrl.set_bit(6, filter > 0);
+            qdctrl.set_bits(2..4, 0);
+            qdctrl.set_bit(0, true);
+        });
+        self.regs.sc.update(|sc| {
+            sc.set_bits(3..5, 1);
+            sc.set_bit(6, true);
+        });
+    }
+
+    /// Stop counting encoder edges
+    ///
+    /// The FTM is left stopped, ready to be used for PWM.
+    pub fn disable_quadrature(&mut self) {
+        self.regs.sc.write(0);
+        self.regs.qdctrl.update(|qdctrl| {
+            qdctrl.set_bit(0, false);
+        });
+        self.regs.mode.update(|mode| {
+            mode.set_bit(0, false);
+        });
+    }
+
+    /// The current value of the counter
+    pub fn count(&self) -> u16 {
+        self.regs.cnt.read() as u16
+    }
+
+    /// Reset the counter to zero
+    ///
+    /// This also clears a pending overflow, so that it is not counted
+    /// against the new value.
+    pub fn reset_count(&mut self) {
+        self.regs.cnt.write(0);
+        self.regs.sc.update(|sc| {
+            sc.set_bit(7, false);
+        });
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx128> for Ftm<Mk20Dx128, 0> {
+    const GATE: (usize, usize) = (6, 24);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_8000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx128> for Ftm<Mk20Dx128, 1> {
+    const GATE: (usize, usize) = (6, 25);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_9000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx256> for Ftm<Mk20Dx256, 0> {
+    const GATE: (usize, usize) = (6, 24);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_8000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx256> for Ftm<Mk20Dx256, 1> {
+    const GATE: (usize, usize) = (6, 25);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_9000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx256> for Ftm<Mk20Dx256, 2> {
+    const GATE: (usize, usize) = (3, 24);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x400B_8000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for Ftm<Mk64Fx512, 0> {
+    const GATE: (usize, usize) = (6, 24);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_8000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for Ftm<Mk64Fx512, 1> {
+    const GATE: (usize, usize) = (6, 25);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_9000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for Ftm<Mk64Fx512, 2> {
+    const GATE: (usize, usize) = (6, 26);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_A000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for Ftm<Mk64Fx512, 3> {
+    const GATE: (usize, usize) = (3, 25);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x400B_9000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Ftm<Mk66Fx1M0, 0> {
+    const GATE: (usize, usize) = (6, 24);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_8000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Ftm<Mk66Fx1M0, 1> {
+    const GATE: (usize, usize) = (6, 25);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_9000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Ftm<Mk66Fx1M0, 2> {
+    const GATE: (usize, usize) = (6, 26);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_A000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Ftm<Mk66Fx1M0, 3> {
+    const GATE: (usize, usize) = (3, 25);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x400B_9000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
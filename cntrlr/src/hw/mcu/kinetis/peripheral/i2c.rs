@@ -0,0 +1,327 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Inter-integrated circuit bus
+
+use super::{
+    super::{Mk20Dx128, Mk20Dx256, Mk64Fx512, Mk66Fx1M0, Mkl26Z64},
+    sim::{Gate, GatedPeripheral},
+};
+use crate::register::Register;
+use bit_field::BitField;
+use core::marker::PhantomData;
+
+#[repr(C)]
+struct I2cRegs {
+    a1: Register<u8>,
+    f: Register<u8>,
+    c1: Register<u8>,
+    s: Register<u8>,
+    d: Register<u8>,
+    c2: Register<u8>,
+    flt: Register<u8>,
+    ra: Register<u8>,
+    smb: Register<u8>,
+    a2: Register<u8>,
+    slth: Register<u8>,
+    sltl: Register<u8>,
+}
+
+/// The SCL divider for each value of the ICR field
+const SCL_DIVIDERS: [usize; 64] = [
+    20, 22, 24, 26, 28, 30, 34, 40, 28, 32, 36, 40, 44, 48, 56, 68, 48, 56, 64, 72, 80, 88, 104,
+    128, 80, 96, 112, 128, 144, 160, 192, 240, 160, 192, 224, 256, 288, 320, 384, 480, 320, 384,
+    448, 512, 576, 640, 768, 960, 640, 768, 896, 1024, 1152, 1280, 1536, 1920, 1280, 1536, 1792,
+    2048, 2304, 2560, 3072, 3840,
+];
+
+/// The handle to an I2C controller
+#[allow(dead_code)]
+pub struct I2c<M, C, D, const N: usize> {
+    regs: &'static mut I2cRegs,
+    scl: C,
+    sda: D,
+    gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+impl<M, const N: usize> I2c<M, (), (), N> {
+    /// Enable this I2C for operation as a master.
+    pub fn enable<C, D>(self, scl: C, sda: D) -> I2c<M, C, D, N>
+    where
+        C: Scl<M, N>,
+        D: Sda<M, N>,
+    {
+        self.regs.c1.write(0);
+        self.regs.s.write(0b0001_0010);
+        self.regs.c1.update(|c1| {
+            c1.set_bit(7, true);
+        });
+
+        I2c {
+            regs: self.regs,
+            scl,
+            sda,
+            gate: self.gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+impl<M, C, D, const N: usize> I2c<M, C, D, N>
+where
+    C: Scl<M, N>,
+    D: Sda<M, N>,
+{
+    /// Set the bus clock rate
+    ///
+    /// The highest rate which is no faster than `rate` is used.
+    /// Returns false if `rate` is too slow to be reached from
+    /// `source_clock`.
+    pub fn set_rate(&mut self, source_clock: usize, rate: usize) -> bool {
+        // The multiplier is left at 1. Higher multipliers break
+        // repeated starts on some of these parts.
+        let icr = SCL_DIVIDERS
+            .iter()
+            .enumerate()
+            .filter(|(_, &divider)| source_clock / divider <= rate)
+            .min_by_key(|(_, &divider)| divider)
+            .map(|(icr, _)| icr);
+        match icr {
+            Some(icr) => {
+                self.regs.f.write(icr as u8);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Check whether any master is using the bus
+    pub fn is_busy(&self) -> bool {
+        self.regs.s.read().get_bit(5)
+    }
+
+    /// Check whether this controller is the bus master
+    pub fn is_master(&self) -> bool {
+        self.regs.c1.read().get_bit(5)
+    }
+
+    /// Send a start, and become the bus master
+    pub fn start(&mut self) {
+        self.regs.c1.update(|c1| {
+            c1.set_bit(4, true);
+            c1.set_bit(5, true);
+        });
+    }
+
+    /// Send a repeated start while remaining the bus master
+    pub fn repeated_start(&mut self) {
+        self.regs.c1.update(|c1| {
+            c1.set_bit(4, true);
+            c1.set_bit(2, true);
+        });
+    }
+
+    /// Send a stop, and release the bus
+    pub fn stop(&mut self) {
+        self.regs.c1.update(|c1| {
+            c1.set_bit(5, false);
+            c1.set_bit(4, false);
+            c1.set_bit(3, false);
+        });
+    }
+
+    /// Set whether writes to the data register transmit
+    ///
+    /// When this is false, reading the data register starts
+    /// receiving the next byte.
+    pub fn set_transmit(&mut self, transmit: bool) {
+        self.regs.c1.update(|c1| {
+            c1.set_bit(4, transmit);
+        });
+    }
+
+    /// Set whether received bytes are acknowledged
+    pub fn set_ack(&mut self, ack: bool) {
+        self.regs.c1.update(|c1| {
+            c1.set_bit(3, !ack);
+        });
+    }
+
+    /// Write a byte to the data register
+    pub fn write_data(&mut self, data: u8) {
+        self.regs.d.write(data);
+    }
+
+    /// Read a byte from the data register
+    pub fn read_data(&mut self) -> u8 {
+        self.regs.d.read()
+    }
+
+    /// Check whether the last byte written was acknowledged
+    pub fn received_ack(&self) -> bool {
+        !self.regs.s.read().get_bit(0)
+    }
+
+    /// Check and clear the interrupt flag
+    ///
+    /// The flag is set when a byte has been transferred, or when
+    /// arbitration is lost.
+    pub fn check_intr(&mut self) -> bool {
+        if self.regs.s.read().get_bit(1) {
+            self.regs.s.write(1 << 1);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Check and clear the arbitration lost flag
+    pub fn check_arbitration_lost(&mut self) -> bool {
+        if self.regs.s.read().get_bit(4) {
+            self.regs.s.write(1 << 4);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Enable the I2C to interrupt when its interrupt flag is set
+    pub fn enable_intr(&mut self) {
+        self.regs.c1.update(|c1| {
+            c1.set_bit(6, true);
+        });
+    }
+}
+
+/// A pin which is appropriate for use as an I2C clock
+pub trait Scl<M, const N: usize>: Unpin {}
+
+/// A pin which is appropriate for use as an I2C data line
+pub trait Sda<M, const N: usize>: Unpin {}
+
+unsafe impl GatedPeripheral<Mk20Dx128> for I2c<Mk20Dx128, (), (), 0> {
+    const GATE: (usize, usize) = (4, 6);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4006_6000 as *mut _),
+            scl: (),
+            sda: (),
+            gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx256> for I2c<Mk20Dx256, (), (), 0> {
+    const GATE: (usize, usize) = (4, 6);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4006_6000 as *mut _),
+            scl: (),
+            sda: (),
+            gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx256> for I2c<Mk20Dx256, (), (), 1> {
+    const GATE: (usize, usize) = (4, 7);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4006_7000 as *mut _),
+            scl: (),
+            sda: (),
+            gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for I2c<Mk64Fx512, (), (), 0> {
+    const GATE: (usize, usize) = (4, 6);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4006_6000 as *mut _),
+            scl: (),
+            sda: (),
+            gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for I2c<Mk64Fx512, (), (), 1> {
+    const GATE: (usize, usize) = (4, 7);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4006_7000 as *mut _),
+            scl: (),
+            sda: (),
+            gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for I2c<Mk66Fx1M0, (), (), 0> {
+    const GATE: (usize, usize) = (4, 6);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4006_6000 as *mut _),
+            scl: (),
+            sda: (),
+            gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for I2c<Mk66Fx1M0, (), (), 1> {
+    const GATE: (usize, usize) = (4, 7);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4006_7000 as *mut _),
+            scl: (),
+            sda: (),
+            gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mkl26Z64> for I2c<Mkl26Z64, (), (), 0> {
+    const GATE: (usize, usize) = (4, 6);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4006_6000 as *mut _),
+            scl: (),
+            sda: (),
+            gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mkl26Z64> for I2c<Mkl26Z64, (), (), 1> {
+    const GATE: (usize, usize) = (4, 7);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4006_7000 as *mut _),
+            scl: (),
+            sda: (),
+            gate,
+            _mcu: PhantomData,
+        }
+    }
+}
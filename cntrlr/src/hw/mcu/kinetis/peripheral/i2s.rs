@@ -0,0 +1,252 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Inter-IC Sound
+//!
+//! The I2S module streams audio samples to a codec. Only the
+//! transmitter is supported here, as the bus master: it drives the
+//! master, bit, and frame clocks. Each frame is two 32-bit words, left
+//! then right, and 16-bit samples are written to the top half of each
+//! word. This is the format used by the Teensy Audio Library. Only the
+//! MK64 and MK66 are supported.
+
+use super::{
+    super::{Mk64Fx512, Mk66Fx1M0},
+    sim::{Gate, GatedPeripheral},
+};
+use crate::register::{Register, Reserved};
+use bit_field::BitField;
+use core::marker::PhantomData;
+
+/// The DMA request source of the transmitter
+pub const TX_REQUEST: u8 = 13;
+
+/// The number of master clock cycles in each frame
+pub const MCLK_PER_FRAME: usize = 256;
+
+#[repr(C)]
+struct I2sRegs {
+    tcsr: Register<u32>,
+    tcr1: Register<u32>,
+    tcr2: Register<u32>,
+    tcr3: Register<u32>,
+    tcr4: Register<u32>,
+    tcr5: Register<u32>,
+    _reserved_0: [Reserved<u32>; 2],
+    tdr: [Register<u32>; 2],
+    _reserved_1: [Reserved<u32>; 6],
+    tfr: [Register<u32>; 2],
+    _reserved_2: [Reserved<u32>; 6],
+    tmr: Register<u32>,
+    _reserved_3: [Reserved<u32>; 7],
+    _receive: [Reserved<u32>; 32],
+    mcr: Register<u32>,
+    mdr: Register<u32>,
+}
+
+/// The handle to the I2S module
+#[allow(dead_code)]
+pub struct I2s<M, C, B, F, D> {
+    regs: &'static mut I2sRegs,
+    mclk: C,
+    bclk: B,
+    fs: F,
+    txd: D,
+    gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+impl<M> I2s<M, (), (), (), ()> {
+    /// Set the master clock for a sample rate
+    ///
+    /// The master clock is divided from `source_clock`, the system
+    /// clock, by a fraction with a numerator of at most 256. Returns
+    /// the sample rate which was reached, or `None` if the system
+    /// clock is too slow for `sample_rate`.
+    pub fn set_sample_rate(&mut self, source_clock: usize, sample_rate: usize) -> Option<usize> {
+        let mclk = sample_rate.checked_mul(MCLK_PER_FRAME)? as u64;
+        let source = source_clock as u64;
+        if mclk == 0 || mclk > source {
+            return None;
+        }
+        let (mult, div) = (1..=256)
+            .filter_map(|mult| {
+                let div = (source * mult + mclk / 2) / mclk;
+                if div < mult || div > 4096 {
+                    None
+                } else {
+                    Some((mult, div))
+                }
+            })
+            .min_by_key(|&(mult, div)| {
+                let rate = source * mult / div;
+                rate.max(mclk) - rate.min(mclk)
+            })?;
+
+        self.regs.mcr.update(|mcr| {
+            // The system clock is the divider's input
+            mcr.set_bits(24..26, 0);
+            mcr.set_bit(30, true);
+        });
+        while self.regs.mcr.read().get_bit(31) {}
+        self.regs.mdr.update(|mdr| {
+            mdr.set_bits(12..20, mult as u32 - 1);
+            mdr.set_bits(0..12, div as u32 - 1);
+        });
+        while self.regs.mcr.read().get_bit(31) {}
+        Some((source * mult / div) as usize / MCLK_PER_FRAME)
+    }
+
+    /// Configure the transmitter, and route it to its pins
+    ///
+    /// The master clock must already be set. The transmitter does not
+    /// run until it is [started](I2s::start).
+    pub fn enable_tx<C, B, F, D>(self, mclk: C, bclk: B, fs: F, txd: D) -> I2s<M, C, B, F, D>
+    where
+        C: Mclk<M>,
+        B: TxBclk<M>,
+        F: TxFs<M>,
+        D: TxData<M>,
+    {
+        self.regs.tcsr.write(0);
+        self.regs.tmr.write(0);
+        self.regs.tcr1.update(|tcr1| {
+            tcr1.set_bits(0..3, 1);
+        });
+        self.regs.tcr2.update(|tcr2| {
+            // The bit clock is a quarter of the master clock, driven
+            // on its falling edge.
+            tcr2.set_bits(30..32, 0);
+            tcr2.set_bits(26..28, 1);
+            tcr2.set_bit(25, true);
+            tcr2.set_bit(24, true);
+            tcr2.set_bits(0..8, 1);
+        });
+        self.regs.tcr3.update(|tcr3| {
+            tcr3.set_bit(16, true);
+        });
+        self.regs.tcr4.update(|tcr4| {
+            // Two words per frame, with the frame sync one bit early
+            // and low for the left channel.
+            tcr4.set_bits(16..21, 1);
+            tcr4.set_bits(8..13, 31);
+            tcr4.set_bit(4, true);
+            tcr4.set_bit(3, true);
+            tcr4.set_bit(1, true);
+            tcr4.set_bit(0, true);
+        });
+        self.regs.tcr5.update(|tcr5| {
+            tcr5.set_bits(24..29, 31);
+            tcr5.set_bits(16..21, 31);
+            tcr5.set_bits(8..13, 31);
+        });
+
+        I2s {
+            regs: self.regs,
+            mclk,
+            bclk,
+            fs,
+            txd,
+            gate: self.gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+impl<M, C, B, F, D> I2s<M, C, B, F, D>
+where
+    C: Mclk<M>,
+    B: TxBclk<M>,
+    F: TxFs<M>,
+    D: TxData<M>,
+{
+    /// Start transmitting
+    ///
+    /// The FIFO requests data from the DMA controller whenever it
+    /// runs low, so the DMA channel should be started first.
+    pub fn start(&mut self) {
+        self.regs.tcsr.update(|tcsr| {
+            tcsr.set_bit(31, true);
+            tcsr.set_bit(28, true);
+            tcsr.set_bit(25, true);
+            tcsr.set_bit(0, true);
+        });
+    }
+
+    /// Stop transmitting
+    ///
+    /// The current frame is finished before the transmitter stops.
+    pub fn stop(&mut self) {
+        self.regs.tcsr.update(|tcsr| {
+            tcsr.set_bit(31, false);
+            tcsr.set_bit(0, false);
+        });
+        while self.regs.tcsr.read().get_bit(31) {}
+        self.regs.tcsr.update(|tcsr| {
+            tcsr.set_bit(28, false);
+        });
+    }
+
+    /// The address which DMA writes 16-bit samples to
+    ///
+    /// This is the top half of the first transmit data register.
+    pub fn tx_data_address(&self) -> usize {
+        &self.regs.tdr[0] as *const _ as usize + 2
+    }
+
+    /// Check whether the FIFO has run empty since this was last cleared
+    pub fn is_underrun(&self) -> bool {
+        self.regs.tcsr.read().get_bit(18)
+    }
+
+    /// Clear the FIFO underrun flag
+    pub fn clear_underrun(&mut self) {
+        self.regs.tcsr.update(|tcsr| {
+            tcsr.set_bit(18, true);
+        });
+    }
+}
+
+/// A pin which is appropriate for use as the I2S master clock
+pub trait Mclk<M>: Unpin {}
+
+/// A pin which is appropriate for use as the I2S transmit bit clock
+pub trait TxBclk<M>: Unpin {}
+
+/// A pin which is appropriate for use as the I2S transmit frame sync
+pub trait TxFs<M>: Unpin {}
+
+/// A pin which is appropriate for use as the I2S transmit data line
+pub trait TxData<M>: Unpin {}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for I2s<Mk64Fx512, (), (), (), ()> {
+    const GATE: (usize, usize) = (6, 15);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4002_F000 as *mut _),
+            mclk: (),
+            bclk: (),
+            fs: (),
+            txd: (),
+            gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for I2s<Mk66Fx1M0, (), (), (), ()> {
+    const GATE: (usize, usize) = (6, 15);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4002_F000 as *mut _),
+            mclk: (),
+            bclk: (),
+            fs: (),
+            txd: (),
+            gate,
+            _mcu: PhantomData,
+        }
+    }
+}
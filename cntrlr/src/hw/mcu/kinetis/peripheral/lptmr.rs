@@ -0,0 +1,232 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Low-Power Timer
+//!
+//! The LPTMR is a 16-bit counter which continues to operate in all
+//! low-power modes except the lowest-leakage stop modes. It can count
+//! either its clock source or pulses on an input pin, and can
+//! interrupt when the count reaches a compare value.
+
+use super::{
+    super::{Mk20Dx128, Mk20Dx256, Mk64Fx512, Mk66Fx1M0, Mkl26Z64},
+    sim::{Gate, GatedPeripheral},
+};
+use crate::register::Register;
+use bit_field::BitField;
+use core::marker::PhantomData;
+
+#[repr(C)]
+struct LptmrRegs {
+    csr: Register<u32>,
+    psr: Register<u32>,
+    cmr: Register<u32>,
+    cnr: Register<u32>,
+}
+
+/// The handle to the LPTMR
+pub struct Lptmr<M> {
+    regs: &'static mut LptmrRegs,
+    _gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+/// The clock used to drive the LPTMR
+#[derive(PartialEq)]
+pub enum ClockSource {
+    /// The MCG internal reference clock
+    Mcgir,
+
+    /// The 1kHz low-power oscillator
+    Lpo,
+
+    /// The 32kHz external reference clock
+    Erclk32k,
+
+    /// The external oscillator clock
+    Oscer,
+}
+
+/// The edge of an input pulse which is counted by the LPTMR
+#[derive(PartialEq)]
+pub enum Polarity {
+    /// Count rising edges
+    Rising,
+
+    /// Count falling edges
+    Falling,
+}
+
+impl<M> Lptmr<M> {
+    /// Enable or disable the timer.
+    ///
+    /// Disabling the timer resets the counter to zero. The clock
+    /// source, prescaler, and counter mode should only be changed
+    /// while the timer is disabled.
+    pub fn enable(&mut self, enabled: bool) {
+        self.regs.csr.update(|csr| {
+            csr.set_bit(0, enabled);
+            // The compare flag is cleared by writing a 1, so it is
+            // masked out of every update which should preserve it.
+            csr.set_bit(7, false);
+        });
+    }
+
+    /// Set the clock used to drive this timer
+    pub fn set_clock_source(&mut self, source: ClockSource) {
+        let source = match source {
+            ClockSource::Mcgir => 0,
+            ClockSource::Lpo => 1,
+            ClockSource::Erclk32k => 2,
+            ClockSource::Oscer => 3,
+        };
+        self.regs.psr.update(|psr| {
+            psr.set_bits(0..2, source);
+        });
+    }
+
+    /// Set the clock prescaler
+    ///
+    /// The clock is divided by `2^(prescale + 1)`. Valid prescale
+    /// values are `0..16`. If `prescale` is `None`, the prescaler is
+    /// bypassed and the counter increments on every clock.
+    pub fn set_prescaler(&mut self, prescale: Option<u32>) {
+        self.regs.psr.update(|psr| {
+            if let Some(prescale) = prescale {
+                assert!(prescale < 16);
+                psr.set_bit(2, false);
+                psr.set_bits(3..7, prescale);
+            } else {
+                psr.set_bit(2, true);
+            }
+        });
+    }
+
+    /// Set whether the counter runs freely
+    ///
+    /// A free-running counter wraps at `u16::MAX`. Otherwise, the
+    /// counter is reset each time it matches the compare value.
+    pub fn set_free_running(&mut self, free_running: bool) {
+        self.regs.csr.update(|csr| {
+            csr.set_bit(2, free_running);
+            csr.set_bit(7, false);
+        });
+    }
+
+    /// Count pulses on an input, rather than the clock source
+    ///
+    /// The meaning of each input is MCU-specific. If `input` is
+    /// `None`, the timer counts its clock source. When counting
+    /// pulses, the prescaler acts as a glitch filter rather than a
+    /// divider.
+    pub fn set_pulse_input(&mut self, input: Option<(u32, Polarity)>) {
+        self.regs.csr.update(|csr| {
+            if let Some((input, polarity)) = input {
+                assert!(input < 4);
+                csr.set_bit(1, true);
+                csr.set_bit(3, polarity == Polarity::Falling);
+                csr.set_bits(4..6, input);
+            } else {
+                csr.set_bit(1, false);
+            }
+            csr.set_bit(7, false);
+        });
+    }
+
+    /// Set the compare value
+    ///
+    /// The compare flag is set when the counter matches this
+    /// value. While the timer is enabled, the compare value can only
+    /// be changed when the compare flag is set.
+    pub fn set_compare(&mut self, compare: u16) {
+        self.regs.cmr.write(compare as u32);
+    }
+
+    /// Enable or disable the compare interrupt
+    pub fn enable_interrupt(&mut self, enabled: bool) {
+        self.regs.csr.update(|csr| {
+            csr.set_bit(6, enabled);
+            csr.set_bit(7, false);
+        });
+    }
+
+    /// Check whether the counter has matched the compare value
+    pub fn compare_flag(&self) -> bool {
+        self.regs.csr.read().get_bit(7)
+    }
+
+    /// Clear the compare flag
+    pub fn clear_compare_flag(&mut self) {
+        self.regs.csr.update(|csr| {
+            csr.set_bit(7, true);
+        });
+    }
+
+    /// Read the current count
+    pub fn counter(&mut self) -> u16 {
+        // The counter must be written to latch the current value
+        // before it is read.
+        self.regs.cnr.write(0);
+        self.regs.cnr.read().get_bits(0..16) as u16
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx128> for Lptmr<Mk20Dx128> {
+    const GATE: (usize, usize) = (5, 0);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4004_0000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx256> for Lptmr<Mk20Dx256> {
+    const GATE: (usize, usize) = (5, 0);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4004_0000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for Lptmr<Mk64Fx512> {
+    const GATE: (usize, usize) = (5, 0);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4004_0000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Lptmr<Mk66Fx1M0> {
+    const GATE: (usize, usize) = (5, 0);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4004_0000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mkl26Z64> for Lptmr<Mkl26Z64> {
+    const GATE: (usize, usize) = (5, 0);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4004_0000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
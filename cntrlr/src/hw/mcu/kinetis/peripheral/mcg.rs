@@ -152,6 +152,51 @@ impl<M> Mcg<M> {
             _ => panic!("Unknown clock configuration"),
         }
     }
+
+    /// Enable or disable the external clock monitor
+    ///
+    /// While enabled, a loss of the external reference raises the MCG
+    /// interrupt, instead of resetting the MCU. The monitor must be
+    /// disabled before the MCG is switched to an internal reference.
+    pub fn enable_clock_monitor(&mut self, enabled: bool) {
+        self.regs.c2.update(|c2| {
+            c2.set_bit(7, false);
+        });
+        self.regs.c6.update(|c6| {
+            c6.set_bit(5, enabled);
+        });
+    }
+
+    /// Enable or disable the PLL loss-of-lock interrupt
+    pub fn enable_loss_of_lock_interrupt(&mut self, enabled: bool) {
+        self.regs.c6.update(|c6| {
+            c6.set_bit(7, enabled);
+        });
+    }
+
+    /// Check whether the clock monitor has seen the external reference stop
+    pub fn clock_lost(&self) -> bool {
+        self.regs.sc.read().get_bit(0)
+    }
+
+    /// Clear the clock monitor's loss-of-clock flag
+    pub fn clear_clock_lost(&mut self) {
+        self.regs.sc.update(|sc| {
+            // The auto-trim fail flag is also cleared by writing a 1
+            sc.set_bit(5, false);
+            sc.set_bit(0, true);
+        });
+    }
+
+    /// Check whether the PLL has lost lock since the flag was cleared
+    pub fn lock_lost(&self) -> bool {
+        self.regs.s.read().get_bit(7)
+    }
+
+    /// Clear the PLL loss-of-lock flag
+    pub fn clear_lock_lost(&mut self) {
+        self.regs.s.write(1 << 7);
+    }
 }
 
 impl<M> Drop for Mcg<M> {
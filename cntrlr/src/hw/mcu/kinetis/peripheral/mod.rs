@@ -3,16 +3,38 @@
 
 //! Shared peripherals for Kinetis family microcontrollers.
 
+pub mod adc;
+pub mod cmp;
+pub mod crc;
+pub mod dac;
+pub mod dma;
+pub mod flexbus;
+pub mod flexcan;
+pub mod ftm;
+pub mod i2c;
+pub mod i2s;
+pub mod lptmr;
 pub mod mcg;
 pub mod osc;
+pub mod pdb;
+pub mod pit;
 pub mod port;
+pub mod power;
+pub mod rfvbat;
+pub mod rtc;
 pub mod sim;
 pub mod smc;
 pub mod spi;
 pub mod systick;
+pub mod tpm;
+pub mod tsi;
 pub mod uart;
+pub mod usb;
 pub mod wdog;
 
+use crate::sync::Flag;
+use core::sync::atomic::Ordering;
+
 /// A Kinetis peripheral
 ///
 /// Trait for peripherals which are always enabled.
@@ -22,3 +44,66 @@ pub trait Peripheral: Sized {
     /// Returns `None` if the peripheral is already in use.
     fn get() -> Option<Self>;
 }
+
+/// All of the ungated peripherals of a Kinetis MCU
+///
+/// This gathers every peripheral singleton into one place, for
+/// applications which want to manage all of the chip's resources
+/// themselves. Gated peripherals, such as the UARTs and ports, are
+/// enabled through the [`Sim`](sim::Sim).
+///
+/// The board-level Cntrlr functions get these peripherals as they
+/// need them, and will fail while they are held here. Drop the
+/// relevant field to return a peripheral to the board layer.
+pub struct Peripherals<M> {
+    /// The multipurpose clock generator
+    pub mcg: mcg::Mcg<M>,
+
+    /// The external oscillator
+    pub osc: osc::Osc<M>,
+
+    /// The system integration module
+    pub sim: sim::Sim<M>,
+
+    /// The system mode controller
+    pub smc: smc::Smc<M>,
+
+    /// The ARM SysTick timer
+    pub systick: systick::SysTick<M>,
+}
+
+static TAKEN: Flag = Flag::new(false);
+
+impl<M> Peripherals<M>
+where
+    mcg::Mcg<M>: Peripheral,
+    osc::Osc<M>: Peripheral,
+    sim::Sim<M>: Peripheral,
+    smc::Smc<M>: Peripheral,
+    systick::SysTick<M>: Peripheral,
+{
+    /// Take all of the peripherals
+    ///
+    /// This succeeds at most once. Returns `None` if the peripherals
+    /// have already been taken, or if any of them is currently in
+    /// use.
+    pub fn take() -> Option<Self> {
+        if TAKEN.swap(true, Ordering::Acquire) {
+            return None;
+        }
+
+        let peripherals = (|| {
+            Some(Self {
+                mcg: Peripheral::get()?,
+                osc: Peripheral::get()?,
+                sim: Peripheral::get()?,
+                smc: Peripheral::get()?,
+                systick: Peripheral::get()?,
+            })
+        })();
+        if peripherals.is_none() {
+            TAKEN.store(false, Ordering::Release);
+        }
+        peripherals
+    }
+}
@@ -0,0 +1,202 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Programmable Delay Block
+//!
+//! The PDB is a 16-bit counter which is started by a hardware or
+//! software trigger, and which raises the ADC pre-triggers at fixed
+//! delays from the start of the count. This gives ADC conversions a
+//! deterministic phase relative to the trigger source.
+
+use super::{
+    super::{Mk20Dx128, Mk20Dx256, Mk64Fx512, Mk66Fx1M0},
+    sim::{Gate, GatedPeripheral},
+};
+use crate::register::{Register, Reserved};
+use bit_field::BitField;
+use core::marker::PhantomData;
+
+#[repr(C)]
+struct PdbChannelRegs {
+    c1: Register<u32>,
+    s: Register<u32>,
+    dly: [Register<u32>; 2],
+    _reserved: [Reserved<u32>; 6],
+}
+
+#[repr(C)]
+struct PdbRegs {
+    sc: Register<u32>,
+    mod_: Register<u32>,
+    cnt: Register<u32>,
+    idly: Register<u32>,
+    ch: [PdbChannelRegs; 2],
+}
+
+/// The handle to the PDB
+pub struct Pdb<M> {
+    regs: &'static mut PdbRegs,
+    _gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+/// The trigger input selected by software
+pub const SOFTWARE_TRIGGER: u32 = 15;
+
+impl<M> Pdb<M> {
+    /// Enable or disable the PDB
+    pub fn enable(&mut self, enabled: bool) {
+        self.regs.sc.update(|sc| {
+            sc.set_bit(7, enabled);
+        });
+    }
+
+    /// Select the input which starts the counter
+    ///
+    /// The meaning of each input is MCU-specific. Valid inputs are
+    /// `0..16`, where [`SOFTWARE_TRIGGER`] selects
+    /// [`trigger()`](Self::trigger).
+    pub fn set_trigger_input(&mut self, input: u32) {
+        assert!(input < 16);
+        self.regs.sc.update(|sc| {
+            sc.set_bits(8..12, input);
+        });
+    }
+
+    /// Set the counter clock dividers
+    ///
+    /// The bus clock is divided by `2^prescale * mult`. Valid
+    /// prescale values are `0..8`, and `mult` must be one of 1, 10,
+    /// 20, or 40.
+    pub fn set_prescaler(&mut self, prescale: u32, mult: u32) {
+        assert!(prescale < 8);
+        let mult = match mult {
+            1 => 0,
+            10 => 1,
+            20 => 2,
+            40 => 3,
+            _ => panic!("Invalid PDB multiplier"),
+        };
+        self.regs.sc.update(|sc| {
+            sc.set_bits(12..15, prescale);
+            sc.set_bits(2..4, mult);
+        });
+    }
+
+    /// Set whether the counter restarts itself after each period
+    pub fn set_continuous(&mut self, continuous: bool) {
+        self.regs.sc.update(|sc| {
+            sc.set_bit(1, continuous);
+        });
+    }
+
+    /// Set the period of the counter
+    ///
+    /// The counter resets when it reaches `modulus`.
+    pub fn set_modulus(&mut self, modulus: u16) {
+        self.regs.mod_.write(modulus as u32);
+    }
+
+    /// Configure an ADC pre-trigger
+    ///
+    /// `channel` is the ADC which is triggered, and `pretrigger` is
+    /// its `A` (0) or `B` (1) conversion. If `delay` is set, the
+    /// pre-trigger is raised when the counter reaches the delay.
+    /// Otherwise, it is disabled.
+    pub fn set_pretrigger(&mut self, channel: usize, pretrigger: usize, delay: Option<u16>) {
+        assert!(pretrigger < 2);
+        let ch = &mut self.regs.ch[channel];
+        if let Some(delay) = delay {
+            ch.dly[pretrigger].write(delay as u32);
+        }
+        ch.c1.update(|c1| {
+            c1.set_bit(pretrigger, delay.is_some());
+            c1.set_bit(pretrigger + 8, delay.is_some());
+            c1.set_bit(pretrigger + 16, false);
+        });
+    }
+
+    /// Check and clear the sequence error flags for an ADC
+    ///
+    /// A sequence error occurs when a pre-trigger is raised before
+    /// the ADC has finished the previous conversion.
+    pub fn take_sequence_errors(&mut self, channel: usize) -> bool {
+        let ch = &mut self.regs.ch[channel];
+        let errors = ch.s.read().get_bits(0..8);
+        ch.s.write(0);
+        errors != 0
+    }
+
+    /// Load the modulus and delay values
+    ///
+    /// New values take effect once they are loaded, after the PDB is
+    /// enabled.
+    pub fn load(&mut self) {
+        self.regs.sc.update(|sc| {
+            sc.set_bit(0, true);
+        });
+    }
+
+    /// Start the counter from software
+    ///
+    /// This only has an effect when the trigger input is
+    /// [`SOFTWARE_TRIGGER`].
+    pub fn trigger(&mut self) {
+        self.regs.sc.update(|sc| {
+            sc.set_bit(16, true);
+        });
+    }
+
+    /// Read the current count
+    pub fn counter(&self) -> u16 {
+        self.regs.cnt.read() as u16
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx128> for Pdb<Mk20Dx128> {
+    const GATE: (usize, usize) = (6, 22);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_6000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx256> for Pdb<Mk20Dx256> {
+    const GATE: (usize, usize) = (6, 22);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_6000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for Pdb<Mk64Fx512> {
+    const GATE: (usize, usize) = (6, 22);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_6000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Pdb<Mk66Fx1M0> {
+    const GATE: (usize, usize) = (6, 22);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_6000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
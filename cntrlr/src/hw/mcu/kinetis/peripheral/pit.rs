@@ -0,0 +1,174 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Periodic Interrupt Timer
+//!
+//! The PIT is a set of 32-bit down-counters, clocked from the bus
+//! clock. Each channel reloads from its load value when it reaches
+//! zero, and can interrupt on every reload. The K-series MCUs have
+//! four channels, and the L-series MCUs have two.
+
+use super::{
+    super::{Mk20Dx128, Mk20Dx256, Mk64Fx512, Mk66Fx1M0, Mkl26Z64},
+    sim::{Gate, GatedPeripheral},
+};
+use crate::register::{Register, Reserved};
+use bit_field::BitField;
+use core::marker::PhantomData;
+
+#[repr(C)]
+struct ChannelRegs {
+    ldval: Register<u32>,
+    cval: Register<u32>,
+    tctrl: Register<u32>,
+    tflg: Register<u32>,
+}
+
+#[repr(C)]
+struct PitRegs {
+    mcr: Register<u32>,
+    _reserved: [Reserved<u32>; 63],
+    channels: [ChannelRegs; 4],
+}
+
+/// The handle to the PIT
+pub struct Pit<M> {
+    regs: &'static mut PitRegs,
+    channels: usize,
+    _gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+impl<M> Pit<M> {
+    /// Enable or disable the timer module
+    ///
+    /// The module must be enabled before any channel is configured.
+    pub fn enable(&mut self, enabled: bool) {
+        self.regs.mcr.update(|mcr| {
+            mcr.set_bit(1, !enabled);
+        });
+    }
+
+    /// Stop the timers while the core is halted by a debugger
+    pub fn set_freeze(&mut self, freeze: bool) {
+        self.regs.mcr.update(|mcr| {
+            mcr.set_bit(0, freeze);
+        });
+    }
+
+    /// The number of channels on this MCU
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Set the value a channel reloads from
+    ///
+    /// The channel counts `load + 1` bus clocks between reloads. A
+    /// new load value takes effect at the next reload, unless the
+    /// channel is disabled and re-enabled.
+    pub fn set_load_value(&mut self, channel: usize, load: u32) {
+        assert!(channel < self.channels);
+        self.regs.channels[channel].ldval.write(load);
+    }
+
+    /// Read the current count of a channel
+    pub fn current_value(&self, channel: usize) -> u32 {
+        assert!(channel < self.channels);
+        self.regs.channels[channel].cval.read()
+    }
+
+    /// Enable or disable a channel
+    ///
+    /// Enabling a channel starts it counting down from its load value.
+    pub fn enable_channel(&mut self, channel: usize, enabled: bool) {
+        assert!(channel < self.channels);
+        self.regs.channels[channel].tctrl.update(|tctrl| {
+            tctrl.set_bit(0, enabled);
+        });
+    }
+
+    /// Enable or disable the reload interrupt of a channel
+    pub fn enable_interrupt(&mut self, channel: usize, enabled: bool) {
+        assert!(channel < self.channels);
+        self.regs.channels[channel].tctrl.update(|tctrl| {
+            tctrl.set_bit(1, enabled);
+        });
+    }
+
+    /// Check whether a channel has reloaded
+    pub fn interrupt_flag(&self, channel: usize) -> bool {
+        assert!(channel < self.channels);
+        self.regs.channels[channel].tflg.read().get_bit(0)
+    }
+
+    /// Clear the reload flag of a channel
+    pub fn clear_interrupt_flag(&mut self, channel: usize) {
+        assert!(channel < self.channels);
+        self.regs.channels[channel].tflg.write(1);
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx128> for Pit<Mk20Dx128> {
+    const GATE: (usize, usize) = (6, 23);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_7000 as *mut _),
+            channels: 4,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx256> for Pit<Mk20Dx256> {
+    const GATE: (usize, usize) = (6, 23);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_7000 as *mut _),
+            channels: 4,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for Pit<Mk64Fx512> {
+    const GATE: (usize, usize) = (6, 23);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_7000 as *mut _),
+            channels: 4,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Pit<Mk66Fx1M0> {
+    const GATE: (usize, usize) = (6, 23);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_7000 as *mut _),
+            channels: 4,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mkl26Z64> for Pit<Mkl26Z64> {
+    const GATE: (usize, usize) = (6, 23);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_7000 as *mut _),
+            channels: 2,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
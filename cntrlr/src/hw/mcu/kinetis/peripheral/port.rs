@@ -10,7 +10,13 @@ use super::{
     super::{Mk20Dx128, Mk20Dx256, Mk64Fx512, Mk66Fx1M0, Mkl26Z64},
     sim::{Gate, GatedPeripheral},
 };
-use crate::{digital::Pull, register::Register, sync::Flag};
+#[cfg(feature = "owners")]
+use crate::owners::Resource;
+use crate::{
+    digital::{DriveStrength, Edge, FilterClock, Pull, SlewRate},
+    register::Register,
+    sync::{without_interrupts, Flag},
+};
 use bit_field::BitField;
 use core::{default::Default, marker::PhantomData, sync::atomic::Ordering};
 
@@ -31,11 +37,21 @@ impl<M, const N: usize> Port<M, N> {
     /// Get a pin from this port
     ///
     /// Returns `None` if the pin is already in use
+    #[cfg_attr(feature = "owners", track_caller)]
     pub fn pin<const P: usize>(&self) -> Option<Pin<M, N, P>> {
         unsafe {
-            if P >= 32 || self.pins[P].swap(true, Ordering::Acquire) {
+            if P >= 32 {
+                None
+            } else if self.pins[P].swap(true, Ordering::Acquire) {
+                #[cfg(feature = "owners")]
+                crate::owners::conflicted(Resource::Pin { port: N, pin: P });
                 None
             } else {
+                #[cfg(feature = "owners")]
+                crate::owners::acquired(
+                    Resource::Pin { port: N, pin: P },
+                    core::panic::Location::caller(),
+                );
                 Some(Pin {
                     reg: &mut *self.base.add(P),
                     port: self,
@@ -43,8 +59,32 @@ impl<M, const N: usize> Port<M, N> {
             }
         }
     }
+
+    /// Set the clock and width of this port's digital input filter
+    ///
+    /// A pin with its digital filter enabled ignores any pulse
+    /// shorter than `width` cycles of the filter clock. This setting
+    /// is shared by every pin on the port. `width` must be below 32.
+    ///
+    /// The L-series MCUs have no digital input filters.
+    pub fn set_digital_filter(&self, clock: FilterClock, width: u32) {
+        assert!(width < 32);
+        unsafe {
+            (*self.base.add(DFCR)).write((clock == FilterClock::Lpo) as u32);
+            (*self.base.add(DFWR)).write(width);
+        }
+    }
 }
 
+/// The index of the digital filter enable register, from the first pin control register
+const DFER: usize = 48;
+
+/// The index of the digital filter clock register, from the first pin control register
+const DFCR: usize = 49;
+
+/// The index of the digital filter width register, from the first pin control register
+const DFWR: usize = 50;
+
 /// A pin from a port
 pub struct Pin<'a, M, const N: usize, const P: usize> {
     reg: &'static mut Register<u32>,
@@ -59,10 +99,96 @@ impl<M, const N: usize, const P: usize> Pin<'_, M, N, P> {
         });
         Gpio(self)
     }
+
+    /// Select one of this pin's functions by its mux number
+    ///
+    /// The function of each mux number is specific to the pin. Where
+    /// the pin has a type-checked conversion for a function, that
+    /// should be used instead.
+    pub fn set_mux(&mut self, mux: u32) {
+        assert!(mux < 8);
+        self.reg.update(|pcr| {
+            pcr.set_bits(8..11, mux);
+            // Don't clear a pending interrupt flag
+            pcr.set_bit(24, false);
+        });
+    }
+
+    /// Disconnect this pin from its digital functions, for use as an analog input
+    pub fn set_analog(&mut self) {
+        self.reg.update(|pcr| {
+            pcr.set_bits(8..11, 0);
+            // Don't clear a pending interrupt flag
+            pcr.set_bit(24, false);
+        });
+    }
+
+    /// Set the output drive strength of this pin
+    ///
+    /// Not every pin supports high drive strength. Returns `false`
+    /// if the requested strength could not be set.
+    pub fn set_drive_strength(&mut self, strength: DriveStrength) -> bool {
+        let high = strength == DriveStrength::High;
+        self.reg.update(|pcr| {
+            pcr.set_bit(6, high);
+            // Don't clear a pending interrupt flag
+            pcr.set_bit(24, false);
+        });
+        self.reg.read().get_bit(6) == high
+    }
+
+    /// Set the output slew rate of this pin
+    ///
+    /// Not every pin supports slow slew rate. Returns `false` if the
+    /// requested rate could not be set.
+    pub fn set_slew_rate(&mut self, rate: SlewRate) -> bool {
+        let slow = rate == SlewRate::Slow;
+        self.reg.update(|pcr| {
+            pcr.set_bit(2, slow);
+            // Don't clear a pending interrupt flag
+            pcr.set_bit(24, false);
+        });
+        self.reg.read().get_bit(2) == slow
+    }
+
+    /// Enable or disable the passive input filter of this pin
+    ///
+    /// The passive filter removes glitches shorter than a few tens
+    /// of nanoseconds. Not every pin has a passive filter. Returns
+    /// `false` if the filter could not be set.
+    pub fn set_passive_filter(&mut self, enabled: bool) -> bool {
+        self.reg.update(|pcr| {
+            pcr.set_bit(4, enabled);
+            // Don't clear a pending interrupt flag
+            pcr.set_bit(24, false);
+        });
+        self.reg.read().get_bit(4) == enabled
+    }
+
+    /// Enable or disable the digital input filter of this pin
+    ///
+    /// The filter is configured for the whole port, with
+    /// [`Port::set_digital_filter`].
+    ///
+    /// The L-series MCUs have no digital input filters.
+    pub fn enable_digital_filter(&mut self, enabled: bool) {
+        without_interrupts(|| unsafe {
+            (*self.port.base.add(DFER)).update(|dfer| {
+                dfer.set_bit(P, enabled);
+            });
+        });
+    }
+
+    /// The port this pin belongs to
+    pub fn port(&self) -> &Port<M, N> {
+        self.port
+    }
 }
 
 impl<M, const N: usize, const P: usize> Drop for Pin<'_, M, N, P> {
     fn drop(&mut self) {
+        #[cfg(feature = "owners")]
+        crate::owners::released(Resource::Pin { port: N, pin: P });
         self.port.pins[P].store(false, Ordering::Release);
     }
 }
@@ -955,176 +1081,1420 @@ impl Pin<'_, Mkl26Z64, 3, 3> {
     }
 }
 
-/// A pin which is configured as a UART reciever
-pub struct UartRx<P>(P);
-
-/// A pin which is configured as a UART transmitter
-pub struct UartTx<P>(P);
-
-/// A pin which is configured as a GPIO
-pub struct Gpio<P>(P);
-
-/// A pin which is configured as an SPI SDO
-pub struct Sdo<P>(P);
+impl Pin<'_, Mk64Fx512, 1, 6> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
 
-/// A pin which is configured as an SPI SDI
-pub struct Sdi<P>(P);
+impl Pin<'_, Mk64Fx512, 1, 7> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
 
-/// A pin which is configured as an SPI clock
-pub struct Sck<P>(P);
+impl Pin<'_, Mk64Fx512, 1, 8> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
 
-/// A pin which is configured as an SPI chip select
-pub struct Cs<P>(P);
+impl Pin<'_, Mk64Fx512, 1, 9> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
 
-impl<M, const N: usize, const P: usize> Gpio<Pin<'_, M, N, P>> {
-    /// Set this pin as high or low
-    pub fn write(&mut self, value: bool) {
-        unsafe {
-            let pdor: &mut Register<u32> = &mut *bitband_address(0x400F_F000 + 0x40 * N, P);
-            pdor.write(value.into());
-        }
+impl Pin<'_, Mk64Fx512, 1, 10> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
     }
+}
 
-    /// Read the status of this pin
-    pub fn read(&self) -> bool {
-        unsafe {
-            let pdir: &Register<u32> = &*bitband_address(0x400F_F010 + 0x40 * N, P);
-            pdir.read() != 0
-        }
+impl Pin<'_, Mk64Fx512, 1, 11> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
     }
+}
 
-    /// Set whether this pin is an output or an input
-    pub fn set_output(&mut self, output: bool) {
-        unsafe {
-            let pddr: &mut Register<u32> = &mut *bitband_address(0x400F_F014 + 0x40 * N, P);
-            pddr.write(output.into());
-        }
+impl Pin<'_, Mk64Fx512, 1, 16> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
     }
+}
 
-    /// Set pullup/down resistors on this pin
-    pub fn set_pull(&mut self, pull: Option<Pull>) {
-        self.0.reg.update(|pcr| {
-            match pull {
-                Some(pull) => {
-                    match pull {
-                        Pull::Up => pcr.set_bit(0, true),
-                        Pull::Down => pcr.set_bit(0, false),
-                    };
-                    pcr.set_bit(1, true)
-                }
-                None => pcr.set_bit(1, false),
-            };
+impl Pin<'_, Mk64Fx512, 1, 17> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
         });
+        FlexBusPin(self)
     }
+}
 
-    /// Set whether this pin is open-drain.
-    pub fn set_open_drain(&mut self, open_drain: bool) {
-        self.0.reg.update(|pcr| {
-            pcr.set_bit(5, open_drain);
+impl Pin<'_, Mk64Fx512, 1, 18> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
         });
+        FlexBusPin(self)
     }
 }
 
-impl super::uart::UartRx<Mk20Dx128, 0> for UartRx<Pin<'_, Mk20Dx128, 1, 16>> {}
-impl super::uart::UartRx<Mk20Dx128, 1> for UartRx<Pin<'_, Mk20Dx128, 2, 3>> {}
-impl super::uart::UartRx<Mk20Dx128, 2> for UartRx<Pin<'_, Mk20Dx128, 3, 2>> {}
-impl super::uart::UartTx<Mk20Dx128, 0> for UartTx<Pin<'_, Mk20Dx128, 1, 17>> {}
-impl super::uart::UartTx<Mk20Dx128, 1> for UartTx<Pin<'_, Mk20Dx128, 2, 4>> {}
-impl super::uart::UartTx<Mk20Dx128, 2> for UartTx<Pin<'_, Mk20Dx128, 3, 3>> {}
+impl Pin<'_, Mk64Fx512, 1, 19> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
 
-impl super::uart::UartRx<Mk20Dx256, 0> for UartRx<Pin<'_, Mk20Dx256, 1, 16>> {}
-impl super::uart::UartRx<Mk20Dx256, 1> for UartRx<Pin<'_, Mk20Dx256, 2, 3>> {}
-impl super::uart::UartRx<Mk20Dx256, 2> for UartRx<Pin<'_, Mk20Dx256, 3, 2>> {}
-impl super::uart::UartTx<Mk20Dx256, 0> for UartTx<Pin<'_, Mk20Dx256, 1, 17>> {}
-impl super::uart::UartTx<Mk20Dx256, 1> for UartTx<Pin<'_, Mk20Dx256, 2, 4>> {}
-impl super::uart::UartTx<Mk20Dx256, 2> for UartTx<Pin<'_, Mk20Dx256, 3, 3>> {}
+impl Pin<'_, Mk64Fx512, 1, 20> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
 
-impl super::uart::UartRx<Mk64Fx512, 3> for UartRx<Pin<'_, Mk64Fx512, 1, 10>> {}
-impl super::uart::UartRx<Mk64Fx512, 0> for UartRx<Pin<'_, Mk64Fx512, 1, 16>> {}
-impl super::uart::UartRx<Mk64Fx512, 1> for UartRx<Pin<'_, Mk64Fx512, 2, 3>> {}
-impl super::uart::UartRx<Mk64Fx512, 2> for UartRx<Pin<'_, Mk64Fx512, 3, 2>> {}
-impl super::uart::UartRx<Mk64Fx512, 5> for UartRx<Pin<'_, Mk64Fx512, 3, 9>> {}
-impl super::uart::UartRx<Mk64Fx512, 4> for UartRx<Pin<'_, Mk64Fx512, 4, 25>> {}
-impl super::uart::UartTx<Mk64Fx512, 3> for UartTx<Pin<'_, Mk64Fx512, 1, 11>> {}
-impl super::uart::UartTx<Mk64Fx512, 0> for UartTx<Pin<'_, Mk64Fx512, 1, 17>> {}
-impl super::uart::UartTx<Mk64Fx512, 1> for UartTx<Pin<'_, Mk64Fx512, 2, 4>> {}
-impl super::uart::UartTx<Mk64Fx512, 2> for UartTx<Pin<'_, Mk64Fx512, 3, 3>> {}
-impl super::uart::UartTx<Mk64Fx512, 5> for UartTx<Pin<'_, Mk64Fx512, 3, 8>> {}
-impl super::uart::UartTx<Mk64Fx512, 4> for UartTx<Pin<'_, Mk64Fx512, 4, 24>> {}
+impl Pin<'_, Mk64Fx512, 1, 21> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
 
-impl super::uart::UartRx<Mk66Fx1M0, 3> for UartRx<Pin<'_, Mk66Fx1M0, 1, 10>> {}
-impl super::uart::UartRx<Mk66Fx1M0, 0> for UartRx<Pin<'_, Mk66Fx1M0, 1, 16>> {}
-impl super::uart::UartRx<Mk66Fx1M0, 1> for UartRx<Pin<'_, Mk66Fx1M0, 2, 3>> {}
-impl super::uart::UartRx<Mk66Fx1M0, 2> for UartRx<Pin<'_, Mk66Fx1M0, 3, 2>> {}
-impl super::uart::UartRx<Mk66Fx1M0, 4> for UartRx<Pin<'_, Mk66Fx1M0, 4, 25>> {}
-impl super::uart::UartTx<Mk66Fx1M0, 3> for UartTx<Pin<'_, Mk66Fx1M0, 1, 11>> {}
-impl super::uart::UartTx<Mk66Fx1M0, 0> for UartTx<Pin<'_, Mk66Fx1M0, 1, 17>> {}
-impl super::uart::UartTx<Mk66Fx1M0, 1> for UartTx<Pin<'_, Mk66Fx1M0, 2, 4>> {}
-impl super::uart::UartTx<Mk66Fx1M0, 2> for UartTx<Pin<'_, Mk66Fx1M0, 3, 3>> {}
-impl super::uart::UartTx<Mk66Fx1M0, 4> for UartTx<Pin<'_, Mk66Fx1M0, 4, 24>> {}
+impl Pin<'_, Mk64Fx512, 1, 22> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
 
-impl super::uart::UartRx<Mkl26Z64, 0> for UartRx<Pin<'_, Mkl26Z64, 1, 16>> {}
-impl super::uart::UartRx<Mkl26Z64, 1> for UartRx<Pin<'_, Mkl26Z64, 2, 3>> {}
-impl super::uart::UartRx<Mkl26Z64, 2> for UartRx<Pin<'_, Mkl26Z64, 3, 2>> {}
-impl super::uart::UartTx<Mkl26Z64, 0> for UartTx<Pin<'_, Mkl26Z64, 1, 17>> {}
-impl super::uart::UartTx<Mkl26Z64, 1> for UartTx<Pin<'_, Mkl26Z64, 2, 4>> {}
-impl super::uart::UartTx<Mkl26Z64, 2> for UartTx<Pin<'_, Mkl26Z64, 3, 3>> {}
+impl Pin<'_, Mk64Fx512, 1, 23> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
 
-impl super::spi::Sdi<Mk20Dx128, 0> for Sdi<Pin<'_, Mk20Dx128, 2, 7>> {}
-impl super::spi::Sdo<Mk20Dx128, 0> for Sdo<Pin<'_, Mk20Dx128, 2, 6>> {}
-impl super::spi::Sck<Mk20Dx128, 0> for Sck<Pin<'_, Mk20Dx128, 2, 5>> {}
-impl super::spi::Cs<Mk20Dx128, 0> for Cs<Pin<'_, Mk20Dx128, 2, 0>> {
-    fn cs_allowed(&self, bit: usize) -> bool {
-        bit == 4
+impl Pin<'_, Mk64Fx512, 2, 0> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
     }
 }
-impl super::spi::Cs<Mk20Dx128, 0> for Cs<Pin<'_, Mk20Dx128, 2, 3>> {
-    fn cs_allowed(&self, bit: usize) -> bool {
-        bit == 1
+
+impl Pin<'_, Mk64Fx512, 2, 1> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
     }
 }
-impl super::spi::Cs<Mk20Dx128, 0> for Cs<Pin<'_, Mk20Dx128, 2, 4>> {
-    fn cs_allowed(&self, bit: usize) -> bool {
-        bit == 0
+
+impl Pin<'_, Mk64Fx512, 2, 2> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
     }
 }
-impl super::spi::Cs<Mk20Dx128, 0> for Cs<Pin<'_, Mk20Dx128, 3, 5>> {
-    fn cs_allowed(&self, bit: usize) -> bool {
-        bit == 2
+
+impl Pin<'_, Mk64Fx512, 2, 3> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
     }
 }
-impl super::spi::Cs<Mk20Dx128, 0> for Cs<Pin<'_, Mk20Dx128, 3, 6>> {
-    fn cs_allowed(&self, bit: usize) -> bool {
-        bit == 3
+
+impl Pin<'_, Mk64Fx512, 2, 4> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
     }
 }
 
-impl super::spi::Sdi<Mk20Dx256, 0> for Sdi<Pin<'_, Mk20Dx256, 2, 7>> {}
-impl super::spi::Sdo<Mk20Dx256, 0> for Sdo<Pin<'_, Mk20Dx256, 2, 6>> {}
-impl super::spi::Sck<Mk20Dx256, 0> for Sck<Pin<'_, Mk20Dx256, 2, 5>> {}
-impl super::spi::Cs<Mk20Dx256, 0> for Cs<Pin<'_, Mk20Dx256, 2, 0>> {
-    fn cs_allowed(&self, bit: usize) -> bool {
-        bit == 4
+impl Pin<'_, Mk64Fx512, 2, 5> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
     }
 }
-impl super::spi::Cs<Mk20Dx256, 0> for Cs<Pin<'_, Mk20Dx256, 2, 3>> {
-    fn cs_allowed(&self, bit: usize) -> bool {
-        bit == 1
+
+impl Pin<'_, Mk64Fx512, 2, 6> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
     }
 }
-impl super::spi::Cs<Mk20Dx256, 0> for Cs<Pin<'_, Mk20Dx256, 2, 4>> {
-    fn cs_allowed(&self, bit: usize) -> bool {
-        bit == 0
+
+impl Pin<'_, Mk64Fx512, 2, 7> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
     }
 }
-impl super::spi::Cs<Mk20Dx256, 0> for Cs<Pin<'_, Mk20Dx256, 3, 5>> {
-    fn cs_allowed(&self, bit: usize) -> bool {
-        bit == 2
+
+impl Pin<'_, Mk64Fx512, 2, 8> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
     }
 }
-impl super::spi::Cs<Mk20Dx256, 0> for Cs<Pin<'_, Mk20Dx256, 3, 6>> {
-    fn cs_allowed(&self, bit: usize) -> bool {
-        bit == 3
+
+impl Pin<'_, Mk64Fx512, 2, 9> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 2, 10> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 2, 11> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 2, 12> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 2, 13> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 2, 14> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 2, 15> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 2, 16> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 2, 17> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 2, 18> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 2, 19> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 3, 0> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 3, 1> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 3, 2> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 3, 3> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 3, 4> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 3, 5> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 3, 6> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 1, 6> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 1, 7> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 1, 8> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 1, 9> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 1, 10> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 1, 11> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 1, 16> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 1, 17> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 1, 18> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 1, 19> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 1, 20> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 1, 21> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 1, 22> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 1, 23> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 0> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 1> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 2> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 3> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 4> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 5> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 6> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 7> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 8> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 9> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 10> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 11> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 12> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 13> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 14> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 15> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 16> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 17> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 18> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 19> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 3, 0> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 3, 1> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 3, 2> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 3, 3> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 3, 4> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 3, 5> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 3, 6> {
+    /// Use this pin for the FlexBus
+    pub fn into_flexbus(self) -> FlexBusPin<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 5);
+        });
+        FlexBusPin(self)
+    }
+}
+
+impl Pin<'_, Mk20Dx128, 1, 2> {
+    /// Use this pin as an I2C clock
+    pub fn into_i2c_scl(self) -> I2cScl<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cScl(self)
+    }
+}
+
+impl Pin<'_, Mk20Dx128, 1, 3> {
+    /// Use this pin as an I2C data line
+    pub fn into_i2c_sda(self) -> I2cSda<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cSda(self)
+    }
+}
+
+impl Pin<'_, Mk20Dx256, 1, 2> {
+    /// Use this pin as an I2C clock
+    pub fn into_i2c_scl(self) -> I2cScl<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cScl(self)
+    }
+}
+
+impl Pin<'_, Mk20Dx256, 1, 3> {
+    /// Use this pin as an I2C data line
+    pub fn into_i2c_sda(self) -> I2cSda<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cSda(self)
+    }
+}
+
+impl Pin<'_, Mk20Dx256, 2, 10> {
+    /// Use this pin as an I2C clock
+    pub fn into_i2c_scl(self) -> I2cScl<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cScl(self)
+    }
+}
+
+impl Pin<'_, Mk20Dx256, 2, 11> {
+    /// Use this pin as an I2C data line
+    pub fn into_i2c_sda(self) -> I2cSda<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cSda(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 1, 2> {
+    /// Use this pin as an I2C clock
+    pub fn into_i2c_scl(self) -> I2cScl<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cScl(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 1, 3> {
+    /// Use this pin as an I2C data line
+    pub fn into_i2c_sda(self) -> I2cSda<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cSda(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 2, 10> {
+    /// Use this pin as an I2C clock
+    pub fn into_i2c_scl(self) -> I2cScl<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cScl(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 2, 11> {
+    /// Use this pin as an I2C data line
+    pub fn into_i2c_sda(self) -> I2cSda<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cSda(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 1, 2> {
+    /// Use this pin as an I2C clock
+    pub fn into_i2c_scl(self) -> I2cScl<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cScl(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 1, 3> {
+    /// Use this pin as an I2C data line
+    pub fn into_i2c_sda(self) -> I2cSda<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cSda(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 10> {
+    /// Use this pin as an I2C clock
+    pub fn into_i2c_scl(self) -> I2cScl<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cScl(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 11> {
+    /// Use this pin as an I2C data line
+    pub fn into_i2c_sda(self) -> I2cSda<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cSda(self)
+    }
+}
+
+impl Pin<'_, Mkl26Z64, 1, 2> {
+    /// Use this pin as an I2C clock
+    pub fn into_i2c_scl(self) -> I2cScl<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cScl(self)
+    }
+}
+
+impl Pin<'_, Mkl26Z64, 1, 3> {
+    /// Use this pin as an I2C data line
+    pub fn into_i2c_sda(self) -> I2cSda<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cSda(self)
+    }
+}
+
+impl Pin<'_, Mkl26Z64, 2, 1> {
+    /// Use this pin as an I2C clock
+    pub fn into_i2c_scl(self) -> I2cScl<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cScl(self)
+    }
+}
+
+impl Pin<'_, Mkl26Z64, 2, 2> {
+    /// Use this pin as an I2C data line
+    pub fn into_i2c_sda(self) -> I2cSda<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+            ctl.set_bit(5, true);
+        });
+        I2cSda(self)
+    }
+}
+
+impl Pin<'_, Mk20Dx256, 0, 12> {
+    /// Use this pin as a CAN transmitter
+    pub fn into_can_tx(self) -> CanTx<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+        });
+        CanTx(self)
+    }
+}
+
+impl Pin<'_, Mk20Dx256, 0, 13> {
+    /// Use this pin as a CAN receiver
+    pub fn into_can_rx(self) -> CanRx<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+        });
+        CanRx(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 0, 12> {
+    /// Use this pin as a CAN transmitter
+    pub fn into_can_tx(self) -> CanTx<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+        });
+        CanTx(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 0, 13> {
+    /// Use this pin as a CAN receiver
+    pub fn into_can_rx(self) -> CanRx<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+        });
+        CanRx(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 0, 12> {
+    /// Use this pin as a CAN transmitter
+    pub fn into_can_tx(self) -> CanTx<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+        });
+        CanTx(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 0, 13> {
+    /// Use this pin as a CAN receiver
+    pub fn into_can_rx(self) -> CanRx<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+        });
+        CanRx(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 4, 24> {
+    /// Use this pin as a CAN transmitter
+    pub fn into_can_tx(self) -> CanTx<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+        });
+        CanTx(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 4, 25> {
+    /// Use this pin as a CAN receiver
+    pub fn into_can_rx(self) -> CanRx<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 2);
+        });
+        CanRx(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 2, 6> {
+    /// Use this pin as the I2S master clock
+    pub fn into_i2s_mclk(self) -> I2sMclk<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 6);
+        });
+        I2sMclk(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 2, 3> {
+    /// Use this pin as the I2S bit clock
+    pub fn into_i2s_bclk(self) -> I2sBclk<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 6);
+        });
+        I2sBclk(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 2, 2> {
+    /// Use this pin as the I2S frame sync
+    pub fn into_i2s_fs(self) -> I2sFs<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 6);
+        });
+        I2sFs(self)
+    }
+}
+
+impl Pin<'_, Mk64Fx512, 2, 1> {
+    /// Use this pin as the I2S transmit data line
+    pub fn into_i2s_txd(self) -> I2sTxd<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 6);
+        });
+        I2sTxd(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 6> {
+    /// Use this pin as the I2S master clock
+    pub fn into_i2s_mclk(self) -> I2sMclk<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 6);
+        });
+        I2sMclk(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 3> {
+    /// Use this pin as the I2S bit clock
+    pub fn into_i2s_bclk(self) -> I2sBclk<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 6);
+        });
+        I2sBclk(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 2> {
+    /// Use this pin as the I2S frame sync
+    pub fn into_i2s_fs(self) -> I2sFs<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 6);
+        });
+        I2sFs(self)
+    }
+}
+
+impl Pin<'_, Mk66Fx1M0, 2, 1> {
+    /// Use this pin as the I2S transmit data line
+    pub fn into_i2s_txd(self) -> I2sTxd<Self> {
+        self.reg.update(|ctl| {
+            ctl.set_bits(8..11, 6);
+        });
+        I2sTxd(self)
+    }
+}
+
+/// A pin which is configured as a UART reciever
+pub struct UartRx<P>(P);
+
+/// A pin which is configured as a UART transmitter
+pub struct UartTx<P>(P);
+
+/// A pin which is configured as a GPIO
+pub struct Gpio<P>(P);
+
+/// A pin which is configured as an SPI SDO
+pub struct Sdo<P>(P);
+
+/// A pin which is configured as an SPI SDI
+pub struct Sdi<P>(P);
+
+/// A pin which is configured as an SPI clock
+pub struct Sck<P>(P);
+
+/// A pin which is configured as an SPI chip select
+pub struct Cs<P>(P);
+
+/// A pin which is configured for the FlexBus
+pub struct FlexBusPin<P>(P);
+
+/// A pin which is configured as an I2C clock
+pub struct I2cScl<P>(P);
+
+/// A pin which is configured as an I2C data line
+pub struct I2cSda<P>(P);
+
+/// A pin which is configured as a CAN transmitter
+pub struct CanTx<P>(P);
+
+/// A pin which is configured as a CAN receiver
+pub struct CanRx<P>(P);
+
+/// A pin which is configured as the I2S master clock
+pub struct I2sMclk<P>(P);
+
+/// A pin which is configured as the I2S bit clock
+pub struct I2sBclk<P>(P);
+
+/// A pin which is configured as the I2S frame sync
+pub struct I2sFs<P>(P);
+
+/// A pin which is configured as the I2S transmit data line
+pub struct I2sTxd<P>(P);
+
+impl<M, const N: usize, const P: usize> Gpio<Pin<'_, M, N, P>> {
+    /// Set this pin as high or low
+    pub fn write(&mut self, value: bool) {
+        unsafe {
+            let pdor: &mut Register<u32> = &mut *bitband_address(0x400F_F000 + 0x40 * N, P);
+            pdor.write(value.into());
+        }
+    }
+
+    /// Read the status of this pin
+    pub fn read(&self) -> bool {
+        unsafe {
+            let pdir: &Register<u32> = &*bitband_address(0x400F_F010 + 0x40 * N, P);
+            pdir.read() != 0
+        }
+    }
+
+    /// Read the state this pin is set to output
+    pub fn read_output(&self) -> bool {
+        unsafe {
+            let pdor: &Register<u32> = &*bitband_address(0x400F_F000 + 0x40 * N, P);
+            pdor.read() != 0
+        }
+    }
+
+    /// Set whether this pin is an output or an input
+    pub fn set_output(&mut self, output: bool) {
+        unsafe {
+            let pddr: &mut Register<u32> = &mut *bitband_address(0x400F_F014 + 0x40 * N, P);
+            pddr.write(output.into());
+        }
+    }
+
+    /// Set pullup/down resistors on this pin
+    pub fn set_pull(&mut self, pull: Option<Pull>) {
+        self.0.reg.update(|pcr| {
+            match pull {
+                Some(pull) => {
+                    match pull {
+                        Pull::Up => pcr.set_bit(0, true),
+                        Pull::Down => pcr.set_bit(0, false),
+                    };
+                    pcr.set_bit(1, true)
+                }
+                None => pcr.set_bit(1, false),
+            };
+        });
+    }
+
+    /// Set whether this pin is open-drain.
+    pub fn set_open_drain(&mut self, open_drain: bool) {
+        self.0.reg.update(|pcr| {
+            pcr.set_bit(5, open_drain);
+        });
+    }
+
+    /// Set the output drive strength of this pin
+    ///
+    /// See [`Pin::set_drive_strength`].
+    pub fn set_drive_strength(&mut self, strength: DriveStrength) -> bool {
+        self.0.set_drive_strength(strength)
+    }
+
+    /// Set the output slew rate of this pin
+    ///
+    /// See [`Pin::set_slew_rate`].
+    pub fn set_slew_rate(&mut self, rate: SlewRate) -> bool {
+        self.0.set_slew_rate(rate)
+    }
+
+    /// Set which edges on this pin trigger the port interrupt
+    ///
+    /// If `edge` is `None`, the interrupt is disabled. Any pending
+    /// interrupt flag for this pin is cleared.
+    pub fn set_interrupt(&mut self, edge: Option<Edge>) {
+        let irqc = match edge {
+            None => 0,
+            Some(Edge::Rising) => 9,
+            Some(Edge::Falling) => 10,
+            Some(Edge::Both) => 11,
+        };
+        self.0.reg.update(|pcr| {
+            pcr.set_bits(16..20, irqc);
+            pcr.set_bit(24, true);
+        });
+    }
+}
+
+impl super::uart::UartRx<Mk20Dx128, 0> for UartRx<Pin<'_, Mk20Dx128, 1, 16>> {}
+impl super::uart::UartRx<Mk20Dx128, 1> for UartRx<Pin<'_, Mk20Dx128, 2, 3>> {}
+impl super::uart::UartRx<Mk20Dx128, 2> for UartRx<Pin<'_, Mk20Dx128, 3, 2>> {}
+impl super::uart::UartTx<Mk20Dx128, 0> for UartTx<Pin<'_, Mk20Dx128, 1, 17>> {}
+impl super::uart::UartTx<Mk20Dx128, 1> for UartTx<Pin<'_, Mk20Dx128, 2, 4>> {}
+impl super::uart::UartTx<Mk20Dx128, 2> for UartTx<Pin<'_, Mk20Dx128, 3, 3>> {}
+
+impl super::uart::UartRx<Mk20Dx256, 0> for UartRx<Pin<'_, Mk20Dx256, 1, 16>> {}
+impl super::uart::UartRx<Mk20Dx256, 1> for UartRx<Pin<'_, Mk20Dx256, 2, 3>> {}
+impl super::uart::UartRx<Mk20Dx256, 2> for UartRx<Pin<'_, Mk20Dx256, 3, 2>> {}
+impl super::uart::UartTx<Mk20Dx256, 0> for UartTx<Pin<'_, Mk20Dx256, 1, 17>> {}
+impl super::uart::UartTx<Mk20Dx256, 1> for UartTx<Pin<'_, Mk20Dx256, 2, 4>> {}
+impl super::uart::UartTx<Mk20Dx256, 2> for UartTx<Pin<'_, Mk20Dx256, 3, 3>> {}
+
+impl super::uart::UartRx<Mk64Fx512, 3> for UartRx<Pin<'_, Mk64Fx512, 1, 10>> {}
+impl super::uart::UartRx<Mk64Fx512, 0> for UartRx<Pin<'_, Mk64Fx512, 1, 16>> {}
+impl super::uart::UartRx<Mk64Fx512, 1> for UartRx<Pin<'_, Mk64Fx512, 2, 3>> {}
+impl super::uart::UartRx<Mk64Fx512, 2> for UartRx<Pin<'_, Mk64Fx512, 3, 2>> {}
+impl super::uart::UartRx<Mk64Fx512, 5> for UartRx<Pin<'_, Mk64Fx512, 3, 9>> {}
+impl super::uart::UartRx<Mk64Fx512, 4> for UartRx<Pin<'_, Mk64Fx512, 4, 25>> {}
+impl super::uart::UartTx<Mk64Fx512, 3> for UartTx<Pin<'_, Mk64Fx512, 1, 11>> {}
+impl super::uart::UartTx<Mk64Fx512, 0> for UartTx<Pin<'_, Mk64Fx512, 1, 17>> {}
+impl super::uart::UartTx<Mk64Fx512, 1> for UartTx<Pin<'_, Mk64Fx512, 2, 4>> {}
+impl super::uart::UartTx<Mk64Fx512, 2> for UartTx<Pin<'_, Mk64Fx512, 3, 3>> {}
+impl super::uart::UartTx<Mk64Fx512, 5> for UartTx<Pin<'_, Mk64Fx512, 3, 8>> {}
+impl super::uart::UartTx<Mk64Fx512, 4> for UartTx<Pin<'_, Mk64Fx512, 4, 24>> {}
+
+impl super::uart::UartRx<Mk66Fx1M0, 3> for UartRx<Pin<'_, Mk66Fx1M0, 1, 10>> {}
+impl super::uart::UartRx<Mk66Fx1M0, 0> for UartRx<Pin<'_, Mk66Fx1M0, 1, 16>> {}
+impl super::uart::UartRx<Mk66Fx1M0, 1> for UartRx<Pin<'_, Mk66Fx1M0, 2, 3>> {}
+impl super::uart::UartRx<Mk66Fx1M0, 2> for UartRx<Pin<'_, Mk66Fx1M0, 3, 2>> {}
+impl super::uart::UartRx<Mk66Fx1M0, 4> for UartRx<Pin<'_, Mk66Fx1M0, 4, 25>> {}
+impl super::uart::UartTx<Mk66Fx1M0, 3> for UartTx<Pin<'_, Mk66Fx1M0, 1, 11>> {}
+impl super::uart::UartTx<Mk66Fx1M0, 0> for UartTx<Pin<'_, Mk66Fx1M0, 1, 17>> {}
+impl super::uart::UartTx<Mk66Fx1M0, 1> for UartTx<Pin<'_, Mk66Fx1M0, 2, 4>> {}
+impl super::uart::UartTx<Mk66Fx1M0, 2> for UartTx<Pin<'_, Mk66Fx1M0, 3, 3>> {}
+impl super::uart::UartTx<Mk66Fx1M0, 4> for UartTx<Pin<'_, Mk66Fx1M0, 4, 24>> {}
+
+impl super::uart::UartRx<Mkl26Z64, 0> for UartRx<Pin<'_, Mkl26Z64, 1, 16>> {}
+impl super::uart::UartRx<Mkl26Z64, 1> for UartRx<Pin<'_, Mkl26Z64, 2, 3>> {}
+impl super::uart::UartRx<Mkl26Z64, 2> for UartRx<Pin<'_, Mkl26Z64, 3, 2>> {}
+impl super::uart::UartTx<Mkl26Z64, 0> for UartTx<Pin<'_, Mkl26Z64, 1, 17>> {}
+impl super::uart::UartTx<Mkl26Z64, 1> for UartTx<Pin<'_, Mkl26Z64, 2, 4>> {}
+impl super::uart::UartTx<Mkl26Z64, 2> for UartTx<Pin<'_, Mkl26Z64, 3, 3>> {}
+
+impl super::spi::Sdi<Mk20Dx128, 0> for Sdi<Pin<'_, Mk20Dx128, 2, 7>> {}
+impl super::spi::Sdo<Mk20Dx128, 0> for Sdo<Pin<'_, Mk20Dx128, 2, 6>> {}
+impl super::spi::Sck<Mk20Dx128, 0> for Sck<Pin<'_, Mk20Dx128, 2, 5>> {}
+impl super::spi::Cs<Mk20Dx128, 0> for Cs<Pin<'_, Mk20Dx128, 2, 0>> {
+    fn cs_allowed(&self, bit: usize) -> bool {
+        bit == 4
+    }
+}
+impl super::spi::Cs<Mk20Dx128, 0> for Cs<Pin<'_, Mk20Dx128, 2, 3>> {
+    fn cs_allowed(&self, bit: usize) -> bool {
+        bit == 1
+    }
+}
+impl super::spi::Cs<Mk20Dx128, 0> for Cs<Pin<'_, Mk20Dx128, 2, 4>> {
+    fn cs_allowed(&self, bit: usize) -> bool {
+        bit == 0
+    }
+}
+impl super::spi::Cs<Mk20Dx128, 0> for Cs<Pin<'_, Mk20Dx128, 3, 5>> {
+    fn cs_allowed(&self, bit: usize) -> bool {
+        bit == 2
+    }
+}
+impl super::spi::Cs<Mk20Dx128, 0> for Cs<Pin<'_, Mk20Dx128, 3, 6>> {
+    fn cs_allowed(&self, bit: usize) -> bool {
+        bit == 3
+    }
+}
+
+impl super::spi::Sdi<Mk20Dx256, 0> for Sdi<Pin<'_, Mk20Dx256, 2, 7>> {}
+impl super::spi::Sdo<Mk20Dx256, 0> for Sdo<Pin<'_, Mk20Dx256, 2, 6>> {}
+impl super::spi::Sck<Mk20Dx256, 0> for Sck<Pin<'_, Mk20Dx256, 2, 5>> {}
+impl super::spi::Cs<Mk20Dx256, 0> for Cs<Pin<'_, Mk20Dx256, 2, 0>> {
+    fn cs_allowed(&self, bit: usize) -> bool {
+        bit == 4
+    }
+}
+impl super::spi::Cs<Mk20Dx256, 0> for Cs<Pin<'_, Mk20Dx256, 2, 3>> {
+    fn cs_allowed(&self, bit: usize) -> bool {
+        bit == 1
+    }
+}
+impl super::spi::Cs<Mk20Dx256, 0> for Cs<Pin<'_, Mk20Dx256, 2, 4>> {
+    fn cs_allowed(&self, bit: usize) -> bool {
+        bit == 0
+    }
+}
+impl super::spi::Cs<Mk20Dx256, 0> for Cs<Pin<'_, Mk20Dx256, 3, 5>> {
+    fn cs_allowed(&self, bit: usize) -> bool {
+        bit == 2
+    }
+}
+impl super::spi::Cs<Mk20Dx256, 0> for Cs<Pin<'_, Mk20Dx256, 3, 6>> {
+    fn cs_allowed(&self, bit: usize) -> bool {
+        bit == 3
     }
 }
 
@@ -1228,6 +2598,50 @@ impl super::spi::Cs<Mk66Fx1M0, 2> for Cs<Pin<'_, Mk66Fx1M0, 3, 15>> {
     }
 }
 
+impl super::i2c::Scl<Mk20Dx128, 0> for I2cScl<Pin<'_, Mk20Dx128, 1, 2>> {}
+impl super::i2c::Sda<Mk20Dx128, 0> for I2cSda<Pin<'_, Mk20Dx128, 1, 3>> {}
+
+impl super::i2c::Scl<Mk20Dx256, 0> for I2cScl<Pin<'_, Mk20Dx256, 1, 2>> {}
+impl super::i2c::Sda<Mk20Dx256, 0> for I2cSda<Pin<'_, Mk20Dx256, 1, 3>> {}
+impl super::i2c::Scl<Mk20Dx256, 1> for I2cScl<Pin<'_, Mk20Dx256, 2, 10>> {}
+impl super::i2c::Sda<Mk20Dx256, 1> for I2cSda<Pin<'_, Mk20Dx256, 2, 11>> {}
+
+impl super::i2c::Scl<Mk64Fx512, 0> for I2cScl<Pin<'_, Mk64Fx512, 1, 2>> {}
+impl super::i2c::Sda<Mk64Fx512, 0> for I2cSda<Pin<'_, Mk64Fx512, 1, 3>> {}
+impl super::i2c::Scl<Mk64Fx512, 1> for I2cScl<Pin<'_, Mk64Fx512, 2, 10>> {}
+impl super::i2c::Sda<Mk64Fx512, 1> for I2cSda<Pin<'_, Mk64Fx512, 2, 11>> {}
+
+impl super::i2c::Scl<Mk66Fx1M0, 0> for I2cScl<Pin<'_, Mk66Fx1M0, 1, 2>> {}
+impl super::i2c::Sda<Mk66Fx1M0, 0> for I2cSda<Pin<'_, Mk66Fx1M0, 1, 3>> {}
+impl super::i2c::Scl<Mk66Fx1M0, 1> for I2cScl<Pin<'_, Mk66Fx1M0, 2, 10>> {}
+impl super::i2c::Sda<Mk66Fx1M0, 1> for I2cSda<Pin<'_, Mk66Fx1M0, 2, 11>> {}
+
+impl super::i2c::Scl<Mkl26Z64, 0> for I2cScl<Pin<'_, Mkl26Z64, 1, 2>> {}
+impl super::i2c::Sda<Mkl26Z64, 0> for I2cSda<Pin<'_, Mkl26Z64, 1, 3>> {}
+impl super::i2c::Scl<Mkl26Z64, 1> for I2cScl<Pin<'_, Mkl26Z64, 2, 1>> {}
+impl super::i2c::Sda<Mkl26Z64, 1> for I2cSda<Pin<'_, Mkl26Z64, 2, 2>> {}
+
+impl super::flexcan::Tx<Mk20Dx256, 0> for CanTx<Pin<'_, Mk20Dx256, 0, 12>> {}
+impl super::flexcan::Rx<Mk20Dx256, 0> for CanRx<Pin<'_, Mk20Dx256, 0, 13>> {}
+
+impl super::flexcan::Tx<Mk64Fx512, 0> for CanTx<Pin<'_, Mk64Fx512, 0, 12>> {}
+impl super::flexcan::Rx<Mk64Fx512, 0> for CanRx<Pin<'_, Mk64Fx512, 0, 13>> {}
+
+impl super::flexcan::Tx<Mk66Fx1M0, 0> for CanTx<Pin<'_, Mk66Fx1M0, 0, 12>> {}
+impl super::flexcan::Rx<Mk66Fx1M0, 0> for CanRx<Pin<'_, Mk66Fx1M0, 0, 13>> {}
+impl super::flexcan::Tx<Mk66Fx1M0, 1> for CanTx<Pin<'_, Mk66Fx1M0, 4, 24>> {}
+impl super::flexcan::Rx<Mk66Fx1M0, 1> for CanRx<Pin<'_, Mk66Fx1M0, 4, 25>> {}
+
+impl super::i2s::Mclk<Mk64Fx512> for I2sMclk<Pin<'_, Mk64Fx512, 2, 6>> {}
+impl super::i2s::TxBclk<Mk64Fx512> for I2sBclk<Pin<'_, Mk64Fx512, 2, 3>> {}
+impl super::i2s::TxFs<Mk64Fx512> for I2sFs<Pin<'_, Mk64Fx512, 2, 2>> {}
+impl super::i2s::TxData<Mk64Fx512> for I2sTxd<Pin<'_, Mk64Fx512, 2, 1>> {}
+
+impl super::i2s::Mclk<Mk66Fx1M0> for I2sMclk<Pin<'_, Mk66Fx1M0, 2, 6>> {}
+impl super::i2s::TxBclk<Mk66Fx1M0> for I2sBclk<Pin<'_, Mk66Fx1M0, 2, 3>> {}
+impl super::i2s::TxFs<Mk66Fx1M0> for I2sFs<Pin<'_, Mk66Fx1M0, 2, 2>> {}
+impl super::i2s::TxData<Mk66Fx1M0> for I2sTxd<Pin<'_, Mk66Fx1M0, 2, 1>> {}
+
 unsafe impl GatedPeripheral<Mk20Dx128> for Port<Mk20Dx128, 0> {
     const GATE: (usize, usize) = (5, 9);
 
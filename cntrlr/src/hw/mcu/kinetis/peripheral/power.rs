@@ -0,0 +1,57 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Explicit peripheral clock gating
+//!
+//! Gated peripherals turn their clock on when their handle is
+//! created with [`Sim::enable_peripheral`](super::sim::Sim::enable_peripheral),
+//! and back off when the handle is dropped. Some blocks are clocked
+//! from reset whether or not anything uses them. These functions
+//! turn clocks on and off directly, so that power-sensitive
+//! applications can shut down the blocks they don't use and measure
+//! the difference.
+//!
+//! A gate is referenced by its live peripheral handle and by
+//! [`gate_on`], and its clock runs while either is held. A gate
+//! cannot be turned off while a handle holds it, and dropping the
+//! handle leaves the clock running if [`gate_on`] was called.
+
+use super::sim::{self, GatedPeripheral};
+
+/// Error type for clock gating
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The clock cannot be turned off because a handle to the peripheral is alive
+    InUse,
+}
+
+/// Turn off the clock to a peripheral
+///
+/// Accesses to the peripheral's registers fault while its clock is
+/// off.
+pub fn gate_off<M, P: GatedPeripheral<M>>() -> Result<(), Error> {
+    if sim::release_gate(P::GATE) {
+        Ok(())
+    } else {
+        Err(Error::InUse)
+    }
+}
+
+/// Turn on the clock to a peripheral
+///
+/// The clock stays on until [`gate_off`] is called, even if a handle
+/// to the peripheral is created and dropped in the meantime.
+pub fn gate_on<M, P: GatedPeripheral<M>>() {
+    sim::hold_gate(P::GATE);
+}
+
+/// Whether the clock to a peripheral is on
+pub fn is_gated_on<M, P: GatedPeripheral<M>>() -> bool {
+    sim::gate_enabled(P::GATE)
+}
+
+/// Whether a handle to a peripheral is alive
+pub fn is_in_use<M, P: GatedPeripheral<M>>() -> bool {
+    sim::gate_in_use(P::GATE)
+}
@@ -0,0 +1,90 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! VBAT Register File
+//!
+//! Eight 32-bit registers in the VBAT power domain, with the RTC. They
+//! keep their contents while the rest of the MCU is unpowered, and
+//! are only reset when VBAT itself loses power. Only the K-series
+//! MCUs have a VBAT domain.
+
+use crate::{register::Register, sync::Flag};
+use core::{marker::PhantomData, sync::atomic::Ordering};
+
+/// The number of registers
+pub const REGISTERS: usize = 8;
+
+#[repr(C)]
+struct RfvbatRegs {
+    reg: [Register<u32>; REGISTERS],
+}
+
+/// The handle to the VBAT register file
+pub struct Rfvbat<M> {
+    regs: &'static mut RfvbatRegs,
+    _mcu: PhantomData<M>,
+}
+
+static LOCK: Flag = Flag::new(false);
+
+macro_rules! get {
+    ($m:ident, $s:literal) => {
+        #[cfg(any(doc, mcu = $s))]
+        #[cfg_attr(feature = "doc-cfg", doc(cfg(mcu = $s)))]
+        impl super::Peripheral for Rfvbat<super::super::$m> {
+            fn get() -> Option<Self> {
+                unsafe {
+                    if LOCK.swap(true, Ordering::Acquire) {
+                        None
+                    } else {
+                        Some(Self {
+                            regs: &mut *(0x4003_E000 as *mut _),
+                            _mcu: PhantomData,
+                        })
+                    }
+                }
+            }
+        }
+    };
+}
+
+get!(Mk20Dx128, "mk20dx128");
+get!(Mk20Dx256, "mk20dx256");
+get!(Mk64Fx512, "mk64fx512");
+get!(Mk66Fx1M0, "mk66fx1m0");
+
+impl<M> Rfvbat<M>
+where
+    Rfvbat<M>: super::Peripheral,
+{
+    /// Get the handle to the register file
+    ///
+    /// Returns `None` if the register file is already in use.
+    pub fn get() -> Option<Self> {
+        super::Peripheral::get()
+    }
+}
+
+impl<M> Rfvbat<M> {
+    /// Read a register
+    ///
+    /// # Panics
+    /// Panics if `index` is not less than [`REGISTERS`].
+    pub fn read(&self, index: usize) -> u32 {
+        self.regs.reg[index].read()
+    }
+
+    /// Write a register
+    ///
+    /// # Panics
+    /// Panics if `index` is not less than [`REGISTERS`].
+    pub fn write(&mut self, index: usize, value: u32) {
+        self.regs.reg[index].write(value);
+    }
+}
+
+impl<M> Drop for Rfvbat<M> {
+    fn drop(&mut self) {
+        LOCK.store(false, Ordering::Release);
+    }
+}
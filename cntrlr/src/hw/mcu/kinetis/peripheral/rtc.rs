@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Real-Time Clock
+//!
+//! The RTC is a 32-bit seconds counter, driven by its own 32.768kHz
+//! crystal oscillator. It is powered from the VBAT pin, so it keeps
+//! counting, and keeps its registers, while the rest of the MCU is
+//! unpowered. Only the K-series MCUs are supported, as the L-series
+//! RTC has no oscillator of its own.
+
+use super::{
+    super::{Mk20Dx128, Mk20Dx256, Mk64Fx512, Mk66Fx1M0},
+    sim::{Gate, GatedPeripheral},
+};
+use crate::register::Register;
+use bit_field::BitField;
+use core::marker::PhantomData;
+
+#[repr(C)]
+struct RtcRegs {
+    tsr: Register<u32>,
+    tpr: Register<u32>,
+    tar: Register<u32>,
+    tcr: Register<u32>,
+    cr: Register<u32>,
+    sr: Register<u32>,
+    lr: Register<u32>,
+    ier: Register<u32>,
+}
+
+/// The handle to the RTC
+///
+/// Dropping the handle only turns off the clock to the registers. The
+/// counter keeps running.
+pub struct Rtc<M> {
+    regs: &'static mut RtcRegs,
+    _gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+impl<M> Rtc<M> {
+    /// Enable the 32.768kHz oscillator
+    ///
+    /// `load` is the load capacitance added to the crystal, in
+    /// picofarads. It is rounded down to a multiple of 2, up to
+    /// 30pF. The oscillator takes up to a second to start, during
+    /// which the counter does not advance.
+    pub fn enable_oscillator(&mut self, load: u32) {
+        let load = load.min(30) / 2;
+        self.regs.cr.update(|cr| {
+            // The capacitor bits run from 16pF down to 2pF
+            cr.set_bit(10, load.get_bit(3));
+            cr.set_bit(11, load.get_bit(2));
+            cr.set_bit(12, load.get_bit(1));
+            cr.set_bit(13, load.get_bit(0));
+            cr.set_bit(8, true);
+        });
+    }
+
+    /// Whether the 32.768kHz oscillator is enabled
+    pub fn oscillator_enabled(&self) -> bool {
+        self.regs.cr.read().get_bit(8)
+    }
+
+    /// Whether the count is invalid
+    ///
+    /// This is set when the VBAT domain is powered on, and cleared by
+    /// [`set_seconds`](Self::set_seconds). The counter does not run
+    /// while it is set.
+    pub fn time_invalid(&self) -> bool {
+        self.regs.sr.read().get_bit(0)
+    }
+
+    /// Whether the counter is running
+    pub fn is_enabled(&self) -> bool {
+        self.regs.sr.read().get_bit(4)
+    }
+
+    /// Start or stop the counter
+    pub fn enable(&mut self, enabled: bool) {
+        self.regs.sr.update(|sr| {
+            sr.set_bit(4, enabled);
+        });
+    }
+
+    /// The number of seconds counted
+    pub fn seconds(&self) -> u32 {
+        // The count can change between reads, so it is read until
+        // two reads agree.
+        loop {
+            let seconds = self.regs.tsr.read();
+            if self.regs.tsr.read() == seconds {
+                return seconds;
+            }
+        }
+    }
+
+    /// Set the number of seconds counted
+    ///
+    /// The counter is stopped while it is written, and the fraction
+    /// of a second already counted is discarded. The counter is left
+    /// running if it was running before.
+    pub fn set_seconds(&mut self, seconds: u32) {
+        let enabled = self.is_enabled();
+        self.enable(false);
+        self.regs.tpr.write(0);
+        self.regs.tsr.write(seconds);
+        self.enable(enabled);
+    }
+
+    /// Set the alarm
+    ///
+    /// The alarm flag is set when the counter increments past
+    /// `seconds`. Writing the alarm clears the flag.
+    pub fn set_alarm(&mut self, seconds: u32) {
+        self.regs.tar.write(seconds);
+    }
+
+    /// Check whether the alarm has passed
+    pub fn alarm_flag(&self) -> bool {
+        self.regs.sr.read().get_bit(2)
+    }
+
+    /// Enable or disable the alarm interrupt
+    ///
+    /// The time invalid and overflow interrupts, which are enabled at
+    /// reset, are always disabled.
+    pub fn enable_interrupt(&mut self, enabled: bool) {
+        self.regs.ier.update(|ier| {
+            ier.set_bits(0..2, 0);
+            ier.set_bit(2, enabled);
+        });
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx128> for Rtc<Mk20Dx128> {
+    const GATE: (usize, usize) = (6, 29);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_D000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx256> for Rtc<Mk20Dx256> {
+    const GATE: (usize, usize) = (6, 29);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_D000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for Rtc<Mk64Fx512> {
+    const GATE: (usize, usize) = (6, 29);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_D000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Rtc<Mk66Fx1M0> {
+    const GATE: (usize, usize) = (6, 29);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_D000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
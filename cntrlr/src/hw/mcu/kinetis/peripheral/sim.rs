@@ -4,12 +4,14 @@
 //! System Integration Module
 
 use super::super::{Mk20Dx128, Mk20Dx256, Mk64Fx512, Mk66Fx1M0, Mkl26Z64};
+#[cfg(feature = "owners")]
+use crate::owners::Resource;
 use crate::{
     register::{Register, Reserved},
-    sync::Flag,
+    sync::{without_interrupts, Flag},
 };
 use bit_field::BitField;
-use core::{marker::PhantomData, sync::atomic::Ordering};
+use core::{cell::UnsafeCell, marker::PhantomData, sync::atomic::Ordering};
 
 /// A clock-gated peripheral
 ///
@@ -91,6 +93,29 @@ pub enum PeripheralClockSource {
     Irc48,
 }
 
+/// The trigger used to start hardware-triggered ADC conversions
+#[derive(PartialEq)]
+pub enum AdcTrigger {
+    /// The PDB pre-triggers
+    ///
+    /// On MCUs without a PDB, this is an MCU-specific default
+    /// trigger.
+    Pdb,
+
+    /// An alternate trigger source
+    ///
+    /// The meaning of each source is MCU-specific. Only one of the
+    /// `A` or `B` conversions is started, depending on
+    /// `pretrigger_b`.
+    Alternate {
+        /// The trigger source, in the range `0..16`
+        source: u32,
+
+        /// Whether the `B` conversion is started, rather than `A`
+        pretrigger_b: bool,
+    },
+}
+
 static LOCK: Flag = Flag::new(false);
 
 macro_rules! get {
@@ -152,20 +177,51 @@ impl<M> Sim<M> {
         });
     }
 
+    /// Select the hardware trigger for an ADC
+    ///
+    /// Valid ADCs are 0 and 1, though not every MCU has two ADCs.
+    pub fn set_adc_trigger(&mut self, adc: usize, trigger: AdcTrigger) {
+        assert!(adc < 2);
+        let shift = adc * 8;
+        self.regs.sopt7.update(|sopt7| match trigger {
+            AdcTrigger::Pdb => {
+                sopt7.set_bit(shift + 7, false);
+            }
+            AdcTrigger::Alternate {
+                source,
+                pretrigger_b,
+            } => {
+                assert!(source < 16);
+                sopt7.set_bits(shift..shift + 4, source);
+                sopt7.set_bit(shift + 4, pretrigger_b);
+                sopt7.set_bit(shift + 7, true);
+            }
+        });
+    }
+
     /// Enable  a peripheral
     ///
     /// Enable a clock-gated peripheral, returning its handle. Returns
-    /// `None` if the peripheral is already active.
+    /// `None` if a handle to the peripheral is already alive.
+    #[cfg_attr(feature = "owners", track_caller)]
     pub fn enable_peripheral<P: GatedPeripheral<M>>(&mut self) -> Option<P> {
-        unsafe {
-            let gate = bitband_address(self.regs.scgc.as_mut_ptr().add(P::GATE.0 - 1), P::GATE.1);
-            if core::ptr::read_volatile(gate) != 0 {
-                None
-            } else {
-                core::ptr::write_volatile(gate, 1);
-                Some(P::new(Gate(gate)))
+        let gate = Gate::acquire(P::GATE);
+        #[cfg(feature = "owners")]
+        let gate = {
+            let resource = Resource::Peripheral(core::any::type_name::<P>());
+            match gate {
+                Some(mut gate) => {
+                    crate::owners::acquired(resource, core::panic::Location::caller());
+                    gate.1 = Some(resource);
+                    Some(gate)
+                }
+                None => {
+                    crate::owners::conflicted(resource);
+                    None
+                }
             }
-        }
+        };
+        gate.map(|gate| unsafe { P::new(gate) })
     }
 }
 
@@ -243,6 +299,16 @@ impl Sim<Mk64Fx512> {
             clkdiv.set_bits(1..4, denominator - 1);
         })
     }
+
+    /// Allow the CPU and DMA to access the FlexBus
+    ///
+    /// By default, all off-chip accesses fault. If `instructions` is
+    /// false, only data can be read from the FlexBus.
+    pub fn set_flexbus_security(&mut self, instructions: bool) {
+        self.regs.sopt2.update(|sopt2| {
+            sopt2.set_bits(8..10, if instructions { 3 } else { 2 });
+        });
+    }
 }
 
 impl Sim<Mk66Fx1M0> {
@@ -269,6 +335,29 @@ impl Sim<Mk66Fx1M0> {
             clkdiv.set_bits(1..4, denominator - 1);
         })
     }
+
+    /// Allow the CPU and DMA to access the FlexBus
+    ///
+    /// By default, all off-chip accesses fault. If `instructions` is
+    /// false, only data can be read from the FlexBus.
+    pub fn set_flexbus_security(&mut self, instructions: bool) {
+        self.regs.sopt2.update(|sopt2| {
+            sopt2.set_bits(8..10, if instructions { 3 } else { 2 });
+        });
+    }
+
+    /// Set the TPM clock source
+    pub fn set_tpm_source(&mut self, source: Option<UartClockSource>) {
+        let source = match source {
+            None => 0,
+            Some(UartClockSource::PllFll) => 1,
+            Some(UartClockSource::Oscer) => 2,
+            Some(UartClockSource::Mcgir) => 3,
+        };
+        self.regs.sopt2.update(|sopt2| {
+            sopt2.set_bits(24..26, source);
+        });
+    }
 }
 
 impl Sim<Mkl26Z64> {
@@ -302,6 +391,21 @@ impl Sim<Mkl26Z64> {
             sopt2.set_bits(26..28, source);
         });
     }
+
+    /// Set the TPM clock source
+    ///
+    /// The TPMs can use the same clocks as UART 0.
+    pub fn set_tpm_source(&mut self, source: Option<UartClockSource>) {
+        let source = match source {
+            None => 0,
+            Some(UartClockSource::PllFll) => 1,
+            Some(UartClockSource::Oscer) => 2,
+            Some(UartClockSource::Mcgir) => 3,
+        };
+        self.regs.sopt2.update(|sopt2| {
+            sopt2.set_bits(24..26, source);
+        });
+    }
 }
 
 impl<M> Drop for Sim<M> {
@@ -312,16 +416,103 @@ impl<M> Drop for Sim<M> {
 
 /// A handle to an enabled clock gate.
 ///
-/// This disables the held clock gate when it is dropped.
-pub struct Gate(*mut u32);
+/// This disables the held clock gate when it is dropped, unless the
+/// gate is also held on by [`power::gate_on`](super::power::gate_on).
+pub struct Gate((usize, usize), #[cfg(feature = "owners")] Option<Resource>);
 
 unsafe impl Send for Gate {}
 
+impl Gate {
+    fn acquire(gate: (usize, usize)) -> Option<Self> {
+        without_interrupts(|| unsafe {
+            let handles = &mut (*GATES.handles.get())[gate.0 - 1];
+            if handles.get_bit(gate.1) {
+                None
+            } else {
+                handles.set_bit(gate.1, true);
+                core::ptr::write_volatile(gate_address(gate), 1);
+                Some(Self(
+                    gate,
+                    #[cfg(feature = "owners")]
+                    None,
+                ))
+            }
+        })
+    }
+}
+
 impl Drop for Gate {
     fn drop(&mut self) {
-        unsafe {
-            core::ptr::write_volatile(self.0, 0);
+        #[cfg(feature = "owners")]
+        if let Some(resource) = self.1 {
+            crate::owners::released(resource);
+        }
+        let gate = self.0;
+        without_interrupts(|| unsafe {
+            (*GATES.handles.get())[gate.0 - 1].set_bit(gate.1, false);
+            if !(*GATES.explicit.get())[gate.0 - 1].get_bit(gate.1) {
+                core::ptr::write_volatile(gate_address(gate), 0);
+            }
+        });
+    }
+}
+
+/// The references to each clock gate
+///
+/// A gate is referenced by its live [`Gate`] handle, and by an
+/// explicit [`power::gate_on`](super::power::gate_on). Its clock runs
+/// while either reference is held.
+struct GateRefs {
+    handles: UnsafeCell<[u32; 7]>,
+    explicit: UnsafeCell<[u32; 7]>,
+}
+
+unsafe impl Sync for GateRefs {}
+
+static GATES: GateRefs = GateRefs {
+    handles: UnsafeCell::new([0; 7]),
+    explicit: UnsafeCell::new([0; 7]),
+};
+
+/// Hold a clock gate on, without a peripheral handle
+pub(crate) fn hold_gate(gate: (usize, usize)) {
+    without_interrupts(|| unsafe {
+        (*GATES.explicit.get())[gate.0 - 1].set_bit(gate.1, true);
+        core::ptr::write_volatile(gate_address(gate), 1);
+    });
+}
+
+/// Turn a clock gate off
+///
+/// Returns `false`, leaving the clock running, if a peripheral handle
+/// holds the gate.
+pub(crate) fn release_gate(gate: (usize, usize)) -> bool {
+    without_interrupts(|| unsafe {
+        if (*GATES.handles.get())[gate.0 - 1].get_bit(gate.1) {
+            false
+        } else {
+            (*GATES.explicit.get())[gate.0 - 1].set_bit(gate.1, false);
+            core::ptr::write_volatile(gate_address(gate), 0);
+            true
         }
+    })
+}
+
+/// Whether a clock gate is currently on
+pub(crate) fn gate_enabled(gate: (usize, usize)) -> bool {
+    unsafe { core::ptr::read_volatile(gate_address(gate)) != 0 }
+}
+
+/// Whether a peripheral handle holds a clock gate
+pub(crate) fn gate_in_use(gate: (usize, usize)) -> bool {
+    without_interrupts(|| unsafe { (*GATES.handles.get())[gate.0 - 1].get_bit(gate.1) })
+}
+
+fn gate_address(gate: (usize, usize)) -> *mut u32 {
+    unsafe {
+        let regs = 0x4004_7000 as *mut SimRegs;
+        let scgc = core::ptr::addr_of_mut!((*regs).scgc) as *mut Register<u32>;
+        bitband_address(scgc.add(gate.0 - 1), gate.1)
     }
 }
 
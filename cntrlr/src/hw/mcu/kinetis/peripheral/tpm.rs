@@ -0,0 +1,155 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Timer/PWM Module
+//!
+//! The TPM is a smaller relative of the [FlexTimer](super::ftm), with
+//! up to 6 channels. It counts a clock selected in the SIM, rather
+//! than the bus clock. Only edge-aligned PWM is supported.
+
+use super::{
+    super::{Mk66Fx1M0, Mkl26Z64},
+    sim::{Gate, GatedPeripheral},
+};
+use crate::register::Register;
+use bit_field::BitField;
+use core::marker::PhantomData;
+
+#[repr(C)]
+struct TpmChannelRegs {
+    csc: Register<u32>,
+    cv: Register<u32>,
+}
+
+#[repr(C)]
+struct TpmRegs {
+    sc: Register<u32>,
+    cnt: Register<u32>,
+    mod_: Register<u32>,
+    ch: [TpmChannelRegs; 6],
+}
+
+/// The handle to a TPM
+pub struct Tpm<M, const N: usize> {
+    regs: &'static mut TpmRegs,
+    _gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+impl<M, const N: usize> Tpm<M, N> {
+    /// Start the counter with a period
+    ///
+    /// The counter counts the TPM clock divided by `2^prescale`, and
+    /// restarts after `period` counts. Valid prescale values are
+    /// `0..8`, and periods are `1..0x1_0000`.
+    pub fn set_period(&mut self, prescale: u32, period: u32) {
+        assert!(prescale < 8);
+        assert!(period > 0 && period < 0x1_0000);
+
+        // The prescaler can only be changed while the counter is
+        // stopped, which takes effect in the TPM clock domain.
+        self.regs.sc.update(|sc| {
+            sc.set_bits(3..5, 0);
+        });
+        while self.regs.sc.read().get_bits(3..5) != 0 {}
+
+        self.regs.cnt.write(0);
+        self.regs.mod_.write(period - 1);
+        self.regs.sc.update(|sc| {
+            sc.set_bits(0..3, prescale);
+        });
+        self.regs.sc.update(|sc| {
+            sc.set_bits(3..5, 1);
+        });
+    }
+
+    /// The prescale and period of the counter
+    ///
+    /// Returns `None` if the counter is stopped.
+    pub fn period(&self) -> Option<(u32, u32)> {
+        let sc = self.regs.sc.read();
+        if sc.get_bits(3..5) == 0 {
+            None
+        } else {
+            Some((sc.get_bits(0..3), self.regs.mod_.read() + 1))
+        }
+    }
+
+    /// Enable or disable the PWM output of a channel
+    ///
+    /// A disabled channel releases its pin.
+    pub fn enable_pwm(&mut self, channel: usize, enabled: bool) {
+        let mut csc = 0;
+        csc.set_bit(5, enabled);
+        csc.set_bit(3, enabled);
+        self.regs.ch[channel].csc.write(csc);
+    }
+
+    /// Set the number of counts in each period for which a channel is high
+    ///
+    /// The new value takes effect at the start of the next period. A
+    /// value of the period or more holds the output high.
+    pub fn set_duty(&mut self, channel: usize, duty: u32) {
+        self.regs.ch[channel].cv.write(duty.min(0xFFFF));
+    }
+}
+
+unsafe impl GatedPeripheral<Mkl26Z64> for Tpm<Mkl26Z64, 0> {
+    const GATE: (usize, usize) = (6, 24);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_8000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mkl26Z64> for Tpm<Mkl26Z64, 1> {
+    const GATE: (usize, usize) = (6, 25);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_9000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mkl26Z64> for Tpm<Mkl26Z64, 2> {
+    const GATE: (usize, usize) = (6, 26);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4003_A000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Tpm<Mk66Fx1M0, 1> {
+    const GATE: (usize, usize) = (2, 9);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x400C_9000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Tpm<Mk66Fx1M0, 2> {
+    const GATE: (usize, usize) = (2, 10);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x400C_A000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,202 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Touch Sensing Input
+//!
+//! The TSI measures the capacitance of an electrode by charging and
+//! discharging it with a constant current, and counting cycles of a
+//! reference oscillator over a number of scans. A finger on the
+//! electrode raises the count.
+//!
+//! There are two versions of the module. The K-series MCUs on the
+//! Teensy 3.0 and 3.2 have a [`Tsi`] which keeps a count for each of
+//! its 16 channels. The MKL26 and MK66 have a [`TsiLite`], which
+//! scans one selected channel. The MK64 has no TSI.
+
+use super::{
+    super::{Mk20Dx128, Mk20Dx256, Mk66Fx1M0, Mkl26Z64},
+    sim::{Gate, GatedPeripheral},
+};
+use crate::register::{Register, Reserved};
+use bit_field::BitField;
+use core::marker::PhantomData;
+
+/// The number of TSI channels
+pub const CHANNELS: u32 = 16;
+
+#[repr(C)]
+struct TsiRegs {
+    gencs: Register<u32>,
+    scanc: Register<u32>,
+    pen: Register<u32>,
+    wucntr: Register<u32>,
+    _reserved_0: [Reserved<u32>; 60],
+    cntr: [Register<u32>; 8],
+    threshold: Register<u32>,
+}
+
+#[repr(C)]
+struct TsiLiteRegs {
+    gencs: Register<u32>,
+    data: Register<u32>,
+    tshd: Register<u32>,
+}
+
+/// The settings of a scan
+#[derive(Clone, Copy, Debug)]
+pub struct Scan {
+    /// The electrode charge current, from 0 to 15
+    ///
+    /// This is `2 * (current + 1)` microamps on the [`Tsi`], and
+    /// `2^current` microamps, up to 7, on the [`TsiLite`].
+    pub current: u32,
+
+    /// The number of scans summed into each count, from 1 to 32
+    pub scans: u32,
+
+    /// The electrode oscillator prescaler, as a power of two from 0 to 7
+    pub prescale: u32,
+}
+
+/// A TSI which can count one channel at a time
+pub trait TouchSense {
+    /// The highest charge current the module supports
+    const MAX_CURRENT: u32;
+
+    /// Start scanning one channel
+    fn start(&mut self, channel: u32, scan: Scan);
+
+    /// Check whether the scan started by [`start`](Self::start) has finished
+    fn is_complete(&self) -> bool;
+
+    /// The count of the scan started by [`start`](Self::start)
+    fn result(&self, channel: u32) -> u16;
+}
+
+/// The handle to a K-series TSI
+pub struct Tsi<M> {
+    regs: &'static mut TsiRegs,
+    _gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+impl<M> TouchSense for Tsi<M> {
+    const MAX_CURRENT: u32 = 15;
+
+    fn start(&mut self, channel: u32, scan: Scan) {
+        assert!(channel < CHANNELS);
+        // The configuration can only change while the module is disabled
+        self.regs.gencs.write(0);
+        self.regs.pen.write(1 << channel);
+        self.regs.scanc.update(|scanc| {
+            scanc.set_bits(24..28, 3);
+            scanc.set_bits(16..20, scan.current);
+        });
+        let mut gencs = 0;
+        // EOSF is cleared by writing 1
+        gencs.set_bit(15, true);
+        gencs.set_bits(19..24, scan.scans - 1);
+        gencs.set_bits(16..19, scan.prescale);
+        gencs.set_bit(7, true);
+        gencs.set_bit(8, true);
+        self.regs.gencs.write(gencs);
+    }
+
+    fn is_complete(&self) -> bool {
+        self.regs.gencs.read().get_bit(15)
+    }
+
+    fn result(&self, channel: u32) -> u16 {
+        let counts = self.regs.cntr[channel as usize / 2].read();
+        if channel % 2 == 0 {
+            counts.get_bits(0..16) as u16
+        } else {
+            counts.get_bits(16..32) as u16
+        }
+    }
+}
+
+/// The handle to an MKL26 or MK66 TSI
+pub struct TsiLite<M> {
+    regs: &'static mut TsiLiteRegs,
+    _gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+impl<M> TouchSense for TsiLite<M> {
+    const MAX_CURRENT: u32 = 7;
+
+    fn start(&mut self, channel: u32, scan: Scan) {
+        assert!(channel < CHANNELS);
+        let mut gencs = 0;
+        gencs.set_bits(21..24, 4);
+        gencs.set_bits(16..19, scan.current);
+        gencs.set_bits(13..16, scan.prescale);
+        gencs.set_bits(8..13, scan.scans - 1);
+        gencs.set_bit(7, true);
+        // EOSF is cleared by writing 1
+        gencs.set_bit(2, true);
+        self.regs.gencs.write(gencs);
+
+        let mut data = 0;
+        data.set_bits(28..32, channel);
+        data.set_bit(22, true);
+        self.regs.data.write(data);
+    }
+
+    fn is_complete(&self) -> bool {
+        self.regs.gencs.read().get_bit(2)
+    }
+
+    fn result(&self, _channel: u32) -> u16 {
+        self.regs.data.read().get_bits(0..16) as u16
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx128> for Tsi<Mk20Dx128> {
+    const GATE: (usize, usize) = (5, 5);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4004_5000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx256> for Tsi<Mk20Dx256> {
+    const GATE: (usize, usize) = (5, 5);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4004_5000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for TsiLite<Mk66Fx1M0> {
+    const GATE: (usize, usize) = (5, 5);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4004_5000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mkl26Z64> for TsiLite<Mkl26Z64> {
+    const GATE: (usize, usize) = (5, 5);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4004_5000 as *mut _),
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
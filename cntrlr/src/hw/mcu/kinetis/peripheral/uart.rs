@@ -7,7 +7,10 @@ use super::{
     super::{Mk20Dx128, Mk20Dx256, Mk64Fx512, Mk66Fx1M0, Mkl26Z64},
     sim::{Gate, GatedPeripheral},
 };
-use crate::register::{Register, Reserved};
+use crate::{
+    io::Parity,
+    register::{Register, Reserved},
+};
 use bit_field::BitField;
 use core::marker::PhantomData;
 
@@ -72,6 +75,36 @@ impl<M, const N: usize> Uart<M, (), (), N> {
     }
 }
 
+impl<M, const N: usize> Uart<M, (), (), N>
+where
+    Uart<M, (), (), N>: Oversample,
+{
+    /// Set the UART divisor and oversampling ratio
+    ///
+    /// The final baud rate is the source clock divided by
+    /// `divisor * ratio`. The divisor has 13 bits, and the ratio must
+    /// be from 4 to 32. Ratios below 8 enable sampling on both edges
+    /// of the clock, which the hardware requires for them.
+    pub fn set_oversampled_divisor(&mut self, divisor: usize, ratio: u8) {
+        assert!(Self::OVERSAMPLE);
+        assert!((1..0x2000).contains(&divisor));
+        assert!((4..=32).contains(&ratio));
+        self.regs.c4.update(|c4| {
+            // OSR
+            c4.set_bits(0..5, ratio - 1);
+        });
+        self.regs.c5.update(|c5| {
+            // BOTHEDGE
+            c5.set_bit(1, ratio < 8);
+        });
+
+        self.regs.bdh.update(|bdh| {
+            bdh.set_bits(0..5, divisor.get_bits(8..13) as u8);
+        });
+        self.regs.bdl.write(divisor.get_bits(0..8) as u8);
+    }
+}
+
 impl<M, T, R, const N: usize> Uart<M, T, R, N> {
     /// Reverse the polarity of this UART
     pub fn invert(&mut self, invert: bool) {
@@ -84,6 +117,121 @@ impl<M, T, R, const N: usize> Uart<M, T, R, N> {
             s2.set_bit(4, invert);
         });
     }
+
+    /// Start counting idle line time after the stop bit
+    ///
+    /// By default, the idle time is counted from the start bit, so
+    /// that trailing ones in a character count towards it and the
+    /// line can be detected idle early.
+    pub fn set_idle_after_stop(&mut self, after_stop: bool) {
+        self.regs.c1.update(|c1| {
+            // ILT
+            c1.set_bit(2, after_stop);
+        });
+    }
+
+    /// Connect the transmitter output to the receiver input
+    ///
+    /// In loopback mode, the receive pin is not used.
+    pub fn set_loopback(&mut self, loopback: bool) {
+        self.regs.c1.update(|c1| {
+            // LOOPS
+            c1.set_bit(7, loopback);
+            // RSRC
+            c1.set_bit(5, false);
+        });
+    }
+
+    /// Use 9-bit characters, with the ninth bit marking addresses
+    ///
+    /// This is the address mark mode used by multi-drop networks such
+    /// as RS-485. While [`Self::sleep_until_address()`] is in effect,
+    /// the receiver ignores characters until one with the address
+    /// mark arrives.
+    pub fn set_address_mark(&mut self, enabled: bool) {
+        self.regs.c1.update(|c1| {
+            // M
+            c1.set_bit(4, enabled);
+            // WAKE
+            c1.set_bit(3, enabled);
+        });
+    }
+
+    /// Use 9-bit characters
+    ///
+    /// When parity is enabled, the parity bit is the ninth bit, and
+    /// the eight data bits are unchanged. Otherwise the ninth bit is
+    /// sent as a one, so that it looks like an extra stop bit, and
+    /// ignored on receive.
+    pub fn set_nine_bit(&mut self, enabled: bool) {
+        self.regs.c1.update(|c1| {
+            // M
+            c1.set_bit(4, enabled);
+        });
+        self.regs.c3.update(|c3| {
+            // T8
+            c3.set_bit(6, enabled);
+        });
+    }
+
+    /// Send and check a parity bit
+    ///
+    /// The parity bit replaces the last data bit of each character,
+    /// so [`Self::set_nine_bit()`] keeps eight data bits with
+    /// parity. Characters which fail the check are still received.
+    pub fn set_parity(&mut self, parity: Parity) {
+        self.regs.c1.update(|c1| {
+            // PE
+            c1.set_bit(1, parity != Parity::None);
+            // PT
+            c1.set_bit(0, parity == Parity::Odd);
+        });
+    }
+
+    /// Ignore received characters until the next address mark
+    ///
+    /// The hardware wakes the receiver when an address is received,
+    /// and the address is the first character read.
+    pub fn sleep_until_address(&mut self) {
+        self.regs.c2.update(|c2| {
+            // RWU
+            c2.set_bit(1, true);
+        });
+    }
+}
+
+impl<M, T, R, const N: usize> Uart<M, T, R, N>
+where
+    Uart<M, T, R, N>: AddressMatch,
+{
+    /// Only wake for an address mark matching `address`
+    ///
+    /// Addresses which do not match are discarded by the
+    /// hardware. Pass `None` to wake for any address.
+    pub fn set_match_address(&mut self, address: Option<u8>) {
+        assert!(Self::ADDRESS_MATCH || address.is_none());
+        if let Some(address) = address {
+            self.regs.ma1.write(address);
+        }
+        self.regs.c4.update(|c4| {
+            // MAEN1
+            c4.set_bit(7, address.is_some());
+        });
+    }
+}
+
+impl<M, T, R, const N: usize> Uart<M, T, R, N>
+where
+    Uart<M, T, R, N>: TwoStopBits,
+{
+    /// Send and expect two stop bits, rather than one
+    pub fn set_two_stop_bits(&mut self, two: bool) {
+        assert!(Self::TWO_STOP_BITS || !two);
+        self.regs.bdh.update(|bdh| {
+            // SBNS
+            bdh.set_bit(5, two);
+        });
+    }
 }
 
 impl<M, R, const N: usize> Uart<M, (), R, N> {
@@ -142,6 +290,24 @@ impl<M, T, R: UartRx<M, N>, const N: usize> Uart<M, T, R, N> {
         }
     }
 
+    /// Check and clear the receiver overrun flag.
+    ///
+    /// On most UARTs the overrun flag is cleared by the next read of
+    /// the data register, so this should be called before draining
+    /// the receiver with [`Self::read_data()`]. UART0 on the
+    /// Kinetis-L parts instead requires the flag to be explicitly
+    /// cleared, which this also does.
+    pub fn check_overrun(&mut self) -> bool {
+        if self.regs.s1.read().get_bit(3) {
+            // OR is write-1-to-clear on Kinetis-L UART0, and S1 is
+            // read-only on all other UARTs.
+            self.regs.s1.write(1 << 3);
+            true
+        } else {
+            false
+        }
+    }
+
     /// Enable the UART to interupt when a byte is recieved.
     pub fn enable_rx_intr(&mut self) {
         self.regs.c2.update(|c2| {
@@ -149,6 +315,36 @@ impl<M, T, R: UartRx<M, N>, const N: usize> Uart<M, T, R, N> {
         });
     }
 
+    /// Check and clear the idle line flag.
+    ///
+    /// The flag is set once the receive line has been idle for a
+    /// character time after receiving data. UART0 on the Kinetis-L
+    /// parts clears the flag when it is written. All other UARTs
+    /// clear it by reading the data register after S1, so it is only
+    /// cleared here if the receiver is empty. Otherwise, it is
+    /// cleared by the next [`Self::read_data()`].
+    pub fn check_idle(&mut self) -> bool {
+        let s1 = self.regs.s1.read();
+        if s1.get_bit(4) {
+            // IDLE is write-1-to-clear on Kinetis-L UART0, and S1 is
+            // read-only on all other UARTs.
+            self.regs.s1.write(1 << 4);
+            if !s1.get_bit(5) {
+                let _ = self.regs.d.read();
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Enable the UART to interrupt when the receive line goes idle.
+    pub fn enable_idle_intr(&mut self) {
+        self.regs.c2.update(|c2| {
+            c2.set_bit(4, true);
+        });
+    }
+
     /// Disable this UART for recieving.
     pub fn disable_rx(self) -> (Uart<M, T, (), N>, R) {
         self.regs.c2.update(|c2| {
@@ -181,6 +377,26 @@ impl<M, T: UartTx<M, N>, R, const N: usize> Uart<M, T, R, N> {
         }
     }
 
+    /// Send an address to the UART, with the address mark set
+    ///
+    /// Returns `false` if the address could not be written. The UART
+    /// must be in address mark mode.
+    pub fn write_address(&mut self, address: u8) -> bool {
+        if self.regs.s1.read().get_bit(7) {
+            // T8 is sent with the character written after it is set
+            self.regs.c3.update(|c3| {
+                c3.set_bit(6, true);
+            });
+            self.regs.d.write(address);
+            self.regs.c3.update(|c3| {
+                c3.set_bit(6, false);
+            });
+            true
+        } else {
+            false
+        }
+    }
+
     /// Check if the UART has transmitted all bytes in the FIFO
     pub fn is_transmit_complete(&self) -> bool {
         self.regs.s1.read().get_bit(6)
@@ -220,6 +436,33 @@ impl<M, T: UartTx<M, N>, R, const N: usize> Uart<M, T, R, N> {
     }
 }
 
+impl<M, T: UartTx<M, N>, R, const N: usize> Uart<M, T, R, N>
+where
+    Uart<M, T, R, N>: TxDma,
+{
+    /// Request a DMA transfer, rather than an interrupt, when the
+    /// UART is ready to transmit a byte.
+    pub fn enable_tx_dma(&mut self, enabled: bool) {
+        if <Self as TxDma>::SELECT_IN_C5 {
+            self.regs.c5.update(|c5| {
+                c5.set_bit(7, enabled);
+            });
+        } else {
+            self.regs.c4.update(|c4| {
+                c4.set_bit(7, enabled);
+            });
+        }
+        self.regs.c2.update(|c2| {
+            c2.set_bit(7, enabled);
+        });
+    }
+
+    /// The address of the data register, for use as a DMA destination
+    pub fn data_address(&self) -> usize {
+        &self.regs.d as *const _ as usize
+    }
+}
+
 impl<M, T, R, const N: usize> Uart<M, T, R, N>
 where
     Uart<M, T, R, N>: Fifo,
@@ -611,3 +854,301 @@ unsafe impl<T, R> Fifo for Uart<Mk66Fx1M0, T, R, 3> {
 unsafe impl<T, R> Fifo for Uart<Mk66Fx1M0, T, R, 4> {
     const DEPTH: u8 = 1;
 }
+
+/// This is a marker trait to indicate whether a given UART can match
+/// received addresses in hardware.
+pub unsafe trait AddressMatch {
+    /// Whether the UART has an address match register
+    const ADDRESS_MATCH: bool;
+}
+
+unsafe impl<T, R> AddressMatch for Uart<Mk20Dx128, T, R, 0> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mk20Dx128, T, R, 1> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mk20Dx128, T, R, 2> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mk20Dx256, T, R, 0> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mk20Dx256, T, R, 1> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mk20Dx256, T, R, 2> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mk64Fx512, T, R, 0> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mk64Fx512, T, R, 1> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mk64Fx512, T, R, 2> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mk64Fx512, T, R, 3> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mk64Fx512, T, R, 4> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mk64Fx512, T, R, 5> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mk66Fx1M0, T, R, 0> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mk66Fx1M0, T, R, 1> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mk66Fx1M0, T, R, 2> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mk66Fx1M0, T, R, 3> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mk66Fx1M0, T, R, 4> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mkl26Z64, T, R, 0> {
+    const ADDRESS_MATCH: bool = true;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mkl26Z64, T, R, 1> {
+    const ADDRESS_MATCH: bool = false;
+}
+unsafe impl<T, R> AddressMatch for Uart<Mkl26Z64, T, R, 2> {
+    const ADDRESS_MATCH: bool = false;
+}
+
+/// This is a marker trait to indicate whether a given UART can send
+/// two stop bits.
+pub unsafe trait TwoStopBits {
+    /// Whether the UART has a stop bit number select
+    const TWO_STOP_BITS: bool;
+}
+
+unsafe impl<T, R> TwoStopBits for Uart<Mk20Dx128, T, R, 0> {
+    const TWO_STOP_BITS: bool = false;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mk20Dx128, T, R, 1> {
+    const TWO_STOP_BITS: bool = false;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mk20Dx128, T, R, 2> {
+    const TWO_STOP_BITS: bool = false;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mk20Dx256, T, R, 0> {
+    const TWO_STOP_BITS: bool = false;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mk20Dx256, T, R, 1> {
+    const TWO_STOP_BITS: bool = false;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mk20Dx256, T, R, 2> {
+    const TWO_STOP_BITS: bool = false;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mk64Fx512, T, R, 0> {
+    const TWO_STOP_BITS: bool = true;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mk64Fx512, T, R, 1> {
+    const TWO_STOP_BITS: bool = true;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mk64Fx512, T, R, 2> {
+    const TWO_STOP_BITS: bool = true;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mk64Fx512, T, R, 3> {
+    const TWO_STOP_BITS: bool = true;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mk64Fx512, T, R, 4> {
+    const TWO_STOP_BITS: bool = true;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mk64Fx512, T, R, 5> {
+    const TWO_STOP_BITS: bool = true;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mk66Fx1M0, T, R, 0> {
+    const TWO_STOP_BITS: bool = true;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mk66Fx1M0, T, R, 1> {
+    const TWO_STOP_BITS: bool = true;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mk66Fx1M0, T, R, 2> {
+    const TWO_STOP_BITS: bool = true;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mk66Fx1M0, T, R, 3> {
+    const TWO_STOP_BITS: bool = true;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mk66Fx1M0, T, R, 4> {
+    const TWO_STOP_BITS: bool = true;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mkl26Z64, T, R, 0> {
+    const TWO_STOP_BITS: bool = true;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mkl26Z64, T, R, 1> {
+    const TWO_STOP_BITS: bool = true;
+}
+unsafe impl<T, R> TwoStopBits for Uart<Mkl26Z64, T, R, 2> {
+    const TWO_STOP_BITS: bool = true;
+}
+
+/// This is a marker trait to indicate whether a given UART has a
+/// configurable oversampling ratio.
+///
+/// These UARTs have no fractional divisor, and instead set the
+/// baud rate with [`Uart::set_oversampled_divisor()`].
+pub unsafe trait Oversample {
+    /// Whether the UART has a configurable oversampling ratio
+    const OVERSAMPLE: bool;
+}
+
+unsafe impl<T, R> Oversample for Uart<Mk20Dx128, T, R, 0> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mk20Dx128, T, R, 1> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mk20Dx128, T, R, 2> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mk20Dx256, T, R, 0> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mk20Dx256, T, R, 1> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mk20Dx256, T, R, 2> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mk64Fx512, T, R, 0> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mk64Fx512, T, R, 1> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mk64Fx512, T, R, 2> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mk64Fx512, T, R, 3> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mk64Fx512, T, R, 4> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mk64Fx512, T, R, 5> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mk66Fx1M0, T, R, 0> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mk66Fx1M0, T, R, 1> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mk66Fx1M0, T, R, 2> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mk66Fx1M0, T, R, 3> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mk66Fx1M0, T, R, 4> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mkl26Z64, T, R, 0> {
+    const OVERSAMPLE: bool = true;
+}
+unsafe impl<T, R> Oversample for Uart<Mkl26Z64, T, R, 1> {
+    const OVERSAMPLE: bool = false;
+}
+unsafe impl<T, R> Oversample for Uart<Mkl26Z64, T, R, 2> {
+    const OVERSAMPLE: bool = false;
+}
+
+/// This is a marker trait to indicate whether a given UART can
+/// request DMA transfers.
+pub unsafe trait TxDma {
+    /// The DMA multiplexer source for transmit requests
+    const TX_REQUEST: u8;
+
+    /// Whether the transmit DMA select bit is in C5, rather than C4
+    const SELECT_IN_C5: bool;
+}
+
+unsafe impl<T, R> TxDma for Uart<Mk20Dx128, T, R, 0> {
+    const TX_REQUEST: u8 = 3;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mk20Dx128, T, R, 1> {
+    const TX_REQUEST: u8 = 5;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mk20Dx128, T, R, 2> {
+    const TX_REQUEST: u8 = 7;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mk20Dx256, T, R, 0> {
+    const TX_REQUEST: u8 = 3;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mk20Dx256, T, R, 1> {
+    const TX_REQUEST: u8 = 5;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mk20Dx256, T, R, 2> {
+    const TX_REQUEST: u8 = 7;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mk64Fx512, T, R, 0> {
+    const TX_REQUEST: u8 = 3;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mk64Fx512, T, R, 1> {
+    const TX_REQUEST: u8 = 5;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mk64Fx512, T, R, 2> {
+    const TX_REQUEST: u8 = 7;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mk64Fx512, T, R, 3> {
+    const TX_REQUEST: u8 = 9;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mk64Fx512, T, R, 4> {
+    const TX_REQUEST: u8 = 10;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mk64Fx512, T, R, 5> {
+    const TX_REQUEST: u8 = 11;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mk66Fx1M0, T, R, 0> {
+    const TX_REQUEST: u8 = 3;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mk66Fx1M0, T, R, 1> {
+    const TX_REQUEST: u8 = 5;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mk66Fx1M0, T, R, 2> {
+    const TX_REQUEST: u8 = 7;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mk66Fx1M0, T, R, 3> {
+    const TX_REQUEST: u8 = 9;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mk66Fx1M0, T, R, 4> {
+    const TX_REQUEST: u8 = 10;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mkl26Z64, T, R, 0> {
+    const TX_REQUEST: u8 = 3;
+    const SELECT_IN_C5: bool = true;
+}
+unsafe impl<T, R> TxDma for Uart<Mkl26Z64, T, R, 1> {
+    const TX_REQUEST: u8 = 5;
+    const SELECT_IN_C5: bool = false;
+}
+unsafe impl<T, R> TxDma for Uart<Mkl26Z64, T, R, 2> {
+    const TX_REQUEST: u8 = 7;
+    const SELECT_IN_C5: bool = false;
+}
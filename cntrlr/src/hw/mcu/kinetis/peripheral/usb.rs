@@ -0,0 +1,340 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! USB On-The-Go
+//!
+//! The full-speed USB controller, in device mode only. The controller
+//! moves each packet to or from memory through the buffer descriptor
+//! table, which has an even and an odd descriptor for each direction
+//! of each endpoint. The serial interface engine alternates between
+//! the two, so the next descriptor of a direction must be known when
+//! arming it.
+//!
+//! Every Kinetis MCU used by the Teensy 3.x boards has this
+//! controller, at the same address. The MK66's high-speed controller
+//! is not supported.
+
+use super::{
+    super::{Mk20Dx128, Mk20Dx256, Mk64Fx512, Mk66Fx1M0, Mkl26Z64},
+    sim::{Gate, GatedPeripheral},
+};
+use crate::register::{Register, Reserved};
+use bit_field::BitField;
+use core::{
+    marker::PhantomData,
+    ptr::{read_volatile, write_volatile},
+};
+
+/// The number of endpoints, including endpoint 0
+pub const ENDPOINTS: usize = 16;
+
+/// The host reset the bus
+pub const INTR_RESET: u8 = 0x01;
+
+/// A transaction finished, and its token is ready
+pub const INTR_TOKEN: u8 = 0x08;
+
+/// The bus was idle for 3 milliseconds
+pub const INTR_SLEEP: u8 = 0x10;
+
+/// A STALL handshake was sent
+pub const INTR_STALL: u8 = 0x80;
+
+/// The packet ID of a SETUP token
+pub const PID_SETUP: u8 = 0x0D;
+
+/// An 8-bit register, in a 32-bit slot
+#[repr(C)]
+struct Reg8 {
+    value: Register<u8>,
+    _reserved: [Reserved<u8>; 3],
+}
+
+#[repr(C)]
+struct UsbRegs {
+    _otg: [Reserved<u32>; 32],
+    istat: Reg8,
+    inten: Reg8,
+    errstat: Reg8,
+    erren: Reg8,
+    stat: Reg8,
+    ctl: Reg8,
+    addr: Reg8,
+    bdtpage1: Reg8,
+    _frame: [Reserved<u32>; 4],
+    bdtpage2: Reg8,
+    bdtpage3: Reg8,
+    _reserved_0: [Reserved<u32>; 2],
+    endpt: [Reg8; ENDPOINTS],
+    usbctrl: Reg8,
+    _observe: Reserved<u32>,
+    control: Reg8,
+    usbtrc0: Reg8,
+}
+
+/// The buffer descriptor table
+///
+/// Each descriptor is a control word and a buffer address.
+#[repr(C, align(512))]
+struct Bdt([[u32; 2]; ENDPOINTS * 4]);
+
+static mut BDT: Bdt = Bdt([[0; 2]; ENDPOINTS * 4]);
+
+/// A finished transaction
+#[derive(Clone, Copy, Debug)]
+pub struct Token {
+    /// The endpoint number
+    pub endpoint: usize,
+
+    /// Whether the packet was sent to the host
+    pub tx: bool,
+
+    /// Whether the odd descriptor was used
+    pub odd: bool,
+
+    /// The packet ID of the token
+    pub pid: u8,
+
+    /// The number of bytes sent or received
+    pub len: usize,
+}
+
+/// The handle to the USB controller
+pub struct Usb<M> {
+    regs: &'static mut UsbRegs,
+    bdt: &'static mut Bdt,
+    _gate: Gate,
+    _mcu: PhantomData<M>,
+}
+
+impl<M> Usb<M> {
+    /// Start the controller as a device, and connect to the bus
+    ///
+    /// The D+ pull-up is enabled, so the host sees the device
+    /// attach and resets the bus.
+    pub fn enable_device(&mut self) {
+        self.regs.usbtrc0.value.write(0x80);
+        while self.regs.usbtrc0.value.read().get_bit(7) {}
+
+        let bdt = self.bdt as *const _ as u32;
+        self.regs.bdtpage1.value.write(bdt.get_bits(8..16) as u8);
+        self.regs.bdtpage2.value.write(bdt.get_bits(16..24) as u8);
+        self.regs.bdtpage3.value.write(bdt.get_bits(24..32) as u8);
+
+        self.regs.istat.value.write(0xFF);
+        self.regs.errstat.value.write(0xFF);
+        // This bit is undocumented, but the controller does not
+        // respond to the bus without it.
+        self.regs.usbtrc0.value.update(|usbtrc0| {
+            usbtrc0.set_bit(6, true);
+        });
+        self.regs.ctl.value.write(0x01);
+        self.regs.usbctrl.value.write(0);
+        self.regs.control.value.write(0x10);
+    }
+
+    /// Disconnect from the bus, and stop the controller
+    pub fn disable_device(&mut self) {
+        self.regs.control.value.write(0);
+        self.regs.inten.value.write(0);
+        self.regs.ctl.value.write(0);
+    }
+
+    /// Return to the state after a bus reset
+    ///
+    /// Every descriptor is released and set back to even, every
+    /// endpoint except the control endpoint is disabled, and the
+    /// address is zero.
+    pub fn reset_endpoints(&mut self) {
+        self.regs.ctl.value.update(|ctl| {
+            ctl.set_bit(1, true);
+        });
+        for descriptor in self.bdt.0.iter_mut() {
+            unsafe { write_volatile(&mut descriptor[0], 0) };
+        }
+        for endpt in self.regs.endpt[1..].iter_mut() {
+            endpt.value.write(0);
+        }
+        self.regs.addr.value.write(0);
+        self.regs.errstat.value.write(0xFF);
+        self.regs.endpt[0].value.write(0x0D);
+        self.regs.ctl.value.write(0x01);
+    }
+
+    /// The pending interrupt flags
+    pub fn interrupts(&self) -> u8 {
+        self.regs.istat.value.read()
+    }
+
+    /// Clear interrupt flags
+    pub fn clear_interrupts(&mut self, flags: u8) {
+        self.regs.istat.value.write(flags);
+    }
+
+    /// Set which interrupt flags raise the USB interrupt
+    pub fn enable_interrupts(&mut self, flags: u8) {
+        self.regs.inten.value.write(flags);
+    }
+
+    /// Take the token of the oldest finished transaction
+    ///
+    /// The controller queues up to four tokens.
+    pub fn token(&mut self) -> Option<Token> {
+        if self.regs.istat.value.read() & INTR_TOKEN == 0 {
+            return None;
+        }
+        let stat = self.regs.stat.value.read();
+        let endpoint = stat.get_bits(4..8) as usize;
+        let tx = stat.get_bit(3);
+        let odd = stat.get_bit(2);
+        let desc = unsafe { read_volatile(&self.bdt.0[descriptor(endpoint, tx, odd)][0]) };
+        self.regs.istat.value.write(INTR_TOKEN);
+        Some(Token {
+            endpoint,
+            tx,
+            odd,
+            pid: desc.get_bits(2..6) as u8,
+            len: desc.get_bits(16..26) as usize,
+        })
+    }
+
+    /// Let the controller process tokens again
+    ///
+    /// The controller holds off every token after a SETUP token until
+    /// this is called.
+    pub fn resume_tokens(&mut self) {
+        self.regs.ctl.value.update(|ctl| {
+            ctl.set_bit(5, false);
+        });
+    }
+
+    /// Set the device's address
+    pub fn set_address(&mut self, address: u8) {
+        self.regs.addr.value.write(address & 0x7F);
+    }
+
+    /// Enable an endpoint's directions
+    ///
+    /// Isochronous endpoints have no handshake.
+    pub fn enable_endpoint(&mut self, endpoint: usize, tx: bool, rx: bool, handshake: bool) {
+        self.regs.endpt[endpoint].value.update(|endpt| {
+            endpt.set_bit(0, handshake);
+            endpt.set_bit(2, endpt.get_bit(2) || tx);
+            endpt.set_bit(3, endpt.get_bit(3) || rx);
+        });
+    }
+
+    /// Stall or unstall both directions of an endpoint
+    pub fn set_stalled(&mut self, endpoint: usize, stalled: bool) {
+        self.regs.endpt[endpoint].value.update(|endpt| {
+            endpt.set_bit(1, stalled);
+        });
+    }
+
+    /// Whether an endpoint is stalled
+    pub fn is_stalled(&self, endpoint: usize) -> bool {
+        self.regs.endpt[endpoint].value.read().get_bit(1)
+    }
+
+    /// Give a buffer to the controller
+    ///
+    /// The controller sends `len` bytes from the buffer, or receives
+    /// up to `len` bytes into it, with the given data toggle.
+    ///
+    /// # Safety
+    /// The buffer must stay valid, and must not be used, until the
+    /// transaction's token is taken or the descriptor is released.
+    pub unsafe fn arm(
+        &mut self,
+        endpoint: usize,
+        tx: bool,
+        odd: bool,
+        data1: bool,
+        buf: *mut u8,
+        len: usize,
+    ) {
+        let descriptor = &mut self.bdt.0[descriptor(endpoint, tx, odd)];
+        let mut desc = 0;
+        desc.set_bits(16..26, len as u32);
+        desc.set_bit(7, true);
+        desc.set_bit(6, data1);
+        desc.set_bit(3, true);
+        write_volatile(&mut descriptor[1], buf as u32);
+        write_volatile(&mut descriptor[0], desc);
+    }
+
+    /// Take a buffer back from the controller, whether or not it was used
+    pub fn release(&mut self, endpoint: usize, tx: bool, odd: bool) {
+        let descriptor = &mut self.bdt.0[descriptor(endpoint, tx, odd)];
+        unsafe { write_volatile(&mut descriptor[0], 0) };
+    }
+}
+
+fn descriptor(endpoint: usize, tx: bool, odd: bool) -> usize {
+    endpoint * 4 + (tx as usize) * 2 + odd as usize
+}
+
+unsafe impl GatedPeripheral<Mk20Dx128> for Usb<Mk20Dx128> {
+    const GATE: (usize, usize) = (4, 18);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4007_2000 as *mut _),
+            bdt: &mut BDT,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk20Dx256> for Usb<Mk20Dx256> {
+    const GATE: (usize, usize) = (4, 18);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4007_2000 as *mut _),
+            bdt: &mut BDT,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk64Fx512> for Usb<Mk64Fx512> {
+    const GATE: (usize, usize) = (4, 18);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4007_2000 as *mut _),
+            bdt: &mut BDT,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mk66Fx1M0> for Usb<Mk66Fx1M0> {
+    const GATE: (usize, usize) = (4, 18);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4007_2000 as *mut _),
+            bdt: &mut BDT,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
+
+unsafe impl GatedPeripheral<Mkl26Z64> for Usb<Mkl26Z64> {
+    const GATE: (usize, usize) = (4, 18);
+
+    unsafe fn new(gate: Gate) -> Self {
+        Self {
+            regs: &mut *(0x4007_2000 as *mut _),
+            bdt: &mut BDT,
+            _gate: gate,
+            _mcu: PhantomData,
+        }
+    }
+}
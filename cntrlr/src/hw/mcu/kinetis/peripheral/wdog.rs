@@ -50,6 +50,36 @@ impl Watchdog {
         });
     }
 
+    /// Enable the watchdog
+    ///
+    /// The watchdog counts the 1kHz LPO, and resets the chip once it
+    /// counts to `timeout` without being refreshed. The count starts
+    /// from zero. `timeout` must be at least 4.
+    ///
+    /// This should be called with interrupts disabled, as the
+    /// watchdog must be configured soon after it is unlocked.
+    pub fn enable(&mut self, timeout: u32) {
+        assert!(timeout >= 4);
+        self.unlock();
+        self.regs.tovalh.write((timeout >> 16) as u16);
+        self.regs.tovall.write(timeout as u16);
+        self.regs.presc.write(0);
+        self.regs.stctrlh.update(|ctrl| {
+            ctrl.set_bit(1, false); // Count the LPO
+            ctrl.set_bit(0, true);
+        });
+        self.refresh();
+    }
+
+    /// Refresh the watchdog, restarting its count
+    ///
+    /// This should be called with interrupts disabled, as the two
+    /// refresh writes must be close together.
+    pub fn refresh(&mut self) {
+        self.regs.refresh.write(0xA602);
+        self.regs.refresh.write(0xB480);
+    }
+
     fn unlock(&mut self) {
         self.regs.unlock.write(0xC520);
         self.regs.unlock.write(0xD928);
@@ -3,5 +3,7 @@
 
 //! Hardware interfaces to specific microcontrollers
 
+pub mod imxrt;
 pub mod kinetis;
+pub mod pio;
 pub mod sifive;
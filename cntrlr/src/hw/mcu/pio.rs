@@ -0,0 +1,566 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Programmable IO
+//!
+//! Programmable IO is built from shifters, which move words between a
+//! buffer and a pin, and timers, which clock the shifters. Together
+//! they can implement serial protocols which the MCU has no dedicated
+//! peripheral for, or extra instances of those which it has.
+//!
+//! [`ProgrammableIo`] is implemented by the i.MX RT's
+//! [FlexIO](super::imxrt::peripheral::flexio). The Kinetis MCUs have
+//! no programmable IO. The protocols here are built only from the
+//! trait's shifter and timer configuration, so they work on any
+//! implementation.
+
+/// An error from setting up a protocol
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The protocol cannot be set up because its rate cannot be
+    /// reached from the module's clock
+    InvalidRate,
+
+    /// The protocol cannot be set up because its word size is out of
+    /// range
+    InvalidWordSize,
+}
+
+/// What a shifter does with its buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShifterMode {
+    /// The shifter is off
+    Disabled,
+
+    /// Shift words in from the pin
+    Receive,
+
+    /// Shift words out to the pin
+    Transmit,
+}
+
+/// A bit added before or after each word a shifter sends
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameBit {
+    /// No bit is added
+    None,
+
+    /// A low bit is added
+    Low,
+
+    /// A high bit is added
+    High,
+}
+
+/// The configuration of a shifter
+#[derive(Clone, Copy, Debug)]
+pub struct Shifter {
+    /// What the shifter does with its buffer
+    pub mode: ShifterMode,
+
+    /// The timer which clocks the shifter
+    pub timer: usize,
+
+    /// Shift on the falling edge of the timer output, instead of the
+    /// rising edge
+    pub falling_edge: bool,
+
+    /// The pin the shifter drives or samples
+    pub pin: usize,
+
+    /// The bit sent before each word
+    pub start: FrameBit,
+
+    /// The bit sent after each word
+    pub stop: FrameBit,
+
+    /// Without a start bit, load the first word on the first shift,
+    /// rather than when the timer is enabled
+    pub load_on_shift: bool,
+}
+
+/// How a timer counts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerMode {
+    /// The timer is off
+    Disabled,
+
+    /// The low byte of the compare value sets the baud rate, and the
+    /// high byte the number of bits
+    Baud,
+
+    /// The low byte of the compare value sets the high period, and
+    /// the high byte the low period
+    Pwm,
+
+    /// The compare value sets the period
+    Counter,
+}
+
+/// A signal which can start or stop a timer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trigger {
+    /// A transmitting shifter has a word in its buffer
+    Shifter(usize),
+
+    /// A pin is high
+    Pin(usize),
+}
+
+/// When a timer starts counting
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerEnable {
+    /// The timer counts as soon as the module is enabled
+    Always,
+
+    /// The timer counts while its trigger is asserted
+    Trigger,
+
+    /// The timer starts on a rising edge of its pin
+    PinRising,
+
+    /// The timer starts with the timer before it
+    Previous,
+}
+
+/// When a timer stops counting
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerDisable {
+    /// The timer never stops
+    Never,
+
+    /// The timer stops when it reaches its compare value
+    Compare,
+
+    /// The timer stops with the timer before it
+    Previous,
+}
+
+/// The level of a timer's output
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerOutput {
+    /// The output starts high
+    High,
+
+    /// The output starts low
+    Low,
+
+    /// The output starts high, and is set high again whenever the
+    /// timer is reset
+    HighOnReset,
+}
+
+/// The configuration of a timer
+#[derive(Clone, Copy, Debug)]
+pub struct Timer {
+    /// How the timer counts
+    pub mode: TimerMode,
+
+    /// The compare value, whose meaning depends on the mode
+    pub compare: u16,
+
+    /// The signal which starts the timer, for [`TimerEnable::Trigger`]
+    pub trigger: Option<Trigger>,
+
+    /// When the timer starts counting
+    pub enable: TimerEnable,
+
+    /// When the timer stops counting
+    pub disable: TimerDisable,
+
+    /// Reset the count on a rising edge of the timer's pin
+    pub reset_on_pin: bool,
+
+    /// The level of the timer's output
+    pub output: TimerOutput,
+
+    /// The timer's pin, if any
+    pub pin: Option<usize>,
+
+    /// Drive the timer's output on its pin, rather than only sampling
+    /// the pin
+    pub pin_output: bool,
+
+    /// Invert the timer's pin
+    pub pin_inverted: bool,
+
+    /// Add a start bit to each word
+    pub start_bit: bool,
+
+    /// Add a stop bit to each word
+    pub stop_bit: bool,
+}
+
+impl Timer {
+    /// A disabled timer
+    pub const fn new() -> Self {
+        Self {
+            mode: TimerMode::Disabled,
+            compare: 0,
+            trigger: None,
+            enable: TimerEnable::Always,
+            disable: TimerDisable::Never,
+            reset_on_pin: false,
+            output: TimerOutput::High,
+            pin: None,
+            pin_output: false,
+            pin_inverted: false,
+            start_bit: false,
+            stop_bit: false,
+        }
+    }
+}
+
+/// The pins of an SPI bus
+#[derive(Clone, Copy, Debug)]
+pub struct SpiPins {
+    /// The data output
+    pub sdo: usize,
+
+    /// The data input
+    pub sdi: usize,
+
+    /// The clock
+    pub sck: usize,
+}
+
+/// The pins of an I2S transmitter
+#[derive(Clone, Copy, Debug)]
+pub struct I2sPins {
+    /// The data output
+    pub data: usize,
+
+    /// The bit clock
+    pub bclk: usize,
+
+    /// The frame sync, which is low for the left channel
+    pub fs: usize,
+}
+
+/// A module of shifters and timers
+///
+/// Shifters and timers are numbered from zero. Methods which take a
+/// shifter or timer panic if it is out of range.
+pub trait ProgrammableIo {
+    /// The number of shifters in this module
+    fn shifters(&self) -> usize;
+
+    /// The number of timers in this module
+    fn timers(&self) -> usize;
+
+    /// Enable this module
+    ///
+    /// Shifters and timers should be configured first.
+    fn enable(&mut self);
+
+    /// Configure a shifter
+    fn set_shifter(&mut self, index: usize, shifter: Shifter);
+
+    /// Configure a timer
+    fn set_timer(&mut self, index: usize, timer: Timer);
+
+    /// Write a word to a shifter's buffer
+    ///
+    /// Returns `false` if the buffer is still full.
+    fn write_data(&mut self, shifter: usize, data: u32) -> bool;
+
+    /// Write a word to a shifter's buffer, to be sent most
+    /// significant bit first
+    ///
+    /// Returns `false` if the buffer is still full.
+    fn write_data_msb_first(&mut self, shifter: usize, data: u32) -> bool;
+
+    /// Read a word from a shifter's buffer
+    ///
+    /// Bits are shifted in at the top of the word. Returns `None` if
+    /// no word has been recieved.
+    fn read_data(&mut self, shifter: usize) -> Option<u32>;
+
+    /// Read a word from a shifter's buffer, which was recieved most
+    /// significant bit first
+    ///
+    /// Returns `None` if no word has been recieved.
+    fn read_data_msb_first(&mut self, shifter: usize) -> Option<u32>;
+
+    /// Configure a shifter and timer as a UART transmitter
+    ///
+    /// Words are sent as 8 bits, least significant first, with one
+    /// start and one stop bit. Each byte is written to the low bits
+    /// of the shifter with [`write_data`](Self::write_data). `clock`
+    /// is the frequency of the module's clock.
+    fn set_uart_tx(
+        &mut self,
+        shifter: usize,
+        timer: usize,
+        pin: usize,
+        clock: usize,
+        baud: usize,
+    ) -> Result<(), Error> {
+        let compare = baud_compare(clock, baud, 8)?;
+        self.set_shifter(
+            shifter,
+            Shifter {
+                mode: ShifterMode::Transmit,
+                timer,
+                falling_edge: false,
+                pin,
+                start: FrameBit::Low,
+                stop: FrameBit::High,
+                load_on_shift: false,
+            },
+        );
+        self.set_timer(
+            timer,
+            Timer {
+                mode: TimerMode::Baud,
+                compare,
+                trigger: Some(Trigger::Shifter(shifter)),
+                enable: TimerEnable::Trigger,
+                disable: TimerDisable::Compare,
+                start_bit: true,
+                stop_bit: true,
+                ..Timer::new()
+            },
+        );
+        Ok(())
+    }
+
+    /// Configure a shifter and timer as a UART receiver
+    ///
+    /// The timer starts on the falling edge of each start bit, and
+    /// samples in the middle of each bit. Each byte is read from the
+    /// top bits of the shifter with [`read_data`](Self::read_data).
+    fn set_uart_rx(
+        &mut self,
+        shifter: usize,
+        timer: usize,
+        pin: usize,
+        clock: usize,
+        baud: usize,
+    ) -> Result<(), Error> {
+        let compare = baud_compare(clock, baud, 8)?;
+        self.set_shifter(
+            shifter,
+            Shifter {
+                mode: ShifterMode::Receive,
+                timer,
+                falling_edge: true,
+                pin,
+                start: FrameBit::Low,
+                stop: FrameBit::High,
+                load_on_shift: false,
+            },
+        );
+        self.set_timer(
+            timer,
+            Timer {
+                mode: TimerMode::Baud,
+                compare,
+                enable: TimerEnable::PinRising,
+                disable: TimerDisable::Compare,
+                reset_on_pin: true,
+                output: TimerOutput::HighOnReset,
+                pin: Some(pin),
+                pin_inverted: true,
+                start_bit: true,
+                stop_bit: true,
+                ..Timer::new()
+            },
+        );
+        Ok(())
+    }
+
+    /// Configure two shifters and a timer as an SPI controller
+    ///
+    /// The first shifter transmits, and the second recieves. The bus
+    /// runs in mode 0: the clock idles low, and data is sampled on
+    /// its rising edge. Words of `bits` bits, from 1 to 32, are sent
+    /// most significant bit first. Each word is written to the top
+    /// bits of the transmitter with
+    /// [`write_data_msb_first`](Self::write_data_msb_first), and the
+    /// word recieved at the same time is read from the low `bits`
+    /// bits of the reciever with
+    /// [`read_data_msb_first`](Self::read_data_msb_first).
+    ///
+    /// There is no chip select, so the device should be selected with
+    /// a GPIO.
+    fn set_spi(
+        &mut self,
+        (tx, rx): (usize, usize),
+        timer: usize,
+        pins: SpiPins,
+        clock: usize,
+        frequency: usize,
+        bits: usize,
+    ) -> Result<(), Error> {
+        if bits == 0 || bits > 32 {
+            return Err(Error::InvalidWordSize);
+        }
+        let compare = baud_compare(clock, frequency, bits)?;
+        self.set_shifter(
+            tx,
+            Shifter {
+                mode: ShifterMode::Transmit,
+                timer,
+                falling_edge: true,
+                pin: pins.sdo,
+                start: FrameBit::None,
+                stop: FrameBit::None,
+                load_on_shift: false,
+            },
+        );
+        self.set_shifter(
+            rx,
+            Shifter {
+                mode: ShifterMode::Receive,
+                timer,
+                falling_edge: false,
+                pin: pins.sdi,
+                start: FrameBit::None,
+                stop: FrameBit::None,
+                load_on_shift: false,
+            },
+        );
+        self.set_timer(
+            timer,
+            Timer {
+                mode: TimerMode::Baud,
+                compare,
+                trigger: Some(Trigger::Shifter(tx)),
+                enable: TimerEnable::Trigger,
+                disable: TimerDisable::Compare,
+                output: TimerOutput::Low,
+                pin: Some(pins.sck),
+                pin_output: true,
+                start_bit: true,
+                stop_bit: true,
+                ..Timer::new()
+            },
+        );
+        Ok(())
+    }
+
+    /// Configure a shifter and two timers as an I2S transmitter
+    ///
+    /// The module drives both clocks. `timer` drives the bit clock,
+    /// and the timer after it the frame sync. Samples of `bits` bits,
+    /// from 8 to 32, are sent most significant bit first, left
+    /// channel first. Each sample is written to the top bits of the
+    /// shifter with [`write_data_msb_first`](Self::write_data_msb_first).
+    fn set_i2s_tx(
+        &mut self,
+        shifter: usize,
+        timer: usize,
+        pins: I2sPins,
+        clock: usize,
+        sample_rate: usize,
+        bits: usize,
+    ) -> Result<(), Error> {
+        if bits < 8 || bits > 32 {
+            return Err(Error::InvalidWordSize);
+        }
+        let compare = baud_compare(clock, sample_rate * 2 * bits, bits)?;
+        let bit_clocks = ((compare & 0xFF) as usize + 1) * 2;
+        self.set_shifter(
+            shifter,
+            Shifter {
+                mode: ShifterMode::Transmit,
+                timer,
+                falling_edge: false,
+                pin: pins.data,
+                start: FrameBit::None,
+                stop: FrameBit::None,
+                load_on_shift: true,
+            },
+        );
+        self.set_timer(
+            timer,
+            Timer {
+                mode: TimerMode::Baud,
+                compare,
+                trigger: Some(Trigger::Shifter(shifter)),
+                enable: TimerEnable::Trigger,
+                pin: Some(pins.bclk),
+                pin_output: true,
+                start_bit: true,
+                ..Timer::new()
+            },
+        );
+        self.set_timer(
+            timer + 1,
+            Timer {
+                mode: TimerMode::Counter,
+                compare: (bits * bit_clocks - 1) as u16,
+                enable: TimerEnable::Previous,
+                pin: Some(pins.fs),
+                pin_output: true,
+                pin_inverted: true,
+                ..Timer::new()
+            },
+        );
+        Ok(())
+    }
+
+    /// Configure a shifter and timer to drive a chain of WS2812 LEDs
+    ///
+    /// Each bit of a WS2812 color is sent as three bits at 2.4MHz,
+    /// `110` for a one and `100` for a zero, so the module's clock
+    /// must be within 3% of a multiple of 4.8MHz. Words of 32 of
+    /// these bits are written with
+    /// [`write_data_msb_first`](Self::write_data_msb_first), and each
+    /// must be written before the last has been sent, or the low
+    /// period of the bit between them is stretched. The pin is held
+    /// low once the last word is sent, which latches the colors after
+    /// 50 microseconds.
+    fn set_ws2812(
+        &mut self,
+        shifter: usize,
+        timer: usize,
+        pin: usize,
+        clock: usize,
+    ) -> Result<(), Error> {
+        let compare = baud_compare(clock, 2_400_000, 32)?;
+        self.set_shifter(
+            shifter,
+            Shifter {
+                mode: ShifterMode::Transmit,
+                timer,
+                falling_edge: false,
+                pin,
+                start: FrameBit::None,
+                stop: FrameBit::None,
+                load_on_shift: false,
+            },
+        );
+        self.set_timer(
+            timer,
+            Timer {
+                mode: TimerMode::Baud,
+                compare,
+                trigger: Some(Trigger::Shifter(shifter)),
+                enable: TimerEnable::Trigger,
+                disable: TimerDisable::Compare,
+                output: TimerOutput::Low,
+                ..Timer::new()
+            },
+        );
+        Ok(())
+    }
+}
+
+/// The compare value of a baud timer, for words of `bits` bits
+///
+/// The timer toggles its output every `div` clocks, so each bit takes
+/// `div * 2`. The rate must be within 3% of `rate`.
+fn baud_compare(clock: usize, rate: usize, bits: usize) -> Result<u16, Error> {
+    if rate == 0 {
+        return Err(Error::InvalidRate);
+    }
+    let div = ((clock + rate) / (rate * 2)).max(1).min(256);
+    let actual = clock / (div * 2);
+    if actual * 100 < rate * 97 || actual * 100 > rate * 103 {
+        return Err(Error::InvalidRate);
+    }
+    Ok(((bits * 2 - 1) << 8 | (div - 1)) as u16)
+}
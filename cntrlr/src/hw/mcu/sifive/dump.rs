@@ -0,0 +1,102 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Register state report for SiFive MCUs
+
+use crate::hw::dump::{Block, Reg, Size::U32};
+use core::fmt;
+
+const PRCI: Block = Block {
+    name: "PRCI",
+    base: 0x1000_8000,
+    regs: &[
+        Reg("HFROSC", 0x0, U32),
+        Reg("HFXOSC", 0x4, U32),
+        Reg("PLLCFG", 0x8, U32),
+        Reg("PLLDIV", 0xC, U32),
+    ],
+};
+
+const UART_REGS: &[Reg] = &[
+    Reg("TXCTRL", 0x8, U32),
+    Reg("RXCTRL", 0xC, U32),
+    Reg("IE", 0x10, U32),
+    Reg("IP", 0x14, U32),
+    Reg("DIV", 0x18, U32),
+];
+
+const UARTS: &[Block] = &[
+    Block {
+        name: "UART0",
+        base: 0x1001_3000,
+        regs: UART_REGS,
+    },
+    Block {
+        name: "UART1",
+        base: 0x1002_3000,
+        regs: UART_REGS,
+    },
+];
+
+const SPI_REGS: &[Reg] = &[
+    Reg("SCKDIV", 0x0, U32),
+    Reg("SCKMODE", 0x4, U32),
+    Reg("CSID", 0x10, U32),
+    Reg("CSDEF", 0x14, U32),
+    Reg("CSMODE", 0x18, U32),
+    Reg("FMT", 0x40, U32),
+    Reg("IE", 0x70, U32),
+    Reg("IP", 0x74, U32),
+];
+
+const SPIS: &[Block] = &[
+    Block {
+        name: "QSPI0",
+        base: 0x1001_4000,
+        regs: &[
+            Reg("SCKDIV", 0x0, U32),
+            Reg("SCKMODE", 0x4, U32),
+            Reg("CSID", 0x10, U32),
+            Reg("CSDEF", 0x14, U32),
+            Reg("CSMODE", 0x18, U32),
+            Reg("FMT", 0x40, U32),
+            Reg("FCTRL", 0x60, U32),
+            Reg("FFMT", 0x64, U32),
+            Reg("IE", 0x70, U32),
+            Reg("IP", 0x74, U32),
+        ],
+    },
+    Block {
+        name: "SPI1",
+        base: 0x1002_4000,
+        regs: SPI_REGS,
+    },
+    Block {
+        name: "SPI2",
+        base: 0x1003_4000,
+        regs: SPI_REGS,
+    },
+];
+
+const PLIC: Block = Block {
+    name: "PLIC",
+    base: 0x0C00_0000,
+    regs: &[
+        Reg("ENABLE0", 0x2000, U32),
+        Reg("ENABLE1", 0x2004, U32),
+        Reg("THRESH", 0x20_0000, U32),
+    ],
+};
+
+/// Write the clock, UART, SPI, and PLIC registers
+///
+/// The FE310 has no clock gates, so every peripheral is listed. The
+/// receive data registers are skipped, since reading them takes a
+/// byte from the FIFO.
+pub(crate) fn dump_state(writer: &mut dyn fmt::Write) -> fmt::Result {
+    PRCI.write(writer)?;
+    for block in UARTS.iter().chain(SPIS) {
+        block.write(writer)?;
+    }
+    PLIC.write(writer)
+}
@@ -7,11 +7,16 @@
 //! the [`Sparkfun Red V`](`crate::hw::board::red_v`) board.
 
 pub use super::{
+    peripheral::aon::LfClockSource,
     peripheral::gpio::{UartRx, UartTx},
     peripheral::plic::Plic,
+    peripheral::spi::{FlashConfig, FlashRead},
     Fe310G002,
 };
 
+/// The always-on domain
+pub type Aon = super::peripheral::aon::Aon<Fe310G002>;
+
 /// A GPIO instance
 pub type Gpio<const N: usize> = super::peripheral::gpio::Gpio<Fe310G002, N>;
 
@@ -19,11 +24,20 @@ pub type Gpio<const N: usize> = super::peripheral::gpio::Gpio<Fe310G002, N>;
 pub type Pin<'a, const N: usize, const P: usize> =
     super::peripheral::gpio::Pin<'a, Fe310G002, N, P>;
 
+/// All of the peripherals
+pub type Peripherals = super::peripheral::Peripherals<Fe310G002>;
+
 /// The PRCI
 pub type Prci = super::peripheral::prci::Prci<Fe310G002>;
 
+/// A PWM block
+pub type Pwm<const N: usize> = super::peripheral::pwm::Pwm<Fe310G002, N>;
+
 /// An SPI instance
 pub type Spi<T, R, const N: usize> = super::peripheral::spi::Spi<Fe310G002, T, R, N>;
 
 /// A UART instance
 pub type Uart<T, R, const N: usize> = super::peripheral::uart::Uart<Fe310G002, T, R, N>;
+
+/// The watchdog
+pub type Wdog = super::peripheral::wdog::Wdog<Fe310G002>;
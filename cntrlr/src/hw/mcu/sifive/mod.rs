@@ -8,4 +8,7 @@ pub struct Fe310G002;
 
 pub mod peripheral;
 
+#[cfg(mcu = "fe310g002")]
+pub(crate) mod dump;
+
 pub mod fe310g002;
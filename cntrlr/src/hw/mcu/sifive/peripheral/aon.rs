@@ -0,0 +1,218 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Always-on domain
+//!
+//! The always-on domain keeps running while the rest of the chip is
+//! powered down. It holds the real-time clock, the low-frequency
+//! clock which drives it, and a set of backup registers which keep
+//! their contents across sleep. The watchdog is also part of this
+//! domain, and has its own handle in [`wdog`](super::wdog).
+//!
+//! The RTC is a 48-bit counter of the low-frequency clock. Its count
+//! is compared after being divided by `2^scale`, and raises the RTC
+//! interrupt once the scaled count reaches the compare value. The
+//! interrupt stays pending until the compare value is raised above
+//! the scaled count.
+
+use crate::{
+    register::{Register, Reserved},
+    sync::Flag,
+};
+use bit_field::BitField;
+use core::{marker::PhantomData, sync::atomic::Ordering};
+
+#[repr(C)]
+struct AonRegs {
+    _wdog: [Reserved<u32>; 16],
+    rtccfg: Register<u32>,
+    _reserved0: Reserved<u32>,
+    rtclo: Register<u32>,
+    rtchi: Register<u32>,
+    rtcs: Register<u32>,
+    _reserved1: [Reserved<u32>; 3],
+    rtccmp0: Register<u32>,
+    _reserved2: [Reserved<u32>; 3],
+    lfrosccfg: Register<u32>,
+    _reserved3: [Reserved<u32>; 2],
+    lfclkmux: Register<u32>,
+    backup: [Register<u32>; 16],
+}
+
+/// The always-on domain
+pub struct Aon<M> {
+    regs: &'static mut AonRegs,
+    _mcu: PhantomData<M>,
+}
+
+/// The source of the low-frequency clock
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LfClockSource {
+    /// The internal low-frequency ring oscillator
+    Internal,
+
+    /// The external low-frequency clock input
+    External,
+}
+
+static LOCK: Flag = Flag::new(false);
+
+#[cfg(any(doc, mcu = "fe310g002"))]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(mcu = "fe310g002")))]
+impl super::Peripheral for Aon<super::super::Fe310G002> {
+    /// Get the always-on domain instance
+    fn get() -> Option<Self> {
+        if LOCK.swap(true, Ordering::Acquire) {
+            None
+        } else {
+            // Safety: The lock ensures this is the only handle
+            unsafe { Some(Self::steal()) }
+        }
+    }
+}
+
+impl<M> Aon<M>
+where
+    Aon<M>: super::Peripheral,
+{
+    /// Return the handle to the always-on domain
+    ///
+    /// Returns 'None' if the always-on domain is already in use.
+    pub fn get() -> Option<Self> {
+        super::Peripheral::get()
+    }
+}
+
+impl<M> Aon<M> {
+    /// Get the always-on domain without taking its lock
+    ///
+    /// # Safety
+    /// The returned handle must not be used at the same time as any
+    /// other handle to the always-on domain, and must never be
+    /// dropped.
+    #[cfg(any(doc, mcu = "fe310g002"))]
+    pub(crate) unsafe fn steal() -> Self {
+        Self {
+            regs: &mut *(0x1000_0000 as *mut _),
+            _mcu: PhantomData,
+        }
+    }
+
+    /// Enable or disable the RTC
+    ///
+    /// The count is compared after being divided by `2^scale`. Valid
+    /// scale values are `0..16`.
+    pub fn enable_rtc(&mut self, enabled: bool, scale: u32) {
+        assert!(scale < 16);
+        self.regs.rtccfg.update(|rtccfg| {
+            rtccfg.set_bits(0..4, scale);
+            rtccfg.set_bit(12, enabled);
+        });
+    }
+
+    /// Whether the RTC is counting
+    pub fn rtc_enabled(&self) -> bool {
+        self.regs.rtccfg.read().get_bit(12)
+    }
+
+    /// The raw 48-bit count of the RTC
+    pub fn rtc_count(&self) -> u64 {
+        loop {
+            let hi = self.regs.rtchi.read();
+            let lo = self.regs.rtclo.read();
+            if self.regs.rtchi.read() == hi {
+                return (hi.get_bits(0..16) as u64) << 32 | lo as u64;
+            }
+        }
+    }
+
+    /// Set the raw 48-bit count of the RTC
+    ///
+    /// The RTC should be disabled while its count is set.
+    pub fn set_rtc_count(&mut self, count: u64) {
+        self.regs.rtclo.write(count as u32);
+        self.regs.rtchi.write((count >> 32) as u32 & 0xFFFF);
+    }
+
+    /// The scaled count of the RTC, which is compared against the compare value
+    pub fn rtc_scaled(&self) -> u32 {
+        self.regs.rtcs.read()
+    }
+
+    /// Set the value the scaled count is compared against
+    pub fn set_rtc_compare(&mut self, compare: u32) {
+        self.regs.rtccmp0.write(compare);
+    }
+
+    /// Whether the RTC interrupt is pending
+    pub fn rtc_pending(&self) -> bool {
+        self.regs.rtccfg.read().get_bit(28)
+    }
+
+    /// Enable the internal low-frequency oscillator
+    ///
+    /// The oscillator runs at about 32kHz before trimming, and is
+    /// divided by `div`, which must be from 1 to 64. `trim` adjusts
+    /// its frequency, and must be below 32. This waits for the
+    /// oscillator to become ready.
+    pub fn enable_lfrosc(&mut self, div: u32, trim: u32) {
+        assert!((1..=64).contains(&div));
+        assert!(trim < 32);
+        self.regs.lfrosccfg.update(|lfrosccfg| {
+            lfrosccfg.set_bits(0..6, div - 1);
+            lfrosccfg.set_bits(16..21, trim);
+            lfrosccfg.set_bit(30, true);
+        });
+        while !self.regs.lfrosccfg.read().get_bit(31) {}
+    }
+
+    /// Disable the internal low-frequency oscillator
+    ///
+    /// The low-frequency clock must be switched to the external
+    /// input first.
+    pub fn disable_lfrosc(&mut self) {
+        self.regs.lfrosccfg.update(|lfrosccfg| {
+            lfrosccfg.set_bit(30, false);
+        });
+    }
+
+    /// The trim value of the internal low-frequency oscillator
+    pub fn lfrosc_trim(&self) -> u32 {
+        self.regs.lfrosccfg.read().get_bits(16..21)
+    }
+
+    /// Select the source of the low-frequency clock
+    pub fn set_lfclk_source(&mut self, source: LfClockSource) {
+        self.regs.lfclkmux.update(|lfclkmux| {
+            lfclkmux.set_bit(0, source == LfClockSource::External);
+        });
+    }
+
+    /// The source of the low-frequency clock
+    pub fn lfclk_source(&self) -> LfClockSource {
+        if self.regs.lfclkmux.read().get_bit(0) {
+            LfClockSource::External
+        } else {
+            LfClockSource::Internal
+        }
+    }
+
+    /// Read a backup register
+    ///
+    /// There are 16 backup registers, which keep their values while
+    /// the rest of the chip sleeps.
+    pub fn backup(&self, index: usize) -> u32 {
+        self.regs.backup[index].read()
+    }
+
+    /// Write a backup register
+    pub fn set_backup(&mut self, index: usize, value: u32) {
+        self.regs.backup[index].write(value);
+    }
+}
+
+impl<M> Drop for Aon<M> {
+    fn drop(&mut self) {
+        LOCK.store(false, Ordering::Release);
+    }
+}
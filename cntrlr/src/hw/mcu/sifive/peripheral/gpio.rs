@@ -4,9 +4,14 @@
 //! The GPIO on an FE310 microcontroller.
 
 use super::super::Fe310G002;
-use crate::sync::Flag;
+use crate::{digital::Edge, sync::Flag};
 use bit_field::BitField;
-use core::{cell::UnsafeCell, marker::PhantomData, ptr::read_volatile, sync::atomic::Ordering};
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    ptr::{read_volatile, write_volatile},
+    sync::atomic::Ordering,
+};
 
 struct GpioReg(UnsafeCell<u32>);
 
@@ -31,6 +36,12 @@ impl GpioReg {
         assert!(N < 32);
         unsafe { read_volatile(self.0.get()).get_bit(N) }
     }
+
+    /// Clear a write-one-to-clear bit, without touching the others
+    fn clear<const N: usize>(&self) {
+        assert!(N < 32);
+        unsafe { write_volatile(self.0.get(), 1 << N) }
+    }
 }
 
 #[repr(C)]
@@ -42,8 +53,11 @@ struct GpioRegs {
     pue: GpioReg,
     ds: GpioReg,
     rise_ie: GpioReg,
+    rise_ip: GpioReg,
     fall_ie: GpioReg,
     fall_ip: GpioReg,
+    high_ie: GpioReg,
+    high_ip: GpioReg,
     low_ie: GpioReg,
     low_ip: GpioReg,
     iof_en: GpioReg,
@@ -118,6 +132,20 @@ impl<M, const N: usize, const P: usize> Pin<'_, M, N, P> {
         self.port.regs.iof_en.set::<P>(false);
         GpioPin(self)
     }
+
+    /// Connect this pin to one of its hardware functions
+    ///
+    /// Each pin has up to two hardware functions. `iof1` selects the
+    /// second, rather than the first.
+    pub fn set_iof(&mut self, iof1: bool) {
+        self.port.regs.iof_sel.set::<P>(iof1);
+        self.port.regs.iof_en.set::<P>(true);
+    }
+
+    /// Set whether this pin uses its high output drive strength
+    pub fn set_high_drive(&mut self, high: bool) {
+        self.port.regs.ds.set::<P>(high);
+    }
 }
 
 impl<M, const N: usize, const P: usize> Drop for Pin<'_, M, N, P> {
@@ -181,6 +209,11 @@ impl<M, const N: usize, const P: usize> GpioPin<Pin<'_, M, N, P>> {
         self.0.port.regs.input_val.get::<P>()
     }
 
+    /// Read the state this pin is set to output
+    pub fn read_output(&self) -> bool {
+        self.0.port.regs.output_val.get::<P>()
+    }
+
     /// Set whether this pin is an output or an input
     pub fn set_output(&mut self, output: bool) {
         self.0.port.regs.output_en.set::<P>(output);
@@ -191,6 +224,25 @@ impl<M, const N: usize, const P: usize> GpioPin<Pin<'_, M, N, P>> {
     pub fn enable_pullup(&mut self, pullup: bool) {
         self.0.port.regs.pue.set::<P>(pullup);
     }
+
+    /// Set which edges on this pin trigger its interrupt
+    ///
+    /// If `edge` is `None`, the interrupt is disabled. Any pending
+    /// edge on this pin is cleared.
+    pub fn set_interrupt(&mut self, edge: Option<Edge>) {
+        let (rise, fall) = match edge {
+            None => (false, false),
+            Some(Edge::Rising) => (true, false),
+            Some(Edge::Falling) => (false, true),
+            Some(Edge::Both) => (true, true),
+        };
+        self.0.port.regs.rise_ie.set::<P>(false);
+        self.0.port.regs.fall_ie.set::<P>(false);
+        self.0.port.regs.rise_ip.clear::<P>();
+        self.0.port.regs.fall_ip.clear::<P>();
+        self.0.port.regs.rise_ie.set::<P>(rise);
+        self.0.port.regs.fall_ie.set::<P>(fall);
+    }
 }
 
 impl super::uart::UartRx<Fe310G002, 0> for UartRx<Pin<'_, Fe310G002, 0, 16>> {}
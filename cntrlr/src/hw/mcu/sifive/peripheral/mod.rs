@@ -3,11 +3,17 @@
 
 //! Peripherals found on SiFive Freedom Everywhere microcontrollers.
 
+pub mod aon;
 pub mod gpio;
 pub mod plic;
 pub mod prci;
+pub mod pwm;
 pub mod spi;
 pub mod uart;
+pub mod wdog;
+
+use crate::sync::Flag;
+use core::sync::atomic::Ordering;
 
 /// A Sifive peripheral
 pub trait Peripheral: Sized {
@@ -16,3 +22,66 @@ pub trait Peripheral: Sized {
     /// Returns `None` if the peripheral is already in use.
     fn get() -> Option<Self>;
 }
+
+/// All of the peripherals of a SiFive MCU
+///
+/// This gathers every peripheral singleton into one place, for
+/// applications which want to manage all of the chip's resources
+/// themselves. The [`Plic`](plic::Plic) is not included, as it is
+/// shared with the trap handler.
+///
+/// The board-level Cntrlr functions get these peripherals as they
+/// need them, and will fail while they are held here. Drop the
+/// relevant field to return a peripheral to the board layer.
+pub struct Peripherals<M> {
+    /// The power, reset, clock, and interrupt controller
+    pub prci: prci::Prci<M>,
+
+    /// The GPIO controller
+    pub gpio: gpio::Gpio<M, 0>,
+
+    /// The first UART
+    pub uart0: uart::Uart<M, (), (), 0>,
+
+    /// The second UART
+    pub uart1: uart::Uart<M, (), (), 1>,
+
+    /// The SPI controller for the boot flash
+    pub spi0: spi::Spi<M, (), (), 0>,
+}
+
+static TAKEN: Flag = Flag::new(false);
+
+impl<M> Peripherals<M>
+where
+    prci::Prci<M>: Peripheral,
+    gpio::Gpio<M, 0>: Peripheral,
+    uart::Uart<M, (), (), 0>: Peripheral,
+    uart::Uart<M, (), (), 1>: Peripheral,
+    spi::Spi<M, (), (), 0>: Peripheral,
+{
+    /// Take all of the peripherals
+    ///
+    /// This succeeds at most once. Returns `None` if the peripherals
+    /// have already been taken, or if any of them is currently in
+    /// use.
+    pub fn take() -> Option<Self> {
+        if TAKEN.swap(true, Ordering::Acquire) {
+            return None;
+        }
+
+        let peripherals = (|| {
+            Some(Self {
+                prci: Peripheral::get()?,
+                gpio: Peripheral::get()?,
+                uart0: Peripheral::get()?,
+                uart1: Peripheral::get()?,
+                spi0: Peripheral::get()?,
+            })
+        })();
+        if peripherals.is_none() {
+            TAKEN.store(false, Ordering::Release);
+        }
+        peripherals
+    }
+}
@@ -7,7 +7,10 @@
 //! FE310 series, and is not intended as a general-purpose RISC-V PLIC
 //! driver.
 
-use crate::register::{Register, Reserved};
+use crate::{
+    register::{Register, Reserved},
+    sync::without_interrupts,
+};
 use bit_field::BitField;
 
 #[repr(C)]
@@ -23,6 +26,9 @@ struct PlicRegs {
     claim: Register<u32>,
 }
 
+/// A handler for a PLIC interrupt
+pub type Handler = extern "C" fn();
+
 /// The PLIC
 pub struct Plic {
     regs: &'static mut PlicRegs,
@@ -71,6 +77,19 @@ impl Plic {
         self.regs.claim.read()
     }
 
+    /// Claim the highest-priority pending interrupt
+    ///
+    /// The interrupt is completed when the returned guard is
+    /// dropped. Returns `None` if no interrupt is pending.
+    pub fn claim_next(&mut self) -> Option<Claim> {
+        let intr = self.claim();
+        if intr == 0 {
+            None
+        } else {
+            Some(Claim { plic: self, intr })
+        }
+    }
+
     /// Complete a claimed interrupt
     pub fn complete(&mut self, intr: u32) {
         self.regs.claim.write(intr);
@@ -90,4 +109,59 @@ impl Plic {
         assert!(priority < 8);
         self.regs.priority[intr - 1].write(priority);
     }
+
+    /// Set the function called for an interrupt by [`dispatch`](Self::dispatch)
+    ///
+    /// If `handler` is `None`, the interrupt will be completed without
+    /// any function being called. This does not enable the interrupt.
+    pub fn set_handler(&mut self, intr: usize, handler: Option<Handler>) {
+        assert!(intr >= 1 && intr <= HANDLER_COUNT);
+        without_interrupts(|| unsafe {
+            HANDLERS[intr - 1] = handler;
+        });
+    }
+
+    /// Claim and handle every pending interrupt
+    ///
+    /// Each interrupt is passed to the function set for it by
+    /// [`set_handler`](Self::set_handler), and completed once the
+    /// function returns. This is intended to be called from the
+    /// machine external interrupt trap.
+    pub fn dispatch(&mut self) {
+        while let Some(claim) = self.claim_next() {
+            let intr = claim.interrupt() as usize;
+            if intr <= HANDLER_COUNT {
+                if let Some(handler) = unsafe { HANDLERS[intr - 1] } {
+                    handler();
+                }
+            }
+        }
+    }
+}
+
+/// A claimed interrupt
+///
+/// The interrupt is completed when this is dropped.
+pub struct Claim<'a> {
+    plic: &'a mut Plic,
+    intr: u32,
+}
+
+impl Claim<'_> {
+    /// The claimed interrupt source
+    pub fn interrupt(&self) -> u32 {
+        self.intr
+    }
 }
+
+impl Drop for Claim<'_> {
+    fn drop(&mut self) {
+        self.plic.complete(self.intr);
+    }
+}
+
+const HANDLER_COUNT: usize = 52;
+
+// Only written with interrupts disabled, and only read from the trap
+// handler.
+static mut HANDLERS: [Option<Handler>; HANDLER_COUNT] = [None; HANDLER_COUNT];
@@ -0,0 +1,132 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! The PWM blocks
+//!
+//! Each PWM block has a counter and four comparators. Comparator 0
+//! sets the period, and comparators 1 through 3 drive the outputs. An
+//! output is high from when the counter reaches its comparator until
+//! the end of the period. PWM 0 has 8-bit comparators, and PWM 1 and
+//! PWM 2 have 16-bit comparators.
+
+use crate::{
+    register::{Register, Reserved},
+    sync::Flag,
+};
+use bit_field::BitField;
+use core::{marker::PhantomData, sync::atomic::Ordering};
+
+#[repr(C)]
+struct PwmRegs {
+    cfg: Register<u32>,
+    _reserved0: Reserved<u32>,
+    count: Register<u32>,
+    _reserved1: Reserved<u32>,
+    s: Register<u32>,
+    _reserved2: [Reserved<u32>; 3],
+    cmp: [Register<u32>; 4],
+}
+
+/// A PWM block
+pub struct Pwm<M, const N: usize> {
+    regs: &'static mut PwmRegs,
+    _mcu: PhantomData<M>,
+}
+
+static LOCKS: [Flag; 3] = [Flag::new(false), Flag::new(false), Flag::new(false)];
+
+macro_rules! get {
+    ($i:literal, $a:literal) => {
+        #[cfg(any(doc, mcu = "fe310g002"))]
+        #[cfg_attr(feature = "doc-cfg", doc(cfg(mcu = "fe310g002")))]
+        impl super::Peripheral for Pwm<super::super::Fe310G002, $i> {
+            fn get() -> Option<Self> {
+                unsafe {
+                    if LOCKS[$i].swap(true, Ordering::Acquire) {
+                        None
+                    } else {
+                        Some(Self {
+                            regs: &mut *($a as *mut _),
+                            _mcu: PhantomData,
+                        })
+                    }
+                }
+            }
+        }
+    };
+}
+
+get!(0, 0x1001_5000);
+get!(1, 0x1002_5000);
+get!(2, 0x1003_5000);
+
+impl<M, const N: usize> Pwm<M, N>
+where
+    Pwm<M, N>: super::Peripheral,
+{
+    /// Get the handle to a PWM block
+    ///
+    /// Returns 'None' if the PWM block is already in use.
+    pub fn get() -> Option<Self> {
+        super::Peripheral::get()
+    }
+}
+
+impl<M, const N: usize> Pwm<M, N> {
+    /// The width of the comparators, in bits
+    pub fn comparator_bits(&self) -> u32 {
+        if N == 0 {
+            8
+        } else {
+            16
+        }
+    }
+
+    /// Start the counter with a period
+    ///
+    /// The counter counts the bus clock divided by `2^scale`, and
+    /// restarts after `period` counts. Valid scales are `0..16`. The
+    /// period must be less than `2^comparator_bits()`, so that an
+    /// output can be held low for a whole period.
+    pub fn set_period(&mut self, scale: u32, period: u32) {
+        assert!(scale < 16);
+        assert!(period > 0 && period < 1 << self.comparator_bits());
+        self.regs.cfg.write(0);
+        self.regs.count.write(0);
+        self.regs.cmp[0].write(period - 1);
+        let mut cfg = 0;
+        cfg.set_bits(0..4, scale);
+        cfg.set_bit(9, true);
+        cfg.set_bit(10, true);
+        cfg.set_bit(12, true);
+        self.regs.cfg.write(cfg);
+    }
+
+    /// The scale and period of the counter
+    ///
+    /// Returns `None` if the counter is stopped.
+    pub fn period(&self) -> Option<(u32, u32)> {
+        let cfg = self.regs.cfg.read();
+        if cfg.get_bit(12) {
+            Some((cfg.get_bits(0..4), self.regs.cmp[0].read() + 1))
+        } else {
+            None
+        }
+    }
+
+    /// Set the number of counts in each period for which an output is high
+    ///
+    /// Valid outputs are 1 through 3. A value of the period or more
+    /// holds the output high.
+    pub fn set_duty(&mut self, output: usize, duty: u32) {
+        assert!(output > 0 && output < 4);
+        let period = self.regs.cmp[0].read() + 1;
+        self.regs.cmp[output].write(period - duty.min(period));
+    }
+}
+
+impl<M, const N: usize> Drop for Pwm<M, N> {
+    fn drop(&mut self) {
+        LOCKS[N].store(false, Ordering::Release);
+    }
+}
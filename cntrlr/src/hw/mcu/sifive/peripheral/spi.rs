@@ -8,8 +8,9 @@
 
 use crate::{
     register::{Register, Reserved},
-    sync::Flag,
+    sync::{without_interrupts, Flag},
 };
+use bit_field::BitField;
 use core::{marker::PhantomData, sync::atomic::Ordering};
 
 #[repr(C)]
@@ -90,6 +91,151 @@ impl<M, const N: usize> Spi<M, (), (), N> {
     }
 }
 
+/// The command used to read from memory-mapped flash
+#[derive(Clone, Copy)]
+#[non_exhaustive]
+pub enum FlashRead {
+    /// The standard read command (`0x03`), on a single data line
+    ///
+    /// Most flash chips limit this command to a lower clock rate than
+    /// the fast read commands.
+    Single,
+
+    /// The fast read command (`0x0B`), on a single data line
+    Fast,
+
+    /// The dual output fast read command (`0x3B`), with data on two lines
+    Dual,
+
+    /// The quad output fast read command (`0x6B`), with data on four lines
+    ///
+    /// The flash chip's quad enable bit must be set for this command
+    /// to work.
+    Quad,
+}
+
+/// Configuration for the memory-mapped flash interface
+#[derive(Clone, Copy)]
+pub struct FlashConfig {
+    /// The SPI clock divisor. See [`Spi::set_divisor`].
+    pub divisor: usize,
+
+    /// The command used to read from the flash
+    pub read: FlashRead,
+
+    /// The minimum number of clocks between chip select and the
+    /// first clock edge, and between the last clock edge and chip
+    /// deselect
+    pub cs_delay: u8,
+
+    /// The minimum number of clocks chip select is deasserted between
+    /// transfers
+    pub cs_idle: u8,
+}
+
+impl Default for FlashConfig {
+    /// The configuration of the flash interface at reset
+    fn default() -> Self {
+        Self {
+            divisor: 8,
+            read: FlashRead::Single,
+            cs_delay: 1,
+            cs_idle: 1,
+        }
+    }
+}
+
+impl<M> Spi<M, (), (), 0> {
+    /// Reconfigure the memory-mapped flash interface
+    ///
+    /// Code executing from flash cannot safely change the interface
+    /// it is being fetched through. The new settings are written by a
+    /// routine in RAM, with interrupts disabled.
+    ///
+    /// # Safety
+    /// The flash chip must support the chosen read command at the
+    /// resulting clock rate. If it does not, the next instruction
+    /// fetch from flash will fail, and the device will hang.
+    pub unsafe fn configure_flash(&mut self, config: &FlashConfig) {
+        let div = config.divisor;
+        assert!(div % 2 == 0 && div >= 2 && div <= 8192);
+
+        let (code, pad, proto) = match config.read {
+            FlashRead::Single => (0x03, 0, 0),
+            FlashRead::Fast => (0x0B, 8, 0),
+            FlashRead::Dual => (0x3B, 8, 1),
+            FlashRead::Quad => (0x6B, 8, 2),
+        };
+        let mut ffmt = 0;
+        ffmt.set_bit(0, true); // Send a command on each transfer
+        ffmt.set_bits(1..4, 3); // Three address bytes
+        ffmt.set_bits(4..8, pad);
+        ffmt.set_bits(12..14, proto);
+        ffmt.set_bits(16..24, code);
+
+        let mut delay0 = 0;
+        delay0.set_bits(0..8, config.cs_delay as u32);
+        delay0.set_bits(16..24, config.cs_delay as u32);
+        let mut delay1 = self.regs.delay1.read();
+        delay1.set_bits(0..8, config.cs_idle as u32);
+
+        let regs = &mut *self.regs as *mut SpiRegs as *mut u32;
+        without_interrupts(|| {
+            write_flash_config(regs, (div / 2 - 1) as u32, ffmt, delay0, delay1);
+        });
+    }
+
+    /// Set the SPI divisor for the memory-mapped flash interface
+    ///
+    /// This is [`set_divisor`](Self::set_divisor), but performed from
+    /// RAM as in [`configure_flash`](Self::configure_flash).
+    ///
+    /// # Safety
+    /// The flash chip must support the current read command at the
+    /// resulting clock rate.
+    pub unsafe fn set_flash_divisor(&mut self, div: usize) {
+        assert!(div % 2 == 0 && div >= 2 && div <= 8192);
+        let ffmt = self.regs.ffmt.read();
+        let delay0 = self.regs.delay0.read();
+        let delay1 = self.regs.delay1.read();
+        let regs = &mut *self.regs as *mut SpiRegs as *mut u32;
+        without_interrupts(|| {
+            write_flash_config(regs, (div / 2 - 1) as u32, ffmt, delay0, delay1);
+        });
+    }
+}
+
+/// Write the flash interface registers
+///
+/// This is placed in RAM by the linker script, so that it does not
+/// fetch instructions from flash while the interface changes. It must
+/// not call any other function, which is why the registers are
+/// written with inline assembly.
+#[cfg_attr(mcu = "fe310g002", link_section = ".__CNTRLR_RAMFUNC.spi_flash")]
+#[inline(never)]
+#[allow(unused_variables)]
+unsafe extern "C" fn write_flash_config(
+    regs: *mut u32,
+    sckdiv: u32,
+    ffmt: u32,
+    delay0: u32,
+    delay1: u32,
+) {
+    #[cfg(mcu = "fe310g002")]
+    asm!("
+        sw {sckdiv}, 0x00({regs})
+        sw {delay0}, 0x28({regs})
+        sw {delay1}, 0x2C({regs})
+        sw {ffmt}, 0x64({regs})
+        fence io, io",
+         regs = in(reg) regs,
+         sckdiv = in(reg) sckdiv,
+         ffmt = in(reg) ffmt,
+         delay0 = in(reg) delay0,
+         delay1 = in(reg) delay1,
+    );
+}
+
 impl<M, T, R, const N: usize> Drop for Spi<M, T, R, N> {
     fn drop(&mut self) {
         LOCKS[N].store(false, Ordering::Release);
@@ -81,12 +81,22 @@ impl<M, const N: usize> Uart<M, (), (), N> {
         assert!(div >= 1);
         self.regs.div.write(div as u32 - 1);
     }
+
+    /// Send two stop bits, rather than one
+    ///
+    /// The receiver only ever checks the first stop bit.
+    pub fn set_two_stop_bits(&mut self, two: bool) {
+        self.regs.txctrl.update(|txctrl| {
+            // NSTOP
+            txctrl.set_bit(1, two);
+        });
+    }
 }
 
 impl<M, T, const N: usize> Uart<M, T, (), N> {
     /// Enable this UART as a reciever
     pub fn enable_rx<R: UartRx<M, N>>(mut self, rx: R) -> Uart<M, T, R, N> {
-        self.regs.txctrl.update(|rxctrl| {
+        self.regs.rxctrl.update(|rxctrl| {
             rxctrl.set_bit(0, true);
         });
         unsafe {
@@ -0,0 +1,150 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Watchdog
+//!
+//! The watchdog is part of the always-on domain, and counts the
+//! low-frequency clock. Every write to a watchdog register must be
+//! preceded by a write of the unlock key.
+
+use crate::{
+    register::{Register, Reserved},
+    sync::Flag,
+};
+use bit_field::BitField;
+use core::{marker::PhantomData, sync::atomic::Ordering};
+
+#[repr(C)]
+struct WdogRegs {
+    wdogcfg: Register<u32>,
+    _reserved0: Reserved<u32>,
+    wdogcount: Register<u32>,
+    _reserved1: Reserved<u32>,
+    wdogs: Register<u32>,
+    _reserved2: Reserved<u32>,
+    wdogfeed: Register<u32>,
+    wdogkey: Register<u32>,
+    wdogcmp0: Register<u32>,
+}
+
+/// The watchdog
+pub struct Wdog<M> {
+    regs: &'static mut WdogRegs,
+    _mcu: PhantomData<M>,
+}
+
+static LOCK: Flag = Flag::new(false);
+
+const KEY: u32 = 0x0051_F15E;
+const FEED: u32 = 0x0D09_F00D;
+
+#[cfg(any(doc, mcu = "fe310g002"))]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(mcu = "fe310g002")))]
+impl super::Peripheral for Wdog<super::super::Fe310G002> {
+    /// Get the watchdog instance
+    fn get() -> Option<Self> {
+        if LOCK.swap(true, Ordering::Acquire) {
+            None
+        } else {
+            // Safety: The lock ensures this is the only handle
+            unsafe { Some(Self::steal()) }
+        }
+    }
+}
+
+impl<M> Wdog<M>
+where
+    Wdog<M>: super::Peripheral,
+{
+    /// Return the handle to the watchdog
+    ///
+    /// Returns 'None' if the watchdog is already in use.
+    pub fn get() -> Option<Self> {
+        super::Peripheral::get()
+    }
+}
+
+impl<M> Wdog<M> {
+    /// Get the watchdog without taking its lock
+    ///
+    /// # Safety
+    /// The returned handle must not be used at the same time as any
+    /// other handle to the watchdog, and must never be dropped.
+    #[cfg(any(doc, mcu = "fe310g002"))]
+    pub(crate) unsafe fn steal() -> Self {
+        Self {
+            regs: &mut *(0x1000_0000 as *mut _),
+            _mcu: PhantomData,
+        }
+    }
+
+    /// Enable the watchdog
+    ///
+    /// The counter is incremented by the low-frequency clock, and is
+    /// compared after being divided by `2^scale`. Valid scale values
+    /// are `0..16`. The chip is reset when the scaled count reaches
+    /// `compare`, unless the watchdog is fed first.
+    pub fn enable(&mut self, scale: u32, compare: u16) {
+        assert!(scale < 16);
+        self.unlock();
+        self.regs.wdogcfg.write(0);
+        self.unlock();
+        self.regs.wdogcount.write(0);
+        self.unlock();
+        self.regs.wdogcmp0.write(compare as u32);
+        self.unlock();
+        self.regs.wdogcfg.update(|wdogcfg| {
+            wdogcfg.set_bits(0..4, scale);
+            wdogcfg.set_bit(8, true); // Reset on timeout
+            wdogcfg.set_bit(12, true); // Always count
+        });
+    }
+
+    /// Disable the watchdog
+    pub fn disable(&mut self) {
+        self.unlock();
+        self.regs.wdogcfg.update(|wdogcfg| {
+            wdogcfg.set_bit(8, false);
+            wdogcfg.set_bit(12, false);
+            wdogcfg.set_bit(13, false);
+        });
+    }
+
+    /// Feed the watchdog, restarting its count
+    pub fn feed(&mut self) {
+        self.unlock();
+        self.regs.wdogfeed.write(FEED);
+    }
+
+    /// Whether the watchdog is enabled
+    pub fn is_enabled(&self) -> bool {
+        let wdogcfg = self.regs.wdogcfg.read();
+        wdogcfg.get_bit(12) || wdogcfg.get_bit(13)
+    }
+
+    /// The current scaled count
+    pub fn count(&self) -> u16 {
+        self.regs.wdogs.read().get_bits(0..16) as u16
+    }
+
+    /// Reset the chip through the watchdog
+    ///
+    /// This is a full reset, including the always-on domain, so its
+    /// effect is the same as a watchdog timeout.
+    pub fn reset(&mut self) -> ! {
+        self.enable(0, 0);
+        loop {
+            core::sync::atomic::compiler_fence(Ordering::SeqCst);
+        }
+    }
+
+    fn unlock(&mut self) {
+        self.regs.wdogkey.write(KEY);
+    }
+}
+
+impl<M> Drop for Wdog<M> {
+    fn drop(&mut self) {
+        LOCK.store(false, Ordering::Release);
+    }
+}
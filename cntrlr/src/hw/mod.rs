@@ -9,3 +9,75 @@
 
 pub mod board;
 pub mod mcu;
+
+#[cfg(any(
+    mcu = "fe310g002",
+    mcu = "imxrt1062",
+    mcu = "mk20dx128",
+    mcu = "mk20dx256",
+    mcu = "mk64fx512",
+    mcu = "mk66fx1m0",
+    mcu = "mkl26z64"
+))]
+mod dump;
+
+// The peripherals of the selected MCU, so that applications can take
+// them without naming the MCU.
+#[cfg(mcu = "fe310g002")]
+pub use mcu::sifive::fe310g002::Peripherals;
+
+#[cfg(mcu = "mk20dx128")]
+pub use mcu::kinetis::mk20dx128::Peripherals;
+
+#[cfg(mcu = "mk20dx256")]
+pub use mcu::kinetis::mk20dx256::Peripherals;
+
+#[cfg(mcu = "mk64fx512")]
+pub use mcu::kinetis::mk64fx512::Peripherals;
+
+#[cfg(mcu = "mk66fx1m0")]
+pub use mcu::kinetis::mk66fx1m0::Peripherals;
+
+#[cfg(mcu = "mkl26z64")]
+pub use mcu::kinetis::mkl26z64::Peripherals;
+
+/// Write a report of the MCU's peripheral state
+///
+/// The report lists the clock configuration, the control and status
+/// registers of each UART and SPI, and which interrupts are enabled
+/// in the NVIC or PLIC, one register per line. It is meant to be
+/// attached to bug reports, or compared between a working and a
+/// broken build.
+///
+/// The registers are read directly, so the report is accurate even
+/// while drivers hold the peripherals. Data registers are never read,
+/// so writing the report does not disturb them. Peripherals whose
+/// clocks are gated off are listed as such, since their registers
+/// cannot be read.
+#[cfg(any(
+    mcu = "fe310g002",
+    mcu = "imxrt1062",
+    mcu = "mk20dx128",
+    mcu = "mk20dx256",
+    mcu = "mk64fx512",
+    mcu = "mk66fx1m0",
+    mcu = "mkl26z64"
+))]
+pub fn dump_state<W: core::fmt::Write>(writer: &mut W) -> core::fmt::Result {
+    #[cfg(mcu = "fe310g002")]
+    use mcu::sifive::dump;
+
+    #[cfg(mcu = "imxrt1062")]
+    use mcu::imxrt::dump;
+
+    #[cfg(any(
+        mcu = "mk20dx128",
+        mcu = "mk20dx256",
+        mcu = "mk64fx512",
+        mcu = "mk66fx1m0",
+        mcu = "mkl26z64"
+    ))]
+    use mcu::kinetis::dump;
+
+    dump::dump_state(writer)
+}
@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Input devices for Cntrlr boards
+//!
+//! An [`Encoder`] follows the position of a quadrature rotary
+//! encoder in hardware, using a FlexTimer's quadrature decoder. No
+//! steps are missed, however fast the encoder turns.
+//!
+//! ```
+//! use cntrlr::{input::Encoder, prelude::*};
+//!
+//! #[entry]
+//! async fn main() -> ! {
+//!     let mut knob = Encoder::new(3, 4, &[]).expect("Failed to start encoder");
+//!     loop {
+//!         let position = knob.wait_for_count(knob.position() + 4).await;
+//!         writeln!(serial_1().await, "Turned to {}", position).await.ok();
+//!     }
+//! }
+//! ```
+//!
+//! Each encoder needs a pair of pins which share a FlexTimer. The
+//! pairs are given as (phase A, phase B):
+//!
+//! | Board      | FTM1               | FTM2     |
+//! |------------|--------------------|----------|
+//! | Teensy 3.0 | (3, 4) or (16, 17) |          |
+//! | Teensy 3.2 | (3, 4) or (16, 17) | (32, 25) |
+//! | Teensy 3.5 | (3, 4) or (16, 17) | (29, 30) |
+//! | Teensy 3.6 | (3, 4) or (16, 17) | (29, 30) |
+//!
+//! An encoder's FlexTimer cannot be used for hardware PWM at the
+//! same time. The Teensy LC has no quadrature decoder.
+
+use cntrlr_macros::board_fn;
+
+/// Options for an encoder
+#[non_exhaustive]
+pub enum EncoderOption {
+    /// Ignore pulses on either phase shorter than `4 * n` bus clock cycles, from 0 to 15
+    ///
+    /// This rejects contact bounce on mechanical encoders. The
+    /// default of 0 disables the filter.
+    Filter(u32),
+}
+
+#[cfg(any(
+    doc,
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36"
+    )))
+)]
+pub use crate::hw::board::teensy_common::input::{Encoder, EncoderError};
+
+/// Start decoding an encoder on a pair of pins
+///
+/// Returns the index of the encoder's slot, which is used by the
+/// other encoder functions.
+#[board_fn(input, teensy_30, teensy_32, teensy_35, teensy_36)]
+pub(crate) fn start_encoder(
+    pin_a: usize,
+    pin_b: usize,
+    filter: u32,
+) -> Result<usize, EncoderError> {
+}
+
+/// The low 16 bits of an encoder's position
+#[board_fn(input, teensy_30, teensy_32, teensy_35, teensy_36)]
+pub(crate) fn encoder_count(encoder: usize) -> u16 {}
+
+/// Reset an encoder's counter to zero
+#[board_fn(input, teensy_30, teensy_32, teensy_35, teensy_36)]
+pub(crate) fn reset_encoder(encoder: usize) {}
+
+/// Stop decoding an encoder, and release its timer
+#[board_fn(input, teensy_30, teensy_32, teensy_35, teensy_36)]
+pub(crate) fn stop_encoder(encoder: usize) {}
@@ -0,0 +1,507 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Block devices and block caching
+
+use super::{Error, ErrorKind};
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+use crate::time::millis;
+use alloc::{vec, vec::Vec};
+use core::{fmt::Debug, future::Future};
+
+/// A device which is read and written in fixed-size blocks
+///
+/// This is the interface between storage, such as an SD card or an
+/// SPI flash, and a filesystem or logger.
+pub trait BlockDevice {
+    /// The error type
+    type Error: Debug;
+
+    /// The future for [`Self::read_block()`]
+    type ReadFuture<'a>: Future<Output = Result<(), Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// The future for [`Self::write_block()`]
+    type WriteFuture<'a>: Future<Output = Result<(), Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// The size of each block, in bytes
+    fn block_size(&self) -> usize;
+
+    /// The number of blocks on the device
+    fn block_count(&self) -> usize;
+
+    /// Read a block
+    ///
+    /// `buf` must be exactly [`Self::block_size()`] bytes long.
+    fn read_block<'a>(&'a mut self, block: usize, buf: &'a mut [u8]) -> Self::ReadFuture<'a>
+    where
+        Self: 'a;
+
+    /// Write a block
+    ///
+    /// `buf` must be exactly [`Self::block_size()`] bytes long.
+    fn write_block<'a>(&'a mut self, block: usize, buf: &'a [u8]) -> Self::WriteFuture<'a>
+    where
+        Self: 'a;
+}
+
+/// Error type for [`BlockCache`]
+#[derive(Debug)]
+pub enum CacheError<E> {
+    /// Error from the underlying device
+    Device(E),
+
+    /// The block cannot be accessed because the buffer is not one block long
+    InvalidBuffer,
+
+    /// The block cannot be accessed because it is past the end of the device
+    InvalidBlock,
+}
+
+impl<E> From<CacheError<E>> for Error
+where
+    E: Into<Error>,
+{
+    fn from(err: CacheError<E>) -> Self {
+        match err {
+            CacheError::Device(err) => err.into(),
+            CacheError::InvalidBuffer | CacheError::InvalidBlock => ErrorKind::InvalidData.into(),
+        }
+    }
+}
+
+/// Statistics collected by a [`BlockCache`]
+#[derive(Clone, Copy, Debug, Default)]
+#[non_exhaustive]
+pub struct CacheStats {
+    /// The number of reads and writes of a block which was already cached
+    pub hits: usize,
+
+    /// The number of reads and writes of a block which was not cached
+    pub misses: usize,
+
+    /// The number of blocks written back to the device
+    pub write_backs: usize,
+
+    /// The number of writes to a block which was already dirty
+    ///
+    /// Each of these is a write which the device never saw.
+    pub coalesced: usize,
+}
+
+impl CacheStats {
+    /// Create a new set of statistics, with all counters at zero
+    pub const fn new() -> Self {
+        Self {
+            hits: 0,
+            misses: 0,
+            write_backs: 0,
+            coalesced: 0,
+        }
+    }
+}
+
+/// A write-back cache in front of a block device
+///
+/// The cache holds a fixed number of blocks. Writes only reach the
+/// device when a dirty block is evicted to make room for another,
+/// when it has been dirty for longer than the flush interval, or
+/// when [`sync_all()`](Self::sync_all) is called. Repeated writes to
+/// the same block, as made by a logger appending to a file, reach the
+/// device once instead of once per write.
+///
+/// The cache implements [`BlockDevice`], so it can be used anywhere
+/// the device itself could.
+///
+/// The flush interval is checked on each access and by
+/// [`flush_expired()`](Self::flush_expired). An application which
+/// may leave a cache idle should call `flush_expired` periodically.
+///
+/// Data in dirty blocks is lost if the cache is dropped or power is
+/// removed before it is written back.
+pub struct BlockCache<D> {
+    device: D,
+    entries: Vec<Entry>,
+    capacity: usize,
+    #[cfg(any(
+        doc,
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    ))]
+    flush_interval: Option<usize>,
+    clock: usize,
+    stats: CacheStats,
+}
+
+struct Entry {
+    block: usize,
+    data: Vec<u8>,
+    dirty: bool,
+    #[cfg(any(
+        doc,
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    ))]
+    dirtied: usize,
+    used: usize,
+}
+
+impl<D> BlockCache<D> {
+    /// The cache's statistics
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Reset all statistics for this cache to zero
+    pub fn reset_stats(&mut self) {
+        self.stats = CacheStats::new();
+    }
+
+    /// Set the longest time, in milliseconds, a block may stay dirty
+    ///
+    /// If `interval` is `None`, blocks are written back only when
+    /// they are evicted or synced. This is the default.
+    #[cfg(any(
+        doc,
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    ))]
+    #[cfg_attr(
+        feature = "doc-cfg",
+        doc(cfg(any(
+            board = "red_v",
+            board = "teensy_30",
+            board = "teensy_32",
+            board = "teensy_35",
+            board = "teensy_36",
+            board = "teensy_40",
+            board = "teensy_41",
+            board = "teensy_lc"
+        )))
+    )]
+    pub fn set_flush_interval(&mut self, interval: Option<usize>) {
+        self.flush_interval = interval;
+    }
+
+    /// The number of blocks which have not been written back
+    pub fn dirty_blocks(&self) -> usize {
+        self.entries.iter().filter(|entry| entry.dirty).count()
+    }
+
+    /// The underlying device
+    pub fn get_ref(&self) -> &D {
+        &self.device
+    }
+
+    /// Get the underlying device back
+    ///
+    /// Any dirty blocks are discarded. Call
+    /// [`sync_all()`](Self::sync_all) first to keep them.
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+}
+
+impl<D> BlockCache<D>
+where
+    D: BlockDevice,
+{
+    /// Cache up to `capacity` blocks of a device
+    ///
+    /// Each cached block is allocated on first use.
+    pub fn new(device: D, capacity: usize) -> Self {
+        assert!(capacity > 0);
+        Self {
+            device,
+            entries: Vec::with_capacity(capacity),
+            capacity,
+            #[cfg(any(
+                doc,
+                board = "red_v",
+                board = "teensy_30",
+                board = "teensy_32",
+                board = "teensy_35",
+                board = "teensy_36",
+                board = "teensy_40",
+                board = "teensy_41",
+                board = "teensy_lc"
+            ))]
+            flush_interval: None,
+            clock: 0,
+            stats: CacheStats::new(),
+        }
+    }
+
+    /// Write every dirty block back to the device
+    pub fn sync_all(&mut self) -> impl Future<Output = Result<(), CacheError<D::Error>>> + '_ {
+        async move {
+            for entry in &mut self.entries {
+                if entry.dirty {
+                    self.device
+                        .write_block(entry.block, &entry.data)
+                        .await
+                        .map_err(CacheError::Device)?;
+                    entry.dirty = false;
+                    self.stats.write_backs += 1;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Write back every block which has been dirty for longer than the flush interval
+    #[cfg(any(
+        doc,
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    ))]
+    #[cfg_attr(
+        feature = "doc-cfg",
+        doc(cfg(any(
+            board = "red_v",
+            board = "teensy_30",
+            board = "teensy_32",
+            board = "teensy_35",
+            board = "teensy_36",
+            board = "teensy_40",
+            board = "teensy_41",
+            board = "teensy_lc"
+        )))
+    )]
+    pub fn flush_expired(&mut self) -> impl Future<Output = Result<(), CacheError<D::Error>>> + '_ {
+        async move {
+            let interval = match self.flush_interval {
+                Some(interval) => interval,
+                None => return Ok(()),
+            };
+            let now = millis();
+            for entry in &mut self.entries {
+                if entry.dirty && now.wrapping_sub(entry.dirtied) >= interval {
+                    self.device
+                        .write_block(entry.block, &entry.data)
+                        .await
+                        .map_err(CacheError::Device)?;
+                    entry.dirty = false;
+                    self.stats.write_backs += 1;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn check(&self, block: usize, len: usize) -> Result<(), CacheError<D::Error>> {
+        if len != self.device.block_size() {
+            Err(CacheError::InvalidBuffer)
+        } else if block >= self.device.block_count() {
+            Err(CacheError::InvalidBlock)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Find the cached entry for a block, marking it as used
+    fn lookup(&mut self, block: usize) -> Option<usize> {
+        self.clock += 1;
+        let idx = self.entries.iter().position(|entry| entry.block == block);
+        if let Some(idx) = idx {
+            self.entries[idx].used = self.clock;
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+        idx
+    }
+
+    /// Make room for a block, evicting the least recently used one if needed
+    fn allocate(
+        &mut self,
+        block: usize,
+    ) -> impl Future<Output = Result<usize, CacheError<D::Error>>> + '_ {
+        async move {
+            if self.entries.len() < self.capacity {
+                self.entries.push(Entry {
+                    block,
+                    data: vec![0; self.device.block_size()],
+                    dirty: false,
+                    #[cfg(any(
+                        doc,
+                        board = "red_v",
+                        board = "teensy_30",
+                        board = "teensy_32",
+                        board = "teensy_35",
+                        board = "teensy_36",
+                        board = "teensy_40",
+                        board = "teensy_41",
+                        board = "teensy_lc"
+                    ))]
+                    dirtied: 0,
+                    used: self.clock,
+                });
+                return Ok(self.entries.len() - 1);
+            }
+
+            let (idx, _) = self
+                .entries
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, entry)| entry.used)
+                .unwrap();
+            let entry = &mut self.entries[idx];
+            if entry.dirty {
+                self.device
+                    .write_block(entry.block, &entry.data)
+                    .await
+                    .map_err(CacheError::Device)?;
+                entry.dirty = false;
+                self.stats.write_backs += 1;
+            }
+            entry.block = block;
+            entry.used = self.clock;
+            Ok(idx)
+        }
+    }
+}
+
+impl<D> BlockDevice for BlockCache<D>
+where
+    D: BlockDevice,
+{
+    type Error = CacheError<D::Error>;
+    #[rustfmt::skip]
+    type ReadFuture<'a> where Self: 'a = impl Future<Output = Result<(), Self::Error>>;
+    #[rustfmt::skip]
+    type WriteFuture<'a> where Self: 'a = impl Future<Output = Result<(), Self::Error>>;
+
+    fn block_size(&self) -> usize {
+        self.device.block_size()
+    }
+
+    fn block_count(&self) -> usize {
+        self.device.block_count()
+    }
+
+    fn read_block<'a>(&'a mut self, block: usize, buf: &'a mut [u8]) -> Self::ReadFuture<'a>
+    where
+        Self: 'a,
+    {
+        async move {
+            self.check(block, buf.len())?;
+            #[cfg(any(
+                doc,
+                board = "red_v",
+                board = "teensy_30",
+                board = "teensy_32",
+                board = "teensy_35",
+                board = "teensy_36",
+                board = "teensy_40",
+                board = "teensy_41",
+                board = "teensy_lc"
+            ))]
+            self.flush_expired().await?;
+
+            let idx = match self.lookup(block) {
+                Some(idx) => idx,
+                None => {
+                    let idx = self.allocate(block).await?;
+                    let entry = &mut self.entries[idx];
+                    if let Err(err) = self.device.read_block(block, &mut entry.data).await {
+                        // The entry holds no valid data, so it must
+                        // not be found by a later lookup.
+                        self.entries.swap_remove(idx);
+                        return Err(CacheError::Device(err));
+                    }
+                    idx
+                }
+            };
+            buf.copy_from_slice(&self.entries[idx].data);
+            Ok(())
+        }
+    }
+
+    fn write_block<'a>(&'a mut self, block: usize, buf: &'a [u8]) -> Self::WriteFuture<'a>
+    where
+        Self: 'a,
+    {
+        async move {
+            self.check(block, buf.len())?;
+            #[cfg(any(
+                doc,
+                board = "red_v",
+                board = "teensy_30",
+                board = "teensy_32",
+                board = "teensy_35",
+                board = "teensy_36",
+                board = "teensy_40",
+                board = "teensy_41",
+                board = "teensy_lc"
+            ))]
+            self.flush_expired().await?;
+
+            // A whole block is written, so a missing block does not
+            // need to be read from the device first.
+            let idx = match self.lookup(block) {
+                Some(idx) => idx,
+                None => self.allocate(block).await?,
+            };
+            let entry = &mut self.entries[idx];
+            entry.data.copy_from_slice(buf);
+            if entry.dirty {
+                self.stats.coalesced += 1;
+            } else {
+                entry.dirty = true;
+                #[cfg(any(
+                    doc,
+                    board = "red_v",
+                    board = "teensy_30",
+                    board = "teensy_32",
+                    board = "teensy_35",
+                    board = "teensy_36",
+                    board = "teensy_40",
+                    board = "teensy_41",
+                    board = "teensy_lc"
+                ))]
+                {
+                    entry.dirtied = millis();
+                }
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,300 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Shared SPI buses
+
+use super::{Error, Spi, SpiTransfer};
+use alloc::{rc::Rc, vec::Vec};
+use core::{
+    cell::RefCell,
+    future::Future,
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+/// The order in which devices on a [`SpiBus`] are given the bus
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Arbitration {
+    /// Devices are given the bus in the order they asked for it
+    Fifo,
+
+    /// Devices with a higher priority are given the bus first
+    ///
+    /// Devices with the same priority are given the bus in the order
+    /// they asked for it. Each time a device is passed over, its
+    /// priority is raised by one, so that a busy high-priority device
+    /// delays, but cannot starve, the others.
+    Priority,
+}
+
+/// An SPI bus shared between several devices
+///
+/// Each [`SpiDevice`] on the bus may be used by a different task. A
+/// device waits for the bus without blocking the executor, and holds
+/// the bus for the duration of each transfer or lock.
+///
+/// ```
+/// use cntrlr::{io::{Arbitration, SpiBus, SpiDevice}, prelude::*};
+///
+/// #[entry]
+/// async fn main() -> ! {
+///     spi_1().await.enable().unwrap();
+///     let bus = SpiBus::new(spi_1, Arbitration::Priority);
+///     let mut display = SpiDevice::new(&bus, 10, 8_000_000, 8);
+///     let mut sd_card = SpiDevice::new(&bus, 15, 4_000_000, 8);
+///     sd_card.set_priority(1);
+///     // Move each device into its own task
+///     # loop {}
+/// }
+/// ```
+pub struct SpiBus<F> {
+    spi: fn() -> F,
+    policy: Arbitration,
+    state: RefCell<BusState>,
+}
+
+struct BusState {
+    locked: bool,
+    next_ticket: usize,
+    waiters: Vec<Waiter>,
+}
+
+struct Waiter {
+    ticket: usize,
+    priority: usize,
+    waker: Option<Waker>,
+}
+
+impl<F> SpiBus<F> {
+    /// Share an SPI port
+    ///
+    /// `spi` is the function used to get the port, such as
+    /// [`spi_1`](super::spi_1). The port should not be used except
+    /// through this bus.
+    pub fn new(spi: fn() -> F, policy: Arbitration) -> Rc<Self> {
+        Rc::new(Self {
+            spi,
+            policy,
+            state: RefCell::new(BusState {
+                locked: false,
+                next_ticket: 0,
+                waiters: Vec::new(),
+            }),
+        })
+    }
+}
+
+impl BusState {
+    /// The ticket of the waiter which should get the bus next
+    fn selected(&self, policy: Arbitration) -> Option<usize> {
+        let waiters = self.waiters.iter();
+        let waiter = match policy {
+            Arbitration::Fifo => waiters.min_by_key(|waiter| waiter.ticket),
+            Arbitration::Priority => {
+                waiters.max_by(|a, b| a.priority.cmp(&b.priority).then(b.ticket.cmp(&a.ticket)))
+            }
+        };
+        waiter.map(|waiter| waiter.ticket)
+    }
+
+    fn wake_all(&mut self) {
+        for waiter in &mut self.waiters {
+            if let Some(waker) = waiter.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// A device on a shared SPI bus
+pub struct SpiDevice<F> {
+    bus: Rc<SpiBus<F>>,
+    cs: usize,
+    baud: usize,
+    packet: usize,
+    priority: usize,
+}
+
+impl<F> SpiDevice<F> {
+    /// Add a device to a bus
+    ///
+    /// Transfers to the device use the chip select pin `cs`, with the
+    /// given baud rate and packet size in bits, as in
+    /// [`Spi::transfer()`].
+    pub fn new(bus: &Rc<SpiBus<F>>, cs: usize, baud: usize, packet: usize) -> Self {
+        Self {
+            bus: bus.clone(),
+            cs,
+            baud,
+            packet,
+            priority: 0,
+        }
+    }
+
+    /// Set the priority of this device
+    ///
+    /// The priority is only used by buses with
+    /// [`Arbitration::Priority`]. Higher values are given the bus
+    /// first. The default priority is 0.
+    pub fn set_priority(&mut self, priority: usize) {
+        self.priority = priority;
+    }
+}
+
+impl<F: Future> SpiDevice<F> {
+    /// Wait for exclusive use of the bus
+    ///
+    /// The bus is held until the returned lock is dropped, so that
+    /// several transfers can be made without another device using
+    /// the bus in between.
+    pub fn lock(&self) -> impl Future<Output = SpiLock<'_, F>> + '_ {
+        async move {
+            let claim = Acquire {
+                device: self,
+                ticket: None,
+            }
+            .await;
+            SpiLock {
+                spi: (self.bus.spi)().await,
+                _claim: claim,
+            }
+        }
+    }
+}
+
+impl<F> SpiDevice<F>
+where
+    F: Future,
+    F::Output: DerefMut,
+    <F::Output as Deref>::Target: Spi,
+{
+    /// Transfer bytes to and from this device
+    ///
+    /// This waits for the bus, and releases it once the transfer is
+    /// flushed. See [`SpiTransfer::transfer()`] for how the buffers
+    /// are used.
+    pub fn transfer<'a>(
+        &'a self,
+        buf_in: &'a [u8],
+        buf_out: &'a mut [u8],
+    ) -> impl Future<Output = Result<usize, Error>> + 'a {
+        async move {
+            let mut lock = self.lock().await;
+            let mut transfer = Spi::transfer(&mut *lock.spi, self.baud, self.cs, self.packet)
+                .await
+                .map_err(Into::into)?;
+            let count = SpiTransfer::transfer(&mut transfer, buf_in, buf_out)
+                .await
+                .map_err(Into::into)?;
+            SpiTransfer::flush(&mut transfer)
+                .await
+                .map_err(Into::into)?;
+            Ok(count)
+        }
+    }
+}
+
+struct Acquire<'a, F> {
+    device: &'a SpiDevice<F>,
+    ticket: Option<usize>,
+}
+
+impl<'a, F> Future for Acquire<'a, F> {
+    type Output = Claim<'a, F>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Claim<'a, F>> {
+        let bus = &*self.device.bus;
+        let priority = self.device.priority;
+        let mut state = bus.state.borrow_mut();
+        let ticket = match self.ticket {
+            Some(ticket) => ticket,
+            None => {
+                let ticket = state.next_ticket;
+                state.next_ticket = state.next_ticket.wrapping_add(1);
+                state.waiters.push(Waiter {
+                    ticket,
+                    priority,
+                    waker: None,
+                });
+                self.ticket = Some(ticket);
+                ticket
+            }
+        };
+
+        if !state.locked && state.selected(bus.policy) == Some(ticket) {
+            state.locked = true;
+            state.waiters.retain(|waiter| waiter.ticket != ticket);
+            for waiter in &mut state.waiters {
+                waiter.priority = waiter.priority.saturating_add(1);
+            }
+            drop(state);
+            self.ticket = None;
+            Poll::Ready(Claim(bus))
+        } else {
+            if let Some(waiter) = state
+                .waiters
+                .iter_mut()
+                .find(|waiter| waiter.ticket == ticket)
+            {
+                waiter.waker = Some(ctx.waker().clone());
+            }
+            Poll::Pending
+        }
+    }
+}
+
+impl<'a, F> Drop for Acquire<'a, F> {
+    fn drop(&mut self) {
+        if let Some(ticket) = self.ticket {
+            let mut state = self.device.bus.state.borrow_mut();
+            state.waiters.retain(|waiter| waiter.ticket != ticket);
+            if !state.locked {
+                state.wake_all();
+            }
+        }
+    }
+}
+
+/// A device's turn on the bus, before it has the port
+struct Claim<'a, F>(&'a SpiBus<F>);
+
+impl<'a, F> Drop for Claim<'a, F> {
+    fn drop(&mut self) {
+        let mut state = self.0.state.borrow_mut();
+        state.locked = false;
+        state.wake_all();
+    }
+}
+
+/// Exclusive use of a shared SPI bus
+///
+/// This dereferences to the SPI port. The bus is released when the
+/// lock is dropped.
+pub struct SpiLock<'a, F: Future> {
+    // The port is released before the bus
+    spi: F::Output,
+    _claim: Claim<'a, F>,
+}
+
+impl<'a, F> Deref for SpiLock<'a, F>
+where
+    F: Future,
+    F::Output: Deref,
+{
+    type Target = <F::Output as Deref>::Target;
+
+    fn deref(&self) -> &Self::Target {
+        &self.spi
+    }
+}
+
+impl<'a, F> DerefMut for SpiLock<'a, F>
+where
+    F: Future,
+    F::Output: DerefMut,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.spi
+    }
+}
@@ -0,0 +1,492 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Frame codecs for [`Framed`](super::Framed)
+//!
+//! A codec converts between frames and the bytes which represent them
+//! on the wire.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt::Debug;
+
+/// A method of splitting a byte stream into frames
+pub trait Codec {
+    /// The type of a decoded frame
+    type Item;
+
+    /// The error type
+    type Error: Debug;
+
+    /// Decode the next frame from the received bytes
+    ///
+    /// The bytes making up a decoded frame should be removed from
+    /// `buf`. Returns `None` if `buf` does not yet contain a complete
+    /// frame.
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Result<Option<Self::Item>, Self::Error>;
+
+    /// Encode a frame, appending its bytes to `buf`
+    fn encode(&mut self, item: &Self::Item, buf: &mut Vec<u8>) -> Result<(), Self::Error>;
+}
+
+/// An error from the [`Lines`] codec
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum LinesError {
+    /// A line was longer than the codec's maximum length
+    ///
+    /// The partial line is discarded.
+    TooLong,
+
+    /// A line was not valid UTF-8
+    Utf8(core::str::Utf8Error),
+}
+
+/// A codec for newline-terminated text
+///
+/// Decoded lines do not include the trailing newline, or a carriage
+/// return before it. Encoded lines have a newline appended.
+pub struct Lines {
+    max_length: usize,
+}
+
+impl Lines {
+    /// Create a line codec
+    ///
+    /// Lines longer than `max_length` bytes are discarded with an
+    /// error, so that a missing newline cannot exhaust memory.
+    pub const fn new(max_length: usize) -> Self {
+        Self { max_length }
+    }
+}
+
+impl Codec for Lines {
+    type Item = String;
+    type Error = LinesError;
+
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Result<Option<String>, LinesError> {
+        let end = match buf.iter().position(|&byte| byte == b'\n') {
+            Some(end) => end,
+            None if buf.len() > self.max_length => {
+                buf.clear();
+                return Err(LinesError::TooLong);
+            }
+            None => return Ok(None),
+        };
+
+        let mut line: Vec<u8> = buf.drain(..=end).collect();
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        if line.len() > self.max_length {
+            return Err(LinesError::TooLong);
+        }
+        String::from_utf8(line)
+            .map(Some)
+            .map_err(|err| LinesError::Utf8(err.utf8_error()))
+    }
+
+    fn encode(&mut self, item: &String, buf: &mut Vec<u8>) -> Result<(), LinesError> {
+        if item.len() > self.max_length {
+            return Err(LinesError::TooLong);
+        }
+        buf.extend_from_slice(item.as_bytes());
+        buf.push(b'\n');
+        Ok(())
+    }
+}
+
+/// An error from the [`LengthPrefixed`] codec
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum LengthPrefixedError {
+    /// A frame was longer than the codec's maximum length
+    ///
+    /// When decoding, the frame's length prefix is discarded, and
+    /// decoding continues from the next byte.
+    TooLong,
+}
+
+/// A codec for binary frames with a length prefix
+///
+/// Each frame is preceded by its length, as a big-endian `u16`.
+pub struct LengthPrefixed {
+    max_length: usize,
+}
+
+impl LengthPrefixed {
+    /// Create a length-prefixed codec
+    ///
+    /// Frames longer than `max_length` bytes are rejected. Lengths
+    /// above `u16::MAX` are treated as `u16::MAX`.
+    pub fn new(max_length: usize) -> Self {
+        Self {
+            max_length: max_length.min(u16::MAX as usize),
+        }
+    }
+}
+
+impl Codec for LengthPrefixed {
+    type Item = Vec<u8>;
+    type Error = LengthPrefixedError;
+
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Result<Option<Vec<u8>>, LengthPrefixedError> {
+        if buf.len() < 2 {
+            return Ok(None);
+        }
+        let len = u16::from_be_bytes([buf[0], buf[1]]) as usize;
+        if len > self.max_length {
+            buf.remove(0);
+            return Err(LengthPrefixedError::TooLong);
+        }
+        if buf.len() < len + 2 {
+            return Ok(None);
+        }
+        let frame = buf[2..len + 2].to_vec();
+        buf.drain(..len + 2);
+        Ok(Some(frame))
+    }
+
+    fn encode(&mut self, item: &Vec<u8>, buf: &mut Vec<u8>) -> Result<(), LengthPrefixedError> {
+        if item.len() > self.max_length {
+            return Err(LengthPrefixedError::TooLong);
+        }
+        buf.extend_from_slice(&(item.len() as u16).to_be_bytes());
+        buf.extend_from_slice(item);
+        Ok(())
+    }
+}
+
+/// An error from the [`Cobs`] codec
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CobsError {
+    /// A frame was longer than the codec's maximum length
+    ///
+    /// When decoding, the partial frame is discarded.
+    TooLong,
+
+    /// A received frame was not validly encoded
+    Invalid,
+}
+
+/// A codec for Consistent Overhead Byte Stuffing
+///
+/// COBS encodes binary frames without any zero bytes, so that a zero
+/// can mark the end of each frame. The overhead is at most one byte
+/// for every 254 bytes of data, plus the delimiter.
+pub struct Cobs {
+    max_length: usize,
+}
+
+impl Cobs {
+    /// Create a COBS codec
+    ///
+    /// Frames longer than `max_length` bytes, before encoding, are
+    /// rejected.
+    pub const fn new(max_length: usize) -> Self {
+        Self { max_length }
+    }
+}
+
+impl Codec for Cobs {
+    type Item = Vec<u8>;
+    type Error = CobsError;
+
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Result<Option<Vec<u8>>, CobsError> {
+        let end = match buf.iter().position(|&byte| byte == 0) {
+            Some(end) => end,
+            None if buf.len() > self.max_length + self.max_length / 254 + 1 => {
+                buf.clear();
+                return Err(CobsError::TooLong);
+            }
+            None => return Ok(None),
+        };
+
+        let encoded: Vec<u8> = buf.drain(..=end).collect();
+        let encoded = &encoded[..end];
+        let mut frame = Vec::with_capacity(encoded.len());
+        let mut idx = 0;
+        while idx < encoded.len() {
+            let code = encoded[idx] as usize;
+            if idx + code > encoded.len() {
+                return Err(CobsError::Invalid);
+            }
+            frame.extend_from_slice(&encoded[idx + 1..idx + code]);
+            idx += code;
+            if code < 0xFF && idx < encoded.len() {
+                frame.push(0);
+            }
+        }
+        if frame.len() > self.max_length {
+            return Err(CobsError::TooLong);
+        }
+        Ok(Some(frame))
+    }
+
+    fn encode(&mut self, item: &Vec<u8>, buf: &mut Vec<u8>) -> Result<(), CobsError> {
+        if item.len() > self.max_length {
+            return Err(CobsError::TooLong);
+        }
+        let mut code_idx = buf.len();
+        buf.push(0);
+        let mut code = 1;
+        for &byte in item {
+            if byte != 0 {
+                buf.push(byte);
+                code += 1;
+            }
+            if byte == 0 || code == 0xFF {
+                buf[code_idx] = code;
+                code_idx = buf.len();
+                buf.push(0);
+                code = 1;
+            }
+        }
+        buf[code_idx] = code;
+        buf.push(0);
+        Ok(())
+    }
+}
+
+/// An error from the [`Slip`] codec
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SlipError {
+    /// A frame was longer than the codec's maximum length
+    ///
+    /// When decoding, the partial frame is discarded.
+    TooLong,
+
+    /// A received frame contained an invalid escape sequence
+    Invalid,
+}
+
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+/// A codec for the Serial Line Internet Protocol framing
+///
+/// SLIP marks the end of each frame with `0xC0`, escaping any
+/// delimiter bytes within the frame. Each encoded frame also starts
+/// with a delimiter, which flushes any line noise received before
+/// it. Empty frames are ignored when decoding.
+pub struct Slip {
+    max_length: usize,
+}
+
+impl Slip {
+    /// Create a SLIP codec
+    ///
+    /// Frames longer than `max_length` bytes, before encoding, are
+    /// rejected.
+    pub const fn new(max_length: usize) -> Self {
+        Self { max_length }
+    }
+}
+
+impl Codec for Slip {
+    type Item = Vec<u8>;
+    type Error = SlipError;
+
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Result<Option<Vec<u8>>, SlipError> {
+        loop {
+            let end = match buf.iter().position(|&byte| byte == SLIP_END) {
+                Some(end) => end,
+                None if buf.len() > self.max_length * 2 => {
+                    buf.clear();
+                    return Err(SlipError::TooLong);
+                }
+                None => return Ok(None),
+            };
+
+            let encoded: Vec<u8> = buf.drain(..=end).collect();
+            if end == 0 {
+                continue;
+            }
+
+            let mut frame = Vec::with_capacity(end);
+            let mut bytes = encoded[..end].iter();
+            while let Some(&byte) = bytes.next() {
+                if byte == SLIP_ESC {
+                    match bytes.next() {
+                        Some(&SLIP_ESC_END) => frame.push(SLIP_END),
+                        Some(&SLIP_ESC_ESC) => frame.push(SLIP_ESC),
+                        _ => return Err(SlipError::Invalid),
+                    }
+                } else {
+                    frame.push(byte);
+                }
+            }
+            if frame.len() > self.max_length {
+                return Err(SlipError::TooLong);
+            }
+            return Ok(Some(frame));
+        }
+    }
+
+    fn encode(&mut self, item: &Vec<u8>, buf: &mut Vec<u8>) -> Result<(), SlipError> {
+        if item.len() > self.max_length {
+            return Err(SlipError::TooLong);
+        }
+        buf.push(SLIP_END);
+        for &byte in item {
+            match byte {
+                SLIP_END => buf.extend_from_slice(&[SLIP_ESC, SLIP_ESC_END]),
+                SLIP_ESC => buf.extend_from_slice(&[SLIP_ESC, SLIP_ESC_ESC]),
+                byte => buf.push(byte),
+            }
+        }
+        buf.push(SLIP_END);
+        Ok(())
+    }
+}
+
+/// A CRC algorithm used by the [`Checked`] codec
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub enum CrcKind {
+    /// CRC-16/CCITT-FALSE, with polynomial `0x1021` and initial value `0xFFFF`
+    Crc16,
+
+    /// The CRC-32 used by Ethernet and zlib, with polynomial `0x04C11DB7`
+    Crc32,
+}
+
+impl CrcKind {
+    fn len(self) -> usize {
+        match self {
+            CrcKind::Crc16 => 2,
+            CrcKind::Crc32 => 4,
+        }
+    }
+}
+
+/// Compute a CRC
+///
+/// On boards with a CRC module, the hardware is used if it is
+/// available.
+pub fn crc(kind: CrcKind, data: &[u8]) -> u32 {
+    #[cfg(any(
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36"
+    ))]
+    {
+        if let Some(crc) = super::hardware_crc(kind, data) {
+            return crc;
+        }
+    }
+
+    match kind {
+        CrcKind::Crc16 => {
+            let mut crc: u16 = 0xFFFF;
+            for &byte in data {
+                crc ^= (byte as u16) << 8;
+                for _ in 0..8 {
+                    crc = if crc & 0x8000 != 0 {
+                        (crc << 1) ^ 0x1021
+                    } else {
+                        crc << 1
+                    };
+                }
+            }
+            crc as u32
+        }
+        CrcKind::Crc32 => {
+            let mut crc: u32 = 0xFFFF_FFFF;
+            for &byte in data {
+                crc ^= byte as u32;
+                for _ in 0..8 {
+                    crc = if crc & 1 != 0 {
+                        (crc >> 1) ^ 0xEDB8_8320
+                    } else {
+                        crc >> 1
+                    };
+                }
+            }
+            !crc
+        }
+    }
+}
+
+/// An error from the [`Checked`] codec
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CheckedError<E> {
+    /// Error from the inner codec
+    Codec(E),
+
+    /// A received frame was too short to contain a CRC
+    TooShort,
+
+    /// A received frame's CRC did not match its contents
+    Mismatch,
+}
+
+/// A codec which appends a CRC to each frame of another codec
+///
+/// The CRC is appended to the frame in little-endian order before
+/// it is encoded by the inner codec, and is checked and removed from
+/// each decoded frame. Frames with a bad CRC are reported as errors,
+/// and decoding can continue with the next frame.
+///
+/// Combined with [`Cobs`] or [`Slip`], this is suitable for reliable
+/// links between a board and a host over a UART or USB serial port.
+pub struct Checked<C> {
+    inner: C,
+    kind: CrcKind,
+    scratch: Vec<u8>,
+}
+
+impl<C> Checked<C> {
+    /// Add a CRC to the frames of `inner`
+    pub fn new(inner: C, kind: CrcKind) -> Self {
+        Self {
+            inner,
+            kind,
+            scratch: Vec::new(),
+        }
+    }
+}
+
+impl<C> Codec for Checked<C>
+where
+    C: Codec<Item = Vec<u8>>,
+{
+    type Item = Vec<u8>;
+    type Error = CheckedError<C::Error>;
+
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Result<Option<Vec<u8>>, Self::Error> {
+        let mut frame = match self.inner.decode(buf).map_err(CheckedError::Codec)? {
+            Some(frame) => frame,
+            None => return Ok(None),
+        };
+
+        let len = self.kind.len();
+        if frame.len() < len {
+            return Err(CheckedError::TooShort);
+        }
+        let mut expected = [0; 4];
+        expected[..len].copy_from_slice(&frame[frame.len() - len..]);
+        frame.truncate(frame.len() - len);
+        if crc(self.kind, &frame) != u32::from_le_bytes(expected) {
+            return Err(CheckedError::Mismatch);
+        }
+        Ok(Some(frame))
+    }
+
+    fn encode(&mut self, item: &Vec<u8>, buf: &mut Vec<u8>) -> Result<(), Self::Error> {
+        let len = self.kind.len();
+        self.scratch.clear();
+        self.scratch.extend_from_slice(item);
+        self.scratch
+            .extend_from_slice(&crc(self.kind, item).to_le_bytes()[..len]);
+        self.inner
+            .encode(&self.scratch, buf)
+            .map_err(CheckedError::Codec)
+    }
+}
@@ -0,0 +1,110 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Bus diagnostics
+//!
+//! These check whether devices are wired correctly, during hardware
+//! bring-up. The [`shell`](crate::shell) can run them as commands.
+
+use super::{Error, ErrorKind, I2c, Spi, SpiTransfer};
+use alloc::vec::Vec;
+use core::future::Future;
+
+/// The first I2C address which is not reserved
+const FIRST_ADDRESS: u8 = 0x08;
+
+/// The last I2C address which is not reserved
+const LAST_ADDRESS: u8 = 0x77;
+
+/// Bytes which exercise each data bit at both levels
+const LOOPBACK_PATTERN: [u8; 12] = [
+    0x00, 0xFF, 0x55, 0xAA, 0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80,
+];
+
+/// A byte read back by [`check_spi_loopback`] which was not the one written
+#[derive(Debug)]
+pub struct LoopbackMismatch {
+    /// The byte written
+    pub written: u8,
+
+    /// The byte read back
+    pub read: u8,
+}
+
+/// Find the devices on an I2C bus
+///
+/// Each address from 0x08 to 0x77 is addressed with an empty write,
+/// and the addresses which are acknowledged are returned. The other
+/// addresses are reserved, and are not scanned. Any error other than
+/// a missing acknowledgement, such as a bus which is held low, stops
+/// the scan.
+///
+/// The bus must already be enabled.
+///
+/// ```
+/// use cntrlr::io::{diag::scan_i2c, I2c, SoftI2c};
+///
+/// async fn list_devices() {
+///     let mut i2c = SoftI2c::new(18, 19).unwrap();
+///     i2c.enable(100_000).unwrap();
+///     for address in scan_i2c(&mut i2c).await.unwrap() {
+///         // Report the address
+///     }
+/// }
+/// ```
+pub fn scan_i2c<I: I2c + ?Sized>(i2c: &mut I) -> impl Future<Output = Result<Vec<u8>, Error>> + '_ {
+    async move {
+        let mut found = Vec::new();
+        for address in FIRST_ADDRESS..=LAST_ADDRESS {
+            match i2c.write(address, &[]).await.map_err(Into::into) {
+                Ok(()) => found.push(address),
+                Err(err) if err.kind() == ErrorKind::NotAcknowledged => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// Check that an SPI port reads back what it writes
+///
+/// The port's data output must be wired to its data input, so that
+/// each byte written is read back. A pattern which sets each bit both
+/// high and low is written at `baud`, with `cs` as the chip select.
+/// An error of kind [`ErrorKind::InvalidData`] is returned for the
+/// first byte which does not match, with a [`LoopbackMismatch`] as
+/// its source.
+///
+/// The port must already be enabled.
+pub fn check_spi_loopback<S: Spi + ?Sized>(
+    spi: &mut S,
+    baud: usize,
+    cs: usize,
+) -> impl Future<Output = Result<(), Error>> + '_ {
+    async move {
+        let mut transfer = spi.transfer(baud, cs, 8).await.map_err(Into::into)?;
+        let mut read = [0; LOOPBACK_PATTERN.len()];
+        let mut done = 0;
+        while done < LOOPBACK_PATTERN.len() {
+            done +=
+                SpiTransfer::transfer(&mut transfer, &LOOPBACK_PATTERN[done..], &mut read[done..])
+                    .await
+                    .map_err(Into::into)?;
+        }
+        SpiTransfer::flush(&mut transfer)
+            .await
+            .map_err(Into::into)?;
+
+        match LOOPBACK_PATTERN
+            .iter()
+            .zip(read.iter())
+            .find(|(written, read)| written != read)
+        {
+            Some((&written, &read)) => Err(Error::new(
+                ErrorKind::InvalidData,
+                LoopbackMismatch { written, read },
+            )),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Frame-oriented I/O
+
+use super::{codec::Codec, Error, ErrorKind, Read, Write, WriteExt};
+use alloc::vec::Vec;
+use core::{fmt::Debug, future::Future, ops::DerefMut};
+
+/// Error type for [`Framed`]
+#[derive(Debug)]
+pub enum FramedError<E, C> {
+    /// Error from the underlying implementation
+    Io(E),
+
+    /// Error from the codec
+    Codec(C),
+}
+
+impl<E, C> From<FramedError<E, C>> for Error
+where
+    E: Into<Error>,
+    C: Debug + Send + Sync + 'static,
+{
+    fn from(err: FramedError<E, C>) -> Self {
+        match err {
+            FramedError::Io(err) => err.into(),
+            FramedError::Codec(err) => Self::new(ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+/// A frame-oriented wrapper around a byte stream
+///
+/// The stream is held through a [`DerefMut`], such as the guard
+/// returned by [`serial_1().await`](super::serial_1) or a mutable
+/// reference.
+///
+/// The [codec](super::codec) determines how frames are split out of
+/// the incoming bytes, and how outgoing frames are encoded. Received
+/// bytes which are not yet part of a complete frame are buffered
+/// until the rest of the frame arrives.
+///
+/// ```
+/// use cntrlr::{io::{codec::Lines, Framed}, prelude::*};
+///
+/// #[entry]
+/// async fn main() -> ! {
+///     serial_1().await.enable(115200).unwrap();
+///     let mut framed = Framed::new(serial_1().await, Lines::new(80));
+///     loop {
+///         if let Ok(line) = framed.read_frame().await {
+///             framed.write_frame(&line).await.ok();
+///         }
+///     }
+/// }
+/// ```
+pub struct Framed<T, C> {
+    inner: T,
+    codec: C,
+    read_buf: Vec<u8>,
+    write_buf: Vec<u8>,
+}
+
+impl<T, C> Framed<T, C> {
+    /// Wrap a byte stream with a codec
+    pub fn new(inner: T, codec: C) -> Self {
+        Self {
+            inner,
+            codec,
+            read_buf: Vec::new(),
+            write_buf: Vec::new(),
+        }
+    }
+
+    /// The underlying byte stream
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// The underlying byte stream
+    ///
+    /// Reading from the stream directly may corrupt any partially
+    /// received frame.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// The codec used to encode and decode frames
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+
+    /// Unwrap the byte stream
+    ///
+    /// Any buffered partial frame is discarded.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T, C> Framed<T, C>
+where
+    T: DerefMut,
+    T::Target: Read,
+    C: Codec,
+{
+    /// Read the next frame
+    pub fn read_frame(
+        &mut self,
+    ) -> impl Future<Output = Result<C::Item, FramedError<<T::Target as Read>::Error, C::Error>>> + '_
+    {
+        async move {
+            loop {
+                if let Some(item) = self
+                    .codec
+                    .decode(&mut self.read_buf)
+                    .map_err(FramedError::Codec)?
+                {
+                    return Ok(item);
+                }
+
+                let mut chunk = [0; 64];
+                let count = self.inner.read(&mut chunk).await.map_err(FramedError::Io)?;
+                self.read_buf.extend_from_slice(&chunk[..count]);
+            }
+        }
+    }
+}
+
+impl<T, C> Framed<T, C>
+where
+    T: DerefMut,
+    T::Target: Write,
+    C: Codec,
+{
+    /// Encode and write a frame
+    pub fn write_frame<'a>(
+        &'a mut self,
+        item: &'a C::Item,
+    ) -> impl Future<Output = Result<(), FramedError<<T::Target as Write>::Error, C::Error>>> + 'a
+    {
+        async move {
+            self.write_buf.clear();
+            self.codec
+                .encode(item, &mut self.write_buf)
+                .map_err(FramedError::Codec)?;
+            self.inner
+                .write_all(&self.write_buf)
+                .await
+                .map_err(FramedError::Io)
+        }
+    }
+}
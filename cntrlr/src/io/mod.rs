@@ -0,0 +1,1206 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! I/O functionality for Cntrlr boards
+
+mod block;
+mod bus;
+pub mod codec;
+pub mod diag;
+mod framed;
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_lc"
+    )))
+)]
+pub mod soft;
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+mod soft_i2c;
+
+#[cfg(any(
+    doc,
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_lc"
+    )))
+)]
+pub use crate::hw::board::teensy_common::usb::{UsbSerial, UsbSerialError};
+pub use block::{BlockCache, BlockDevice, CacheError, CacheStats};
+pub use bus::{Arbitration, SpiBus, SpiDevice, SpiLock};
+pub use framed::{Framed, FramedError};
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    )))
+)]
+pub use soft_i2c::{SoftI2c, SoftI2cError};
+
+use alloc::{boxed::Box, string::String};
+use cntrlr_macros::board_fn;
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+use core::ops::DerefMut;
+use core::{fmt::Debug, future::Future, ops::Deref};
+
+/// Error type for [`ReadExt::read_line`]
+#[derive(Debug)]
+pub enum LineError<E> {
+    /// Error from the underlying implementation
+    Read(E),
+
+    /// Error decoding the read bytes as UTF-8
+    Utf8(core::str::Utf8Error),
+}
+
+/// The general category of an [`Error`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The device cannot be used because it is not enabled
+    NotEnabled,
+
+    /// The device cannot be enabled because a resource it needs is in use
+    InUse,
+
+    /// The device cannot be enabled because a setting or option is unsupported
+    InvalidSetting,
+
+    /// The operation cannot be performed with the provided data
+    InvalidData,
+
+    /// The device did not acknowledge its address or data
+    NotAcknowledged,
+
+    /// Any other error
+    Other,
+}
+
+/// A board-independent I/O error
+///
+/// Every board's serial and SPI errors can be converted into this
+/// type, so that applications can handle errors without knowing
+/// which board they are built for. The original board-specific
+/// error is kept, and is available from [`Error::source()`].
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    source: Option<Box<dyn Debug + Send + Sync>>,
+}
+
+impl Error {
+    /// Create an error from a kind and a more specific source error
+    pub fn new<E>(kind: ErrorKind, source: E) -> Self
+    where
+        E: Debug + Send + Sync + 'static,
+    {
+        Self {
+            kind,
+            source: Some(Box::new(source)),
+        }
+    }
+
+    /// The general category of this error
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The board-specific error this was created from, if any
+    pub fn source(&self) -> Option<&(dyn Debug + Send + Sync)> {
+        self.source.as_deref()
+    }
+}
+
+impl<E> From<LineError<E>> for Error
+where
+    E: Into<Error>,
+{
+    fn from(err: LineError<E>) -> Self {
+        match err {
+            LineError::Read(err) => err.into(),
+            LineError::Utf8(err) => Self::new(ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Self { kind, source: None }
+    }
+}
+
+/// Serial configuration options
+#[non_exhaustive]
+pub enum SerialOption {
+    /// Invert the serial polarity
+    Invert(bool),
+
+    /// Connect the transmitter to the receiver inside the UART
+    ///
+    /// Every byte written is read back, without any external
+    /// wiring. This is useful for testing a board's serial driver
+    /// and interrupt handling. The Red-V UARTs have no loopback mode,
+    /// and reject this option.
+    Loopback(bool),
+
+    /// Receive only the messages sent to an address
+    ///
+    /// The port uses 9-bit characters, with the ninth bit marking an
+    /// address, as on RS-485 multi-drop networks. The receiver
+    /// sleeps until this address is received, discarding messages to
+    /// other nodes without waking the MCU. The address itself is the
+    /// first byte read. Ports which cannot match addresses in
+    /// hardware reject this option.
+    AddressMark(u8),
+
+    /// Sample each bit this many times
+    ///
+    /// Lower ratios allow higher baud rates from the same source
+    /// clock, and choosing the ratio can bring the baud rate closer
+    /// to the one requested. Ratios from 4 to 32 are valid, and ratios
+    /// below 8 sample on both clock edges. Only UART0 on the Teensy
+    /// LC can set its oversampling ratio, and every other port
+    /// rejects this option. That UART samples 16 times per bit if
+    /// this option is not given.
+    Oversample(u8),
+
+    /// Write buffers of at least this many bytes through DMA
+    ///
+    /// Shorter writes, and every write when this option is not
+    /// given, are sent a FIFO's worth at a time from the transmit
+    /// interrupt. A DMA write moves the buffer, up to 32KiB on the
+    /// Teensy 3.x, without waking the task, and completes from the
+    /// DMA interrupt. Each port uses
+    /// the DMA channel with the same index as its UART, so serial 1
+    /// uses channel 0. Only the Teensy 3.x and LC ports can write
+    /// through DMA, and every other port rejects this option.
+    DmaThreshold(usize),
+
+    /// Send and check a parity bit with each character
+    ///
+    /// Characters which fail the check are still read. The Kinetis
+    /// UARTs send the parity bit as a ninth bit, so it cannot be
+    /// combined with 9 data bits or [`Self::AddressMark`]. The Red-V
+    /// and Teensy 4 UARTs only accept [`Parity::None`].
+    Parity(Parity),
+
+    /// Send this many stop bits, either 1 or 2
+    ///
+    /// The UARTs on the Teensy 3.0, 3.1, and 3.2, and the Teensy 4
+    /// UARTs, only accept 1 stop bit. The Red-V UARTs send 2 stop
+    /// bits, but only check for 1.
+    StopBits(u8),
+
+    /// Use characters with this many data bits, either 8 or 9
+    ///
+    /// Reads and writes still transfer bytes, so the ninth bit is
+    /// sent as a one and ignored on receive. It then looks like an
+    /// extra stop bit, which lets UARTs with only 1 stop bit talk to
+    /// devices such as DMX receivers that expect 2. Only the Teensy
+    /// 3.x and LC UARTs accept 9 data bits, and they cannot combine
+    /// it with [`Self::AddressMark`].
+    DataBits(u8),
+}
+
+/// The parity bit sent with each serial character
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parity {
+    /// No parity bit
+    None,
+
+    /// An even number of one bits, including the parity bit
+    Even,
+
+    /// An odd number of one bits, including the parity bit
+    Odd,
+}
+
+/// Statistics collected by a serial port
+///
+/// Statistics are always collected, and are cheap enough to leave
+/// running in production firmware. They can be read with
+/// [`Serial::stats()`] and cleared with [`Serial::reset_stats()`].
+#[derive(Clone, Copy, Debug, Default)]
+#[non_exhaustive]
+pub struct SerialStats {
+    /// The number of times the hardware reported a receive overrun
+    ///
+    /// Each overrun means at least one byte was lost because it
+    /// arrived before the application read the previous data. Not
+    /// all hardware can detect overruns - on those ports, this
+    /// counter is always zero.
+    ///
+    /// The hardware is only checked for an overrun when the port is
+    /// read, so an overrun is counted by the next read after it, and
+    /// several overruns between two reads are counted once.
+    pub overruns: usize,
+
+    /// The largest number of bytes received in a single read
+    ///
+    /// A value close to the hardware FIFO depth indicates that the
+    /// application is close to overrunning the port.
+    pub max_burst: usize,
+}
+
+impl SerialStats {
+    /// Create a new set of statistics, with all counters at zero
+    pub const fn new() -> Self {
+        Self {
+            overruns: 0,
+            max_burst: 0,
+        }
+    }
+}
+
+/// A set of pins which a serial port can be routed to
+///
+/// The pin sets for a port can be listed with
+/// [`Serial::alt_pin_sets()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SerialPins {
+    /// The board pin used for transmit
+    pub tx: usize,
+
+    /// The board pin used for receive
+    pub rx: usize,
+}
+
+/// SPI configuration options
+#[non_exhaustive]
+pub enum SpiOption {
+    /// Use a hardware chip select for the given pin
+    ///
+    /// Only some pins can act as a hardware chip select for each SPI
+    /// port. The valid pins can be listed with
+    /// [`Spi::hardware_cs_pins()`].
+    HardwareCs(usize),
+}
+
+/// Allows reading bytes from a source
+pub trait Read {
+    /// The error type
+    type Error: Debug;
+
+    /// The future for [`Self::read()`]
+    type Future<'a>: Future<Output = Result<usize, Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// Read bytes from the device
+    ///
+    /// This reads as many bytes as are currently available, up to
+    /// `buf.len()`, and returns the number of bytes written.
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Future<'a>
+    where
+        Self: 'a;
+}
+
+/// A buffer for [`Write::write_vectored()`]
+///
+/// This is a thin wrapper around a byte slice, matching the standard
+/// library's `IoSlice`.
+#[derive(Clone, Copy, Debug)]
+pub struct IoSlice<'a>(&'a [u8]);
+
+impl<'a> IoSlice<'a> {
+    /// Wrap a byte slice
+    pub const fn new(buf: &'a [u8]) -> Self {
+        Self(buf)
+    }
+}
+
+impl<'a> Deref for IoSlice<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.0
+    }
+}
+
+/// Allows writing bytes to  a sink
+pub trait Write {
+    /// The error type
+    type Error: Debug;
+
+    /// The future for [`Self::write()`]
+    type Future<'a>: Future<Output = Result<usize, Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// The future for [`Self::flush()`]
+    type FlushFuture<'a>: Future<Output = Result<(), Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// The future for [`Self::write_vectored()`]
+    type VectoredFuture<'a>: Future<Output = Result<usize, Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// Write bytes to the device
+    ///
+    /// This writes as many bytes as possible, up to `buf.len()`, and
+    /// returns the number of bytes written.
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Future<'a>
+    where
+        Self: 'a;
+
+    /// Write bytes from several buffers to the device
+    ///
+    /// This writes as many bytes as possible from each buffer in
+    /// turn, and returns the total number of bytes written. Serial
+    /// ports fill their transmit FIFO from every buffer before
+    /// waiting, so a header and payload in separate buffers are sent
+    /// without copying them together. Devices without such support
+    /// write from only the first non-empty buffer.
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'a>]) -> Self::VectoredFuture<'a>
+    where
+        Self: 'a;
+
+    /// Ensure all written bytes have been transmitted
+    ///
+    /// On devices which do not support this operation, this function
+    /// does nothing.
+    fn flush<'a>(&'a mut self) -> Self::FlushFuture<'a>
+    where
+        Self: 'a;
+}
+
+/// Extended functions for reading bytes
+pub trait ReadExt: Read {
+    /// The future for [`Self::read_exact()`]
+    type ExactFuture<'a>: Future<Output = Result<(), Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// The future for [`Self::read_line()`]
+    type LineFuture<'a>: Future<Output = Result<(), LineError<Self::Error>>> + 'a
+    where
+        Self: 'a;
+
+    /// Read bytes from the device
+    ///
+    /// This reads exactly `buf.len()` bytes from the device.
+    fn read_exact<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::ExactFuture<'a>
+    where
+        Self: 'a;
+
+    /// Read a line from the device
+    ///
+    /// This reads bytes from the device into the passed
+    /// [`String`] until it reaches a newline
+    /// (0x0A). The newline will also be included in the output
+    /// string.
+    fn read_line<'a>(&'a mut self, buf: &'a mut String) -> Self::LineFuture<'a>
+    where
+        Self: 'a;
+}
+
+impl<T: Read + ?Sized> ReadExt for T {
+    #[rustfmt::skip]
+    type ExactFuture<'a> where Self: 'a = impl Future<Output = Result<(), Self::Error>> + 'a;
+    #[rustfmt::skip]
+    type LineFuture<'a> where Self: 'a = impl Future<Output = Result<(), LineError<Self::Error>>> + 'a;
+
+    fn read_exact<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::ExactFuture<'a>
+    where
+        Self: 'a,
+    {
+        async move {
+            let mut buf = buf;
+            while !buf.is_empty() {
+                let read = self.read(buf).await?;
+                buf = &mut buf[read..];
+            }
+            Ok(())
+        }
+    }
+
+    fn read_line<'a>(&'a mut self, buf: &'a mut String) -> Self::LineFuture<'a>
+    where
+        Self: 'a,
+    {
+        async move {
+            let buf = unsafe { buf.as_mut_vec() };
+            loop {
+                let mut byte = [0];
+                self.read(&mut byte).await.map_err(LineError::Read)?;
+                buf.push(byte[0]);
+                if byte[0] == b'\n' {
+                    break;
+                }
+            }
+            let _ = core::str::from_utf8(&buf).map_err(LineError::Utf8)?;
+            Ok(())
+        }
+    }
+}
+
+/// Extended functions for writing bytes
+pub trait WriteExt: Write {
+    /// The future for [`Self::write_all()`]
+    type AllFuture<'a>: Future<Output = Result<(), Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// The future for [`Self::write_fmt()`]
+    type FmtFuture<'a>: Future<Output = Result<(), Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// The future for [`Self::write_all_vectored()`]
+    type AllVectoredFuture<'a>: Future<Output = Result<(), Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// Write bytes to the device
+    ///
+    /// This writes exactly `buf.len()` bytes to the device.
+    fn write_all<'a>(&'a mut self, buf: &'a [u8]) -> Self::AllFuture<'a>
+    where
+        Self: 'a;
+
+    /// Write every byte from several buffers to the device
+    fn write_all_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'a>]) -> Self::AllVectoredFuture<'a>
+    where
+        Self: 'a;
+
+    /// Write a formatted message to the device
+    ///
+    /// Internally, this will allocate a [`String`] to hold the
+    /// formatted output.
+    fn write_fmt<'a>(&'a mut self, fmt: core::fmt::Arguments<'a>) -> Self::FmtFuture<'a>
+    where
+        Self: 'a;
+}
+
+impl<T: Write + ?Sized> WriteExt for T {
+    #[rustfmt::skip]
+    type AllFuture<'a> where Self: 'a = impl Future<Output = Result<(), Self::Error>> + 'a;
+
+    #[rustfmt::skip]
+    type FmtFuture<'a> where Self: 'a = impl Future<Output = Result<(), Self::Error>> + 'a;
+
+    #[rustfmt::skip]
+    type AllVectoredFuture<'a> where Self: 'a = impl Future<Output = Result<(), Self::Error>> + 'a;
+
+    fn write_all<'a>(&'a mut self, buf: &'a [u8]) -> Self::AllFuture<'a>
+    where
+        Self: 'a,
+    {
+        async move {
+            let mut buf = buf;
+            while !buf.is_empty() {
+                let written = self.write(buf).await?;
+                buf = &buf[written..];
+            }
+            Ok(())
+        }
+    }
+
+    fn write_all_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'a>]) -> Self::AllVectoredFuture<'a>
+    where
+        Self: 'a,
+    {
+        async move {
+            let mut bufs = bufs;
+            let mut offset = 0;
+            loop {
+                // Skip any buffers which have already been written
+                while let Some(buf) = bufs.first() {
+                    if offset < buf.len() {
+                        break;
+                    }
+                    offset -= buf.len();
+                    bufs = &bufs[1..];
+                }
+                if bufs.is_empty() {
+                    return Ok(());
+                }
+
+                let written = if offset == 0 {
+                    self.write_vectored(bufs).await?
+                } else {
+                    self.write(&bufs[0][offset..]).await?
+                };
+                offset += written;
+            }
+        }
+    }
+
+    fn write_fmt<'a>(&'a mut self, fmt: core::fmt::Arguments<'a>) -> Self::FmtFuture<'a>
+    where
+        Self: 'a,
+    {
+        async move {
+            use alloc::format;
+
+            let formatted = format!("{}", fmt);
+            self.write_all(formatted.as_bytes()).await
+        }
+    }
+}
+
+/// Trait for RS232/UART-style serial devices
+pub trait Serial: Read + Write {
+    /// The error type
+    ///
+    /// This can be converted into a board-independent [`Error`].
+    type Error: Debug + Into<Error>;
+
+    /// Enable the serial port at the specified baud rate
+    fn enable(&mut self, baud: usize) -> Result<(), <Self as Serial>::Error> {
+        self.enable_with_options(baud, &[])
+    }
+
+    /// Enable the serial port at the specified baud rate, with the selected options
+    fn enable_with_options(
+        &mut self,
+        baud: usize,
+        options: &[SerialOption],
+    ) -> Result<(), <Self as Serial>::Error>;
+
+    /// Disable the serial port.
+    fn disable(&mut self) -> Result<(), <Self as Serial>::Error>;
+
+    /// Get the statistics collected for this serial port
+    ///
+    /// Ports which collect no statistics report all counters as
+    /// zero.
+    fn stats(&self) -> SerialStats {
+        SerialStats::default()
+    }
+
+    /// Reset all statistics for this serial port to zero
+    fn reset_stats(&mut self) {}
+
+    /// The sets of pins this serial port can use
+    ///
+    /// The first set is the one used by default. No board currently
+    /// supports routing a port to its alternate pins, so this always
+    /// contains exactly one set for hardware ports. Software ports
+    /// are created on their pins, and have no sets.
+    fn alt_pin_sets(&self) -> &'static [SerialPins] {
+        &[]
+    }
+}
+
+/// Trait for SPI devices
+///
+/// SPI is a synchronous, bidirectional protocol. See
+/// [`SpiTransfer::transfer()`] for details of how this
+/// bidirectionality is handled in the API.
+pub trait Spi {
+    /// The error type
+    ///
+    /// This can be converted into a board-independent [`Error`].
+    type Error: Debug + Into<Error>;
+
+    /// The associated transfer type
+    type Transfer<'a>: SpiTransfer<Error = Self::Error>
+        + Read<Error = Self::Error>
+        + Write<Error = Self::Error>
+        + 'a
+    where
+        Self: 'a;
+
+    /// The Future for starting a transfr
+    type TransferFuture<'a>: Future<Output = Result<Self::Transfer<'a>, Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// The future for the `flush()` function
+    type FlushFuture<'a>: Future<Output = Result<(), Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// Enable the SPI port
+    fn enable(&mut self) -> Result<(), <Self as Spi>::Error> {
+        self.enable_with_options(&[])
+    }
+
+    /// Enable the SPI port with the selected options
+    fn enable_with_options(&mut self, options: &[SpiOption]) -> Result<(), <Self as Spi>::Error>;
+
+    /// Disable the spi port
+    fn disable(&mut self) -> Result<(), <Self as Spi>::Error>;
+
+    /// The pins which can be passed to [`SpiOption::HardwareCs`]
+    ///
+    /// Any other pin is rejected when the port is enabled.
+    fn hardware_cs_pins(&self) -> &'static [usize];
+
+    /// Begin a transfer on this SPI port
+    ///
+    /// A transfer is any series of SPI packets to the same device, at
+    /// a single baud rate. The actual baud rate may vary from the one
+    /// requested, and will be the closest possible rate below the
+    /// requested one.
+    fn transfer<'a>(
+        &'a mut self,
+        baud: usize,
+        cs: usize,
+        packet: usize,
+    ) -> Self::TransferFuture<'a>
+    where
+        Self: 'a;
+
+    /// Flush any outstanding writes to this SPI port
+    fn flush<'a>(&'a mut self) -> Self::FlushFuture<'a>
+    where
+        Self: 'a;
+}
+
+/// An SPI transfer to or from a device
+///
+/// All SPI transfers are bidirectional. See
+/// [`SpiTransfer::transfer()`] for details.
+pub trait SpiTransfer {
+    /// The error type
+    type Error: Debug;
+
+    /// The future for a transfer
+    type TransferFuture<'a>: Future<Output = Result<usize, Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// The future for the `flush()` function
+    type FlushFuture<'a>: Future<Output = Result<(), Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// Perform a bidirectional SPI transfer
+    ///
+    /// SPI transfers are always done in a multiple of the packet
+    /// size. If `buf_in` is below the packet size, the packet will be
+    /// padded with zeroes. If `buf_out` is below the packet size, any
+    /// additional read data will be discarded..
+    ///
+    /// At least one of `buf_in` or `buf_out` must be large enough for
+    /// an entire packet to be written or read, respectively.
+    fn transfer<'a>(
+        &'a mut self,
+        buf_in: &'a [u8],
+        buf_out: &'a mut [u8],
+    ) -> Self::TransferFuture<'a>
+    where
+        Self: 'a;
+
+    /// Flush any outstanding writes from this SPI transfer
+    fn flush<'a>(&'a mut self) -> Self::FlushFuture<'a>
+    where
+        Self: 'a;
+}
+
+impl<T> Read for T
+where
+    T: SpiTransfer,
+{
+    type Error = <Self as SpiTransfer>::Error;
+    type Future<'a>
+    where
+        Self: 'a,
+    = <Self as SpiTransfer>::TransferFuture<'a>;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Future<'a>
+    where
+        Self: 'a,
+    {
+        <Self as SpiTransfer>::transfer(self, &[], buf)
+    }
+}
+
+impl<T> Write for T
+where
+    T: SpiTransfer,
+{
+    type Error = <Self as SpiTransfer>::Error;
+    type Future<'a>
+    where
+        Self: 'a,
+    = <Self as SpiTransfer>::TransferFuture<'a>;
+    type FlushFuture<'a>
+    where
+        Self: 'a,
+    = <Self as SpiTransfer>::FlushFuture<'a>;
+    type VectoredFuture<'a>
+    where
+        Self: 'a,
+    = <Self as SpiTransfer>::TransferFuture<'a>;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Future<'a>
+    where
+        Self: 'a,
+    {
+        <Self as SpiTransfer>::transfer(self, buf, &mut [])
+    }
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'a>]) -> Self::VectoredFuture<'a>
+    where
+        Self: 'a,
+    {
+        let buf = bufs
+            .iter()
+            .find(|buf| !buf.is_empty())
+            .map_or(&[][..], |buf| buf.0);
+        <Self as SpiTransfer>::transfer(self, buf, &mut [])
+    }
+
+    fn flush<'a>(&'a mut self) -> Self::FlushFuture<'a>
+    where
+        Self: 'a,
+    {
+        <Self as SpiTransfer>::flush(self)
+    }
+}
+
+/// Trait for I2C bus masters
+///
+/// Devices are addressed by their 7-bit address. Each transfer starts
+/// the bus, addresses the device, and stops the bus once it is done.
+pub trait I2c {
+    /// The error type
+    ///
+    /// This can be converted into a board-independent [`Error`].
+    type Error: Debug + Into<Error>;
+
+    /// The future for a transfer
+    type Future<'a>: Future<Output = Result<(), Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// Enable the I2C bus, with its clock at `rate` Hz
+    fn enable(&mut self, rate: usize) -> Result<(), Self::Error>;
+
+    /// Disable the I2C bus
+    fn disable(&mut self) -> Result<(), Self::Error>;
+
+    /// Write bytes to a device, then read bytes from it
+    ///
+    /// The read follows the write with a repeated start, so no other
+    /// master can take the bus in between. This is how most devices
+    /// have a register selected and then read. If both buffers are
+    /// empty, the device is only addressed, which checks whether it
+    /// is present.
+    fn write_read<'a>(
+        &'a mut self,
+        address: u8,
+        buf_in: &'a [u8],
+        buf_out: &'a mut [u8],
+    ) -> Self::Future<'a>
+    where
+        Self: 'a;
+
+    /// Write bytes to a device
+    fn write<'a>(&'a mut self, address: u8, buf: &'a [u8]) -> Self::Future<'a>
+    where
+        Self: 'a,
+    {
+        self.write_read(address, buf, &mut [])
+    }
+
+    /// Read bytes from a device
+    fn read<'a>(&'a mut self, address: u8, buf: &'a mut [u8]) -> Self::Future<'a>
+    where
+        Self: 'a,
+    {
+        self.write_read(address, &[], buf)
+    }
+}
+
+/// A CAN frame identifier
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CanId {
+    /// An 11-bit standard identifier
+    Standard(u16),
+
+    /// A 29-bit extended identifier
+    Extended(u32),
+}
+
+impl CanId {
+    /// Check whether the identifier fits in its number of bits
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            CanId::Standard(id) => id <= 0x7FF,
+            CanId::Extended(id) => id <= 0x1FFF_FFFF,
+        }
+    }
+}
+
+/// A CAN data or remote frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CanFrame {
+    id: CanId,
+    remote: bool,
+    dlc: u8,
+    data: [u8; 8],
+}
+
+impl CanFrame {
+    /// Create a data frame
+    ///
+    /// Returns `None` if the identifier is not valid, or if there are
+    /// more than 8 bytes of data.
+    pub fn new(id: CanId, data: &[u8]) -> Option<Self> {
+        if !id.is_valid() || data.len() > 8 {
+            return None;
+        }
+        let mut frame = Self {
+            id,
+            remote: false,
+            dlc: data.len() as u8,
+            data: [0; 8],
+        };
+        frame.data[..data.len()].copy_from_slice(data);
+        Some(frame)
+    }
+
+    /// Create a remote frame, which requests `len` bytes of data
+    ///
+    /// Returns `None` if the identifier is not valid, or if `len` is
+    /// more than 8.
+    pub fn new_remote(id: CanId, len: usize) -> Option<Self> {
+        if !id.is_valid() || len > 8 {
+            return None;
+        }
+        Some(Self {
+            id,
+            remote: true,
+            dlc: len as u8,
+            data: [0; 8],
+        })
+    }
+
+    /// The identifier of this frame
+    pub fn id(&self) -> CanId {
+        self.id
+    }
+
+    /// Whether this is a remote frame
+    pub fn is_remote(&self) -> bool {
+        self.remote
+    }
+
+    /// The data length code of this frame
+    ///
+    /// This is the length of the data for a data frame, and the
+    /// length requested for a remote frame.
+    pub fn dlc(&self) -> usize {
+        self.dlc as usize
+    }
+
+    /// The data carried by this frame
+    ///
+    /// A remote frame carries no data.
+    pub fn data(&self) -> &[u8] {
+        if self.remote {
+            &[]
+        } else {
+            &self.data[..self.dlc as usize]
+        }
+    }
+}
+
+/// Trait for CAN bus controllers
+///
+/// A controller receives every frame on the bus, and queues a few
+/// of them until they are read.
+pub trait Can {
+    /// The error type
+    ///
+    /// This can be converted into a board-independent [`Error`].
+    type Error: Debug + Into<Error>;
+
+    /// The future for a send
+    type SendFuture<'a>: Future<Output = Result<(), Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// The future for a receive
+    type RecvFuture<'a>: Future<Output = Result<CanFrame, Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// Enable the CAN controller, and join the bus at `bitrate` bits per second
+    fn enable(&mut self, bitrate: usize) -> Result<(), Self::Error>;
+
+    /// Leave the bus, and disable the CAN controller
+    fn disable(&mut self) -> Result<(), Self::Error>;
+
+    /// Send a frame
+    ///
+    /// This completes once the frame is queued for transmission,
+    /// which waits for any frame queued before it to be sent.
+    fn send<'a>(&'a mut self, frame: CanFrame) -> Self::SendFuture<'a>
+    where
+        Self: 'a;
+
+    /// Receive the oldest frame which has not been read
+    fn recv<'a>(&'a mut self) -> Self::RecvFuture<'a>
+    where
+        Self: 'a;
+}
+
+/// Trait for audio outputs
+///
+/// Audio is a stream of frames, each of which is a left and a right
+/// 16-bit sample. Frames are queued in a double buffer: the hardware
+/// plays one block of frames while the next is filled. If the queue
+/// runs empty, silence is played until more frames are written.
+pub trait AudioOut {
+    /// The error type
+    ///
+    /// This can be converted into a board-independent [`Error`].
+    type Error: Debug + Into<Error>;
+
+    /// The future for a write
+    type WriteFuture<'a>: Future<Output = Result<usize, Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// The future for the `flush()` function
+    type FlushFuture<'a>: Future<Output = Result<(), Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// Enable the output at a sample rate, in Hz
+    ///
+    /// The rate is divided from the board's clocks, and may differ
+    /// slightly from the one requested. See [`Self::sample_rate()`].
+    fn enable(&mut self, sample_rate: usize) -> Result<(), Self::Error>;
+
+    /// Disable the output
+    ///
+    /// Any queued frames are discarded.
+    fn disable(&mut self) -> Result<(), Self::Error>;
+
+    /// The actual sample rate, or `None` if the output is disabled
+    fn sample_rate(&self) -> Option<usize>;
+
+    /// The number of frames in each block of the queue
+    fn block_size(&self) -> usize;
+
+    /// Queue frames to be played
+    ///
+    /// This waits until there is room in the queue, and returns the
+    /// number of frames which were queued. That is never more than
+    /// one block.
+    fn write<'a>(&'a mut self, frames: &'a [[i16; 2]]) -> Self::WriteFuture<'a>
+    where
+        Self: 'a;
+
+    /// Wait for every queued frame to be played
+    ///
+    /// A partly-filled block is padded with silence.
+    fn flush<'a>(&'a mut self) -> Self::FlushFuture<'a>
+    where
+        Self: 'a;
+}
+
+/// The serial connection to a host PC
+///
+/// On some boards, this is an alias for the serial port at
+/// [`serial_1`]. If you intend to use the serial port for off-board
+/// communication, you should use [`serial_1`] for compatibility with
+/// boards which differentiate the two serial ports.
+#[board_fn(io, red_v)]
+pub fn pc_serial() -> impl Future<Output = impl DerefMut<Target = impl Serial>> {}
+
+/// The first hardware serial port
+///
+/// This port is typically on pins 0 and 1
+///
+/// The port is held until the returned guard is dropped. Other tasks
+/// which ask for the port wait, without blocking the executor, until
+/// it is released. This is the same for every serial and SPI port.
+///
+/// On some boards, this is an alias for the serial port at
+/// [`pc_serial`]. If you intend to use the serial port for PC
+/// communication, you should use [`pc_serial`] for compatibility with
+/// boards which differentiate the two serial ports.
+#[board_fn(
+    io, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+)]
+pub fn serial_1() -> impl Future<Output = impl DerefMut<Target = impl Serial>> {}
+
+/// The second hardware serial port
+#[board_fn(io, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn serial_2() -> impl Future<Output = impl DerefMut<Target = impl Serial>> {}
+
+/// The third hardware serial port
+#[board_fn(io, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn serial_3() -> impl Future<Output = impl DerefMut<Target = impl Serial>> {}
+
+/// The fourth hardware serial port
+#[board_fn(io, teensy_35, teensy_36)]
+pub fn serial_4() -> impl Future<Output = impl DerefMut<Target = impl Serial>> {}
+
+/// The fifth hardware serial port
+#[board_fn(io, teensy_35, teensy_36)]
+pub fn serial_5() -> impl Future<Output = impl DerefMut<Target = impl Serial>> {}
+
+/// The sixth hardware serial port
+#[board_fn(io, teensy_35)]
+pub fn serial_6() -> impl Future<Output = impl DerefMut<Target = impl Serial>> {}
+
+/// The first serial port built from programmable IO
+///
+/// On the Teensy 4.x, this runs on FlexIO 2, on pins 6 and 9. It
+/// supports 8N1 framing only.
+#[board_fn(io, teensy_40, teensy_41)]
+pub fn flexio_serial_1() -> impl Future<Output = impl DerefMut<Target = impl Serial>> {}
+
+/// The USB serial port
+///
+/// The board appears to the host as a USB serial port, which needs
+/// no drivers. The USB stack is started the first time this is
+/// called, and runs in a background task added by
+/// [`entry`](crate::macros::entry). Unlike the hardware serial
+/// ports, it has no baud rate and is not enabled: it can be read and
+/// written as soon as it is locked. Data written while no program on
+/// the host has the port open is discarded.
+///
+/// The port is only available with the `usb-serial` feature, so that
+/// applications which don't use it don't run the USB stack's task.
+#[cfg(feature = "usb-serial")]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "usb-serial")))]
+#[board_fn(io, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn usb_serial() -> impl Future<Output = impl DerefMut<Target = impl Read + Write>> {}
+
+/// Run the USB serial port
+#[cfg(feature = "usb-serial")]
+#[board_fn(io, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub(crate) fn usb_serial_task() -> impl Future<Output = !> {}
+
+/// The first hardware SPI port
+///
+/// Pinouts can vary, but on most boards, the following pinout is
+/// used:
+/// * 11: Data Out
+/// * 12: Data In
+/// * 13: Clock
+///
+/// Some boards support hardware chip selects. See the documentation
+/// for your board for details.
+#[board_fn(io, teensy_30, teensy_32, teensy_35, teensy_36)]
+pub fn spi_1() -> impl Future<Output = impl DerefMut<Target = impl Spi>> {}
+
+/// The second hardware SPI port
+#[board_fn(io, teensy_35, teensy_36)]
+pub fn spi_2() -> impl Future<Output = impl DerefMut<Target = impl Spi>> {}
+
+/// The third hardware SPI port
+#[board_fn(io, teensy_35, teensy_36)]
+pub fn spi_3() -> impl Future<Output = impl DerefMut<Target = impl Spi>> {}
+
+/// The first hardware I2C bus
+///
+/// On most boards, this bus uses pin 19 as its clock and pin 18 as
+/// its data line.
+#[board_fn(io, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn i2c_1() -> impl DerefMut<Target = impl I2c> {}
+
+/// The second hardware I2C bus
+#[board_fn(io, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn i2c_2() -> impl DerefMut<Target = impl I2c> {}
+
+/// The first CAN bus
+///
+/// This bus uses pin 3 to transmit and pin 4 to receive. A CAN
+/// transceiver is needed to connect it to the bus.
+#[board_fn(io, teensy_32, teensy_35, teensy_36)]
+pub fn can_1() -> impl DerefMut<Target = impl Can> {}
+
+/// The second CAN bus
+///
+/// This bus uses pin 33 to transmit and pin 34 to receive.
+#[board_fn(io, teensy_36)]
+pub fn can_2() -> impl DerefMut<Target = impl Can> {}
+
+/// The I2S audio output
+///
+/// This output drives a codec, such as the SGTL5000 on the Teensy
+/// Audio Adaptor, as the master of its clocks. It uses the following
+/// pins:
+/// * 9: Bit clock
+/// * 11: Master clock
+/// * 22: Data
+/// * 23: Frame sync (left/right clock)
+///
+/// Pin 11 is also the data out pin of [`spi_1`], so the two cannot be
+/// used together.
+///
+/// The sample rate is divided from the CPU clock, so changing the
+/// clock while the output is enabled changes the rate.
+#[board_fn(io, teensy_35, teensy_36)]
+pub fn audio_out() -> impl DerefMut<Target = impl AudioOut> {}
+
+/// Compute a CRC with the MCU's CRC module
+///
+/// Returns `None` if the CRC module is in use.
+#[board_fn(io, teensy_30, teensy_32, teensy_35, teensy_36)]
+pub(crate) fn hardware_crc(kind: codec::CrcKind, data: &[u8]) -> Option<u32> {}
@@ -0,0 +1,518 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Bit-banged serial ports
+//!
+//! A [`SoftSerial`] drives its transmit pin in software, and receives
+//! from its receive pin's interrupt. This allows a serial port on any
+//! two pins, for shields and modules wired to pins without a UART.
+//!
+//! Every bit is timed by busy-waiting, with interrupts disabled while
+//! a character is sent, and from the receive pin's interrupt while
+//! one is received. Each character therefore delays every other
+//! interrupt, including the other serial ports, by its full length:
+//! about a millisecond at 9600 baud. Hardware serial ports should be
+//! preferred where they are available, and their own receive FIFOs
+//! are needed to cover these delays at high baud rates.
+//!
+//! A software port cannot receive while any software port is
+//! transmitting, so half-duplex protocols, where the other end only
+//! replies once it has been sent a request, work best.
+//!
+//! Software ports need pin interrupts, so they are not available on
+//! the Teensy 4.x boards.
+
+use super::{Error, ErrorKind, IoSlice, Parity, Read, Serial, SerialOption, SerialStats, Write};
+use crate::{
+    digital::{
+        digital_read, digital_write, dump_pin_config, mcu_pin, pin_mode, set_pin_interrupt, Edge,
+        PinMode, Pull,
+    },
+    sync::{without_interrupts, Flag, Value},
+    task::WakerSet,
+    time::delay_us_blocking,
+};
+use core::{
+    cell::UnsafeCell,
+    future::{poll_fn, ready, Future},
+    sync::atomic::Ordering,
+    task::Poll,
+};
+
+/// The highest supported baud rate
+const MAX_BAUD: usize = 38_400;
+
+/// The most ports which can be enabled at once
+const MAX_PORTS: usize = 4;
+
+/// The number of received bytes buffered for each port
+const BUFFER_LEN: usize = 64;
+
+const NO_PIN: usize = usize::MAX;
+
+/// An error from a [`SoftSerial`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SoftSerialError {
+    /// The serial port cannot be read or written because it is disabled
+    NotEnabled,
+
+    /// The serial port cannot be created because a pin does not exist, or both pins are the same
+    InvalidPin,
+
+    /// The serial port cannot be enabled because its receive pin's interrupt is in use, or it has none
+    PinInUse,
+
+    /// The serial port cannot be enabled because four other software ports are enabled
+    TooManyPorts,
+
+    /// The serial port cannot be enabled because the baud rate is zero or above 38400
+    InvalidBaud,
+
+    /// The serial port cannot be enabled because a requested option is invalid
+    InvalidOption,
+}
+
+impl From<SoftSerialError> for Error {
+    fn from(err: SoftSerialError) -> Self {
+        let kind = match err {
+            SoftSerialError::NotEnabled => ErrorKind::NotEnabled,
+            SoftSerialError::PinInUse | SoftSerialError::TooManyPorts => ErrorKind::InUse,
+            SoftSerialError::InvalidPin
+            | SoftSerialError::InvalidBaud
+            | SoftSerialError::InvalidOption => ErrorKind::InvalidSetting,
+        };
+        Error::new(kind, err)
+    }
+}
+
+/// A serial port on any two pins
+///
+/// The port supports the [`SerialOption::Invert`],
+/// [`SerialOption::Parity`], and [`SerialOption::StopBits`] options,
+/// and [`SerialOption::DataBits`] of 8. Baud rates up to 38400 can
+/// be requested, though the highest rates are only reliable on the
+/// faster boards. Up to 64 received bytes are buffered until they
+/// are read. Characters with a missing stop bit are discarded, but
+/// parity is not checked on receive.
+///
+/// See the [module documentation](self) for how this affects the
+/// rest of the program. As with [`digital_write`], the port does not
+/// take ownership of its pins.
+///
+/// ```
+/// use cntrlr::io::{soft::SoftSerial, Serial, WriteExt};
+///
+/// async fn hello() {
+///     let mut serial = SoftSerial::new(2, 3).unwrap();
+///     serial.enable(9600).unwrap();
+///     serial.write_all(b"Hello, world\r\n").await.unwrap();
+/// }
+/// ```
+pub struct SoftSerial {
+    tx: usize,
+    rx: usize,
+    config: Option<Config>,
+    stats: SerialStats,
+}
+
+#[derive(Clone, Copy)]
+struct Config {
+    slot: usize,
+    bit_time: usize,
+    invert: bool,
+    parity: Parity,
+    stop_bits: usize,
+}
+
+impl SoftSerial {
+    /// Create a serial port on two pins
+    ///
+    /// The port must be enabled before it is used.
+    pub fn new(tx: usize, rx: usize) -> Result<Self, SoftSerialError> {
+        if tx == rx || mcu_pin(tx).is_none() || mcu_pin(rx).is_none() {
+            return Err(SoftSerialError::InvalidPin);
+        }
+        Ok(Self {
+            tx,
+            rx,
+            config: None,
+            stats: Default::default(),
+        })
+    }
+
+    /// Send one character, with interrupts disabled
+    fn send(&self, config: &Config, byte: u8) {
+        let mut bits = (byte as usize) << 1;
+        let mut len = 9;
+        if config.parity != Parity::None {
+            let odd_ones = byte.count_ones() % 2 == 1;
+            let parity = match config.parity {
+                Parity::Odd => !odd_ones,
+                _ => odd_ones,
+            };
+            bits |= (parity as usize) << len;
+            len += 1;
+        }
+        for _ in 0..config.stop_bits {
+            bits |= 1 << len;
+            len += 1;
+        }
+
+        without_interrupts(|| {
+            let mut timer = BitTimer::new();
+            for bit in 0..len {
+                digital_write(self.tx, (bits >> bit & 1 != 0) != config.invert);
+                timer.wait(config.bit_time);
+            }
+        });
+    }
+}
+
+impl Read for SoftSerial {
+    type Error = SoftSerialError;
+    type Future<'a> = impl Future<Output = Result<usize, Self::Error>> + 'a;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Future<'a>
+    where
+        Self: 'a,
+    {
+        poll_fn(move |ctx| {
+            if buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+
+            let config = self.config.ok_or(SoftSerialError::NotEnabled)?;
+            let receiver = &RECEIVERS[config.slot];
+            // Bytes which arrived while the buffer was full are lost,
+            // as they would be in a hardware overrun.
+            if receiver.dropped.swap(0, Ordering::Relaxed) > 0 {
+                self.stats.overruns = self.stats.overruns.wrapping_add(1);
+            }
+            let mut count = 0;
+            while let Some(byte) = receiver.pop() {
+                buf[count] = byte;
+                count += 1;
+                if count >= buf.len() {
+                    break;
+                }
+            }
+            if count > 0 {
+                self.stats.max_burst = self.stats.max_burst.max(count);
+                Poll::Ready(Ok(count))
+            } else {
+                receiver.wakers.add(ctx.waker().clone());
+                // A character may have arrived before the waker was
+                // added.
+                if !receiver.is_empty() {
+                    ctx.waker().wake_by_ref();
+                }
+                Poll::Pending
+            }
+        })
+    }
+}
+
+impl Write for SoftSerial {
+    type Error = SoftSerialError;
+    type Future<'a> = impl Future<Output = Result<usize, Self::Error>> + 'a;
+    type FlushFuture<'a> = impl Future<Output = Result<(), Self::Error>> + 'a;
+    type VectoredFuture<'a> = impl Future<Output = Result<usize, Self::Error>> + 'a;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Future<'a>
+    where
+        Self: 'a,
+    {
+        async move {
+            let config = self.config.ok_or(SoftSerialError::NotEnabled)?;
+            for &byte in buf {
+                self.send(&config, byte);
+            }
+            Ok(buf.len())
+        }
+    }
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'a>]) -> Self::VectoredFuture<'a>
+    where
+        Self: 'a,
+    {
+        async move {
+            let config = self.config.ok_or(SoftSerialError::NotEnabled)?;
+            let mut count = 0;
+            for buf in bufs {
+                for &byte in buf.iter() {
+                    self.send(&config, byte);
+                }
+                count += buf.len();
+            }
+            Ok(count)
+        }
+    }
+
+    fn flush<'a>(&'a mut self) -> Self::FlushFuture<'a>
+    where
+        Self: 'a,
+    {
+        // Every write finishes sending before it returns
+        ready(Ok(()))
+    }
+}
+
+impl Serial for SoftSerial {
+    type Error = SoftSerialError;
+
+    fn enable_with_options(
+        &mut self,
+        baud: usize,
+        options: &[SerialOption],
+    ) -> Result<(), <Self as Serial>::Error> {
+        if baud == 0 || baud > MAX_BAUD {
+            return Err(SoftSerialError::InvalidBaud);
+        }
+        let mut invert = false;
+        let mut parity = Parity::None;
+        let mut stop_bits = 1;
+        for option in options {
+            match option {
+                SerialOption::Invert(option_invert) => invert = *option_invert,
+                SerialOption::Parity(option_parity) => parity = *option_parity,
+                SerialOption::StopBits(bits @ 1..=2) => stop_bits = *bits as usize,
+                SerialOption::DataBits(8) => {}
+                _ => return Err(SoftSerialError::InvalidOption),
+            }
+        }
+        self.disable()?;
+
+        let (port, pin) = mcu_pin(self.rx).ok_or(SoftSerialError::InvalidPin)?;
+        if dump_pin_config(self.rx).map_or(true, |config| config.interrupt.is_some()) {
+            return Err(SoftSerialError::PinInUse);
+        }
+        let slot = RECEIVERS
+            .iter()
+            .position(|receiver| !receiver.claimed.swap(true, Ordering::Acquire))
+            .ok_or(SoftSerialError::TooManyPorts)?;
+
+        digital_write(self.tx, !invert);
+        pin_mode(self.tx, PinMode::Output);
+        let pull = if invert { Pull::Down } else { Pull::Up };
+        pin_mode(self.rx, PinMode::PulledInput(pull));
+
+        let bit_time = (baud / 2 + 256_000_000) / baud;
+        let receiver = &RECEIVERS[slot];
+        receiver.pin.store(self.rx, Ordering::Relaxed);
+        receiver.bit_time.store(bit_time, Ordering::Relaxed);
+        receiver.invert.store(invert as usize, Ordering::Relaxed);
+        receiver.head.store(0, Ordering::Relaxed);
+        receiver.tail.store(0, Ordering::Relaxed);
+        receiver.dropped.store(0, Ordering::Relaxed);
+        receiver.id.store(port * 32 + pin, Ordering::Release);
+        let edge = if invert { Edge::Rising } else { Edge::Falling };
+        if !set_pin_interrupt(self.rx, Some(edge)) {
+            receiver.release();
+            return Err(SoftSerialError::PinInUse);
+        }
+
+        self.config = Some(Config {
+            slot,
+            bit_time,
+            invert,
+            parity,
+            stop_bits,
+        });
+        Ok(())
+    }
+
+    fn disable(&mut self) -> Result<(), <Self as Serial>::Error> {
+        if let Some(config) = self.config.take() {
+            set_pin_interrupt(self.rx, None);
+            RECEIVERS[config.slot].release();
+        }
+        Ok(())
+    }
+
+    fn stats(&self) -> SerialStats {
+        self.stats
+    }
+
+    fn reset_stats(&mut self) {
+        self.stats = Default::default();
+        if let Some(config) = self.config {
+            RECEIVERS[config.slot].dropped.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for SoftSerial {
+    fn drop(&mut self) {
+        let _ = self.disable();
+    }
+}
+
+/// Busy-waits for whole bits
+///
+/// Bit times are kept in 1/256ths of a microsecond, and the fraction
+/// left over from each bit is carried to the next, so that the error
+/// does not build up over a character.
+struct BitTimer {
+    remainder: usize,
+}
+
+impl BitTimer {
+    fn new() -> Self {
+        Self { remainder: 0 }
+    }
+
+    fn wait(&mut self, time: usize) {
+        let time = time + self.remainder;
+        delay_us_blocking(time >> 8);
+        self.remainder = time & 0xFF;
+    }
+}
+
+/// The receiving side of an enabled port
+///
+/// Received characters are written into the buffer by the pin
+/// interrupt, and read out by the port.
+struct Receiver {
+    claimed: Flag,
+    id: Value,
+    pin: Value,
+    bit_time: Value,
+    invert: Value,
+    head: Value,
+    tail: Value,
+    dropped: Value,
+    buffer: UnsafeCell<[u8; BUFFER_LEN]>,
+    wakers: WakerSet,
+}
+
+// Safety: Each byte of the buffer is written by the pin interrupt
+// before the head is advanced past it, and only read by the port
+// after that.
+unsafe impl Sync for Receiver {}
+
+impl Receiver {
+    const fn new() -> Self {
+        Self {
+            claimed: Flag::new(false),
+            id: Value::new(NO_PIN),
+            pin: Value::new(NO_PIN),
+            bit_time: Value::new(0),
+            invert: Value::new(0),
+            head: Value::new(0),
+            tail: Value::new(0),
+            dropped: Value::new(0),
+            buffer: UnsafeCell::new([0; BUFFER_LEN]),
+            wakers: WakerSet::new(),
+        }
+    }
+
+    fn release(&self) {
+        self.id.store(NO_PIN, Ordering::Relaxed);
+        self.claimed.store(false, Ordering::Release);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Acquire) == self.tail.load(Ordering::Relaxed)
+    }
+
+    fn push(&self, byte: u8) {
+        let head = self.head.load(Ordering::Relaxed);
+        if head.wrapping_sub(self.tail.load(Ordering::Acquire)) >= BUFFER_LEN {
+            let dropped = self.dropped.load(Ordering::Relaxed);
+            self.dropped
+                .store(dropped.wrapping_add(1), Ordering::Relaxed);
+            return;
+        }
+        unsafe { (*self.buffer.get())[head % BUFFER_LEN] = byte };
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+    }
+
+    fn pop(&self) -> Option<u8> {
+        if self.is_empty() {
+            return None;
+        }
+        let tail = self.tail.load(Ordering::Relaxed);
+        let byte = unsafe { (*self.buffer.get())[tail % BUFFER_LEN] };
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Some(byte)
+    }
+
+    /// Receive characters, starting from a start bit's edge
+    ///
+    /// Characters which follow straight on are received before
+    /// returning, as their start bits' edges can arrive before the
+    /// interrupt is cleared.
+    fn receive(&self) {
+        let pin = self.pin.load(Ordering::Relaxed);
+        let bit_time = self.bit_time.load(Ordering::Relaxed);
+        let idle = self.invert.load(Ordering::Relaxed) == 0;
+
+        // The last character's edges can leave the interrupt pending
+        // once it has been received, which is seen here as an idle
+        // line.
+        if digital_read(pin) == idle {
+            return;
+        }
+
+        loop {
+            let mut timer = BitTimer::new();
+            timer.wait(bit_time / 2);
+            if digital_read(pin) == idle {
+                // Too short for a start bit
+                return;
+            }
+
+            let mut byte = 0;
+            for bit in 0..8 {
+                timer.wait(bit_time);
+                if digital_read(pin) == idle {
+                    byte |= 1 << bit;
+                }
+            }
+            // The parity bit, if any, is skipped over while waiting
+            // for the next start bit.
+            timer.wait(bit_time);
+            if digital_read(pin) != idle {
+                // Missing stop bit
+                return;
+            }
+            self.push(byte);
+            self.wakers.wake();
+
+            // Wait out the rest of the stop bit, and up to two more
+            // bits for stop and parity bits, for the next start bit.
+            let mut waited = 0;
+            while digital_read(pin) == idle {
+                if waited >= (bit_time * 5 / 2) >> 8 {
+                    return;
+                }
+                delay_us_blocking(1);
+                waited += 1;
+            }
+        }
+    }
+}
+
+static RECEIVERS: [Receiver; MAX_PORTS] = [
+    Receiver::new(),
+    Receiver::new(),
+    Receiver::new(),
+    Receiver::new(),
+];
+
+/// Receive on a pin, if it belongs to a software port
+///
+/// This is called from the pin interrupt, and returns `true` if the
+/// pin belongs to a port, in which case its interrupt must be left
+/// enabled.
+pub(crate) fn rx_intr(port: usize, pin: usize) -> bool {
+    let id = port * 32 + pin;
+    for receiver in &RECEIVERS {
+        if receiver.id.load(Ordering::Acquire) == id {
+            receiver.receive();
+            return true;
+        }
+    }
+    false
+}
@@ -0,0 +1,319 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Bit-banged I2C
+
+use super::{Error, ErrorKind, I2c};
+use crate::{
+    digital::{digital_read, digital_write, mcu_pin, pin_mode, PinMode, Pull},
+    time::delay_us_blocking,
+};
+use core::future::Future;
+
+/// The highest supported clock rate, in Hz
+const MAX_RATE: usize = 100_000;
+
+/// How long a device may hold the clock low, in microseconds
+const STRETCH_TIMEOUT: usize = 25_000;
+
+/// An error from a [`SoftI2c`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SoftI2cError {
+    /// The bus cannot be used because it is disabled
+    NotEnabled,
+
+    /// The bus cannot be created because a pin does not exist, or both pins are the same
+    InvalidPin,
+
+    /// The bus cannot be enabled because the clock rate is zero or above 100kHz
+    InvalidRate,
+
+    /// The device cannot be addressed because its address is above 0x7F
+    InvalidAddress,
+
+    /// No device acknowledged the address
+    AddressNack,
+
+    /// The device did not acknowledge a byte written to it
+    DataNack,
+
+    /// Another master drove the data line while this one was sending
+    ArbitrationLost,
+
+    /// A device held the clock low for more than 25ms
+    Timeout,
+}
+
+impl From<SoftI2cError> for Error {
+    fn from(err: SoftI2cError) -> Self {
+        let kind = match err {
+            SoftI2cError::NotEnabled => ErrorKind::NotEnabled,
+            SoftI2cError::InvalidPin | SoftI2cError::InvalidRate => ErrorKind::InvalidSetting,
+            SoftI2cError::InvalidAddress => ErrorKind::InvalidData,
+            SoftI2cError::AddressNack | SoftI2cError::DataNack => ErrorKind::NotAcknowledged,
+            SoftI2cError::ArbitrationLost | SoftI2cError::Timeout => ErrorKind::Other,
+        };
+        Error::new(kind, err)
+    }
+}
+
+/// An I2C master on any two pins
+///
+/// The bus is driven in software, so it can use pins without
+/// hardware I2C, or stand in for a hardware bus which is in use. The
+/// pins act as open-drain outputs: they are pulled low as outputs,
+/// and released as inputs with their pull-up enabled. The internal
+/// pull-ups are weak, so most buses still need external pull-up
+/// resistors.
+///
+/// Devices may stretch the clock by holding it low for up to 25ms.
+/// Clock rates up to 100kHz can be requested. The actual rate is
+/// somewhat lower, as each bit takes time to drive on top of its
+/// delays.
+///
+/// Transfers busy-wait for their whole length, and stall the
+/// executor meanwhile. A byte takes about 90µs at 100kHz. As with
+/// [`digital_write`], the bus does not take ownership of its pins.
+///
+/// ```
+/// use cntrlr::io::{I2c, SoftI2c};
+///
+/// async fn read_temperature() -> u16 {
+///     let mut i2c = SoftI2c::new(18, 19).unwrap();
+///     i2c.enable(100_000).unwrap();
+///     let mut temperature = [0; 2];
+///     i2c.write_read(0x48, &[0x00], &mut temperature).await.unwrap();
+///     u16::from_be_bytes(temperature)
+/// }
+/// ```
+pub struct SoftI2c {
+    scl: usize,
+    sda: usize,
+    half_period: Option<usize>,
+}
+
+impl SoftI2c {
+    /// Create an I2C bus on two pins
+    ///
+    /// The bus must be enabled before it is used.
+    pub fn new(scl: usize, sda: usize) -> Result<Self, SoftI2cError> {
+        if scl == sda || mcu_pin(scl).is_none() || mcu_pin(sda).is_none() {
+            return Err(SoftI2cError::InvalidPin);
+        }
+        Ok(Self {
+            scl,
+            sda,
+            half_period: None,
+        })
+    }
+
+    /// Run a transfer, and leave the bus idle afterwards
+    fn transfer(
+        &mut self,
+        address: u8,
+        buf_in: &[u8],
+        buf_out: &mut [u8],
+    ) -> Result<(), SoftI2cError> {
+        let half_period = self.half_period.ok_or(SoftI2cError::NotEnabled)?;
+        if address > 0x7F {
+            return Err(SoftI2cError::InvalidAddress);
+        }
+
+        let mut bus = Bits {
+            scl: self.scl,
+            sda: self.sda,
+            half_period,
+        };
+        match bus.transfer(address, buf_in, buf_out) {
+            // The bus belongs to the other master now
+            Err(SoftI2cError::ArbitrationLost) => {
+                bus.release(bus.scl);
+                bus.release(bus.sda);
+                Err(SoftI2cError::ArbitrationLost)
+            }
+            Err(err) => {
+                let _ = bus.stop();
+                Err(err)
+            }
+            Ok(()) => bus.stop(),
+        }
+    }
+}
+
+impl I2c for SoftI2c {
+    type Error = SoftI2cError;
+    type Future<'a> = impl Future<Output = Result<(), Self::Error>> + 'a;
+
+    fn enable(&mut self, rate: usize) -> Result<(), Self::Error> {
+        if rate == 0 || rate > MAX_RATE {
+            return Err(SoftI2cError::InvalidRate);
+        }
+        digital_write(self.scl, false);
+        digital_write(self.sda, false);
+        pin_mode(self.scl, PinMode::PulledInput(Pull::Up));
+        pin_mode(self.sda, PinMode::PulledInput(Pull::Up));
+        self.half_period = Some((500_000 + rate - 1) / rate);
+        Ok(())
+    }
+
+    fn disable(&mut self) -> Result<(), Self::Error> {
+        self.half_period = None;
+        Ok(())
+    }
+
+    fn write_read<'a>(
+        &'a mut self,
+        address: u8,
+        buf_in: &'a [u8],
+        buf_out: &'a mut [u8],
+    ) -> Self::Future<'a>
+    where
+        Self: 'a,
+    {
+        async move { self.transfer(address, buf_in, buf_out) }
+    }
+}
+
+/// The bit-level signalling of a [`SoftI2c`]
+///
+/// Between bits, the clock is held low. Each pin's output level is
+/// always low, so switching it to an output pulls it low.
+struct Bits {
+    scl: usize,
+    sda: usize,
+    half_period: usize,
+}
+
+impl Bits {
+    fn transfer(
+        &mut self,
+        address: u8,
+        buf_in: &[u8],
+        buf_out: &mut [u8],
+    ) -> Result<(), SoftI2cError> {
+        if !buf_in.is_empty() || buf_out.is_empty() {
+            self.start()?;
+            if !self.write_byte(address << 1)? {
+                return Err(SoftI2cError::AddressNack);
+            }
+            for &byte in buf_in {
+                if !self.write_byte(byte)? {
+                    return Err(SoftI2cError::DataNack);
+                }
+            }
+        }
+
+        if !buf_out.is_empty() {
+            self.start()?;
+            if !self.write_byte(address << 1 | 1)? {
+                return Err(SoftI2cError::AddressNack);
+            }
+            let last = buf_out.len() - 1;
+            for (idx, byte) in buf_out.iter_mut().enumerate() {
+                // The last byte is not acknowledged, to end the read
+                *byte = self.read_byte(idx != last)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Send a start, or a repeated start
+    fn start(&mut self) -> Result<(), SoftI2cError> {
+        self.release(self.sda);
+        self.delay();
+        self.release_scl()?;
+        if !digital_read(self.sda) {
+            return Err(SoftI2cError::ArbitrationLost);
+        }
+        self.delay();
+        self.pull_low(self.sda);
+        self.delay();
+        self.pull_low(self.scl);
+        Ok(())
+    }
+
+    /// Send a stop, leaving both lines released
+    fn stop(&mut self) -> Result<(), SoftI2cError> {
+        self.pull_low(self.sda);
+        self.delay();
+        self.release_scl()?;
+        self.delay();
+        self.release(self.sda);
+        self.delay();
+        if !digital_read(self.sda) {
+            return Err(SoftI2cError::ArbitrationLost);
+        }
+        Ok(())
+    }
+
+    fn write_bit(&mut self, bit: bool) -> Result<(), SoftI2cError> {
+        if bit {
+            self.release(self.sda);
+        } else {
+            self.pull_low(self.sda);
+        }
+        self.delay();
+        self.release_scl()?;
+        if bit && !digital_read(self.sda) {
+            return Err(SoftI2cError::ArbitrationLost);
+        }
+        self.delay();
+        self.pull_low(self.scl);
+        Ok(())
+    }
+
+    fn read_bit(&mut self) -> Result<bool, SoftI2cError> {
+        self.release(self.sda);
+        self.delay();
+        self.release_scl()?;
+        let bit = digital_read(self.sda);
+        self.delay();
+        self.pull_low(self.scl);
+        Ok(bit)
+    }
+
+    /// Write a byte, and return whether it was acknowledged
+    fn write_byte(&mut self, byte: u8) -> Result<bool, SoftI2cError> {
+        for bit in (0..8).rev() {
+            self.write_bit(byte >> bit & 1 != 0)?;
+        }
+        Ok(!self.read_bit()?)
+    }
+
+    /// Read a byte, and acknowledge it if `ack` is set
+    fn read_byte(&mut self, ack: bool) -> Result<u8, SoftI2cError> {
+        let mut byte = 0;
+        for _ in 0..8 {
+            byte = byte << 1 | self.read_bit()? as u8;
+        }
+        self.write_bit(!ack)?;
+        Ok(byte)
+    }
+
+    /// Release the clock, and wait for any device stretching it
+    fn release_scl(&mut self) -> Result<(), SoftI2cError> {
+        self.release(self.scl);
+        let mut waited = 0;
+        while !digital_read(self.scl) {
+            if waited == STRETCH_TIMEOUT {
+                return Err(SoftI2cError::Timeout);
+            }
+            delay_us_blocking(1);
+            waited += 1;
+        }
+        Ok(())
+    }
+
+    fn release(&self, pin: usize) {
+        pin_mode(pin, PinMode::PulledInput(Pull::Up));
+    }
+
+    fn pull_low(&self, pin: usize) {
+        pin_mode(pin, PinMode::Output);
+    }
+
+    fn delay(&self) {
+        delay_us_blocking(self.half_period);
+    }
+}
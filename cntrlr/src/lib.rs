@@ -9,8 +9,8 @@
 //!
 //! #[entry]
 //! async fn main() -> ! {
-//!    serial_1().enable(9600);
-//!    writeln!(serial_1(), "Hello, World").await.expect("Failed to message");
+//!    serial_1().await.enable(9600);
+//!    writeln!(serial_1().await, "Hello, World").await.expect("Failed to message");
 //!    pending().await
 //! }
 //! ```
@@ -31,25 +31,66 @@
 #![allow(incomplete_features)]
 #![deny(missing_docs)]
 #![feature(
-    alloc_error_handler,
     asm,
     cfg_target_has_atomic,
     future_poll_fn,
     generic_associated_types,
     naked_functions,
     never_type,
+    rustc_attrs,
     type_alias_impl_trait
 )]
+#![cfg_attr(feature = "allocator", feature(alloc_error_handler))]
 #![cfg_attr(feature = "doc-cfg", feature(doc_cfg))]
 
 extern crate alloc;
 
+pub mod analog;
+#[cfg(feature = "blackbox")]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "blackbox")))]
+pub mod blackbox;
+pub mod clock;
+pub mod config;
+pub mod counter;
 pub mod digital;
+#[cfg(all(
+    feature = "ffi",
+    any(
+        doc,
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    )
+))]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "ffi")))]
+pub mod ffi;
 pub mod hw;
+pub mod input;
 pub mod io;
+pub mod logger;
+#[cfg(feature = "owners")]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "owners")))]
+pub mod owners;
+pub mod pins;
+pub mod post;
+pub mod protocol;
+pub mod pwm;
+pub mod runtime;
+#[cfg(feature = "shell")]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "shell")))]
+pub mod shell;
 pub mod sync;
 pub mod task;
 pub mod time;
+pub mod touch;
+pub mod usb;
+pub mod vbat;
+pub mod watchdog;
 
 /// Support Macros
 pub mod macros {
@@ -59,16 +100,36 @@ pub mod macros {
 /// Common functions and traits for using Cntrlr
 pub mod prelude {
     pub use crate::digital::{PinMode, Pull};
-    pub use crate::io::{Read, ReadExt, Serial, SerialOption, Spi, SpiOption, Write, WriteExt};
+    pub use crate::io::{
+        AudioOut, Can, CanFrame, CanId, I2c, Read, ReadExt, Serial, SerialOption, Spi, SpiOption,
+        Write, WriteExt,
+    };
+    pub use crate::pwm::Pwm;
     use cntrlr_macros::prelude_fn;
 
+    #[prelude_fn(teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+    pub use crate::analog::analog_read;
+
+    #[prelude_fn(teensy_35, teensy_36)]
+    pub use crate::analog::dac_write;
+
+    #[prelude_fn(
+        red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+    )]
+    pub use crate::digital::{digital_read, digital_write, led, pin_mode};
+
     #[prelude_fn(red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
-    pub use crate::digital::{digital_read, digital_write, pin_mode};
+    pub use crate::digital::{wait_for_falling_edge, wait_for_rising_edge};
+
+    #[prelude_fn(red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+    pub use crate::pwm::analog_write;
 
     #[prelude_fn(red_v)]
     pub use crate::io::pc_serial;
 
-    #[prelude_fn(red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+    #[prelude_fn(
+        red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+    )]
     pub use crate::io::serial_1;
 
     #[prelude_fn(red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
@@ -86,6 +147,10 @@ pub mod prelude {
     #[prelude_fn(teensy_35)]
     pub use crate::io::serial_6;
 
+    #[cfg(feature = "usb-serial")]
+    #[prelude_fn(teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+    pub use crate::io::usb_serial;
+
     #[prelude_fn(teensy_30, teensy_32, teensy_35, teensy_36)]
     pub use crate::io::spi_1;
 
@@ -95,12 +160,32 @@ pub mod prelude {
     #[prelude_fn(teensy_35, teensy_36)]
     pub use crate::io::spi_3;
 
-    #[prelude_fn(red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
-    pub use crate::time::{millis, sleep_millis};
+    #[prelude_fn(teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+    pub use crate::io::i2c_1;
+
+    #[prelude_fn(teensy_32, teensy_35, teensy_36, teensy_lc)]
+    pub use crate::io::i2c_2;
+
+    #[prelude_fn(teensy_32, teensy_35, teensy_36)]
+    pub use crate::io::can_1;
+
+    #[prelude_fn(teensy_36)]
+    pub use crate::io::can_2;
+
+    #[prelude_fn(teensy_35, teensy_36)]
+    pub use crate::io::audio_out;
+
+    #[prelude_fn(
+        red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+    )]
+    pub use crate::time::{micros, millis, sleep_micros, sleep_millis};
+
+    #[prelude_fn(teensy_30, teensy_32, teensy_36, teensy_lc)]
+    pub use crate::touch::touch_read;
 
     pub use crate::macros::entry;
 }
 
+#[cfg(feature = "allocator")]
 mod allocator;
 mod register;
-mod runtime;
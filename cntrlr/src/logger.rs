@@ -0,0 +1,371 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Time-stamped data logging
+//!
+//! A [`Logger`] batches [records](Record) into chunks, each of which
+//! fills one block of a [`BlockDevice`]. Every record is stamped with
+//! the time it was logged. The logger uses a fixed range of blocks as
+//! a ring, so once the range is full the oldest chunks are
+//! overwritten.
+//!
+//! Each chunk is laid out as:
+//!
+//! | Offset       | Size | Contents                           |
+//! |--------------|------|------------------------------------|
+//! | 0            | 4    | The magic bytes `CLOG`             |
+//! | 4            | 4    | The chunk's sequence number        |
+//! | 8            | 2    | The number of records in the chunk |
+//! | 10           | 2    | The length of the records, `n`     |
+//! | 12           | `n`  | The records                        |
+//! | `size` - 4   | 4    | CRC-32 of the rest of the block    |
+//!
+//! Each record is an 8-byte timestamp, a 2-byte length, and the
+//! encoded record. All integers are little-endian. Sequence numbers
+//! start at zero and increase by one for each chunk, so the newest
+//! chunk is the valid chunk with the highest sequence number.
+
+use crate::io::{
+    codec::{crc, CrcKind},
+    BlockDevice, Error, ErrorKind,
+};
+use alloc::{vec, vec::Vec};
+use core::{convert::TryInto, future::Future, iter};
+
+const MAGIC: &[u8; 4] = b"CLOG";
+const HEADER: usize = 12;
+const TRAILER: usize = 4;
+const RECORD_HEADER: usize = 10;
+
+/// A value which can be written to a [`Logger`]
+pub trait Record {
+    /// Append the encoded record to `buf`
+    fn encode(&self, buf: &mut Vec<u8>);
+
+    /// Decode a record which was encoded by [`Self::encode()`]
+    ///
+    /// Returns `None` if `buf` is not a valid record.
+    fn decode(buf: &[u8]) -> Option<Self>
+    where
+        Self: Sized;
+}
+
+/// An error from a [`Logger`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum LogError<E> {
+    /// Error from the underlying device
+    Device(E),
+
+    /// The record cannot be logged because it does not fit in a single chunk
+    RecordTooLarge,
+
+    /// The logger cannot be opened because the block range is empty or past the end of the device
+    InvalidRange,
+}
+
+impl<E> From<LogError<E>> for Error
+where
+    E: Into<Error>,
+{
+    fn from(err: LogError<E>) -> Self {
+        match err {
+            LogError::Device(err) => err.into(),
+            LogError::RecordTooLarge => ErrorKind::InvalidData.into(),
+            LogError::InvalidRange => ErrorKind::InvalidSetting.into(),
+        }
+    }
+}
+
+/// A chunk read back from a block
+pub struct Chunk<'a> {
+    sequence: u32,
+    count: usize,
+    records: &'a [u8],
+}
+
+impl<'a> Chunk<'a> {
+    /// Parse a block written by a [`Logger`]
+    ///
+    /// Returns `None` if the block is not a chunk, or if its CRC does
+    /// not match.
+    pub fn parse(block: &'a [u8]) -> Option<Self> {
+        if block.len() < HEADER + TRAILER || &block[0..4] != MAGIC {
+            return None;
+        }
+        let (body, trailer) = block.split_at(block.len() - TRAILER);
+        if crc(CrcKind::Crc32, body) != u32::from_le_bytes(trailer.try_into().ok()?) {
+            return None;
+        }
+        let sequence = u32::from_le_bytes(block[4..8].try_into().ok()?);
+        let count = u16::from_le_bytes(block[8..10].try_into().ok()?) as usize;
+        let len = u16::from_le_bytes(block[10..12].try_into().ok()?) as usize;
+        let records = body.get(HEADER..HEADER + len)?;
+        Some(Self {
+            sequence,
+            count,
+            records,
+        })
+    }
+
+    /// The chunk's sequence number
+    pub fn sequence(&self) -> u32 {
+        self.sequence
+    }
+
+    /// The number of records in the chunk
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Whether the chunk has no records
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The timestamp and encoded bytes of each record in the chunk
+    pub fn records(&self) -> impl Iterator<Item = (u64, &'a [u8])> + 'a {
+        let mut records = self.records;
+        iter::from_fn(move || {
+            let timestamp = u64::from_le_bytes(records.get(0..8)?.try_into().ok()?);
+            let len = u16::from_le_bytes(records.get(8..10)?.try_into().ok()?) as usize;
+            let record = records.get(RECORD_HEADER..RECORD_HEADER + len)?;
+            records = &records[RECORD_HEADER + len..];
+            Some((timestamp, record))
+        })
+        .take(self.count)
+    }
+
+    /// Decode each record in the chunk
+    ///
+    /// Records which fail to decode are skipped.
+    pub fn decode<R: Record>(&self) -> impl Iterator<Item = (u64, R)> + 'a {
+        self.records()
+            .filter_map(|(timestamp, record)| R::decode(record).map(|record| (timestamp, record)))
+    }
+}
+
+/// A logger of time-stamped records to a block device
+///
+/// Records are collected in memory until a chunk is full, and then
+/// written to the next block in the logger's range. Call
+/// [`flush()`](Self::flush) to write a partial chunk, for instance
+/// before sleeping or when power is about to be removed. A flushed
+/// chunk stays open, and is written again as more records are added
+/// to it. Wrapping the device in a [`BlockCache`](crate::io::BlockCache)
+/// keeps frequent flushes from wearing out flash storage.
+///
+/// Records are stamped with [`millis()`](crate::time::millis) unless
+/// another clock is set with [`set_clock()`](Self::set_clock), such as
+/// a real-time clock. Without a board there is no default clock, and
+/// records are stamped zero.
+///
+/// ```
+/// # extern crate alloc;
+/// use alloc::vec::Vec;
+/// use cntrlr::{logger::{Logger, Record}, prelude::*};
+/// # use cntrlr::io::BlockDevice;
+/// # fn sd_card() -> impl BlockDevice { unimplemented!() }
+///
+/// struct Reading(u16);
+///
+/// impl Record for Reading {
+///     fn encode(&self, buf: &mut Vec<u8>) {
+///         buf.extend_from_slice(&self.0.to_le_bytes());
+///     }
+///
+///     fn decode(buf: &[u8]) -> Option<Self> {
+///         Some(Reading(u16::from_le_bytes([*buf.get(0)?, *buf.get(1)?])))
+///     }
+/// }
+///
+/// #[entry]
+/// async fn main() -> ! {
+///     let mut logger = Logger::open(sd_card(), 0, 1024).await.unwrap();
+///     loop {
+///         logger.log(&Reading(42)).await.ok();
+///         sleep_millis(1000).await;
+///     }
+/// }
+/// ```
+pub struct Logger<D> {
+    device: D,
+    first: usize,
+    count: usize,
+    sequence: u32,
+    records: usize,
+    chunk: Vec<u8>,
+    record: Vec<u8>,
+    clock: fn() -> u64,
+}
+
+impl<D> Logger<D> {
+    /// Set the clock used to timestamp records
+    pub fn set_clock(&mut self, clock: fn() -> u64) {
+        self.clock = clock;
+    }
+
+    /// The sequence number of the chunk being filled
+    pub fn sequence(&self) -> u32 {
+        self.sequence
+    }
+
+    /// Get the underlying device back
+    ///
+    /// Any records which have not been flushed are discarded.
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+}
+
+impl<D> Logger<D>
+where
+    D: BlockDevice,
+{
+    /// Start logging to `count` blocks of a device, starting at `first`
+    ///
+    /// Every block in the range is read to find the newest chunk, and
+    /// logging resumes with a new chunk after it. The range should be the
+    /// same each time the logger is opened, or the search may find
+    /// stale chunks.
+    pub fn open(
+        device: D,
+        first: usize,
+        count: usize,
+    ) -> impl Future<Output = Result<Self, LogError<D::Error>>> {
+        async move {
+            if count == 0 || first + count > device.block_count() {
+                return Err(LogError::InvalidRange);
+            }
+
+            let mut logger = Self {
+                chunk: vec![0; device.block_size()],
+                record: Vec::new(),
+                device,
+                first,
+                count,
+                sequence: 0,
+                records: 0,
+                clock: default_clock,
+            };
+
+            let mut newest = None;
+            for block in first..first + count {
+                logger
+                    .device
+                    .read_block(block, &mut logger.chunk)
+                    .await
+                    .map_err(LogError::Device)?;
+                if let Some(chunk) = Chunk::parse(&logger.chunk) {
+                    if newest.map_or(true, |newest| chunk.sequence() > newest) {
+                        newest = Some(chunk.sequence());
+                    }
+                }
+            }
+
+            logger.sequence = newest.map_or(0, |newest| newest.wrapping_add(1));
+            logger.chunk.iter_mut().for_each(|byte| *byte = 0);
+            Ok(logger)
+        }
+    }
+
+    /// Log a record
+    ///
+    /// If the record does not fit in the current chunk, the chunk is
+    /// written out and the record starts a new one.
+    pub fn log<'a, R: Record>(
+        &'a mut self,
+        record: &'a R,
+    ) -> impl Future<Output = Result<(), LogError<D::Error>>> + 'a {
+        async move {
+            self.record.clear();
+            self.record.extend_from_slice(&(self.clock)().to_le_bytes());
+            self.record.extend_from_slice(&[0, 0]);
+            record.encode(&mut self.record);
+
+            let len = self.record.len() - RECORD_HEADER;
+            let capacity = self.chunk.len() - HEADER - TRAILER;
+            if len > u16::MAX as usize || self.record.len() > capacity {
+                return Err(LogError::RecordTooLarge);
+            }
+            self.record[8..10].copy_from_slice(&(len as u16).to_le_bytes());
+
+            if self.used() + self.record.len() > capacity {
+                self.flush().await?;
+                self.next_chunk();
+            }
+
+            let start = HEADER + self.used();
+            self.chunk[start..start + self.record.len()].copy_from_slice(&self.record);
+            self.records += 1;
+            let used = (self.used() + self.record.len()) as u16;
+            self.chunk[10..12].copy_from_slice(&used.to_le_bytes());
+            Ok(())
+        }
+    }
+
+    /// Write the current chunk to the device
+    ///
+    /// This does nothing if no records have been logged since the
+    /// last chunk was written.
+    pub fn flush(&mut self) -> impl Future<Output = Result<(), LogError<D::Error>>> + '_ {
+        async move {
+            if self.records == 0 {
+                return Ok(());
+            }
+
+            let len = self.chunk.len();
+            self.chunk[0..4].copy_from_slice(MAGIC);
+            self.chunk[4..8].copy_from_slice(&self.sequence.to_le_bytes());
+            self.chunk[8..10].copy_from_slice(&(self.records as u16).to_le_bytes());
+            let crc = crc(CrcKind::Crc32, &self.chunk[..len - TRAILER]);
+            self.chunk[len - TRAILER..].copy_from_slice(&crc.to_le_bytes());
+
+            let block = self.first + self.sequence as usize % self.count;
+            self.device
+                .write_block(block, &self.chunk)
+                .await
+                .map_err(LogError::Device)
+        }
+    }
+
+    fn used(&self) -> usize {
+        u16::from_le_bytes([self.chunk[10], self.chunk[11]]) as usize
+    }
+
+    fn next_chunk(&mut self) {
+        self.sequence = self.sequence.wrapping_add(1);
+        self.records = 0;
+        self.chunk.iter_mut().for_each(|byte| *byte = 0);
+    }
+}
+
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+fn default_clock() -> u64 {
+    crate::time::millis() as u64
+}
+
+#[cfg(not(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+)))]
+fn default_clock() -> u64 {
+    0
+}
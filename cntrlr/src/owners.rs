@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Records of who holds each pin and peripheral
+//!
+//! When a pin or clock-gated peripheral is acquired, the name of the
+//! current task and the location of the call which acquired it are
+//! recorded, until it is released. When acquiring one fails because
+//! it is in use, its owner is kept as the [`last_conflict`], and the
+//! `Debug` output of the "in use" serial and SPI errors includes it:
+//!
+//! ```text
+//! PinInUse { conflict: Some(Conflict { resource: Pin { port: 1, pin: 17 },
+//!     owner: Some(Owner { task: Some("logger"), location: Location {
+//!     file: "src/hw/board/teensy_lc/io.rs", line: 53, col: 14 } }) }) }
+//! ```
+//!
+//! Pins and peripherals are currently recorded on the Kinetis MCUs
+//! only.
+
+use crate::{
+    sync::{without_interrupts, Mutex},
+    task,
+};
+use alloc::vec::Vec;
+use core::panic::Location;
+
+/// A pin or peripheral which can be held
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resource {
+    /// A pin, by its port and its index in the port
+    Pin {
+        /// The port, where port A is 0
+        port: usize,
+
+        /// The index of the pin in its port
+        pin: usize,
+    },
+
+    /// A clock-gated peripheral, by the name of its type
+    Peripheral(&'static str),
+}
+
+/// The holder of a resource
+#[derive(Clone, Copy, Debug)]
+pub struct Owner {
+    /// The task which acquired the resource, if it has a name
+    pub task: Option<&'static str>,
+
+    /// The call which acquired the resource
+    pub location: &'static Location<'static>,
+}
+
+/// A failure to acquire a resource which was in use
+#[derive(Clone, Copy, Debug)]
+pub struct Conflict {
+    /// The resource which could not be acquired
+    pub resource: Resource,
+
+    /// The holder of the resource
+    ///
+    /// This is `None` if it was acquired without being recorded,
+    /// such as by unsafe code which steals a handle.
+    pub owner: Option<Owner>,
+}
+
+static OWNERS: Mutex<Vec<(Resource, Owner)>> = Mutex::new(Vec::new());
+static LAST_CONFLICT: Mutex<Option<Conflict>> = Mutex::new(None);
+
+/// The current holder of a resource
+///
+/// Returns `None` if the resource is not held.
+pub fn owner(resource: Resource) -> Option<Owner> {
+    without_interrupts(|| {
+        OWNERS
+            .lock()
+            .iter()
+            .find(|(held, _)| *held == resource)
+            .map(|(_, owner)| *owner)
+    })
+}
+
+/// The most recent failure to acquire a resource
+pub fn last_conflict() -> Option<Conflict> {
+    without_interrupts(|| *LAST_CONFLICT.lock())
+}
+
+/// Record that a resource was acquired by the current task
+pub(crate) fn acquired(resource: Resource, location: &'static Location<'static>) {
+    let owner = Owner {
+        task: task::current_name(),
+        location,
+    };
+    without_interrupts(|| {
+        let mut owners = OWNERS.lock();
+        owners.retain(|(held, _)| *held != resource);
+        owners.push((resource, owner));
+    });
+}
+
+/// Record that a resource was released
+pub(crate) fn released(resource: Resource) {
+    without_interrupts(|| OWNERS.lock().retain(|(held, _)| *held != resource));
+}
+
+/// Record that a resource could not be acquired
+pub(crate) fn conflicted(resource: Resource) {
+    let conflict = Conflict {
+        resource,
+        owner: owner(resource),
+    };
+    without_interrupts(|| *LAST_CONFLICT.lock() = Some(conflict));
+}
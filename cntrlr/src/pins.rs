@@ -0,0 +1,59 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Named pins for Cntrlr boards
+//!
+//! These constants can be passed to any function which takes a pin
+//! number, instead of using bare numbers. `Dn` is digital pin `n`,
+//! and `An` is analog input `n`, matching the usual Arduino names.
+//! `LED_PIN` is the pin connected to the board's built-in LED.
+//!
+//! The set of available pins depends on the selected board. See the
+//! `pins` module of your board's `digital` module for details.
+//!
+//! Each `Dn` is also a type. APIs which take a pin as a type
+//! parameter, such as `spi_1().await.enable_with_cs::<pins::D10>()`, are
+//! bound by the function traits in this module, so a pin which
+//! cannot serve the function fails to compile rather than returning
+//! an error.
+
+#[cfg(board = "red_v")]
+pub use crate::hw::board::red_v::digital::pins::*;
+
+#[cfg(board = "teensy_30")]
+pub use crate::hw::board::teensy_30::digital::pins::*;
+
+#[cfg(board = "teensy_32")]
+pub use crate::hw::board::teensy_32::digital::pins::*;
+
+#[cfg(board = "teensy_35")]
+pub use crate::hw::board::teensy_35::digital::pins::*;
+
+#[cfg(board = "teensy_36")]
+pub use crate::hw::board::teensy_36::digital::pins::*;
+
+#[cfg(board = "teensy_40")]
+pub use crate::hw::board::teensy_40::digital::pins::*;
+
+#[cfg(board = "teensy_41")]
+pub use crate::hw::board::teensy_41::digital::pins::*;
+
+#[cfg(board = "teensy_lc")]
+pub use crate::hw::board::teensy_lc::digital::pins::*;
+
+/// A board pin, as a type
+pub trait BoardPin {
+    /// The number of the pin
+    const NUMBER: usize;
+}
+
+/// A pin which can be a hardware chip select for an SPI port
+///
+/// `SPI` is the index of the SPI port, so `spi_1()` takes the pins
+/// which implement `HardwareCs<0>`.
+#[rustc_on_unimplemented(
+    message = "`{Self}` cannot be a hardware chip select for this SPI port",
+    label = "not a hardware chip select for this SPI port",
+    note = "`Spi::hardware_cs_pins()` lists the pins which can be"
+)]
+pub trait HardwareCs<const SPI: usize>: BoardPin {}
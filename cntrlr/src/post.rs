@@ -0,0 +1,271 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Power-on self-test
+//!
+//! A production device can check its hardware each time it starts,
+//! before the application runs. After the board is initialized, and
+//! before interrupts are enabled and the main task starts, the
+//! runtime calls `__cntrlr_post`. By default this does nothing. An
+//! application which defines it can run any of the tests here, and
+//! [`fail`] if one of them does.
+//!
+//! ```
+//! use cntrlr::post;
+//!
+//! #[no_mangle]
+//! pub extern "C" fn __cntrlr_post() {
+//!     static mut SCRATCH: [usize; 64] = [0; 64];
+//!
+//!     // Safety: this is the only use of SCRATCH
+//!     let scratch = unsafe { &mut SCRATCH };
+//!     if let Err(err) = post::run(scratch, 50) {
+//!         post::fail(err);
+//!     }
+//! }
+//! ```
+//!
+//! The flash test checks the image against a length and CRC stored
+//! in the image itself. `cargo cntrlr` writes these after each build.
+
+use crate::runtime::{halt, Fault};
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+use crate::time::measure_core_clock;
+use core::ptr::{read_volatile, write_volatile};
+
+/// The length and CRC of the flash image
+#[repr(C)]
+struct ImageInfo {
+    length: u32,
+    crc: u32,
+}
+
+/// The image info, filled in by `cargo cntrlr` after the image is linked
+///
+/// The link scripts place this just past the end of the image.
+#[link_section = ".__CNTRLR_IMAGE_INFO"]
+#[export_name = "__cntrlr_image_info"]
+static IMAGE_INFO: ImageInfo = ImageInfo { length: 0, crc: 0 };
+
+/// An error from a self-test
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PostError {
+    /// The RAM test failed because a word did not hold the value written to it
+    Ram {
+        /// The address of the word
+        address: usize,
+
+        /// The value written to the word
+        expected: usize,
+
+        /// The value read back from the word
+        actual: usize,
+    },
+
+    /// The flash test failed because `cargo cntrlr` did not record the image's length and CRC
+    Unstamped,
+
+    /// The flash test failed because the image's CRC does not match the one recorded
+    Flash {
+        /// The recorded CRC
+        expected: u32,
+
+        /// The CRC of the image in flash
+        actual: u32,
+    },
+
+    /// The clock test failed because the core clock is too far from its configured frequency
+    Clock {
+        /// The configured core clock, in Hz
+        expected: usize,
+
+        /// The measured core clock, in Hz
+        measured: usize,
+    },
+}
+
+impl PostError {
+    /// The fault which [`fail`] reports for this error
+    pub fn fault(&self) -> Fault {
+        match self {
+            PostError::Ram { .. } => Fault::RamTest,
+            PostError::Unstamped | PostError::Flash { .. } => Fault::FlashTest,
+            PostError::Clock { .. } => Fault::ClockTest,
+        }
+    }
+}
+
+/// Test a region of RAM
+///
+/// Each word is written and read back with alternating bit patterns,
+/// and then with its own address, which catches stuck bits, shorted
+/// neighbouring bits, and shorted address lines within the region.
+/// The region is left zeroed.
+pub fn check_ram(region: &mut [usize]) -> Result<(), PostError> {
+    let patterns: [fn(usize) -> usize; 3] = [
+        |_| 0x5555_5555_5555_5555_u64 as usize,
+        |_| 0xAAAA_AAAA_AAAA_AAAA_u64 as usize,
+        |address| address,
+    ];
+
+    for pattern in &patterns {
+        for word in region.iter_mut() {
+            let address = word as *mut usize;
+            unsafe { write_volatile(address, pattern(address as usize)) };
+        }
+        for word in region.iter_mut() {
+            let address = word as *mut usize;
+            let expected = pattern(address as usize);
+            let actual = unsafe { read_volatile(address) };
+            if actual != expected {
+                return Err(PostError::Ram {
+                    address: address as usize,
+                    expected,
+                    actual,
+                });
+            }
+        }
+    }
+
+    for word in region.iter_mut() {
+        unsafe { write_volatile(word, 0) };
+    }
+    Ok(())
+}
+
+/// Test the flash image against its recorded CRC-32
+pub fn check_flash() -> Result<(), PostError> {
+    extern "C" {
+        static __cntrlr_image_start: u8;
+    }
+
+    unsafe {
+        let info = &IMAGE_INFO as *const ImageInfo;
+        let length = read_volatile(&(*info).length) as usize;
+        let expected = read_volatile(&(*info).crc);
+        let start = &__cntrlr_image_start as *const u8 as usize;
+        if length == 0 || length != info as usize - start {
+            return Err(PostError::Unstamped);
+        }
+
+        // The image may start at address zero, so it is read byte by
+        // byte rather than as a slice.
+        let mut crc: u32 = 0xFFFF_FFFF;
+        for address in start..start + length {
+            crc ^= read_volatile(address as *const u8) as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 {
+                    (crc >> 1) ^ 0xEDB8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        let actual = !crc;
+
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(PostError::Flash { expected, actual })
+        }
+    }
+}
+
+/// Test that the core clock is within `tolerance` percent of its configured frequency
+///
+/// See [`measure_core_clock`] for how the clock is measured, and how
+/// accurate the measurement is. If the board cannot measure its
+/// clock, the test passes.
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    )))
+)]
+pub fn check_clock(tolerance: usize) -> Result<(), PostError> {
+    let (expected, measured) = match measure_core_clock() {
+        Some(clock) => clock,
+        None => return Ok(()),
+    };
+    let error = if measured > expected {
+        measured - expected
+    } else {
+        expected - measured
+    };
+    if error as u64 * 100 > expected as u64 * tolerance as u64 {
+        Err(PostError::Clock { expected, measured })
+    } else {
+        Ok(())
+    }
+}
+
+/// Run every self-test
+///
+/// `scratch` is used for the RAM test, and the clock test allows
+/// `tolerance` percent of error.
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    )))
+)]
+pub fn run(scratch: &mut [usize], tolerance: usize) -> Result<(), PostError> {
+    check_ram(scratch)?;
+    check_flash()?;
+    check_clock(tolerance)
+}
+
+/// Halt the board after a failed self-test
+///
+/// The failure is reported with the blink code of
+/// [`PostError::fault()`].
+pub fn fail(err: PostError) -> ! {
+    halt(err.fault())
+}
@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Remote procedure calls from a host
+//!
+//! A [`Server`] runs async handlers on request from a host, such as
+//! a test harness or `cntrlr-host` tool. Each handler is registered
+//! under a 16-bit ID, and takes and returns an opaque byte
+//! payload. The `cntrlr-host` crate provides the matching client.
+//!
+//! # Wire format
+//!
+//! Each message is a [`Cobs`] frame containing the message followed
+//! by its little-endian CRC-32. A request is:
+//!
+//! | Bytes | Contents                        |
+//! |-------|---------------------------------|
+//! | 0     | Sequence number, chosen by host |
+//! | 1-2   | Handler ID, little-endian       |
+//! | 3..   | Request payload                 |
+//!
+//! Each request receives one response:
+//!
+//! | Bytes | Contents                         |
+//! |-------|----------------------------------|
+//! | 0     | Sequence number from the request |
+//! | 1     | [Status](Status)                 |
+//! | 2..   | Response payload                 |
+//!
+//! Requests which fail their CRC are dropped without a response.
+
+use crate::io::{
+    codec::{Checked, Cobs, CrcKind},
+    Error, Framed, FramedError, Read, Write,
+};
+use alloc::{boxed::Box, format, vec::Vec};
+use core::{future::Future, ops::DerefMut, pin::Pin};
+
+/// The largest request or response payload, in bytes
+pub const MAX_PAYLOAD: usize = 1024;
+
+/// The status of a response
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+#[non_exhaustive]
+pub enum Status {
+    /// The handler succeeded, and the payload is its response
+    Ok = 0,
+
+    /// No handler is registered with the requested ID
+    UnknownHandler = 1,
+
+    /// The handler failed, and the payload describes its error
+    HandlerFailed = 2,
+
+    /// The request was too short to contain a handler ID
+    Malformed = 3,
+}
+
+/// An error from an RPC [`Server`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RpcError {
+    /// The handler cannot be registered because its ID is in use
+    IdInUse,
+}
+
+type Handler = Box<dyn FnMut(Vec<u8>) -> Pin<Box<dyn Future<Output = Result<Vec<u8>, Error>>>>>;
+
+/// A server for remote procedure calls
+///
+/// The server is held through a [`DerefMut`], in the same way as
+/// [`Framed`].
+///
+/// ```
+/// use cntrlr::{prelude::*, protocol::rpc::Server};
+///
+/// #[entry]
+/// async fn main() -> ! {
+///     serial_1().await.enable(115200).unwrap();
+///     let mut server = Server::new(serial_1().await);
+///     server
+///         .register(1, |payload| async move { Ok(payload) })
+///         .unwrap();
+///     server.serve().await.unwrap()
+/// }
+/// ```
+pub struct Server<T> {
+    framed: Framed<T, Checked<Cobs>>,
+    handlers: Vec<(u16, Handler)>,
+}
+
+impl<T> Server<T> {
+    /// Create a server on a byte stream
+    pub fn new(inner: T) -> Self {
+        Self {
+            framed: Framed::new(
+                inner,
+                Checked::new(Cobs::new(MAX_PAYLOAD + 3 + 4), CrcKind::Crc32),
+            ),
+            handlers: Vec::new(),
+        }
+    }
+
+    /// Register a handler
+    ///
+    /// The handler is called with the payload of each request for
+    /// `id`. An error from the handler is reported to the host, and
+    /// does not stop the server.
+    pub fn register<F, Fut>(&mut self, id: u16, mut handler: F) -> Result<(), RpcError>
+    where
+        F: FnMut(Vec<u8>) -> Fut + 'static,
+        Fut: Future<Output = Result<Vec<u8>, Error>> + 'static,
+    {
+        if self
+            .handlers
+            .iter()
+            .any(|(handler_id, _)| *handler_id == id)
+        {
+            return Err(RpcError::IdInUse);
+        }
+        self.handlers
+            .push((id, Box::new(move |payload| Box::pin(handler(payload)))));
+        Ok(())
+    }
+
+    /// Unwrap the byte stream
+    pub fn into_inner(self) -> T {
+        self.framed.into_inner()
+    }
+}
+
+impl<T> Server<T>
+where
+    T: DerefMut,
+    T::Target: Read + Write,
+    <T::Target as Read>::Error: Into<Error>,
+    <T::Target as Write>::Error: Into<Error>,
+{
+    /// Answer requests until the byte stream fails
+    ///
+    /// Handlers are run one at a time, in the order their requests
+    /// are received.
+    pub fn serve(&mut self) -> impl Future<Output = Result<!, Error>> + '_ {
+        async move {
+            loop {
+                let request = match self.framed.read_frame().await {
+                    Ok(request) => request,
+                    Err(FramedError::Io(err)) => return Err(err.into()),
+                    Err(FramedError::Codec(_)) => continue,
+                };
+                let seq = match request.first() {
+                    Some(&seq) => seq,
+                    None => continue,
+                };
+
+                let (status, payload) = if request.len() < 3 {
+                    (Status::Malformed, Vec::new())
+                } else {
+                    let id = u16::from_le_bytes([request[1], request[2]]);
+                    match self
+                        .handlers
+                        .iter_mut()
+                        .find(|(handler_id, _)| *handler_id == id)
+                    {
+                        Some((_, handler)) => match handler(request[3..].to_vec()).await {
+                            Ok(payload) if payload.len() <= MAX_PAYLOAD => (Status::Ok, payload),
+                            Ok(_) => (Status::HandlerFailed, b"response too long".to_vec()),
+                            Err(err) => (
+                                Status::HandlerFailed,
+                                format!("{:?}", err.kind()).into_bytes(),
+                            ),
+                        },
+                        None => (Status::UnknownHandler, Vec::new()),
+                    }
+                };
+
+                let mut response = Vec::with_capacity(payload.len() + 2);
+                response.push(seq);
+                response.push(status as u8);
+                response.extend_from_slice(&payload);
+                self.framed
+                    .write_frame(&response)
+                    .await
+                    .map_err(Error::from)?;
+            }
+        }
+    }
+}
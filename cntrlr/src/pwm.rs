@@ -0,0 +1,455 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! PWM output for Cntrlr boards
+//!
+//! [`analog_write`] sets the duty cycle of a pin's hardware PWM
+//! output, like Arduino's `analogWrite`. For more control, the
+//! output can be taken with [`pwm_pin`], and driven through the
+//! [`Pwm`] trait.
+//!
+//! ```
+//! use cntrlr::prelude::*;
+//!
+//! // Drive pin 3 high for half of each period
+//! analog_write(3, 128).expect("Failed to write pin");
+//! ```
+//!
+//! Each PWM-capable pin is driven by one channel of a timer, and all
+//! of the channels on a timer share one frequency. This module tracks
+//! which pins share each timer, so that configuring one pin cannot
+//! silently change the frequency of another.
+//!
+//! [`claim`] reserves a pin's channel at a frequency, and fails if
+//! another pin on the same timer is running at a different
+//! frequency. [`pwm_channels`] and [`shared_pins`] can be used to
+//! plan pin assignments ahead of time.
+//!
+//! On the Teensy boards, [`soft_pwm_write`] drives any digital pin
+//! from a timer interrupt instead, at a lower frequency.
+//!
+//! ```
+//! use cntrlr::pwm;
+//!
+//! // Pins 5 and 6 share a timer on the Teensy 3.x
+//! pwm::claim(5, 1000).expect("Failed to claim pin 5");
+//! assert!(pwm::claim(6, 20_000).is_err());
+//! pwm::release(5);
+//! pwm::claim(6, 20_000).expect("Failed to claim pin 6");
+//! ```
+
+use crate::sync::{without_interrupts, Value};
+use cntrlr_macros::board_fn;
+use core::{fmt::Debug, sync::atomic::Ordering};
+
+/// The default frequency of [`analog_write`], in Hz
+pub const DEFAULT_ANALOG_WRITE_FREQUENCY: usize = 488;
+
+/// Trait for hardware PWM outputs
+///
+/// The output is high for the first part of each period, and low for
+/// the rest. Every output on a timer shares the timer's frequency,
+/// which is checked through [`claim`].
+pub trait Pwm {
+    /// The error type
+    type Error: Debug;
+
+    /// Start the output at `frequency` Hz
+    ///
+    /// The output's channel is claimed, so this fails if another
+    /// output on the same timer is running at a different
+    /// frequency. An output which is already enabled keeps its duty
+    /// cycle, though that is now a different fraction of the period
+    /// if the frequency changed.
+    fn enable(&mut self, frequency: usize) -> Result<(), Self::Error>;
+
+    /// Stop the output, and release its channel
+    fn disable(&mut self) -> Result<(), Self::Error>;
+
+    /// The duty cycle which holds the output high
+    ///
+    /// This is the number of timer counts in each period, so it
+    /// depends on the frequency. It is zero while the output is
+    /// disabled.
+    fn max_duty(&self) -> usize;
+
+    /// Set the duty cycle
+    ///
+    /// The output is high for `duty` out of every
+    /// [`max_duty()`](Self::max_duty) counts. The new duty cycle
+    /// takes effect at the start of the next period.
+    fn set_duty(&mut self, duty: usize) -> Result<(), Self::Error>;
+}
+
+/// A pin's PWM timer and channel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PwmChannel {
+    /// The board pin number
+    pub pin: usize,
+
+    /// The index of the timer which drives this pin
+    ///
+    /// Timers are numbered from 0 on each board, in the order of the
+    /// MCU's timer modules.
+    pub timer: usize,
+
+    /// The channel of the timer which drives this pin
+    pub channel: usize,
+}
+
+impl PwmChannel {
+    #[cfg(any(
+        doc,
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_lc"
+    ))]
+    pub(crate) const fn new(pin: usize, timer: usize, channel: usize) -> Self {
+        Self {
+            pin,
+            timer,
+            channel,
+        }
+    }
+}
+
+/// An error from claiming a PWM channel
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PwmError {
+    /// The channel cannot be claimed because the pin has no PWM output
+    InvalidPin,
+
+    /// The pin cannot be driven because it is in use
+    PinInUse,
+
+    /// The duty cycle cannot be set because the output is not enabled
+    NotEnabled,
+
+    /// The frequency cannot be set because it is zero, or out of the timer's range
+    InvalidFrequency,
+
+    /// The channel cannot be claimed because its timer is running at another frequency, which is given
+    FrequencyConflict(usize),
+
+    /// The resolution cannot be set because it is not between 1 and 16 bits
+    InvalidResolution,
+
+    /// The pin cannot be driven because the software PWM is driving as many pins as it can
+    TooManyPins,
+
+    /// The pin cannot be driven because its timer, or the software PWM timer, is in use
+    TimerUnavailable,
+}
+
+/// Every PWM-capable pin on this board
+#[board_fn(pwm, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn pwm_channels() -> &'static [PwmChannel] {}
+
+/// The timer and channel which drive a pin
+///
+/// Returns `None` if the pin has no PWM output.
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_lc"
+    )))
+)]
+pub fn pwm_channel(pin: usize) -> Option<PwmChannel> {
+    pwm_channels()
+        .iter()
+        .copied()
+        .find(|channel| channel.pin == pin)
+}
+
+/// The pins which share a timer with `pin`, not including `pin`
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_lc"
+    )))
+)]
+pub fn shared_pins(pin: usize) -> impl Iterator<Item = usize> {
+    let timer = pwm_channel(pin).map(|channel| channel.timer);
+    pwm_channels()
+        .iter()
+        .filter(move |channel| Some(channel.timer) == timer && channel.pin != pin)
+        .map(|channel| channel.pin)
+}
+
+/// The frequency of a timer, in Hz
+///
+/// Returns `None` if no channel of the timer is claimed.
+pub fn timer_frequency(timer: usize) -> Option<usize> {
+    let state = TIMERS.get(timer)?;
+    without_interrupts(|| {
+        if state.channels.load(Ordering::Relaxed) == 0 {
+            None
+        } else {
+            Some(state.frequency.load(Ordering::Relaxed))
+        }
+    })
+}
+
+/// Claim a pin's PWM channel at a frequency, in Hz
+///
+/// If the pin's timer has other claimed channels, `frequency` must
+/// match theirs. A pin which is already claimed can be claimed again
+/// to change its frequency, if it is the only claimed channel on its
+/// timer.
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_lc"
+    )))
+)]
+pub fn claim(pin: usize, frequency: usize) -> Result<PwmChannel, PwmError> {
+    let channel = pwm_channel(pin).ok_or(PwmError::InvalidPin)?;
+    if frequency == 0 {
+        return Err(PwmError::InvalidFrequency);
+    }
+
+    let state = &TIMERS[channel.timer];
+    without_interrupts(|| {
+        let channels = state.channels.load(Ordering::Relaxed);
+        let others = channels & !(1 << channel.channel);
+        let current = state.frequency.load(Ordering::Relaxed);
+        if others != 0 && current != frequency {
+            return Err(PwmError::FrequencyConflict(current));
+        }
+        state.frequency.store(frequency, Ordering::Relaxed);
+        state
+            .channels
+            .store(channels | (1 << channel.channel), Ordering::Relaxed);
+        Ok(channel)
+    })
+}
+
+/// Release a pin's PWM channel
+///
+/// Once every channel of a timer is released, the timer can be
+/// claimed at any frequency. Does nothing if the pin is not claimed.
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_lc"
+    )))
+)]
+pub fn release(pin: usize) {
+    if let Some(channel) = pwm_channel(pin) {
+        let state = &TIMERS[channel.timer];
+        without_interrupts(|| {
+            let channels = state.channels.load(Ordering::Relaxed);
+            state
+                .channels
+                .store(channels & !(1 << channel.channel), Ordering::Relaxed);
+        });
+    }
+}
+
+/// Get the hardware PWM output of a pin
+///
+/// The output must be [enabled](Pwm::enable) before it is used. It
+/// keeps running when it is dropped, until it is disabled, in the
+/// same way as [`analog_write`]. Each timer's frequency is derived
+/// from the board clock when an output is enabled, so outputs should
+/// be enabled again after the board clock is changed.
+///
+/// Returns [`PwmError::InvalidPin`] if the pin has no PWM output.
+#[board_fn(pwm, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn pwm_pin(pin: usize) -> Result<impl Pwm<Error = PwmError>, PwmError> {}
+
+/// Set the duty cycle of a pin's hardware PWM output
+///
+/// This is Arduino's `analogWrite`. `duty` is a fraction of
+/// `2^resolution`, where the resolution is 8 bits by default: a
+/// `duty` of zero holds the pin low, 128 is high for half of each
+/// period, and 256 or more holds the pin high.
+///
+/// The pin's channel is claimed at the frequency set with
+/// [`set_analog_write_frequency`], and stays claimed until it is
+/// released through [`pwm_pin`]. Writing a pin whose timer runs at a
+/// different frequency for another pin fails with
+/// [`PwmError::FrequencyConflict`].
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_lc"
+    )))
+)]
+pub fn analog_write(pin: usize, duty: usize) -> Result<(), PwmError> {
+    let mut output = pwm_pin(pin)?;
+    output.enable(ANALOG_WRITE_FREQUENCY.load(Ordering::Relaxed))?;
+    let bits = ANALOG_WRITE_RESOLUTION.load(Ordering::Relaxed);
+    let duty = duty.min(1 << bits) as u64 * output.max_duty() as u64 >> bits;
+    output.set_duty(duty as usize)
+}
+
+/// Set the frequency used by [`analog_write`], in Hz
+///
+/// The frequency is 488Hz by default. Pins which are already running
+/// keep their frequency until they are written again.
+pub fn set_analog_write_frequency(frequency: usize) -> Result<(), PwmError> {
+    if frequency == 0 {
+        return Err(PwmError::InvalidFrequency);
+    }
+    ANALOG_WRITE_FREQUENCY.store(frequency, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Set the resolution of [`analog_write`] duty cycles, in bits
+///
+/// The resolution is 8 bits by default, and can be from 1 to 16
+/// bits.
+pub fn set_analog_write_resolution(bits: u32) -> Result<(), PwmError> {
+    if !(1..=16).contains(&bits) {
+        return Err(PwmError::InvalidResolution);
+    }
+    ANALOG_WRITE_RESOLUTION.store(bits as usize, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Set the duty cycle of any digital pin, with software PWM
+///
+/// This drives pins which have no PWM channel, or whose timer is
+/// already claimed at another frequency. Every software PWM pin is
+/// driven from one timer interrupt, and shares one frequency and
+/// resolution. A `duty` of zero holds the pin low, and a `duty` of
+/// `2^resolution` or more holds it high.
+///
+/// The pin is set as a digital output the first time it is written,
+/// and the new duty cycle takes effect at the start of the next
+/// period. Up to 16 pins can be driven at once.
+///
+/// Edges are timed by an interrupt, so they jitter by the interrupt
+/// latency, and edges within about 4µs of each other are merged. This
+/// is fine for LEDs and most motors, but not for precisely timed
+/// signals. The schedule is computed from the bus clock when a pin
+/// or setting changes, so pins should be written again after the
+/// board clock is changed.
+#[board_fn(pwm, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn soft_pwm_write(pin: usize, duty: usize) -> Result<(), PwmError> {}
+
+/// Stop driving a pin with software PWM
+///
+/// The pin is left as a digital output, driven low. Does nothing if
+/// the pin is not driven with software PWM.
+#[board_fn(pwm, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn soft_pwm_release(pin: usize) {}
+
+/// Set the frequency of every software PWM pin, in Hz
+///
+/// The frequency is 500Hz by default, and can be at most 1kHz.
+#[board_fn(pwm, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn set_soft_pwm_frequency(frequency: usize) -> Result<(), PwmError> {}
+
+/// Set the resolution of software PWM duty cycles, in bits
+///
+/// The resolution is 8 bits by default, and can be from 1 to 16
+/// bits. Changing the resolution changes the meaning of the duty
+/// cycles which were already written.
+#[board_fn(pwm, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn set_soft_pwm_resolution(bits: u32) -> Result<(), PwmError> {}
+
+static ANALOG_WRITE_FREQUENCY: Value = Value::new(DEFAULT_ANALOG_WRITE_FREQUENCY);
+static ANALOG_WRITE_RESOLUTION: Value = Value::new(8);
+
+struct TimerState {
+    frequency: Value,
+    channels: Value,
+}
+
+impl TimerState {
+    const fn new() -> Self {
+        Self {
+            frequency: Value::new(0),
+            channels: Value::new(0),
+        }
+    }
+}
+
+/// The allocation state of each timer
+///
+/// This is large enough for every supported board. The Teensy 3.6
+/// has the most PWM timers, with four FTMs and one TPM.
+static TIMERS: [TimerState; 5] = [
+    TimerState::new(),
+    TimerState::new(),
+    TimerState::new(),
+    TimerState::new(),
+    TimerState::new(),
+];
@@ -1,20 +1,630 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 // Copyright 2020 Branan Riley <me@branan.info>
 
-use crate::sync::enable_interrupts;
-use core::panic::PanicInfo;
+//! The Cntrlr runtime
+//!
+//! This sets up memory and the board before the application's main
+//! task is started.
+//!
+//! When the board halts after a fault, the fault is logged in RAM
+//! which is not cleared at startup, and the next run of the program
+//! can read it with [`last_crash`]. A device in the field can then
+//! report why it stopped, such as after the watchdog resets it.
+//!
+//! On the ARM boards, a fault exception calls `__cntrlr_fault` with
+//! the [registers](FaultRegisters) which describe it, and then halts.
+//! By default this does nothing. An application which defines it
+//! can report the fault, or reboot instead of halting.
+//!
+//! ```
+//! use cntrlr::runtime::FaultRegisters;
+//!
+//! #[no_mangle]
+//! pub extern "C" fn __cntrlr_fault(registers: &FaultRegisters) {
+//!     // Record or report the faulting instruction
+//!     let _pc = registers.pc;
+//! }
+//! ```
 
-#[panic_handler]
-fn panic(_: &PanicInfo) -> ! {
-    loop {
-        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+use crate::sync::{enable_interrupts, without_interrupts, Value};
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+use crate::{digital::led_write, time::delay_us_blocking};
+use cntrlr_macros::board_fn;
+use core::{
+    fmt::{self, Write},
+    mem::MaybeUninit,
+    panic::PanicInfo,
+    ptr::read_volatile,
+    str,
+    sync::atomic::{compiler_fence, Ordering},
+};
+
+static FAULT_BLINK: Value = Value::new(1);
+static FAULT: Value = Value::new(0);
+static PANIC_SERIAL: Value = Value::new(0);
+
+/// The class of fault which stopped the program
+///
+/// After a fault, the built-in LED repeatedly flashes the fault's
+/// [blink code](Self::blink_code), so that a board without a serial
+/// console can still say why it stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Fault {
+    /// A panic in application or library code
+    Panic,
+
+    /// A memory allocation failed
+    OutOfMemory,
+
+    /// The CPU raised an exception, such as an illegal instruction
+    /// or a misaligned access
+    Exception,
+
+    /// The CPU raised an exception which it could not handle
+    ///
+    /// On ARM, this is a HardFault, or a MemManage fault, BusFault,
+    /// or UsageFault on the cores which have them.
+    HardFault,
+
+    /// The [RAM self-test](crate::post::check_ram) failed
+    RamTest,
+
+    /// The [flash self-test](crate::post::check_flash) failed
+    FlashTest,
+
+    /// The [clock self-test](crate::post::check_clock) failed
+    ClockTest,
+}
+
+impl Fault {
+    /// The number of long and short flashes which identify this fault
+    ///
+    /// | Fault           | Long | Short |
+    /// |-----------------|------|-------|
+    /// | `Panic`         | 1    | 1     |
+    /// | `OutOfMemory`   | 1    | 2     |
+    /// | `Exception`     | 2    | 1     |
+    /// | `HardFault`     | 2    | 2     |
+    /// | `RamTest`       | 3    | 1     |
+    /// | `FlashTest`     | 3    | 2     |
+    /// | `ClockTest`     | 3    | 3     |
+    pub const fn blink_code(self) -> (usize, usize) {
+        match self {
+            Fault::Panic => (1, 1),
+            Fault::OutOfMemory => (1, 2),
+            Fault::Exception => (2, 1),
+            Fault::HardFault => (2, 2),
+            Fault::RamTest => (3, 1),
+            Fault::FlashTest => (3, 2),
+            Fault::ClockTest => (3, 3),
+        }
+    }
+
+    fn from_usize(fault: usize) -> Self {
+        match fault {
+            1 => Fault::OutOfMemory,
+            2 => Fault::Exception,
+            3 => Fault::HardFault,
+            4 => Fault::RamTest,
+            5 => Fault::FlashTest,
+            6 => Fault::ClockTest,
+            _ => Fault::Panic,
+        }
+    }
+}
+
+/// The fault which stopped the previous run of the program
+///
+/// See [`last_crash`].
+#[derive(Clone)]
+pub struct Crash {
+    fault: Fault,
+    registers: Option<FaultRegisters>,
+    message: [u8; CRASH_MESSAGE_LEN],
+    len: usize,
+}
+
+impl Crash {
+    /// The class of fault
+    pub fn fault(&self) -> Fault {
+        self.fault
+    }
+
+    /// The panic message and its location
+    ///
+    /// This is truncated to 128 bytes, and is empty if the fault was
+    /// not a panic.
+    pub fn message(&self) -> &str {
+        str::from_utf8(&self.message[..self.len]).unwrap_or("")
+    }
+
+    /// The registers which describe the fault, if it was an ARM
+    /// fault exception
+    pub fn registers(&self) -> Option<FaultRegisters> {
+        self.registers
+    }
+}
+
+impl fmt::Debug for Crash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Crash")
+            .field("fault", &self.fault)
+            .field("registers", &self.registers)
+            .field("message", &self.message())
+            .finish()
+    }
+}
+
+/// The registers which describe an ARM fault exception
+///
+/// The program counter, link register, and status register are the
+/// ones saved on the stack when the exception was taken. The fault
+/// status registers are not available on the Teensy LC, whose
+/// Cortex-M0+ does not have them, and are zero there.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FaultRegisters {
+    /// The exception number
+    ///
+    /// This is 3 for a HardFault, 4 for a MemManage fault, 5 for a
+    /// BusFault, and 6 for a UsageFault. The Teensy LC only has
+    /// HardFaults.
+    pub exception: u32,
+
+    /// The address of the instruction which faulted
+    pub pc: u32,
+
+    /// The link register of the code which faulted
+    pub lr: u32,
+
+    /// The program status register of the code which faulted
+    pub psr: u32,
+
+    /// The Configurable Fault Status Register
+    pub cfsr: u32,
+
+    /// The HardFault Status Register
+    pub hfsr: u32,
+
+    /// The MemManage Fault Address Register
+    ///
+    /// This is only valid when bit 7 of `cfsr` is set.
+    pub mmfar: u32,
+
+    /// The BusFault Address Register
+    ///
+    /// This is only valid when bit 15 of `cfsr` is set.
+    pub bfar: u32,
+}
+
+impl FaultRegisters {
+    fn to_words(self) -> [u32; 8] {
+        [
+            self.exception,
+            self.pc,
+            self.lr,
+            self.psr,
+            self.cfsr,
+            self.hfsr,
+            self.mmfar,
+            self.bfar,
+        ]
+    }
+
+    fn from_words(words: [u32; 8]) -> Self {
+        Self {
+            exception: words[0],
+            pc: words[1],
+            lr: words[2],
+            psr: words[3],
+            cfsr: words[4],
+            hfsr: words[5],
+            mmfar: words[6],
+            bfar: words[7],
+        }
+    }
+}
+
+/// Get the fault which stopped the previous run of the program
+///
+/// Returns `None` if the previous run did not halt after a fault.
+/// The log is kept in RAM, so it survives a reset by the watchdog
+/// or the reset button, but not a loss of power.
+pub fn last_crash() -> Option<Crash> {
+    // Safety: LAST_CRASH is only written at startup
+    let log = unsafe { &LAST_CRASH };
+    if log.magic != CRASH_MAGIC {
+        return None;
+    }
+    let registers = if log.has_registers != 0 {
+        Some(FaultRegisters::from_words(log.registers))
+    } else {
+        None
+    };
+    Some(Crash {
+        fault: Fault::from_usize(log.fault as usize),
+        registers,
+        message: log.message,
+        len: (log.len as usize).min(CRASH_MESSAGE_LEN),
+    })
+}
+
+/// Enable or disable blink codes after a fault
+///
+/// Blink codes are enabled by default. When they are disabled, a
+/// fault silently halts the board.
+pub fn set_fault_blink(enabled: bool) {
+    FAULT_BLINK.store(enabled as usize, Ordering::Relaxed);
+}
+
+/// Report panics on a hardware serial port
+///
+/// When a port is set, the panic handler writes the panic's message
+/// and location to it before halting, so `set_panic_serial(1)`
+/// reports panics on [`serial_1`](crate::io::serial_1). The port
+/// must already be enabled, and is written by busy-waiting with
+/// interrupts disabled, even if a task holds it. Serial ports are
+/// numbered from 1, so port 0 turns reporting off again.
+///
+/// By default no port is set, and a panic halts without any output.
+pub fn set_panic_serial(port: usize) {
+    PANIC_SERIAL.store(port, Ordering::Relaxed);
+}
+
+/// Record the class of a fault which is about to panic
+pub(crate) fn set_fault(fault: Fault) {
+    FAULT.store(fault as usize, Ordering::Relaxed);
+}
+
+/// Halt the board after a fault
+///
+/// If blink codes are enabled, this flashes the fault's blink code
+/// on the built-in LED forever. Interrupts are disabled and the
+/// flashes are timed with [`delay_us_blocking`], so this works even
+/// when the fault was in an interrupt handler or the timer.
+pub fn halt(fault: Fault) -> ! {
+    without_interrupts(|| {
+        unsafe {
+            seal_crash_log(fault);
+        }
+        let (long, short) = fault.blink_code();
+        loop {
+            if FAULT_BLINK.load(Ordering::Relaxed) == 0 {
+                compiler_fence(Ordering::SeqCst);
+                continue;
+            }
+            flash_code(long, short);
+        }
+    })
+}
+
+/// Flash a blink code once on the built-in LED
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+fn flash_code(long: usize, short: usize) {
+    for _ in 0..long {
+        blink(600_000, 300_000);
+    }
+    for _ in 0..short {
+        blink(150_000, 300_000);
+    }
+    delay_us_blocking(1_500_000);
+}
+
+/// Without a board there is no LED to flash
+#[cfg(not(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+)))]
+fn flash_code(_long: usize, _short: usize) {}
+
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+fn blink(on: usize, off: usize) {
+    led_write(true);
+    delay_us_blocking(on);
+    led_write(false);
+    delay_us_blocking(off);
+}
+
+#[cfg_attr(not(test), panic_handler)]
+fn panic(info: &PanicInfo) -> ! {
+    without_interrupts(|| unsafe {
+        let log = crash_log();
+        log.has_registers = 0;
+        log.len = 0;
+        let _ = write!(log, "{}", info);
+    });
+    #[cfg(feature = "blackbox")]
+    crate::blackbox::record(format_args!("{}", info));
+    #[cfg(any(
+        doc,
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    ))]
+    {
+        let port = PANIC_SERIAL.load(Ordering::Relaxed);
+        if port != 0 {
+            without_interrupts(|| {
+                let _ = write!(PanicSerial(port), "\r\n{}\r\n", info);
+            });
+        }
+    }
+    halt(Fault::from_usize(FAULT.load(Ordering::Relaxed)))
+}
+
+/// The serial port chosen with [`set_panic_serial`]
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+struct PanicSerial(usize);
+
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+impl Write for PanicSerial {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        panic_write(self.0, s.as_bytes());
+        Ok(())
     }
 }
 
+/// Write to a serial port from the panic handler
+#[board_fn(
+    io, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+)]
+fn panic_write(port: usize, bytes: &[u8]) {}
+
 /// Default interrupt handler
 #[allow(dead_code)]
 pub extern "C" fn unused_interrupt() {}
 
+/// Default ARM fault handler
+///
+/// This handles HardFaults, and MemManage faults, BusFaults, and
+/// UsageFaults on the cores which have them. It finds the registers
+/// which the core saved on the stack, which is the main stack unless
+/// the fault was in code using the process stack.
+///
+/// # Safety
+/// This function should never be called by user code; it is public
+/// only so that boards can put it in their exception tables.
+#[cfg_attr(target_arch = "arm", naked)]
+#[allow(dead_code)]
+pub unsafe extern "C" fn arm_fault() {
+    #[cfg(target_arch = "arm")]
+    asm!("
+        movs r0, #4
+        mov r1, lr
+        tst r0, r1
+        mrs r0, msp
+        beq 1f
+        mrs r0, psp
+    1:
+        bl {}",
+         sym handle_fault, options(noreturn)
+    );
+}
+
+#[allow(dead_code)]
+unsafe extern "C" fn handle_fault(frame: *const u32) -> ! {
+    extern "C" {
+        fn __cntrlr_fault(registers: &FaultRegisters);
+    }
+
+    #[allow(unused_mut)]
+    let mut registers = FaultRegisters {
+        exception: 0,
+        pc: read_volatile(frame.add(6)),
+        lr: read_volatile(frame.add(5)),
+        psr: read_volatile(frame.add(7)),
+        cfsr: 0,
+        hfsr: 0,
+        mmfar: 0,
+        bfar: 0,
+    };
+    #[cfg(target_arch = "arm")]
+    {
+        let ipsr: u32;
+        asm!("mrs {}, ipsr", out(reg) ipsr);
+        registers.exception = ipsr & 0x1FF;
+    }
+    #[cfg(all(target_arch = "arm", not(mcu = "mkl26z64")))]
+    {
+        registers.cfsr = read_volatile(0xE000_ED28 as *const u32);
+        registers.hfsr = read_volatile(0xE000_ED2C as *const u32);
+        registers.mmfar = read_volatile(0xE000_ED34 as *const u32);
+        registers.bfar = read_volatile(0xE000_ED38 as *const u32);
+    }
+
+    let log = crash_log();
+    log.registers = registers.to_words();
+    log.has_registers = 1;
+    log.len = 0;
+    __cntrlr_fault(&registers);
+    halt(Fault::HardFault)
+}
+
+/// The default fault hook
+///
+/// This does nothing, so the board halts. See [`crate::runtime`] for
+/// how to replace it.
+#[no_mangle]
+pub extern "C" fn __cntrlr_default_fault(_registers: &FaultRegisters) {}
+
+/// Enable the MemManage, BusFault, and UsageFault exceptions
+///
+/// Otherwise, these faults are escalated to HardFaults.
+unsafe fn enable_faults() {
+    #[cfg(all(target_arch = "arm", not(mcu = "mkl26z64")))]
+    {
+        let shcsr = 0xE000_ED24 as *mut u32;
+        core::ptr::write_volatile(shcsr, read_volatile(shcsr) | 0x7_0000);
+    }
+}
+
+/// The number of bytes of a panic message kept in the crash log
+const CRASH_MESSAGE_LEN: usize = 128;
+
+/// Marks a crash log which was completed by [`halt`]
+const CRASH_MAGIC: u32 = 0xC7A5_4106;
+
+/// The crash log, as it is kept in RAM
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct CrashLog {
+    magic: u32,
+    checksum: u32,
+    fault: u32,
+    has_registers: u32,
+    registers: [u32; 8],
+    len: u32,
+    message: [u8; CRASH_MESSAGE_LEN],
+}
+
+impl CrashLog {
+    const fn new() -> Self {
+        Self {
+            magic: 0,
+            checksum: 0,
+            fault: 0,
+            has_registers: 0,
+            registers: [0; 8],
+            len: 0,
+            message: [0; CRASH_MESSAGE_LEN],
+        }
+    }
+
+    /// Check everything after the checksum
+    ///
+    /// RAM holds random values after power-on, which could happen to
+    /// include the magic number.
+    fn checksum(&self) -> u32 {
+        let words = [self.fault, self.has_registers, self.len];
+        words
+            .iter()
+            .chain(self.registers.iter())
+            .copied()
+            .chain(self.message.iter().map(|&byte| byte as u32))
+            .fold(0xFFFF_FFFF, |sum, word| sum.rotate_left(5) ^ word)
+    }
+}
+
+impl Write for CrashLog {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let start = (self.len as usize).min(CRASH_MESSAGE_LEN);
+        let mut len = s.len().min(CRASH_MESSAGE_LEN - start);
+        // Only whole characters are kept, so the message stays UTF-8
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.message[start..start + len].copy_from_slice(&s.as_bytes()[..len]);
+        self.len = (start + len) as u32;
+        Ok(())
+    }
+}
+
+/// The crash log of this run
+///
+/// This is not initialized at startup, so that it survives a reset.
+#[link_section = ".noinit"]
+static mut CRASH_LOG: MaybeUninit<CrashLog> = MaybeUninit::uninit();
+
+/// The crash log of the previous run, read at startup
+static mut LAST_CRASH: CrashLog = CrashLog::new();
+
+/// Get the crash log of this run
+///
+/// # Safety
+/// Interrupts must be disabled, or the caller must otherwise be the
+/// only user of the log.
+unsafe fn crash_log() -> &'static mut CrashLog {
+    &mut *CRASH_LOG.as_mut_ptr()
+}
+
+/// Complete the crash log, marking it valid for the next run
+///
+/// # Safety
+/// See [`crash_log`].
+unsafe fn seal_crash_log(fault: Fault) {
+    let log = crash_log();
+    log.fault = fault as u32;
+    log.checksum = log.checksum();
+    log.magic = CRASH_MAGIC;
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Keep the previous run's crash log, and start a new one
+///
+/// # Safety
+/// This must be called once at startup, after `.bss` is initialized.
+unsafe fn load_crash_log() {
+    let log = crash_log();
+    if log.magic == CRASH_MAGIC && log.checksum == log.checksum() {
+        LAST_CRASH = *log;
+    }
+    *log = CrashLog::new();
+}
+
 /// The default reset vector
 ///
 /// This is the entrypoint used when a custom reset has not been
@@ -30,6 +640,7 @@ pub unsafe extern "C" fn __cntrlr_default_reset() -> ! {
         static mut __cntrlr_heap_start: u8;
         fn __cntrlr_main() -> !;
         fn __cntrlr_board_init();
+        fn __cntrlr_post();
         fn __cntrlr_board_start();
     }
     __cntrlr_board_start();
@@ -39,12 +650,23 @@ pub unsafe extern "C" fn __cntrlr_default_reset() -> ! {
         &__cntrlr_data_flash_start,
     );
     init_bss(&mut __cntrlr_bss_start, &mut __cntrlr_bss_end);
+    load_crash_log();
+    #[cfg(feature = "blackbox")]
+    crate::blackbox::load();
+    enable_faults();
     init_heap(&mut __cntrlr_heap_start);
     __cntrlr_board_init();
+    __cntrlr_post();
     enable_interrupts();
     __cntrlr_main();
 }
 
+/// The default power-on self-test
+///
+/// This does nothing. See [`crate::post`] for how to replace it.
+#[no_mangle]
+pub extern "C" fn __cntrlr_default_post() {}
+
 unsafe fn init_data(data: *mut u8, data_end: *mut u8, data_flash: *const u8) {
     let data_len = data_end as usize - data as usize;
     for i in 0..data_len {
@@ -59,6 +681,34 @@ unsafe fn init_bss(bss: *mut u8, bss_end: *mut u8) {
     }
 }
 
+#[cfg(feature = "allocator")]
 unsafe fn init_heap(brk: *mut u8) {
     crate::allocator::init(brk);
 }
+
+#[cfg(not(feature = "allocator"))]
+unsafe fn init_heap(_brk: *mut u8) {}
+
+/// The start of the memory available for the heap
+///
+/// With the default `allocator` feature, Cntrlr provides its own
+/// global allocator, which owns this memory. Applications which
+/// disable the feature must provide a `#[global_allocator]` and an
+/// `#[alloc_error_handler]`, and can use the memory from here up to
+/// the bottom of the stack.
+pub fn heap_start() -> *mut u8 {
+    extern "C" {
+        static mut __cntrlr_heap_start: u8;
+    }
+    unsafe { &mut __cntrlr_heap_start }
+}
+
+/// Reboot the board
+///
+/// This performs a full reset of the MCU.
+#[cfg(any(doc, board = "red_v"))]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(board = "red_v")))]
+pub fn reboot() -> ! {
+    #[cfg(board = "red_v")]
+    crate::hw::board::red_v::reboot()
+}
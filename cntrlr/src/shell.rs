@@ -0,0 +1,349 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! A command shell over a serial port
+//!
+//! A [`Shell`] reads lines from any byte stream, such as a serial
+//! port, and runs the command named by the first word of each. It
+//! echoes what is typed, handles backspace, and discards the line on
+//! Ctrl-C, so it can be used from any terminal program. A `help`
+//! command lists every registered command.
+//!
+//! [`Shell::add_debug_commands`] registers commands to read and write
+//! memory and to drive pins, for a debug console which needs no code
+//! of its own. [`Shell::add_i2c_scan`] and [`Shell::add_spi_loopback`]
+//! register the [bus diagnostics](crate::io::diag) for a bus.
+
+use crate::io::{
+    diag::{check_spi_loopback, scan_i2c},
+    Error, I2c, Read, ReadExt, Spi, Write, WriteExt,
+};
+use alloc::{boxed::Box, format, string::String, vec::Vec};
+use core::{future::Future, ops::DerefMut, pin::Pin};
+
+/// The longest line the shell accepts, in bytes
+pub const MAX_LINE: usize = 128;
+
+/// An error from a [`Shell`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ShellError {
+    /// The command cannot be registered because its name is in use
+    NameInUse,
+}
+
+type Handler = Box<dyn FnMut(Vec<String>) -> Pin<Box<dyn Future<Output = Result<String, String>>>>>;
+
+struct Command {
+    name: &'static str,
+    help: &'static str,
+    handler: Handler,
+}
+
+/// A command shell
+///
+/// The shell is held through a [`DerefMut`], in the same way as
+/// [`Framed`](crate::io::Framed).
+///
+/// ```
+/// use cntrlr::{prelude::*, shell::Shell};
+///
+/// #[entry]
+/// async fn main() -> ! {
+///     serial_1().await.enable(115200).unwrap();
+///     let mut shell = Shell::new(serial_1().await);
+///     shell.add_debug_commands();
+///     shell
+///         .register("uptime", "Print the milliseconds since boot", |_| async {
+///             Ok(format!("{}ms", millis()))
+///         })
+///         .unwrap();
+///     shell.run().await.unwrap()
+/// }
+/// ```
+pub struct Shell<T> {
+    inner: T,
+    prompt: &'static str,
+    commands: Vec<Command>,
+}
+
+impl<T> Shell<T> {
+    /// Create a shell on a byte stream
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            prompt: "> ",
+            commands: Vec::new(),
+        }
+    }
+
+    /// Set the prompt printed before each line
+    pub fn set_prompt(&mut self, prompt: &'static str) {
+        self.prompt = prompt;
+    }
+
+    /// Register a command
+    ///
+    /// The handler is called with the words of each line which
+    /// starts with `name`, not including the name itself. The text it
+    /// returns is printed, whether it succeeded or failed. `help` is
+    /// a one-line description, printed after the name by the `help`
+    /// command.
+    pub fn register<F, Fut>(
+        &mut self,
+        name: &'static str,
+        help: &'static str,
+        mut handler: F,
+    ) -> Result<(), ShellError>
+    where
+        F: FnMut(Vec<String>) -> Fut + 'static,
+        Fut: Future<Output = Result<String, String>> + 'static,
+    {
+        if name == "help" || self.commands.iter().any(|command| command.name == name) {
+            return Err(ShellError::NameInUse);
+        }
+        self.commands.push(Command {
+            name,
+            help,
+            handler: Box::new(move |args| Box::pin(handler(args))),
+        });
+        Ok(())
+    }
+
+    /// Register commands for debugging the board
+    ///
+    /// These are `peek` and `poke`, to read and write 32-bit words of
+    /// memory, and `pin`, to read a pin or drive it high or low. Any
+    /// of these which is already registered is left as it is.
+    ///
+    /// `poke` can write any address, including peripheral registers
+    /// in use by drivers, and should not be left in production
+    /// firmware.
+    pub fn add_debug_commands(&mut self) {
+        let _ = self.register("peek", "<addr>: Read a word of memory", |args| async move {
+            let addr = parse_addr(args.get(0))?;
+            // Safety: None. The user is trusted to peek an address
+            // which can be read.
+            let value = unsafe { core::ptr::read_volatile(addr as *const u32) };
+            Ok(format!("{:#010x}", value))
+        });
+        let _ = self.register(
+            "poke",
+            "<addr> <value>: Write a word of memory",
+            |args| async move {
+                let addr = parse_addr(args.get(0))?;
+                let value = parse_number(args.get(1))?;
+                // Safety: None. The user is trusted to poke an
+                // address which can be written.
+                unsafe { core::ptr::write_volatile(addr as *mut u32, value as u32) };
+                Ok(String::new())
+            },
+        );
+
+        #[cfg(any(
+            board = "red_v",
+            board = "teensy_30",
+            board = "teensy_32",
+            board = "teensy_35",
+            board = "teensy_36",
+            board = "teensy_40",
+            board = "teensy_41",
+            board = "teensy_lc"
+        ))]
+        let _ = self.register(
+            "pin",
+            "<n> [0|1]: Read a pin, or drive it low or high",
+            |args| async move {
+                use crate::digital::{digital_read, digital_write, mcu_pin, pin_mode, PinMode};
+
+                let pin = parse_number(args.get(0))?;
+                if mcu_pin(pin).is_none() {
+                    return Err(format!("no pin {}", pin));
+                }
+                match args.get(1).map(String::as_str) {
+                    None => Ok(format!("{}", digital_read(pin) as u8)),
+                    Some(level @ "0") | Some(level @ "1") => {
+                        pin_mode(pin, PinMode::Output);
+                        digital_write(pin, level == "1");
+                        Ok(String::new())
+                    }
+                    Some(level) => Err(format!("invalid level {}", level)),
+                }
+            },
+        );
+    }
+
+    /// Register a command which lists the devices on an I2C bus
+    ///
+    /// The bus is taken from `i2c` each time the command runs, and
+    /// must already be enabled. See [`scan_i2c`].
+    pub fn add_i2c_scan<G>(&mut self, name: &'static str, i2c: fn() -> G) -> Result<(), ShellError>
+    where
+        G: DerefMut + 'static,
+        G::Target: I2c,
+    {
+        self.register(
+            name,
+            ": List the devices on the I2C bus",
+            move |_| async move {
+                let found = scan_i2c(&mut *i2c())
+                    .await
+                    .map_err(|err| format!("{:?}", err))?;
+                if found.is_empty() {
+                    return Ok(String::from("no devices"));
+                }
+                let found = found
+                    .iter()
+                    .map(|address| format!("{:#04x}", address))
+                    .collect::<Vec<_>>();
+                Ok(found.join(" "))
+            },
+        )
+    }
+
+    /// Register a command which checks an SPI port with its data lines joined
+    ///
+    /// The command takes the chip select pin, and optionally the baud
+    /// rate, which defaults to 1MHz. The port is taken from `spi`
+    /// each time the command runs, and must already be enabled. See
+    /// [`check_spi_loopback`].
+    pub fn add_spi_loopback<G>(
+        &mut self,
+        name: &'static str,
+        spi: fn() -> G,
+    ) -> Result<(), ShellError>
+    where
+        G: DerefMut + 'static,
+        G::Target: Spi,
+    {
+        self.register(
+            name,
+            "<cs> [baud]: Check the SPI port with its data out joined to data in",
+            move |args| async move {
+                let cs = parse_number(args.get(0))?;
+                let baud = if args.len() > 1 {
+                    parse_number(args.get(1))?
+                } else {
+                    1_000_000
+                };
+                check_spi_loopback(&mut *spi(), baud, cs)
+                    .await
+                    .map_err(|err| format!("{:?}", err))?;
+                Ok(String::from("ok"))
+            },
+        )
+    }
+
+    /// Unwrap the byte stream
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T> Shell<T>
+where
+    T: DerefMut,
+    T::Target: Read + Write,
+    <T::Target as Read>::Error: Into<Error>,
+    <T::Target as Write>::Error: Into<Error>,
+{
+    /// Run commands until the byte stream fails
+    pub fn run(&mut self) -> impl Future<Output = Result<!, Error>> + '_ {
+        async move {
+            loop {
+                let line = self.read_line().await?;
+                let mut words = line.split_whitespace();
+                let name = match words.next() {
+                    Some(name) => name,
+                    None => continue,
+                };
+                let args = words.map(String::from).collect::<Vec<_>>();
+
+                let output = if name == "help" {
+                    Ok(self.help())
+                } else {
+                    match self
+                        .commands
+                        .iter_mut()
+                        .find(|command| command.name == name)
+                    {
+                        Some(command) => (command.handler)(args).await,
+                        None => Err(format!("unknown command {}. Try 'help'", name)),
+                    }
+                };
+
+                let output = match output {
+                    Ok(output) => output,
+                    Err(err) => format!("error: {}", err),
+                };
+                if !output.is_empty() {
+                    self.write(output.as_bytes()).await?;
+                    self.write(b"\r\n").await?;
+                }
+            }
+        }
+    }
+
+    /// Print the prompt, and read a line with echo and editing
+    async fn read_line(&mut self) -> Result<String, Error> {
+        self.write(self.prompt.as_bytes()).await?;
+        let mut line = String::new();
+        loop {
+            let mut byte = [0];
+            self.inner.read_exact(&mut byte).await.map_err(Into::into)?;
+            match byte[0] {
+                b'\r' | b'\n' => {
+                    self.write(b"\r\n").await?;
+                    return Ok(line);
+                }
+                // Backspace and delete
+                0x08 | 0x7F => {
+                    if line.pop().is_some() {
+                        self.write(b"\x08 \x08").await?;
+                    }
+                }
+                // Ctrl-C
+                0x03 => {
+                    line.clear();
+                    self.write(b"^C\r\n").await?;
+                    self.write(self.prompt.as_bytes()).await?;
+                }
+                byte @ 0x20..=0x7E if line.len() < MAX_LINE => {
+                    line.push(byte as char);
+                    self.write(&[byte]).await?;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    async fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.inner.write_all(buf).await.map_err(Into::into)
+    }
+
+    /// The text of the `help` command
+    fn help(&self) -> String {
+        let mut help = String::from("help: List commands");
+        for command in &self.commands {
+            help.push_str(&format!("\r\n{} {}", command.name, command.help));
+        }
+        help
+    }
+}
+
+fn parse_number(arg: Option<&String>) -> Result<usize, String> {
+    let arg = arg.ok_or_else(|| String::from("missing argument"))?;
+    let parsed = match arg.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => arg.parse(),
+    };
+    parsed.map_err(|_| format!("invalid number {}", arg))
+}
+
+fn parse_addr(arg: Option<&String>) -> Result<usize, String> {
+    let addr = parse_number(arg)?;
+    if addr % 4 != 0 {
+        return Err(format!("unaligned address {:#x}", addr));
+    }
+    Ok(addr)
+}
@@ -3,10 +3,16 @@
 
 //! Synchronization primitives
 
+use crate::task::WakerSet;
+use alloc::{boxed::Box, collections::VecDeque, vec::Vec};
 use core::{
     cell::UnsafeCell,
+    future::{poll_fn, Future},
+    mem::MaybeUninit,
     ops::{Deref, DerefMut},
+    pin::Pin,
     sync::atomic::{AtomicUsize, Ordering},
+    task::{Context, Poll, Waker},
 };
 
 #[cfg(not(mcu = "fe310g002"))]
@@ -54,6 +60,22 @@ impl Flag {
     pub fn store(&self, value: bool, ordering: Ordering) {
         self.0.store(value.into(), ordering)
     }
+
+    /// Load the value of the flag
+    ///
+    /// See [`core::sync::atomic::AtomicBool::load`]
+    pub fn load(&self, ordering: Ordering) -> bool {
+        let value = self.0.load(ordering);
+        #[cfg(mcu = "fe310g002")]
+        {
+            value != 0
+        }
+
+        #[cfg(not(mcu = "fe310g002"))]
+        {
+            value
+        }
+    }
 }
 
 #[cfg(any(doc, target_has_atomic = "8"))]
@@ -138,6 +160,13 @@ impl Value {
     pub fn swap(&self, value: usize, ordering: Ordering) -> usize {
         self.0.swap(value, ordering)
     }
+
+    /// Adds to the value, returning the previous value
+    ///
+    /// See [`core::sync::atomic::AtomicUsize::fetch_add`]
+    pub fn fetch_add(&self, value: usize, ordering: Ordering) -> usize {
+        self.0.fetch_add(value, ordering)
+    }
 }
 
 #[cfg(not(any(doc, target_has_atomic = "32")))]
@@ -160,6 +189,25 @@ impl Value {
             out
         })
     }
+
+    /// Adds to the value, returning the previous value
+    ///
+    /// See [`core::sync::atomic::AtomicUsize::fetch_add`]
+    pub fn fetch_add(&self, value: usize, ordering: Ordering) -> usize {
+        let (load_ordering, store_ordering) = match ordering {
+            Ordering::Relaxed => (Ordering::Relaxed, Ordering::Relaxed),
+            Ordering::Acquire => (Ordering::Acquire, Ordering::Relaxed),
+            Ordering::Release => (Ordering::Relaxed, Ordering::Release),
+            Ordering::AcqRel => (Ordering::Acquire, Ordering::Release),
+            Ordering::SeqCst => (Ordering::SeqCst, Ordering::SeqCst),
+            _ => panic!("Unsupported fetch_add ordering"),
+        };
+        without_interrupts(|| {
+            let out = self.0.load(load_ordering);
+            self.0.store(out.wrapping_add(value), store_ordering);
+            out
+        })
+    }
 }
 
 /// A value which can be initalized only once
@@ -217,6 +265,10 @@ impl<T> Once<T> {
 /// mutex in an interrupt context may deadlock. In interrupt contexts,
 /// prefer a dedicated synchronization primitive based around
 /// [`without_interrupts`]
+///
+/// Waiting tasks spin rather than yield, so a task which holds the
+/// lock across an `.await` can deadlock the executor. Prefer
+/// [`AsyncMutex`] for locks which are held across an `.await`.
 pub struct Mutex<T> {
     lock: Flag,
     value: UnsafeCell<T>,
@@ -270,6 +322,124 @@ impl<'a, T> Drop for MutexGuard<'a, T> {
     }
 }
 
+/// A lock which waits asynchronously
+///
+/// Unlike [`Mutex`], a task waiting for this lock yields to the
+/// executor, so the lock can be held across an `.await` without
+/// deadlocking other tasks on the same executor. It should not be
+/// used from interrupts, which cannot wait.
+///
+/// ```
+/// use cntrlr::sync::AsyncMutex;
+///
+/// static LOG: AsyncMutex<Vec<u8>> = AsyncMutex::new(Vec::new());
+///
+/// async fn record(byte: u8) {
+///     let mut log = LOG.lock().await;
+///     log.push(byte);
+/// }
+/// ```
+pub struct AsyncMutex<T> {
+    lock: Flag,
+    wakers: WakerSet,
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for AsyncMutex<T> {}
+unsafe impl<T: Send> Sync for AsyncMutex<T> {}
+
+impl<T> AsyncMutex<T> {
+    /// Create a new AsyncMutex
+    pub const fn new(item: T) -> Self {
+        Self {
+            lock: Flag::new(false),
+            wakers: WakerSet::new(),
+            value: UnsafeCell::new(item),
+        }
+    }
+
+    /// Acquire this mutex, waiting while it is held
+    pub fn lock(&self) -> AsyncMutexLock<T> {
+        AsyncMutexLock(self)
+    }
+
+    /// Acquire this mutex without waiting
+    ///
+    /// Returns `None` if the mutex is held.
+    pub fn try_lock(&self) -> Option<AsyncMutexGuard<T>> {
+        if self.lock.swap(true, Ordering::AcqRel) {
+            None
+        } else {
+            Some(AsyncMutexGuard(self))
+        }
+    }
+
+    unsafe fn unlock(&self) {
+        self.lock.store(false, Ordering::Release);
+        self.wakers.wake();
+    }
+}
+
+/// The future returned by [`AsyncMutex::lock`]
+pub struct AsyncMutexLock<'a, T>(&'a AsyncMutex<T>);
+
+impl<'a, T> AsyncMutexLock<'a, T> {
+    /// Access the value without waiting for the mutex
+    ///
+    /// # Safety
+    /// Nothing else may use the value while the returned reference
+    /// is live, including a task which holds the mutex. This is for
+    /// the panic handler, after which no task runs again.
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) unsafe fn steal(self) -> &'a mut T {
+        &mut *self.0.value.get()
+    }
+}
+
+impl<'a, T> Future for AsyncMutexLock<'a, T> {
+    type Output = AsyncMutexGuard<'a, T>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<AsyncMutexGuard<'a, T>> {
+        if let Some(guard) = self.0.try_lock() {
+            return Poll::Ready(guard);
+        }
+        self.0.wakers.add(ctx.waker().clone());
+        // The holder may have unlocked before the waker was added,
+        // which would otherwise be a lost wakeup.
+        match self.0.try_lock() {
+            Some(guard) => Poll::Ready(guard),
+            None => Poll::Pending,
+        }
+    }
+}
+
+/// An RAII guard for an [`AsyncMutex`]
+///
+/// Dropping the guard wakes every task waiting for the lock.
+pub struct AsyncMutexGuard<'a, T>(&'a AsyncMutex<T>);
+
+impl<'a, T> Deref for AsyncMutexGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.0.value.get() }
+    }
+}
+
+impl<'a, T> DerefMut for AsyncMutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.0.value.get() }
+    }
+}
+
+impl<'a, T> Drop for AsyncMutexGuard<'a, T> {
+    fn drop(&mut self) {
+        unsafe {
+            self.0.unlock();
+        }
+    }
+}
+
 struct InterruptGate {
     count: UnsafeCell<usize>,
     enable: UnsafeCell<bool>,
@@ -340,6 +510,407 @@ pub fn enable_interrupts() {
     INTERRUPTS.flag_enable()
 }
 
+/// A queue which passes values between tasks
+///
+/// Senders wait while the channel is full, and receivers wait while
+/// it is empty. Its state is guarded by disabling interrupts while
+/// it is locked. It is usually placed in a static.
+///
+/// ```
+/// use cntrlr::sync::Channel;
+///
+/// static SAMPLES: Channel<u16> = Channel::new(16);
+///
+/// async fn producer() -> ! {
+///     loop {
+///         SAMPLES.send(read_sensor().await).await;
+///     }
+/// }
+///
+/// async fn consumer() -> ! {
+///     loop {
+///         let sample = SAMPLES.recv().await;
+///         // ...
+///     }
+/// }
+/// ```
+pub struct Channel<T> {
+    lock: Flag,
+    capacity: usize,
+    state: UnsafeCell<ChannelState<T>>,
+}
+
+struct ChannelState<T> {
+    values: VecDeque<T>,
+    senders: Vec<Waker>,
+    receivers: Vec<Waker>,
+}
+
+unsafe impl<T: Send> Send for Channel<T> {}
+unsafe impl<T: Send> Sync for Channel<T> {}
+
+impl<T> Channel<T> {
+    /// Create a channel which holds up to `capacity` values
+    ///
+    /// # Panics
+    /// Sending or receiving panics if `capacity` is zero.
+    pub const fn new(capacity: usize) -> Self {
+        Self {
+            lock: Flag::new(false),
+            capacity,
+            state: UnsafeCell::new(ChannelState {
+                values: VecDeque::new(),
+                senders: Vec::new(),
+                receivers: Vec::new(),
+            }),
+        }
+    }
+
+    /// Send a value, waiting while the channel is full
+    pub fn send(&self, value: T) -> impl Future<Output = ()> + '_ {
+        let mut value = Some(value);
+        poll_fn(move |ctx| match self.try_send(value.take().unwrap()) {
+            Ok(()) => Poll::Ready(()),
+            Err(returned) => {
+                value = Some(returned);
+                self.register(ctx.waker(), |state| &mut state.senders);
+                // A receiver may have made room before the waker was
+                // added, which would otherwise be a lost wakeup.
+                match self.try_send(value.take().unwrap()) {
+                    Ok(()) => Poll::Ready(()),
+                    Err(returned) => {
+                        value = Some(returned);
+                        Poll::Pending
+                    }
+                }
+            }
+        })
+    }
+
+    /// Receive a value, waiting while the channel is empty
+    pub fn recv(&self) -> impl Future<Output = T> + '_ {
+        poll_fn(move |ctx| {
+            if let Some(value) = self.try_recv() {
+                return Poll::Ready(value);
+            }
+            self.register(ctx.waker(), |state| &mut state.receivers);
+            // A sender may have sent before the waker was added,
+            // which would otherwise be a lost wakeup.
+            match self.try_recv() {
+                Some(value) => Poll::Ready(value),
+                None => Poll::Pending,
+            }
+        })
+    }
+
+    /// Send a value without waiting
+    ///
+    /// Returns the value back if the channel is full.
+    pub fn try_send(&self, value: T) -> Result<(), T> {
+        assert!(self.capacity > 0, "Channel has no capacity");
+
+        // The storage for the values is allocated by the first send,
+        // outside the lock.
+        let mut storage = VecDeque::new();
+        if self.locked(|state| state.values.capacity() < self.capacity) {
+            storage = VecDeque::with_capacity(self.capacity);
+        }
+
+        let wakers = self.locked(|state| {
+            if state.values.len() >= self.capacity {
+                return Err(value);
+            }
+            if state.values.capacity() < self.capacity {
+                storage.append(&mut state.values);
+                core::mem::swap(&mut state.values, &mut storage);
+            }
+            state.values.push_back(value);
+            Ok(core::mem::take(&mut state.receivers))
+        })?;
+        for waker in wakers {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// Receive a value without waiting
+    ///
+    /// Returns `None` if the channel is empty.
+    pub fn try_recv(&self) -> Option<T> {
+        assert!(self.capacity > 0, "Channel has no capacity");
+        let (value, wakers) = self.locked(|state| {
+            let value = state.values.pop_front()?;
+            Some((value, core::mem::take(&mut state.senders)))
+        })?;
+        for waker in wakers {
+            waker.wake();
+        }
+        Some(value)
+    }
+
+    /// The number of values waiting in the channel
+    pub fn len(&self) -> usize {
+        self.locked(|state| state.values.len())
+    }
+
+    /// Whether the channel has no values waiting
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add a waker to one of the channel's waker lists
+    ///
+    /// A waker which will wake the same task as one already in the
+    /// list is not added again. The list is grown outside the lock, so
+    /// interrupts are not held off while allocating.
+    fn register(&self, waker: &Waker, list: fn(&mut ChannelState<T>) -> &mut Vec<Waker>) {
+        let mut spare = Vec::new();
+        loop {
+            let full = self.locked(|state| {
+                let wakers = list(state);
+                if wakers.iter().any(|registered| registered.will_wake(waker)) {
+                    return None;
+                }
+                if wakers.len() == wakers.capacity() {
+                    if spare.capacity() <= wakers.len() {
+                        return Some(wakers.len());
+                    }
+                    spare.append(wakers);
+                    core::mem::swap(wakers, &mut spare);
+                }
+                wakers.push(waker.clone());
+                None
+            });
+            match full {
+                Some(len) => spare = Vec::with_capacity((len * 2).max(4)),
+                None => return,
+            }
+        }
+    }
+
+    /// Run a closure with the channel's state locked
+    ///
+    /// Interrupts are disabled while the lock is held, so that a
+    /// handler cannot spin on a lock held by the code it
+    /// interrupted. Wakers are woken by the caller after the lock is
+    /// released.
+    fn locked<R>(&self, f: impl FnOnce(&mut ChannelState<T>) -> R) -> R {
+        without_interrupts(|| {
+            while self.lock.swap(true, Ordering::Acquire) {}
+            // Safety: The lock ensures this is the only reference
+            let out = f(unsafe { &mut *self.state.get() });
+            self.lock.store(false, Ordering::Release);
+            out
+        })
+    }
+}
+
+/// Create a bounded channel which holds up to `N` values
+///
+/// The channel's storage is allocated once, and never freed. Channels
+/// which are created at startup, or which need to be reachable from
+/// an interrupt handler, can be placed in a static [`BoundedChannel`]
+/// instead. `N` must be at least 1, or this fails to compile.
+pub fn channel<T: Send + 'static, const N: usize>() -> (Sender<T, N>, Receiver<T, N>) {
+    let channel: &'static BoundedChannel<T, N> = Box::leak(Box::new(BoundedChannel::new()));
+    // The channel was just created, so its receiver is available
+    (channel.sender(), channel.receiver().unwrap())
+}
+
+/// A fixed-size queue which passes values from interrupts to a task
+///
+/// Unlike [`Channel`], this never allocates while sending, and
+/// sending never waits, so a [`Sender`] can be used from an interrupt
+/// handler. Any number of senders may share the channel, but it has a
+/// single [`Receiver`], which waits for values asynchronously.
+///
+/// ```
+/// use cntrlr::sync::BoundedChannel;
+///
+/// static EVENTS: BoundedChannel<u32, 8> = BoundedChannel::new();
+///
+/// fn some_intr() {
+///     // If the queue is full, the event is dropped
+///     let _ = EVENTS.sender().send(read_status());
+/// }
+///
+/// async fn handler() -> ! {
+///     let events = EVENTS.receiver().unwrap();
+///     loop {
+///         let event = events.recv().await;
+///         // ...
+///     }
+/// }
+/// ```
+pub struct BoundedChannel<T, const N: usize> {
+    lock: Flag,
+    receiver_taken: Flag,
+    state: UnsafeCell<BoundedState<T, N>>,
+    wakers: WakerSet,
+}
+
+struct BoundedState<T, const N: usize> {
+    values: MaybeUninit<[T; N]>,
+    head: usize,
+    len: usize,
+}
+
+unsafe impl<T: Send, const N: usize> Send for BoundedChannel<T, N> {}
+unsafe impl<T: Send, const N: usize> Sync for BoundedChannel<T, N> {}
+
+impl<T, const N: usize> BoundedChannel<T, N> {
+    /// Checked when a channel is created, so that a channel with no
+    /// room for values is rejected at compile time
+    const HAS_CAPACITY: () = assert!(N > 0, "BoundedChannel must hold at least one value");
+
+    /// Create an empty channel
+    ///
+    /// `N` must be at least 1, or this fails to compile.
+    pub const fn new() -> Self {
+        let () = Self::HAS_CAPACITY;
+        Self {
+            lock: Flag::new(false),
+            receiver_taken: Flag::new(false),
+            state: UnsafeCell::new(BoundedState {
+                values: MaybeUninit::uninit(),
+                head: 0,
+                len: 0,
+            }),
+            wakers: WakerSet::new(),
+        }
+    }
+
+    /// Get a sender for this channel
+    pub fn sender(&'static self) -> Sender<T, N> {
+        Sender(self)
+    }
+
+    /// Take the receiver for this channel
+    ///
+    /// Returns `None` if the receiver was already taken.
+    pub fn receiver(&'static self) -> Option<Receiver<T, N>> {
+        if self.receiver_taken.swap(true, Ordering::Relaxed) {
+            None
+        } else {
+            Some(Receiver(self))
+        }
+    }
+
+    /// The number of values waiting in the channel
+    pub fn len(&self) -> usize {
+        self.locked(|state| state.len)
+    }
+
+    /// Whether the channel has no values waiting
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Run a closure with the channel's state locked
+    ///
+    /// This is locked in the same way as [`Channel`], so that it can
+    /// be used from interrupts.
+    fn locked<R>(&self, f: impl FnOnce(&mut BoundedState<T, N>) -> R) -> R {
+        without_interrupts(|| {
+            while self.lock.swap(true, Ordering::Acquire) {}
+            // Safety: The lock ensures this is the only reference
+            let out = f(unsafe { &mut *self.state.get() });
+            self.lock.store(false, Ordering::Release);
+            out
+        })
+    }
+}
+
+impl<T, const N: usize> BoundedState<T, N> {
+    fn slot(&mut self, idx: usize) -> *mut T {
+        // Safety: idx is always reduced modulo N, so the slot is
+        // within the array.
+        unsafe { (self.values.as_mut_ptr() as *mut T).add(idx % N) }
+    }
+
+    fn push(&mut self, value: T) -> Result<(), T> {
+        if self.len >= N {
+            return Err(value);
+        }
+        let slot = self.slot(self.head + self.len);
+        // Safety: Slots past the end of the queue are uninitialized
+        unsafe { slot.write(value) };
+        self.len += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        // Safety: The slot at the head of the queue is initialized,
+        // and is uninitialized once the head moves past it.
+        let value = unsafe { self.slot(self.head).read() };
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(value)
+    }
+}
+
+impl<T, const N: usize> Drop for BoundedState<T, N> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+/// The sending half of a [`BoundedChannel`]
+///
+/// Senders can be freely copied, and are safe to use from interrupt
+/// handlers.
+pub struct Sender<T: 'static, const N: usize>(&'static BoundedChannel<T, N>);
+
+impl<T, const N: usize> Clone for Sender<T, N> {
+    fn clone(&self) -> Self {
+        Sender(self.0)
+    }
+}
+
+impl<T, const N: usize> Copy for Sender<T, N> {}
+
+impl<T, const N: usize> Sender<T, N> {
+    /// Send a value, and wake the receiver
+    ///
+    /// Returns the value back if the channel is full.
+    pub fn send(&self, value: T) -> Result<(), T> {
+        self.0.locked(|state| state.push(value))?;
+        self.0.wakers.wake();
+        Ok(())
+    }
+}
+
+/// The receiving half of a [`BoundedChannel`]
+pub struct Receiver<T: 'static, const N: usize>(&'static BoundedChannel<T, N>);
+
+impl<T, const N: usize> Receiver<T, N> {
+    /// Receive a value, waiting while the channel is empty
+    pub fn recv(&self) -> impl Future<Output = T> + '_ {
+        poll_fn(move |ctx| {
+            if let Some(value) = self.try_recv() {
+                return Poll::Ready(value);
+            }
+            self.0.wakers.add(ctx.waker().clone());
+            // A sender may have sent before the waker was added,
+            // which would otherwise be a lost wakeup.
+            match self.try_recv() {
+                Some(value) => Poll::Ready(value),
+                None => Poll::Pending,
+            }
+        })
+    }
+
+    /// Receive a value without waiting
+    ///
+    /// Returns `None` if the channel is empty.
+    pub fn try_recv(&self) -> Option<T> {
+        self.0.locked(|state| state.pop())
+    }
+}
+
 #[cfg(target_arch = "riscv32")]
 mod arch {
     use bit_field::BitField;
@@ -3,7 +3,7 @@
 
 //! Async task support for Cntrlr
 
-use crate::sync::without_interrupts;
+use crate::sync::{without_interrupts, Flag, Value};
 use alloc::{boxed::Box, vec::Vec};
 use core::{
     cell::UnsafeCell,
@@ -14,15 +14,108 @@ use core::{
     task::{Context, RawWaker, RawWakerVTable, Waker},
 };
 
+/// The ID to give to the next task added to any executor
+static NEXT_TASK_ID: Value = Value::new(0);
+
+/// The task currently being polled
+static CURRENT_TASK: CurrentTask = CurrentTask(UnsafeCell::new(None));
+
+struct CurrentTask(UnsafeCell<Option<(usize, Option<&'static str>)>>);
+
+unsafe impl Sync for CurrentTask {}
+
+impl CurrentTask {
+    fn set(&self, task: Option<(usize, Option<&'static str>)>) {
+        unsafe { without_interrupts(|| *self.0.get() = task) }
+    }
+
+    fn get(&self) -> Option<(usize, Option<&'static str>)> {
+        unsafe { without_interrupts(|| *self.0.get()) }
+    }
+}
+
 struct Task {
-    wake: AtomicBool,
+    id: usize,
+    state: TaskState,
     future: Pin<Box<dyn Future<Output = !>>>,
+    name: Option<&'static str>,
+}
+
+/// The part of a task which its wakers point to
+struct TaskState {
+    wake: AtomicBool,
+
+    /// The wake flag of the executor which owns this task
+    woken: *const AtomicBool,
+
+    /// The number of live wakers for this task
+    #[cfg(debug_assertions)]
+    wakers: Value,
+
+    /// When the task was last woken, if latency is being measured
+    woken_at: Value,
+}
+
+impl TaskState {
+    fn new(woken: &AtomicBool) -> Self {
+        Self {
+            wake: AtomicBool::new(true),
+            woken,
+            #[cfg(debug_assertions)]
+            wakers: Value::new(0),
+            woken_at: Value::new(0),
+        }
+    }
 }
 
 /// Task Executor
+///
+/// The wakers given to tasks are safe to use from interrupt handlers
+/// on all supported targets. Waking a task sets a flag which the
+/// executor checks with interrupts disabled before it sleeps, and
+/// signals the processor in case it is already asleep.
+///
+/// Each executor has its own wake flag, which the wakers of its tasks
+/// set. Every supported MCU has a single core, and [`run()`](Self::run)
+/// never returns, so only one executor runs at a time.
 #[derive(Default)]
 pub struct Executor {
     tasks: Vec<Task>,
+
+    /// Set whenever any task on this executor is woken
+    ///
+    /// The executor will not sleep while this is set. It is boxed so
+    /// that task wakers can point to it.
+    woken: Box<AtomicBool>,
+
+    #[cfg(all(
+        debug_assertions,
+        any(
+            doc,
+            board = "red_v",
+            board = "teensy_30",
+            board = "teensy_32",
+            board = "teensy_35",
+            board = "teensy_36",
+            board = "teensy_40",
+            board = "teensy_41",
+            board = "teensy_lc"
+        )
+    ))]
+    stalls: Option<StallDetector>,
+
+    #[cfg(any(
+        doc,
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    ))]
+    latency: Option<LatencyMonitor>,
 }
 
 impl Executor {
@@ -36,11 +129,227 @@ impl Executor {
     where
         F: Future<Output = !> + 'static,
     {
+        self.push_task(None, Box::pin(task));
+    }
+
+    /// Add a new task to this Executor, with a name for diagnostics
+    pub fn add_task_named<F>(&mut self, name: &'static str, task: F)
+    where
+        F: Future<Output = !> + 'static,
+    {
+        self.push_task(Some(name), Box::pin(task));
+    }
+
+    /// Add the background tasks of the board's device drivers
+    ///
+    /// [`entry`](crate::macros::entry) adds these to its
+    /// executor. Applications using
+    /// [`raw_entry`](crate::macros::raw_entry) must add them to one of
+    /// their own executors, or drivers which need them, such as
+    /// [`usb_serial`](crate::io::usb_serial), never make progress.
+    pub fn add_driver_tasks(&mut self) {
+        #[cfg(all(
+            feature = "usb-serial",
+            any(
+                board = "teensy_30",
+                board = "teensy_32",
+                board = "teensy_35",
+                board = "teensy_36",
+                board = "teensy_lc"
+            )
+        ))]
+        self.add_task_named("usb_serial", crate::io::usb_serial_task());
+    }
+
+    fn push_task(&mut self, name: Option<&'static str>, future: Pin<Box<dyn Future<Output = !>>>) {
+        let id = NEXT_TASK_ID.fetch_add(1, Ordering::Relaxed);
         let task = Task {
-            wake: AtomicBool::new(true),
-            future: Box::pin(task),
+            id,
+            state: TaskState::new(&self.woken),
+            future,
+            name,
         };
-        self.tasks.push(task)
+
+        #[cfg(any(
+            doc,
+            board = "red_v",
+            board = "teensy_30",
+            board = "teensy_32",
+            board = "teensy_35",
+            board = "teensy_36",
+            board = "teensy_40",
+            board = "teensy_41",
+            board = "teensy_lc"
+        ))]
+        if self.latency.is_some() {
+            task.state
+                .woken_at
+                .store(crate::time::millis(), Ordering::Relaxed);
+        }
+
+        self.tasks.push(task);
+        self.woken.store(true, Ordering::Release);
+    }
+
+    /// Report tasks which have not been polled for `timeout` milliseconds
+    ///
+    /// This catches lost wakeups in drivers. When a task which is
+    /// waiting on a waker has not been polled for `timeout`
+    /// milliseconds, the executor calls `sink` with a line naming the
+    /// task. Each task is reported once per stall, however busy the
+    /// other tasks are.
+    ///
+    /// A task which is validly waiting for longer than `timeout`, such
+    /// as for input which has not arrived, will also be reported. Stall
+    /// detection is only available in debug builds.
+    #[cfg(all(
+        debug_assertions,
+        any(
+            doc,
+            board = "red_v",
+            board = "teensy_30",
+            board = "teensy_32",
+            board = "teensy_35",
+            board = "teensy_36",
+            board = "teensy_40",
+            board = "teensy_41",
+            board = "teensy_lc"
+        )
+    ))]
+    #[cfg_attr(
+        feature = "doc-cfg",
+        doc(cfg(all(
+            debug_assertions,
+            any(
+                board = "red_v",
+                board = "teensy_30",
+                board = "teensy_32",
+                board = "teensy_35",
+                board = "teensy_36",
+                board = "teensy_40",
+                board = "teensy_41",
+                board = "teensy_lc"
+            )
+        )))
+    )]
+    pub fn detect_stalls(&mut self, timeout: usize, sink: fn(&str)) {
+        self.stalls = Some(StallDetector::new(timeout, sink));
+    }
+
+    /// Measure how long woken tasks wait to be polled
+    ///
+    /// Once enabled, each poll records the time from the task's first
+    /// wake to the start of the poll, and how long the poll ran. The
+    /// statistics over recent polls are returned by
+    /// [`latency_stats()`](Self::latency_stats). Times are measured
+    /// with [`millis()`](crate::time::millis), so latencies below a
+    /// millisecond read as 0.
+    #[cfg(any(
+        doc,
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    ))]
+    #[cfg_attr(
+        feature = "doc-cfg",
+        doc(cfg(any(
+            board = "red_v",
+            board = "teensy_30",
+            board = "teensy_32",
+            board = "teensy_35",
+            board = "teensy_36",
+            board = "teensy_40",
+            board = "teensy_41",
+            board = "teensy_lc"
+        )))
+    )]
+    pub fn measure_latency(&mut self) {
+        if self.latency.is_some() {
+            return;
+        }
+        self.latency = Some(LatencyMonitor::new());
+
+        // Tasks woken before now have no wake time to measure from
+        let now = crate::time::millis();
+        for task in &self.tasks {
+            task.state.woken_at.store(now, Ordering::Relaxed);
+        }
+        MEASURE_LATENCY.store(true, Ordering::Relaxed);
+    }
+
+    /// Report any poll which runs for longer than `threshold` milliseconds
+    ///
+    /// A task which blocks instead of awaiting keeps every other task
+    /// from running. When a single poll runs for longer than
+    /// `threshold`, the executor calls `sink` with a line naming the
+    /// task. This also enables [`measure_latency()`](Self::measure_latency).
+    #[cfg(any(
+        doc,
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    ))]
+    #[cfg_attr(
+        feature = "doc-cfg",
+        doc(cfg(any(
+            board = "red_v",
+            board = "teensy_30",
+            board = "teensy_32",
+            board = "teensy_35",
+            board = "teensy_36",
+            board = "teensy_40",
+            board = "teensy_41",
+            board = "teensy_lc"
+        )))
+    )]
+    pub fn warn_slow_polls(&mut self, threshold: usize, sink: fn(&str)) {
+        self.measure_latency();
+        if let Some(latency) = &mut self.latency {
+            latency.threshold = threshold;
+            latency.sink = Some(sink);
+        }
+    }
+
+    /// Latency statistics over recent polls
+    ///
+    /// Returns `None` if [`measure_latency()`](Self::measure_latency)
+    /// has not been called.
+    #[cfg(any(
+        doc,
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    ))]
+    #[cfg_attr(
+        feature = "doc-cfg",
+        doc(cfg(any(
+            board = "red_v",
+            board = "teensy_30",
+            board = "teensy_32",
+            board = "teensy_35",
+            board = "teensy_36",
+            board = "teensy_40",
+            board = "teensy_41",
+            board = "teensy_lc"
+        )))
+    )]
+    pub fn latency_stats(&self) -> Option<LatencyStats> {
+        self.latency.as_ref().map(LatencyMonitor::stats)
     }
 
     /// Hand control off to the Executor
@@ -51,38 +360,473 @@ impl Executor {
         loop {
             // The execution loop is broken into two parts:
             //
-            // 1. With interrupts disabled, check all tasks and sleep
-            // if none are ready. On all supported architectures, this
-            // sleep implicitly enables interrupts.
+            // 1. With interrupts disabled, check whether any task has
+            // been woken and sleep if none have. On all supported
+            // architectures, a pending interrupt ends the sleep even
+            // while interrupts are disabled, and the sleep
+            // implicitly enables interrupts.
             //
             // 2. When we wake from sleep, run any tasks which have
             // been made ready.
             //
-            // This two-phase check uses a few more cycles, but it
-            // is a simple way to ensure we don't miss a wake.
+            // The wake flag is cleared before any task is polled, so
+            // a wake from an interrupt or from another task while
+            // tasks are being polled leaves it set, and the next
+            // check will not sleep.
             without_interrupts(|| {
-                if self
-                    .tasks
-                    .iter()
-                    .all(|task| !task.wake.load(Ordering::Acquire))
-                {
+                if !self.woken.load(Ordering::Acquire) {
                     // This is the same instruction with basically
                     // the same semantics on both ARM and RISC-V.
                     asm!("wfi")
                 }
+                self.woken.store(false, Ordering::Relaxed);
             });
-            for task in &mut self.tasks {
-                if task.wake.load(Ordering::Acquire) {
-                    task.wake.store(false, Ordering::Relaxed);
-                    let waker = waker_new(&task.wake);
+            crate::watchdog::auto_feed();
+            for (idx, task) in self.tasks.iter_mut().enumerate() {
+                if task.state.wake.load(Ordering::Acquire) {
+                    task.state.wake.store(false, Ordering::Relaxed);
+                    let waker = waker_new(&task.state);
                     let mut context = Context::from_waker(&waker);
+
+                    #[cfg(any(
+                        doc,
+                        board = "red_v",
+                        board = "teensy_30",
+                        board = "teensy_32",
+                        board = "teensy_35",
+                        board = "teensy_36",
+                        board = "teensy_40",
+                        board = "teensy_41",
+                        board = "teensy_lc"
+                    ))]
+                    let poll_start = self.latency.as_ref().map(|_| crate::time::millis());
+
+                    CURRENT_TASK.set(Some((task.id, task.name)));
                     let _ = task.future.as_mut().poll(&mut context);
+                    CURRENT_TASK.set(None);
+                    crate::watchdog::auto_feed();
+
+                    #[cfg(all(
+                        debug_assertions,
+                        any(
+                            doc,
+                            board = "red_v",
+                            board = "teensy_30",
+                            board = "teensy_32",
+                            board = "teensy_35",
+                            board = "teensy_36",
+                            board = "teensy_40",
+                            board = "teensy_41",
+                            board = "teensy_lc"
+                        )
+                    ))]
+                    if let Some(stalls) = &mut self.stalls {
+                        stalls.polled(idx, crate::time::millis());
+                    }
+
+                    #[cfg(any(
+                        doc,
+                        board = "red_v",
+                        board = "teensy_30",
+                        board = "teensy_32",
+                        board = "teensy_35",
+                        board = "teensy_36",
+                        board = "teensy_40",
+                        board = "teensy_41",
+                        board = "teensy_lc"
+                    ))]
+                    if let (Some(latency), Some(poll_start)) = (&mut self.latency, poll_start) {
+                        latency.record(task, poll_start);
+                    }
                 }
             }
+
+            #[cfg(all(
+                debug_assertions,
+                any(
+                    doc,
+                    board = "red_v",
+                    board = "teensy_30",
+                    board = "teensy_32",
+                    board = "teensy_35",
+                    board = "teensy_36",
+                    board = "teensy_40",
+                    board = "teensy_41",
+                    board = "teensy_lc"
+                )
+            ))]
+            if let Some(stalls) = &mut self.stalls {
+                stalls.check(crate::time::millis(), &self.tasks);
+            }
+        }
+    }
+}
+
+/// Executor state for [`Executor::detect_stalls`]
+#[cfg(all(
+    debug_assertions,
+    any(
+        doc,
+        test,
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    )
+))]
+struct StallDetector {
+    timeout: usize,
+    sink: fn(&str),
+
+    /// When each task was last polled, by its index in the executor
+    last_polls: Vec<usize>,
+
+    /// Whether each task has been reported since it was last polled
+    reported: Vec<bool>,
+}
+
+#[cfg(all(
+    debug_assertions,
+    any(
+        doc,
+        test,
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    )
+))]
+impl StallDetector {
+    fn new(timeout: usize, sink: fn(&str)) -> Self {
+        Self {
+            timeout,
+            sink,
+            last_polls: Vec::new(),
+            reported: Vec::new(),
+        }
+    }
+
+    /// Record that a task was polled at `now`
+    fn polled(&mut self, idx: usize, now: usize) {
+        self.track(idx + 1, now);
+        self.last_polls[idx] = now;
+        self.reported[idx] = false;
+    }
+
+    /// Report the tasks which are waiting on a waker, and have not
+    /// been polled for the timeout
+    fn check(&mut self, now: usize, tasks: &[Task]) {
+        use alloc::format;
+
+        self.track(tasks.len(), now);
+        for (idx, task) in tasks.iter().enumerate() {
+            let waiting = now.wrapping_sub(self.last_polls[idx]);
+            if self.reported[idx]
+                || waiting < self.timeout
+                || task.state.wakers.load(Ordering::Relaxed) == 0
+            {
+                continue;
+            }
+
+            self.reported[idx] = true;
+            let line = match task.name {
+                Some(name) => format!("task {} has not been polled for {}ms", name, waiting),
+                None => format!("task #{} has not been polled for {}ms", idx, waiting),
+            };
+            (self.sink)(&line);
+        }
+    }
+
+    /// Start tracking tasks up to `len`
+    ///
+    /// Tasks which were added since the last check are counted from
+    /// `now`.
+    fn track(&mut self, len: usize, now: usize) {
+        if self.last_polls.len() < len {
+            self.last_polls.resize(len, now);
+            self.reported.resize(len, false);
         }
     }
 }
 
+/// The number of polls [`Executor::latency_stats`] covers
+const LATENCY_WINDOW: usize = 32;
+
+/// Executor latency statistics
+///
+/// All times are in milliseconds, over the most recent polls.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LatencyStats {
+    /// The longest time a woken task waited to be polled
+    pub max_latency: usize,
+
+    /// The mean time a woken task waited to be polled
+    pub mean_latency: usize,
+
+    /// The longest time a single poll ran
+    pub max_poll: usize,
+
+    /// The number of polls the statistics cover
+    pub samples: usize,
+}
+
+/// Set while any executor is measuring latency
+///
+/// Wakers only record their wake time while this is set.
+static MEASURE_LATENCY: AtomicBool = AtomicBool::new(false);
+
+/// Executor state for [`Executor::measure_latency`]
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+struct LatencyMonitor {
+    latencies: [u16; LATENCY_WINDOW],
+    polls: [u16; LATENCY_WINDOW],
+    next: usize,
+    samples: usize,
+    threshold: usize,
+    sink: Option<fn(&str)>,
+}
+
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+impl LatencyMonitor {
+    fn new() -> Self {
+        Self {
+            latencies: [0; LATENCY_WINDOW],
+            polls: [0; LATENCY_WINDOW],
+            next: 0,
+            samples: 0,
+            threshold: 0,
+            sink: None,
+        }
+    }
+
+    fn record(&mut self, task: &Task, poll_start: usize) {
+        use alloc::format;
+        use core::convert::TryFrom;
+
+        let now = crate::time::millis();
+        let latency = poll_start.wrapping_sub(task.state.woken_at.load(Ordering::Relaxed));
+        let poll = now.wrapping_sub(poll_start);
+
+        self.latencies[self.next] = u16::try_from(latency).unwrap_or(u16::MAX);
+        self.polls[self.next] = u16::try_from(poll).unwrap_or(u16::MAX);
+        self.next = (self.next + 1) % LATENCY_WINDOW;
+        self.samples = (self.samples + 1).min(LATENCY_WINDOW);
+
+        if let Some(sink) = self.sink {
+            if poll > self.threshold {
+                let line = match task.name {
+                    Some(name) => format!("slow poll: {} ran for {}ms", name, poll),
+                    None => format!("slow poll: task #{} ran for {}ms", task.id, poll),
+                };
+                sink(&line);
+            }
+        }
+    }
+
+    fn stats(&self) -> LatencyStats {
+        let latencies = &self.latencies[..self.samples];
+        let polls = &self.polls[..self.samples];
+        let total: usize = latencies.iter().map(|&latency| latency as usize).sum();
+        LatencyStats {
+            max_latency: latencies.iter().copied().max().unwrap_or(0) as usize,
+            mean_latency: total.checked_div(self.samples).unwrap_or(0),
+            max_poll: polls.iter().copied().max().unwrap_or(0) as usize,
+            samples: self.samples,
+        }
+    }
+}
+
+/// Limit how often a future is polled in each millisecond tick
+///
+/// A background task which always has more work, such as bulk
+/// logging or network IO, is woken again as soon as it yields, and
+/// so can take most of the executor's time. The budgeted future is
+/// polled at most `max_polls_per_tick` times in each tick of
+/// [`millis()`](crate::time::millis). Once its budget is spent, it
+/// sleeps until the next tick, leaving the rest of the tick to the
+/// other tasks.
+///
+/// ```
+/// use cntrlr::task::{budget, Executor};
+///
+/// let mut executor = Executor::new();
+/// executor.add_task(budget(log_to_sd(), 4));
+/// ```
+///
+/// A budget of 0 is treated as 1.
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    )))
+)]
+pub fn budget<F: Future>(future: F, max_polls_per_tick: usize) -> impl Future<Output = F::Output> {
+    use crate::time::{millis, sleep_millis};
+    use core::{future::poll_fn, task::Poll};
+
+    let max_polls_per_tick = max_polls_per_tick.max(1);
+    async move {
+        let mut future = Box::pin(future);
+        let mut tick = millis();
+        let mut polls = 0;
+        loop {
+            let now = millis();
+            if now != tick {
+                tick = now;
+                polls = 0;
+            }
+            if polls >= max_polls_per_tick {
+                sleep_millis(1).await;
+                continue;
+            }
+            polls += 1;
+
+            // Poll the future once. When it has been woken, come back
+            // around the loop to check the budget before polling it
+            // again.
+            let mut polled = false;
+            let output = poll_fn(|ctx| {
+                if polled {
+                    return Poll::Ready(None);
+                }
+                polled = true;
+                future.as_mut().poll(ctx).map(Some)
+            })
+            .await;
+            if let Some(output) = output {
+                return output;
+            }
+        }
+    }
+}
+
+/// The name of the task currently being run
+///
+/// Returns `None` if the task was added without a name, or if no
+/// task is running. An interrupt handler sees the name of the task
+/// it interrupted.
+pub fn current_name() -> Option<&'static str> {
+    CURRENT_TASK.get().and_then(|(_, name)| name)
+}
+
+/// Storage with a separate value for each task
+///
+/// Each task sees its own value, which starts as `None`. Values live
+/// as long as the executor, since tasks never finish. This lets a
+/// driver keep per-task state, such as a buffer or a configuration,
+/// without a global static for each task.
+///
+/// Task-local storage must not be used from interrupt handlers,
+/// which would see the value of whichever task they interrupted.
+///
+/// ```
+/// use cntrlr::task::TaskLocal;
+///
+/// static CALLS: TaskLocal<usize> = TaskLocal::new();
+///
+/// fn count_call() -> usize {
+///     CALLS.with(|calls| {
+///         let calls = calls.get_or_insert(0);
+///         *calls += 1;
+///         *calls
+///     })
+/// }
+/// ```
+pub struct TaskLocal<T> {
+    values: UnsafeCell<Vec<(usize, Option<T>)>>,
+    borrowed: Flag,
+}
+
+unsafe impl<T> Sync for TaskLocal<T> {}
+
+impl<T> TaskLocal<T> {
+    /// Create a new task-local storage cell
+    pub const fn new() -> Self {
+        Self {
+            values: UnsafeCell::new(Vec::new()),
+            borrowed: Flag::new(false),
+        }
+    }
+
+    /// Access the current task's value
+    ///
+    /// # Panics
+    /// This function panics if it is called outside of a task, or
+    /// from within another call to `with` on the same cell.
+    pub fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Option<T>) -> R,
+    {
+        let id = match CURRENT_TASK.get() {
+            Some((id, _)) => id,
+            None => panic!("TaskLocal used outside of a task"),
+        };
+        if self.borrowed.swap(true, Ordering::Acquire) {
+            panic!("TaskLocal is already borrowed");
+        }
+
+        // Safety: The borrow flag ensures only one reference to the
+        // values exists at a time.
+        let values = unsafe { &mut *self.values.get() };
+        let idx = match values.iter().position(|(task, _)| *task == id) {
+            Some(idx) => idx,
+            None => {
+                values.push((id, None));
+                values.len() - 1
+            }
+        };
+        let out = f(&mut values[idx].1);
+        self.borrowed.store(false, Ordering::Release);
+        out
+    }
+}
+
 /// Interrupt-safe waker management
 ///
 /// This struct controls access to the underlying list of wakers using
@@ -118,26 +862,168 @@ impl WakerSet {
             })
         }
     }
+
+    /// Clear the set without waking any task
+    pub fn clear(&self) {
+        unsafe {
+            without_interrupts(|| {
+                (*self.0.get()).clear();
+            })
+        }
+    }
 }
 
 static WAKER_VTABLE: RawWakerVTable =
     RawWakerVTable::new(waker_clone, waker_wake, waker_wake_by_ref, waker_drop);
 
 unsafe fn waker_clone(waker: *const ()) -> RawWaker {
+    count_waker(waker, true);
     RawWaker::new(waker, &WAKER_VTABLE)
 }
 
 unsafe fn waker_wake(waker: *const ()) {
     waker_wake_by_ref(waker);
+    waker_drop(waker);
 }
 
 unsafe fn waker_wake_by_ref(waker: *const ()) {
-    let waker: *const AtomicBool = waker as _;
-    (*waker).store(true, Ordering::Release);
+    let state: *const TaskState = waker as _;
+    #[cfg(any(
+        doc,
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    ))]
+    if MEASURE_LATENCY.load(Ordering::Relaxed) && !(*state).wake.load(Ordering::Relaxed) {
+        // Latency is measured from the first wake since the last poll
+        (*state)
+            .woken_at
+            .store(crate::time::millis(), Ordering::Relaxed);
+    }
+    (*state).wake.store(true, Ordering::Release);
+    (*(*state).woken).store(true, Ordering::Release);
+    signal_wake();
+}
+
+unsafe fn waker_drop(waker: *const ()) {
+    count_waker(waker, false);
+}
+
+unsafe fn waker_new(state: &TaskState) -> Waker {
+    let waker = state as *const _ as *const _;
+    count_waker(waker, true);
+    Waker::from_raw(RawWaker::new(waker, &WAKER_VTABLE))
 }
 
-unsafe fn waker_drop(_waker: *const ()) {}
+/// Track the number of live wakers for a task
+///
+/// The count is used by stall detection, and is only kept in debug
+/// builds.
+#[allow(unused_variables)]
+unsafe fn count_waker(waker: *const (), added: bool) {
+    #[cfg(debug_assertions)]
+    {
+        let state: *const TaskState = waker as _;
+        without_interrupts(|| {
+            let count = (*state).wakers.load(Ordering::Relaxed);
+            let count = if added {
+                count + 1
+            } else {
+                count.saturating_sub(1)
+            };
+            (*state).wakers.store(count, Ordering::Relaxed);
+        });
+    }
+}
 
-unsafe fn waker_new(waker: &AtomicBool) -> Waker {
-    Waker::from_raw(RawWaker::new(waker as *const _ as *const _, &WAKER_VTABLE))
+/// Signal the processor that a task has been woken
+///
+/// On ARM, this sets the event register, so that a `wfe` also
+/// returns. On RISC-V, this raises the machine software interrupt,
+/// which ends a `wfi` even if the wake came from code which is not an
+/// interrupt handler.
+fn signal_wake() {
+    #[cfg(target_arch = "arm")]
+    unsafe {
+        asm!("sev");
+    }
+
+    #[cfg(mcu = "fe310g002")]
+    unsafe {
+        core::ptr::write_volatile(CLINT_MSIP, 1);
+    }
+}
+
+/// Clear the wake signal raised by a task waker
+///
+/// This is called from the machine software interrupt.
+#[cfg(any(doc, mcu = "fe310g002"))]
+pub(crate) fn clear_wake_signal() {
+    unsafe {
+        core::ptr::write_volatile(CLINT_MSIP, 0);
+    }
+}
+
+#[cfg(any(doc, mcu = "fe310g002"))]
+const CLINT_MSIP: *mut u32 = 0x0200_0000 as _;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+
+    fn waiting_task(woken: &AtomicBool) -> Task {
+        let task = Task {
+            id: 0,
+            state: TaskState::new(woken),
+            future: Box::pin(core::future::pending()),
+            name: Some("waiting"),
+        };
+        // The task holds a waker which is never woken
+        task.state.wakers.store(1, Ordering::Relaxed);
+        task
+    }
+
+    static STALLS: Value = Value::new(0);
+
+    fn count_stall(_: &str) {
+        STALLS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn stall_detector_reports_unpolled_task() {
+        let woken = AtomicBool::new(false);
+        let tasks = [waiting_task(&woken), waiting_task(&woken)];
+        let mut stalls = StallDetector::new(100, count_stall);
+        stalls.polled(0, 0);
+        stalls.polled(1, 0);
+
+        // The second task is polled every tick, as a sleeping task
+        // is, but the first is never polled again.
+        for now in 1..100 {
+            stalls.polled(1, now);
+            stalls.check(now, &tasks);
+        }
+        assert_eq!(STALLS.load(Ordering::Relaxed), 0);
+
+        for now in 100..300 {
+            stalls.polled(1, now);
+            stalls.check(now, &tasks);
+        }
+        assert_eq!(STALLS.load(Ordering::Relaxed), 1);
+
+        // Once the task is polled, it can be reported again
+        stalls.polled(0, 300);
+        stalls.polled(1, 350);
+        stalls.check(350, &tasks);
+        assert_eq!(STALLS.load(Ordering::Relaxed), 1);
+        stalls.polled(1, 400);
+        stalls.check(400, &tasks);
+        assert_eq!(STALLS.load(Ordering::Relaxed), 2);
+    }
 }
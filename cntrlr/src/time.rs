@@ -2,20 +2,1006 @@
 // Copyright 2020 Branan Riley <me@branan.info>
 
 //! Time functionality for Cntrlr boards
+//!
+//! Time is tracked with millisecond resolution. An [`Instant`] is a
+//! point on the board's wrapping millisecond counter, and a
+//! [`Duration`] is a span of time between two instants. A separate
+//! microsecond counter, [`micros`], is available for precise delays
+//! and bit-banged protocols.
+//!
+//! By default, the board's timer interrupts once each millisecond.
+//! [`set_tick_mode`] can lengthen the period, or interrupt only when
+//! the earliest sleep ends, to save power.
+//!
+//! A [`Timer`] waits in a queue which is checked by the timer
+//! interrupt, and is only woken once it expires. [`sleep_millis`]
+//! instead wakes every sleeping task whenever the timer interrupt
+//! ends a sleep, so timers are better suited to many tasks which
+//! sleep at once, or which sleep periodically.
 
+use crate::sync::without_interrupts;
+use alloc::vec::Vec;
 use cntrlr_macros::board_fn;
-use core::future::Future;
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36"
+))]
+use core::ops::DerefMut;
+use core::{
+    cell::UnsafeCell,
+    convert::TryFrom,
+    future::Future,
+    ops::{Add, AddAssign, Sub, SubAssign},
+    task::Waker,
+};
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+use core::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// A span of time, in milliseconds
+///
+/// Arithmetic with the operators panics on overflow. The `checked_`
+/// methods return `None` instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Duration(usize);
+
+impl Duration {
+    /// A duration of zero time
+    pub const ZERO: Self = Self(0);
+
+    /// The longest representable duration
+    pub const MAX: Self = Self(usize::MAX);
+
+    /// Create a duration from a number of milliseconds
+    pub const fn from_millis(millis: usize) -> Self {
+        Self(millis)
+    }
+
+    /// Create a duration from a number of seconds
+    ///
+    /// # Panics
+    /// This panics if the duration is too long to be represented.
+    pub const fn from_secs(secs: usize) -> Self {
+        Self(secs * 1000)
+    }
+
+    /// The number of whole seconds in this duration
+    pub const fn as_secs(self) -> usize {
+        self.0 / 1000
+    }
+
+    /// The number of milliseconds in this duration
+    pub const fn as_millis(self) -> usize {
+        self.0
+    }
+
+    /// Add two durations, or return `None` on overflow
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        self.0.checked_add(other.0).map(Self)
+    }
+
+    /// Subtract two durations, or return `None` if `other` is longer
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.0.checked_sub(other.0).map(Self)
+    }
+
+    /// Multiply a duration, or return `None` on overflow
+    pub fn checked_mul(self, other: usize) -> Option<Self> {
+        self.0.checked_mul(other).map(Self)
+    }
+
+    /// Divide a duration, or return `None` if `other` is zero
+    pub fn checked_div(self, other: usize) -> Option<Self> {
+        self.0.checked_div(other).map(Self)
+    }
+}
+
+impl Add for Duration {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        self.checked_add(other)
+            .expect("overflow when adding durations")
+    }
+}
+
+impl AddAssign for Duration {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Duration {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self.checked_sub(other)
+            .expect("overflow when subtracting durations")
+    }
+}
+
+impl SubAssign for Duration {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+/// The error when a [`core::time::Duration`] is too long to be converted
+#[derive(Debug)]
+pub struct DurationOverflow;
+
+impl TryFrom<core::time::Duration> for Duration {
+    type Error = DurationOverflow;
+
+    /// Convert a duration, rounding down to whole milliseconds
+    fn try_from(duration: core::time::Duration) -> Result<Self, DurationOverflow> {
+        usize::try_from(duration.as_millis())
+            .map(Self)
+            .map_err(|_| DurationOverflow)
+    }
+}
+
+impl From<Duration> for core::time::Duration {
+    fn from(duration: Duration) -> Self {
+        core::time::Duration::from_millis(duration.0 as u64)
+    }
+}
+
+/// A point in time, as measured by [`millis`]
+///
+/// Since the millisecond counter wraps, instants are only meaningful
+/// relative to each other when they are less than a full counter
+/// period apart. For the same reason, instants are not ordered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Instant(usize);
+
+impl Instant {
+    /// Create an instant from a value of the millisecond counter
+    pub const fn from_millis(millis: usize) -> Self {
+        Self(millis)
+    }
+
+    /// The value of the millisecond counter at this instant
+    pub const fn as_millis(self) -> usize {
+        self.0
+    }
+
+    /// The current instant
+    #[cfg(any(
+        doc,
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    ))]
+    pub fn now() -> Self {
+        Self(millis())
+    }
+
+    /// The time elapsed since this instant
+    #[cfg(any(
+        doc,
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    ))]
+    pub fn elapsed(self) -> Duration {
+        Self::now() - self
+    }
+
+    /// The time elapsed from `earlier` to this instant
+    pub fn duration_since(self, earlier: Self) -> Duration {
+        self - earlier
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Self;
+
+    fn add(self, duration: Duration) -> Self {
+        Self(self.0.wrapping_add(duration.0))
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Self;
+
+    fn sub(self, duration: Duration) -> Self {
+        Self(self.0.wrapping_sub(duration.0))
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+impl Sub for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Self) -> Duration {
+        Duration(self.0.wrapping_sub(earlier.0))
+    }
+}
 
 /// Retrieve the number of milliseconds the device has been running.
 ///
 /// This is a wrapping counter. Its size is dependent on the board
-/// used.
-#[board_fn(time, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+/// used. [`Instant::now`] returns the same count as an [`Instant`].
+#[board_fn(
+    time, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+)]
 pub fn millis() -> usize {}
 
+/// Retrieve the number of microseconds the device has been running.
+///
+/// This is a wrapping counter. Its size is dependent on the board
+/// used. The resolution also depends on the board: the Teensy 3.x and
+/// LC boards count the bus clock on the PIT, the Teensy 4.x boards
+/// count in steps of 10 microseconds, and the Red-V counts `mtime` in
+/// steps of about 31 microseconds.
+#[board_fn(
+    time, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+)]
+pub fn micros() -> usize {}
+
 /// Sleep this task for some number of milliseconds
 ///
 /// This task will be slept, and awoken once the nymber of
 /// milliseconds has passed.
-#[board_fn(time, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+#[board_fn(
+    time, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+)]
 pub fn sleep_millis(duration: usize) -> impl Future<Output = ()> {}
+
+/// Sleep this task for some number of microseconds
+///
+/// The task is slept for all but the last millisecond or two, which
+/// are spent busy-waiting on [`micros`] so that the task wakes on
+/// time. This blocks the rest of the executor for that tail, so
+/// long sleeps which do not need this precision should use
+/// [`sleep_millis`] instead.
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    )))
+)]
+pub fn sleep_micros(duration: usize) -> impl Future<Output = ()> {
+    async move {
+        let start = micros();
+
+        // A millisecond sleep can end up to a millisecond early, since
+        // it starts part way through one.
+        if duration >= 2000 {
+            sleep_millis(duration / 1000 - 1).await;
+        }
+        while micros().wrapping_sub(start) < duration {}
+    }
+}
+
+/// Sleep this task for a duration
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    )))
+)]
+pub fn sleep(duration: Duration) -> impl Future<Output = ()> {
+    sleep_millis(duration.as_millis())
+}
+
+/// Sleep this task until an instant
+///
+/// If the instant has already passed, this completes immediately.
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    )))
+)]
+pub fn sleep_until(instant: Instant) -> impl Future<Output = ()> {
+    let remaining = instant - Instant::now();
+
+    // An instant in the past wraps around to a very long duration.
+    let remaining = if remaining.as_millis() > usize::MAX / 2 {
+        Duration::ZERO
+    } else {
+        remaining
+    };
+    sleep(remaining)
+}
+
+/// A timer which completes at a deadline, or once each period
+///
+/// Timers wait in a single queue, ordered by deadline, which is
+/// checked by the board's timer interrupt. Only the timers which have
+/// expired are woken, so many tasks can wait on timers at once
+/// without each being polled on every tick.
+///
+/// A one-shot timer is a future which completes once its deadline
+/// has passed:
+///
+/// ```
+/// use cntrlr::time::{Duration, Timer};
+///
+/// async fn blink() {
+///     // Turn the LED on
+///     Timer::after(Duration::from_millis(100)).await;
+///     // Turn the LED off
+/// }
+/// ```
+///
+/// A periodic timer is awaited once per period with [`tick`](Timer::tick):
+///
+/// ```
+/// use cntrlr::time::{Duration, Timer};
+///
+/// async fn sample() -> ! {
+///     let mut timer = Timer::periodic(Duration::from_millis(10));
+///     loop {
+///         timer.tick().await;
+///         // Take a sample
+///     }
+/// }
+/// ```
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_40",
+        board = "teensy_41",
+        board = "teensy_lc"
+    )))
+)]
+#[derive(Debug)]
+pub struct Timer {
+    deadline: Instant,
+    period: Option<Duration>,
+    id: Option<usize>,
+}
+
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+impl Timer {
+    /// A timer which completes once `duration` has passed
+    pub fn after(duration: Duration) -> Self {
+        Self::at(Instant::now() + duration)
+    }
+
+    /// A timer which completes once an instant has passed
+    ///
+    /// If the instant has already passed, the timer completes
+    /// immediately.
+    pub fn at(deadline: Instant) -> Self {
+        Self {
+            deadline,
+            period: None,
+            id: None,
+        }
+    }
+
+    /// A timer which completes once each `period`
+    ///
+    /// The first period starts now. Each period is measured from the
+    /// end of the last, rather than from when the timer was awaited,
+    /// so the timer does not drift. If a task falls more than a
+    /// period behind, the missed periods complete immediately until
+    /// it catches up.
+    ///
+    /// # Panics
+    /// Panics if `period` is zero.
+    pub fn periodic(period: Duration) -> Self {
+        assert!(period != Duration::ZERO, "Timer period is zero");
+        Self {
+            deadline: Instant::now() + period,
+            period: Some(period),
+            id: None,
+        }
+    }
+
+    /// The instant at which this timer next completes
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Wait for the timer's next deadline
+    ///
+    /// This is the same as awaiting the timer itself, but can be
+    /// awaited repeatedly on a periodic timer.
+    pub fn tick(&mut self) -> &mut Self {
+        self
+    }
+}
+
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+impl Future for Timer {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+        let now = Instant::now();
+        // A deadline in the past wraps around to a very long duration.
+        let remaining = (self.deadline - now).as_millis();
+        if remaining == 0 || remaining > usize::MAX / 2 {
+            if let Some(id) = self.id.take() {
+                TIMERS.remove(id);
+            }
+            if let Some(period) = self.period {
+                self.deadline += period;
+            }
+            Poll::Ready(())
+        } else {
+            let deadline = self.deadline.as_millis();
+            self.id = Some(TIMERS.insert(self.id, now.as_millis(), deadline, ctx.waker().clone()));
+            note_timer(deadline);
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_40",
+    board = "teensy_41",
+    board = "teensy_lc"
+))]
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            TIMERS.remove(id);
+        }
+    }
+}
+
+/// Make sure the board's timer interrupt fires at a timer's deadline
+///
+/// The deadline is a value of the millisecond counter. The board
+/// calls [`wake_timers`] from the interrupt.
+#[board_fn(
+    time, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+)]
+pub(crate) fn note_timer(deadline: usize) {}
+
+/// Wake every timer whose deadline has passed
+///
+/// This is called by the board's timer interrupt, with the current
+/// value of the millisecond counter. Returns the deadline of the
+/// earliest timer still waiting, which the board should pass to
+/// [`note_timer`].
+pub(crate) fn wake_timers(now: usize) -> Option<usize> {
+    TIMERS.wake(now)
+}
+
+/// The queue of timers waiting on their deadlines
+struct TimerQueue(UnsafeCell<TimerState>);
+
+struct TimerState {
+    next_id: usize,
+    entries: Vec<TimerEntry>,
+}
+
+struct TimerEntry {
+    deadline: usize,
+    id: usize,
+    waker: Waker,
+}
+
+unsafe impl Sync for TimerQueue {}
+
+static TIMERS: TimerQueue = TimerQueue(UnsafeCell::new(TimerState {
+    next_id: 0,
+    entries: Vec::new(),
+}));
+
+impl TimerQueue {
+    /// Run a closure with the queue locked
+    fn locked<R>(&self, f: impl FnOnce(&mut TimerState) -> R) -> R {
+        // Safety: Interrupts are disabled, so this is the only reference
+        without_interrupts(|| f(unsafe { &mut *self.0.get() }))
+    }
+
+    /// Queue a waker until a deadline, returning its timer's id
+    ///
+    /// If the timer was already queued, its old entry is replaced.
+    fn insert(&self, id: Option<usize>, now: usize, deadline: usize, waker: Waker) -> usize {
+        self.locked(|state| {
+            let id = match id {
+                Some(id) => {
+                    state.entries.retain(|entry| entry.id != id);
+                    id
+                }
+                None => {
+                    state.next_id = state.next_id.wrapping_add(1);
+                    state.next_id
+                }
+            };
+            // Deadlines are compared relative to now, as the counter
+            // may wrap between them.
+            let position = state
+                .entries
+                .iter()
+                .position(|entry| entry.deadline.wrapping_sub(now) > deadline.wrapping_sub(now))
+                .unwrap_or_else(|| state.entries.len());
+            state.entries.insert(
+                position,
+                TimerEntry {
+                    deadline,
+                    id,
+                    waker,
+                },
+            );
+            id
+        })
+    }
+
+    /// Remove a timer from the queue
+    fn remove(&self, id: usize) {
+        self.locked(|state| state.entries.retain(|entry| entry.id != id));
+    }
+
+    /// Wake the expired timers, and return the earliest deadline left
+    fn wake(&self, now: usize) -> Option<usize> {
+        self.locked(|state| {
+            while let Some(entry) = state.entries.first() {
+                if now.wrapping_sub(entry.deadline) > usize::MAX / 2 {
+                    return Some(entry.deadline);
+                }
+                state.entries.remove(0).waker.wake();
+            }
+            None
+        })
+    }
+}
+
+/// Busy-wait for at least some number of core clock cycles
+///
+/// Unlike [`sleep`], this blocks the whole executor. It does not
+/// depend on interrupts or the millisecond timer, so it is safe to
+/// call from `__cntrlr_board_init` and from interrupt handlers.
+#[board_fn(
+    time, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+)]
+pub fn delay_cycles(cycles: usize) {}
+
+/// Busy-wait for at least some number of microseconds
+///
+/// This is based on [`delay_cycles`] and the current core clock, and
+/// is safe to call from the same places. It may run long if it is
+/// interrupted.
+#[board_fn(
+    time, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+)]
+pub fn delay_us_blocking(us: usize) {}
+
+/// Measure the core clock against an independent reference clock
+///
+/// Returns the configured and the measured core clock frequency, in
+/// Hz, or `None` if the reference is not running. This busy-waits for
+/// about 100 milliseconds.
+///
+/// The Teensy 3.x and LC boards measure against the 1kHz low-power
+/// oscillator, which is only accurate enough to catch gross errors,
+/// such as a core clock which never switched to the crystal. The
+/// Teensy 4.x boards measure against the SysTick's 100kHz reference,
+/// which is derived from the crystal. The Red V measures against the
+/// clock which drives `mtime`.
+#[board_fn(
+    time, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_40, teensy_41, teensy_lc
+)]
+pub fn measure_core_clock() -> Option<(usize, usize)> {}
+
+/// How the board's timer interrupt is scheduled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TickMode {
+    /// Interrupt every this many milliseconds
+    ///
+    /// Sleeping tasks are only woken on a tick, so sleeps are rounded
+    /// up to a whole number of periods. The default is a period of 1
+    /// millisecond.
+    Periodic(usize),
+
+    /// Interrupt only when the earliest sleep ends
+    ///
+    /// Timers which cannot count far enough for the earliest sleep
+    /// interrupt at their longest period instead.
+    Tickless,
+}
+
+/// An error from [`set_tick_mode`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TickModeError {
+    /// The tick mode cannot be set because the period is zero or longer than the timer can count
+    InvalidPeriod,
+}
+
+/// Set how the board's timer interrupt is scheduled
+///
+/// [`millis`] stays accurate to the millisecond in every mode.
+#[board_fn(time, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn set_tick_mode(mode: TickMode) -> Result<(), TickModeError> {}
+
+/// An error from [`with_deadline_strict`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DeadlineError {
+    /// The deadline cannot be armed because the board's watchdog cannot be enabled after startup
+    Unsupported,
+
+    /// The deadline cannot be armed because another strict deadline, or the watchdog, is in use
+    InUse,
+
+    /// The deadline cannot be armed because it is longer than the watchdog can count
+    TooLong,
+}
+
+/// Arm the board's watchdog to reset the board after some number of milliseconds
+#[board_fn(time, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub(crate) fn arm_strict_deadline(duration: usize) -> Result<(), DeadlineError> {}
+
+/// Disarm the watchdog armed by [`arm_strict_deadline`]
+#[board_fn(time, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub(crate) fn disarm_strict_deadline() {}
+
+/// Whether the last reset was caused by the board's watchdog
+///
+/// This is how firmware can recognize, after the reset, that a
+/// [`with_deadline_strict`] deadline was missed. On the Red-V,
+/// rebooting the board also resets through the watchdog.
+#[board_fn(time, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn reset_by_watchdog() -> bool {}
+
+/// Run a future with a deadline enforced by the hardware
+///
+/// The board's watchdog is armed when the future is first polled,
+/// and disarmed once it completes or is dropped. If it has not
+/// completed after `duration`, the watchdog resets the board, even
+/// if the executor is stuck or interrupts are disabled. This is for
+/// sections which must not overrun, where a timeout enforced by the
+/// executor cannot be trusted.
+///
+/// Only one strict deadline can be armed at a time. The Teensy 3.x
+/// boards use the WDOG, and lengthen deadlines below 4 milliseconds
+/// to 4. The Red-V uses the watchdog in the always-on domain. The
+/// Teensy LC's watchdog cannot be enabled after startup, so its
+/// strict deadlines always fail with [`DeadlineError::Unsupported`].
+///
+/// ```
+/// use cntrlr::time::{reset_by_watchdog, with_deadline_strict, Duration};
+///
+/// async fn actuate() {
+///     if reset_by_watchdog() {
+///         // Recover from a missed deadline
+///     }
+///     with_deadline_strict(Duration::from_millis(50), async {
+///         // Drive the actuator
+///     })
+///     .await
+///     .expect("Could not arm the deadline");
+/// }
+/// ```
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_lc"
+    )))
+)]
+pub fn with_deadline_strict<F: Future>(
+    duration: Duration,
+    fut: F,
+) -> impl Future<Output = Result<F::Output, DeadlineError>> {
+    /// Disarms the deadline when the future completes or is dropped
+    struct Armed;
+
+    impl Drop for Armed {
+        fn drop(&mut self) {
+            disarm_strict_deadline();
+        }
+    }
+
+    async move {
+        arm_strict_deadline(duration.as_millis())?;
+        let _armed = Armed;
+        Ok(fut.await)
+    }
+}
+
+/// A calendar date and time
+///
+/// The fields are in order of significance, so date-times compare in
+/// time order. No time zone is implied, though the conversions to and
+/// from Unix seconds assume UTC, without leap seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    /// The year, such as 2020
+    pub year: u32,
+
+    /// The month, from 1 to 12
+    pub month: u8,
+
+    /// The day of the month, from 1
+    pub day: u8,
+
+    /// The hour, from 0 to 23
+    pub hour: u8,
+
+    /// The minute, from 0 to 59
+    pub minute: u8,
+
+    /// The second, from 0 to 59
+    pub second: u8,
+}
+
+/// An error from a [`DateTime`] conversion
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DateTimeError {
+    /// The date-time cannot be converted because a field is out of range
+    InvalidField,
+
+    /// The date-time cannot be converted because it is before the Unix epoch
+    BeforeEpoch,
+}
+
+impl DateTime {
+    /// The date-time some number of seconds after the Unix epoch
+    pub fn from_unix_seconds(seconds: u64) -> Self {
+        let days = seconds / 86400;
+        let time = seconds % 86400;
+
+        // Days to a civil date, in 400-year eras which start on the
+        // 1st of March, so that leap days fall at the end of a year.
+        let days = days + 719_468;
+        let era = days / 146_097;
+        let day_of_era = days % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_from_march = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+        let month = if month_from_march < 10 {
+            month_from_march + 3
+        } else {
+            month_from_march - 9
+        };
+        let year = year_of_era + era * 400 + (month <= 2) as u64;
+
+        Self {
+            year: year as u32,
+            month: month as u8,
+            day: day as u8,
+            hour: (time / 3600) as u8,
+            minute: (time / 60 % 60) as u8,
+            second: (time % 60) as u8,
+        }
+    }
+
+    /// The number of seconds from the Unix epoch to this date-time
+    pub fn to_unix_seconds(&self) -> Result<u64, DateTimeError> {
+        if !(1..=12).contains(&self.month)
+            || self.day == 0
+            || self.day > days_in_month(self.year, self.month)
+            || self.hour > 23
+            || self.minute > 59
+            || self.second > 59
+        {
+            return Err(DateTimeError::InvalidField);
+        }
+        if self.year < 1970 {
+            return Err(DateTimeError::BeforeEpoch);
+        }
+
+        // The inverse of the conversion in from_unix_seconds
+        let year = self.year as u64 - (self.month <= 2) as u64;
+        let month = self.month as u64;
+        let era = year / 400;
+        let year_of_era = year % 400;
+        let month_from_march = if month > 2 { month - 3 } else { month + 9 };
+        let day_of_year = (153 * month_from_march + 2) / 5 + self.day as u64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146_097 + day_of_era - 719_468;
+
+        Ok(days * 86400 + self.hour as u64 * 3600 + self.minute as u64 * 60 + self.second as u64)
+    }
+}
+
+fn days_in_month(year: u32, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// A real-time clock
+///
+/// The clock counts seconds from an application-chosen epoch, and
+/// keeps counting while the board sleeps. Its count is independent of
+/// [`millis`], and is not affected by the tick mode. The calendar
+/// methods take the epoch to be the Unix epoch.
+pub trait Rtc {
+    /// The future for [`Self::alarm()`]
+    type AlarmFuture<'a>: Future<Output = ()> + 'a
+    where
+        Self: 'a;
+
+    /// The number of seconds counted by the clock
+    fn seconds(&self) -> u64;
+
+    /// Set the number of seconds counted by the clock
+    ///
+    /// The clock is started if it was not already counting.
+    fn set_seconds(&mut self, seconds: u64);
+
+    /// Wait until the clock reaches `seconds`
+    ///
+    /// If the clock has already passed `seconds`, this completes
+    /// immediately. Only one alarm can be pending at a time.
+    fn alarm<'a>(&'a mut self, seconds: u64) -> Self::AlarmFuture<'a>
+    where
+        Self: 'a;
+
+    /// The calendar date and time of the clock
+    fn date_time(&self) -> DateTime {
+        DateTime::from_unix_seconds(self.seconds())
+    }
+
+    /// Set the clock to a calendar date and time
+    fn set_date_time(&mut self, date_time: DateTime) -> Result<(), DateTimeError> {
+        self.set_seconds(date_time.to_unix_seconds()?);
+        Ok(())
+    }
+}
+
+/// The board's real-time clock
+///
+/// The Red-V counts the low-frequency clock in the always-on domain,
+/// which keeps running while the core is powered down. The count is
+/// lost only when the board loses power.
+///
+/// The Teensy 3.x boards use the RTC, which is powered from the VBAT
+/// pin. With a coin cell on that pin, the count survives while the
+/// board is unpowered. The Teensy 3.0 and 3.2 need a 32.768kHz
+/// crystal soldered to the board for the RTC to count.
+#[board_fn(time, red_v, teensy_30, teensy_32, teensy_35, teensy_36)]
+pub fn rtc() -> impl DerefMut<Target = impl Rtc> {}
@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Capacitive touch sensing for Cntrlr boards
+//!
+//! [`touch_read`] measures the capacitance of a pin, like
+//! Teensyduino's `touchRead`. The result rises when a finger touches
+//! the pin, or a pad connected to it. The reading of an untouched
+//! pin depends on the board and on what is connected, so
+//! applications should compare against a baseline measured at
+//! startup.
+//!
+//! ```
+//! use cntrlr::prelude::*;
+//!
+//! #[entry]
+//! async fn main() -> ! {
+//!     let baseline = touch_read(0).await.expect("Failed to read pin");
+//!     loop {
+//!         let touched = touch_read(0).await.unwrap() > baseline + baseline / 2;
+//!         led().set(touched);
+//!     }
+//! }
+//! ```
+//!
+//! The Teensy 3.5 has no touch sensing module.
+
+use cntrlr_macros::board_fn;
+#[cfg(any(
+    doc,
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+use core::future::Future;
+
+/// Configuration for touch sensing
+///
+/// Larger values make each reading larger and less noisy, but
+/// slower.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TouchOptions {
+    /// The current which charges the pin
+    ///
+    /// The Teensy 3.0 and 3.2 support 0 to 15, for 2 to 32
+    /// microamps. The Teensy 3.6 and LC support 0 to 7, for 1 to
+    /// 128 microamps.
+    pub current: u32,
+
+    /// The number of scans summed into each reading, from 1 to 32
+    pub scans: u32,
+
+    /// The divider of the oscillator which is counted, as a power of two from 0 to 7
+    pub prescale: u32,
+}
+
+impl TouchOptions {
+    /// The default touch configuration
+    ///
+    /// These are the settings Teensyduino uses.
+    pub const DEFAULT: Self = Self {
+        current: 2,
+        scans: 10,
+        prescale: 2,
+    };
+}
+
+impl Default for TouchOptions {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// An error from touch sensing
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TouchError {
+    /// The pin cannot be used for touch sensing
+    InvalidPin,
+
+    /// An option is out of range for this board
+    InvalidOptions,
+
+    /// The touch sensing module is in use by another module
+    TsiInUse,
+}
+
+/// Configure touch sensing
+///
+/// The options apply to every reading started after this.
+#[board_fn(touch, teensy_30, teensy_32, teensy_36, teensy_lc)]
+pub fn set_touch_options(options: TouchOptions) -> Result<(), TouchError> {}
+
+/// The current touch sensing configuration
+#[board_fn(touch, teensy_30, teensy_32, teensy_36, teensy_lc)]
+pub fn touch_options() -> TouchOptions {}
+
+/// Measure the capacitance of a pin
+///
+/// The pin is disconnected from its digital functions. If another
+/// reading is in progress, this waits for it to finish. A reading
+/// with the default options takes about a millisecond.
+#[board_fn(touch, teensy_30, teensy_32, teensy_36, teensy_lc)]
+pub fn touch_read(pin: usize) -> impl Future<Output = Result<u16, TouchError>> {}
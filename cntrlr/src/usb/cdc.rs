@@ -0,0 +1,233 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! USB serial ports
+//!
+//! The CDC Abstract Control Model is the class which hosts use for
+//! USB serial ports. It needs no drivers on Linux, macOS, or Windows.
+
+use super::{
+    Allocator, Bus, Class, DescriptorWriter, EndpointIn, EndpointOut, EndpointType, Recipient,
+    Request, RequestKind,
+};
+use alloc::vec::Vec;
+use core::{cell::Cell, ops::Range};
+
+const CLASS_COMM: u8 = 0x02;
+const CLASS_DATA: u8 = 0x0A;
+const SUBCLASS_ACM: u8 = 0x02;
+const PROTOCOL_NONE: u8 = 0x00;
+
+const DESCRIPTOR_CS_INTERFACE: u8 = 0x24;
+const FUNCTIONAL_HEADER: u8 = 0x00;
+const FUNCTIONAL_CALL_MANAGEMENT: u8 = 0x01;
+const FUNCTIONAL_ACM: u8 = 0x02;
+const FUNCTIONAL_UNION: u8 = 0x06;
+
+const REQUEST_SET_LINE_CODING: u8 = 0x20;
+const REQUEST_GET_LINE_CODING: u8 = 0x21;
+const REQUEST_SET_CONTROL_LINE_STATE: u8 = 0x22;
+const REQUEST_SEND_BREAK: u8 = 0x23;
+
+/// The packet size of the data endpoints
+pub const PACKET_SIZE: u16 = 64;
+
+/// The serial settings requested by the host
+///
+/// These have no effect on the USB link itself. They are useful for a
+/// device which bridges to a real serial port.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineCoding {
+    /// The baud rate
+    pub baud: u32,
+
+    /// The number of stop bits, where 0 is 1 bit, 1 is 1.5 bits, and 2 is 2 bits
+    pub stop_bits: u8,
+
+    /// The parity, where 0 is none, 1 is odd, 2 is even, 3 is mark, and 4 is space
+    pub parity: u8,
+
+    /// The number of data bits
+    pub data_bits: u8,
+}
+
+impl LineCoding {
+    fn to_bytes(self) -> [u8; 7] {
+        let [b0, b1, b2, b3] = self.baud.to_le_bytes();
+        [b0, b1, b2, b3, self.stop_bits, self.parity, self.data_bits]
+    }
+}
+
+/// A USB serial port
+pub struct Cdc<'a, B> {
+    comm_interface: u8,
+    data_interface: u8,
+    notify: EndpointIn<'a, B>,
+    read: EndpointOut<'a, B>,
+    write: EndpointIn<'a, B>,
+    line_coding: Cell<LineCoding>,
+    dtr: Cell<bool>,
+    rts: Cell<bool>,
+    configured: Cell<bool>,
+}
+
+impl<'a, B> Cdc<'a, B>
+where
+    B: Bus,
+{
+    /// Create a serial port, with its interfaces and endpoints
+    pub fn new(allocator: &'a Allocator<B>) -> Self {
+        Self {
+            comm_interface: allocator.interface(),
+            data_interface: allocator.interface(),
+            notify: allocator.endpoint_in(EndpointType::Interrupt, 8),
+            read: allocator.endpoint_out(EndpointType::Bulk, PACKET_SIZE),
+            write: allocator.endpoint_in(EndpointType::Bulk, PACKET_SIZE),
+            line_coding: Cell::new(LineCoding {
+                baud: 115200,
+                stop_bits: 0,
+                parity: 0,
+                data_bits: 8,
+            }),
+            dtr: Cell::new(false),
+            rts: Cell::new(false),
+            configured: Cell::new(false),
+        }
+    }
+
+    /// The serial settings last set by the host
+    pub fn line_coding(&self) -> LineCoding {
+        self.line_coding.get()
+    }
+
+    /// Whether the host has asserted DTR
+    ///
+    /// Most hosts assert DTR while a terminal has the port open.
+    pub fn dtr(&self) -> bool {
+        self.dtr.get()
+    }
+
+    /// Whether the host has asserted RTS
+    pub fn rts(&self) -> bool {
+        self.rts.get()
+    }
+
+    /// Whether the host has configured the device
+    pub fn is_configured(&self) -> bool {
+        self.configured.get()
+    }
+
+    /// Receive one packet from the host
+    ///
+    /// `buf` must be at least [`PACKET_SIZE`] bytes. Returns the
+    /// number of bytes received.
+    pub fn read<'b>(&'b self, buf: &'b mut [u8]) -> B::ReadFuture<'b> {
+        self.read.read(buf)
+    }
+
+    /// Send one packet to the host
+    ///
+    /// `buf` must be no longer than [`PACKET_SIZE`] bytes. A write of
+    /// exactly [`PACKET_SIZE`] bytes should be followed by a shorter
+    /// write, which may be empty, or the host may wait for more data
+    /// before passing it on.
+    pub fn write<'b>(&'b self, buf: &'b [u8]) -> B::WriteFuture<'b> {
+        self.write.write(buf)
+    }
+}
+
+impl<'a, B> Class for Cdc<'a, B>
+where
+    B: Bus,
+{
+    fn interfaces(&self) -> Range<u8> {
+        self.comm_interface..self.data_interface + 1
+    }
+
+    fn descriptors(&self, writer: &mut DescriptorWriter) {
+        writer.interface_association(
+            self.comm_interface,
+            2,
+            CLASS_COMM,
+            SUBCLASS_ACM,
+            PROTOCOL_NONE,
+        );
+
+        writer.interface(
+            self.comm_interface,
+            1,
+            CLASS_COMM,
+            SUBCLASS_ACM,
+            PROTOCOL_NONE,
+            0,
+        );
+        writer.write(DESCRIPTOR_CS_INTERFACE, &[FUNCTIONAL_HEADER, 0x10, 0x01]);
+        writer.write(
+            DESCRIPTOR_CS_INTERFACE,
+            &[FUNCTIONAL_CALL_MANAGEMENT, 0x00, self.data_interface],
+        );
+        // Supports the line coding, control line state, and break
+        // requests.
+        writer.write(DESCRIPTOR_CS_INTERFACE, &[FUNCTIONAL_ACM, 0x06]);
+        writer.write(
+            DESCRIPTOR_CS_INTERFACE,
+            &[FUNCTIONAL_UNION, self.comm_interface, self.data_interface],
+        );
+        writer.endpoint_in(&self.notify, 255);
+
+        writer.interface(self.data_interface, 2, CLASS_DATA, 0, 0, 0);
+        writer.endpoint_out(&self.read, 0);
+        writer.endpoint_in(&self.write, 0);
+    }
+
+    fn control_in(&self, request: &Request, response: &mut Vec<u8>) -> bool {
+        if request.kind() != RequestKind::Class
+            || request.recipient() != Recipient::Interface
+            || request.index as u8 != self.comm_interface
+        {
+            return false;
+        }
+        match request.request {
+            REQUEST_GET_LINE_CODING => {
+                response.extend_from_slice(&self.line_coding.get().to_bytes());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn control_out(&self, request: &Request, data: &[u8]) -> bool {
+        if request.kind() != RequestKind::Class
+            || request.recipient() != Recipient::Interface
+            || request.index as u8 != self.comm_interface
+        {
+            return false;
+        }
+        match request.request {
+            REQUEST_SET_LINE_CODING if data.len() >= 7 => {
+                self.line_coding.set(LineCoding {
+                    baud: u32::from_le_bytes([data[0], data[1], data[2], data[3]]),
+                    stop_bits: data[4],
+                    parity: data[5],
+                    data_bits: data[6],
+                });
+                true
+            }
+            REQUEST_SET_CONTROL_LINE_STATE => {
+                self.dtr.set(request.value & 0x01 != 0);
+                self.rts.set(request.value & 0x02 != 0);
+                true
+            }
+            REQUEST_SEND_BREAK => true,
+            _ => false,
+        }
+    }
+
+    fn set_configured(&self, configured: bool) {
+        self.configured.set(configured);
+        if !configured {
+            self.dtr.set(false);
+            self.rts.set(false);
+        }
+    }
+}
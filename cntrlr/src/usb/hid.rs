@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! USB human interface devices
+//!
+//! Besides keyboards and mice, HID is useful as a driverless control
+//! channel: with a vendor-defined report descriptor, such as
+//! [`RAW_REPORT_DESCRIPTOR`], hosts pass reports between the device
+//! and an application without interpreting them.
+
+use super::{
+    Allocator, Bus, Class, DescriptorWriter, EndpointIn, EndpointOut, EndpointType, Recipient,
+    Request, RequestKind,
+};
+use alloc::vec::Vec;
+use core::{cell::Cell, ops::Range};
+
+const CLASS_HID: u8 = 0x03;
+
+const DESCRIPTOR_HID: u8 = 0x21;
+const DESCRIPTOR_REPORT: u8 = 0x22;
+
+const REQUEST_GET_DESCRIPTOR: u8 = 6;
+const REQUEST_GET_REPORT: u8 = 0x01;
+const REQUEST_GET_IDLE: u8 = 0x02;
+const REQUEST_GET_PROTOCOL: u8 = 0x03;
+const REQUEST_SET_IDLE: u8 = 0x0A;
+const REQUEST_SET_PROTOCOL: u8 = 0x0B;
+
+/// A report descriptor for 64-byte vendor-defined input and output reports
+///
+/// This is the usual descriptor for raw HID, which hosts let
+/// applications read and write directly.
+pub const RAW_REPORT_DESCRIPTOR: &[u8] = &[
+    0x06, 0xAB, 0xFF, // Usage page (vendor defined 0xFFAB)
+    0x0A, 0x00, 0x02, // Usage (0x0200)
+    0xA1, 0x01, // Collection (application)
+    0x75, 0x08, //   Report size (8 bits)
+    0x15, 0x00, //   Logical minimum (0)
+    0x26, 0xFF, 0x00, //   Logical maximum (255)
+    0x95, 0x40, //   Report count (64)
+    0x09, 0x01, //   Usage (0x01)
+    0x81, 0x02, //   Input (data, variable, absolute)
+    0x95, 0x40, //   Report count (64)
+    0x09, 0x02, //   Usage (0x02)
+    0x91, 0x02, //   Output (data, variable, absolute)
+    0xC0, // End collection
+];
+
+/// A human interface device
+pub struct Hid<'a, B> {
+    interface: u8,
+    report_descriptor: &'static [u8],
+    interval: u8,
+    read: EndpointOut<'a, B>,
+    write: EndpointIn<'a, B>,
+    idle: Cell<u8>,
+    protocol: Cell<u8>,
+}
+
+impl<'a, B> Hid<'a, B>
+where
+    B: Bus,
+{
+    /// Create a HID, with its interface and endpoints
+    ///
+    /// Reports are exchanged in single packets of up to
+    /// `max_packet_size` bytes, which the host polls for every
+    /// `interval` milliseconds.
+    pub fn new(
+        allocator: &'a Allocator<B>,
+        report_descriptor: &'static [u8],
+        max_packet_size: u16,
+        interval: u8,
+    ) -> Self {
+        Self {
+            interface: allocator.interface(),
+            report_descriptor,
+            interval,
+            read: allocator.endpoint_out(EndpointType::Interrupt, max_packet_size),
+            write: allocator.endpoint_in(EndpointType::Interrupt, max_packet_size),
+            idle: Cell::new(0),
+            protocol: Cell::new(1),
+        }
+    }
+
+    /// Receive an output report from the host
+    ///
+    /// `buf` must be at least the maximum packet size. Returns the
+    /// length of the report.
+    pub fn read_report<'b>(&'b self, buf: &'b mut [u8]) -> B::ReadFuture<'b> {
+        self.read.read(buf)
+    }
+
+    /// Send an input report to the host
+    ///
+    /// `report` must be no longer than the maximum packet size.
+    pub fn write_report<'b>(&'b self, report: &'b [u8]) -> B::WriteFuture<'b> {
+        self.write.write(report)
+    }
+
+    fn hid_descriptor(&self) -> [u8; 7] {
+        let [len_lo, len_hi] = (self.report_descriptor.len() as u16).to_le_bytes();
+        [0x11, 0x01, 0x00, 1, DESCRIPTOR_REPORT, len_lo, len_hi]
+    }
+}
+
+impl<'a, B> Class for Hid<'a, B>
+where
+    B: Bus,
+{
+    fn interfaces(&self) -> Range<u8> {
+        self.interface..self.interface + 1
+    }
+
+    fn descriptors(&self, writer: &mut DescriptorWriter) {
+        writer.interface(self.interface, 2, CLASS_HID, 0, 0, 0);
+        writer.write(DESCRIPTOR_HID, &self.hid_descriptor());
+        writer.endpoint_in(&self.write, self.interval);
+        writer.endpoint_out(&self.read, self.interval);
+    }
+
+    fn control_in(&self, request: &Request, response: &mut Vec<u8>) -> bool {
+        if request.recipient() != Recipient::Interface || request.index as u8 != self.interface {
+            return false;
+        }
+        match (request.kind(), request.request) {
+            (RequestKind::Standard, REQUEST_GET_DESCRIPTOR) => match (request.value >> 8) as u8 {
+                DESCRIPTOR_REPORT => {
+                    response.extend_from_slice(self.report_descriptor);
+                    true
+                }
+                DESCRIPTOR_HID => {
+                    response.push(9);
+                    response.push(DESCRIPTOR_HID);
+                    response.extend_from_slice(&self.hid_descriptor());
+                    true
+                }
+                _ => false,
+            },
+            (RequestKind::Class, REQUEST_GET_REPORT) => {
+                // Reports are only sent on the interrupt endpoint, so
+                // an empty input report is returned here.
+                response.resize(request.length as usize, 0);
+                true
+            }
+            (RequestKind::Class, REQUEST_GET_IDLE) => {
+                response.push(self.idle.get());
+                true
+            }
+            (RequestKind::Class, REQUEST_GET_PROTOCOL) => {
+                response.push(self.protocol.get());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn control_out(&self, request: &Request, _data: &[u8]) -> bool {
+        if request.kind() != RequestKind::Class
+            || request.recipient() != Recipient::Interface
+            || request.index as u8 != self.interface
+        {
+            return false;
+        }
+        match request.request {
+            REQUEST_SET_IDLE => {
+                self.idle.set((request.value >> 8) as u8);
+                true
+            }
+            REQUEST_SET_PROTOCOL => {
+                self.protocol.set(request.value as u8);
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,976 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! USB device support
+//!
+//! A USB device is built from one or more [classes](Class), such as a
+//! [CDC serial port](cdc::Cdc) and a [HID](hid::Hid) control
+//! channel. Each class is created with an [`Allocator`], which gives
+//! it its interface and endpoint numbers. The [`Device`] then
+//! composes every class into a single configuration: it generates the
+//! device, configuration, and string descriptors, answers the
+//! standard control requests, and passes requests for each interface
+//! on to the class which owns it.
+//!
+//! The stack talks to the USB controller through the [`Bus`]
+//! trait. [`Device::run()`] handles the control endpoint, and each
+//! class's own endpoints can be read and written from any task while
+//! it runs.
+//!
+//! ```
+//! use cntrlr::{prelude::*, usb::{cdc::Cdc, hid::{self, Hid}, Allocator, Bus, Device, DeviceInfo}};
+//! # fn usb_bus() -> impl Bus { unimplemented!() }
+//!
+//! #[entry]
+//! async fn main() -> ! {
+//!     let usb = Allocator::new(usb_bus());
+//!     let serial = Cdc::new(&usb);
+//!     let control = Hid::new(&usb, hid::RAW_REPORT_DESCRIPTOR, 64, 10);
+//!     let info = DeviceInfo {
+//!         product: Some("Widget"),
+//!         ..DeviceInfo::new(0x16C0, 0x0483)
+//!     };
+//!     let device = Device::new(&usb, info, &[&serial, &control]);
+//!     // Join device.run() with tasks which use serial and control
+//!     # loop {}
+//! }
+//! ```
+
+pub mod cdc;
+pub mod hid;
+
+use alloc::{vec, vec::Vec};
+use core::{
+    cell::{Cell, RefCell},
+    fmt::Debug,
+    future::Future,
+};
+
+/// The largest packet on the control endpoint
+pub const CONTROL_PACKET_SIZE: u16 = 64;
+
+const DESCRIPTOR_DEVICE: u8 = 1;
+const DESCRIPTOR_CONFIGURATION: u8 = 2;
+const DESCRIPTOR_STRING: u8 = 3;
+const DESCRIPTOR_INTERFACE: u8 = 4;
+const DESCRIPTOR_ENDPOINT: u8 = 5;
+const DESCRIPTOR_INTERFACE_ASSOCIATION: u8 = 11;
+
+const REQUEST_GET_STATUS: u8 = 0;
+const REQUEST_CLEAR_FEATURE: u8 = 1;
+const REQUEST_SET_FEATURE: u8 = 3;
+const REQUEST_SET_ADDRESS: u8 = 5;
+const REQUEST_GET_DESCRIPTOR: u8 = 6;
+const REQUEST_GET_CONFIGURATION: u8 = 8;
+const REQUEST_SET_CONFIGURATION: u8 = 9;
+const REQUEST_GET_INTERFACE: u8 = 10;
+const REQUEST_SET_INTERFACE: u8 = 11;
+
+const FEATURE_ENDPOINT_HALT: u16 = 0;
+
+/// An event on the bus
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// The host reset the bus
+    ///
+    /// The device is deconfigured, and its address is zero.
+    Reset,
+
+    /// A setup packet was received on the control endpoint
+    Setup([u8; 8]),
+
+    /// The bus was idle long enough for the device to suspend
+    Suspend,
+
+    /// The bus resumed after a suspend
+    Resume,
+}
+
+/// The transfer type of an endpoint
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EndpointType {
+    /// Control transfers
+    Control,
+
+    /// Isochronous transfers
+    Isochronous,
+
+    /// Bulk transfers
+    Bulk,
+
+    /// Interrupt transfers
+    Interrupt,
+}
+
+/// The direction of an endpoint, from the host's point of view
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Data from the host to the device
+    Out,
+
+    /// Data from the device to the host
+    In,
+}
+
+/// The address of an endpoint: its number and direction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EndpointAddress(u8);
+
+impl EndpointAddress {
+    /// Create an endpoint address
+    pub const fn new(number: u8, direction: Direction) -> Self {
+        match direction {
+            Direction::Out => Self(number & 0x0F),
+            Direction::In => Self(number & 0x0F | 0x80),
+        }
+    }
+
+    /// The endpoint number
+    pub const fn number(self) -> u8 {
+        self.0 & 0x0F
+    }
+
+    /// The endpoint direction
+    pub const fn direction(self) -> Direction {
+        if self.0 & 0x80 == 0 {
+            Direction::Out
+        } else {
+            Direction::In
+        }
+    }
+
+    /// The address as it appears in descriptors and requests
+    pub const fn raw(self) -> u8 {
+        self.0
+    }
+}
+
+/// A USB device controller
+///
+/// This is implemented by each MCU's USB peripheral. Every method
+/// takes `&self`, so that the control endpoint and each class's
+/// endpoints can be used from different tasks at once. An
+/// implementation only needs to support one read and one write in
+/// flight per endpoint.
+pub trait Bus {
+    /// The error type
+    type Error: Debug;
+
+    /// The future for [`Self::event()`]
+    type EventFuture<'a>: Future<Output = Result<Event, Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// The future for [`Self::read()`]
+    type ReadFuture<'a>: Future<Output = Result<usize, Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// The future for [`Self::write()`]
+    type WriteFuture<'a>: Future<Output = Result<(), Self::Error>> + 'a
+    where
+        Self: 'a;
+
+    /// The number of endpoint numbers, including endpoint 0
+    fn endpoint_count(&self) -> u8;
+
+    /// Enable an endpoint
+    ///
+    /// Endpoints other than endpoint 0 are enabled when the host
+    /// configures the device, and disabled by a bus reset.
+    fn enable_endpoint(
+        &self,
+        address: EndpointAddress,
+        kind: EndpointType,
+        max_packet_size: u16,
+    ) -> Result<(), Self::Error>;
+
+    /// Set the device address assigned by the host
+    fn set_address(&self, address: u8);
+
+    /// Stall or unstall an endpoint
+    fn set_stalled(&self, address: EndpointAddress, stalled: bool);
+
+    /// Whether an endpoint is stalled
+    fn is_stalled(&self, address: EndpointAddress) -> bool;
+
+    /// Wait for the next bus event
+    fn event<'a>(&'a self) -> Self::EventFuture<'a>
+    where
+        Self: 'a;
+
+    /// Read one packet from an OUT endpoint
+    ///
+    /// Returns the length of the packet. `buf` must be at least the
+    /// endpoint's maximum packet size.
+    fn read<'a>(&'a self, endpoint: u8, buf: &'a mut [u8]) -> Self::ReadFuture<'a>
+    where
+        Self: 'a;
+
+    /// Write one packet to an IN endpoint
+    ///
+    /// The future completes when the host has received the
+    /// packet. `buf` must be no longer than the endpoint's maximum
+    /// packet size. An empty `buf` sends a zero-length packet.
+    fn write<'a>(&'a self, endpoint: u8, buf: &'a [u8]) -> Self::WriteFuture<'a>
+    where
+        Self: 'a;
+}
+
+/// Whether a request is defined by the USB standard, a class, or a vendor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestKind {
+    /// A standard request, such as `GET_DESCRIPTOR`
+    Standard,
+
+    /// A class-specific request
+    Class,
+
+    /// A vendor-specific request
+    Vendor,
+
+    /// A reserved request type
+    Reserved,
+}
+
+/// What a request is addressed to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recipient {
+    /// The device as a whole
+    Device,
+
+    /// The interface in the low byte of the request's index
+    Interface,
+
+    /// The endpoint in the low byte of the request's index
+    Endpoint,
+
+    /// Some other recipient
+    Other,
+}
+
+/// A control request, decoded from a setup packet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Request {
+    /// The raw `bmRequestType` field
+    pub request_type: u8,
+
+    /// The request number
+    pub request: u8,
+
+    /// The request's value
+    pub value: u16,
+
+    /// The request's index
+    pub index: u16,
+
+    /// The length of the data stage
+    pub length: u16,
+}
+
+impl Request {
+    /// Decode a setup packet
+    pub fn parse(setup: &[u8; 8]) -> Self {
+        Self {
+            request_type: setup[0],
+            request: setup[1],
+            value: u16::from_le_bytes([setup[2], setup[3]]),
+            index: u16::from_le_bytes([setup[4], setup[5]]),
+            length: u16::from_le_bytes([setup[6], setup[7]]),
+        }
+    }
+
+    /// The direction of the data stage
+    pub fn direction(&self) -> Direction {
+        if self.request_type & 0x80 == 0 {
+            Direction::Out
+        } else {
+            Direction::In
+        }
+    }
+
+    /// Who defines the request
+    pub fn kind(&self) -> RequestKind {
+        match (self.request_type >> 5) & 0x03 {
+            0 => RequestKind::Standard,
+            1 => RequestKind::Class,
+            2 => RequestKind::Vendor,
+            _ => RequestKind::Reserved,
+        }
+    }
+
+    /// What the request is addressed to
+    pub fn recipient(&self) -> Recipient {
+        match self.request_type & 0x1F {
+            0 => Recipient::Device,
+            1 => Recipient::Interface,
+            2 => Recipient::Endpoint,
+            _ => Recipient::Other,
+        }
+    }
+}
+
+/// A function of a USB device, with its own interfaces and endpoints
+///
+/// Requests addressed to one of the class's interfaces are passed to
+/// the class. A class which does not handle a request returns
+/// `false`, and the stack either answers it, for a few standard
+/// requests, or stalls it.
+pub trait Class {
+    /// The interfaces which belong to this class
+    fn interfaces(&self) -> core::ops::Range<u8>;
+
+    /// Write the class's interface, endpoint, and class-specific descriptors
+    fn descriptors(&self, writer: &mut DescriptorWriter);
+
+    /// Handle a request with an IN data stage
+    ///
+    /// The response is appended to `response`, and is truncated to
+    /// the length the host asked for.
+    fn control_in(&self, request: &Request, response: &mut Vec<u8>) -> bool {
+        let _ = (request, response);
+        false
+    }
+
+    /// Handle a request with an OUT data stage, or no data stage
+    fn control_out(&self, request: &Request, data: &[u8]) -> bool {
+        let _ = (request, data);
+        false
+    }
+
+    /// Called when the host configures or deconfigures the device
+    fn set_configured(&self, configured: bool) {
+        let _ = configured;
+    }
+}
+
+struct EndpointInfo {
+    address: EndpointAddress,
+    kind: EndpointType,
+    max_packet_size: u16,
+}
+
+/// The allocator of interfaces, endpoints, and strings for the classes of a device
+///
+/// Classes are numbered in the order they are created.
+pub struct Allocator<B> {
+    bus: B,
+    next_interface: Cell<u8>,
+    used_in: Cell<u16>,
+    used_out: Cell<u16>,
+    endpoints: RefCell<Vec<EndpointInfo>>,
+    strings: RefCell<Vec<&'static str>>,
+}
+
+impl<B> Allocator<B>
+where
+    B: Bus,
+{
+    /// Start allocating for a USB controller
+    pub fn new(bus: B) -> Self {
+        Self {
+            bus,
+            next_interface: Cell::new(0),
+            // Endpoint 0 is the control endpoint
+            used_in: Cell::new(1),
+            used_out: Cell::new(1),
+            endpoints: RefCell::new(Vec::new()),
+            strings: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// The underlying controller
+    pub fn bus(&self) -> &B {
+        &self.bus
+    }
+
+    /// Allocate an interface number
+    pub fn interface(&self) -> u8 {
+        let interface = self.next_interface.get();
+        self.next_interface.set(interface + 1);
+        interface
+    }
+
+    /// Allocate an IN endpoint
+    ///
+    /// # Panics
+    /// Panics if the controller has no free IN endpoints.
+    pub fn endpoint_in(&self, kind: EndpointType, max_packet_size: u16) -> EndpointIn<'_, B> {
+        EndpointIn(self.endpoint(Direction::In, kind, max_packet_size))
+    }
+
+    /// Allocate an OUT endpoint
+    ///
+    /// # Panics
+    /// Panics if the controller has no free OUT endpoints.
+    pub fn endpoint_out(&self, kind: EndpointType, max_packet_size: u16) -> EndpointOut<'_, B> {
+        EndpointOut(self.endpoint(Direction::Out, kind, max_packet_size))
+    }
+
+    /// Allocate a string descriptor, and get its index
+    pub fn string(&self, string: &'static str) -> u8 {
+        let mut strings = self.strings.borrow_mut();
+        strings.push(string);
+        (strings.len() + STRING_CLASSES) as u8
+    }
+
+    fn endpoint(
+        &self,
+        direction: Direction,
+        kind: EndpointType,
+        max_packet_size: u16,
+    ) -> Endpoint<'_, B> {
+        let used = match direction {
+            Direction::In => &self.used_in,
+            Direction::Out => &self.used_out,
+        };
+        let number = (1..self.bus.endpoint_count())
+            .find(|number| used.get() & 1 << number == 0)
+            .expect("No free USB endpoints");
+        used.set(used.get() | 1 << number);
+
+        let address = EndpointAddress::new(number, direction);
+        self.endpoints.borrow_mut().push(EndpointInfo {
+            address,
+            kind,
+            max_packet_size,
+        });
+        Endpoint {
+            allocator: self,
+            address,
+            kind,
+            max_packet_size,
+        }
+    }
+}
+
+struct Endpoint<'a, B> {
+    allocator: &'a Allocator<B>,
+    address: EndpointAddress,
+    kind: EndpointType,
+    max_packet_size: u16,
+}
+
+/// An endpoint which sends data to the host
+pub struct EndpointIn<'a, B>(Endpoint<'a, B>);
+
+/// An endpoint which receives data from the host
+pub struct EndpointOut<'a, B>(Endpoint<'a, B>);
+
+impl<'a, B> EndpointIn<'a, B>
+where
+    B: Bus,
+{
+    /// The endpoint's address
+    pub fn address(&self) -> EndpointAddress {
+        self.0.address
+    }
+
+    /// The endpoint's maximum packet size
+    pub fn max_packet_size(&self) -> u16 {
+        self.0.max_packet_size
+    }
+
+    /// Send one packet to the host
+    ///
+    /// `buf` must be no longer than the maximum packet size.
+    pub fn write<'b>(&'b self, buf: &'b [u8]) -> B::WriteFuture<'b> {
+        self.0.allocator.bus.write(self.0.address.number(), buf)
+    }
+
+    fn descriptor(&self, writer: &mut DescriptorWriter, interval: u8) {
+        writer.endpoint(&self.0, interval);
+    }
+}
+
+impl<'a, B> EndpointOut<'a, B>
+where
+    B: Bus,
+{
+    /// The endpoint's address
+    pub fn address(&self) -> EndpointAddress {
+        self.0.address
+    }
+
+    /// The endpoint's maximum packet size
+    pub fn max_packet_size(&self) -> u16 {
+        self.0.max_packet_size
+    }
+
+    /// Receive one packet from the host
+    ///
+    /// `buf` must be at least the maximum packet size. Returns the
+    /// length of the packet.
+    pub fn read<'b>(&'b self, buf: &'b mut [u8]) -> B::ReadFuture<'b> {
+        self.0.allocator.bus.read(self.0.address.number(), buf)
+    }
+
+    fn descriptor(&self, writer: &mut DescriptorWriter, interval: u8) {
+        writer.endpoint(&self.0, interval);
+    }
+}
+
+/// A builder for the descriptors of a configuration
+pub struct DescriptorWriter {
+    buf: Vec<u8>,
+    interfaces: u8,
+}
+
+impl DescriptorWriter {
+    /// Write a descriptor
+    ///
+    /// The length and type bytes are added before `data`.
+    pub fn write(&mut self, kind: u8, data: &[u8]) {
+        self.buf.push(data.len() as u8 + 2);
+        self.buf.push(kind);
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Write an interface association descriptor
+    ///
+    /// This groups `count` interfaces, starting at `first`, into a
+    /// single function of a composite device.
+    pub fn interface_association(
+        &mut self,
+        first: u8,
+        count: u8,
+        class: u8,
+        subclass: u8,
+        protocol: u8,
+    ) {
+        self.write(
+            DESCRIPTOR_INTERFACE_ASSOCIATION,
+            &[first, count, class, subclass, protocol, 0],
+        );
+    }
+
+    /// Write an interface descriptor
+    ///
+    /// `endpoints` is the number of endpoint descriptors which follow,
+    /// and `string` is an index from [`Allocator::string()`], or zero.
+    pub fn interface(
+        &mut self,
+        number: u8,
+        endpoints: u8,
+        class: u8,
+        subclass: u8,
+        protocol: u8,
+        string: u8,
+    ) {
+        self.interfaces += 1;
+        self.write(
+            DESCRIPTOR_INTERFACE,
+            &[number, 0, endpoints, class, subclass, protocol, string],
+        );
+    }
+
+    /// Write the descriptor of an IN endpoint
+    ///
+    /// `interval` is the polling interval for interrupt and
+    /// isochronous endpoints.
+    pub fn endpoint_in<B: Bus>(&mut self, endpoint: &EndpointIn<'_, B>, interval: u8) {
+        endpoint.descriptor(self, interval);
+    }
+
+    /// Write the descriptor of an OUT endpoint
+    ///
+    /// `interval` is the polling interval for interrupt and
+    /// isochronous endpoints.
+    pub fn endpoint_out<B: Bus>(&mut self, endpoint: &EndpointOut<'_, B>, interval: u8) {
+        endpoint.descriptor(self, interval);
+    }
+
+    fn endpoint<B>(&mut self, endpoint: &Endpoint<'_, B>, interval: u8) {
+        let kind = match endpoint.kind {
+            EndpointType::Control => 0,
+            EndpointType::Isochronous => 1,
+            EndpointType::Bulk => 2,
+            EndpointType::Interrupt => 3,
+        };
+        let [size_lo, size_hi] = endpoint.max_packet_size.to_le_bytes();
+        self.write(
+            DESCRIPTOR_ENDPOINT,
+            &[endpoint.address.raw(), kind, size_lo, size_hi, interval],
+        );
+    }
+}
+
+/// The identity of a device, as reported in its descriptors
+#[derive(Clone, Copy, Debug)]
+pub struct DeviceInfo {
+    /// The USB vendor ID
+    pub vendor_id: u16,
+
+    /// The product ID
+    pub product_id: u16,
+
+    /// The device release number, in binary-coded decimal
+    pub release: u16,
+
+    /// The name of the manufacturer
+    pub manufacturer: Option<&'static str>,
+
+    /// The name of the product
+    pub product: Option<&'static str>,
+
+    /// The device's serial number
+    pub serial_number: Option<&'static str>,
+
+    /// The most current the device draws from the bus, in milliamps
+    pub max_power: u16,
+}
+
+impl DeviceInfo {
+    /// Identify a device by its vendor and product IDs
+    ///
+    /// The device has no strings, and draws up to 100mA.
+    pub const fn new(vendor_id: u16, product_id: u16) -> Self {
+        Self {
+            vendor_id,
+            product_id,
+            release: 0x0100,
+            manufacturer: None,
+            product: None,
+            serial_number: None,
+            max_power: 100,
+        }
+    }
+}
+
+/// The number of string indices used by [`DeviceInfo`], before those of the classes
+const STRING_CLASSES: usize = 3;
+
+/// A USB device, composed of one or more classes
+///
+/// Every class is placed in a single configuration. Classes with more
+/// than one interface, such as CDC, describe themselves with an
+/// interface association, so the device uses the class codes for a
+/// composite device, and hosts load a driver for each class.
+pub struct Device<'a, B> {
+    allocator: &'a Allocator<B>,
+    info: DeviceInfo,
+    classes: &'a [&'a dyn Class],
+    configuration: Cell<u8>,
+}
+
+impl<'a, B> Device<'a, B>
+where
+    B: Bus,
+{
+    /// Compose classes into a device
+    ///
+    /// Every class must have been created with `allocator`.
+    pub fn new(
+        allocator: &'a Allocator<B>,
+        info: DeviceInfo,
+        classes: &'a [&'a dyn Class],
+    ) -> Self {
+        Self {
+            allocator,
+            info,
+            classes,
+            configuration: Cell::new(0),
+        }
+    }
+
+    /// Whether the host has configured the device
+    pub fn is_configured(&self) -> bool {
+        self.configuration.get() != 0
+    }
+
+    /// The device descriptor
+    pub fn device_descriptor(&self) -> Vec<u8> {
+        let [vendor_lo, vendor_hi] = self.info.vendor_id.to_le_bytes();
+        let [product_lo, product_hi] = self.info.product_id.to_le_bytes();
+        let [release_lo, release_hi] = self.info.release.to_le_bytes();
+        let string = |string: Option<&str>, index| if string.is_some() { index } else { 0 };
+        vec![
+            18,
+            DESCRIPTOR_DEVICE,
+            0x00,
+            0x02,
+            // Miscellaneous device class, with interface associations
+            0xEF,
+            0x02,
+            0x01,
+            CONTROL_PACKET_SIZE as u8,
+            vendor_lo,
+            vendor_hi,
+            product_lo,
+            product_hi,
+            release_lo,
+            release_hi,
+            string(self.info.manufacturer, 1),
+            string(self.info.product, 2),
+            string(self.info.serial_number, 3),
+            1,
+        ]
+    }
+
+    /// The configuration descriptor, followed by every class's descriptors
+    pub fn configuration_descriptor(&self) -> Vec<u8> {
+        let mut writer = DescriptorWriter {
+            buf: vec![0; 9],
+            interfaces: 0,
+        };
+        for class in self.classes {
+            class.descriptors(&mut writer);
+        }
+
+        let [len_lo, len_hi] = (writer.buf.len() as u16).to_le_bytes();
+        let power = (self.info.max_power / 2).min(250) as u8;
+        writer.buf[..9].copy_from_slice(&[
+            9,
+            DESCRIPTOR_CONFIGURATION,
+            len_lo,
+            len_hi,
+            writer.interfaces,
+            1,
+            0,
+            0x80,
+            power,
+        ]);
+        writer.buf
+    }
+
+    /// A string descriptor
+    ///
+    /// Index 0 is the list of supported languages, which is only US
+    /// English. Returns `None` for an unknown index.
+    pub fn string_descriptor(&self, index: u8) -> Option<Vec<u8>> {
+        let string = match index as usize {
+            0 => return Some(vec![4, DESCRIPTOR_STRING, 0x09, 0x04]),
+            1 => self.info.manufacturer?,
+            2 => self.info.product?,
+            3 => self.info.serial_number?,
+            index => *self
+                .allocator
+                .strings
+                .borrow()
+                .get(index - STRING_CLASSES - 1)?,
+        };
+        let mut descriptor = vec![0, DESCRIPTOR_STRING];
+        for unit in string.encode_utf16() {
+            descriptor.extend_from_slice(&unit.to_le_bytes());
+        }
+        descriptor[0] = descriptor.len().min(254) as u8;
+        descriptor.truncate(254);
+        Some(descriptor)
+    }
+
+    /// Run the control endpoint
+    ///
+    /// This answers the host's requests until the controller reports
+    /// an error. It must run for the device to be enumerated.
+    pub fn run(&self) -> impl Future<Output = B::Error> + '_ {
+        async move {
+            loop {
+                let event = match self.allocator.bus.event().await {
+                    Ok(event) => event,
+                    Err(err) => return err,
+                };
+                match event {
+                    Event::Reset => self.reset(),
+                    Event::Setup(setup) => {
+                        if let Err(err) = self.setup(Request::parse(&setup)).await {
+                            return err;
+                        }
+                    }
+                    Event::Suspend | Event::Resume => {}
+                }
+            }
+        }
+    }
+
+    fn reset(&self) {
+        if self.configuration.replace(0) != 0 {
+            for class in self.classes {
+                class.set_configured(false);
+            }
+        }
+    }
+
+    fn setup(&self, request: Request) -> impl Future<Output = Result<(), B::Error>> + '_ {
+        async move {
+            let bus = &self.allocator.bus;
+            if request.direction() == Direction::In {
+                let mut response = Vec::new();
+                if !self.control_in(&request, &mut response) {
+                    self.stall();
+                    return Ok(());
+                }
+                response.truncate(request.length as usize);
+                let mut packets = response.chunks(CONTROL_PACKET_SIZE as usize);
+                for packet in &mut packets {
+                    bus.write(0, packet).await?;
+                }
+                // A short response which fills its last packet is
+                // ended with a zero-length packet.
+                if response.len() < request.length as usize
+                    && response.len() % CONTROL_PACKET_SIZE as usize == 0
+                {
+                    bus.write(0, &[]).await?;
+                }
+                bus.read(0, &mut [0; CONTROL_PACKET_SIZE as usize]).await?;
+            } else {
+                let mut data = vec![0; request.length as usize];
+                let mut received = 0;
+                let mut packet = [0; CONTROL_PACKET_SIZE as usize];
+                while received < data.len() {
+                    let len = bus.read(0, &mut packet).await?;
+                    let len = len.min(data.len() - received);
+                    data[received..received + len].copy_from_slice(&packet[..len]);
+                    received += len;
+                    if len < CONTROL_PACKET_SIZE as usize {
+                        break;
+                    }
+                }
+                data.truncate(received);
+
+                if !self.control_out(&request, &data) {
+                    self.stall();
+                    return Ok(());
+                }
+                bus.write(0, &[]).await?;
+
+                // The new address only takes effect after the status
+                // stage of the request which set it.
+                if request.kind() == RequestKind::Standard
+                    && request.recipient() == Recipient::Device
+                    && request.request == REQUEST_SET_ADDRESS
+                {
+                    bus.set_address(request.value as u8);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn stall(&self) {
+        let bus = &self.allocator.bus;
+        bus.set_stalled(EndpointAddress::new(0, Direction::In), true);
+        bus.set_stalled(EndpointAddress::new(0, Direction::Out), true);
+    }
+
+    fn class(&self, request: &Request) -> Option<&'a dyn Class> {
+        if request.recipient() != Recipient::Interface {
+            return None;
+        }
+        let interface = request.index as u8;
+        self.classes
+            .iter()
+            .copied()
+            .find(|class| class.interfaces().contains(&interface))
+    }
+
+    fn control_in(&self, request: &Request, response: &mut Vec<u8>) -> bool {
+        if let Some(class) = self.class(request) {
+            if class.control_in(request, response) {
+                return true;
+            }
+        }
+        if request.kind() != RequestKind::Standard {
+            return false;
+        }
+
+        match (request.recipient(), request.request) {
+            (Recipient::Device, REQUEST_GET_DESCRIPTOR) => {
+                let [index, kind] = request.value.to_le_bytes();
+                let descriptor = match kind {
+                    DESCRIPTOR_DEVICE => Some(self.device_descriptor()),
+                    DESCRIPTOR_CONFIGURATION => Some(self.configuration_descriptor()),
+                    DESCRIPTOR_STRING => self.string_descriptor(index),
+                    _ => None,
+                };
+                match descriptor {
+                    Some(descriptor) => {
+                        response.extend_from_slice(&descriptor);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            (Recipient::Device, REQUEST_GET_CONFIGURATION) => {
+                response.push(self.configuration.get());
+                true
+            }
+            (Recipient::Device, REQUEST_GET_STATUS) => {
+                // Self-powered and remote wakeup are both off
+                response.extend_from_slice(&[0, 0]);
+                true
+            }
+            (Recipient::Interface, REQUEST_GET_STATUS) => {
+                response.extend_from_slice(&[0, 0]);
+                true
+            }
+            (Recipient::Interface, REQUEST_GET_INTERFACE) => {
+                response.push(0);
+                true
+            }
+            (Recipient::Endpoint, REQUEST_GET_STATUS) => {
+                let address = EndpointAddress(request.index as u8);
+                let halted = self.allocator.bus.is_stalled(address);
+                response.extend_from_slice(&[halted as u8, 0]);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn control_out(&self, request: &Request, data: &[u8]) -> bool {
+        if let Some(class) = self.class(request) {
+            if class.control_out(request, data) {
+                return true;
+            }
+        }
+        if request.kind() != RequestKind::Standard {
+            return false;
+        }
+
+        match (request.recipient(), request.request) {
+            (Recipient::Device, REQUEST_SET_ADDRESS) => request.value < 128,
+            (Recipient::Device, REQUEST_SET_CONFIGURATION) => match request.value {
+                0 => {
+                    self.reset();
+                    true
+                }
+                1 => {
+                    if self.configuration.replace(1) == 0 {
+                        for endpoint in self.allocator.endpoints.borrow().iter() {
+                            if self
+                                .allocator
+                                .bus
+                                .enable_endpoint(
+                                    endpoint.address,
+                                    endpoint.kind,
+                                    endpoint.max_packet_size,
+                                )
+                                .is_err()
+                            {
+                                self.configuration.set(0);
+                                return false;
+                            }
+                        }
+                        for class in self.classes {
+                            class.set_configured(true);
+                        }
+                    }
+                    true
+                }
+                _ => false,
+            },
+            (Recipient::Interface, REQUEST_SET_INTERFACE) => request.value == 0,
+            (Recipient::Endpoint, REQUEST_SET_FEATURE)
+            | (Recipient::Endpoint, REQUEST_CLEAR_FEATURE)
+                if request.value == FEATURE_ENDPOINT_HALT =>
+            {
+                let address = EndpointAddress(request.index as u8);
+                self.allocator
+                    .bus
+                    .set_stalled(address, request.request == REQUEST_SET_FEATURE);
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,57 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! Battery-backed storage for Cntrlr boards
+//!
+//! The Teensy 3.x boards have a small register file in the VBAT power
+//! domain, alongside the [real-time clock](crate::time::rtc). With a
+//! coin cell connected to the VBAT pin, it keeps its contents while
+//! the board is unpowered, so applications can keep a little state,
+//! such as a boot counter or the last settings, across power loss.
+//! It is cleared when the coin cell is removed or runs flat, so its
+//! contents should be checked before they are trusted.
+//!
+//! ```
+//! use cntrlr::{prelude::*, vbat};
+//!
+//! #[entry]
+//! async fn main() -> ! {
+//!     let mut count = [0; 4];
+//!     vbat::read(0, &mut count).expect("Failed to read VBAT storage");
+//!     let count = u32::from_le_bytes(count).wrapping_add(1);
+//!     vbat::write(0, &count.to_le_bytes()).expect("Failed to write VBAT storage");
+//!     writeln!(serial_1().await, "Booted {} times", count).await.ok();
+//!     loop {}
+//! }
+//! ```
+//!
+//! The Teensy LC has no VBAT power domain.
+
+use cntrlr_macros::board_fn;
+
+/// The number of bytes of storage
+pub const SIZE: usize = 32;
+
+/// An error from battery-backed storage
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum VbatError {
+    /// The range of bytes extends past the end of the storage
+    OutOfRange,
+
+    /// The register file is in use by another module
+    InUse,
+}
+
+/// Read from battery-backed storage
+///
+/// Fills `buf` with the bytes starting at `offset`.
+#[board_fn(vbat, teensy_30, teensy_32, teensy_35, teensy_36)]
+pub fn read(offset: usize, buf: &mut [u8]) -> Result<(), VbatError> {}
+
+/// Write to battery-backed storage
+///
+/// Stores `data` in the bytes starting at `offset`. The other bytes
+/// are unchanged.
+#[board_fn(vbat, teensy_30, teensy_32, teensy_35, teensy_36)]
+pub fn write(offset: usize, data: &[u8]) -> Result<(), VbatError> {}
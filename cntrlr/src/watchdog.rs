@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright 2020 Branan Riley <me@branan.info>
+
+//! The board's watchdog
+//!
+//! Each board's startup disables the watchdog, so that slow
+//! initialization cannot reset the board. [`enable`] turns it back on
+//! with a timeout. From then on, the board resets unless [`feed`] is
+//! called more often than that.
+//!
+//! With [`set_auto_feed`], the executor feeds the watchdog after
+//! each task poll, and each time it wakes from sleep. The board is
+//! then only reset if a single poll runs for longer than the
+//! timeout, such as a task stuck in a loop, or if the executor sleeps
+//! for longer than the timeout. Sleeps are woken by the timer
+//! interrupt at least once per tick, so the timeout should be longer
+//! than the tick period set with
+//! [`set_tick_mode`](crate::time::set_tick_mode).
+//!
+//! The watchdog is shared with
+//! [`with_deadline_strict`](crate::time::with_deadline_strict), and
+//! only one of them can use it at a time.
+//!
+//! ```
+//! use cntrlr::{prelude::*, time::Duration, watchdog};
+//!
+//! #[entry]
+//! async fn main() -> ! {
+//!     if cntrlr::time::reset_by_watchdog() {
+//!         // Recover from a hang
+//!     }
+//!     watchdog::enable(Duration::from_millis(100)).unwrap();
+//!     watchdog::set_auto_feed(true);
+//!     loop {
+//!         // A poll which blocks for 100ms resets the board
+//!         sleep_millis(10).await;
+//!     }
+//! }
+//! ```
+
+use crate::sync::Value;
+#[cfg(any(
+    doc,
+    board = "red_v",
+    board = "teensy_30",
+    board = "teensy_32",
+    board = "teensy_35",
+    board = "teensy_36",
+    board = "teensy_lc"
+))]
+use crate::time::Duration;
+use cntrlr_macros::board_fn;
+use core::sync::atomic::Ordering;
+
+/// An error from [`enable`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WatchdogError {
+    /// The watchdog cannot be enabled because the board's watchdog cannot be enabled after startup
+    Unsupported,
+
+    /// The watchdog cannot be enabled because a strict deadline, or another driver, is using it
+    InUse,
+
+    /// The watchdog cannot be enabled because the timeout is longer than it can count
+    TooLong,
+}
+
+/// Enable the watchdog, or change its timeout
+///
+/// The count restarts from zero. The Teensy 3.x boards count the
+/// 1kHz LPO, and lengthen timeouts below 4 milliseconds to 4. The
+/// Red-V counts the 32.768kHz low-frequency clock, and rounds the
+/// timeout up to a tick of that clock, or of its scaled count for
+/// timeouts longer than about two seconds. The Teensy LC's watchdog
+/// cannot be enabled after startup, so this always fails with
+/// [`WatchdogError::Unsupported`] there.
+#[board_fn(watchdog, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn enable(timeout: Duration) -> Result<(), WatchdogError> {}
+
+/// Disable the watchdog
+///
+/// This does nothing if the watchdog was not enabled by [`enable`].
+#[board_fn(watchdog, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn disable() {}
+
+/// Feed the watchdog, restarting its count
+///
+/// This does nothing if the watchdog was not enabled by [`enable`].
+/// It is safe to call from interrupts.
+#[board_fn(watchdog, red_v, teensy_30, teensy_32, teensy_35, teensy_36, teensy_lc)]
+pub fn feed() {}
+
+/// Set whether the executor feeds the watchdog
+///
+/// When this is set, the executor calls [`feed`] after each task
+/// poll, and each time it wakes from sleep.
+pub fn set_auto_feed(enabled: bool) {
+    AUTO_FEED.store(enabled as usize, Ordering::Relaxed);
+}
+
+/// Feed the watchdog if [`set_auto_feed`] is set
+///
+/// This is called by the executor.
+pub(crate) fn auto_feed() {
+    #[cfg(any(
+        board = "red_v",
+        board = "teensy_30",
+        board = "teensy_32",
+        board = "teensy_35",
+        board = "teensy_36",
+        board = "teensy_lc"
+    ))]
+    if AUTO_FEED.load(Ordering::Relaxed) != 0 {
+        feed();
+    }
+}
+
+static AUTO_FEED: Value = Value::new(0);